@@ -160,66 +160,30 @@ async fn main() -> Result<()> {
     app.post("/upload", move |req, res| {
         let uploader = single_uploader.clone();
         async move {
-            // Get content type header
-            let content_type = req.content_type().unwrap_or_default().to_string();
-
-            // Check if it's multipart form data
-            if !content_type.contains("multipart/form-data") {
-                return res.bad_request("Content-Type must be multipart/form-data");
-            }
-
-            // Parse boundary
-            let boundary = match parse_boundary(&content_type) {
-                Some(b) => b,
-                None => return res.bad_request("Missing boundary in Content-Type"),
-            };
-
-            // Parse multipart form data
-            let fields = match parse_multipart(req.body(), &boundary) {
-                Ok(f) => f,
-                Err(e) => return res.bad_request(&format!("Failed to parse form data: {}", e)),
-            };
-
-            // Find file field
-            for field in fields {
-                if field.name == "file" {
-                    if let Some(filename) = field.filename {
-                        let mimetype = field
-                            .content_type
-                            .unwrap_or_else(|| "application/octet-stream".to_string());
-
-                        match uploader
-                            .upload_single(&field.name, field.data, &filename, &mimetype)
-                            .await
-                        {
-                            Ok(file) => {
-                                info!("File uploaded: {} ({} bytes)", file.filename, file.size);
-                                return res.json(json!({
-                                    "success": true,
-                                    "message": "File uploaded successfully",
-                                    "file": {
-                                        "filename": file.filename,
-                                        "originalName": file.original_name,
-                                        "size": file.size,
-                                        "mimetype": file.mimetype,
-                                        "extension": file.extension,
-                                        "path": file.path.to_string_lossy()
-                                    }
-                                }));
-                            }
-                            Err(e) => {
-                                error!("Upload failed: {}", e);
-                                return res.status(400).json(json!({
-                                    "success": false,
-                                    "error": e.to_string()
-                                }));
-                            }
+            match uploader.single(&req, "file").await {
+                Ok(file) => {
+                    info!("File uploaded: {} ({} bytes)", file.filename, file.size);
+                    res.json(json!({
+                        "success": true,
+                        "message": "File uploaded successfully",
+                        "file": {
+                            "filename": file.filename,
+                            "originalName": file.original_name,
+                            "size": file.size,
+                            "mimetype": file.mimetype,
+                            "extension": file.extension,
+                            "path": file.path.to_string_lossy()
                         }
-                    }
+                    }))
+                }
+                Err(e) => {
+                    error!("Upload failed: {}", e);
+                    res.status(400).json(json!({
+                        "success": false,
+                        "error": e.to_string()
+                    }))
                 }
             }
-
-            res.bad_request("No file provided in 'file' field")
         }
     });
 
@@ -235,38 +199,8 @@ async fn main() -> Result<()> {
     app.post("/upload-multiple", move |req, res| {
         let uploader = multi_uploader.clone();
         async move {
-            let content_type = req.content_type().unwrap_or_default().to_string();
-
-            if !content_type.contains("multipart/form-data") {
-                return res.bad_request("Content-Type must be multipart/form-data");
-            }
-
-            let boundary = match parse_boundary(&content_type) {
-                Some(b) => b,
-                None => return res.bad_request("Missing boundary"),
-            };
-
-            let fields = match parse_multipart(req.body(), &boundary) {
-                Ok(f) => f,
-                Err(e) => return res.bad_request(&format!("Parse error: {}", e)),
-            };
-
-            // Collect all file fields
-            let mut files_data = Vec::new();
-            for field in fields {
-                if let Some(filename) = field.filename {
-                    let mimetype = field
-                        .content_type
-                        .unwrap_or_else(|| "application/octet-stream".to_string());
-                    files_data.push((field.name, field.data, filename, mimetype));
-                }
-            }
-
-            if files_data.is_empty() {
-                return res.bad_request("No files provided");
-            }
-
-            match uploader.upload_multiple(files_data).await {
+            match uploader.array(&req, "files", 5).await {
+                Ok(files) if files.is_empty() => res.bad_request("No files provided"),
                 Ok(files) => {
                     info!("Uploaded {} files", files.len());
                     res.json(json!({
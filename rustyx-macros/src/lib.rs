@@ -0,0 +1,564 @@
+//! # RustyX Macros
+//!
+//! Derive macros for [RustyX](https://docs.rs/rustyx). Use them through the
+//! re-exports in `rustyx::models` rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Expr, Fields, GenericArgument, Ident, LitStr, Path,
+    PathArguments, Type,
+};
+
+mod schema;
+
+/// Implement `Model`, and `Timestamps` / `SoftDeletes` when the fields exist
+///
+/// - The table is the snake_case plural of the struct name (`BlogPost` →
+///   `blog_posts`), or `#[model(table = "...")]`.
+/// - The primary key is the field named `id`, or the one marked
+///   `#[model(primary_key)]`. It may be any `Display + FromStr` type,
+///   optionally wrapped in `Option`.
+/// - `created_at` + `updated_at` fields implement `Timestamps` and are
+///   maintained on save; a `deleted_at` field implements `SoftDeletes` and
+///   scopes queries. Other fields can take these roles with
+///   `#[model(created_at)]`, `#[model(updated_at)]` and
+///   `#[model(deleted_at)]`; `#[model(skip_timestamps)]` on the struct opts out.
+/// - `#[validate(...)]` field rules implement `validate()`: `required`,
+///   `email`, `url`, `length(min = .., max = ..)`, `range(min = .., max = ..)`
+///   and `custom = "path::to_fn"`. See `rustyx::models::validation`.
+///   `nested` validates a field implementing `Validate` (or a `Vec` of
+///   them), reporting its errors under paths like `address.city` or
+///   `items[0].sku`.
+/// - `#[model(before_save = "path::to_fn")]` and likewise `after_save`,
+///   `before_delete` and `after_delete` on the struct implement the lifecycle
+///   hooks by calling the function with `self`.
+///
+/// ```rust,ignore
+/// #[derive(Clone, Serialize, Deserialize, Model)]
+/// struct User {
+///     id: Option<i64>,
+///     email: String,
+///     created_at: Option<DateTime<Utc>>,
+///     updated_at: Option<DateTime<Utc>>,
+/// }
+///
+/// assert_eq!(User::collection_name(), "users");
+/// ```
+#[proc_macro_derive(Model, attributes(model, validate))]
+pub fn derive_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implement `Validate` from `#[validate(...)]` field rules
+///
+/// Takes the same rules as `#[derive(Model)]`, for request payloads and
+/// nested values that are not models.
+///
+/// ```rust,ignore
+/// #[derive(Deserialize, Validate)]
+/// struct Address {
+///     #[validate(required)]
+///     city: String,
+/// }
+///
+/// #[derive(Deserialize, Validate)]
+/// struct Signup {
+///     #[validate(email)]
+///     email: String,
+///     #[validate(nested)]
+///     address: Address,
+/// }
+/// ```
+#[proc_macro_derive(Validate, attributes(validate))]
+pub fn derive_validate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_validate(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_validate(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "Validate can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "Validate can only be derived for structs",
+            ))
+        }
+    };
+    let validate = validate_fn(fields)?.unwrap_or_else(|| {
+        quote! {
+            fn validate(&self) -> ::rustyx::Result<()> {
+                Ok(())
+            }
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::rustyx::models::validation::Validate for #name #ty_generics #where_clause {
+            #validate
+        }
+    })
+}
+
+/// Implement `ToSchema` for OpenAPI documents, following serde attributes
+///
+/// - Structs with named fields become objects. Fields are required unless
+///   they are an `Option` or `#[serde(default)]`, and `///` comments become
+///   descriptions.
+/// - `#[serde(rename)]`, `rename_all`, `skip` and `flatten` are honoured.
+/// - Newtype structs take the inner type's schema.
+/// - Enums of unit variants become string enums; others become `oneOf`
+///   schemas in serde's externally tagged or `untagged` representation.
+///
+/// ```rust,ignore
+/// /// A registered user
+/// #[derive(Serialize, ToSchema)]
+/// #[serde(rename_all = "camelCase")]
+/// struct User {
+///     id: u64,
+///     /// Shown on the profile page
+///     display_name: String,
+///     avatar: Option<String>,
+/// }
+/// ```
+#[proc_macro_derive(ToSchema)]
+pub fn derive_to_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    schema::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Roles<'a> {
+    primary_key: Option<&'a syn::Field>,
+    created_at: Option<&'a syn::Field>,
+    updated_at: Option<&'a syn::Field>,
+    deleted_at: Option<&'a syn::Field>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut table = None;
+    let mut skip_timestamps = false;
+    let mut hooks = Vec::new();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("model")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("skip_timestamps") {
+                skip_timestamps = true;
+                Ok(())
+            } else if let Some(hook) = HOOKS.iter().find(|hook| meta.path.is_ident(hook)) {
+                let path: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                hooks.push(hook_fn(hook, &path));
+                Ok(())
+            } else {
+                Err(meta.error("expected `table = \"...\"`, `skip_timestamps` or a lifecycle hook"))
+            }
+        })?;
+    }
+    let table = table.unwrap_or_else(|| pluralize(&snake_case(&name.to_string())));
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "Model can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "Model can only be derived for structs",
+            ))
+        }
+    };
+
+    // Explicit attributes first, then fall back to conventional field names
+    let mut roles = Roles::default();
+    for field in fields {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("model")) {
+            attr.parse_nested_meta(|meta| {
+                let slot = if meta.path.is_ident("primary_key") {
+                    &mut roles.primary_key
+                } else if meta.path.is_ident("created_at") {
+                    &mut roles.created_at
+                } else if meta.path.is_ident("updated_at") {
+                    &mut roles.updated_at
+                } else if meta.path.is_ident("deleted_at") {
+                    &mut roles.deleted_at
+                } else {
+                    return Err(meta.error(
+                        "expected `primary_key`, `created_at`, `updated_at` or `deleted_at`",
+                    ));
+                };
+                if slot.is_some() {
+                    return Err(meta.error("duplicate model field attribute"));
+                }
+                *slot = Some(field);
+                Ok(())
+            })?;
+        }
+    }
+    let named = |ident: &str| {
+        fields
+            .iter()
+            .find(|f| f.ident.as_ref().is_some_and(|i| i == ident))
+    };
+    let roles = Roles {
+        primary_key: roles.primary_key.or_else(|| named("id")),
+        created_at: roles.created_at.or_else(|| named("created_at")),
+        updated_at: roles.updated_at.or_else(|| named("updated_at")),
+        deleted_at: roles.deleted_at.or_else(|| named("deleted_at")),
+    };
+
+    let primary_key = roles.primary_key.ok_or_else(|| {
+        syn::Error::new_spanned(
+            name,
+            "Model needs an `id` field or a field marked #[model(primary_key)]",
+        )
+    })?;
+    let id = field_ident(primary_key);
+    let id_name = id.to_string();
+    let (get_id, set_id) = if option_inner(&primary_key.ty).is_some() {
+        (
+            quote!(self.#id.as_ref().map(|id| id.to_string())),
+            quote!(self.#id = id.parse().ok();),
+        )
+    } else {
+        (
+            quote!(Some(self.#id.to_string())),
+            quote! {
+                if let Ok(id) = id.parse() {
+                    self.#id = id;
+                }
+            },
+        )
+    };
+
+    let validate = validate_fn(fields)?;
+
+    let soft_delete_column = roles.deleted_at.map(|field| {
+        let column = field_ident(field).to_string();
+        quote! {
+            fn soft_delete_column() -> Option<&'static str> {
+                Some(#column)
+            }
+        }
+    });
+
+    let timestamp_columns = match (skip_timestamps, roles.created_at, roles.updated_at) {
+        (false, Some(created), Some(updated)) => {
+            let created = field_ident(created).to_string();
+            let updated = field_ident(updated).to_string();
+            Some(quote! {
+                fn timestamp_columns() -> Option<(&'static str, &'static str)> {
+                    Some((#created, #updated))
+                }
+            })
+        }
+        _ => None,
+    };
+
+    let mut output = quote! {
+        impl #impl_generics ::rustyx::models::Model for #name #ty_generics #where_clause {
+            fn collection_name() -> &'static str {
+                #table
+            }
+
+            fn primary_key() -> &'static str {
+                #id_name
+            }
+
+            fn get_id(&self) -> Option<String> {
+                #get_id
+            }
+
+            fn set_id(&mut self, id: String) {
+                #set_id
+            }
+
+            #timestamp_columns
+
+            #soft_delete_column
+
+            #validate
+
+            #(#hooks)*
+        }
+    };
+
+    let datetime = quote!(::rustyx::__chrono::DateTime<::rustyx::__chrono::Utc>);
+    if !skip_timestamps {
+        match (roles.created_at, roles.updated_at) {
+            (Some(created), Some(updated)) => {
+                let (created_at, set_created_at) = timestamp_accessors(created);
+                let (updated_at, set_updated_at) = timestamp_accessors(updated);
+                output.extend(quote! {
+                    impl #impl_generics ::rustyx::models::Timestamps for #name #ty_generics #where_clause {
+                        fn created_at(&self) -> Option<#datetime> {
+                            #created_at
+                        }
+
+                        fn updated_at(&self) -> Option<#datetime> {
+                            #updated_at
+                        }
+
+                        fn set_created_at(&mut self, time: #datetime) {
+                            #set_created_at
+                        }
+
+                        fn set_updated_at(&mut self, time: #datetime) {
+                            #set_updated_at
+                        }
+                    }
+                });
+            }
+            (Some(field), None) | (None, Some(field)) => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "Timestamps need both `created_at` and `updated_at` fields",
+                ));
+            }
+            (None, None) => {}
+        }
+    }
+
+    if let Some(field) = roles.deleted_at {
+        if option_inner(&field.ty).is_none() {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "`deleted_at` must be an Option<DateTime<Utc>>",
+            ));
+        }
+        let deleted_at = field_ident(field);
+        output.extend(quote! {
+            impl #impl_generics ::rustyx::models::SoftDeletes for #name #ty_generics #where_clause {
+                fn deleted_at(&self) -> Option<#datetime> {
+                    self.#deleted_at
+                }
+
+                fn set_deleted_at(&mut self, time: Option<#datetime>) {
+                    self.#deleted_at = time;
+                }
+            }
+        });
+    }
+
+    Ok(output)
+}
+
+/// `Model::validate` running the `#[validate(...)]` rules, if there are any
+fn validate_fn(fields: &Punctuated<syn::Field, Comma>) -> syn::Result<Option<TokenStream2>> {
+    let rules = quote!(::rustyx::models::validation);
+    let mut checks = Vec::new();
+    for field in fields {
+        let ident = field_ident(field);
+        let name = ident.to_string();
+        let mut required = None;
+        let mut field_checks = Vec::new();
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("validate")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("required") {
+                    required = Some(quote! {
+                        errors.extend(#rules::required(#name, &self.#ident));
+                    });
+                } else if meta.path.is_ident("email") {
+                    field_checks.push(quote!(#rules::email(#name, value)));
+                } else if meta.path.is_ident("url") {
+                    field_checks.push(quote!(#rules::url(#name, value)));
+                } else if meta.path.is_ident("length") || meta.path.is_ident("range") {
+                    let mut min = quote!(None);
+                    let mut max = quote!(None);
+                    let is_range = meta.path.is_ident("range");
+                    meta.parse_nested_meta(|bound| {
+                        let value: Expr = bound.value()?.parse()?;
+                        let value = if is_range {
+                            quote!(Some((#value) as f64))
+                        } else {
+                            quote!(Some(#value))
+                        };
+                        if bound.path.is_ident("min") {
+                            min = value;
+                        } else if bound.path.is_ident("max") {
+                            max = value;
+                        } else {
+                            return Err(bound.error("expected `min` or `max`"));
+                        }
+                        Ok(())
+                    })?;
+                    let check = if is_range {
+                        quote!(range)
+                    } else {
+                        quote!(length)
+                    };
+                    field_checks.push(quote!(#rules::#check(#name, value, #min, #max)));
+                } else if meta.path.is_ident("custom") {
+                    let path: Path = meta.value()?.parse::<LitStr>()?.parse()?;
+                    field_checks.push(quote!(#rules::custom(#name, value, #path)));
+                } else if meta.path.is_ident("nested") {
+                    let ty = option_inner(&field.ty).unwrap_or(&field.ty);
+                    field_checks.push(if generic_inner(ty, "Vec").is_some() {
+                        quote!(#rules::nested_each(#name, value))
+                    } else {
+                        quote!(#rules::nested(#name, value))
+                    });
+                } else {
+                    return Err(meta.error(
+                        "expected `required`, `email`, `url`, `length`, `range`, `custom` or `nested`",
+                    ));
+                }
+                Ok(())
+            })?;
+        }
+
+        checks.extend(required);
+        if field_checks.is_empty() {
+            continue;
+        }
+        let body = quote! {
+            #(errors.extend(#field_checks);)*
+        };
+        checks.push(if option_inner(&field.ty).is_some() {
+            quote! {
+                if let Some(value) = &self.#ident {
+                    #body
+                }
+            }
+        } else {
+            quote! {
+                let value = &self.#ident;
+                #body
+            }
+        });
+    }
+
+    if checks.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(quote! {
+        fn validate(&self) -> ::rustyx::Result<()> {
+            let mut errors = ::std::vec::Vec::new();
+            #(#checks)*
+            #rules::finish(errors)
+        }
+    }))
+}
+
+const HOOKS: [&str; 4] = ["before_save", "after_save", "before_delete", "after_delete"];
+
+fn hook_fn(hook: &str, path: &Path) -> TokenStream2 {
+    match hook {
+        "before_save" => quote! {
+            fn before_save(&mut self) -> ::rustyx::Result<()> {
+                #path(self)
+            }
+        },
+        "after_save" => quote! {
+            fn after_save(&mut self) {
+                #path(self)
+            }
+        },
+        "before_delete" => quote! {
+            fn before_delete(&self) -> ::rustyx::Result<()> {
+                #path(self)
+            }
+        },
+        _ => quote! {
+            fn after_delete(&self) {
+                #path(self)
+            }
+        },
+    }
+}
+
+/// Getter and setter bodies for a `DateTime` or `Option<DateTime>` field
+fn timestamp_accessors(field: &syn::Field) -> (TokenStream2, TokenStream2) {
+    let ident = field_ident(field);
+    if option_inner(&field.ty).is_some() {
+        (quote!(self.#ident), quote!(self.#ident = Some(time);))
+    } else {
+        (quote!(Some(self.#ident)), quote!(self.#ident = time;))
+    }
+}
+
+fn field_ident(field: &syn::Field) -> &Ident {
+    field.ident.as_ref().expect("named field")
+}
+
+/// The `T` of an `Option<T>` field type
+fn option_inner(ty: &Type) -> Option<&Type> {
+    generic_inner(ty, "Option")
+}
+
+/// `T` of a `wrapper<T>` type such as `Vec<T>`
+fn generic_inner<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// English plural for the common cases (`user` → `users`, `category` → `categories`)
+fn pluralize(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix('y') {
+        if !stem.ends_with(['a', 'e', 'i', 'o', 'u']) {
+            return format!("{}ies", stem);
+        }
+    }
+    if word.ends_with(['s', 'x', 'z']) || word.ends_with("ch") || word.ends_with("sh") {
+        return format!("{}es", word);
+    }
+    format!("{}s", word)
+}
@@ -0,0 +1,324 @@
+//! `#[derive(ToSchema)]`
+
+use crate::{field_ident, snake_case};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::ext::IdentExt;
+use syn::meta::ParseNestedMeta;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{Attribute, Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr, Meta, Token};
+
+const RENAME_RULES: [&str; 8] = [
+    "lowercase",
+    "UPPERCASE",
+    "PascalCase",
+    "camelCase",
+    "snake_case",
+    "SCREAMING_SNAKE_CASE",
+    "kebab-case",
+    "SCREAMING-KEBAB-CASE",
+];
+
+/// The serde attributes that change a type's JSON shape
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    skip: bool,
+    default: bool,
+    flatten: bool,
+    untagged: bool,
+}
+
+fn serde_attrs(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+    let mut out = SerdeAttrs::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                out.rename = Some(serialized_name(&meta)?.value());
+            } else if meta.path.is_ident("rename_all") {
+                let rule = serialized_name(&meta)?;
+                if !RENAME_RULES.contains(&rule.value().as_str()) {
+                    return Err(syn::Error::new_spanned(rule, "unknown rename rule"));
+                }
+                out.rename_all = Some(rule.value());
+            } else if meta.path.is_ident("skip") {
+                out.skip = true;
+            } else if meta.path.is_ident("default") {
+                out.default = true;
+                skip_value(&meta)?;
+            } else if meta.path.is_ident("flatten") {
+                out.flatten = true;
+            } else if meta.path.is_ident("untagged") {
+                out.untagged = true;
+            } else if meta.path.is_ident("tag") || meta.path.is_ident("content") {
+                return Err(
+                    meta.error("ToSchema supports externally tagged and untagged enums only")
+                );
+            } else {
+                skip_value(&meta)?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(out)
+}
+
+/// `rename = "..."`, or the `serialize` name of
+/// `rename(serialize = "...", deserialize = "...")`
+fn serialized_name(meta: &ParseNestedMeta) -> syn::Result<LitStr> {
+    if meta.input.peek(Token![=]) {
+        return meta.value()?.parse();
+    }
+    let mut name = None;
+    meta.parse_nested_meta(|inner| {
+        let value: LitStr = inner.value()?.parse()?;
+        if inner.path.is_ident("serialize") {
+            name = Some(value);
+        }
+        Ok(())
+    })?;
+    name.ok_or_else(|| meta.error("expected a `serialize` name"))
+}
+
+/// Consume the value of a serde attribute that doesn't affect the schema
+fn skip_value(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<TokenStream2>()?;
+    }
+    Ok(())
+}
+
+/// The `///` comment on an item, if any
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// Apply a serde `rename_all` rule to a snake_case field or PascalCase
+/// variant name
+fn rename(name: &str, rule: Option<&str>, variant: bool) -> String {
+    let Some(rule) = rule else {
+        return name.to_string();
+    };
+    let snake = if variant {
+        snake_case(name)
+    } else {
+        name.to_string()
+    };
+    let pascal: String = snake
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect();
+    match rule {
+        "lowercase" => name.to_lowercase(),
+        "UPPERCASE" => name.to_uppercase(),
+        "PascalCase" => pascal,
+        "camelCase" => {
+            let mut chars = pascal.chars();
+            chars
+                .next()
+                .map(|c| c.to_lowercase().chain(chars).collect())
+                .unwrap_or_default()
+        }
+        "snake_case" => snake,
+        "SCREAMING_SNAKE_CASE" => snake.to_uppercase(),
+        "kebab-case" => snake.replace('_', "-"),
+        _ => snake.replace('_', "-").to_uppercase(),
+    }
+}
+
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attrs = serde_attrs(&input.attrs)?;
+    let doc = doc_comment(&input.attrs);
+
+    let schema = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => object(
+                &fields.named,
+                attrs.rename_all.as_deref(),
+                attrs.default,
+                doc,
+            )?,
+            fields => described(tuple(fields), doc),
+        },
+        Data::Enum(data) => {
+            let rule = attrs.rename_all.as_deref();
+            let variants: Vec<_> = data
+                .variants
+                .iter()
+                .map(|v| Ok((v, serde_attrs(&v.attrs)?)))
+                .filter(|v| !matches!(v, Ok((_, a)) if a.skip))
+                .collect::<syn::Result<_>>()?;
+
+            let unit_only = variants.iter().all(|(v, _)| v.fields.is_empty());
+            if unit_only && !attrs.untagged {
+                let names = variants.iter().map(|(v, a)| {
+                    a.rename
+                        .clone()
+                        .unwrap_or_else(|| rename(&v.ident.unraw().to_string(), rule, true))
+                });
+                described(
+                    quote!(::rustyx::__serde_json::json!({
+                        "type": "string",
+                        "enum": [#(#names),*],
+                    })),
+                    doc,
+                )
+            } else {
+                let mut schemas = Vec::new();
+                for (variant, variant_attrs) in &variants {
+                    let tag = variant_attrs
+                        .rename
+                        .clone()
+                        .unwrap_or_else(|| rename(&variant.ident.unraw().to_string(), rule, true));
+                    let variant_doc = doc_comment(&variant.attrs);
+                    let schema = match &variant.fields {
+                        Fields::Unit if attrs.untagged => {
+                            quote!(::rustyx::__serde_json::json!({ "type": "null" }))
+                        }
+                        Fields::Unit => {
+                            schemas.push(described(
+                                quote!(::rustyx::__serde_json::json!({
+                                    "type": "string",
+                                    "const": #tag,
+                                })),
+                                variant_doc,
+                            ));
+                            continue;
+                        }
+                        Fields::Named(fields) => object(
+                            &fields.named,
+                            variant_attrs.rename_all.as_deref(),
+                            false,
+                            None,
+                        )?,
+                        fields => tuple(fields),
+                    };
+                    schemas.push(described(
+                        if attrs.untagged {
+                            schema
+                        } else {
+                            quote! {
+                                ::rustyx::openapi::ObjectSchema::new()
+                                    .field(#tag, #schema, true)
+                                    .build()
+                            }
+                        },
+                        variant_doc,
+                    ));
+                }
+                described(
+                    quote!(::rustyx::__serde_json::json!({ "oneOf": [#(#schemas),*] })),
+                    doc,
+                )
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "ToSchema can only be derived for structs and enums",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::rustyx::openapi::ToSchema for #name #ty_generics #where_clause {
+            fn schema() -> ::rustyx::__serde_json::Value {
+                #schema
+            }
+        }
+    })
+}
+
+/// Object schema for named fields
+fn object(
+    fields: &Punctuated<syn::Field, Comma>,
+    rename_all: Option<&str>,
+    all_default: bool,
+    doc: Option<String>,
+) -> syn::Result<TokenStream2> {
+    let trait_ = quote!(::rustyx::openapi::ToSchema);
+    let mut calls = Vec::new();
+    if let Some(doc) = doc {
+        calls.push(quote!(.description(#doc)));
+    }
+    for field in fields {
+        let attrs = serde_attrs(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ty = &field.ty;
+        if attrs.flatten {
+            calls.push(quote!(.flatten(<#ty as #trait_>::schema())));
+            continue;
+        }
+        let name = attrs
+            .rename
+            .unwrap_or_else(|| rename(&field_ident(field).unraw().to_string(), rename_all, false));
+        let schema = described(
+            quote!(<#ty as #trait_>::schema()),
+            doc_comment(&field.attrs),
+        );
+        let required = if attrs.default || all_default {
+            quote!(false)
+        } else {
+            quote!(<#ty as #trait_>::required())
+        };
+        calls.push(quote!(.field(#name, #schema, #required)));
+    }
+    Ok(quote!(::rustyx::openapi::ObjectSchema::new() #(#calls)* .build()))
+}
+
+/// Schema for tuple fields: the inner schema of a newtype, or a
+/// fixed-length array
+fn tuple(fields: &Fields) -> TokenStream2 {
+    let trait_ = quote!(::rustyx::openapi::ToSchema);
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    match types.as_slice() {
+        [] => quote!(::rustyx::__serde_json::json!({ "type": "null" })),
+        [ty] => quote!(<#ty as #trait_>::schema()),
+        types => {
+            let len = types.len();
+            quote!(::rustyx::__serde_json::json!({
+                "type": "array",
+                "prefixItems": [#(<#types as #trait_>::schema()),*],
+                "minItems": #len,
+                "maxItems": #len,
+            }))
+        }
+    }
+}
+
+fn described(schema: TokenStream2, doc: Option<String>) -> TokenStream2 {
+    match doc {
+        Some(doc) => quote!(::rustyx::openapi::described(#schema, #doc)),
+        None => schema,
+    }
+}
@@ -20,25 +20,44 @@
 //! }
 //! ```
 
-use crate::error::Result;
-use crate::middleware::{MiddlewareStack, Next};
+pub mod config;
+
+use crate::cache::Cache;
+use crate::controllers::{Controller, ResourceController};
+use crate::error::{Error, ErrorFormat, Result};
+use crate::health::{HealthChecks, HealthStatus};
+use crate::metrics::Metrics;
+use crate::middleware::rate_limit::{rate_limiter, RateLimiterConfig};
+use crate::middleware::{access_log, cors_with_options, CorsOptions};
+use crate::middleware::{from_middleware, Middleware, MiddlewareGroup, MiddlewareStack, Next};
+use crate::openapi::{OpenApi, Operation};
 use crate::request::Request;
-use crate::response::Response;
+use crate::response::{IntoResponse, Response, ResponseBody};
 use crate::router::Router;
+use crate::routes::{ApiVersion, RouteDefinition, RouteGroup};
+use crate::static_files::{static_handler, StaticConfig};
+use crate::testing::TestClient;
+use crate::views::Views;
+use crate::websocket::{self, WsConfig, WsHandler, WsServer};
 
-use bytes::Bytes;
-use http_body_util::Full;
+use config::AppConfig;
+use futures::FutureExt;
+use http_body_util::Limited;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{body::Incoming, Method};
 use hyper_util::rt::TokioIo;
+use std::collections::HashMap;
 use std::convert::Infallible;
 use std::future::Future;
 use std::net::SocketAddr;
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::TcpListener;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 /// Handler function type for route callbacks.
 ///
@@ -47,6 +66,19 @@ use tracing::{error, info};
 pub type HandlerFn =
     Arc<dyn Fn(Request, Response) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
 
+/// Box a handler returning anything that converts [`IntoResponse`]
+pub(crate) fn handler_fn<F, Fut, R>(handler: F) -> HandlerFn
+where
+    F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = R> + Send + 'static,
+    R: IntoResponse,
+{
+    Arc::new(move |req, res| {
+        let response = handler(req, res);
+        Box::pin(async move { response.await.into_response() })
+    })
+}
+
 /// The main application struct, similar to Express's `app`.
 ///
 /// `RustyX` is the core of your web application. It handles routing,
@@ -82,7 +114,15 @@ pub type HandlerFn =
 pub struct RustyX {
     router: Arc<std::sync::RwLock<Router>>,
     middleware_stack: Arc<std::sync::RwLock<MiddlewareStack>>,
+    middleware_groups: Arc<std::sync::RwLock<HashMap<String, MiddlewareGroup>>>,
     settings: Arc<std::sync::RwLock<AppSettings>>,
+    views: Arc<std::sync::RwLock<Option<Arc<Views>>>>,
+    ws_server: WsServer,
+    /// Print the routes banner on `listen`, set by dev mode
+    banner: Arc<AtomicBool>,
+    /// Address and TLS settings used by [`RustyX::run`]
+    config: Arc<std::sync::RwLock<AppConfig>>,
+    cache: Arc<std::sync::RwLock<Cache>>,
 }
 
 /// Application settings configuration.
@@ -100,6 +140,10 @@ pub struct AppSettings {
     pub strict_routing: bool,
     /// Current environment (development, production, etc.)
     pub env: String,
+    /// How error responses are rendered
+    pub error_format: ErrorFormat,
+    /// Largest accepted request body in bytes; larger requests get `413`
+    pub body_limit: Option<usize>,
 }
 
 impl Default for AppSettings {
@@ -110,6 +154,8 @@ impl Default for AppSettings {
             case_sensitive_routing: false,
             strict_routing: false,
             env: std::env::var("RUST_ENV").unwrap_or_else(|_| "development".to_string()),
+            error_format: ErrorFormat::default(),
+            body_limit: None,
         }
     }
 }
@@ -128,8 +174,100 @@ impl RustyX {
         Self {
             router: Arc::new(std::sync::RwLock::new(Router::new())),
             middleware_stack: Arc::new(std::sync::RwLock::new(MiddlewareStack::new())),
+            middleware_groups: Arc::new(std::sync::RwLock::new(HashMap::new())),
             settings: Arc::new(std::sync::RwLock::new(AppSettings::default())),
+            views: Arc::new(std::sync::RwLock::new(None)),
+            ws_server: WsServer::new(),
+            banner: Arc::new(AtomicBool::new(false)),
+            config: Arc::new(std::sync::RwLock::new(AppConfig::default())),
+            cache: Arc::new(std::sync::RwLock::new(Cache::memory())),
+        }
+    }
+
+    /// Create an application from a TOML or YAML file, connecting the
+    /// configured database
+    ///
+    /// See [`config`] for the format and environment overrides. Start it
+    /// with [`run`](Self::run) to listen on the configured address.
+    ///
+    /// ```rust,ignore
+    /// let app = RustyX::from_config("rustyx.toml").await?;
+    /// app.get("/", |_req, res| async move { res.send("Hello!") });
+    /// app.run().await
+    /// ```
+    pub async fn from_config(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let config = AppConfig::load(path)?;
+        if let Some(database) = &config.database {
+            crate::db::connection::init_db(database.connection()?).await?;
+        }
+        Ok(Self::with_config(config))
+    }
+
+    /// Create an application from loaded settings, registering the access
+    /// log, CORS and rate limiting middleware and the static mounts
+    ///
+    /// The database is not connected; [`from_config`](Self::from_config)
+    /// does that.
+    pub fn with_config(config: AppConfig) -> Self {
+        let app = Self::new();
+        {
+            let mut settings = app.settings.write().unwrap();
+            if let Some(env) = &config.env {
+                settings.env = env.clone();
+            }
+            settings.trust_proxy = config.trust_proxy;
+            settings.json_spaces = config.json_spaces;
+            settings.case_sensitive_routing = config.case_sensitive_routing;
+            settings.strict_routing = config.strict_routing;
+            settings.body_limit = config.body_limit;
+        }
+
+        if let Some(logs) = &config.access_log {
+            app.use_middleware(access_log(logs.config()));
         }
+        if let Some(cors) = &config.cors {
+            let mut options = CorsOptions {
+                exposed_headers: cors.exposed_headers.clone(),
+                credentials: cors.credentials,
+                max_age: cors.max_age,
+                ..CorsOptions::default()
+            };
+            if !cors.origins.iter().any(|origin| origin == "*") {
+                options.allowed_origins = cors.origins.clone();
+            }
+            if !cors.methods.is_empty() {
+                options.methods = cors.methods.clone();
+            }
+            if !cors.headers.is_empty() {
+                options.allowed_headers = cors.headers.clone();
+            }
+            app.use_middleware(cors_with_options(options));
+        }
+        if let Some(limit) = &config.rate_limit {
+            let limiter = RateLimiterConfig::new(limit.max_requests, limit.window_secs)
+                .skip(limit.skip.iter().map(String::as_str).collect());
+            app.use_middleware(rate_limiter(limiter));
+        }
+        for mount in &config.static_mounts {
+            let mut files = StaticConfig::new(&mount.dir).prefix(&mount.path);
+            if let Some(max_age) = mount.max_age {
+                files = files.max_age(max_age);
+            }
+            if let Some(index) = &mount.index {
+                files = files.index(index);
+            }
+            let prefix = mount.path.trim_end_matches('/');
+            app.get(&format!("{}/*", prefix), static_handler(files));
+        }
+
+        *app.config.write().unwrap() = config;
+        app
+    }
+
+    /// The settings passed to [`with_config`](Self::with_config), or the
+    /// defaults
+    pub fn config(&self) -> AppConfig {
+        self.config.read().unwrap().clone()
     }
 
     /// Set an application setting
@@ -145,12 +283,54 @@ impl RustyX {
                     settings.strict_routing = value.to_string().parse().unwrap_or(false)
                 }
                 "env" => settings.env = value.to_string(),
+                "body_limit" => settings.body_limit = value.to_string().parse().ok(),
                 _ => {}
             }
         }
         self
     }
 
+    /// Choose how errors are rendered
+    ///
+    /// Routers can override this for their paths with
+    /// [`Router::error_format`].
+    ///
+    /// ```rust,ignore
+    /// app.error_format(ErrorFormat::ProblemJson);
+    /// ```
+    pub fn error_format(&self, format: ErrorFormat) -> &Self {
+        if let Ok(mut settings) = self.settings.write() {
+            settings.error_format = format;
+        }
+        self
+    }
+
+    /// The application cache, in memory unless replaced with
+    /// [`set_cache`](Self::set_cache)
+    ///
+    /// See [`cache`](crate::cache).
+    pub fn cache(&self) -> Cache {
+        self.cache.read().unwrap().clone()
+    }
+
+    /// Replace the application cache, e.g. with [`Cache::redis`]
+    ///
+    /// Clones taken earlier keep the previous backend.
+    pub fn set_cache(&self, cache: Cache) -> &Self {
+        *self.cache.write().unwrap() = cache;
+        self
+    }
+
+    /// Set the template engine used by [`Response::render`]
+    ///
+    /// See [`views`](crate::views).
+    pub fn views(&self, views: Views) -> &Self {
+        if let Ok(mut slot) = self.views.write() {
+            *slot = Some(Arc::new(views));
+        }
+        self
+    }
+
     /// Add middleware to the application
     pub fn use_middleware<F, Fut>(&self, middleware: F) -> &Self
     where
@@ -165,75 +345,406 @@ impl RustyX {
         self
     }
 
+    /// Add struct-based middleware implementing the [`Middleware`] trait
+    pub fn use_middleware_obj(&self, middleware: Arc<dyn Middleware>) -> &Self {
+        self.use_middleware(from_middleware(middleware))
+    }
+
+    /// Define a named middleware group
+    ///
+    /// Groups can be applied to routers with [`Router::use_group`] or to
+    /// individual routes with [`RustyX::with_groups`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use rustyx::prelude::*;
+    ///
+    /// let app = RustyX::new();
+    /// app.middleware_group("api", MiddlewareGroup::new().with(logger()).with(helmet()));
+    ///
+    /// app.with_groups(&["api"]).get("/api/status", |_req, res| async move {
+    ///     res.json(json!({ "status": "ok" }))
+    /// });
+    /// ```
+    pub fn middleware_group(&self, name: &str, group: MiddlewareGroup) -> &Self {
+        if let Ok(mut groups) = self.middleware_groups.write() {
+            groups.insert(name.to_string(), group);
+        }
+        self
+    }
+
+    /// Combine named middleware groups into a single stack
+    fn resolve_groups<S: AsRef<str>>(&self, names: &[S]) -> MiddlewareStack {
+        let mut stack = MiddlewareStack::new();
+        if let Ok(groups) = self.middleware_groups.read() {
+            for name in names {
+                match groups.get(name.as_ref()) {
+                    Some(group) => stack.extend(group.stack()),
+                    None => warn!("Unknown middleware group: {}", name.as_ref()),
+                }
+            }
+        }
+        stack
+    }
+
+    /// Register routes wrapped in the given middleware groups
+    pub fn with_groups(&self, names: &[&str]) -> GroupedRoutes<'_> {
+        GroupedRoutes {
+            app: self,
+            stack: self.resolve_groups(names),
+        }
+    }
+
     /// Mount a router at a specific path prefix
-    pub fn use_router(&self, path: &str, router: Router) -> &Self {
+    pub fn use_router(&self, path: &str, mut router: Router) -> &Self {
+        let groups = router.take_groups();
+        if !groups.is_empty() {
+            router.prepend_middleware(self.resolve_groups(&groups));
+        }
+
         if let Ok(mut main_router) = self.router.write() {
             main_router.mount(path, router);
         }
         self
     }
 
+    /// Register the routes of a [`RouteDefinition`]
+    ///
+    /// ```rust,ignore
+    /// app.register::<UserRoutes>();
+    /// ```
+    pub fn register<R: RouteDefinition>(&self) -> &Self {
+        if let Ok(mut router) = self.router.write() {
+            R::register(&mut router);
+        }
+        self
+    }
+
+    /// Register the routes of a [`RouteGroup`]
+    pub fn mount_group(&self, group: RouteGroup) -> &Self {
+        if let Ok(mut router) = self.router.write() {
+            group.register(&mut router);
+        }
+        self
+    }
+
+    /// Register a controller's CRUD routes
+    ///
+    /// | Method          | Path         | Action    |
+    /// |-----------------|--------------|-----------|
+    /// | `GET`           | `/users`     | `index`   |
+    /// | `GET`           | `/users/:id` | `show`    |
+    /// | `POST`          | `/users`     | `create`  |
+    /// | `PUT` / `PATCH` | `/users/:id` | `update`  |
+    /// | `DELETE`        | `/users/:id` | `destroy` |
+    ///
+    /// ```rust,ignore
+    /// app.resource("/users", UserController);
+    /// ```
+    pub fn resource<C: Controller + 'static>(&self, path: &str, controller: C) -> &Self {
+        self.use_resource(ResourceController::new(path, controller))
+    }
+
+    /// Register a resource configured with `only` / `except`
+    pub fn use_resource<C: Controller + 'static>(&self, resource: ResourceController<C>) -> &Self {
+        if let Ok(mut router) = self.router.write() {
+            resource.register(&mut router);
+        }
+        self
+    }
+
+    /// Mount a router under an API version's prefix, applying its
+    /// deprecation policy (see [`ApiVersion`])
+    pub fn use_version(&self, version: &ApiVersion, mut router: Router) -> &Self {
+        if version.is_deprecated() {
+            let mut stack = MiddlewareStack::new();
+            let middleware = version.middleware();
+            stack.push(Box::new(move |req, res, next| middleware(req, res, next)));
+            router.prepend_middleware(stack);
+        }
+        self.use_router(version.prefix(), router)
+    }
+
     /// Register a GET route handler
-    pub fn get<F, Fut>(&self, path: &str, handler: F) -> &Self
+    pub fn get<F, Fut, R>(&self, path: &str, handler: F) -> &Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
         self.route(Method::GET, path, handler)
     }
 
     /// Register a POST route handler
-    pub fn post<F, Fut>(&self, path: &str, handler: F) -> &Self
+    pub fn post<F, Fut, R>(&self, path: &str, handler: F) -> &Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
         self.route(Method::POST, path, handler)
     }
 
     /// Register a PUT route handler
-    pub fn put<F, Fut>(&self, path: &str, handler: F) -> &Self
+    pub fn put<F, Fut, R>(&self, path: &str, handler: F) -> &Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
         self.route(Method::PUT, path, handler)
     }
 
     /// Register a DELETE route handler
-    pub fn delete<F, Fut>(&self, path: &str, handler: F) -> &Self
+    pub fn delete<F, Fut, R>(&self, path: &str, handler: F) -> &Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
         self.route(Method::DELETE, path, handler)
     }
 
     /// Register a PATCH route handler
-    pub fn patch<F, Fut>(&self, path: &str, handler: F) -> &Self
+    pub fn patch<F, Fut, R>(&self, path: &str, handler: F) -> &Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
         self.route(Method::PATCH, path, handler)
     }
 
+    /// Register a WebSocket endpoint
+    ///
+    /// GET requests to `path` are upgraded and each connection is registered
+    /// with the app's [`WsServer`], so handlers can reply or broadcast through
+    /// [`RustyX::ws_server`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// app.ws("/chat", ChatHandler::new(app.ws_server()));
+    /// ```
+    pub fn ws<H>(&self, path: &str, handler: H) -> &Self
+    where
+        H: WsHandler + 'static,
+    {
+        self.ws_with_config(path, handler, WsConfig::default())
+    }
+
+    /// Register a WebSocket endpoint with custom limits or a handshake guard
+    pub fn ws_with_config<H>(&self, path: &str, handler: H, config: WsConfig) -> &Self
+    where
+        H: WsHandler + 'static,
+    {
+        let handler: Arc<dyn WsHandler> = Arc::new(handler);
+        let server = self.ws_server.clone();
+        self.route(Method::GET, path, move |req, res| {
+            websocket::accept(req, res, handler.clone(), server.clone(), config.clone())
+        })
+    }
+
+    /// Get the WebSocket server shared by all `ws` endpoints
+    pub fn ws_server(&self) -> WsServer {
+        self.ws_server.clone()
+    }
+
     /// Internal method to register a route
-    fn route<F, Fut>(&self, method: Method, path: &str, handler: F) -> &Self
+    fn route<F, Fut, R>(&self, method: Method, path: &str, handler: F) -> &Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
+        self.add_handler(method, path, handler_fn(handler))
+    }
+
+    /// Internal method to register an already boxed handler
+    fn add_handler(&self, method: Method, path: &str, handler: HandlerFn) -> &Self {
         if let Ok(mut router) = self.router.write() {
-            router.add_route(
-                method,
-                path,
-                Arc::new(move |req, res| Box::pin(handler(req, res))),
-            );
+            router.add_route(method, path, handler);
         }
         self
     }
 
+    /// Document the most recently registered route
+    ///
+    /// ```rust,ignore
+    /// app.get("/users/:id", show)
+    ///     .doc(Operation::new("Get a user").response::<User>(200, "The user"));
+    /// ```
+    pub fn doc(&self, operation: Operation) -> &Self {
+        if let Ok(mut router) = self.router.write() {
+            router.doc(operation);
+        }
+        self
+    }
+
+    /// Serve the app's OpenAPI document, and the Swagger UI or Redoc pages
+    /// when configured
+    ///
+    /// The document is built on each request, so routes registered later
+    /// are included. See [`openapi`](crate::openapi).
+    ///
+    /// ```rust,ignore
+    /// app.openapi(OpenApi::new("Users API", "1.0.0").swagger_ui("/docs"));
+    /// ```
+    pub fn openapi(&self, docs: OpenApi) -> &Self {
+        let docs = Arc::new(docs);
+        // Weak, as the router owns this handler
+        let router = Arc::downgrade(&self.router);
+        let spec = Arc::clone(&docs);
+        self.get(&docs.path, move |_req, res| {
+            let doc = router
+                .upgrade()
+                .map(|router| spec.document(&router.read().unwrap()));
+            async move { res.json(doc) }
+        })
+        .doc(Operation::default().hidden());
+
+        let pages = [
+            (docs.swagger_ui.clone(), docs.swagger_ui_html()),
+            (docs.redoc.clone(), docs.redoc_html()),
+        ];
+        for (path, html) in pages {
+            if let Some(path) = path {
+                self.get(&path, move |_req, res| {
+                    let html = html.clone();
+                    async move { res.html(html) }
+                })
+                .doc(Operation::default().hidden());
+            }
+        }
+        self
+    }
+
+    /// Serve health checks at `path`, `path/live` and `path/ready`
+    ///
+    /// See [`health`](crate::health).
+    ///
+    /// ```rust,ignore
+    /// app.health("/healthz", HealthChecks::new().database("default"));
+    /// ```
+    pub fn health(&self, path: &str, checks: HealthChecks) -> &Self {
+        let checks = Arc::new(checks);
+        let base = path.trim_end_matches('/');
+        let endpoints = [
+            (base.to_string(), false),
+            (format!("{}/live", base), true),
+            (format!("{}/ready", base), false),
+        ];
+        for (path, liveness) in endpoints {
+            let checks = Arc::clone(&checks);
+            self.get(&path, move |_req, res| {
+                let checks = Arc::clone(&checks);
+                async move {
+                    let report = checks.run(liveness).await;
+                    let status = match report.status {
+                        HealthStatus::Up => 200,
+                        HealthStatus::Down => 503,
+                    };
+                    res.status(status)
+                        .header("cache-control", "no-store")
+                        .json(report)
+                }
+            });
+        }
+        self
+    }
+
+    /// The process-wide metrics registry
+    ///
+    /// ```rust,ignore
+    /// app.metrics().counter("jobs_processed").inc();
+    /// ```
+    pub fn metrics(&self) -> Metrics {
+        Metrics::global().clone()
+    }
+
+    /// Serve the metrics registry and this app's WebSocket metrics at
+    /// `path` in the Prometheus text format
+    pub fn metrics_route(&self, path: &str) -> &Self {
+        let ws_server = self.ws_server.clone();
+        self.get(path, move |_req, res| {
+            let mut body = Metrics::global().render();
+            body.push_str(&ws_server.metrics().to_prometheus());
+            async move {
+                res.header("content-type", "text/plain; version=0.0.4; charset=utf-8")
+                    .header("cache-control", "no-store")
+                    .send(body)
+            }
+        })
+    }
+
+    /// Watch templates, static assets and config files, reloading them
+    /// without a restart
+    ///
+    /// See [`dev`](crate::dev). Ignored when `env` is `production`.
+    ///
+    /// ```rust,ignore
+    /// app.dev(DevMode::new().templates("templates").assets("public").config(".env"))?;
+    /// ```
+    #[cfg(feature = "dev")]
+    pub fn dev(&self, mode: crate::dev::DevMode) -> Result<&Self> {
+        use crate::dev::{LIVE_RELOAD_PATH, LIVE_RELOAD_SCRIPT, LIVE_RELOAD_SCRIPT_PATH};
+
+        if self.settings.read().unwrap().env == "production" {
+            tracing::warn!("Dev mode is disabled in production");
+            return Ok(self);
+        }
+        let live_reload = mode.live_reload;
+        let reload = mode.start(Arc::clone(&self.views))?;
+        self.banner.store(true, Ordering::Relaxed);
+        if live_reload {
+            self.get(LIVE_RELOAD_PATH, move |req, res| {
+                let reload = Arc::clone(&reload);
+                async move {
+                    let since = req.query_param("since").cloned();
+                    let token = reload
+                        .wait(since.as_deref(), std::time::Duration::from_secs(30))
+                        .await;
+                    res.header("cache-control", "no-store")
+                        .json(serde_json::json!({ "token": token }))
+                }
+            })
+            .doc(Operation::default().hidden());
+            self.get(LIVE_RELOAD_SCRIPT_PATH, |_req, res| async move {
+                res.header("content-type", "application/javascript; charset=utf-8")
+                    .send(LIVE_RELOAD_SCRIPT)
+            })
+            .doc(Operation::default().hidden());
+            self.use_middleware(crate::dev::inject_script);
+        }
+        Ok(self)
+    }
+
+    /// The registered routes, one `METHOD  /path` per line, leaving out
+    /// routes hidden from the API docs
+    pub fn routes_banner(&self) -> String {
+        let router = self.router.read().unwrap();
+        let mut out = String::new();
+        for (method, path, operation) in router.operations() {
+            if operation.is_some_and(Operation::is_hidden) {
+                continue;
+            }
+            out.push_str(&format!("  {:<7} {}\n", method.as_str(), path));
+        }
+        out
+    }
+
+    /// In-process client that sends requests through the middleware and
+    /// router without binding a port
+    ///
+    /// ```rust,ignore
+    /// let res = app.test().post("/users").json(&json!({ "name": "Ann" })).send().await;
+    /// res.assert_status(201).assert_json_field("name", "Ann");
+    /// ```
+    pub fn test(&self) -> TestClient<'_> {
+        TestClient::new(self)
+    }
+
     /// Start the HTTP server and listen on the specified port
     pub async fn listen(self, port: u16) -> Result<()> {
         self.listen_with_callback(port, || {
@@ -251,39 +762,113 @@ impl RustyX {
         let listener = TcpListener::bind(addr).await?;
 
         callback();
+        self.serve(listener, None).await
+    }
+
+    /// Listen on the address from [`config`](Self::config), over HTTPS when
+    /// it has TLS settings
+    pub async fn run(self) -> Result<()> {
+        let config = self.config();
+        let listener = TcpListener::bind((config.host.as_str(), config.port)).await?;
+        let scheme = if config.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        info!(
+            "🚀 RustyX server running at {}://{}:{}",
+            scheme, config.host, config.port
+        );
+        match &config.tls {
+            #[cfg(feature = "tls")]
+            Some(tls) => {
+                let acceptor = tls_acceptor(&tls.cert, &tls.key)?;
+                self.serve(listener, Some(acceptor)).await
+            }
+            #[cfg(not(feature = "tls"))]
+            Some(_) => Err(Error::Custom(
+                "serving TLS requires the `tls` feature".to_string(),
+            )),
+            None => self.serve(listener, None).await,
+        }
+    }
+
+    /// Start an HTTPS server on the specified port with a PEM certificate
+    /// chain and private key
+    #[cfg(feature = "tls")]
+    pub async fn listen_tls(
+        self,
+        port: u16,
+        cert: impl AsRef<std::path::Path>,
+        key: impl AsRef<std::path::Path>,
+    ) -> Result<()> {
+        let acceptor = tls_acceptor(cert.as_ref(), key.as_ref())?;
+        let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+        info!("🚀 RustyX server running at https://localhost:{}", port);
+        self.serve(listener, Some(acceptor)).await
+    }
+
+    /// Accept connections until the listener fails
+    pub(crate) async fn serve(self, listener: TcpListener, tls: Option<TlsAcceptor>) -> Result<()> {
+        if self.banner.load(Ordering::Relaxed) {
+            println!("\nRoutes:\n{}", self.routes_banner());
+        }
 
         let app = Arc::new(self);
 
         loop {
             let (stream, remote_addr) = listener.accept().await?;
-            let io = TokioIo::new(stream);
             let app = Arc::clone(&app);
+            let tls = tls.clone();
 
             tokio::spawn(async move {
-                let service = service_fn(move |req: hyper::Request<Incoming>| {
-                    let app = Arc::clone(&app);
-                    async move {
-                        let response = app.handle_request(req, remote_addr).await;
-                        Ok::<_, Infallible>(response)
-                    }
-                });
-
-                if let Err(err) = http1::Builder::new().serve_connection(io, service).await {
-                    error!("Error serving connection: {:?}", err);
+                match tls {
+                    #[cfg(feature = "tls")]
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(stream) => {
+                            serve_connection(app, TokioIo::new(stream), remote_addr).await
+                        }
+                        Err(err) => warn!("TLS handshake with {} failed: {}", remote_addr, err),
+                    },
+                    #[cfg(not(feature = "tls"))]
+                    Some(never) => match never {},
+                    None => serve_connection(app, TokioIo::new(stream), remote_addr).await,
                 }
             });
         }
     }
 
     /// Handle an incoming HTTP request
-    async fn handle_request(
+    pub(crate) async fn handle_request<B>(
         &self,
-        req: hyper::Request<Incoming>,
+        req: hyper::Request<B>,
         remote_addr: SocketAddr,
-    ) -> hyper::Response<Full<Bytes>> {
-        // Convert hyper request to our Request type
+    ) -> hyper::Response<ResponseBody>
+    where
+        B: hyper::body::Body,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        // Convert hyper request to our Request type, refusing bodies over
+        // the limit before reading them where the length is declared
+        let scoped = self.router.read().unwrap().body_limit_for(req.uri().path());
+        let limit = scoped.or(self.settings.read().unwrap().body_limit);
+        let declared = req
+            .headers()
+            .get(hyper::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        let too_large = Response::new()
+            .status(413)
+            .json(serde_json::json!({ "error": "Payload Too Large" }));
+        if let (Some(limit), Some(declared)) = (limit, declared) {
+            if declared > limit {
+                return too_large.into_hyper();
+            }
+        }
+        let req = req.map(|body| Limited::new(body, limit.unwrap_or(usize::MAX)));
         let request = match Request::from_hyper(req, remote_addr).await {
             Ok(r) => r,
+            Err(Error::PayloadTooLarge(_)) => return too_large.into_hyper(),
             Err(e) => {
                 error!("Failed to parse request: {:?}", e);
                 return Response::new()
@@ -293,28 +878,225 @@ impl RustyX {
             }
         };
 
-        let response = Response::new();
+        self.handle(request).await.into_hyper()
+    }
+
+    /// Run a request through the middleware chain and router
+    pub(crate) async fn handle(&self, request: Request) -> Response {
+        // Run the middleware chain, ending with the route dispatcher
+        let router = Arc::clone(&self.router);
+        let endpoint: Next = Arc::new(move |req, res| {
+            let router = Arc::clone(&router);
+            Box::pin(async move { dispatch(&router, req, res).await })
+        });
+        let chain = self.middleware_stack.read().unwrap().compose(endpoint);
 
-        // Find and execute the route handler
-        let handler_and_params = {
-            let router = self.router.read().unwrap();
-            router
-                .find_route(request.method(), request.path())
-                .map(|(h, p)| (Arc::clone(h), p))
+        // Hyper drops this future when the client disconnects; the guard
+        // then cancels the request's token so detached work can stop too
+        let path = request.path().to_string();
+        let method = request.method().clone();
+        let started = Instant::now();
+        let (development, views) = {
+            let settings = self.settings.read().unwrap();
+            let development = settings.env == "development";
+            let views = self.views.read().unwrap().clone().map(|views| {
+                let reload = views.reloads(development);
+                (views, reload)
+            });
+            (development, views)
+        };
+        let guard = request.cancellation_token().drop_guard();
+        let res = Response::new().with_views(views.clone());
+        let response = match AssertUnwindSafe(chain(request, res)).catch_unwind().await {
+            Ok(response) => response,
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "handler panicked".to_string());
+                error!("Request handler panicked: {}", message);
+                Response::from(Error::Internal(message))
+            }
         };
+        guard.disarm();
 
-        if let Some((handler, params)) = handler_and_params {
-            let mut request = request;
-            request.set_params(params);
+        let format = {
+            let scoped = self.router.read().unwrap().error_format_for(&path);
+            scoped.unwrap_or(self.settings.read().unwrap().error_format)
+        };
+        let response = response
+            .with_views(views)
+            .render_error(&format, development);
+        Metrics::global().record_http(
+            method.as_str(),
+            response.get_status().as_u16(),
+            started.elapsed(),
+        );
+        response
+    }
+}
 
-            let response = handler(request, response).await;
-            response.into_hyper()
-        } else {
-            Response::new()
-                .status(404)
-                .json(serde_json::json!({ "error": "Not Found" }))
-                .into_hyper()
+#[cfg(feature = "tls")]
+pub(crate) type TlsAcceptor = tokio_rustls::TlsAcceptor;
+
+/// Stands in for the acceptor without the `tls` feature
+#[cfg(not(feature = "tls"))]
+#[derive(Clone)]
+pub(crate) enum TlsAcceptor {}
+
+/// Build a TLS acceptor from PEM files
+#[cfg(feature = "tls")]
+fn tls_acceptor(cert: &std::path::Path, key: &std::path::Path) -> Result<TlsAcceptor> {
+    use tokio_rustls::rustls;
+
+    let open = |path: &std::path::Path| {
+        std::fs::File::open(path)
+            .map(std::io::BufReader::new)
+            .map_err(|e| Error::Custom(format!("failed to read {}: {}", path.display(), e)))
+    };
+    let certs = rustls_pemfile::certs(&mut open(cert)?)?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect::<Vec<_>>();
+    if certs.is_empty() {
+        return Err(Error::Custom(format!(
+            "no certificates in {}",
+            cert.display()
+        )));
+    }
+    let key = rustls_pemfile::read_all(&mut open(key)?)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(rustls::PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| Error::Custom(format!("no private key in {}", key.display())))?;
+
+    let mut config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| Error::Custom(format!("invalid TLS certificate: {}", e)))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Serve HTTP/1.1 requests on one connection
+async fn serve_connection<I>(app: Arc<RustyX>, io: TokioIo<I>, remote_addr: SocketAddr)
+where
+    I: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let service = service_fn(move |req: hyper::Request<Incoming>| {
+        let app = Arc::clone(&app);
+        async move {
+            let response = app.handle_request(req, remote_addr).await;
+            Ok::<_, Infallible>(response)
         }
+    });
+
+    if let Err(err) = http1::Builder::new()
+        .serve_connection(io, service)
+        .with_upgrades()
+        .await
+    {
+        error!("Error serving connection: {:?}", err);
+    }
+}
+
+/// Find and execute the route handler for a request
+async fn dispatch(router: &std::sync::RwLock<Router>, req: Request, res: Response) -> Response {
+    let handler_and_route = {
+        let router = router.read().unwrap();
+        router
+            .match_route(req.method(), req.path())
+            .map(|(h, route)| (Arc::clone(h), route))
+    };
+
+    if let Some((handler, route)) = handler_and_route {
+        let mut req = req;
+        req.set_params(route.params.clone());
+        handler(req, res).await.with_route(route)
+    } else {
+        let error = Error::not_found(req.path());
+        res.status(404)
+            .json(serde_json::json!({ "error": "Not Found" }))
+            .with_error(error)
+    }
+}
+
+/// Route registrar that wraps every handler in a set of middleware groups.
+///
+/// Created by [`RustyX::with_groups`].
+pub struct GroupedRoutes<'a> {
+    app: &'a RustyX,
+    stack: MiddlewareStack,
+}
+
+impl GroupedRoutes<'_> {
+    /// Register a GET route handler
+    pub fn get<F, Fut, R>(&self, path: &str, handler: F) -> &Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::GET, path, handler)
+    }
+
+    /// Register a POST route handler
+    pub fn post<F, Fut, R>(&self, path: &str, handler: F) -> &Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::POST, path, handler)
+    }
+
+    /// Register a PUT route handler
+    pub fn put<F, Fut, R>(&self, path: &str, handler: F) -> &Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::PUT, path, handler)
+    }
+
+    /// Register a DELETE route handler
+    pub fn delete<F, Fut, R>(&self, path: &str, handler: F) -> &Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::DELETE, path, handler)
+    }
+
+    /// Register a PATCH route handler
+    pub fn patch<F, Fut, R>(&self, path: &str, handler: F) -> &Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::PATCH, path, handler)
+    }
+
+    /// Internal method to register a wrapped route
+    fn route<F, Fut, R>(&self, method: Method, path: &str, handler: F) -> &Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        let handler: HandlerFn = handler_fn(handler);
+        self.app
+            .add_handler(method, path, self.stack.compose(handler));
+        self
     }
 }
 
@@ -329,7 +1111,13 @@ impl Clone for RustyX {
         Self {
             router: Arc::clone(&self.router),
             middleware_stack: Arc::clone(&self.middleware_stack),
+            middleware_groups: Arc::clone(&self.middleware_groups),
             settings: Arc::clone(&self.settings),
+            views: Arc::clone(&self.views),
+            ws_server: self.ws_server.clone(),
+            banner: Arc::clone(&self.banner),
+            config: Arc::clone(&self.config),
+            cache: Arc::clone(&self.cache),
         }
     }
 }
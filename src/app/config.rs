@@ -0,0 +1,463 @@
+//! Configuration Files
+//!
+//! [`AppConfig`] describes a deployment: the address to bind, TLS
+//! certificates, request body limits, CORS, rate limiting, static mounts
+//! the access log and the database. It is read from a TOML file (or YAML with the `yaml`
+//! feature) and applied by [`RustyX::from_config`](crate::RustyX::from_config).
+//!
+//! ```toml
+//! host = "0.0.0.0"
+//! port = 8080
+//! env = "production"
+//! body_limit = "10MB"
+//!
+//! [tls]
+//! cert = "certs/server.pem"
+//! key = "certs/server.key"
+//!
+//! [cors]
+//! origins = ["https://app.example.com"]
+//! credentials = true
+//!
+//! [rate_limit]
+//! max_requests = 100
+//! window_secs = 60
+//!
+//! [access_log]
+//! path = "logs/access.log"
+//! format = "json"
+//! max_size = "100MB"
+//! rotate = "daily"
+//! keep = 14
+//!
+//! [[static]]
+//! path = "/assets"
+//! dir = "public"
+//! max_age = 86400
+//!
+//! [database]
+//! url = "postgres://app:secret@db/shop"
+//! max_connections = 20
+//! ```
+//!
+//! Variables named `RUSTYX_<KEY>` in `.env` or the environment override
+//! the file, with `__` separating nested keys and commas separating list
+//! items: `RUSTYX_PORT=9000`, `RUSTYX_DATABASE__URL=...`,
+//! `RUSTYX_CORS__ORIGINS=https://a.com,https://b.com`.
+
+use crate::error::{Error, Result};
+use crate::middleware::{AccessLogConfig, AccessLogFormat, RotationInterval};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Prefix of the variables overriding configuration files
+const ENV_PREFIX: &str = "RUSTYX_";
+
+/// Settings loaded from a configuration file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Address to bind (default `0.0.0.0`)
+    pub host: String,
+    /// Port to listen on (default 3000)
+    pub port: u16,
+    /// Environment name, overriding `RUST_ENV`
+    pub env: Option<String>,
+    pub trust_proxy: bool,
+    pub json_spaces: Option<usize>,
+    pub case_sensitive_routing: bool,
+    pub strict_routing: bool,
+    /// Largest accepted request body, in bytes or as `"512KB"`, `"10MB"`
+    #[serde(deserialize_with = "byte_size")]
+    pub body_limit: Option<usize>,
+    pub tls: Option<TlsSettings>,
+    pub cors: Option<CorsSettings>,
+    pub rate_limit: Option<RateLimitSettings>,
+    pub access_log: Option<AccessLogSettings>,
+    /// Directories served under URL prefixes, `[[static]]` in TOML
+    #[serde(rename = "static")]
+    pub static_mounts: Vec<StaticMount>,
+    pub database: Option<DatabaseSettings>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 3000,
+            env: None,
+            trust_proxy: false,
+            json_spaces: None,
+            case_sensitive_routing: false,
+            strict_routing: false,
+            body_limit: None,
+            tls: None,
+            cors: None,
+            rate_limit: None,
+            access_log: None,
+            static_mounts: Vec::new(),
+            database: None,
+        }
+    }
+}
+
+/// PEM certificate chain and private key for HTTPS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TlsSettings {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// CORS middleware options, see [`CorsOptions`](crate::middleware::CorsOptions)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsSettings {
+    /// Allowed origins; empty or `*` allows any
+    pub origins: Vec<String>,
+    /// Allowed methods, empty for the middleware's defaults
+    pub methods: Vec<String>,
+    /// Allowed request headers, empty for the middleware's defaults
+    pub headers: Vec<String>,
+    pub exposed_headers: Vec<String>,
+    pub credentials: bool,
+    pub max_age: u32,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            origins: Vec::new(),
+            methods: Vec::new(),
+            headers: Vec::new(),
+            exposed_headers: Vec::new(),
+            credentials: false,
+            max_age: 86400,
+        }
+    }
+}
+
+/// Rate limiter options, see
+/// [`RateLimiterConfig`](crate::middleware::rate_limit::RateLimiterConfig)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    pub max_requests: u32,
+    pub window_secs: u64,
+    /// Paths that are never limited
+    pub skip: Vec<String>,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            max_requests: 100,
+            window_secs: 60,
+            skip: Vec::new(),
+        }
+    }
+}
+
+/// Access log options, see [`AccessLogConfig`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessLogSettings {
+    pub path: PathBuf,
+    /// `json`, `common` or `combined` (the default)
+    #[serde(default)]
+    pub format: AccessLogFormat,
+    /// Rotate past this size, in bytes or as `"100MB"`
+    #[serde(default, deserialize_with = "byte_size")]
+    pub max_size: Option<usize>,
+    /// `hourly` or `daily`
+    #[serde(default)]
+    pub rotate: Option<RotationInterval>,
+    /// Rotated files to keep
+    #[serde(default)]
+    pub keep: Option<usize>,
+    /// Remove rotated files older than this many days
+    #[serde(default)]
+    pub max_age_days: Option<u64>,
+    /// Paths that are not logged
+    #[serde(default)]
+    pub skip: Vec<String>,
+}
+
+impl AccessLogSettings {
+    /// The middleware config for these settings
+    pub fn config(&self) -> AccessLogConfig {
+        let mut config = AccessLogConfig::new(&self.path)
+            .format(self.format)
+            .skip(self.skip.iter().map(String::as_str).collect());
+        config.max_size = self.max_size.map(|size| size as u64);
+        config.interval = self.rotate;
+        config.keep = self.keep;
+        config.max_age = self
+            .max_age_days
+            .map(|days| std::time::Duration::from_secs(days * 86_400));
+        config
+    }
+}
+
+/// A directory served under a URL prefix
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StaticMount {
+    /// URL prefix, e.g. `/assets`
+    pub path: String,
+    pub dir: String,
+    /// `Cache-Control` max-age in seconds
+    #[serde(default)]
+    pub max_age: Option<u32>,
+    /// Index file for directories
+    #[serde(default)]
+    pub index: Option<String>,
+}
+
+/// The default database connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseSettings {
+    /// Connection URL, see [`DatabaseConfig::from_url`](crate::db::DatabaseConfig::from_url)
+    pub url: String,
+    #[serde(default)]
+    pub max_connections: Option<u32>,
+    #[serde(default)]
+    pub min_connections: Option<u32>,
+}
+
+impl DatabaseSettings {
+    /// The connection config, with the pool sizes applied
+    pub fn connection(&self) -> Result<crate::db::DatabaseConfig> {
+        let mut config = crate::db::DatabaseConfig::from_url(&self.url)?;
+        if let Some(max) = self.max_connections {
+            config = config.max_connections(max);
+        }
+        if let Some(min) = self.min_connections {
+            config = config.min_connections(min);
+        }
+        Ok(config)
+    }
+}
+
+impl AppConfig {
+    /// Read `path`, then apply `RUSTYX_*` overrides from `.env` and the
+    /// environment
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::Custom(format!("failed to read {}: {}", path.display(), e)))?;
+        let file = parse_file(path, &text)?;
+        let vars = crate::utils::config::read_env_files(&[PathBuf::from(".env")])?;
+        Self::from_sources(file, vars.into_iter().chain(std::env::vars())).map_err(|e| {
+            Error::Custom(format!(
+                "invalid configuration in {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Parse TOML text, applying only the given overrides
+    pub fn from_toml(text: &str, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let file = toml::from_str(text).map_err(|e| Error::ParseError(e.to_string()))?;
+        Self::from_sources(file, vars)
+    }
+
+    fn from_sources(file: Value, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let mut root = serde_json::to_value(Self::default())?;
+        merge(&mut root, file);
+        for (name, value) in vars {
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let path: Vec<String> = key.to_lowercase().split("__").map(String::from).collect();
+            set_path(&mut root, &path, &value);
+        }
+        Ok(serde_json::from_value(root)?)
+    }
+}
+
+fn parse_file(path: &Path, text: &str) -> Result<Value> {
+    let invalid = |e: &dyn std::fmt::Display| {
+        Error::ParseError(format!("failed to parse {}: {}", path.display(), e))
+    };
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(text).map_err(|e| invalid(&e)),
+        Some("json") => serde_json::from_str(text).map_err(|e| invalid(&e)),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => serde_yaml::from_str(text).map_err(|e| invalid(&e)),
+        #[cfg(not(feature = "yaml"))]
+        Some("yaml" | "yml") => Err(Error::Custom(format!(
+            "reading {} requires the `yaml` feature",
+            path.display()
+        ))),
+        _ => Err(Error::Custom(format!(
+            "unsupported configuration file {}, expected .toml, .yaml or .json",
+            path.display()
+        ))),
+    }
+}
+
+/// Merge `overlay` into `base`, recursing into tables
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Set the value at `path` from a variable, typed after the value it
+/// replaces
+fn set_path(node: &mut Value, path: &[String], raw: &str) {
+    let Some((key, rest)) = path.split_first() else {
+        *node = coerce(node, raw);
+        return;
+    };
+    if !node.is_object() {
+        *node = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = node {
+        set_path(map.entry(key.clone()).or_insert(Value::Null), rest, raw);
+    }
+}
+
+fn coerce(current: &Value, raw: &str) -> Value {
+    let raw = raw.trim();
+    match current {
+        Value::String(_) => Value::String(raw.to_string()),
+        Value::Array(_) => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        ),
+        // Numbers, booleans and unset values take JSON scalars, keeping
+        // anything else as a string for the field to reject or parse
+        _ => match serde_json::from_str::<Value>(raw) {
+            Ok(value @ (Value::Number(_) | Value::Bool(_))) => value,
+            _ => Value::String(raw.to_string()),
+        },
+    }
+}
+
+fn byte_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<usize>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(usize),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&text)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid size `{}`", text))),
+    }
+}
+
+/// Parse `1024`, `512KB`, `10MB` or `1GB` (binary multiples)
+fn parse_size(text: &str) -> Option<usize> {
+    let text = text.trim().to_ascii_uppercase();
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+    let multiplier = match unit.trim() {
+        "" | "B" => 1,
+        "KB" | "K" | "KIB" => 1 << 10,
+        "MB" | "M" | "MIB" => 1 << 20,
+        "GB" | "G" | "GIB" => 1 << 30,
+        _ => return None,
+    };
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    const CONFIG: &str = r#"
+        port = 8080
+        body_limit = "2KB"
+
+        [cors]
+        origins = ["https://app.example.com"]
+
+        [rate_limit]
+        max_requests = 2
+
+        [[static]]
+        path = "/assets"
+        dir = "src"
+    "#;
+
+    #[tokio::test]
+    async fn test_config_file_with_overrides() {
+        let vars = [
+            ("RUSTYX_PORT", "9000"),
+            ("RUSTYX_ENV", "staging"),
+            ("RUSTYX_CORS__ORIGINS", "https://a.com, https://b.com"),
+            ("RUSTYX_DATABASE__URL", "sqlite::memory:"),
+            ("OTHER_PORT", "1"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let config = AppConfig::from_toml(CONFIG, vars).unwrap();
+        assert_eq!((config.host.as_str(), config.port), ("0.0.0.0", 9000));
+        assert_eq!(config.env.as_deref(), Some("staging"));
+        assert_eq!(config.body_limit, Some(2048));
+        let cors = config.cors.as_ref().unwrap();
+        assert_eq!(cors.origins, ["https://a.com", "https://b.com"]);
+        assert_eq!(cors.max_age, 86400);
+        assert_eq!(config.rate_limit.as_ref().unwrap().window_secs, 60);
+        assert_eq!(config.database.as_ref().unwrap().url, "sqlite::memory:");
+        assert_eq!(parse_size("10 MB"), Some(10 << 20));
+
+        let logs = "[access_log]\npath = \"access.log\"\nmax_size = \"1MB\"\nrotate = \"daily\"";
+        let logs = AppConfig::from_toml(logs, Vec::new())
+            .unwrap()
+            .access_log
+            .unwrap();
+        let logs = logs.config();
+        assert_eq!(logs.format, AccessLogFormat::Combined);
+        assert_eq!(logs.max_size, Some(1 << 20));
+        assert_eq!(logs.interval, Some(RotationInterval::Daily));
+        assert_eq!(parse_size("ten"), None);
+
+        let invalid = [("RUSTYX_PORT".to_string(), "http".to_string())];
+        assert!(AppConfig::from_toml(CONFIG, invalid).is_err());
+
+        let app = RustyX::with_config(AppConfig {
+            database: None,
+            ..config
+        });
+        app.get("/", |_req, res| async move { res.send("ok") });
+        let res = app
+            .test()
+            .get("/")
+            .header("origin", "https://b.com")
+            .send()
+            .await;
+        res.assert_status(200)
+            .assert_header("access-control-allow-origin", "https://b.com");
+        app.test()
+            .get("/assets/lib.rs")
+            .send()
+            .await
+            .assert_status(200);
+        app.test().get("/").send().await.assert_status(429);
+        assert_eq!(app.config().port, 9000);
+    }
+}
@@ -0,0 +1,608 @@
+//! Cache
+//!
+//! A key-value cache for handlers and middleware, in process or in Redis
+//! (`redis` feature). Values are stored as JSON under a key prefix.
+//!
+//! ```rust,ignore
+//! let cache = app.cache();
+//! app.get("/stats", move |_req, res| {
+//!     let cache = cache.clone();
+//!     async move {
+//!         let stats: Stats = cache
+//!             .tags(&["stats"])
+//!             .remember("stats:daily", Duration::from_secs(300), || compute_stats())
+//!             .await?;
+//!         Ok::<_, Error>(res.json(stats))
+//!     }
+//! });
+//!
+//! // Later, after the data changes
+//! app.cache().tags(&["stats"]).flush().await?;
+//! ```
+//!
+//! The app's cache also backs middleware: pass it to
+//! [`CacheConfig::store`](crate::middleware::cache::CacheConfig::store)
+//! for response caching and to
+//! [`RateLimiterConfig::cache`](crate::middleware::rate_limit::RateLimiterConfig::cache)
+//! to share rate limit counters, e.g. between instances behind a load
+//! balancer with [`Cache::redis`].
+
+use crate::error::{Error, Result};
+use crate::middleware::cache::{CacheStore, CachedResponse};
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Storage for a [`Cache`]
+///
+/// Keys arrive with the cache's prefix applied.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Store a value, expiring after `ttl` when given
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()>;
+
+    /// Delete keys, returning how many existed
+    async fn delete(&self, keys: &[String]) -> Result<u64>;
+
+    /// Delete every key starting with `prefix`
+    async fn delete_prefix(&self, prefix: &str) -> Result<()>;
+
+    /// Add `by` to a counter, creating it with `ttl`, and return the new
+    /// value and the time left until it expires
+    async fn increment(&self, key: &str, by: i64, ttl: Duration) -> Result<(i64, Duration)>;
+
+    /// Add a member to the set at `key`
+    async fn add_to_set(&self, key: &str, member: &str) -> Result<()>;
+
+    /// Members of the set at `key`
+    async fn set_members(&self, key: &str) -> Result<Vec<String>>;
+}
+
+enum Stored {
+    Bytes(Vec<u8>),
+    Set(HashSet<String>),
+}
+
+struct MemoryItem {
+    value: Stored,
+    expires_at: Option<Instant>,
+}
+
+impl MemoryItem {
+    fn live(&self, now: Instant) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+    }
+}
+
+/// In-process [`CacheBackend`]
+#[derive(Clone)]
+pub struct MemoryCache {
+    entries: Arc<RwLock<HashMap<String, MemoryItem>>>,
+    last_sweep: Arc<RwLock<Instant>>,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            last_sweep: Arc::new(RwLock::new(Instant::now())),
+        }
+    }
+
+    /// Drop expired entries about once a minute
+    fn sweep(&self, entries: &mut HashMap<String, MemoryItem>, now: Instant) {
+        let mut last = self.last_sweep.write();
+        if now.duration_since(*last) >= Duration::from_secs(60) {
+            entries.retain(|_, item| item.live(now));
+            *last = now;
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let entries = self.entries.read();
+        Ok(match entries.get(key) {
+            Some(item) if item.live(Instant::now()) => match &item.value {
+                Stored::Bytes(bytes) => Some(bytes.clone()),
+                Stored::Set(_) => None,
+            },
+            _ => None,
+        })
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
+        let now = Instant::now();
+        let mut entries = self.entries.write();
+        self.sweep(&mut entries, now);
+        entries.insert(
+            key.to_string(),
+            MemoryItem {
+                value: Stored::Bytes(value),
+                expires_at: ttl.map(|ttl| now + ttl),
+            },
+        );
+        Ok(())
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<u64> {
+        let now = Instant::now();
+        let mut entries = self.entries.write();
+        Ok(keys
+            .iter()
+            .filter_map(|key| entries.remove(key))
+            .filter(|item| item.live(now))
+            .count() as u64)
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> Result<()> {
+        self.entries
+            .write()
+            .retain(|key, _| !key.starts_with(prefix));
+        Ok(())
+    }
+
+    async fn increment(&self, key: &str, by: i64, ttl: Duration) -> Result<(i64, Duration)> {
+        let now = Instant::now();
+        let mut entries = self.entries.write();
+        self.sweep(&mut entries, now);
+        let item = match entries.get_mut(key) {
+            Some(item) if item.live(now) => item,
+            _ => {
+                entries.insert(
+                    key.to_string(),
+                    MemoryItem {
+                        value: Stored::Bytes(b"0".to_vec()),
+                        expires_at: Some(now + ttl),
+                    },
+                );
+                entries.get_mut(key).unwrap()
+            }
+        };
+        let Stored::Bytes(bytes) = &mut item.value else {
+            return Err(Error::Internal(format!(
+                "cache key {} is not a counter",
+                key
+            )));
+        };
+        let count = std::str::from_utf8(bytes)
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .ok_or_else(|| Error::Internal(format!("cache key {} is not a counter", key)))?
+            + by;
+        *bytes = count.to_string().into_bytes();
+        let left = item
+            .expires_at
+            .map_or(Duration::MAX, |at| at.saturating_duration_since(now));
+        Ok((count, left))
+    }
+
+    async fn add_to_set(&self, key: &str, member: &str) -> Result<()> {
+        let now = Instant::now();
+        let mut entries = self.entries.write();
+        let item = entries.entry(key.to_string()).or_insert(MemoryItem {
+            value: Stored::Set(HashSet::new()),
+            expires_at: None,
+        });
+        if !item.live(now) || matches!(item.value, Stored::Bytes(_)) {
+            *item = MemoryItem {
+                value: Stored::Set(HashSet::new()),
+                expires_at: None,
+            };
+        }
+        if let Stored::Set(members) = &mut item.value {
+            members.insert(member.to_string());
+        }
+        Ok(())
+    }
+
+    async fn set_members(&self, key: &str) -> Result<Vec<String>> {
+        Ok(match self.entries.read().get(key) {
+            Some(MemoryItem {
+                value: Stored::Set(members),
+                ..
+            }) => members.iter().cloned().collect(),
+            _ => Vec::new(),
+        })
+    }
+}
+
+/// Redis [`CacheBackend`], shared between server instances
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisCache {
+    conn: redis::aio::ConnectionManager,
+}
+
+#[cfg(feature = "redis")]
+impl RedisCache {
+    pub fn new(client: &crate::db::redis::RedisClient) -> Self {
+        Self {
+            conn: client.connection(),
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl CacheBackend for RedisCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        use redis::AsyncCommands;
+        let mut conn = self.conn.clone();
+        conn.get(key).await.map_err(crate::db::redis::redis_error)
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
+        use redis::AsyncCommands;
+        let mut conn = self.conn.clone();
+        match ttl {
+            Some(ttl) => {
+                conn.pset_ex(key, value, ttl.as_millis().max(1) as u64)
+                    .await
+            }
+            None => conn.set(key, value).await,
+        }
+        .map_err(crate::db::redis::redis_error)
+    }
+
+    async fn delete(&self, keys: &[String]) -> Result<u64> {
+        use redis::AsyncCommands;
+        if keys.is_empty() {
+            return Ok(0);
+        }
+        let mut conn = self.conn.clone();
+        conn.del(keys).await.map_err(crate::db::redis::redis_error)
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> Result<()> {
+        use redis::AsyncCommands;
+        let escaped: String = prefix
+            .chars()
+            .flat_map(|c| match c {
+                '*' | '?' | '[' | ']' | '\\' => vec!['\\', c],
+                _ => vec![c],
+            })
+            .collect();
+        let mut conn = self.conn.clone();
+        let mut keys = Vec::new();
+        {
+            let mut iter = conn
+                .scan_match::<_, String>(format!("{}*", escaped))
+                .await
+                .map_err(crate::db::redis::redis_error)?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+        }
+        self.delete(&keys).await.map(|_| ())
+    }
+
+    async fn increment(&self, key: &str, by: i64, ttl: Duration) -> Result<(i64, Duration)> {
+        let mut conn = self.conn.clone();
+        let (count, left): (i64, i64) = redis::pipe()
+            .atomic()
+            .incr(key, by)
+            .pttl(key)
+            .query_async(&mut conn)
+            .await
+            .map_err(crate::db::redis::redis_error)?;
+        if left >= 0 {
+            return Ok((count, Duration::from_millis(left as u64)));
+        }
+        // A new counter has no expiry yet
+        redis::cmd("PEXPIRE")
+            .arg(key)
+            .arg(ttl.as_millis().max(1) as u64)
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(crate::db::redis::redis_error)?;
+        Ok((count, ttl))
+    }
+
+    async fn add_to_set(&self, key: &str, member: &str) -> Result<()> {
+        use redis::AsyncCommands;
+        let mut conn = self.conn.clone();
+        conn.sadd(key, member)
+            .await
+            .map_err(crate::db::redis::redis_error)
+    }
+
+    async fn set_members(&self, key: &str) -> Result<Vec<String>> {
+        use redis::AsyncCommands;
+        let mut conn = self.conn.clone();
+        conn.smembers(key)
+            .await
+            .map_err(crate::db::redis::redis_error)
+    }
+}
+
+/// Cache facade over a [`CacheBackend`]
+///
+/// Cloning is cheap and clones share the backend.
+#[derive(Clone)]
+pub struct Cache {
+    backend: Arc<dyn CacheBackend>,
+    prefix: String,
+    tags: Vec<String>,
+}
+
+impl std::fmt::Debug for Cache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cache")
+            .field("prefix", &self.prefix)
+            .field("tags", &self.tags)
+            .finish()
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::memory()
+    }
+}
+
+impl Cache {
+    /// A cache over `backend` with the key prefix `rustyx:`
+    pub fn new(backend: impl CacheBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            prefix: "rustyx:".to_string(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// An in-process cache
+    pub fn memory() -> Self {
+        Self::new(MemoryCache::new())
+    }
+
+    /// A cache in Redis
+    #[cfg(feature = "redis")]
+    pub fn redis(client: &crate::db::redis::RedisClient) -> Self {
+        Self::new(RedisCache::new(client))
+    }
+
+    /// A view of the same backend under another prefix, e.g. per tenant
+    pub fn prefix(&self, prefix: &str) -> Self {
+        Self {
+            backend: Arc::clone(&self.backend),
+            prefix: prefix.to_string(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// A view whose writes are recorded under `tags`, so
+    /// [`flush`](Self::flush) can remove them together
+    pub fn tags(&self, tags: &[&str]) -> Self {
+        Self {
+            backend: Arc::clone(&self.backend),
+            prefix: self.prefix.clone(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    fn tag_key(&self, tag: &str) -> String {
+        format!("{}tag:{}", self.prefix, tag)
+    }
+
+    /// Get a value, `None` when missing, expired or of another type
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        Ok(self
+            .get_bytes(key)
+            .await?
+            .and_then(|raw| serde_json::from_slice(&raw).ok()))
+    }
+
+    /// Store a value for `ttl`
+    pub async fn set<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<()> {
+        self.set_bytes(key, serde_json::to_vec(value)?, Some(ttl))
+            .await
+    }
+
+    /// Store a value without expiry
+    pub async fn forever<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<()> {
+        self.set_bytes(key, serde_json::to_vec(value)?, None).await
+    }
+
+    /// Get a value, or compute and store it for `ttl`
+    ///
+    /// Errors from `compute` are returned and nothing is stored.
+    pub async fn remember<T, F, Fut>(&self, key: &str, ttl: Duration, compute: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        if let Some(value) = self.get(key).await? {
+            return Ok(value);
+        }
+        let value = compute().await?;
+        self.set(key, &value, ttl).await?;
+        Ok(value)
+    }
+
+    /// Remove a value, returning whether it existed
+    pub async fn forget(&self, key: &str) -> Result<bool> {
+        Ok(self.backend.delete(&[self.key(key)]).await? > 0)
+    }
+
+    /// Remove the values written under this view's tags, or every value
+    /// under the prefix when it has none
+    pub async fn flush(&self) -> Result<()> {
+        if self.tags.is_empty() {
+            return self.backend.delete_prefix(&self.prefix).await;
+        }
+        for tag in &self.tags {
+            let tag_key = self.tag_key(tag);
+            let mut keys = self.backend.set_members(&tag_key).await?;
+            keys.push(tag_key);
+            self.backend.delete(&keys).await?;
+        }
+        Ok(())
+    }
+
+    /// Add `by` to a counter that expires `ttl` after its first increment,
+    /// returning the new value and the time left
+    pub async fn increment(&self, key: &str, by: i64, ttl: Duration) -> Result<(i64, Duration)> {
+        self.backend.increment(&self.key(key), by, ttl).await
+    }
+
+    /// Get raw bytes
+    pub async fn get_bytes(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.backend.get(&self.key(key)).await
+    }
+
+    /// Store raw bytes, expiring after `ttl` when given
+    pub async fn set_bytes(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
+        let full = self.key(key);
+        self.backend.set(&full, value, ttl).await?;
+        for tag in &self.tags {
+            self.backend.add_to_set(&self.tag_key(tag), &full).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Lets the response cache middleware store responses in a [`Cache`]
+///
+/// Response keys already carry their own prefix, so they are stored as
+/// given rather than under the cache's prefix.
+#[async_trait]
+impl CacheStore for Cache {
+    async fn get(&self, key: &str) -> Option<CachedResponse> {
+        match self.backend.get(key).await {
+            Ok(raw) => raw.and_then(|raw| serde_json::from_slice(&raw).ok()),
+            Err(e) => {
+                tracing::warn!("Response cache read failed: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn set(&self, key: &str, value: CachedResponse, ttl: Duration) {
+        let Ok(raw) = serde_json::to_vec(&value) else {
+            return;
+        };
+        if let Err(e) = self.backend.set(key, raw, Some(ttl)).await {
+            tracing::warn!("Response cache write failed: {}", e);
+        }
+    }
+
+    async fn remove_prefix(&self, prefix: &str) {
+        if let Err(e) = self.backend.delete_prefix(prefix).await {
+            tracing::warn!("Response cache invalidation failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::cache::CacheConfig;
+    use crate::prelude::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_cache_facade() {
+        let cache = Cache::memory();
+        let calls = AtomicU32::new(0);
+        for _ in 0..2 {
+            let value: Vec<u32> = cache
+                .remember("numbers", Duration::from_secs(60), || async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Ok(vec![1, 2, 3])
+                })
+                .await
+                .unwrap();
+            assert_eq!(value, [1, 2, 3]);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(cache.forget("numbers").await.unwrap());
+        assert!(!cache.forget("numbers").await.unwrap());
+
+        cache
+            .set("short", "gone", Duration::from_millis(10))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(cache.get::<String>("short").await.unwrap(), None);
+
+        let users = cache.tags(&["users"]);
+        users.forever("user:1", "Ann").await.unwrap();
+        cache.forever("post:1", "Hello").await.unwrap();
+        users.flush().await.unwrap();
+        assert_eq!(cache.get::<String>("user:1").await.unwrap(), None);
+        assert_eq!(
+            cache.get::<String>("post:1").await.unwrap().unwrap(),
+            "Hello"
+        );
+
+        let tenant = cache.prefix("tenant:acme:");
+        tenant.forever("post:1", "Acme").await.unwrap();
+        assert_eq!(
+            tenant.get::<String>("post:1").await.unwrap().unwrap(),
+            "Acme"
+        );
+        tenant.flush().await.unwrap();
+        assert_eq!(tenant.get::<String>("post:1").await.unwrap(), None);
+        assert!(cache.get::<String>("post:1").await.unwrap().is_some());
+
+        let ttl = Duration::from_secs(60);
+        assert_eq!(cache.increment("hits", 1, ttl).await.unwrap().0, 1);
+        let (count, left) = cache.increment("hits", 2, ttl).await.unwrap();
+        assert_eq!(count, 3);
+        assert!(left <= ttl && left > Duration::from_secs(50));
+
+        let app = RustyX::new();
+        app.use_middleware(rate_limiter(
+            RateLimiterConfig::new(2, 60).cache(app.cache()),
+        ));
+        app.use_middleware(crate::middleware::cache::cache(
+            CacheConfig::new(60).store(app.cache()),
+        ));
+        app.get("/", |_req, res| async move { res.send("ok") });
+        app.test()
+            .get("/")
+            .send()
+            .await
+            .assert_header("x-cache", "MISS");
+        app.test()
+            .get("/")
+            .send()
+            .await
+            .assert_header("x-cache", "HIT");
+        app.test().get("/").send().await.assert_status(429);
+        assert_eq!(
+            app.cache()
+                .prefix("")
+                .increment("rustyx:ratelimit:127.0.0.1", 0, ttl)
+                .await
+                .unwrap()
+                .0,
+            3
+        );
+    }
+}
@@ -0,0 +1,695 @@
+//! Command-Line Interface
+//!
+//! The `rustyx` binary, built with the `cli` feature, scaffolds projects and
+//! files and runs migrations:
+//!
+//! ```text
+//! cargo install rustyx --features cli
+//!
+//! rustyx new blog --db postgres
+//! rustyx generate model Post title:string body:text published:bool
+//! rustyx generate controller Post
+//! rustyx generate routes posts
+//! rustyx generate migration add_slug_to_posts
+//! rustyx migrate
+//! rustyx rollback
+//! rustyx seed
+//! rustyx dev -- --port 4000
+//! ```
+//!
+//! Generators run in the project root. Model and migration generators
+//! write SQL for the driver in `DATABASE_URL` (read from `.env` or the
+//! environment), SQLite when unset. Field types are `string`, `text`,
+//! `integer`, `float`, `bool`, `datetime`, `uuid` and `json`; a trailing
+//! `?` makes a field optional, e.g. `published_at:datetime?`.
+//!
+//! Migrations live in `migrations/` as `<version>_<name>.up.sql` and
+//! `.down.sql` pairs (see [`Migration::load_dir`](crate::db::sql::Migration::load_dir)).
+//! `rustyx seed` runs the statements in `seeds/*.sql` in file name order.
+//!
+//! `rustyx dev` runs the app with `cargo run`, passing on arguments after
+//! `--`, and restarts it whenever `src/` or `Cargo.toml` change. Templates,
+//! assets and config reload in-process with [`RustyX::dev`](crate::RustyX::dev).
+
+use crate::db::{DatabaseConfig, DbDriver};
+use crate::error::{Error, Result};
+use crate::models::schema::Schema;
+use crate::utils::text::{to_pascal_case, to_snake_case};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+Usage: rustyx <command> [args]
+
+Commands:
+  new <name> [--db sqlite|mysql|postgres]   Create a project in ./<name>
+  generate model <Name> [field:type ...]    Model and its table migration
+  generate controller <Name>                Resource controller
+  generate routes <name>                    Router module
+  generate migration <name>                 Empty migration
+  migrate                                   Apply pending migrations
+  rollback                                  Roll back the latest migration
+  seed                                      Run seeds/*.sql
+  dev [-- args]                             Run the app, restarting on code changes
+
+`g` is short for `generate`.";
+
+/// Run the CLI with its arguments, not including the program name
+pub async fn run(args: impl IntoIterator<Item = String>) -> Result<()> {
+    let args: Vec<String> = args.into_iter().collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let root = Path::new(".");
+    let created = match args.as_slice() {
+        [] | ["help" | "-h" | "--help"] => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        ["version" | "-V" | "--version"] => {
+            println!("rustyx {}", crate::VERSION);
+            return Ok(());
+        }
+        ["new", name, rest @ ..] => {
+            let driver = match rest {
+                [] => DbDriver::SQLite,
+                ["--db", driver] => parse_driver(driver)?,
+                _ => return Err(usage()),
+            };
+            new_project(&root.join(name), name, driver)?
+        }
+        ["generate" | "g", kind, name, fields @ ..] => {
+            generate(root, kind, name, fields, database_driver())?
+        }
+        ["migrate"] => return migrate(root, false).await,
+        ["rollback"] => return migrate(root, true).await,
+        ["seed"] => return seed(root).await,
+        ["dev", rest @ ..] => {
+            let args = match rest {
+                [] => Vec::new(),
+                ["--", args @ ..] => args.iter().map(|arg| arg.to_string()).collect(),
+                _ => return Err(usage()),
+            };
+            let root = root.to_path_buf();
+            return tokio::task::spawn_blocking(move || dev(&root, &args))
+                .await
+                .map_err(|e| Error::Internal(e.to_string()))?;
+        }
+        _ => return Err(usage()),
+    };
+    for path in created {
+        println!("  create  {}", path.display());
+    }
+    Ok(())
+}
+
+fn usage() -> Error {
+    Error::Custom(format!("invalid arguments\n\n{}", USAGE))
+}
+
+fn parse_driver(name: &str) -> Result<DbDriver> {
+    match name {
+        "sqlite" => Ok(DbDriver::SQLite),
+        "mysql" => Ok(DbDriver::MySQL),
+        "postgres" | "postgresql" => Ok(DbDriver::PostgreSQL),
+        other => Err(Error::Custom(format!("unsupported database `{}`", other))),
+    }
+}
+
+#[derive(Deserialize)]
+struct DatabaseEnv {
+    database_url: String,
+}
+
+fn database_config() -> Result<DatabaseConfig> {
+    let env: DatabaseEnv = crate::utils::config::load()?;
+    DatabaseConfig::from_url(&env.database_url)
+}
+
+/// The driver from `DATABASE_URL`, SQLite when it's unset
+fn database_driver() -> DbDriver {
+    database_config()
+        .map(|config| config.driver)
+        .unwrap_or(DbDriver::SQLite)
+}
+
+/// Create a project skeleton in `dir`
+pub fn new_project(dir: &Path, name: &str, driver: DbDriver) -> Result<Vec<PathBuf>> {
+    if dir.exists() {
+        return Err(Error::Conflict(format!("{} already exists", dir.display())));
+    }
+    let (feature, url) = match driver {
+        DbDriver::MySQL => ("mysql", format!("mysql://root@localhost:3306/{}", name)),
+        DbDriver::PostgreSQL => (
+            "postgres",
+            format!("postgres://postgres@localhost:5432/{}", name),
+        ),
+        _ => ("sqlite", format!("sqlite://{}.db?mode=rwc", name)),
+    };
+    let cargo_toml = CARGO_TOML
+        .replace("{{name}}", name)
+        .replace("{{version}}", crate::VERSION)
+        .replace("{{feature}}", feature);
+    let files = [
+        ("Cargo.toml", cargo_toml),
+        (".env", format!("PORT=3000\nDATABASE_URL={}\n", url)),
+        (".gitignore", "/target\n.env\n*.db\n".to_string()),
+        ("src/main.rs", MAIN_RS.to_string()),
+        ("src/routes/mod.rs", ROUTES_MOD_RS.replace("{{name}}", name)),
+        ("src/controllers/mod.rs", "//! Controllers\n".to_string()),
+        ("src/models/mod.rs", "//! Models\n".to_string()),
+        ("migrations/.gitkeep", String::new()),
+        ("seeds/.gitkeep", String::new()),
+    ];
+    files
+        .into_iter()
+        .map(|(path, contents)| write_new(&dir.join(path), &contents))
+        .collect()
+}
+
+/// Generate a `model`, `controller`, `routes` or `migration` in the project
+/// at `root`
+pub fn generate(
+    root: &Path,
+    kind: &str,
+    name: &str,
+    fields: &[&str],
+    driver: DbDriver,
+) -> Result<Vec<PathBuf>> {
+    match kind {
+        "model" => generate_model(root, name, fields, driver),
+        "controller" => {
+            let name = to_pascal_case(name.strip_suffix("Controller").unwrap_or(name));
+            let module = to_snake_case(&name);
+            let source = CONTROLLER_RS
+                .replace("{{Name}}", &name)
+                .replace("{{table}}", &pluralize(&module));
+            Ok(vec![
+                write_new(
+                    &root.join(format!("src/controllers/{}.rs", module)),
+                    &source,
+                )?,
+                add_module(&root.join("src/controllers/mod.rs"), &module)?,
+            ])
+        }
+        "routes" => {
+            let module = to_snake_case(name);
+            let source = ROUTES_RS
+                .replace("{{path}}", &module.replace('_', "-"))
+                .replace("{{module}}", &module);
+            Ok(vec![
+                write_new(&root.join(format!("src/routes/{}.rs", module)), &source)?,
+                add_module(&root.join("src/routes/mod.rs"), &module)?,
+            ])
+        }
+        "migration" => Ok(write_migration(
+            root,
+            &to_snake_case(name),
+            "-- Write your migration here\n",
+            "-- Undo the migration here\n",
+        )?
+        .to_vec()),
+        other => Err(Error::Custom(format!(
+            "unknown generator `{}`, expected model, controller, routes or migration",
+            other
+        ))),
+    }
+}
+
+fn generate_model(
+    root: &Path,
+    name: &str,
+    fields: &[&str],
+    driver: DbDriver,
+) -> Result<Vec<PathBuf>> {
+    let name = to_pascal_case(name);
+    let module = to_snake_case(&name);
+    let table = pluralize(&module);
+
+    let mut columns = Vec::new();
+    for field in fields {
+        let (column, ty) = field
+            .split_once(':')
+            .ok_or_else(|| Error::Custom(format!("expected `name:type`, got `{}`", field)))?;
+        let (ty, optional) = match ty.strip_suffix('?') {
+            Some(ty) => (ty, true),
+            None => (ty, false),
+        };
+        let rust_type = match ty {
+            "string" | "text" => "String",
+            "int" | "integer" => "i64",
+            "float" => "f64",
+            "bool" | "boolean" => "bool",
+            "datetime" => "DateTime<Utc>",
+            "uuid" => "Uuid",
+            "json" => "Value",
+            other => return Err(Error::Custom(format!("unknown field type `{}`", other))),
+        };
+        columns.push((to_snake_case(column), ty, rust_type, optional));
+    }
+
+    let mut body = String::from("    pub id: Option<i64>,\n");
+    for (column, _, rust_type, optional) in &columns {
+        let rust_type = if *optional {
+            format!("Option<{}>", rust_type)
+        } else {
+            rust_type.to_string()
+        };
+        body.push_str(&format!("    pub {}: {},\n", column, rust_type));
+    }
+    body.push_str("    pub created_at: Option<DateTime<Utc>>,\n");
+    body.push_str("    pub updated_at: Option<DateTime<Utc>>,\n");
+    let source = MODEL_RS
+        .replace("{{Name}}", &name)
+        .replace("{{fields}}", &body)
+        .replace(
+            "{{imports}}",
+            if columns.iter().any(|(_, ty, _, _)| *ty == "uuid") {
+                "use uuid::Uuid;\n"
+            } else {
+                ""
+            },
+        );
+
+    let schema = Schema::create(&table, |t| {
+        t.increments("id");
+        for (column, ty, _, optional) in &columns {
+            let builder = match *ty {
+                "string" => t.string(column),
+                "text" => t.text(column),
+                "int" | "integer" => t.integer(column),
+                "float" => t.float(column),
+                "bool" | "boolean" => t.boolean(column),
+                "datetime" => t.datetime(column),
+                "uuid" => t.uuid(column),
+                _ => t.json(column),
+            };
+            if !optional {
+                builder.required();
+            }
+        }
+        t.timestamps();
+    });
+
+    let mut created = vec![
+        write_new(&root.join(format!("src/models/{}.rs", module)), &source)?,
+        add_module(&root.join("src/models/mod.rs"), &module)?,
+    ];
+    created.extend(write_migration(
+        root,
+        &format!("create_{}", table),
+        &format!("{}\n", schema.to_sql(driver.clone())),
+        &format!("{}\n", Schema::drop_sql(&table, driver)),
+    )?);
+    Ok(created)
+}
+
+/// Write `migrations/<version>_<name>.up.sql` and `.down.sql`
+fn write_migration(root: &Path, name: &str, up: &str, down: &str) -> Result<[PathBuf; 2]> {
+    let dir = root.join("migrations");
+    // Versions must be unique, so bump past migrations made this second
+    let mut version: u64 = chrono::Utc::now()
+        .format("%Y%m%d%H%M%S")
+        .to_string()
+        .parse()
+        .unwrap_or_default();
+    while std::fs::read_dir(&dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&format!("{}_", version))
+        })
+    }) {
+        version += 1;
+    }
+    let stem = format!("{}_{}", version, name);
+    Ok([
+        write_new(&dir.join(format!("{}.up.sql", stem)), up)?,
+        write_new(&dir.join(format!("{}.down.sql", stem)), down)?,
+    ])
+}
+
+fn write_new(path: &Path, contents: &str) -> Result<PathBuf> {
+    if path.exists() {
+        return Err(Error::Conflict(format!(
+            "{} already exists",
+            path.display()
+        )));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)?;
+    Ok(path.to_path_buf())
+}
+
+/// Declare `pub mod <module>;` in a `mod.rs`, creating it when missing
+fn add_module(mod_rs: &Path, module: &str) -> Result<PathBuf> {
+    let declaration = format!("pub mod {};", module);
+    let mut source = std::fs::read_to_string(mod_rs).unwrap_or_default();
+    if !source.lines().any(|line| line.trim() == declaration) {
+        if !source.is_empty() && !source.ends_with('\n') {
+            source.push('\n');
+        }
+        // After the last `mod` line, or else the module docs
+        let mut at = 0;
+        let mut offset = 0;
+        for line in source.split_inclusive('\n') {
+            offset += line.len();
+            let line = line.trim();
+            if line.starts_with("pub mod ") || line.starts_with("mod ") || line.starts_with("//!") {
+                at = offset;
+            }
+        }
+        source.insert_str(at, &format!("{}\n", declaration));
+        std::fs::write(mod_rs, source)?;
+    }
+    Ok(mod_rs.to_path_buf())
+}
+
+/// Same rules as the `Model` derive's table names
+fn pluralize(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix('y') {
+        if !stem.ends_with(['a', 'e', 'i', 'o', 'u']) {
+            return format!("{}ies", stem);
+        }
+    }
+    if word.ends_with(['s', 'x', 'z']) || word.ends_with("ch") || word.ends_with("sh") {
+        return format!("{}es", word);
+    }
+    format!("{}s", word)
+}
+
+/// Run `cargo run`, restarting it when the code changes
+fn dev(root: &Path, args: &[String]) -> Result<()> {
+    let src = root.join("src").canonicalize()?;
+    let manifest = root.join("Cargo.toml").canonicalize()?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    crate::dev::watch(
+        vec![src.clone(), manifest.clone()],
+        std::time::Duration::from_millis(300),
+        move |paths| {
+            // The manifest is watched through its directory, which also
+            // sees Cargo.lock and the like
+            let paths: Vec<PathBuf> = paths
+                .into_iter()
+                .filter(|path| path.starts_with(&src) || *path == manifest)
+                .collect();
+            if !paths.is_empty() {
+                let _ = tx.send(paths);
+            }
+        },
+    )?;
+    loop {
+        let mut child = std::process::Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .args(args)
+            .current_dir(root)
+            .spawn()?;
+        let Ok(paths) = rx.recv() else {
+            return Ok(());
+        };
+        for path in &paths {
+            println!("  changed  {}", path.display());
+        }
+        println!("Restarting...");
+        // The process may have exited already, e.g. after a build error
+        let _ = child.kill();
+        child.wait()?;
+    }
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+async fn executor() -> Result<crate::db::sql::SqlExecutor> {
+    let pool = crate::db::sql::connect(&database_config()?).await?;
+    Ok(crate::db::sql::SqlExecutor::new(pool))
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+async fn migrate(root: &Path, rollback: bool) -> Result<()> {
+    let migrations = crate::db::sql::Migration::load_dir(root.join("migrations"))?;
+    let sql = executor().await?;
+    if rollback {
+        match sql.rollback(&migrations).await? {
+            Some(version) => println!("  rolled back  {}", version),
+            None => println!("Nothing to roll back"),
+        }
+        return Ok(());
+    }
+    let applied = sql.migrate(&migrations).await?;
+    if applied.is_empty() {
+        println!("Nothing to migrate");
+    }
+    for version in applied {
+        println!("  migrated  {}", version);
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+async fn seed(root: &Path) -> Result<()> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(root.join("seeds"))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    files.retain(|path| path.extension().is_some_and(|ext| ext == "sql"));
+    files.sort();
+    let sql = executor().await?;
+    for path in files {
+        for statement in split_statements(&std::fs::read_to_string(&path)?) {
+            sql.execute(&statement).await?;
+        }
+        println!("  seeded  {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(feature = "mysql", feature = "postgres", feature = "sqlite")))]
+async fn migrate(_root: &Path, _rollback: bool) -> Result<()> {
+    Err(no_sql_driver())
+}
+
+#[cfg(not(any(feature = "mysql", feature = "postgres", feature = "sqlite")))]
+async fn seed(_root: &Path) -> Result<()> {
+    Err(no_sql_driver())
+}
+
+#[cfg(not(any(feature = "mysql", feature = "postgres", feature = "sqlite")))]
+fn no_sql_driver() -> Error {
+    Error::Custom("rustyx was built without a SQL driver feature".to_string())
+}
+
+/// Split a SQL script on `;` outside quotes and `--` comments
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+fn split_statements(script: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut chars = script.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '-') if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        current.push('\n');
+                        break;
+                    }
+                }
+                continue;
+            }
+            (None, '\'' | '"' | '`') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ';') => {
+                statements.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    statements.push(current);
+    statements
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+const CARGO_TOML: &str = r#"[package]
+name = "{{name}}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+rustyx = { version = "{{version}}", default-features = false, features = ["{{feature}}"] }
+tokio = { version = "1", features = ["full"] }
+serde = { version = "1", features = ["derive"] }
+chrono = { version = "0.4", features = ["serde"] }
+uuid = { version = "1", features = ["v4", "serde"] }
+tracing-subscriber = { version = "0.3", features = ["env-filter"] }
+"#;
+
+const MAIN_RS: &str = r#"use rustyx::prelude::*;
+use rustyx::utils::config::ConfigLoader;
+
+mod controllers;
+mod models;
+mod routes;
+
+#[derive(Deserialize)]
+struct Config {
+    port: u16,
+    database_url: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt().with_env_filter("info").init();
+    let config: Config = ConfigLoader::new().default("port", 3000).load()?;
+    init_db(DatabaseConfig::from_url(&config.database_url)?).await?;
+
+    let app = RustyX::new();
+    app.use_middleware(logger());
+    routes::register(&app);
+    app.listen(config.port).await
+}
+"#;
+
+const ROUTES_MOD_RS: &str = r#"//! Routes
+
+use rustyx::prelude::*;
+
+pub fn register(app: &RustyX) {
+    app.get("/", |_req, res| async move {
+        res.json(json!({ "message": "Welcome to {{name}}" }))
+    });
+}
+"#;
+
+const ROUTES_RS: &str = r#"use rustyx::prelude::*;
+
+/// Mount with `app.use_router("/{{path}}", routes::{{module}}::router())`
+pub fn router() -> Router {
+    let mut router = Router::new();
+    router.get("/", |_req, res| async move { res.json(json!([])) });
+    router
+}
+"#;
+
+const MODEL_RS: &str = r#"use chrono::{DateTime, Utc};
+use rustyx::prelude::*;
+{{imports}}
+#[derive(Debug, Clone, Serialize, Deserialize, Model)]
+pub struct {{Name}} {
+{{fields}}}
+"#;
+
+const CONTROLLER_RS: &str = r#"use rustyx::prelude::*;
+
+/// Register with `app.resource("/{{table}}", {{Name}}Controller)`
+pub struct {{Name}}Controller;
+
+#[async_trait]
+impl Controller for {{Name}}Controller {
+    async fn index(&self, _req: Request, res: Response) -> Response {
+        res.json(json!([]))
+    }
+
+    async fn show(&self, req: Request, res: Response) -> Response {
+        let id = req.param("id").cloned().unwrap_or_default();
+        res.json(json!({ "id": id }))
+    }
+
+    async fn create(&self, req: Request, res: Response) -> Response {
+        match req.json::<Value>() {
+            Ok(body) => res.status(201).json(body),
+            Err(e) => res.bad_request(&e.to_string()),
+        }
+    }
+
+    async fn update(&self, req: Request, res: Response) -> Response {
+        let id = req.param("id").cloned().unwrap_or_default();
+        match req.json::<Value>() {
+            Ok(body) => res.json(json!({ "id": id, "data": body })),
+            Err(e) => res.bad_request(&e.to_string()),
+        }
+    }
+
+    async fn destroy(&self, _req: Request, res: Response) -> Response {
+        res.status(204)
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scaffold_and_generate() {
+        let dir = std::env::temp_dir().join(format!("rustyx-cli-{}", uuid::Uuid::new_v4()));
+        let root = dir.join("blog");
+        let created = new_project(&root, "blog", DbDriver::SQLite).unwrap();
+        assert!(created.contains(&root.join("src/main.rs")));
+        let cargo = std::fs::read_to_string(root.join("Cargo.toml")).unwrap();
+        assert!(cargo.contains("name = \"blog\""));
+        assert!(cargo.contains("features = [\"sqlite\"]"));
+        assert!(new_project(&root, "blog", DbDriver::SQLite).is_err());
+
+        generate(
+            &root,
+            "model",
+            "BlogPost",
+            &["title:string", "published_at:datetime?"],
+            DbDriver::SQLite,
+        )
+        .unwrap();
+        let model = std::fs::read_to_string(root.join("src/models/blog_post.rs")).unwrap();
+        assert!(model.contains("pub struct BlogPost {"));
+        assert!(model.contains("    pub title: String,\n"));
+        assert!(model.contains("    pub published_at: Option<DateTime<Utc>>,\n"));
+        let mods = std::fs::read_to_string(root.join("src/models/mod.rs")).unwrap();
+        assert_eq!(mods, "//! Models\npub mod blog_post;\n");
+        generate(&root, "routes", "admin", &[], DbDriver::SQLite).unwrap();
+        let routes = std::fs::read_to_string(root.join("src/routes/mod.rs")).unwrap();
+        assert!(routes.starts_with("//! Routes\npub mod admin;\n\nuse rustyx::prelude::*;"));
+
+        generate(
+            &root,
+            "controller",
+            "BlogPostController",
+            &[],
+            DbDriver::SQLite,
+        )
+        .unwrap();
+        let controller =
+            std::fs::read_to_string(root.join("src/controllers/blog_post.rs")).unwrap();
+        assert!(controller.contains("impl Controller for BlogPostController"));
+        assert!(controller.contains("app.resource(\"/blog_posts\""));
+        assert!(generate(&root, "widget", "x", &[], DbDriver::SQLite).is_err());
+
+        #[cfg(feature = "sqlite")]
+        {
+            let migrations = crate::db::sql::Migration::load_dir(root.join("migrations")).unwrap();
+            assert_eq!(migrations.len(), 1);
+            assert_eq!(migrations[0].name, "create_blog_posts");
+
+            let pool = crate::db::sql::connect(&DatabaseConfig::sqlite_memory())
+                .await
+                .unwrap();
+            let sql = crate::db::sql::SqlExecutor::new(pool);
+            sql.migrate(&migrations).await.unwrap();
+            for statement in split_statements(
+                "-- posts\nINSERT INTO blog_posts (title) VALUES ('a;b');\n\
+                 INSERT INTO blog_posts (title) VALUES ('c');",
+            ) {
+                sql.execute(&statement).await.unwrap();
+            }
+            let rows: Vec<serde_json::Value> =
+                sql.query("SELECT title FROM blog_posts").await.unwrap();
+            assert_eq!(rows.len(), 2);
+            assert_eq!(rows[0]["title"], "a;b");
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
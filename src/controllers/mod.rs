@@ -4,9 +4,13 @@
 
 #![allow(unused_variables)]
 
+use crate::app::HandlerFn;
 use crate::request::Request;
 use crate::response::Response;
+use crate::router::Router;
 use async_trait::async_trait;
+use hyper::Method;
+use std::sync::Arc;
 
 /// Base Controller trait
 #[async_trait]
@@ -82,10 +86,48 @@ macro_rules! crud_controller {
     };
 }
 
+/// A [`Controller`] action wired up by [`ResourceController`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceAction {
+    /// `GET /path`
+    Index,
+    /// `GET /path/:id`
+    Show,
+    /// `POST /path`
+    Create,
+    /// `PUT /path/:id` and `PATCH /path/:id`
+    Update,
+    /// `DELETE /path/:id`
+    Destroy,
+}
+
+impl ResourceAction {
+    pub const ALL: [ResourceAction; 5] = [
+        ResourceAction::Index,
+        ResourceAction::Show,
+        ResourceAction::Create,
+        ResourceAction::Update,
+        ResourceAction::Destroy,
+    ];
+}
+
 /// Resource controller that auto-registers CRUD routes
+///
+/// # Example
+///
+/// ```rust,ignore
+/// app.resource("/users", UserController);
+///
+/// // Read-only resource
+/// app.use_resource(
+///     ResourceController::new("/posts", PostController)
+///         .only(&[ResourceAction::Index, ResourceAction::Show]),
+/// );
+/// ```
 pub struct ResourceController<C: Controller> {
     pub controller: C,
     pub path: String,
+    pub actions: Vec<ResourceAction>,
 }
 
 impl<C: Controller + 'static> ResourceController<C> {
@@ -93,6 +135,84 @@ impl<C: Controller + 'static> ResourceController<C> {
         Self {
             controller,
             path: path.to_string(),
+            actions: ResourceAction::ALL.to_vec(),
         }
     }
+
+    /// Register only these actions
+    pub fn only(mut self, actions: &[ResourceAction]) -> Self {
+        self.actions.retain(|action| actions.contains(action));
+        self
+    }
+
+    /// Register every action except these
+    pub fn except(mut self, actions: &[ResourceAction]) -> Self {
+        self.actions.retain(|action| !actions.contains(action));
+        self
+    }
+
+    /// Add the resource's routes to a router
+    pub fn register(self, router: &mut Router) {
+        let controller = Arc::new(self.controller);
+        let base = self.path.trim_end_matches('/');
+        let collection = if base.is_empty() { "/" } else { base };
+        let member = format!("{}/:id", base);
+
+        for action in self.actions {
+            let routes: &[(Method, &str)] = match action {
+                ResourceAction::Index => &[(Method::GET, collection)],
+                ResourceAction::Show => &[(Method::GET, &member)],
+                ResourceAction::Create => &[(Method::POST, collection)],
+                ResourceAction::Update => &[(Method::PUT, &member), (Method::PATCH, &member)],
+                ResourceAction::Destroy => &[(Method::DELETE, &member)],
+            };
+            for (method, path) in routes {
+                router.add_route(method.clone(), path, action_handler(&controller, action));
+            }
+        }
+    }
+}
+
+pub(crate) fn action_handler<C: Controller + 'static>(
+    controller: &Arc<C>,
+    action: ResourceAction,
+) -> HandlerFn {
+    let controller = Arc::clone(controller);
+    Arc::new(move |req, res| {
+        let controller = Arc::clone(&controller);
+        Box::pin(async move {
+            match action {
+                ResourceAction::Index => controller.index(req, res).await,
+                ResourceAction::Show => controller.show(req, res).await,
+                ResourceAction::Create => controller.create(req, res).await,
+                ResourceAction::Update => controller.update(req, res).await,
+                ResourceAction::Destroy => controller.destroy(req, res).await,
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Users;
+
+    #[async_trait]
+    impl Controller for Users {}
+
+    #[test]
+    fn test_resource_routes() {
+        let mut router = Router::new();
+        ResourceController::new("/users", Users)
+            .except(&[ResourceAction::Destroy])
+            .register(&mut router);
+
+        assert!(router.find_route(&Method::GET, "/users").is_some());
+        assert!(router.find_route(&Method::POST, "/users").is_some());
+        assert!(router.find_route(&Method::PATCH, "/users/7").is_some());
+        let (_, params) = router.find_route(&Method::GET, "/users/7").unwrap();
+        assert_eq!(params.get("id").map(String::as_str), Some("7"));
+        assert!(router.find_route(&Method::DELETE, "/users/7").is_none());
+    }
 }
@@ -2,21 +2,33 @@
 #![allow(dead_code)]
 
 use crate::db::{Database, DatabaseConfig, DbDriver};
-use crate::error::Result;
+use crate::error::{Error, Result};
 use async_trait::async_trait;
-use once_cell::sync::OnceCell;
+use once_cell::sync::{Lazy, OnceCell};
 use parking_lot::RwLock;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 static DB_INSTANCE: OnceCell<Arc<RwLock<Option<DatabaseConnection>>>> = OnceCell::new();
 
+static CONNECTIONS: Lazy<RwLock<HashMap<String, DatabaseConnection>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Name under which [`init_db`] registers its connection
+pub const DEFAULT_CONNECTION: &str = "default";
+
 /// Database connection wrapper
+///
+/// Cloning is cheap and clones share the underlying pools and clients.
+#[derive(Clone)]
 pub struct DatabaseConnection {
     config: DatabaseConfig,
     #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
     sql_pool: Option<sqlx::AnyPool>,
     #[cfg(feature = "mongodb")]
     mongo_client: Option<mongodb::Client>,
+    #[cfg(feature = "redis")]
+    redis: Option<crate::db::redis::RedisClient>,
 }
 
 impl DatabaseConnection {
@@ -28,29 +40,58 @@ impl DatabaseConnection {
             sql_pool: None,
             #[cfg(feature = "mongodb")]
             mongo_client: None,
+            #[cfg(feature = "redis")]
+            redis: None,
         };
         conn.establish_connection().await?;
         Ok(conn)
     }
 
+    /// Wrap an already open SQL pool
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub(crate) fn from_sql_pool(config: DatabaseConfig, pool: sqlx::AnyPool) -> Self {
+        Self {
+            config,
+            sql_pool: Some(pool),
+            #[cfg(feature = "mongodb")]
+            mongo_client: None,
+            #[cfg(feature = "redis")]
+            redis: None,
+        }
+    }
+
     async fn establish_connection(&mut self) -> Result<()> {
         match self.config.driver {
             #[cfg(feature = "sqlite")]
             DbDriver::SQLite => {
-                // SQLite connection handled by sqlx
+                self.sql_pool = Some(crate::db::sql::connect(&self.config).await?);
             }
             #[cfg(feature = "mysql")]
             DbDriver::MySQL => {
-                // MySQL connection handled by sqlx
+                self.sql_pool = Some(crate::db::sql::connect(&self.config).await?);
             }
             #[cfg(feature = "postgres")]
             DbDriver::PostgreSQL => {
-                // PostgreSQL connection handled by sqlx
+                self.sql_pool = Some(crate::db::sql::connect(&self.config).await?);
             }
             #[cfg(feature = "mongodb")]
             DbDriver::MongoDB => {
-                // MongoDB connection
+                let client = mongodb::Client::with_uri_str(self.config.connection_string())
+                    .await
+                    .map_err(Error::from)?;
+                self.mongo_client = Some(client);
+            }
+            #[cfg(feature = "redis")]
+            DbDriver::Redis => {
+                self.redis = Some(crate::db::redis::RedisClient::from_config(&self.config).await?);
             }
+            #[cfg(not(all(
+                feature = "sqlite",
+                feature = "mysql",
+                feature = "postgres",
+                feature = "mongodb",
+                feature = "redis"
+            )))]
             _ => {}
         }
         Ok(())
@@ -60,6 +101,68 @@ impl DatabaseConnection {
     pub fn config(&self) -> &DatabaseConfig {
         &self.config
     }
+
+    /// The SQL connection pool, for MySQL, PostgreSQL and SQLite connections
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub fn sql_pool(&self) -> Option<&sqlx::AnyPool> {
+        self.sql_pool.as_ref()
+    }
+
+    /// The MongoDB client, for MongoDB connections
+    #[cfg(feature = "mongodb")]
+    pub fn mongo(&self) -> Option<&mongodb::Client> {
+        self.mongo_client.as_ref()
+    }
+
+    /// The configured MongoDB database, for MongoDB connections
+    #[cfg(feature = "mongodb")]
+    pub fn mongo_database(&self) -> Option<mongodb::Database> {
+        self.mongo_client
+            .as_ref()
+            .map(|client| client.database(&self.config.database))
+    }
+
+    /// The Redis client, for Redis connections
+    #[cfg(feature = "redis")]
+    pub fn redis(&self) -> Option<&crate::db::redis::RedisClient> {
+        self.redis.as_ref()
+    }
+
+    /// Query executor for this connection's SQL pool
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub fn sql(&self) -> Result<crate::db::sql::SqlExecutor> {
+        self.sql_pool
+            .clone()
+            .map(crate::db::sql::SqlExecutor::new)
+            .ok_or_else(|| Error::Database("No SQL connection".to_string()))
+    }
+
+    /// Round-trip to the database: `SELECT 1` for SQL, `ping` for MongoDB
+    /// and `PING` for Redis
+    pub async fn ping(&self) -> Result<()> {
+        #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+        if let Some(pool) = &self.sql_pool {
+            sqlx::query("SELECT 1")
+                .execute(pool)
+                .await
+                .map_err(Error::from)?;
+            return Ok(());
+        }
+        #[cfg(feature = "mongodb")]
+        if let Some(client) = &self.mongo_client {
+            client
+                .database("admin")
+                .run_command(mongodb::bson::doc! { "ping": 1 }, None)
+                .await
+                .map_err(Error::from)?;
+            return Ok(());
+        }
+        #[cfg(feature = "redis")]
+        if let Some(redis) = &self.redis {
+            return redis.ping().await;
+        }
+        Err(Error::Database("No open connection".to_string()))
+    }
 }
 
 #[async_trait]
@@ -69,21 +172,102 @@ impl Database for DatabaseConnection {
     }
 
     async fn disconnect(&self) -> Result<()> {
+        #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+        if let Some(pool) = &self.sql_pool {
+            pool.close().await;
+        }
         Ok(())
     }
     async fn is_connected(&self) -> bool {
+        #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+        if let Some(pool) = &self.sql_pool {
+            return !pool.is_closed();
+        }
         true
     }
 }
 
 /// Initialize the global database connection
+///
+/// The connection is also registered as [`DEFAULT_CONNECTION`], so
+/// `db("default")` returns it.
 pub async fn init_db(config: DatabaseConfig) -> Result<()> {
     let conn = DatabaseConnection::new(config).await?;
+    CONNECTIONS
+        .write()
+        .insert(DEFAULT_CONNECTION.to_string(), conn.clone());
     DB_INSTANCE.get_or_init(|| Arc::new(RwLock::new(Some(conn))));
     Ok(())
 }
 
+/// Open a connection and register it under `name`, replacing any previous one
+///
+/// ```rust,ignore
+/// add_connection("primary", DatabaseConfig::from_env()?).await?;
+/// add_connection("analytics", DatabaseConfig::from_url("mongodb://metrics:27017/events")?).await?;
+///
+/// let users = db("primary")?.sql()?.query("SELECT * FROM users").await?;
+/// let events = db("analytics")?.mongo_database();
+/// ```
+pub async fn add_connection(name: &str, config: DatabaseConfig) -> Result<()> {
+    let conn = DatabaseConnection::new(config).await?;
+    register_connection(name, conn);
+    Ok(())
+}
+
+pub(crate) fn register_connection(name: &str, conn: DatabaseConnection) {
+    CONNECTIONS.write().insert(name.to_string(), conn);
+}
+
+/// Get a connection registered with [`add_connection`] or [`init_db`]
+pub fn db(name: &str) -> Result<DatabaseConnection> {
+    CONNECTIONS
+        .read()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| Error::Database(format!("No database connection named `{}`", name)))
+}
+
+/// Unregister a connection, returning it so it can be disconnected
+pub fn remove_connection(name: &str) -> Option<DatabaseConnection> {
+    CONNECTIONS.write().remove(name)
+}
+
+/// Names of the registered connections
+pub fn connection_names() -> Vec<String> {
+    CONNECTIONS.read().keys().cloned().collect()
+}
+
 /// Get the global database connection
 pub fn get_db() -> Option<Arc<RwLock<Option<DatabaseConnection>>>> {
     DB_INSTANCE.get().cloned()
 }
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_named_connections() {
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        add_connection("reports", config).await.unwrap();
+
+        let sql = db("reports").unwrap().sql().unwrap();
+        sql.execute("CREATE TABLE visits (id INTEGER)")
+            .await
+            .unwrap();
+        sql.execute("INSERT INTO visits VALUES (1)").await.unwrap();
+        let rows: Vec<serde_json::Value> = db("reports")
+            .unwrap()
+            .sql()
+            .unwrap()
+            .query("SELECT * FROM visits")
+            .await
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+
+        assert!(connection_names().contains(&"reports".to_string()));
+        assert!(remove_connection("reports").is_some());
+        assert!(db("reports").is_err());
+    }
+}
@@ -1,7 +1,8 @@
 //! MongoDB Module
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use async_trait::async_trait;
+use futures::{Stream, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 
 /// MongoDB Repository trait
@@ -48,6 +49,26 @@ where
     async fn count(&self, filter: Option<serde_json::Value>) -> Result<u64>;
 }
 
+/// `update` command inserting `document`, or setting its fields on the
+/// document matching `filter`
+///
+/// Run it with `Database::run_command`; the MongoDB counterpart of
+/// [`QueryBuilder::insert_or_update`](super::query::QueryBuilder::insert_or_update).
+pub fn upsert_command(
+    collection: &str,
+    filter: serde_json::Value,
+    document: serde_json::Value,
+) -> serde_json::Value {
+    serde_json::json!({
+        "update": collection,
+        "updates": [{
+            "q": filter,
+            "u": { "$set": document },
+            "upsert": true,
+        }],
+    })
+}
+
 /// MongoDB aggregation pipeline builder
 pub struct AggregationBuilder {
     stages: Vec<serde_json::Value>,
@@ -83,9 +104,100 @@ impl AggregationBuilder {
         self
     }
 
+    /// Join documents from another collection into the `as_field` array
+    pub fn lookup(
+        mut self,
+        from: &str,
+        local_field: &str,
+        foreign_field: &str,
+        as_field: &str,
+    ) -> Self {
+        self.stages.push(serde_json::json!({
+            "$lookup": {
+                "from": from,
+                "localField": local_field,
+                "foreignField": foreign_field,
+                "as": as_field,
+            }
+        }));
+        self
+    }
+
+    /// Embed a model relation with `$lookup`
+    pub fn lookup_relation(mut self, relation: &crate::models::Relation) -> Self {
+        self.stages.extend(relation.lookup_stages());
+        self
+    }
+
+    pub fn project(mut self, projection: serde_json::Value) -> Self {
+        self.stages
+            .push(serde_json::json!({ "$project": projection }));
+        self
+    }
+
+    /// Output one document per element of an array field (`"tags"` or `"$tags"`)
+    pub fn unwind(mut self, path: &str) -> Self {
+        let path = if path.starts_with('$') {
+            path.to_string()
+        } else {
+            format!("${}", path)
+        };
+        self.stages.push(serde_json::json!({ "$unwind": path }));
+        self
+    }
+
+    /// Run several sub-pipelines over the same input, one output field each
+    pub fn facet(mut self, facets: Vec<(&str, AggregationBuilder)>) -> Self {
+        let facets: serde_json::Map<String, serde_json::Value> = facets
+            .into_iter()
+            .map(|(name, pipeline)| (name.to_string(), pipeline.build().into()))
+            .collect();
+        self.stages.push(serde_json::json!({ "$facet": facets }));
+        self
+    }
+
+    /// Append a raw stage, for operators without a helper here
+    pub fn stage(mut self, stage: serde_json::Value) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
     pub fn build(self) -> Vec<serde_json::Value> {
         self.stages
     }
+
+    /// The stages as BSON documents
+    fn pipeline(&self) -> Result<Vec<mongodb::bson::Document>> {
+        self.stages
+            .iter()
+            .map(|stage| mongodb::bson::to_document(stage).map_err(mongo_error))
+            .collect()
+    }
+
+    /// Execute the pipeline on a collection, streaming results as `T`
+    ///
+    /// ```rust,ignore
+    /// let mut totals = AggregationBuilder::new()
+    ///     .match_stage(json!({ "status": "paid" }))
+    ///     .group(json!({ "_id": "$customer", "total": { "$sum": "$amount" } }))
+    ///     .run::<Total, _>(&db.collection::<Document>("orders"))
+    ///     .await?;
+    /// while let Some(total) = totals.try_next().await? { /* ... */ }
+    /// ```
+    pub async fn run<T, C>(
+        &self,
+        collection: &mongodb::Collection<C>,
+    ) -> Result<impl Stream<Item = Result<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        let cursor = collection.aggregate(self.pipeline()?, None).await?;
+        Ok(cursor.map(|doc| mongodb::bson::from_document(doc?).map_err(mongo_error)))
+    }
+}
+
+fn mongo_error(e: impl std::fmt::Display) -> Error {
+    Error::Database(e.to_string())
 }
 
 impl Default for AggregationBuilder {
@@ -93,3 +205,58 @@ impl Default for AggregationBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+    use serde_json::json;
+
+    #[test]
+    fn test_aggregation_pipeline() {
+        let pipeline = AggregationBuilder::new()
+            .match_stage(json!({ "status": "paid" }))
+            .lookup("customers", "customer_id", "_id", "customer")
+            .unwind("customer")
+            .project(json!({ "amount": 1, "customer.name": 1 }))
+            .facet(vec![
+                (
+                    "top",
+                    AggregationBuilder::new()
+                        .sort(json!({ "amount": -1 }))
+                        .limit(3),
+                ),
+                (
+                    "count",
+                    AggregationBuilder::new().stage(json!({ "$count": "n" })),
+                ),
+            ])
+            .pipeline()
+            .unwrap();
+
+        assert_eq!(
+            pipeline,
+            [
+                doc! { "$match": { "status": "paid" } },
+                doc! { "$lookup": {
+                    "from": "customers",
+                    "localField": "customer_id",
+                    "foreignField": "_id",
+                    "as": "customer",
+                } },
+                doc! { "$unwind": "$customer" },
+                doc! { "$project": { "amount": 1_i64, "customer.name": 1_i64 } },
+                doc! { "$facet": {
+                    "top": [{ "$sort": { "amount": -1_i64 } }, { "$limit": 3_i64 }],
+                    "count": [{ "$count": "n" }],
+                } },
+            ]
+        );
+
+        let err = AggregationBuilder::new()
+            .stage(json!(5))
+            .pipeline()
+            .unwrap_err();
+        assert!(matches!(err, Error::Database(_)));
+    }
+}
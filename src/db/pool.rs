@@ -1,6 +1,5 @@
 //! Database Pool Module
 #![allow(dead_code)]
-#![allow(clippy::mismatched_lifetime_syntaxes)]
 
 use crate::db::DatabaseConfig;
 use crate::error::Result;
@@ -1,23 +1,148 @@
 //! Query Builder Module
+//!
+//! Values never end up in the SQL text: `build()` returns the statement
+//! with placeholders for the target driver, plus the values to bind.
 #![allow(dead_code)]
 
+use crate::db::DbDriver;
+
 /// Query builder for constructing database queries
 #[derive(Debug, Clone)]
 pub struct QueryBuilder {
     table: String,
     select_fields: Vec<String>,
-    where_clauses: Vec<WhereClause>,
+    where_clauses: Vec<(Connector, Condition)>,
     order_by: Vec<(String, Order)>,
     limit: Option<u32>,
     offset: Option<u32>,
     joins: Vec<Join>,
+    driver: DbDriver,
 }
 
 #[derive(Debug, Clone)]
 pub struct WhereClause {
     pub field: String,
     pub operator: Operator,
-    pub value: String,
+    /// Operands: none for `IsNull`/`IsNotNull`, two for `Between`, any
+    /// number for `In`/`NotIn`, one otherwise
+    pub values: Vec<BindValue>,
+}
+
+/// A where condition: a single clause or a parenthesized group
+#[derive(Debug, Clone)]
+pub enum Condition {
+    Clause(WhereClause),
+    Group(Vec<(Connector, Condition)>),
+}
+
+/// How a condition joins the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    And,
+    Or,
+}
+
+/// A value bound to a query placeholder
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<&str> for BindValue {
+    fn from(value: &str) -> Self {
+        BindValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for BindValue {
+    fn from(value: String) -> Self {
+        BindValue::Text(value)
+    }
+}
+
+impl From<&String> for BindValue {
+    fn from(value: &String) -> Self {
+        BindValue::Text(value.clone())
+    }
+}
+
+impl From<bool> for BindValue {
+    fn from(value: bool) -> Self {
+        BindValue::Bool(value)
+    }
+}
+
+macro_rules! bind_int {
+    ($($t:ty),*) => {
+        $(impl From<$t> for BindValue {
+            fn from(value: $t) -> Self {
+                BindValue::Int(value as i64)
+            }
+        })*
+    };
+}
+
+bind_int!(i8, i16, i32, i64, u8, u16, u32);
+
+impl From<f32> for BindValue {
+    fn from(value: f32) -> Self {
+        BindValue::Float(value as f64)
+    }
+}
+
+impl From<f64> for BindValue {
+    fn from(value: f64) -> Self {
+        BindValue::Float(value)
+    }
+}
+
+impl From<Vec<u8>> for BindValue {
+    fn from(value: Vec<u8>) -> Self {
+        BindValue::Bytes(value)
+    }
+}
+
+impl<T: Into<BindValue>> From<Option<T>> for BindValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(BindValue::Null, Into::into)
+    }
+}
+
+impl From<&serde_json::Value> for BindValue {
+    /// Scalars map to their SQL counterparts, arrays and objects to JSON text
+    fn from(value: &serde_json::Value) -> Self {
+        use serde_json::Value;
+        match value {
+            Value::Null => BindValue::Null,
+            Value::Bool(v) => BindValue::Bool(*v),
+            Value::Number(n) => n
+                .as_i64()
+                .map(BindValue::Int)
+                .unwrap_or_else(|| BindValue::Float(n.as_f64().unwrap_or_default())),
+            Value::String(v) => BindValue::Text(v.clone()),
+            other => BindValue::Text(other.to_string()),
+        }
+    }
+}
+
+/// Build a `Vec<BindValue>` of query parameters
+///
+/// ```rust
+/// use rustyx::db::query::BindValue;
+///
+/// let params = rustyx::params![1, "ann", None::<f64>];
+/// assert_eq!(params, [BindValue::Int(1), BindValue::Text("ann".into()), BindValue::Null]);
+/// ```
+#[macro_export]
+macro_rules! params {
+    ($($value:expr),* $(,)?) => {
+        vec![$($crate::db::query::BindValue::from($value)),*]
+    };
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +158,7 @@ pub enum Operator {
     NotIn,
     IsNull,
     IsNotNull,
+    Between,
 }
 
 #[derive(Debug, Clone)]
@@ -67,21 +193,213 @@ impl QueryBuilder {
             limit: None,
             offset: None,
             joins: Vec::new(),
+            driver: DbDriver::SQLite,
         }
     }
 
+    /// Set the driver the query is built for (placeholder style)
+    ///
+    /// PostgreSQL uses `$1, $2, ...`; the others use `?`. Defaults to SQLite.
+    pub fn driver(mut self, driver: DbDriver) -> Self {
+        self.driver = driver;
+        self
+    }
+
     /// Select specific fields
     pub fn select(mut self, fields: &[&str]) -> Self {
         self.select_fields = fields.iter().map(|s| s.to_string()).collect();
         self
     }
 
+    /// Add a condition, ANDed with the previous ones
+    pub fn where_op(self, field: &str, operator: Operator, value: impl Into<BindValue>) -> Self {
+        self.clause(Connector::And, field, operator, vec![value.into()])
+    }
+
+    /// Add a condition, ORed with the previous ones
+    ///
+    /// `a AND b OR c` follows SQL precedence; use
+    /// [`where_group`](Self::where_group) to group conditions explicitly.
+    pub fn or_where(self, field: &str, operator: Operator, value: impl Into<BindValue>) -> Self {
+        self.clause(Connector::Or, field, operator, vec![value.into()])
+    }
+
     /// Add a where clause
-    pub fn where_eq(mut self, field: &str, value: impl ToString) -> Self {
-        self.where_clauses.push(WhereClause {
-            field: field.to_string(),
-            operator: Operator::Eq,
-            value: value.to_string(),
+    pub fn where_eq(self, field: &str, value: impl Into<BindValue>) -> Self {
+        self.where_op(field, Operator::Eq, value)
+    }
+
+    /// `field <> value`
+    pub fn where_ne(self, field: &str, value: impl Into<BindValue>) -> Self {
+        self.where_op(field, Operator::Ne, value)
+    }
+
+    /// `field > value`
+    pub fn where_gt(self, field: &str, value: impl Into<BindValue>) -> Self {
+        self.where_op(field, Operator::Gt, value)
+    }
+
+    /// `field >= value`
+    pub fn where_gte(self, field: &str, value: impl Into<BindValue>) -> Self {
+        self.where_op(field, Operator::Gte, value)
+    }
+
+    /// `field < value`
+    pub fn where_lt(self, field: &str, value: impl Into<BindValue>) -> Self {
+        self.where_op(field, Operator::Lt, value)
+    }
+
+    /// `field <= value`
+    pub fn where_lte(self, field: &str, value: impl Into<BindValue>) -> Self {
+        self.where_op(field, Operator::Lte, value)
+    }
+
+    /// `field LIKE pattern`
+    pub fn where_like(self, field: &str, pattern: impl Into<BindValue>) -> Self {
+        self.where_op(field, Operator::Like, pattern)
+    }
+
+    /// `field IN (...)`; an empty list matches nothing
+    pub fn where_in<V: Into<BindValue>>(
+        self,
+        field: &str,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        self.clause(Connector::And, field, Operator::In, values)
+    }
+
+    /// `field NOT IN (...)`; an empty list matches everything
+    pub fn where_not_in<V: Into<BindValue>>(
+        self,
+        field: &str,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        self.clause(Connector::And, field, Operator::NotIn, values)
+    }
+
+    /// `field IS NULL`
+    pub fn where_is_null(self, field: &str) -> Self {
+        self.clause(Connector::And, field, Operator::IsNull, Vec::new())
+    }
+
+    /// `field IS NOT NULL`
+    pub fn where_is_not_null(self, field: &str) -> Self {
+        self.clause(Connector::And, field, Operator::IsNotNull, Vec::new())
+    }
+
+    /// `field BETWEEN low AND high`
+    pub fn where_between(
+        self,
+        field: &str,
+        low: impl Into<BindValue>,
+        high: impl Into<BindValue>,
+    ) -> Self {
+        let values = vec![low.into(), high.into()];
+        self.clause(Connector::And, field, Operator::Between, values)
+    }
+
+    /// Add a parenthesized group of conditions, ANDed with the previous ones
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rustyx::db::prelude::*;
+    ///
+    /// // ... WHERE active = ? AND (role = ? OR karma > ?)
+    /// let (sql, binds) = QueryBuilder::table("users")
+    ///     .where_eq("active", true)
+    ///     .where_group(|q| q.where_eq("role", "admin").or_where("karma", Operator::Gt, 100))
+    ///     .build();
+    /// assert!(sql.ends_with("WHERE active = ? AND (role = ? OR karma > ?)"));
+    /// assert_eq!(binds.len(), 3);
+    /// ```
+    pub fn where_group(self, group: impl FnOnce(QueryBuilder) -> QueryBuilder) -> Self {
+        self.group(Connector::And, group)
+    }
+
+    /// Add a parenthesized group of conditions, ORed with the previous ones
+    pub fn or_where_group(self, group: impl FnOnce(QueryBuilder) -> QueryBuilder) -> Self {
+        self.group(Connector::Or, group)
+    }
+
+    /// AND conditions with everything added so far, grouping the existing
+    /// conditions when they contain an OR
+    ///
+    /// Used for scopes that must hold whatever the caller's filter, such as
+    /// excluding soft-deleted rows.
+    pub fn scope(mut self, scope: impl FnOnce(QueryBuilder) -> QueryBuilder) -> Self {
+        let conditions = scope(QueryBuilder::table(&self.table)).where_clauses;
+        if self
+            .where_clauses
+            .iter()
+            .any(|(connector, _)| *connector == Connector::Or)
+        {
+            let existing = std::mem::take(&mut self.where_clauses);
+            self.where_clauses
+                .push((Connector::And, Condition::Group(existing)));
+        }
+        self.where_clauses.extend(conditions);
+        self
+    }
+
+    fn clause(
+        mut self,
+        connector: Connector,
+        field: &str,
+        operator: Operator,
+        values: Vec<BindValue>,
+    ) -> Self {
+        self.where_clauses.push((
+            connector,
+            Condition::Clause(WhereClause {
+                field: field.to_string(),
+                operator,
+                values,
+            }),
+        ));
+        self
+    }
+
+    fn group(
+        mut self,
+        connector: Connector,
+        group: impl FnOnce(QueryBuilder) -> QueryBuilder,
+    ) -> Self {
+        let conditions = group(QueryBuilder::table(&self.table)).where_clauses;
+        if !conditions.is_empty() {
+            self.where_clauses
+                .push((connector, Condition::Group(conditions)));
+        }
+        self
+    }
+
+    /// `INNER JOIN table ON on`
+    pub fn join(self, table: &str, on: &str) -> Self {
+        self.add_join(JoinType::Inner, table, on)
+    }
+
+    /// `LEFT JOIN table ON on`
+    pub fn left_join(self, table: &str, on: &str) -> Self {
+        self.add_join(JoinType::Left, table, on)
+    }
+
+    /// `RIGHT JOIN table ON on`
+    pub fn right_join(self, table: &str, on: &str) -> Self {
+        self.add_join(JoinType::Right, table, on)
+    }
+
+    /// `FULL OUTER JOIN table ON on`
+    pub fn full_join(self, table: &str, on: &str) -> Self {
+        self.add_join(JoinType::Full, table, on)
+    }
+
+    fn add_join(mut self, join_type: JoinType, table: &str, on: &str) -> Self {
+        self.joins.push(Join {
+            table: table.to_string(),
+            on: on.to_string(),
+            join_type,
         });
         self
     }
@@ -104,23 +422,27 @@ impl QueryBuilder {
         self
     }
 
-    /// Build the SQL query
-    pub fn build(&self) -> String {
+    /// Build the SQL query and the values to bind, in placeholder order
+    pub fn build(&self) -> (String, Vec<BindValue>) {
+        let mut binds = Vec::new();
         let mut sql = format!(
             "SELECT {} FROM {}",
             self.select_fields.join(", "),
             self.table
         );
 
-        if !self.where_clauses.is_empty() {
-            let conditions: Vec<String> = self
-                .where_clauses
-                .iter()
-                .map(|w| format!("{} = '{}'", w.field, w.value))
-                .collect();
-            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        for join in &self.joins {
+            let kind = match join.join_type {
+                JoinType::Inner => "INNER JOIN",
+                JoinType::Left => "LEFT JOIN",
+                JoinType::Right => "RIGHT JOIN",
+                JoinType::Full => "FULL OUTER JOIN",
+            };
+            sql.push_str(&format!(" {} {} ON {}", kind, join.table, join.on));
         }
 
+        self.push_where(&mut sql, &mut binds);
+
         if !self.order_by.is_empty() {
             let orders: Vec<String> = self
                 .order_by
@@ -146,6 +468,397 @@ impl QueryBuilder {
             sql.push_str(&format!(" OFFSET {}", offset));
         }
 
+        (sql, binds)
+    }
+
+    /// Build a `SELECT COUNT(*) AS count` over the matching rows
+    ///
+    /// Ordering, limit and offset are ignored.
+    pub fn build_count(&self) -> (String, Vec<BindValue>) {
+        let mut counted = self.clone();
+        counted.select_fields = vec!["COUNT(*) AS count".to_string()];
+        counted.order_by.clear();
+        counted.limit = None;
+        counted.offset = None;
+        counted.build()
+    }
+
+    /// Build an `UPDATE` of the matching rows, setting each column to its value
+    ///
+    /// Joins, ordering and limits are ignored.
+    pub fn build_update(&self, values: Vec<(&str, BindValue)>) -> (String, Vec<BindValue>) {
+        let mut binds = Vec::new();
+        let assignments: Vec<String> = values
+            .into_iter()
+            .map(|(column, value)| {
+                binds.push(value);
+                format!("{} = {}", column, self.placeholder(binds.len()))
+            })
+            .collect();
+        let mut sql = format!("UPDATE {} SET {}", self.table, assignments.join(", "));
+        self.push_where(&mut sql, &mut binds);
+        (sql, binds)
+    }
+
+    /// Build an `INSERT` of one row into the table
+    pub fn build_insert(&self, values: Vec<(&str, BindValue)>) -> (String, Vec<BindValue>) {
+        let mut binds = Vec::new();
+        let mut columns = Vec::new();
+        let mut placeholders = Vec::new();
+        for (column, value) in values {
+            binds.push(value);
+            columns.push(column);
+            placeholders.push(self.placeholder(binds.len()));
+        }
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            self.table,
+            columns.join(", "),
+            placeholders.join(", ")
+        );
+        (sql, binds)
+    }
+
+    /// Build an `INSERT` that updates the `update` columns instead when a row
+    /// with the same `conflict` columns exists
+    ///
+    /// PostgreSQL and SQLite get `ON CONFLICT (..) DO UPDATE`, MySQL gets
+    /// `ON DUPLICATE KEY UPDATE` (which uses any unique key, so `conflict`
+    /// is not rendered). With no `update` columns existing rows are kept;
+    /// on MySQL with no `conflict` columns either, the first inserted column
+    /// is assigned to itself (or `INSERT IGNORE` is used without columns).
+    ///
+    /// ```rust
+    /// use rustyx::db::{query::QueryBuilder, DbDriver};
+    ///
+    /// let (sql, binds) = QueryBuilder::table("stock")
+    ///     .driver(DbDriver::PostgreSQL)
+    ///     .build_upsert(vec![("sku", "A1".into()), ("qty", 5.into())], &["sku"], &["qty"]);
+    /// assert_eq!(
+    ///     sql,
+    ///     "INSERT INTO stock (sku, qty) VALUES ($1, $2) ON CONFLICT (sku) DO UPDATE SET qty = excluded.qty"
+    /// );
+    /// assert_eq!(binds.len(), 2);
+    /// ```
+    pub fn build_upsert(
+        &self,
+        values: Vec<(&str, BindValue)>,
+        conflict: &[&str],
+        update: &[&str],
+    ) -> (String, Vec<BindValue>) {
+        let first = values.first().map(|(column, _)| column.to_string());
+        let (mut sql, binds) = self.build_insert(values);
+        match self.driver {
+            DbDriver::MySQL => {
+                let assignments: Vec<String> = if update.is_empty() {
+                    // A no-op assignment keeps the existing row
+                    let keep: Vec<&str> = match (conflict, &first) {
+                        ([], Some(first)) => vec![first.as_str()],
+                        _ => conflict.to_vec(),
+                    };
+                    keep.iter().map(|c| format!("{} = {}", c, c)).collect()
+                } else {
+                    update
+                        .iter()
+                        .map(|c| format!("{} = VALUES({})", c, c))
+                        .collect()
+                };
+                if assignments.is_empty() {
+                    sql = sql.replacen("INSERT INTO", "INSERT IGNORE INTO", 1);
+                } else {
+                    sql.push_str(&format!(
+                        " ON DUPLICATE KEY UPDATE {}",
+                        assignments.join(", ")
+                    ));
+                }
+            }
+            _ => {
+                sql.push_str(&format!(" ON CONFLICT ({})", conflict.join(", ")));
+                if update.is_empty() {
+                    sql.push_str(" DO NOTHING");
+                } else {
+                    let assignments: Vec<String> = update
+                        .iter()
+                        .map(|c| format!("{} = excluded.{}", c, c))
+                        .collect();
+                    sql.push_str(&format!(" DO UPDATE SET {}", assignments.join(", ")));
+                }
+            }
+        }
+        (sql, binds)
+    }
+
+    /// Build a `DELETE` of the matching rows
+    ///
+    /// Joins, ordering and limits are ignored.
+    pub fn build_delete(&self) -> (String, Vec<BindValue>) {
+        let mut binds = Vec::new();
+        let mut sql = format!("DELETE FROM {}", self.table);
+        self.push_where(&mut sql, &mut binds);
+        (sql, binds)
+    }
+
+    fn push_where(&self, sql: &mut String, binds: &mut Vec<BindValue>) {
+        if !self.where_clauses.is_empty() {
+            let conditions = self.render_conditions(&self.where_clauses, binds);
+            sql.push_str(&format!(" WHERE {}", conditions));
+        }
+    }
+
+    fn render_conditions(
+        &self,
+        conditions: &[(Connector, Condition)],
+        binds: &mut Vec<BindValue>,
+    ) -> String {
+        let mut sql = String::new();
+        for (i, (connector, condition)) in conditions.iter().enumerate() {
+            if i > 0 {
+                sql.push_str(match connector {
+                    Connector::And => " AND ",
+                    Connector::Or => " OR ",
+                });
+            }
+            match condition {
+                Condition::Clause(clause) => sql.push_str(&self.render_clause(clause, binds)),
+                Condition::Group(group) => {
+                    sql.push_str(&format!("({})", self.render_conditions(group, binds)))
+                }
+            }
+        }
         sql
     }
+
+    fn render_clause(&self, clause: &WhereClause, binds: &mut Vec<BindValue>) -> String {
+        let mut bind = |value: &BindValue| {
+            binds.push(value.clone());
+            self.placeholder(binds.len())
+        };
+        let field = &clause.field;
+        let first = clause.values.first().unwrap_or(&BindValue::Null);
+
+        match clause.operator {
+            Operator::Eq => format!("{} = {}", field, bind(first)),
+            Operator::Ne => format!("{} <> {}", field, bind(first)),
+            Operator::Gt => format!("{} > {}", field, bind(first)),
+            Operator::Gte => format!("{} >= {}", field, bind(first)),
+            Operator::Lt => format!("{} < {}", field, bind(first)),
+            Operator::Lte => format!("{} <= {}", field, bind(first)),
+            Operator::Like => format!("{} LIKE {}", field, bind(first)),
+            Operator::IsNull => format!("{} IS NULL", field),
+            Operator::IsNotNull => format!("{} IS NOT NULL", field),
+            // `IN ()` isn't valid SQL
+            Operator::In if clause.values.is_empty() => "1 = 0".to_string(),
+            Operator::NotIn if clause.values.is_empty() => "1 = 1".to_string(),
+            Operator::In | Operator::NotIn => {
+                let list: Vec<String> = clause.values.iter().map(&mut bind).collect();
+                let not = if matches!(clause.operator, Operator::NotIn) {
+                    "NOT "
+                } else {
+                    ""
+                };
+                format!("{} {}IN ({})", field, not, list.join(", "))
+            }
+            Operator::Between => {
+                let high = clause.values.get(1).unwrap_or(&BindValue::Null);
+                let low = bind(first);
+                format!("{} BETWEEN {} AND {}", field, low, bind(high))
+            }
+        }
+    }
+
+    /// Placeholder for the `n`th bound value (1-based)
+    fn placeholder(&self, n: usize) -> String {
+        match self.driver {
+            DbDriver::PostgreSQL => format!("${}", n),
+            _ => "?".to_string(),
+        }
+    }
+
+    /// Run the query on a pool, deserializing each row by column name
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub async fn execute<T: serde::de::DeserializeOwned>(
+        &self,
+        pool: &sqlx::AnyPool,
+    ) -> crate::error::Result<Vec<T>> {
+        let (sql, binds) = self.build();
+        crate::db::sql::SqlExecutor::new(pool.clone())
+            .query_with(&sql, binds)
+            .await
+    }
+
+    /// Insert a row into the table, or update its other columns when one
+    /// with the same `conflict` columns exists; returns the affected rows
+    ///
+    /// See [`build_upsert`](Self::build_upsert) for the generated SQL.
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub async fn insert_or_update(
+        &self,
+        pool: &sqlx::AnyPool,
+        values: Vec<(&str, BindValue)>,
+        conflict: &[&str],
+    ) -> crate::error::Result<u64> {
+        let update: Vec<&str> = values
+            .iter()
+            .map(|(column, _)| *column)
+            .filter(|column| !conflict.contains(column))
+            .collect();
+        let (sql, binds) = self.build_upsert(values, conflict, &update);
+        crate::db::sql::SqlExecutor::new(pool.clone())
+            .execute_with(&sql, binds)
+            .await
+    }
+
+    /// Run a page of the query, plus a `COUNT` for the totals
+    ///
+    /// Pages start at 1; `page` and `per_page` below 1 are raised to 1.
+    ///
+    /// ```rust,ignore
+    /// let page: PaginatedResponse<User> = QueryBuilder::table("users")
+    ///     .order_by("id", Order::Asc)
+    ///     .paginate(sql.pool(), 2, 20)
+    ///     .await?;
+    /// ```
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub async fn paginate<T: serde::de::DeserializeOwned>(
+        &self,
+        pool: &sqlx::AnyPool,
+        page: u32,
+        per_page: u32,
+    ) -> crate::error::Result<crate::utils::PaginatedResponse<T>> {
+        use crate::utils::{PaginatedResponse, Pagination};
+
+        #[derive(serde::Deserialize)]
+        struct Count {
+            count: i64,
+        }
+
+        let sql = crate::db::sql::SqlExecutor::new(pool.clone());
+        let (query, binds) = self.build_count();
+        let total = sql
+            .query_with::<Count>(&query, binds)
+            .await?
+            .first()
+            .map_or(0, |row| row.count.max(0) as u64);
+
+        let pagination = Pagination::new(page.max(1), per_page.max(1), total);
+        let (query, binds) = self
+            .clone()
+            .limit(pagination.per_page)
+            .offset(pagination.offset())
+            .build();
+        let data = sql.query_with(&query, binds).await?;
+        Ok(PaginatedResponse::new(data, pagination))
+    }
+
+    /// Run a page of the query keyed on `column`, for stable paging of
+    /// large or changing tables
+    ///
+    /// Rows come ordered by `column` (which should be unique), starting
+    /// after the `after` value; pass the response's `next_cursor` to get
+    /// the following page. Any other ordering is replaced.
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub async fn cursor_paginate<T: serde::de::DeserializeOwned>(
+        &self,
+        pool: &sqlx::AnyPool,
+        column: &str,
+        after: Option<&serde_json::Value>,
+        per_page: u32,
+    ) -> crate::error::Result<crate::utils::CursorPaginatedResponse<T>> {
+        use serde_json::{Map, Value};
+
+        let per_page = per_page.max(1);
+        let mut query = self.clone();
+        query.order_by = vec![(column.to_string(), Order::Asc)];
+        if let Some(after) = after {
+            query = query.scope(|q| q.where_gt(column, after));
+        }
+        // One extra row tells whether there is a next page
+        query.offset = None;
+        let (query, binds) = query.limit(per_page + 1).build();
+        let mut rows: Vec<Map<String, Value>> = crate::db::sql::SqlExecutor::new(pool.clone())
+            .query_with(&query, binds)
+            .await?;
+
+        let next_cursor = if rows.len() > per_page as usize {
+            rows.truncate(per_page as usize);
+            rows.last().and_then(|row| row.get(column)).cloned()
+        } else {
+            None
+        };
+        let data = rows
+            .into_iter()
+            .map(|row| serde_json::from_value(Value::Object(row)))
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(crate::utils::CursorPaginatedResponse { data, next_cursor })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_are_bound() {
+        let (sql, binds) = QueryBuilder::table("users")
+            .where_eq("email", "x' OR '1'='1")
+            .where_eq("active", true)
+            .limit(5)
+            .build();
+        assert_eq!(
+            sql,
+            "SELECT * FROM users WHERE email = ? AND active = ? LIMIT 5"
+        );
+        assert_eq!(
+            binds,
+            [
+                BindValue::Text("x' OR '1'='1".into()),
+                BindValue::Bool(true)
+            ]
+        );
+
+        let (sql, _) = QueryBuilder::table("users")
+            .driver(DbDriver::PostgreSQL)
+            .where_eq("id", 7)
+            .where_eq("role", "admin")
+            .build();
+        assert_eq!(sql, "SELECT * FROM users WHERE id = $1 AND role = $2");
+    }
+
+    #[test]
+    fn test_mysql_upsert_without_columns() {
+        let query = QueryBuilder::table("stock").driver(DbDriver::MySQL);
+        let (sql, _) = query.build_upsert(vec![("sku", "A1".into()), ("qty", 5.into())], &[], &[]);
+        assert_eq!(
+            sql,
+            "INSERT INTO stock (sku, qty) VALUES (?, ?) ON DUPLICATE KEY UPDATE sku = sku"
+        );
+        let (sql, _) = query.build_upsert(vec![("sku", "A1".into())], &["sku"], &[]);
+        assert!(sql.ends_with("ON DUPLICATE KEY UPDATE sku = sku"));
+        let (sql, _) = query.build_upsert(vec![("qty", 5.into())], &["sku"], &["qty"]);
+        assert!(sql.ends_with("ON DUPLICATE KEY UPDATE qty = VALUES(qty)"));
+        let (sql, _) = query.build_upsert(Vec::new(), &[], &[]);
+        assert_eq!(sql, "INSERT IGNORE INTO stock () VALUES ()");
+    }
+
+    #[test]
+    fn test_operators_groups_and_joins() {
+        let (sql, binds) = QueryBuilder::table("posts")
+            .driver(DbDriver::PostgreSQL)
+            .select(&["posts.*", "users.name"])
+            .left_join("users", "users.id = posts.user_id")
+            .where_in("posts.status", ["draft", "published"])
+            .where_between("posts.views", 10, 100)
+            .where_is_null("posts.deleted_at")
+            .or_where_group(|q| q.where_like("title", "%rust%").where_ne("user_id", 3))
+            .where_not_in("posts.id", Vec::<i64>::new())
+            .build();
+        assert_eq!(
+            sql,
+            "SELECT posts.*, users.name FROM posts LEFT JOIN users ON users.id = posts.user_id \
+             WHERE posts.status IN ($1, $2) AND posts.views BETWEEN $3 AND $4 \
+             AND posts.deleted_at IS NULL OR (title LIKE $5 AND user_id <> $6) AND 1 = 1"
+        );
+        assert_eq!(binds.len(), 6);
+        assert_eq!(binds[4], BindValue::Text("%rust%".into()));
+    }
 }
@@ -0,0 +1,209 @@
+//! Redis Module
+//!
+//! A thin async client for caching, counters and pub/sub, shared by the
+//! Redis-backed stores (`RedisCache`, `RedisCacheStore`, `RedisWsAdapter`).
+
+use super::DatabaseConfig;
+use crate::error::{Error, Result};
+use futures::{Stream, StreamExt};
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, FromRedisValue, ToRedisArgs};
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+
+/// Redis client over a reconnecting connection
+///
+/// Cloning is cheap and clones share the connection.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let redis = RedisClient::connect("redis://127.0.0.1/").await?;
+/// redis.set_ex("greeting", "hello", Duration::from_secs(60)).await?;
+/// let hits = redis.incr("hits", 1).await?;
+/// ```
+#[derive(Clone)]
+pub struct RedisClient {
+    client: redis::Client,
+    conn: ConnectionManager,
+}
+
+impl RedisClient {
+    /// Connect to Redis (e.g. `redis://127.0.0.1/`)
+    pub async fn connect(url: &str) -> Result<Self> {
+        let client = redis::Client::open(url).map_err(redis_error)?;
+        let conn = client.get_connection_manager().await.map_err(redis_error)?;
+        Ok(Self { client, conn })
+    }
+
+    /// Connect using a [`DbDriver::Redis`](super::DbDriver::Redis) configuration
+    pub async fn from_config(config: &DatabaseConfig) -> Result<Self> {
+        Self::connect(&config.connection_string()).await
+    }
+
+    /// The connection, for commands without a helper here
+    pub fn connection(&self) -> ConnectionManager {
+        self.conn.clone()
+    }
+
+    /// The underlying client
+    pub fn client(&self) -> &redis::Client {
+        &self.client
+    }
+
+    /// Get a value, `None` when the key does not exist
+    pub async fn get<T: FromRedisValue>(&self, key: &str) -> Result<Option<T>> {
+        self.conn.clone().get(key).await.map_err(redis_error)
+    }
+
+    /// Set a value without expiry
+    pub async fn set<V: ToRedisArgs + Send + Sync>(&self, key: &str, value: V) -> Result<()> {
+        self.conn.clone().set(key, value).await.map_err(redis_error)
+    }
+
+    /// Set a value expiring after `ttl` (at least one second)
+    pub async fn set_ex<V: ToRedisArgs + Send + Sync>(
+        &self,
+        key: &str,
+        value: V,
+        ttl: Duration,
+    ) -> Result<()> {
+        self.conn
+            .clone()
+            .set_ex(key, value, expiry_secs(ttl))
+            .await
+            .map_err(redis_error)
+    }
+
+    /// Get a JSON-encoded value
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        match self.get::<Vec<u8>>(key).await? {
+            Some(raw) => Ok(Some(serde_json::from_slice(&raw)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store a value as JSON, optionally expiring after `ttl`
+    pub async fn set_json<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Option<Duration>,
+    ) -> Result<()> {
+        let raw = serde_json::to_vec(value)?;
+        match ttl {
+            Some(ttl) => self.set_ex(key, raw, ttl).await,
+            None => self.set(key, raw).await,
+        }
+    }
+
+    /// Delete keys, returning how many existed
+    pub async fn del(&self, keys: &[&str]) -> Result<u64> {
+        self.conn.clone().del(keys).await.map_err(redis_error)
+    }
+
+    pub async fn exists(&self, key: &str) -> Result<bool> {
+        self.conn.clone().exists(key).await.map_err(redis_error)
+    }
+
+    /// Set a key's expiry (at least one second), `false` when the key does
+    /// not exist
+    pub async fn expire(&self, key: &str, ttl: Duration) -> Result<bool> {
+        self.conn
+            .clone()
+            .expire(key, expiry_secs(ttl) as i64)
+            .await
+            .map_err(redis_error)
+    }
+
+    /// Remaining time to live, `None` for missing keys and keys without expiry
+    pub async fn ttl(&self, key: &str) -> Result<Option<Duration>> {
+        let secs: i64 = self.conn.clone().ttl(key).await.map_err(redis_error)?;
+        Ok(remaining(secs))
+    }
+
+    /// Check the server answers `PING`
+    pub async fn ping(&self) -> Result<()> {
+        redis::cmd("PING")
+            .query_async::<String>(&mut self.conn.clone())
+            .await
+            .map(|_| ())
+            .map_err(redis_error)
+    }
+
+    /// Increment a counter, creating it at 0, and return the new value
+    pub async fn incr(&self, key: &str, by: i64) -> Result<i64> {
+        self.conn.clone().incr(key, by).await.map_err(redis_error)
+    }
+
+    /// Publish a message, returning how many subscribers received it
+    pub async fn publish<V: ToRedisArgs + Send + Sync>(
+        &self,
+        channel: &str,
+        message: V,
+    ) -> Result<u64> {
+        self.conn
+            .clone()
+            .publish(channel, message)
+            .await
+            .map_err(redis_error)
+    }
+
+    /// Subscribe to a channel, yielding message payloads
+    ///
+    /// The subscription uses its own connection and ends when the stream is
+    /// dropped.
+    pub async fn subscribe(&self, channel: &str) -> Result<impl Stream<Item = Vec<u8>>> {
+        let mut pubsub = self.client.get_async_pubsub().await.map_err(redis_error)?;
+        pubsub.subscribe(channel).await.map_err(redis_error)?;
+        Ok(pubsub
+            .into_on_message()
+            .filter_map(|msg| async move { msg.get_payload::<Vec<u8>>().ok() }))
+    }
+}
+
+pub(crate) fn redis_error(e: redis::RedisError) -> Error {
+    Error::Database(e.to_string())
+}
+
+/// Whole seconds for an expiry; Redis deletes keys given 0, so sub-second
+/// durations round up to one
+fn expiry_secs(ttl: Duration) -> u64 {
+    ttl.as_secs().max(1)
+}
+
+/// A `TTL` reply as a duration; Redis answers -2 for missing keys and -1
+/// for keys without expiry
+fn remaining(secs: i64) -> Option<Duration> {
+    (secs >= 0).then(|| Duration::from_secs(secs as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DbDriver;
+
+    #[test]
+    fn test_expiry_helpers() {
+        assert_eq!(expiry_secs(Duration::from_millis(1)), 1);
+        assert_eq!(expiry_secs(Duration::ZERO), 1);
+        assert_eq!(expiry_secs(Duration::from_millis(90_500)), 90);
+        assert_eq!(remaining(-2), None);
+        assert_eq!(remaining(-1), None);
+        assert_eq!(remaining(0), Some(Duration::ZERO));
+        assert_eq!(remaining(42), Some(Duration::from_secs(42)));
+    }
+
+    #[test]
+    fn test_connection_url() {
+        let mut config = DatabaseConfig::new(DbDriver::Redis, "2");
+        assert_eq!(config.port, 6379);
+        assert_eq!(config.connection_string(), "redis://localhost:6379/2");
+        config.password = "secret".to_string();
+        config.host = "cache.internal".to_string();
+        assert_eq!(
+            config.connection_string(),
+            "redis://:secret@cache.internal:6379/2"
+        );
+    }
+}
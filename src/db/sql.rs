@@ -1,8 +1,15 @@
 //! SQL Database Module (MySQL, PostgreSQL, SQLite)
 
-use crate::error::Result;
+use super::connection::get_db;
+use super::query::BindValue;
+use super::{DatabaseConfig, DbDriver};
+use crate::error::{Error, Result};
+use crate::models::schema::Schema;
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use sqlx::any::{AnyArguments, AnyPoolOptions, AnyRow};
+use sqlx::{AnyPool, Arguments, Column, FromRow, Row, TypeInfo, ValueRef};
 
 /// SQL Repository trait for CRUD operations
 #[async_trait]
@@ -32,24 +39,354 @@ where
     async fn count(&self) -> Result<u64>;
 }
 
-/// SQL query executor
-pub struct SqlExecutor;
+/// SQL query executor over a connection pool
+///
+/// # Example
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// struct User { id: i64, email: String }
+///
+/// let sql = SqlExecutor::global()?;
+/// let users: Vec<User> = sql.query("SELECT id, email FROM users").await?;
+/// sql.execute("DELETE FROM sessions WHERE expired = 1").await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct SqlExecutor {
+    pool: AnyPool,
+}
 
 impl SqlExecutor {
-    /// Execute a raw SQL query
-    pub async fn query<T: DeserializeOwned>(_sql: &str) -> Result<Vec<T>> {
-        // Implementation would use sqlx
-        Ok(Vec::new())
+    /// Create an executor for a pool
+    pub fn new(pool: AnyPool) -> Self {
+        Self { pool }
+    }
+
+    /// Executor for the global connection set up by [`init_db`](super::connection::init_db)
+    ///
+    /// Inside a request whose [tenant](crate::tenancy) has a schema or
+    /// database of its own, that tenant's pool is used instead.
+    pub fn global() -> Result<Self> {
+        if let Some(conn) = crate::tenancy::current_connection() {
+            return conn.sql();
+        }
+        let db = get_db().ok_or_else(|| Error::Database("Database not initialized".to_string()))?;
+        let conn = db.read();
+        let pool = conn
+            .as_ref()
+            .and_then(|conn| conn.sql_pool())
+            .ok_or_else(|| Error::Database("No SQL connection".to_string()))?;
+        Ok(Self::new(pool.clone()))
+    }
+
+    /// The underlying pool
+    pub fn pool(&self) -> &AnyPool {
+        &self.pool
+    }
+
+    /// Execute a raw SQL query, deserializing each row by column name
+    pub async fn query<T: DeserializeOwned>(&self, sql: &str) -> Result<Vec<T>> {
+        self.query_with(sql, Vec::new()).await
+    }
+
+    /// Execute a SQL query with bound placeholder values
+    pub async fn query_with<T: DeserializeOwned>(
+        &self,
+        sql: &str,
+        binds: Vec<BindValue>,
+    ) -> Result<Vec<T>> {
+        let rows = timed(
+            "query",
+            sqlx::query_with(sql, arguments(binds)).fetch_all(&self.pool),
+        )
+        .await?;
+        rows.iter()
+            .map(|row| Ok(serde_json::from_value(Value::Object(row_to_json(row)?))?))
+            .collect()
+    }
+
+    /// Execute a SQL query with bound values, mapping rows with sqlx's `FromRow`
+    ///
+    /// Placeholders are `?`, or `$1, $2, ...` on PostgreSQL.
+    ///
+    /// ```rust,ignore
+    /// #[derive(sqlx::FromRow)]
+    /// struct User { id: i64, email: String }
+    ///
+    /// let users: Vec<User> = sql
+    ///     .query_as("SELECT id, email FROM users WHERE karma > ?", params![10])
+    ///     .await?;
+    /// ```
+    pub async fn query_as<T>(&self, sql: &str, params: Vec<BindValue>) -> Result<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, AnyRow> + Send + Unpin,
+    {
+        timed(
+            "query",
+            sqlx::query_as_with(sql, arguments(params)).fetch_all(&self.pool),
+        )
+        .await
     }
 
-    /// Execute a raw SQL command
-    pub async fn execute(_sql: &str) -> Result<u64> {
-        // Implementation would use sqlx
-        Ok(0)
+    /// Execute a SQL query expecting exactly one row
+    ///
+    /// Fails with [`Error::NotFound`] when there is no row; extra rows are
+    /// ignored.
+    pub async fn fetch_one<T: DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: Vec<BindValue>,
+    ) -> Result<T> {
+        self.fetch_optional(sql, params)
+            .await?
+            .ok_or_else(|| Error::NotFound("No row returned".to_string()))
+    }
+
+    /// Execute a SQL query returning the first row, if any
+    pub async fn fetch_optional<T: DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: Vec<BindValue>,
+    ) -> Result<Option<T>> {
+        let row = timed(
+            "query",
+            sqlx::query_with(sql, arguments(params)).fetch_optional(&self.pool),
+        )
+        .await?;
+        match row {
+            Some(row) => Ok(Some(serde_json::from_value(Value::Object(row_to_json(
+                &row,
+            )?))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Execute a raw SQL command, returning the number of affected rows
+    pub async fn execute(&self, sql: &str) -> Result<u64> {
+        let result = timed("execute", sqlx::query(sql).execute(&self.pool)).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Execute a SQL command with bound placeholder values, returning the
+    /// number of affected rows
+    pub async fn execute_with(&self, sql: &str, binds: Vec<BindValue>) -> Result<u64> {
+        let result = timed(
+            "execute",
+            sqlx::query_with(sql, arguments(binds)).execute(&self.pool),
+        )
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Execute an `INSERT` with bound values, returning the generated row id
+    ///
+    /// Only MySQL reports the id through the `Any` driver; use `RETURNING`
+    /// on SQLite and PostgreSQL.
+    pub async fn insert_with(&self, sql: &str, binds: Vec<BindValue>) -> Result<Option<i64>> {
+        let result = timed(
+            "execute",
+            sqlx::query_with(sql, arguments(binds)).execute(&self.pool),
+        )
+        .await?;
+        Ok(result.last_insert_id())
+    }
+
+    /// Apply pending migrations in version order
+    ///
+    /// Applied versions are recorded in the `_rustyx_migrations` table.
+    /// Each migration runs in a transaction with its version row, and its
+    /// SQL may hold several statements. Returns the versions applied by
+    /// this call.
+    pub async fn migrate(&self, migrations: &[Migration]) -> Result<Vec<String>> {
+        let applied = self.applied_migrations().await?;
+        let mut pending: Vec<&Migration> = migrations
+            .iter()
+            .filter(|m| !applied.contains(&m.version))
+            .collect();
+        pending.sort_by(|a, b| a.version.cmp(&b.version));
+
+        let mut versions = Vec::new();
+        for migration in pending {
+            self.run_migration(
+                &migration.up,
+                &format!(
+                    "INSERT INTO {} (version, name) VALUES ({}, {})",
+                    MIGRATIONS_TABLE,
+                    self.placeholder(1),
+                    self.placeholder(2)
+                ),
+                vec![
+                    migration.version.as_str().into(),
+                    migration.name.as_str().into(),
+                ],
+            )
+            .await?;
+            versions.push(migration.version.clone());
+        }
+        Ok(versions)
+    }
+
+    /// Roll back the most recently applied migration, returning its version
+    pub async fn rollback(&self, migrations: &[Migration]) -> Result<Option<String>> {
+        let applied = self.applied_migrations().await?;
+        let Some(version) = applied.last() else {
+            return Ok(None);
+        };
+        let migration = migrations
+            .iter()
+            .find(|m| &m.version == version)
+            .ok_or_else(|| Error::Database(format!("Unknown migration {}", version)))?;
+
+        self.run_migration(
+            &migration.down,
+            &format!(
+                "DELETE FROM {} WHERE version = {}",
+                MIGRATIONS_TABLE,
+                self.placeholder(1)
+            ),
+            vec![version.as_str().into()],
+        )
+        .await?;
+        Ok(Some(version.clone()))
+    }
+
+    /// Run migration SQL unprepared, so it may hold several statements,
+    /// together with the statement recording it in one transaction
+    ///
+    /// MySQL commits DDL implicitly, so there a failing migration can still
+    /// leave earlier statements applied.
+    async fn run_migration(&self, sql: &str, record: &str, binds: Vec<BindValue>) -> Result<()> {
+        let mut tx = self.pool.begin().await.map_err(Error::from)?;
+        if !sql.trim().is_empty() {
+            timed("execute", sqlx::Executor::execute(&mut *tx, sql)).await?;
+        }
+        timed(
+            "execute",
+            sqlx::query_with(record, arguments(binds)).execute(&mut *tx),
+        )
+        .await?;
+        tx.commit().await.map_err(Error::from)
+    }
+
+    /// Versions already applied, oldest first
+    async fn applied_migrations(&self) -> Result<Vec<String>> {
+        self.execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (version VARCHAR(255) PRIMARY KEY, name VARCHAR(255) NOT NULL)",
+            MIGRATIONS_TABLE
+        ))
+        .await?;
+        let rows: Vec<(String,)> = sqlx::query_as(&format!(
+            "SELECT version FROM {} ORDER BY version",
+            MIGRATIONS_TABLE
+        ))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Error::from)?;
+        Ok(rows.into_iter().map(|(version,)| version).collect())
+    }
+
+    /// The driver behind the pool, from its connection URL scheme
+    pub fn driver(&self) -> DbDriver {
+        let url = &self.pool.connect_options().database_url;
+        match url.scheme() {
+            "postgres" | "postgresql" => DbDriver::PostgreSQL,
+            "mysql" | "mariadb" => DbDriver::MySQL,
+            _ => DbDriver::SQLite,
+        }
+    }
+
+    fn placeholder(&self, n: usize) -> String {
+        match self.driver() {
+            DbDriver::PostgreSQL => format!("${}", n),
+            _ => "?".to_string(),
+        }
     }
 }
 
+const MIGRATIONS_TABLE: &str = "_rustyx_migrations";
+
+/// Open a pool for a MySQL, PostgreSQL or SQLite configuration
+///
+/// An in-memory SQLite database lives only as long as its connection, so
+/// `:memory:` pools hold exactly one connection that is never recycled.
+pub(crate) async fn connect(config: &DatabaseConfig) -> Result<AnyPool> {
+    sqlx::any::install_default_drivers();
+    let options = if config.is_sqlite_memory() {
+        AnyPoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+    } else {
+        AnyPoolOptions::new()
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+    };
+    options
+        .connect(&config.connection_string())
+        .await
+        .map_err(Error::from)
+}
+
+/// Run a statement, recording its outcome and latency in the global
+/// [`Metrics`](crate::metrics::Metrics) registry
+async fn timed<T>(
+    operation: &str,
+    statement: impl std::future::Future<Output = std::result::Result<T, sqlx::Error>>,
+) -> Result<T> {
+    let started = std::time::Instant::now();
+    let result = statement.await;
+    crate::metrics::Metrics::global().record_db_query(operation, result.is_ok(), started.elapsed());
+    result.map_err(Error::from)
+}
+
+/// Query arguments holding the values in placeholder order
+pub(crate) fn arguments<'q>(binds: Vec<BindValue>) -> AnyArguments<'q> {
+    let mut args = AnyArguments::default();
+    for value in binds {
+        match value {
+            BindValue::Null => args.add(None::<String>),
+            BindValue::Bool(v) => args.add(v),
+            BindValue::Int(v) => args.add(v),
+            BindValue::Float(v) => args.add(v),
+            BindValue::Text(v) => args.add(v),
+            BindValue::Bytes(v) => args.add(v),
+        }
+    }
+    args
+}
+
+/// Convert a row into a JSON object keyed by column name
+///
+/// Integers, floats, booleans and text map to their JSON counterparts,
+/// blobs to arrays of bytes and NULL to `null`.
+pub fn row_to_json(row: &AnyRow) -> Result<Map<String, Value>> {
+    let mut object = Map::new();
+    for column in row.columns() {
+        let i = column.ordinal();
+        let raw = row.try_get_raw(i).map_err(Error::from)?;
+        let value = if raw.is_null() {
+            Value::Null
+        } else {
+            // Any driver type names, see `AnyTypeInfo`
+            match raw.type_info().name() {
+                "NULL" => Value::Null,
+                "BOOLEAN" => Value::from(row.try_get::<bool, _>(i).map_err(Error::from)?),
+                "SMALLINT" | "INTEGER" | "BIGINT" => {
+                    Value::from(row.try_get::<i64, _>(i).map_err(Error::from)?)
+                }
+                "REAL" | "DOUBLE" => Value::from(row.try_get::<f64, _>(i).map_err(Error::from)?),
+                "BLOB" => Value::from(row.try_get::<Vec<u8>, _>(i).map_err(Error::from)?),
+                _ => Value::from(row.try_get::<String, _>(i).map_err(Error::from)?),
+            }
+        };
+        object.insert(column.name().to_string(), value);
+    }
+    Ok(object)
+}
+
 /// Migration helper
+#[derive(Debug, Clone)]
 pub struct Migration {
     pub version: String,
     pub name: String,
@@ -66,4 +403,330 @@ impl Migration {
             down: down.to_string(),
         }
     }
+
+    /// Load migrations from `<version>_<name>.up.sql` files in `dir`, each
+    /// with an optional `<version>_<name>.down.sql` beside it
+    ///
+    /// These are the files written by `rustyx generate migration`.
+    pub fn load_dir(dir: impl AsRef<std::path::Path>) -> Result<Vec<Self>> {
+        let dir = dir.as_ref();
+        let mut migrations = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(stem) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".up.sql"))
+            else {
+                continue;
+            };
+            let (version, name) = stem.split_once('_').unwrap_or((stem, stem));
+            let down = dir.join(format!("{}.down.sql", stem));
+            migrations.push(Self::new(
+                version,
+                name,
+                &std::fs::read_to_string(&path)?,
+                &std::fs::read_to_string(down).unwrap_or_default(),
+            ));
+        }
+        migrations.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(migrations)
+    }
+
+    /// Migration creating a table from a [`Schema`] and dropping it on rollback
+    pub fn create_table(version: &str, name: &str, schema: &Schema, driver: DbDriver) -> Self {
+        Self::new(
+            version,
+            name,
+            &schema.to_sql(driver.clone()),
+            &Schema::drop_sql(schema.table(), driver),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq, sqlx::FromRow)]
+    struct User {
+        id: i64,
+        email: String,
+        score: Option<f64>,
+    }
+
+    #[tokio::test]
+    async fn test_query_and_execute() {
+        // One connection: every in-memory SQLite connection is its own database
+        let config =
+            DatabaseConfig::new(crate::db::DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(connect(&config).await.unwrap());
+
+        sql.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, score REAL)")
+            .await
+            .unwrap();
+        let inserted = sql
+            .execute("INSERT INTO users (email, score) VALUES ('a@example.com', 1.5), ('b@example.com', NULL)")
+            .await
+            .unwrap();
+        assert_eq!(inserted, 2);
+
+        let users: Vec<User> = sql.query("SELECT * FROM users ORDER BY id").await.unwrap();
+        assert_eq!(
+            users,
+            [
+                User {
+                    id: 1,
+                    email: "a@example.com".into(),
+                    score: Some(1.5)
+                },
+                User {
+                    id: 2,
+                    email: "b@example.com".into(),
+                    score: None
+                },
+            ]
+        );
+        let users: Vec<User> = sql
+            .query_as(
+                "SELECT * FROM users WHERE score IS NOT NULL AND email = ?",
+                crate::params!["a@example.com"],
+            )
+            .await
+            .unwrap();
+        assert_eq!(users[0].id, 1);
+        let user: User = sql
+            .fetch_one("SELECT * FROM users WHERE id = ?", crate::params![2])
+            .await
+            .unwrap();
+        assert_eq!(user.score, None);
+        let missing: Option<User> = sql
+            .fetch_optional("SELECT * FROM users WHERE id = ?", crate::params![9])
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+        assert!(matches!(
+            sql.fetch_one::<User>("SELECT * FROM users WHERE id = ?", crate::params![9])
+                .await,
+            Err(Error::NotFound(_))
+        ));
+
+        let users: Vec<User> = super::super::query::QueryBuilder::table("users")
+            .where_eq("email", "b@example.com' OR '1'='1")
+            .execute(sql.pool())
+            .await
+            .unwrap();
+        assert!(users.is_empty());
+        let users: Vec<User> = super::super::query::QueryBuilder::table("users")
+            .where_eq("email", "b@example.com")
+            .execute(sql.pool())
+            .await
+            .unwrap();
+        assert_eq!(users[0].id, 2);
+
+        let err = sql.execute("SELECT * FROM missing").await.unwrap_err();
+        assert!(matches!(err, Error::Database(_)));
+
+        sql.execute("CREATE UNIQUE INDEX users_email ON users (email)")
+            .await
+            .unwrap();
+        let err = sql
+            .execute("INSERT INTO users (email) VALUES ('a@example.com')")
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 409);
+        assert!(!err.to_string().contains("users.email"));
+        let err = sql
+            .execute("INSERT INTO users (email) VALUES (NULL)")
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), 400);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_helpers() {
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(connect(&config).await.unwrap());
+        sql.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, score REAL)")
+            .await
+            .unwrap();
+        sql.execute("INSERT INTO users (email, score) VALUES ('a@example.com', 2.5), ('b@example.com', 4.0)")
+            .await
+            .unwrap();
+
+        // fetch_one: a missing row is a 404
+        let user: User = sql
+            .fetch_one(
+                "SELECT * FROM users WHERE email = ?",
+                crate::params!["a@example.com"],
+            )
+            .await
+            .unwrap();
+        assert_eq!(user.score, Some(2.5));
+        let err = sql
+            .fetch_one::<User>("SELECT * FROM users WHERE id = ?", crate::params![42])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::NotFound(_)));
+        assert_eq!(err.status_code(), 404);
+
+        // fetch_optional: a missing row is None
+        let user: Option<User> = sql
+            .fetch_optional("SELECT * FROM users WHERE id = ?", crate::params![2])
+            .await
+            .unwrap();
+        assert_eq!(user.map(|u| u.email).as_deref(), Some("b@example.com"));
+        let missing: Option<User> = sql
+            .fetch_optional("SELECT * FROM users WHERE id = ?", crate::params![42])
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+
+        // query_as: rows decode through FromRow
+        let users: Vec<User> = sql
+            .query_as(
+                "SELECT id, email, score FROM users WHERE id >= ? ORDER BY id DESC",
+                crate::params![1],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            users,
+            [
+                User {
+                    id: 2,
+                    email: "b@example.com".into(),
+                    score: Some(4.0)
+                },
+                User {
+                    id: 1,
+                    email: "a@example.com".into(),
+                    score: Some(2.5)
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginate() {
+        use crate::db::query::{Order, QueryBuilder};
+
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(connect(&config).await.unwrap());
+        sql.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, score REAL)")
+            .await
+            .unwrap();
+        for i in 1..=7 {
+            sql.execute(&format!(
+                "INSERT INTO users (email, score) VALUES ('u{}@example.com', {})",
+                i, i
+            ))
+            .await
+            .unwrap();
+        }
+
+        let query = QueryBuilder::table("users")
+            .where_gt("score", 1)
+            .order_by("id", Order::Asc);
+        let page = query.paginate::<User>(sql.pool(), 2, 4).await.unwrap();
+        assert_eq!(page.pagination.total, 6);
+        assert_eq!(page.pagination.total_pages, 2);
+        assert!(!page.pagination.has_next());
+        let ids: Vec<i64> = page.data.iter().map(|u| u.id).collect();
+        assert_eq!(ids, [6, 7]);
+
+        let first = query
+            .cursor_paginate::<User>(sql.pool(), "id", None, 4)
+            .await
+            .unwrap();
+        assert_eq!(first.data.len(), 4);
+        assert_eq!(first.next_cursor, Some(serde_json::json!(5)));
+        let second = query
+            .cursor_paginate::<User>(sql.pool(), "id", first.next_cursor.as_ref(), 4)
+            .await
+            .unwrap();
+        let ids: Vec<i64> = second.data.iter().map(|u| u.id).collect();
+        assert_eq!(ids, [6, 7]);
+        assert_eq!(second.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_and_rollback() {
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(connect(&config).await.unwrap());
+
+        let users = Schema::create("users", |t| {
+            t.increments("id");
+            t.string("email").required().unique();
+        });
+        let migrations = [
+            Migration::new(
+                "002",
+                "add_score",
+                "ALTER TABLE users ADD COLUMN score REAL",
+                "ALTER TABLE users DROP COLUMN score",
+            ),
+            Migration::create_table("001", "create_users", &users, DbDriver::SQLite),
+        ];
+
+        assert_eq!(sql.migrate(&migrations).await.unwrap(), ["001", "002"]);
+        assert!(sql.migrate(&migrations).await.unwrap().is_empty());
+        sql.execute("INSERT INTO users (email, score) VALUES ('a@example.com', 2.0)")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sql.rollback(&migrations).await.unwrap().as_deref(),
+            Some("002")
+        );
+        assert_eq!(
+            sql.rollback(&migrations).await.unwrap().as_deref(),
+            Some("001")
+        );
+        assert_eq!(sql.rollback(&migrations).await.unwrap(), None);
+        assert!(sql.execute("SELECT * FROM users").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_migrations_are_atomic_and_multi_statement() {
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(connect(&config).await.unwrap());
+
+        let good = Migration::new(
+            "001",
+            "tags",
+            "CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT);\n\
+             INSERT INTO tags (name) VALUES ('a');\n\
+             INSERT INTO tags (name) VALUES ('b');",
+            "DROP TABLE tags;\nSELECT 1;",
+        );
+        let broken = Migration::new(
+            "002",
+            "broken",
+            "CREATE TABLE posts (id INTEGER PRIMARY KEY);\nINSERT INTO missing VALUES (1);",
+            "",
+        );
+
+        assert_eq!(
+            sql.migrate(std::slice::from_ref(&good)).await.unwrap(),
+            ["001"]
+        );
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tags")
+            .fetch_one(sql.pool())
+            .await
+            .unwrap();
+        assert_eq!(count.0, 2);
+
+        let migrations = [good, broken];
+        assert!(sql.migrate(&migrations).await.is_err());
+        assert!(sql.execute("SELECT * FROM posts").await.is_err());
+        assert_eq!(sql.applied_migrations().await.unwrap(), ["001"]);
+
+        assert_eq!(
+            sql.rollback(&migrations).await.unwrap().as_deref(),
+            Some("001")
+        );
+        assert!(sql.applied_migrations().await.unwrap().is_empty());
+    }
 }
@@ -0,0 +1,122 @@
+//! Database Test Fixtures
+//!
+//! Throwaway in-memory SQLite databases for tests. Every [`TestDatabase`] is
+//! a separate database, so tests can run in parallel without sharing rows.
+//!
+//! ```rust,ignore
+//! #[tokio::test]
+//! async fn creates_users() {
+//!     let db = TestDatabase::with_migrations(&migrations()).await.unwrap();
+//!     db.register("primary");
+//!
+//!     db.rollback(|sql| async move {
+//!         sql.execute("INSERT INTO users (email) VALUES ('a@example.com')").await?;
+//!         let users: Vec<User> = sql.query("SELECT * FROM users").await?;
+//!         assert_eq!(users.len(), 1);
+//!         Ok(())
+//!     })
+//!     .await
+//!     .unwrap();
+//! }
+//! ```
+
+use super::connection::{register_connection, DatabaseConnection};
+use super::sql::{connect, Migration, SqlExecutor};
+use super::DatabaseConfig;
+use crate::error::Result;
+use std::future::Future;
+
+/// An isolated in-memory SQLite database
+pub struct TestDatabase {
+    config: DatabaseConfig,
+    sql: SqlExecutor,
+}
+
+impl TestDatabase {
+    /// Open an empty database
+    pub async fn new() -> Result<Self> {
+        let config = DatabaseConfig::sqlite_memory();
+        let pool = connect(&config).await?;
+        Ok(Self {
+            config,
+            sql: SqlExecutor::new(pool),
+        })
+    }
+
+    /// Open a database with the migrations applied
+    pub async fn with_migrations(migrations: &[Migration]) -> Result<Self> {
+        let db = Self::new().await?;
+        db.sql.migrate(migrations).await?;
+        Ok(db)
+    }
+
+    /// Executor for this database
+    pub fn sql(&self) -> &SqlExecutor {
+        &self.sql
+    }
+
+    /// Register this database as the named connection `name`, so code using
+    /// [`db(name)`](super::connection::db) talks to it
+    pub fn register(&self, name: &str) {
+        let conn = DatabaseConnection::from_sql_pool(self.config.clone(), self.sql.pool().clone());
+        register_connection(name, conn);
+    }
+
+    /// Run `f` inside a transaction that is always rolled back
+    ///
+    /// Changes made through the executor passed to `f` are visible inside
+    /// the closure and discarded afterwards, leaving fixtures untouched.
+    pub async fn rollback<F, Fut, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(SqlExecutor) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        // The pool has a single connection, so every statement below runs
+        // on the connection holding the transaction.
+        self.sql.execute("BEGIN").await?;
+        let result = f(self.sql.clone()).await;
+        self.sql.execute("ROLLBACK").await?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn test_isolated_rollback() {
+        let migrations = [Migration::new(
+            "1",
+            "create_notes",
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)",
+            "DROP TABLE notes",
+        )];
+        let db = TestDatabase::with_migrations(&migrations).await.unwrap();
+        db.sql()
+            .execute("INSERT INTO notes (body) VALUES ('kept')")
+            .await
+            .unwrap();
+
+        let inside = db
+            .rollback(|sql| async move {
+                sql.execute("INSERT INTO notes (body) VALUES ('discarded')")
+                    .await?;
+                sql.query::<Value>("SELECT * FROM notes").await
+            })
+            .await
+            .unwrap();
+        assert_eq!(inside.len(), 2);
+
+        let after: Vec<Value> = db.sql().query("SELECT * FROM notes").await.unwrap();
+        assert_eq!(after.len(), 1);
+
+        let other = TestDatabase::new().await.unwrap();
+        assert!(other
+            .sql()
+            .query::<Value>("SELECT * FROM notes")
+            .await
+            .is_err());
+    }
+}
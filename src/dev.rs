@@ -0,0 +1,403 @@
+//! Development Mode
+//!
+//! Hot reloading while developing, with the `dev` feature:
+//!
+//! ```rust,ignore
+//! app.dev(
+//!     DevMode::new()
+//!         .templates("templates")
+//!         .assets("public")
+//!         .config(".env")
+//!         .on_change(|change| info!("{:?} changed: {:?}", change.kind, change.paths)),
+//! )?;
+//! ```
+//!
+//! - Template changes reload the [`Views`](crate::views::Views) engine
+//! - `.env` files are re-read into the process environment before the
+//!   [`on_change`](DevMode::on_change) hooks run, so the app can pick up new
+//!   values; other config files only run the hooks
+//! - HTML responses get a script that long-polls `/__rustyx/livereload`
+//!   and refreshes the page after any change
+//! - [`listen`](crate::RustyX::listen) prints a banner listing the routes
+//!
+//! Code changes need a rebuild: `rustyx dev` (the `cli` feature) runs
+//! `cargo run` and restarts it when `src/` or `Cargo.toml` change. Open
+//! pages refresh then too, as each process has its own reload token.
+//!
+//! Dev mode is ignored when `env` is `production`.
+
+use crate::error::{Error, Result};
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use crate::views::Views;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+pub(crate) const LIVE_RELOAD_PATH: &str = "/__rustyx/livereload";
+pub(crate) const LIVE_RELOAD_SCRIPT_PATH: &str = "/__rustyx/livereload.js";
+
+/// Polls for a new reload token and refreshes the page when it changes;
+/// retries while the server restarts
+pub(crate) const LIVE_RELOAD_SCRIPT: &str = r#"(function () {
+  var token = null;
+  function poll() {
+    fetch("/__rustyx/livereload" + (token === null ? "" : "?since=" + encodeURIComponent(token)))
+      .then(function (res) { return res.json(); })
+      .then(function (body) {
+        if (token !== null && body.token !== token) return location.reload();
+        token = body.token;
+        poll();
+      })
+      .catch(function () { setTimeout(poll, 1000); });
+  }
+  poll();
+})();
+"#;
+
+/// What kind of watched file changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Template,
+    Asset,
+    Config,
+}
+
+/// Files that changed together, passed to [`DevMode::on_change`] hooks
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub paths: Vec<PathBuf>,
+}
+
+type Hook = Arc<dyn Fn(&Change) + Send + Sync>;
+
+/// What [`RustyX::dev`](crate::RustyX::dev) watches and how it reloads
+#[derive(Clone)]
+pub struct DevMode {
+    watched: Vec<(ChangeKind, PathBuf)>,
+    hooks: Vec<Hook>,
+    pub(crate) live_reload: bool,
+    debounce: Duration,
+}
+
+impl std::fmt::Debug for DevMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DevMode")
+            .field("watched", &self.watched)
+            .field("live_reload", &self.live_reload)
+            .field("debounce", &self.debounce)
+            .finish()
+    }
+}
+
+impl Default for DevMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DevMode {
+    pub fn new() -> Self {
+        Self {
+            watched: Vec::new(),
+            hooks: Vec::new(),
+            live_reload: true,
+            debounce: Duration::from_millis(100),
+        }
+    }
+
+    /// Reload the view engine when files under `path` change
+    pub fn templates(self, path: impl Into<PathBuf>) -> Self {
+        self.watch(ChangeKind::Template, path)
+    }
+
+    /// Refresh pages when static files under `path` change
+    pub fn assets(self, path: impl Into<PathBuf>) -> Self {
+        self.watch(ChangeKind::Asset, path)
+    }
+
+    /// Re-read a config file when it changes; `.env` files are loaded into
+    /// the environment
+    pub fn config(self, path: impl Into<PathBuf>) -> Self {
+        self.watch(ChangeKind::Config, path)
+    }
+
+    fn watch(mut self, kind: ChangeKind, path: impl Into<PathBuf>) -> Self {
+        self.watched.push((kind, path.into()));
+        self
+    }
+
+    /// Run `hook` after each change is applied
+    pub fn on_change(mut self, hook: impl Fn(&Change) + Send + Sync + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Inject the live-reload script into HTML responses (default `true`)
+    pub fn live_reload(mut self, enabled: bool) -> Self {
+        self.live_reload = enabled;
+        self
+    }
+
+    /// Wait this long for more events before applying a change (default
+    /// 100ms), as editors often write a file several times
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Start watching, applying changes on a background thread
+    pub(crate) fn start(
+        self,
+        views: Arc<std::sync::RwLock<Option<Arc<Views>>>>,
+    ) -> Result<Arc<LiveReload>> {
+        let reload = Arc::new(LiveReload::new());
+        let mut watched = Vec::new();
+        for (kind, path) in &self.watched {
+            match path.canonicalize() {
+                Ok(path) => watched.push((*kind, path)),
+                Err(e) => tracing::warn!("Not watching {}: {}", path.display(), e),
+            }
+        }
+        let paths = watched.iter().map(|(_, path)| path.clone()).collect();
+
+        let live = Arc::clone(&reload);
+        watch(paths, self.debounce, move |changed| {
+            let mut applied = false;
+            for kind in [ChangeKind::Config, ChangeKind::Template, ChangeKind::Asset] {
+                let paths: Vec<PathBuf> = changed
+                    .iter()
+                    .filter(|path| {
+                        watched
+                            .iter()
+                            .any(|(k, root)| *k == kind && path.starts_with(root))
+                    })
+                    .cloned()
+                    .collect();
+                if paths.is_empty() {
+                    continue;
+                }
+                applied = true;
+                let change = Change { kind, paths };
+                apply(&change, &views);
+                for hook in &self.hooks {
+                    hook(&change);
+                }
+            }
+            if applied {
+                live.bump();
+            }
+        })?;
+        Ok(reload)
+    }
+}
+
+fn apply(change: &Change, views: &std::sync::RwLock<Option<Arc<Views>>>) {
+    match change.kind {
+        ChangeKind::Template => {
+            let views = views.read().unwrap().clone();
+            if let Some(views) = views {
+                match views.reload_engine() {
+                    Ok(()) => tracing::info!("Reloaded templates"),
+                    Err(e) => tracing::error!("Failed to reload templates: {}", e),
+                }
+            }
+        }
+        ChangeKind::Config => {
+            for path in &change.paths {
+                let is_env = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(".env") || name.ends_with(".env"));
+                if is_env {
+                    match dotenvy::from_path_override(path) {
+                        Ok(()) => tracing::info!("Reloaded {}", path.display()),
+                        Err(e) => tracing::error!("Failed to reload {}: {}", path.display(), e),
+                    }
+                }
+            }
+        }
+        ChangeKind::Asset => {}
+    }
+}
+
+/// Watch files and directories on a background thread, calling
+/// `on_change` with the changed paths once events stop arriving for
+/// `debounce`
+pub(crate) fn watch(
+    paths: Vec<PathBuf>,
+    debounce: Duration,
+    mut on_change: impl FnMut(Vec<PathBuf>) + Send + 'static,
+) -> Result<()> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if !matches!(event.kind, EventKind::Access(_)) {
+                let _ = tx.send(event.paths);
+            }
+        }
+    })
+    .map_err(watch_error)?;
+    for path in &paths {
+        // Editors often replace files rather than write them, which ends a
+        // watch on the file itself, so files are watched through their
+        // directory
+        let (path, mode) = match path.parent() {
+            Some(parent) if path.is_file() => (parent, RecursiveMode::NonRecursive),
+            _ => (path.as_path(), RecursiveMode::Recursive),
+        };
+        watcher.watch(path, mode).map_err(watch_error)?;
+    }
+
+    std::thread::Builder::new()
+        .name("rustyx-dev-watch".to_string())
+        .spawn(move || {
+            // The watcher stops when dropped, so the thread keeps it
+            let _watcher = watcher;
+            while let Ok(mut changed) = rx.recv() {
+                while let Ok(more) = rx.recv_timeout(debounce) {
+                    changed.extend(more);
+                }
+                changed.sort();
+                changed.dedup();
+                on_change(changed);
+            }
+        })?;
+    Ok(())
+}
+
+fn watch_error(e: notify::Error) -> Error {
+    Error::Internal(format!("file watcher: {}", e))
+}
+
+/// The token pages poll to learn about changes
+#[derive(Debug)]
+pub(crate) struct LiveReload {
+    boot: String,
+    generation: AtomicU64,
+    token: watch::Sender<String>,
+}
+
+impl LiveReload {
+    fn new() -> Self {
+        let boot = uuid::Uuid::new_v4().simple().to_string();
+        let (token, _) = watch::channel(format!("{}-0", boot));
+        Self {
+            boot,
+            generation: AtomicU64::new(0),
+            token,
+        }
+    }
+
+    pub(crate) fn bump(&self) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.token
+            .send_replace(format!("{}-{}", self.boot, generation));
+    }
+
+    /// The current token, waiting up to `timeout` for a change when it is
+    /// still `since`
+    pub(crate) async fn wait(&self, since: Option<&str>, timeout: Duration) -> String {
+        let mut rx = self.token.subscribe();
+        if since.is_some_and(|since| *rx.borrow() == since) {
+            let _ = tokio::time::timeout(timeout, rx.changed()).await;
+        }
+        let token = rx.borrow().clone();
+        token
+    }
+}
+
+/// Add the live-reload script before `</body>` in HTML responses
+pub(crate) async fn inject_script(req: Request, res: Response, next: Next) -> Response {
+    let res = next(req, res).await;
+    let is_html = res
+        .get_headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if !is_html || res.is_streaming() {
+        return res;
+    }
+    let mut html = res.body_text();
+    let Some(at) = html.rfind("</body>") else {
+        return res;
+    };
+    html.insert_str(
+        at,
+        &format!("<script src=\"{}\"></script>", LIVE_RELOAD_SCRIPT_PATH),
+    );
+    res.send_bytes(html.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn test_dev_mode_reloads_on_change() {
+        let dir = std::env::temp_dir().join(format!("rustyx-dev-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("public")).unwrap();
+        std::fs::write(dir.join(".env"), "RUSTYX_DEV_TEST=one\n").unwrap();
+
+        let changes = Arc::new(AtomicUsize::new(0));
+        let seen = Arc::clone(&changes);
+        let app = RustyX::new();
+        app.get("/", |_req, res| async move {
+            res.html("<html><body>Hi</body></html>")
+        });
+        app.dev(
+            DevMode::new()
+                .assets(dir.join("public"))
+                .config(dir.join(".env"))
+                .on_change(move |change| {
+                    if change.kind == ChangeKind::Config {
+                        seen.fetch_add(1, Ordering::SeqCst);
+                    }
+                }),
+        )
+        .unwrap();
+
+        let res = app.test().get("/").send().await;
+        assert_eq!(
+            res.text(),
+            "<html><body>Hi<script src=\"/__rustyx/livereload.js\"></script></body></html>"
+        );
+        app.test()
+            .get("/__rustyx/livereload.js")
+            .send()
+            .await
+            .assert_header("content-type", "application/javascript");
+        let token = app
+            .test()
+            .get("/__rustyx/livereload")
+            .send()
+            .await
+            .json::<Value>()["token"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let poll = app
+            .test()
+            .get(&format!("/__rustyx/livereload?since={}", token))
+            .send();
+        let write = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            std::fs::write(dir.join(".env"), "RUSTYX_DEV_TEST=two\n").unwrap();
+        };
+        let (res, ()) = tokio::join!(poll, write);
+        assert_ne!(res.json::<Value>()["token"], token);
+        assert_eq!(std::env::var("RUSTYX_DEV_TEST").unwrap(), "two");
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        assert!(app.routes_banner().contains("GET     /\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
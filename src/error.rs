@@ -2,6 +2,10 @@
 //!
 //! Provides error types and Result alias for the framework.
 
+use crate::middleware::sanitize::escape_html;
+use crate::response::Response;
+use crate::upload::UploadError;
+use serde::Serialize;
 use thiserror::Error;
 
 /// Custom error type for RustyX
@@ -25,6 +29,9 @@ pub enum Error {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Validation failed: {}", FieldError::summary(.0))]
+    ValidationFields(Vec<FieldError>),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
@@ -37,6 +44,15 @@ pub enum Error {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("{0}")]
+    Upload(#[from] UploadError),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -45,17 +61,101 @@ pub enum Error {
 
     #[error("{0}")]
     Custom(String),
+
+    /// An error with a description of what was being done, added by
+    /// [`ResultExt::context`]; the status comes from `source`
+    #[error("{message}: {source}")]
+    Context {
+        message: String,
+        #[source]
+        source: Box<Error>,
+        /// Captured when `RUST_BACKTRACE` is set
+        backtrace: Option<String>,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Adds [`context`](ResultExt::context) to results whose error converts
+/// into [`Error`]
+///
+/// ```rust,ignore
+/// let user: User = sql
+///     .fetch_one("SELECT * FROM users WHERE id = ?", params![id])
+///     .await
+///     .context(format!("loading user {}", id))?;
+/// ```
+pub trait ResultExt<T> {
+    /// Wrap the error with a message describing the failed operation
+    fn context(self, message: impl Into<String>) -> Result<T>;
+
+    /// Like [`context`](ResultExt::context), building the message only on error
+    fn with_context<M: Into<String>>(self, message: impl FnOnce() -> M) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context(self, message: impl Into<String>) -> Result<T> {
+        self.map_err(|e| e.into().context(message))
+    }
+
+    fn with_context<M: Into<String>>(self, message: impl FnOnce() -> M) -> Result<T> {
+        self.map_err(|e| e.into().context(message()))
+    }
+}
+
+/// A validation failure on one field
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Path of the field, e.g. `email`, `address.city` or `items[0].sku`
+    pub field: String,
+    /// Machine-readable rule name, e.g. `email` or `length`
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Nest the error under `prefix`: `city` becomes `address.city`, and
+    /// `[0]` or `sku` under `items[0]` become `items[0]` and `items[0].sku`
+    pub fn prefixed(mut self, prefix: &str) -> Self {
+        self.field = if self.field.is_empty() {
+            prefix.to_string()
+        } else if self.field.starts_with('[') {
+            format!("{}{}", prefix, self.field)
+        } else {
+            format!("{}.{}", prefix, self.field)
+        };
+        self
+    }
+
+    fn summary(errors: &[FieldError]) -> String {
+        errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
 impl Error {
     pub fn status_code(&self) -> u16 {
         match self {
+            Error::Context { source, .. } => source.status_code(),
             Error::NotFound(_) => 404,
             Error::Unauthorized(_) => 401,
             Error::Forbidden(_) => 403,
             Error::BadRequest(_) | Error::Validation(_) | Error::ParseError(_) => 400,
+            Error::ValidationFields(_) => 422,
+            Error::Conflict(_) => 409,
+            Error::PayloadTooLarge(_) => 413,
+            Error::Upload(e) => e.status_code(),
             _ => 500,
         }
     }
@@ -72,14 +172,313 @@ impl Error {
     pub fn database(msg: impl Into<String>) -> Self {
         Error::Database(msg.into())
     }
+
+    /// Wrap the error with a message describing the failed operation
+    pub fn context(self, message: impl Into<String>) -> Self {
+        let backtrace = std::backtrace::Backtrace::capture();
+        let backtrace = (backtrace.status() == std::backtrace::BacktraceStatus::Captured)
+            .then(|| backtrace.to_string());
+        Error::Context {
+            message: message.into(),
+            source: Box::new(self),
+            backtrace,
+        }
+    }
+
+    /// The innermost error, below any added context
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// The backtrace captured by the outermost context, if any
+    pub fn backtrace(&self) -> Option<&str> {
+        match self {
+            Error::Context { backtrace, .. } => backtrace.as_deref(),
+            _ => None,
+        }
+    }
 }
 
-impl From<Error> for crate::response::Response {
+/// Missing rows are 404, unique and foreign key violations 409, and
+/// not-null or check violations 400
+///
+/// Constraint violations get a generic message, since the driver's text names
+/// tables and columns; it is logged instead.
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        use sqlx::error::ErrorKind;
+        match &e {
+            sqlx::Error::RowNotFound => Error::NotFound("Record not found".to_string()),
+            sqlx::Error::Database(db) => match db.kind() {
+                ErrorKind::UniqueViolation | ErrorKind::ForeignKeyViolation => {
+                    tracing::debug!("Database conflict: {}", db.message());
+                    Error::Conflict("Record conflicts with existing data".to_string())
+                }
+                ErrorKind::NotNullViolation | ErrorKind::CheckViolation => {
+                    tracing::debug!("Database constraint violation: {}", db.message());
+                    Error::BadRequest("Record violates a constraint".to_string())
+                }
+                _ => Error::Database(e.to_string()),
+            },
+            _ => Error::Database(e.to_string()),
+        }
+    }
+}
+
+/// Duplicate key errors are 409
+#[cfg(feature = "mongodb")]
+impl From<mongodb::error::Error> for Error {
+    fn from(e: mongodb::error::Error) -> Self {
+        use mongodb::error::{ErrorKind, WriteFailure};
+        const DUPLICATE_KEY: i32 = 11000;
+        let duplicate = match e.kind.as_ref() {
+            ErrorKind::Write(WriteFailure::WriteError(error)) => error.code == DUPLICATE_KEY,
+            ErrorKind::BulkWrite(failure) => failure
+                .write_errors
+                .iter()
+                .flatten()
+                .any(|error| error.code == DUPLICATE_KEY),
+            _ => false,
+        };
+        if duplicate {
+            tracing::debug!("Duplicate key: {}", e);
+            Error::Conflict("Record already exists".to_string())
+        } else {
+            Error::Database(e.to_string())
+        }
+    }
+}
+
+impl From<Error> for Response {
     fn from(error: Error) -> Self {
-        crate::response::Response::new()
+        let body = json_body(&error, error.to_string(), None);
+        Response::new()
             .status(error.status_code())
-            .json(serde_json::json!({ "error": error.to_string() }))
+            .json(body)
+            .with_error(error)
+    }
+}
+
+/// How error responses are rendered, set with
+/// [`RustyX::error_format`](crate::app::RustyX::error_format)
+///
+/// Applies to handlers returning `Err`, responses built with
+/// `Response::from(error)`, and panics (rendered as `Error::Internal`).
+/// Unless `env` is `development`, messages of 5xx errors are replaced by
+/// "Internal Server Error". In development, 5xx responses and errors with
+/// [`context`](Error::context) include a `trace` of the error and its
+/// sources, and a `backtrace` when one was captured.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ErrorFormat {
+    /// `{ "error": "...", "errors": [...] }`
+    #[default]
+    Json,
+    /// RFC 9457 `application/problem+json`
+    ProblemJson,
+    /// A minimal HTML error page
+    Html,
+    /// Render with a function
+    Custom(fn(&Error) -> Response),
+}
+
+impl ErrorFormat {
+    /// Render `error`, with internal details only when `development` is set
+    pub fn render(&self, error: &Error, development: bool) -> Response {
+        let status = error.status_code();
+        let message = public_message(error, development);
+        let trace = public_trace(error, development);
+
+        let res = Response::new().status(status);
+        match self {
+            ErrorFormat::Json => res.json(json_body(error, message, trace)),
+            ErrorFormat::ProblemJson => {
+                let title = res.get_status().canonical_reason().unwrap_or("Error");
+                let mut body = serde_json::json!({
+                    "type": "about:blank",
+                    "title": title,
+                    "status": status,
+                    "detail": message,
+                });
+                if let Error::ValidationFields(fields) = error.root() {
+                    body["errors"] = serde_json::json!(fields);
+                }
+                if let Some(trace) = trace {
+                    body["trace"] = serde_json::json!(trace);
+                }
+                res.json(body).content_type("application/problem+json")
+            }
+            ErrorFormat::Html => {
+                let title = format!(
+                    "{} {}",
+                    status,
+                    res.get_status().canonical_reason().unwrap_or("Error")
+                );
+                let mut page = format!(
+                    "<!DOCTYPE html><html><head><title>{title}</title></head><body><h1>{title}</h1><p>{}</p>",
+                    escape_html(&message)
+                );
+                if let Error::ValidationFields(fields) = error.root() {
+                    page.push_str("<ul>");
+                    for field in fields {
+                        page.push_str(&format!(
+                            "<li>{}: {}</li>",
+                            escape_html(&field.field),
+                            escape_html(&field.message)
+                        ));
+                    }
+                    page.push_str("</ul>");
+                }
+                if let Some(trace) = trace {
+                    page.push_str(&format!("<pre>{}</pre>", escape_html(&trace.join("\n"))));
+                }
+                page.push_str("</body></html>");
+                res.html(page)
+            }
+            ErrorFormat::Custom(render) => render(error),
+        }
+    }
+}
+
+/// The message shown to clients, hiding 5xx details outside development
+pub(crate) fn public_message(error: &Error, development: bool) -> String {
+    if !development && error.status_code() >= 500 {
+        "Internal Server Error".to_string()
+    } else {
+        error.to_string()
+    }
+}
+
+/// The trace shown to clients in development, for 5xx errors and errors
+/// with context
+pub(crate) fn public_trace(error: &Error, development: bool) -> Option<Vec<String>> {
+    let chained = matches!(error, Error::Context { .. });
+    (development && (error.status_code() >= 500 || chained)).then(|| error_trace(error))
+}
+
+fn json_body(error: &Error, message: String, trace: Option<Vec<String>>) -> serde_json::Value {
+    let mut body = serde_json::json!({ "error": message });
+    if let Error::ValidationFields(fields) = error.root() {
+        body["errors"] = serde_json::json!(fields);
+    }
+    if let Some(trace) = trace {
+        body["trace"] = serde_json::json!(trace);
     }
+    body
+}
+
+/// The error's chain of contexts and sources, then any captured backtrace
+fn error_trace(error: &Error) -> Vec<String> {
+    let mut trace = Vec::new();
+    let mut current = error;
+    while let Error::Context {
+        message, source, ..
+    } = current
+    {
+        trace.push(message.clone());
+        current = source;
+    }
+    trace.push(format!("{:?}", current));
+    let mut source = std::error::Error::source(current);
+    while let Some(cause) = source {
+        trace.push(cause.to_string());
+        source = cause.source();
+    }
+    if let Some(backtrace) = error.backtrace() {
+        trace.extend(backtrace.lines().map(str::to_string));
+    }
+    trace
 }
 
 // Body collection errors are handled inline where they occur
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_formats() {
+        let error = Error::not_found("user 7");
+        let res = ErrorFormat::ProblemJson.render(&error, false);
+        assert_eq!(res.get_status(), 404);
+        assert_eq!(
+            res.get_headers()["content-type"],
+            "application/problem+json"
+        );
+        let body: serde_json::Value = serde_json::from_slice(res.get_body()).unwrap();
+        assert_eq!(body["title"], "Not Found");
+        assert_eq!(body["detail"], "Not found: user 7");
+
+        let error = Error::Internal("db password is hunter2".to_string());
+        let body: serde_json::Value =
+            serde_json::from_slice(ErrorFormat::Json.render(&error, false).get_body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "error": "Internal Server Error" })
+        );
+        let body: serde_json::Value =
+            serde_json::from_slice(ErrorFormat::Json.render(&error, true).get_body()).unwrap();
+        assert!(body["trace"].is_array());
+
+        let error = Error::not_found("user 7").context("loading profile");
+        assert_eq!(error.status_code(), 404);
+        assert_eq!(error.to_string(), "loading profile: Not found: user 7");
+        let body: serde_json::Value =
+            serde_json::from_slice(ErrorFormat::Json.render(&error, true).get_body()).unwrap();
+        assert_eq!(body["trace"][0], "loading profile");
+        assert_eq!(body["trace"][1], "NotFound(\"user 7\")");
+
+        let res = ErrorFormat::Html.render(&Error::bad_request("<b>"), true);
+        assert!(std::str::from_utf8(res.get_body())
+            .unwrap()
+            .contains("<h1>400 Bad Request</h1><p>Bad request: &lt;b&gt;</p>"));
+
+        let error = Error::from(UploadError::FileTooLarge {
+            max: 10,
+            actual: 20,
+        });
+        assert_eq!(error.status_code(), 413);
+    }
+
+    #[test]
+    fn test_upload_error_status() {
+        let too_large = Error::from(UploadError::FileTooLarge {
+            max: 10,
+            actual: 20,
+        });
+        assert_eq!(too_large.status_code(), 413);
+        let too_many = Error::from(UploadError::TooManyFiles { max: 1, actual: 2 });
+        assert_eq!(too_many.status_code(), 400);
+        let in_field = Error::from(UploadError::in_field(
+            "photos",
+            UploadError::FileTooLarge {
+                max: 10,
+                actual: 20,
+            },
+        ));
+        assert_eq!(in_field.status_code(), 413);
+    }
+
+    #[cfg(feature = "mongodb")]
+    #[test]
+    fn test_mongodb_duplicate_key() {
+        use mongodb::error::{ErrorKind, WriteError, WriteFailure};
+
+        let write_error = |code: i32| {
+            let error: WriteError = mongodb::bson::from_document(mongodb::bson::doc! {
+                "code": code,
+                "errmsg": "E11000 duplicate key error collection: app.users index: email_1",
+            })
+            .unwrap();
+            mongodb::error::Error::from(ErrorKind::Write(WriteFailure::WriteError(error)))
+        };
+
+        let error = Error::from(write_error(11000));
+        assert_eq!(error.status_code(), 409);
+        assert!(!error.to_string().contains("email_1"));
+        assert_eq!(Error::from(write_error(121)).status_code(), 500);
+    }
+}
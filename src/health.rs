@@ -0,0 +1,276 @@
+//! Health Checks
+//!
+//! Liveness and readiness endpoints for load balancers and Kubernetes
+//! probes. Every check runs concurrently under a timeout and reports its
+//! status and latency; the endpoint answers `200` when all pass and `503`
+//! otherwise.
+//!
+//! ```rust,ignore
+//! app.health(
+//!     "/healthz",
+//!     HealthChecks::new()
+//!         .database("default")
+//!         .disk_space("/var/data", 1 << 30)
+//!         .check("queue", || async { queue.ping().await }),
+//! );
+//! ```
+//!
+//! - `GET /healthz/live` runs only the checks added with
+//!   [`HealthChecks::liveness`], answering `200` while the process can serve
+//! - `GET /healthz/ready` and `GET /healthz` run every check
+//!
+//! ```json
+//! {
+//!   "status": "down",
+//!   "checks": {
+//!     "database:default": { "status": "up", "latency_ms": 2 },
+//!     "disk:/var/data": { "status": "down", "latency_ms": 0, "error": "..." }
+//!   }
+//! }
+//! ```
+
+use crate::error::{Error, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type ProbeFn = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+
+struct Check {
+    name: String,
+    liveness: bool,
+    probe: ProbeFn,
+}
+
+/// Outcome of a check or of all checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// Result of one check
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of running a set of checks
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub checks: BTreeMap<String, CheckReport>,
+}
+
+/// Probes served by [`RustyX::health`](crate::RustyX::health)
+#[derive(Clone)]
+pub struct HealthChecks {
+    checks: Vec<Arc<Check>>,
+    timeout: Duration,
+}
+
+impl std::fmt::Debug for HealthChecks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HealthChecks")
+            .field(
+                "checks",
+                &self.checks.iter().map(|c| &c.name).collect::<Vec<_>>(),
+            )
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Default for HealthChecks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthChecks {
+    pub fn new() -> Self {
+        Self {
+            checks: Vec::new(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Fail checks that take longer than `timeout` (default 5 seconds)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Add a readiness check
+    pub fn check<F, Fut>(self, name: &str, probe: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.push(name, false, probe)
+    }
+
+    /// Add a check that also runs for liveness, for failures only a
+    /// restart fixes
+    pub fn liveness<F, Fut>(self, name: &str, probe: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.push(name, true, probe)
+    }
+
+    fn push<F, Fut>(mut self, name: &str, liveness: bool, probe: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.checks.push(Arc::new(Check {
+            name: name.to_string(),
+            liveness,
+            probe: Arc::new(move || Box::pin(probe())),
+        }));
+        self
+    }
+
+    /// Ping a connection registered with
+    /// [`init_db`](crate::db::connection::init_db) or
+    /// [`add_connection`](crate::db::connection::add_connection)
+    pub fn database(self, connection: &str) -> Self {
+        let name = connection.to_string();
+        self.check(&format!("database:{}", connection), move || {
+            let name = name.clone();
+            async move { crate::db::connection::db(&name)?.ping().await }
+        })
+    }
+
+    /// Ping a Redis server
+    #[cfg(feature = "redis")]
+    pub fn redis(self, client: crate::db::redis::RedisClient) -> Self {
+        self.check("redis", move || {
+            let client = client.clone();
+            async move { client.ping().await }
+        })
+    }
+
+    /// Require at least `min_free` bytes available on the filesystem
+    /// holding `path`
+    pub fn disk_space(self, path: impl Into<PathBuf>, min_free: u64) -> Self {
+        let path = path.into();
+        self.check(&format!("disk:{}", path.display()), move || {
+            let path = path.clone();
+            async move {
+                let available = tokio::task::spawn_blocking(move || fs2::available_space(&path))
+                    .await
+                    .map_err(|e| Error::Internal(e.to_string()))??;
+                if available < min_free {
+                    return Err(Error::Internal(format!(
+                        "{} bytes free, {} required",
+                        available, min_free
+                    )));
+                }
+                Ok(())
+            }
+        })
+    }
+
+    /// Run the checks concurrently, only liveness checks when `liveness`
+    pub async fn run(&self, liveness: bool) -> HealthReport {
+        let runs = self
+            .checks
+            .iter()
+            .filter(|check| !liveness || check.liveness)
+            .map(|check| {
+                let check = Arc::clone(check);
+                let timeout = self.timeout;
+                async move {
+                    let started = Instant::now();
+                    let outcome = match tokio::time::timeout(timeout, (check.probe)()).await {
+                        Ok(outcome) => outcome,
+                        Err(_) => Err(Error::Internal(format!("timed out after {:?}", timeout))),
+                    };
+                    let report = CheckReport {
+                        status: if outcome.is_ok() {
+                            HealthStatus::Up
+                        } else {
+                            HealthStatus::Down
+                        },
+                        latency_ms: started.elapsed().as_millis() as u64,
+                        error: outcome.err().map(|e| e.to_string()),
+                    };
+                    (check.name.clone(), report)
+                }
+            });
+        let checks: BTreeMap<_, _> = futures::future::join_all(runs).await.into_iter().collect();
+        let status = if checks.values().all(|c| c.status == HealthStatus::Up) {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        };
+        HealthReport { status, checks }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[tokio::test]
+    async fn test_health_endpoints() {
+        let ready = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&ready);
+        let app = RustyX::new();
+        app.health(
+            "/healthz",
+            HealthChecks::new()
+                .timeout(Duration::from_millis(50))
+                .liveness("process", || async { Ok(()) })
+                .check("warmup", move || {
+                    let ready = flag.load(Ordering::SeqCst);
+                    async move {
+                        if ready {
+                            Ok(())
+                        } else {
+                            Err(Error::Internal("warming up".to_string()))
+                        }
+                    }
+                })
+                .check("slow", || async {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(())
+                })
+                .disk_space(".", 1),
+        );
+
+        let live = app.test().get("/healthz/live").send().await;
+        live.assert_status(200).assert_json_field("status", "up");
+        assert_eq!(live.json::<Value>()["checks"].as_object().unwrap().len(), 1);
+
+        let res = app.test().get("/healthz/ready").send().await;
+        res.assert_status(503).assert_json_field("status", "down");
+        let body: Value = res.json();
+        assert_eq!(
+            body["checks"]["warmup"]["error"],
+            "Internal error: warming up"
+        );
+        assert!(body["checks"]["slow"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("timed out"));
+        assert_eq!(body["checks"]["disk:."]["status"], "up");
+
+        ready.store(true, Ordering::SeqCst);
+        let res = app.test().get("/healthz").send().await;
+        assert_eq!(res.json::<Value>()["checks"]["warmup"]["status"], "up");
+    }
+}
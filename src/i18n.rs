@@ -0,0 +1,570 @@
+//! Internationalization Module
+//!
+//! Message catalogs and the per-request locale resolved by the
+//! [`locale`](mod@crate::middleware::locale) middleware.
+//!
+//! Messages interpolate `{name}` arguments. A message given as an object of
+//! CLDR plural categories is picked by the `count` argument:
+//!
+//! ```json
+//! { "cart": { "items": { "one": "{count} item", "other": "{count} items" } } }
+//! ```
+//!
+//! ```rust,ignore
+//! app.get("/cart", |req, res| async move {
+//!     res.send(t!("cart.items", count = cart.len()))
+//! });
+//! ```
+//!
+//! With the `fluent` feature, catalogs also load Fluent (`.ftl`) files,
+//! whose messages take precedence for the same locale.
+
+use crate::error::{Error, Result};
+use intl_pluralrules::{PluralCategory, PluralRuleType, PluralRules};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use unic_langid::LanguageIdentifier;
+
+/// Locale used when nothing else matches
+pub const DEFAULT_LOCALE: &str = "en";
+
+/// Locale resolved for the current request (stored in request extensions)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale(pub String);
+
+/// Message catalog shared with handlers (stored in request extensions)
+#[derive(Debug, Clone)]
+pub struct SharedCatalog(pub Arc<Catalog>);
+
+/// Translation catalog keyed by locale and message key
+///
+/// Nested JSON objects are flattened into dotted keys, so
+/// `{"auth": {"login": "Log in"}}` is looked up as `auth.login`.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    messages: HashMap<String, HashMap<String, String>>,
+    fallback: String,
+    /// Locales tried after a locale, before its primary language
+    chains: HashMap<String, Vec<String>>,
+    #[cfg(feature = "fluent")]
+    fluent: fluent::Bundles,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOCALE)
+    }
+}
+
+impl Catalog {
+    /// Create an empty catalog with a fallback locale
+    pub fn new(fallback: &str) -> Self {
+        Self {
+            messages: HashMap::new(),
+            fallback: normalize(fallback),
+            chains: HashMap::new(),
+            #[cfg(feature = "fluent")]
+            fluent: fluent::Bundles::default(),
+        }
+    }
+
+    /// Try `chain` after `locale`, e.g. `pt-BR` -> `pt-PT` -> `es`
+    ///
+    /// The primary language and the catalog fallback are still tried last.
+    pub fn with_fallbacks(mut self, locale: &str, chain: &[&str]) -> Self {
+        self.chains.insert(
+            normalize(locale),
+            chain.iter().map(|l| normalize(l)).collect(),
+        );
+        self
+    }
+
+    /// Load every `<locale>.json` file in a directory, and `<locale>.ftl`
+    /// with the `fluent` feature
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // locales/en.json, locales/fr.json, ...
+    /// let catalog = Catalog::load_dir("locales", "en")?;
+    /// ```
+    pub fn load_dir(dir: impl AsRef<Path>, fallback: &str) -> Result<Self> {
+        let mut catalog = Self::new(fallback);
+
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(locale) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            match path.extension().and_then(|e| e.to_str()) {
+                Some("json") => {
+                    let value: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+                    catalog.add_json(locale, &value)?;
+                }
+                #[cfg(feature = "fluent")]
+                Some("ftl") => catalog.add_ftl(locale, &std::fs::read_to_string(&path)?)?,
+                _ => {}
+            }
+        }
+
+        Ok(catalog)
+    }
+
+    /// Add messages for a locale from a JSON object
+    pub fn add_json(&mut self, locale: &str, value: &Value) -> Result<()> {
+        if !value.is_object() {
+            return Err(Error::ParseError(format!(
+                "Catalog for '{}' must be a JSON object",
+                locale
+            )));
+        }
+
+        let messages = self.messages.entry(normalize(locale)).or_default();
+        flatten(value, "", messages);
+        Ok(())
+    }
+
+    /// Add a single message
+    pub fn insert(&mut self, locale: &str, key: &str, message: &str) {
+        self.messages
+            .entry(normalize(locale))
+            .or_default()
+            .insert(key.to_string(), message.to_string());
+    }
+
+    /// Add a single message (builder style)
+    pub fn with(mut self, locale: &str, key: &str, message: &str) -> Self {
+        self.insert(locale, key, message);
+        self
+    }
+
+    /// Get the fallback locale
+    pub fn fallback(&self) -> &str {
+        &self.fallback
+    }
+
+    /// Get all locales with messages
+    pub fn locales(&self) -> Vec<&str> {
+        self.messages.keys().map(String::as_str).collect()
+    }
+
+    /// Add messages for a locale from Fluent source
+    #[cfg(feature = "fluent")]
+    pub fn add_ftl(&mut self, locale: &str, source: &str) -> Result<()> {
+        self.fluent.add(&normalize(locale), source)
+    }
+
+    /// Locales tried for `locale`: itself and its configured chain, its
+    /// primary language and that language's chain, then the fallback
+    fn chain(&self, locale: &str) -> Vec<String> {
+        let locale = normalize(locale);
+        let primary = locale.split('-').next().unwrap_or(&locale).to_string();
+        let mut chain: Vec<String> = Vec::new();
+        for tag in [&locale, &primary] {
+            let configured = self.chains.get(tag).into_iter().flatten();
+            for l in std::iter::once(tag).chain(configured) {
+                if !chain.contains(l) {
+                    chain.push(l.clone());
+                }
+            }
+        }
+        if !chain.contains(&self.fallback) {
+            chain.push(self.fallback.clone());
+        }
+        chain
+    }
+
+    /// Look up a message along the fallback chain of `locale`
+    pub fn translate(&self, locale: &str, key: &str) -> Option<&str> {
+        self.chain(locale)
+            .iter()
+            .find_map(|l| self.messages.get(l).and_then(|m| m.get(key)))
+            .map(String::as_str)
+    }
+
+    /// Look up and format a message with arguments
+    ///
+    /// A numeric `count` argument selects the plural form (`key.one`,
+    /// `key.few`, ..., `key.other`) using the rules of the locale the
+    /// message was found in.
+    pub fn format(&self, locale: &str, key: &str, args: &[(&str, Value)]) -> Option<String> {
+        let count = args
+            .iter()
+            .find(|(name, _)| *name == "count")
+            .map(|(_, value)| value)
+            .filter(|value| value.is_number());
+
+        for l in self.chain(locale) {
+            #[cfg(feature = "fluent")]
+            if let Some(message) = self.fluent.format(&l, key, args) {
+                return Some(message);
+            }
+            let Some(messages) = self.messages.get(&l) else {
+                continue;
+            };
+            let plural = count.and_then(|count| {
+                let form = plural_category(&l, count);
+                messages
+                    .get(&format!("{}.{}", key, form))
+                    .or_else(|| messages.get(&format!("{}.other", key)))
+            });
+            if let Some(message) = plural.or_else(|| messages.get(key)) {
+                return Some(interpolate(message, args));
+            }
+        }
+        None
+    }
+}
+
+/// Replace `{name}` placeholders with arguments, leaving unknown ones as is
+pub fn interpolate(message: &str, args: &[(&str, Value)]) -> String {
+    if args.is_empty() {
+        return message.to_string();
+    }
+    let mut out = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let name = after[..end].trim();
+            args.iter()
+                .find(|(arg, _)| *arg == name)
+                .map(|(_, value)| (value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                match value {
+                    Value::String(s) => out.push_str(s),
+                    other => out.push_str(&other.to_string()),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// CLDR cardinal plural category of `count` in `locale`, e.g. `one`
+pub fn plural_category(locale: &str, count: &Value) -> &'static str {
+    let rules = locale
+        .parse::<LanguageIdentifier>()
+        .ok()
+        .and_then(|id| PluralRules::create(id, PluralRuleType::CARDINAL).ok());
+    let category = match (rules, count.as_i64()) {
+        (Some(rules), Some(n)) => rules.select(n).ok(),
+        (Some(rules), None) => count.as_f64().and_then(|n| rules.select(n).ok()),
+        (None, n) => Some(if n == Some(1) {
+            PluralCategory::ONE
+        } else {
+            PluralCategory::OTHER
+        }),
+    };
+    match category.unwrap_or(PluralCategory::OTHER) {
+        PluralCategory::ZERO => "zero",
+        PluralCategory::ONE => "one",
+        PluralCategory::TWO => "two",
+        PluralCategory::FEW => "few",
+        PluralCategory::MANY => "many",
+        PluralCategory::OTHER => "other",
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: (String, Option<Arc<Catalog>>);
+}
+
+/// Run `future` with a locale and catalog for [`t!`](crate::t)
+///
+/// The [`locale`](mod@crate::middleware::locale) middleware does this for
+/// each request.
+pub async fn scope<F: std::future::Future>(
+    locale: String,
+    catalog: Option<Arc<Catalog>>,
+    future: F,
+) -> F::Output {
+    CURRENT.scope((locale, catalog), future).await
+}
+
+/// Translate `key` in the current request's locale, returning the key
+/// itself when no catalog is in scope or the key is missing
+///
+/// Used by [`t!`](crate::t).
+pub fn translate(key: &str, args: &[(&str, Value)]) -> String {
+    CURRENT
+        .try_with(|(locale, catalog)| {
+            catalog
+                .as_ref()
+                .and_then(|catalog| catalog.format(locale, key, args))
+        })
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Translate a message key into the locale resolved by the
+/// [`locale`](mod@crate::middleware::locale) middleware
+///
+/// Takes `name = value` arguments for `{name}` placeholders; a numeric
+/// `count` also selects the plural form. Works in handlers and the
+/// middleware after `locale`, but not in spawned tasks.
+///
+/// ```rust,ignore
+/// res.send(t!("home.welcome"));
+/// res.send(t!("greeting", name = user.name));
+/// res.send(t!("cart.items", count = cart.len()));
+/// ```
+#[macro_export]
+macro_rules! t {
+    ($key:expr $(,)?) => {
+        $crate::i18n::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::translate(
+            $key,
+            &[$((stringify!($name), $crate::__serde_json::json!($value))),+],
+        )
+    };
+}
+
+#[cfg(feature = "fluent")]
+mod fluent {
+    use super::*;
+    use fluent_bundle::concurrent::FluentBundle;
+    use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+
+    type Bundle = FluentBundle<Arc<FluentResource>>;
+
+    /// Fluent bundles by locale, rebuilt when resources are added
+    #[derive(Clone, Default)]
+    pub(super) struct Bundles {
+        resources: HashMap<String, Vec<Arc<FluentResource>>>,
+        bundles: HashMap<String, Arc<Bundle>>,
+    }
+
+    impl std::fmt::Debug for Bundles {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Bundles")
+                .field("locales", &self.bundles.keys().collect::<Vec<_>>())
+                .finish()
+        }
+    }
+
+    impl Bundles {
+        pub(super) fn add(&mut self, locale: &str, source: &str) -> Result<()> {
+            let resource = FluentResource::try_new(source.to_string()).map_err(|(_, errors)| {
+                Error::ParseError(format!(
+                    "Invalid Fluent catalog for '{}': {:?}",
+                    locale, errors
+                ))
+            })?;
+            let id: LanguageIdentifier = locale
+                .parse()
+                .map_err(|_| Error::ParseError(format!("Invalid locale '{}'", locale)))?;
+
+            let resources = self.resources.entry(locale.to_string()).or_default();
+            resources.push(Arc::new(resource));
+            let mut bundle = Bundle::new_concurrent(vec![id]);
+            bundle.set_use_isolating(false);
+            for resource in resources.iter() {
+                bundle.add_resource_overriding(Arc::clone(resource));
+            }
+            self.bundles.insert(locale.to_string(), Arc::new(bundle));
+            Ok(())
+        }
+
+        pub(super) fn format(
+            &self,
+            locale: &str,
+            key: &str,
+            args: &[(&str, Value)],
+        ) -> Option<String> {
+            let bundle = self.bundles.get(locale)?;
+            let pattern = bundle.get_message(key)?.value()?;
+            let mut fluent_args = FluentArgs::new();
+            for (name, value) in args {
+                let value = match value {
+                    Value::Number(n) => match n.as_i64() {
+                        Some(n) => FluentValue::from(n),
+                        None => FluentValue::from(n.as_f64().unwrap_or_default()),
+                    },
+                    Value::String(s) => FluentValue::from(s.clone()),
+                    other => FluentValue::from(other.to_string()),
+                };
+                fluent_args.set(*name, value);
+            }
+            let mut errors = Vec::new();
+            let message = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+            Some(message.into_owned())
+        }
+    }
+}
+
+/// Normalize a language tag (`en_us` -> `en-US`)
+pub fn normalize(tag: &str) -> String {
+    let mut parts = tag.trim().split(['-', '_']);
+    let mut normalized = parts.next().unwrap_or("").to_lowercase();
+
+    for part in parts {
+        normalized.push('-');
+        if part.len() == 2 {
+            normalized.push_str(&part.to_uppercase());
+        } else {
+            normalized.push_str(part);
+        }
+    }
+
+    normalized
+}
+
+/// Flatten nested JSON objects into dotted keys
+fn flatten(value: &Value, prefix: &str, out: &mut HashMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(item, &key, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        Value::Null => {}
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_catalog_fallback_chain() {
+        let mut catalog = Catalog::new("en");
+        catalog
+            .add_json(
+                "en",
+                &json!({ "auth": { "login": "Log in", "logout": "Log out" } }),
+            )
+            .unwrap();
+        catalog
+            .add_json("fr", &json!({ "auth": { "login": "Connexion" } }))
+            .unwrap();
+
+        assert_eq!(catalog.translate("fr-CA", "auth.login"), Some("Connexion"));
+        assert_eq!(catalog.translate("fr", "auth.logout"), Some("Log out"));
+        assert_eq!(catalog.translate("de", "auth.login"), Some("Log in"));
+        assert_eq!(catalog.translate("en", "missing"), None);
+        assert_eq!(normalize("pt_br"), "pt-BR");
+    }
+
+    #[test]
+    fn test_format_plurals_and_chains() {
+        let mut catalog = Catalog::new("en").with_fallbacks("pt-BR", &["pt-PT"]);
+        catalog
+            .add_json(
+                "en",
+                &json!({
+                    "greeting": "Hello {name}!",
+                    "cart": { "items": { "one": "{count} item", "other": "{count} items" } },
+                }),
+            )
+            .unwrap();
+        catalog
+            .add_json(
+                "ru",
+                &json!({ "files": {
+                    "one": "{count} файл", "few": "{count} файла", "many": "{count} файлов",
+                } }),
+            )
+            .unwrap();
+        catalog.insert("pt-PT", "bye", "Adeus");
+
+        let args = |count: i64| [("count", json!(count))];
+        assert_eq!(
+            catalog.format("en", "cart.items", &args(1)).unwrap(),
+            "1 item"
+        );
+        assert_eq!(
+            catalog.format("en", "cart.items", &args(5)).unwrap(),
+            "5 items"
+        );
+        assert_eq!(catalog.format("ru", "files", &args(3)).unwrap(), "3 файла");
+        assert_eq!(
+            catalog.format("ru", "files", &args(11)).unwrap(),
+            "11 файлов"
+        );
+        assert_eq!(
+            catalog
+                .format("de", "greeting", &[("name", json!("Ann"))])
+                .unwrap(),
+            "Hello Ann!"
+        );
+        assert_eq!(
+            interpolate("{missing} {", &[("x", json!(1))]),
+            "{missing} {"
+        );
+        assert_eq!(catalog.translate("pt-BR", "bye"), Some("Adeus"));
+        assert_eq!(catalog.translate("pt", "bye"), None);
+    }
+
+    #[tokio::test]
+    async fn test_t_macro_uses_request_locale() {
+        use crate::middleware::{locale, LocaleOptions};
+        use crate::prelude::*;
+
+        let catalog = Catalog::new("en")
+            .with("en", "greeting", "Hello {name}")
+            .with("fr", "greeting", "Bonjour {name}");
+        let app = RustyX::new();
+        app.use_middleware(locale(
+            LocaleOptions::new(vec!["en", "fr"]).catalog(catalog),
+        ));
+        app.get("/", |_req, res| async move {
+            let name = "Ann".to_string();
+            res.send(t!("greeting", name = name))
+        });
+
+        let res = app.test().get("/?lang=fr").send().await;
+        assert_eq!(res.text(), "Bonjour Ann");
+        assert_eq!(t!("greeting"), "greeting");
+    }
+
+    #[cfg(feature = "fluent")]
+    #[test]
+    fn test_fluent_catalog() {
+        let mut catalog = Catalog::new("en");
+        catalog
+            .add_ftl(
+                "en",
+                "emails = { $count ->\n    [one] One email\n   *[other] { $count } emails\n}\n",
+            )
+            .unwrap();
+        assert_eq!(
+            catalog
+                .format("en-GB", "emails", &[("count", json!(1))])
+                .unwrap(),
+            "One email"
+        );
+        assert_eq!(
+            catalog
+                .format("en", "emails", &[("count", json!(4))])
+                .unwrap(),
+            "4 emails"
+        );
+    }
+}
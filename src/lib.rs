@@ -104,6 +104,7 @@
 //! - `req.param("name")` - URL parameters
 //! - `req.query_param("key")` - Query parameters
 //! - `req.json::<T>()` - Parse JSON body
+//! - `req.validated_json::<T>()` - Parse and validate JSON body
 //! - `req.header("name")` - Get header value
 //! - `req.bearer_token()` - Extract Bearer token
 //! - `req.ip()` - Client IP address
@@ -141,30 +142,13 @@
 //!     app.post("/upload", move |req, res| {
 //!         let uploader = uploader.clone();
 //!         async move {
-//!             // Parse multipart form data
-//!             let content_type = req.content_type().unwrap_or_default();
-//!             let boundary = parse_boundary(&content_type).unwrap();
-//!             let fields = parse_multipart(req.body(), &boundary).unwrap();
-//!             
-//!             for field in fields {
-//!                 if let Some(filename) = field.filename {
-//!                     let result = uploader.upload_single(
-//!                         &field.name,
-//!                         field.data,
-//!                         &filename,
-//!                         &field.content_type.unwrap_or_default()
-//!                     ).await;
-//!                     
-//!                     match result {
-//!                         Ok(file) => return res.json(json!({
-//!                             "filename": file.filename,
-//!                             "size": file.size
-//!                         })),
-//!                         Err(e) => return res.bad_request(&e.to_string())
-//!                     }
-//!                 }
+//!             match uploader.single(&req, "file").await {
+//!                 Ok(file) => res.json(json!({
+//!                     "filename": file.filename,
+//!                     "size": file.size
+//!                 })),
+//!                 Err(e) => res.bad_request(&e.to_string()),
 //!             }
-//!             res.bad_request("No file provided")
 //!         }
 //!     });
 //!
@@ -205,6 +189,10 @@
 //! | `postgres` | PostgreSQL database support |
 //! | `sqlite` | SQLite database support |
 //! | `mongodb` | MongoDB database support |
+//! | `redis` | Redis-backed response cache store |
+//! | `oauth` | OAuth2 / OpenID Connect login |
+//! | `s3` | S3-compatible upload storage |
+//! | `image` | Image processing for uploads |
 //! | `full` | All database drivers enabled |
 //!
 //! ## Modules
@@ -218,36 +206,73 @@
 //! - [`db`] - Database integration
 //! - [`websocket`] - WebSocket support
 //! - [`static_files`] - Static file serving
+//! - [`i18n`] - Locales and message catalogs
+//! - [`tenancy`] - Tenant resolution and per-tenant databases
+//! - `oauth` - OAuth2 / OpenID Connect login (feature `oauth`)
+//! - `mail` - Email over SMTP with templated bodies (feature `mail`)
+//! - `webhooks` - Signed outgoing webhooks (feature `webhooks`)
 
 #![doc(html_root_url = "https://docs.rs/rustyx/0.2.0")]
 #![allow(missing_docs)] // TODO: Add docs for all public items before 1.0
 #![warn(rustdoc::missing_crate_level_docs)]
 
 pub mod app;
+pub mod cache;
+#[cfg(feature = "cli")]
+pub mod cli;
 pub mod controllers;
 pub mod db;
+#[cfg(feature = "dev")]
+pub mod dev;
 pub mod error;
+pub mod health;
+pub mod i18n;
+#[cfg(feature = "mail")]
+pub mod mail;
+pub mod metrics;
 pub mod middleware;
 pub mod models;
+#[cfg(feature = "oauth")]
+pub mod oauth;
+pub mod openapi;
 pub mod request;
 pub mod response;
 pub mod router;
 pub mod routes;
 pub mod static_files;
+pub mod tenancy;
+pub mod testing;
 pub mod upload;
 pub mod utils;
+pub mod views;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
 pub mod websocket;
 
 // Re-exports for convenience
+pub use app::config::AppConfig;
 pub use app::RustyX;
-pub use error::{Error, Result};
-pub use middleware::{Middleware, MiddlewareFn, Next};
-pub use request::Request;
-pub use response::Response;
-pub use router::Router;
+pub use cache::Cache;
+pub use error::{Error, ErrorFormat, FieldError, Result, ResultExt};
+pub use health::HealthChecks;
+pub use metrics::Metrics;
+pub use middleware::{from_middleware, Middleware, MiddlewareFn, MiddlewareGroup, Next};
+pub use openapi::{OpenApi, Operation, ToSchema};
+pub use request::{Request, RequestBuilder};
+pub use response::{IntoResponse, Response, Upstream};
+pub use router::{MatchedRoute, Router};
 pub use static_files::{static_handler, StaticConfig};
 pub use upload::{UploadConfig, UploadedFile, Uploader};
-pub use websocket::{WsMessage, WsRoom, WsServer};
+pub use views::{ViewEngine, Views};
+pub use websocket::{WsConfig, WsConn, WsHandler, WsMessage, WsRoom, WsServer};
+
+// Lets `#[derive(Model)]` output, which uses `::rustyx::` paths, compile in this crate
+extern crate self as rustyx;
+
+#[doc(hidden)]
+pub use chrono as __chrono;
+#[doc(hidden)]
+pub use serde_json as __serde_json;
 
 /// Prelude module for convenient imports.
 ///
@@ -266,24 +291,37 @@ pub use websocket::{WsMessage, WsRoom, WsServer};
 /// - Tracing macros
 pub mod prelude {
     pub use crate::app::RustyX;
-    pub use crate::controllers::Controller;
+    pub use crate::cache::Cache;
+    pub use crate::controllers::{Controller, ResourceAction, ResourceController};
     pub use crate::db::prelude::*;
-    pub use crate::error::{Error, Result};
+    pub use crate::error::{Error, ErrorFormat, Result, ResultExt};
+    pub use crate::health::HealthChecks;
+    pub use crate::metrics::Metrics;
     pub use crate::middleware::{
-        cors, cors_with_options, helmet, json, logger, rate_limiter, request_id, response_time,
-        simple_rate_limit, timeout, CorsOptions, JsonOptions, Middleware, MiddlewareFn, Next,
-        RateLimiterConfig,
+        authorize, cache, cors, cors_with_options, etag, from_middleware, helmet, json, jwt_auth,
+        locale, logger, only, rate_limiter, request_id, response_time, sanitize, simple_rate_limit,
+        tenancy, timeout, unless, validate_json, Authorize, CacheConfig, CorsOptions, JsonOptions,
+        LocaleOptions, Middleware, MiddlewareExt, MiddlewareFn, MiddlewareGroup, Next, Principal,
+        RateLimiterConfig, TenancyOptions,
     };
-    pub use crate::models::Model;
+    pub use crate::models::{Model, Validate};
+    pub use crate::openapi::{OpenApi, Operation, ToSchema};
     pub use crate::request::Request;
-    pub use crate::response::{CookieOptions, Response};
+    pub use crate::response::{CookieOptions, IntoResponse, Response};
     pub use crate::router::Router;
     pub use crate::static_files::{static_handler, StaticConfig};
+    pub use crate::t;
+    pub use crate::tenancy::{Tenant, TenantStorage, Tenants};
     pub use crate::upload::{
         parse_boundary, parse_multipart, FileNaming, MultipartField, StorageType, UploadConfig,
         UploadError, UploadedFile, Uploader,
     };
-    pub use crate::websocket::{WsMessage, WsRoom, WsServer};
+    pub use crate::utils::jwt::{Claims, Jwt, JwtKey};
+    pub use crate::views::{ViewEngine, Views};
+    pub use crate::websocket::{
+        CloseFrame, ConnectionId, WsConfig, WsConn, WsEvents, WsHandler, WsMessage, WsPresence,
+        WsRoom, WsServer,
+    };
     pub use async_trait::async_trait;
     pub use serde::{Deserialize, Serialize};
     pub use serde_json::{json, Value};
@@ -1,11 +1,22 @@
 //! RustyX - Main Entry
 //!
-//! This is the main entry point for development/testing.
+//! This is the main entry point for development/testing. Built with the
+//! `cli` feature it is the `rustyx` command-line tool instead.
 
-use rustyx::prelude::*;
+#[cfg(feature = "cli")]
+#[tokio::main]
+async fn main() {
+    if let Err(e) = rustyx::cli::run(std::env::args().skip(1)).await {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
 
+#[cfg(not(feature = "cli"))]
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> rustyx::Result<()> {
+    use rustyx::prelude::*;
+
     // Initialize logging
     tracing_subscriber::fmt().with_env_filter("info").init();
 
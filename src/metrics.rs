@@ -0,0 +1,565 @@
+//! Metrics Registry
+//!
+//! Counters, gauges and histograms exposed in the Prometheus text format.
+//! The registry is process-wide: handlers record into it through
+//! [`RustyX::metrics`](crate::RustyX::metrics) and the framework reports
+//! HTTP requests, SQL queries and uploads into the same registry.
+//!
+//! ```rust,ignore
+//! let metrics = app.metrics();
+//! metrics.describe("jobs_processed", "Background jobs completed");
+//!
+//! app.post("/jobs", move |req, res| {
+//!     let processed = metrics.counter_with("jobs_processed", &[("queue", "mail")]);
+//!     async move {
+//!         run_job(req).await?;
+//!         processed.inc();
+//!         Ok::<_, Error>(res.status(202))
+//!     }
+//! });
+//!
+//! app.metrics_route("/metrics");
+//! ```
+//!
+//! Built-in series:
+//!
+//! | Name | Type | Labels |
+//! |------|------|--------|
+//! | `rustyx_http_requests_total` | counter | `method`, `status` |
+//! | `rustyx_http_request_duration_seconds` | histogram | `method` |
+//! | `rustyx_db_queries_total` | counter | `operation`, `status` |
+//! | `rustyx_db_query_duration_seconds` | histogram | `operation` |
+//! | `rustyx_uploads_total` | counter | `status` |
+//! | `rustyx_upload_bytes_total` | counter | |
+//!
+//! The app's WebSocket server metrics (`rustyx_ws_*`) are appended to the
+//! exposition.
+
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default histogram buckets in seconds, suited to request latencies
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static GLOBAL: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+type Labels = Vec<(String, String)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl Kind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram => "histogram",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Series {
+    Counter(Counter),
+    Gauge(Gauge),
+    Histogram(Histogram),
+}
+
+#[derive(Debug)]
+struct Family {
+    kind: Kind,
+    buckets: Vec<f64>,
+    series: BTreeMap<Labels, Series>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    help: BTreeMap<String, String>,
+    families: BTreeMap<String, Family>,
+}
+
+/// A registry of metrics
+///
+/// Cloning is cheap and shares the registry. Asking for a metric that
+/// already exists returns the same series, so handles can be looked up
+/// where they're needed rather than passed around.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    inner: Arc<RwLock<Registry>>,
+}
+
+impl Metrics {
+    /// An empty registry, separate from [`Metrics::global`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The process-wide registry the framework reports into
+    pub fn global() -> &'static Metrics {
+        &GLOBAL
+    }
+
+    /// Set the `# HELP` text for a metric
+    pub fn describe(&self, name: &str, help: &str) -> &Self {
+        self.inner
+            .write()
+            .help
+            .insert(name.to_string(), help.to_string());
+        self
+    }
+
+    /// A counter without labels
+    pub fn counter(&self, name: &str) -> Counter {
+        self.counter_with(name, &[])
+    }
+
+    /// The counter for a set of labels
+    ///
+    /// # Panics
+    ///
+    /// When `name` or a label name isn't a valid Prometheus name, or
+    /// `name` is already registered as another type.
+    pub fn counter_with(&self, name: &str, labels: &[(&str, &str)]) -> Counter {
+        match self.series(name, labels, Kind::Counter, &[]) {
+            Series::Counter(counter) => counter,
+            _ => unreachable!(),
+        }
+    }
+
+    /// A gauge without labels
+    pub fn gauge(&self, name: &str) -> Gauge {
+        self.gauge_with(name, &[])
+    }
+
+    /// The gauge for a set of labels, panicking like
+    /// [`counter_with`](Self::counter_with)
+    pub fn gauge_with(&self, name: &str, labels: &[(&str, &str)]) -> Gauge {
+        match self.series(name, labels, Kind::Gauge, &[]) {
+            Series::Gauge(gauge) => gauge,
+            _ => unreachable!(),
+        }
+    }
+
+    /// A histogram with [`DEFAULT_BUCKETS`]
+    pub fn histogram(&self, name: &str) -> Histogram {
+        self.histogram_with(name, &[], DEFAULT_BUCKETS)
+    }
+
+    /// The histogram for a set of labels, panicking like
+    /// [`counter_with`](Self::counter_with)
+    ///
+    /// `buckets` are upper bounds; the first registration of `name` decides
+    /// the buckets of all its series.
+    pub fn histogram_with(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        buckets: &[f64],
+    ) -> Histogram {
+        match self.series(name, labels, Kind::Histogram, buckets) {
+            Series::Histogram(histogram) => histogram,
+            _ => unreachable!(),
+        }
+    }
+
+    fn series(&self, name: &str, labels: &[(&str, &str)], kind: Kind, buckets: &[f64]) -> Series {
+        let mut key: Labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        key.sort();
+
+        if let Some(series) = self
+            .inner
+            .read()
+            .families
+            .get(name)
+            .filter(|family| family.kind == kind)
+            .and_then(|family| family.series.get(&key))
+        {
+            return series.clone_handle();
+        }
+
+        assert!(valid_name(name), "invalid metric name `{}`", name);
+        for (label, _) in &key {
+            assert!(
+                valid_name(label) && !label.contains(':') && label != "le",
+                "invalid label name `{}` on metric `{}`",
+                label,
+                name
+            );
+        }
+
+        let mut registry = self.inner.write();
+        let family = registry
+            .families
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let mut buckets = buckets.to_vec();
+                buckets.retain(|b| b.is_finite());
+                buckets.sort_by(f64::total_cmp);
+                buckets.dedup();
+                Family {
+                    kind,
+                    buckets,
+                    series: BTreeMap::new(),
+                }
+            });
+        assert!(
+            family.kind == kind,
+            "metric `{}` is already registered as a {}",
+            name,
+            family.kind.as_str()
+        );
+        let bounds = family.buckets.clone();
+        family
+            .series
+            .entry(key)
+            .or_insert_with(|| match kind {
+                Kind::Counter => Series::Counter(Counter::default()),
+                Kind::Gauge => Series::Gauge(Gauge::default()),
+                Kind::Histogram => Series::Histogram(Histogram::new(bounds)),
+            })
+            .clone_handle()
+    }
+
+    /// The registry in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let registry = self.inner.read();
+        let mut out = String::new();
+        for (name, family) in &registry.families {
+            if let Some(help) = registry.help.get(name) {
+                let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+                let _ = writeln!(out, "# HELP {} {}", name, help);
+            }
+            let _ = writeln!(out, "# TYPE {} {}", name, family.kind.as_str());
+            for (labels, series) in &family.series {
+                match series {
+                    Series::Counter(counter) => {
+                        let _ =
+                            writeln!(out, "{}{} {}", name, label_set(labels, None), counter.get());
+                    }
+                    Series::Gauge(gauge) => {
+                        let _ = writeln!(
+                            out,
+                            "{}{} {}",
+                            name,
+                            label_set(labels, None),
+                            number(gauge.get())
+                        );
+                    }
+                    Series::Histogram(histogram) => {
+                        let (counts, sum, count) = histogram.snapshot();
+                        let mut total = 0;
+                        for (bound, n) in family.buckets.iter().zip(counts) {
+                            total += n;
+                            let le = number(*bound);
+                            let _ = writeln!(
+                                out,
+                                "{}_bucket{} {}",
+                                name,
+                                label_set(labels, Some(&le)),
+                                total
+                            );
+                        }
+                        let _ = writeln!(
+                            out,
+                            "{}_bucket{} {}",
+                            name,
+                            label_set(labels, Some("+Inf")),
+                            count
+                        );
+                        let _ = writeln!(
+                            out,
+                            "{}_sum{} {}",
+                            name,
+                            label_set(labels, None),
+                            number(sum)
+                        );
+                        let _ =
+                            writeln!(out, "{}_count{} {}", name, label_set(labels, None), count);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub(crate) fn record_http(&self, method: &str, status: u16, elapsed: Duration) {
+        self.describe_builtin("rustyx_http_requests_total", "HTTP requests handled");
+        self.describe_builtin(
+            "rustyx_http_request_duration_seconds",
+            "HTTP request latency",
+        );
+        self.counter_with(
+            "rustyx_http_requests_total",
+            &[("method", method), ("status", &status.to_string())],
+        )
+        .inc();
+        self.histogram_with(
+            "rustyx_http_request_duration_seconds",
+            &[("method", method)],
+            DEFAULT_BUCKETS,
+        )
+        .observe(elapsed.as_secs_f64());
+    }
+
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub(crate) fn record_db_query(&self, operation: &str, ok: bool, elapsed: Duration) {
+        self.describe_builtin("rustyx_db_queries_total", "SQL statements executed");
+        self.describe_builtin("rustyx_db_query_duration_seconds", "SQL statement latency");
+        self.counter_with(
+            "rustyx_db_queries_total",
+            &[("operation", operation), ("status", status_label(ok))],
+        )
+        .inc();
+        self.histogram_with(
+            "rustyx_db_query_duration_seconds",
+            &[("operation", operation)],
+            DEFAULT_BUCKETS,
+        )
+        .observe(elapsed.as_secs_f64());
+    }
+
+    pub(crate) fn record_upload(&self, ok: bool, bytes: usize) {
+        self.describe_builtin("rustyx_uploads_total", "Files uploaded or rejected");
+        self.describe_builtin(
+            "rustyx_upload_bytes_total",
+            "Bytes of uploaded files stored",
+        );
+        self.counter_with("rustyx_uploads_total", &[("status", status_label(ok))])
+            .inc();
+        if ok {
+            self.counter("rustyx_upload_bytes_total")
+                .inc_by(bytes as u64);
+        }
+    }
+
+    fn describe_builtin(&self, name: &str, help: &str) {
+        if !self.inner.read().help.contains_key(name) {
+            self.describe(name, help);
+        }
+    }
+}
+
+fn status_label(ok: bool) -> &'static str {
+    if ok {
+        "ok"
+    } else {
+        "error"
+    }
+}
+
+/// `[a-zA-Z_:][a-zA-Z0-9_:]*`
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn number(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// `{a="1",b="2"}`, with an optional `le` bucket label last
+fn label_set(labels: &Labels, le: Option<&str>) -> String {
+    if labels.is_empty() && le.is_none() {
+        return String::new();
+    }
+    let pairs = labels
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .chain(le.map(|le| ("le", le)))
+        .map(|(k, v)| {
+            let v = v
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", k, v)
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", pairs.join(","))
+}
+
+impl Series {
+    fn clone_handle(&self) -> Series {
+        match self {
+            Series::Counter(c) => Series::Counter(c.clone()),
+            Series::Gauge(g) => Series::Gauge(g.clone()),
+            Series::Histogram(h) => Series::Histogram(h.clone()),
+        }
+    }
+}
+
+/// A monotonically increasing count
+#[derive(Debug, Clone, Default)]
+pub struct Counter {
+    value: Arc<AtomicU64>,
+}
+
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// A value that goes up and down
+#[derive(Debug, Clone, Default)]
+pub struct Gauge {
+    bits: Arc<AtomicU64>,
+}
+
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn add(&self, delta: f64) {
+        let _ = self
+            .bits
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + delta).to_bits())
+            });
+    }
+
+    pub fn inc(&self) {
+        self.add(1.0);
+    }
+
+    pub fn dec(&self) {
+        self.add(-1.0);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+/// Observations counted into buckets
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bounds: Arc<[f64]>,
+    state: Arc<Mutex<(Vec<u64>, f64, u64)>>,
+}
+
+impl Histogram {
+    fn new(bounds: Vec<f64>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new((vec![0; bounds.len()], 0.0, 0))),
+            bounds: bounds.into(),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let mut state = self.state.lock();
+        if let Some(i) = self.bounds.iter().position(|bound| value <= *bound) {
+            state.0[i] += 1;
+        }
+        state.1 += value;
+        state.2 += 1;
+    }
+
+    /// Observe the time elapsed since `started`, in seconds
+    pub fn observe_since(&self, started: std::time::Instant) {
+        self.observe(started.elapsed().as_secs_f64());
+    }
+
+    /// Number of observations
+    pub fn count(&self) -> u64 {
+        self.state.lock().2
+    }
+
+    /// Per-bucket (non-cumulative) counts, sum and count
+    fn snapshot(&self) -> (Vec<u64>, f64, u64) {
+        self.state.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_render_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.describe("jobs_processed", "Jobs done");
+        metrics
+            .counter_with("jobs_processed", &[("queue", "mail")])
+            .inc_by(2);
+        metrics
+            .counter_with("jobs_processed", &[("queue", "mail")])
+            .inc();
+        metrics.gauge("workers").set(4.5);
+        let latency = metrics.histogram_with("latency", &[("q", "a\"b")], &[0.1, 1.0]);
+        latency.observe(0.05);
+        latency.observe(0.5);
+        latency.observe(3.0);
+
+        let text = metrics.render();
+        assert!(text.contains("# HELP jobs_processed Jobs done\n# TYPE jobs_processed counter\n"));
+        assert!(text.contains("jobs_processed{queue=\"mail\"} 3\n"));
+        assert!(text.contains("# TYPE workers gauge\nworkers 4.5\n"));
+        assert!(text.contains("latency_bucket{q=\"a\\\"b\",le=\"0.1\"} 1\n"));
+        assert!(text.contains("latency_bucket{q=\"a\\\"b\",le=\"1\"} 2\n"));
+        assert!(text.contains("latency_bucket{q=\"a\\\"b\",le=\"+Inf\"} 3\n"));
+        assert!(text.contains("latency_sum{q=\"a\\\"b\"} 3.55\n"));
+        assert!(text.contains("latency_count{q=\"a\\\"b\"} 3\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered as a counter")]
+    fn test_type_conflict_panics() {
+        let metrics = Metrics::new();
+        metrics.counter("things");
+        metrics.gauge("things");
+    }
+
+    #[tokio::test]
+    async fn test_metrics_route_reports_requests() {
+        let app = RustyX::new();
+        app.metrics().counter("metrics_test_custom").inc();
+        app.get("/metrics-test", |_req, res| async move { res.send("ok") });
+        app.metrics_route("/metrics");
+
+        app.test()
+            .get("/metrics-test")
+            .send()
+            .await
+            .assert_status(200);
+        let res = app.test().get("/metrics").send().await;
+        res.assert_status(200)
+            .assert_header("content-type", "text/plain; version=0.0.4");
+        let text = res.text();
+        assert!(text.contains("metrics_test_custom 1\n"));
+        assert!(text.contains("rustyx_http_requests_total{method=\"GET\",status=\"200\"}"));
+        assert!(text.contains("# TYPE rustyx_ws_connections_active gauge"));
+    }
+}
@@ -2,7 +2,20 @@
 //!
 //! Provides middleware functionality similar to Express middleware.
 
+pub mod access_log;
+pub mod audit;
+pub mod authorize;
+pub mod bot;
+pub mod cache;
+pub mod conditional;
+pub mod etag;
+pub mod jwt;
+pub mod locale;
 pub mod rate_limit;
+pub mod sanitize;
+pub mod signed_url;
+pub mod tenant;
+pub mod validate;
 
 use crate::request::Request;
 use crate::response::Response;
@@ -14,7 +27,50 @@ use std::sync::Arc;
 use tracing::info;
 
 // Re-export rate limiting
-pub use rate_limit::{rate_limiter, simple_rate_limit, RateLimiter, RateLimiterConfig};
+pub use rate_limit::{
+    rate_limiter, simple_rate_limit, RateLimitHeaders, RateLimiter, RateLimiterConfig,
+};
+
+// Re-export conditional wrappers
+pub use conditional::{only, unless, MiddlewareExt};
+
+// Re-export conditional GET
+pub use etag::{etag, etag_with_options, EtagOptions};
+
+// Re-export access logging
+pub use access_log::{
+    access_log, AccessLog, AccessLogConfig, AccessLogEntry, AccessLogFormat, RotationInterval,
+};
+
+// Re-export audit logging
+pub use audit::{audit, AuditConfig, AuditEvent, AuditSink};
+
+// Re-export authorization guards
+pub use authorize::{authorize, require_permissions, Authorize, Denial, Policy, Principal};
+
+// Re-export JWT authentication
+pub use jwt::jwt_auth;
+
+// Re-export bot detection
+pub use bot::{bot_detection, BotAction, BotDetectionConfig, BotScore};
+
+// Re-export locale negotiation
+pub use locale::{locale, LocaleOptions};
+
+// Re-export input sanitization
+pub use sanitize::{sanitize, SanitizeOptions};
+
+// Re-export signed URL verification
+pub use signed_url::signed;
+
+// Re-export response caching
+pub use cache::{cache, CacheConfig, CacheStore, MemoryCacheStore, ResponseCache};
+
+// Re-export tenant resolution
+pub use tenant::{tenancy, TenancyOptions, TenantResolver, TenantSource};
+
+// Re-export request validation
+pub use validate::validate_json;
 
 /// Next function type for middleware chaining
 pub type Next =
@@ -26,15 +82,66 @@ pub type MiddlewareFn = Box<
 >;
 
 /// Trait for implementing middleware
+///
+/// Useful for stateful middleware with its own constructor and config.
+/// Register it with [`RustyX::use_middleware_obj`](crate::RustyX::use_middleware_obj)
+/// or adapt it with [`from_middleware`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rustyx::prelude::*;
+/// use std::sync::Arc;
+///
+/// struct ApiKey {
+///     key: String,
+/// }
+///
+/// #[async_trait]
+/// impl Middleware for ApiKey {
+///     async fn handle(&self, req: Request, res: Response, next: Next) -> Response {
+///         if req.header("x-api-key") == Some(self.key.as_str()) {
+///             next(req, res).await
+///         } else {
+///             res.unauthorized()
+///         }
+///     }
+/// }
+///
+/// let app = RustyX::new();
+/// app.use_middleware_obj(Arc::new(ApiKey { key: "secret".into() }));
+/// ```
 #[async_trait]
 pub trait Middleware: Send + Sync {
     /// Process the request and optionally call the next middleware
     async fn handle(&self, req: Request, res: Response, next: Next) -> Response;
 }
 
+/// Adapt a [`Middleware`] trait object into a middleware function
+///
+/// The result can be used anywhere a middleware closure is accepted, e.g.
+/// [`MiddlewareGroup::with`], [`only`] or [`unless`].
+pub fn from_middleware(
+    middleware: Arc<dyn Middleware>,
+) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone {
+    move |req: Request, res: Response, next: Next| {
+        let middleware = Arc::clone(&middleware);
+        Box::pin(async move { middleware.handle(req, res, next).await })
+    }
+}
+
+/// Middleware function shared between the stack and composed chains
+type SharedMiddlewareFn = Arc<
+    dyn Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync,
+>;
+
 /// Stack of middleware functions
+#[derive(Clone)]
 pub struct MiddlewareStack {
-    stack: Vec<MiddlewareFn>,
+    stack: Vec<SharedMiddlewareFn>,
 }
 
 impl MiddlewareStack {
@@ -45,7 +152,21 @@ impl MiddlewareStack {
 
     /// Push a middleware function onto the stack
     pub fn push(&mut self, middleware: MiddlewareFn) {
-        self.stack.push(middleware);
+        self.stack.push(Arc::from(middleware));
+    }
+
+    /// Compose the stack into a single [`Next`] that runs every middleware
+    /// in registration order and finally calls `endpoint`
+    pub fn compose(&self, endpoint: Next) -> Next {
+        self.stack.iter().rev().fold(endpoint, |next, middleware| {
+            let middleware = Arc::clone(middleware);
+            Arc::new(move |req, res| middleware(req, res, Arc::clone(&next)))
+        })
+    }
+
+    /// Append every middleware from another stack
+    pub fn extend(&mut self, other: &MiddlewareStack) {
+        self.stack.extend(other.stack.iter().cloned());
     }
 
     /// Get the number of middleware in the stack
@@ -65,6 +186,54 @@ impl Default for MiddlewareStack {
     }
 }
 
+/// Reusable stack of middleware that can be registered under a name
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::{helmet, logger, MiddlewareGroup};
+///
+/// app.middleware_group("api", MiddlewareGroup::new().with(logger()).with(helmet()));
+/// ```
+#[derive(Clone, Default)]
+pub struct MiddlewareGroup {
+    stack: MiddlewareStack,
+}
+
+impl MiddlewareGroup {
+    /// Create an empty group
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a middleware to the group
+    pub fn with<F, Fut>(mut self, middleware: F) -> Self
+    where
+        F: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.stack.push(Box::new(move |req, res, next| {
+            Box::pin(middleware(req, res, next))
+        }));
+        self
+    }
+
+    /// Get the group's middleware stack
+    pub fn stack(&self) -> &MiddlewareStack {
+        &self.stack
+    }
+
+    /// Get the number of middleware in the group
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Check if the group is empty
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+}
+
 // ============================================================================
 // Built-in Middleware
 // ============================================================================
@@ -114,24 +283,27 @@ pub fn logger() -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output
 /// app.use_middleware(cors("https://example.com"));
 /// ```
 pub fn cors(
-    origin: &'static str,
+    origin: &str,
 ) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
        + Send
        + Sync
        + Clone {
+    let origin = origin.to_string();
+
     move |req: Request, res: Response, next: Next| {
+        let origin = origin.clone();
         Box::pin(async move {
             // Handle preflight requests
             if req.method() == hyper::Method::OPTIONS {
                 return res
                     .status(204)
-                    .cors(origin)
+                    .cors(&origin)
                     .header("access-control-max-age", "86400");
             }
 
             let response = next(req, res).await;
             response
-                .header("access-control-allow-origin", origin)
+                .header("access-control-allow-origin", &origin)
                 .header(
                     "access-control-allow-methods",
                     "GET, POST, PUT, DELETE, PATCH, OPTIONS",
@@ -144,10 +316,16 @@ pub fn cors(
     }
 }
 
+/// Async origin check used by [`CorsOptions::origin_fn`]
+pub type OriginPredicate =
+    Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = bool> + Send>> + Send + Sync>;
+
 /// Advanced CORS options
 #[derive(Clone)]
 pub struct CorsOptions {
     pub origin: String,
+    pub allowed_origins: Vec<String>,
+    pub origin_predicate: Option<OriginPredicate>,
     pub methods: Vec<String>,
     pub allowed_headers: Vec<String>,
     pub exposed_headers: Vec<String>,
@@ -159,11 +337,13 @@ impl Default for CorsOptions {
     fn default() -> Self {
         Self {
             origin: "*".to_string(),
-            methods: vec!["GET", "POST", "PUT", "DELETE", "PATCH", "OPTIONS"]
+            allowed_origins: Vec::new(),
+            origin_predicate: None,
+            methods: ["GET", "POST", "PUT", "DELETE", "PATCH", "OPTIONS"]
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
-            allowed_headers: vec!["Content-Type", "Authorization"]
+            allowed_headers: ["Content-Type", "Authorization"]
                 .iter()
                 .map(|s| s.to_string())
                 .collect(),
@@ -184,25 +364,160 @@ impl CorsOptions {
         self
     }
 
+    /// Allow a fixed list of origins; the matching one is echoed back
+    pub fn origins(mut self, origins: Vec<&str>) -> Self {
+        self.allowed_origins = origins.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Decide per request whether an origin is allowed
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let options = CorsOptions::new().origin_fn(|origin| async move {
+    ///     tenant_domains().await.contains(&origin)
+    /// });
+    /// ```
+    pub fn origin_fn<F, Fut>(mut self, predicate: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.origin_predicate = Some(Arc::new(move |origin| Box::pin(predicate(origin))));
+        self
+    }
+
+    pub fn methods(mut self, methods: Vec<&str>) -> Self {
+        self.methods = methods.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn allowed_headers(mut self, headers: Vec<&str>) -> Self {
+        self.allowed_headers = headers.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn exposed_headers(mut self, headers: Vec<&str>) -> Self {
+        self.exposed_headers = headers.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Allow cookies and auth headers on cross-origin requests
+    ///
+    /// Needs `origin()`, `origins()` or `origin_fn()`: with the default `*`
+    /// origin no origin is allowed, since any site could read responses.
     pub fn credentials(mut self, allow: bool) -> Self {
         self.credentials = allow;
         self
     }
+
+    pub fn max_age(mut self, seconds: u32) -> Self {
+        self.max_age = seconds;
+        self
+    }
+
+    /// Whether the allowed origin depends on the request
+    fn is_dynamic(&self) -> bool {
+        self.origin_predicate.is_some()
+            || !self.allowed_origins.is_empty()
+            || (self.origin == "*" && self.credentials)
+    }
+
+    /// Resolve the `Access-Control-Allow-Origin` value for a request origin
+    ///
+    /// Returns `None` when the origin is not allowed.
+    pub async fn resolve_origin(&self, request_origin: Option<&str>) -> Option<String> {
+        if !self.is_dynamic() {
+            return Some(self.origin.clone());
+        }
+
+        let origin = request_origin?;
+        if self.allowed_origins.iter().any(|o| o == origin) {
+            return Some(origin.to_string());
+        }
+        if let Some(predicate) = &self.origin_predicate {
+            return predicate(origin.to_string())
+                .await
+                .then(|| origin.to_string());
+        }
+        // A wildcard with credentials would let every site read responses
+        None
+    }
+
+    /// Whether credentials are allowed for any origin, which is refused
+    fn is_wildcard_with_credentials(&self) -> bool {
+        self.credentials
+            && self.origin == "*"
+            && self.allowed_origins.is_empty()
+            && self.origin_predicate.is_none()
+    }
+}
+
+/// Add a value to the `Vary` header without dropping existing entries
+fn append_vary(response: Response, value: &str) -> Response {
+    let existing = response
+        .get_headers()
+        .get("vary")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    match existing {
+        Some(vary)
+            if vary
+                .split(',')
+                .any(|v| v.trim() == "*" || v.trim().eq_ignore_ascii_case(value)) =>
+        {
+            response
+        }
+        Some(vary) => response.header("vary", &format!("{}, {}", vary, value)),
+        None => response.header("vary", value),
+    }
 }
 
 /// Advanced CORS middleware with options
+///
+/// Origins are checked against `origins()` / `origin_fn()` when set and the
+/// matching origin is echoed back with `Vary: Origin`. CORS headers already
+/// set by an inner middleware or handler are left untouched, so a router or
+/// route can override the app-wide policy.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// app.use_middleware(cors_with_options(
+///     CorsOptions::new()
+///         .origins(vec!["https://app.example.com", "https://admin.example.com"])
+///         .exposed_headers(vec!["X-Total-Count"])
+///         .credentials(true),
+/// ));
+/// ```
 pub fn cors_with_options(
     options: CorsOptions,
 ) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
        + Send
        + Sync
        + Clone {
+    if options.is_wildcard_with_credentials() {
+        tracing::warn!(
+            "CORS credentials need an allowed origin list or predicate; cross-origin requests will be refused"
+        );
+    }
     move |req: Request, res: Response, next: Next| {
         let opts = options.clone();
         Box::pin(async move {
+            let allowed = opts.resolve_origin(req.header("origin")).await;
+
             if req.method() == hyper::Method::OPTIONS {
                 let mut response = res.status(204);
-                response = response.header("access-control-allow-origin", &opts.origin);
+                if opts.is_dynamic() {
+                    response = append_vary(response, "Origin");
+                }
+                let Some(origin) = allowed else {
+                    return response;
+                };
+
+                response = response.header("access-control-allow-origin", &origin);
                 response =
                     response.header("access-control-allow-methods", &opts.methods.join(", "));
                 response = response.header(
@@ -216,11 +531,30 @@ pub fn cors_with_options(
                 return response;
             }
 
-            let response = next(req, res).await;
-            let mut response = response.header("access-control-allow-origin", &opts.origin);
+            let mut response = next(req, res).await;
+            if response
+                .get_headers()
+                .contains_key("access-control-allow-origin")
+            {
+                return response;
+            }
+            if opts.is_dynamic() {
+                response = append_vary(response, "Origin");
+            }
+            let Some(origin) = allowed else {
+                return response;
+            };
+
+            response = response.header("access-control-allow-origin", &origin);
             if opts.credentials {
                 response = response.header("access-control-allow-credentials", "true");
             }
+            if !opts.exposed_headers.is_empty() {
+                response = response.header(
+                    "access-control-expose-headers",
+                    &opts.exposed_headers.join(", "),
+                );
+            }
             response
         })
     }
@@ -375,3 +709,147 @@ pub fn response_time(
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Struct-based middleware with its own state
+    struct ApiKey {
+        key: String,
+        seen: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Middleware for ApiKey {
+        async fn handle(&self, req: Request, res: Response, next: Next) -> Response {
+            self.seen.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if req.header("x-api-key") == Some(self.key.as_str()) {
+                next(req, res).await
+            } else {
+                res.unauthorized()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_trait_objects() {
+        let api_key = Arc::new(ApiKey {
+            key: "secret".to_string(),
+            seen: Default::default(),
+        });
+        let app = crate::RustyX::new();
+        app.use_middleware_obj(api_key.clone());
+        app.get("/", |_req, res| async move { res.send("ok") });
+
+        app.test().get("/").send().await.assert_status(401);
+        app.test()
+            .get("/")
+            .header("x-api-key", "secret")
+            .send()
+            .await
+            .assert_status(200);
+        assert_eq!(api_key.seen.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // Router-scoped registration only guards the router's routes
+        let app = crate::RustyX::new();
+        let mut admin = crate::Router::new();
+        admin.use_middleware_obj(Arc::new(ApiKey {
+            key: "admin".to_string(),
+            seen: Default::default(),
+        }));
+        admin.get("/stats", |_req, res| async move { res.send("stats") });
+        app.use_router("/admin", admin);
+        app.get("/public", |_req, res| async move { res.send("public") });
+
+        app.test()
+            .get("/admin/stats")
+            .send()
+            .await
+            .assert_status(401);
+        app.test().get("/public").send().await.assert_status(200);
+    }
+
+    #[tokio::test]
+    async fn test_cors_origin_resolution() {
+        let fixed = CorsOptions::new().origin("https://a.com");
+        assert_eq!(
+            fixed
+                .resolve_origin(Some("https://evil.com"))
+                .await
+                .as_deref(),
+            Some("https://a.com")
+        );
+
+        let listed = CorsOptions::new().origins(vec!["https://a.com", "https://b.com"]);
+        assert_eq!(
+            listed
+                .resolve_origin(Some("https://b.com"))
+                .await
+                .as_deref(),
+            Some("https://b.com")
+        );
+        assert_eq!(listed.resolve_origin(Some("https://c.com")).await, None);
+        assert_eq!(listed.resolve_origin(None).await, None);
+
+        let dynamic =
+            CorsOptions::new().origin_fn(|origin| async move { origin.ends_with(".tenant.io") });
+        assert!(dynamic
+            .resolve_origin(Some("https://x.tenant.io"))
+            .await
+            .is_some());
+        assert!(dynamic
+            .resolve_origin(Some("https://x.other.io"))
+            .await
+            .is_none());
+
+        let wildcard = CorsOptions::new().credentials(true);
+        assert_eq!(
+            wildcard.resolve_origin(Some("https://evil.com")).await,
+            None
+        );
+        let listed = listed.credentials(true);
+        assert_eq!(listed.resolve_origin(Some("https://c.com")).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cors_credentials_fail_closed() {
+        let app = crate::RustyX::new();
+        app.use_middleware(cors_with_options(
+            CorsOptions::new()
+                .origins(vec!["https://app.example.com"])
+                .credentials(true),
+        ));
+        app.get("/", |_req, res| async move { res.send("ok") });
+
+        let res = app
+            .test()
+            .get("/")
+            .header("origin", "https://evil.com")
+            .send()
+            .await;
+        assert!(res.header("access-control-allow-origin").is_none());
+        assert!(res.header("access-control-allow-credentials").is_none());
+        res.assert_header("vary", "Origin");
+
+        app.test()
+            .get("/")
+            .header("origin", "https://app.example.com")
+            .send()
+            .await
+            .assert_header("access-control-allow-origin", "https://app.example.com")
+            .assert_header("access-control-allow-credentials", "true");
+
+        let app = crate::RustyX::new();
+        app.use_middleware(cors_with_options(CorsOptions::new().credentials(true)));
+        app.get("/", |_req, res| async move { res.send("ok") });
+        let res = app
+            .test()
+            .request(hyper::Method::OPTIONS, "/")
+            .header("origin", "https://evil.com")
+            .send()
+            .await;
+        assert!(res.header("access-control-allow-origin").is_none());
+        assert!(res.header("access-control-allow-credentials").is_none());
+    }
+}
@@ -0,0 +1,525 @@
+//! Access Log Middleware
+//!
+//! Writes one line per request to a file, as JSON or in the Common or
+//! Combined Log Format, rotating it by size or time and removing old files.
+//! Lines are written on a background thread and don't go through
+//! `tracing`, so access logs are configured apart from application logs.
+//!
+//! ```rust,ignore
+//! use rustyx::middleware::{access_log, AccessLogConfig, AccessLogFormat, RotationInterval};
+//!
+//! app.use_middleware(access_log(
+//!     AccessLogConfig::new("logs/access.log")
+//!         .format(AccessLogFormat::Json)
+//!         .max_size(50 * 1024 * 1024)
+//!         .rotate(RotationInterval::Daily)
+//!         .keep(14),
+//! ));
+//! ```
+//!
+//! Rotated files are renamed to `access.log.20240101-000000`, after the
+//! time they were rotated.
+
+use crate::middleware::{Next, Principal};
+use crate::request::Request;
+use crate::response::Response;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Line format of the access log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// One JSON object per line
+    Json,
+    /// Common Log Format
+    Common,
+    /// Combined Log Format: CLF with referer and user agent
+    #[default]
+    Combined,
+}
+
+/// When to start a new file regardless of size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RotationInterval {
+    Hourly,
+    Daily,
+}
+
+impl RotationInterval {
+    /// Files rotate when this changes
+    fn period(self, at: DateTime<Utc>) -> String {
+        match self {
+            RotationInterval::Hourly => at.format("%Y%m%d%H").to_string(),
+            RotationInterval::Daily => at.format("%Y%m%d").to_string(),
+        }
+    }
+}
+
+/// Access log options
+#[derive(Debug, Clone)]
+pub struct AccessLogConfig {
+    pub path: PathBuf,
+    pub format: AccessLogFormat,
+    /// Rotate before the file grows past this many bytes
+    pub max_size: Option<u64>,
+    pub interval: Option<RotationInterval>,
+    /// Rotated files to keep (all by default)
+    pub keep: Option<usize>,
+    /// Remove rotated files older than this
+    pub max_age: Option<Duration>,
+    /// Paths that are not logged, e.g. health checks
+    pub skip_paths: Vec<String>,
+}
+
+impl AccessLogConfig {
+    /// Log to `path` in the Combined Log Format, without rotation
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            format: AccessLogFormat::default(),
+            max_size: None,
+            interval: None,
+            keep: None,
+            max_age: None,
+            skip_paths: Vec::new(),
+        }
+    }
+
+    pub fn format(mut self, format: AccessLogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Rotate once the file would exceed `bytes`
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Rotate every hour or day
+    pub fn rotate(mut self, interval: RotationInterval) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Keep only the `count` newest rotated files
+    pub fn keep(mut self, count: usize) -> Self {
+        self.keep = Some(count);
+        self
+    }
+
+    /// Remove rotated files older than `age`
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Add paths to skip
+    pub fn skip(mut self, paths: Vec<&str>) -> Self {
+        self.skip_paths = paths.iter().map(|s| s.to_string()).collect();
+        self
+    }
+}
+
+/// A logged request
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub ip: String,
+    /// The authenticated principal's id
+    pub user: Option<String>,
+    pub method: String,
+    /// Path and query string
+    pub uri: String,
+    pub protocol: String,
+    pub status: u16,
+    /// Body size, `None` for streamed bodies of unknown length
+    pub bytes: Option<u64>,
+    pub latency_ms: u128,
+    pub referer: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub tenant: Option<String>,
+}
+
+impl AccessLogEntry {
+    /// The entry as a line in `format`, without the newline
+    pub fn format(&self, format: AccessLogFormat) -> String {
+        let quoted = |value: Option<&str>| value.unwrap_or("-").replace('"', "\\\"");
+        let common = format!(
+            "{} - {} [{}] \"{} {} {}\" {} {}",
+            self.ip,
+            self.user.as_deref().unwrap_or("-").replace(' ', "_"),
+            self.timestamp.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            quoted(Some(&self.uri)),
+            self.protocol,
+            self.status,
+            self.bytes.map_or("-".to_string(), |b| b.to_string()),
+        );
+        match format {
+            AccessLogFormat::Json => serde_json::to_string(self).unwrap_or_default(),
+            AccessLogFormat::Common => common,
+            AccessLogFormat::Combined => format!(
+                "{} \"{}\" \"{}\"",
+                common,
+                quoted(self.referer.as_deref()),
+                quoted(self.user_agent.as_deref()),
+            ),
+        }
+    }
+}
+
+enum Command {
+    Line(String),
+    Flush(mpsc::Sender<()>),
+}
+
+/// Handle to an access log file written on a background thread
+///
+/// Cloning is cheap; the thread stops once every clone is dropped.
+#[derive(Clone)]
+pub struct AccessLog {
+    format: AccessLogFormat,
+    skip_paths: Arc<Vec<String>>,
+    sender: mpsc::Sender<Command>,
+}
+
+impl std::fmt::Debug for AccessLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessLog")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl AccessLog {
+    /// Start the writer thread; the file is opened with the first line
+    pub fn open(config: AccessLogConfig) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let log = Self {
+            format: config.format,
+            skip_paths: Arc::new(config.skip_paths.clone()),
+            sender,
+        };
+        let mut file = LogFile::new(config);
+        std::thread::Builder::new()
+            .name("rustyx-access-log".to_string())
+            .spawn(move || file.run(receiver))
+            .expect("failed to spawn the access log thread");
+        log
+    }
+
+    /// Queue an entry
+    pub fn write(&self, entry: &AccessLogEntry) {
+        let _ = self.sender.send(Command::Line(entry.format(self.format)));
+    }
+
+    /// Block until queued entries are on disk
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::channel();
+        if self.sender.send(Command::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
+
+impl From<AccessLogConfig> for AccessLog {
+    fn from(config: AccessLogConfig) -> Self {
+        Self::open(config)
+    }
+}
+
+/// The file being written and its rotation state
+struct LogFile {
+    config: AccessLogConfig,
+    writer: Option<BufWriter<File>>,
+    size: u64,
+    period: Option<String>,
+}
+
+impl LogFile {
+    fn new(config: AccessLogConfig) -> Self {
+        Self {
+            config,
+            writer: None,
+            size: 0,
+            period: None,
+        }
+    }
+
+    fn run(&mut self, receiver: mpsc::Receiver<Command>) {
+        while let Ok(command) = receiver.recv() {
+            let mut waiting = Vec::new();
+            for command in std::iter::once(command).chain(receiver.try_iter()) {
+                match command {
+                    Command::Line(line) => {
+                        if let Err(e) = self.write(&line) {
+                            tracing::error!(
+                                "Failed to write access log {:?}: {}",
+                                self.config.path,
+                                e
+                            );
+                            self.writer = None;
+                        }
+                    }
+                    Command::Flush(done) => waiting.push(done),
+                }
+            }
+            if let Some(writer) = &mut self.writer {
+                if let Err(e) = writer.flush() {
+                    tracing::error!("Failed to write access log {:?}: {}", self.config.path, e);
+                    self.writer = None;
+                }
+            }
+            for done in waiting {
+                let _ = done.send(());
+            }
+        }
+    }
+
+    fn write(&mut self, line: &str) -> std::io::Result<()> {
+        let now = Utc::now();
+        if self.writer.is_none() {
+            self.open(now)?;
+        }
+        let len = line.len() as u64 + 1;
+        let too_big = self
+            .config
+            .max_size
+            .is_some_and(|max| self.size > 0 && self.size + len > max);
+        let new_period = match (self.config.interval, &self.period) {
+            (Some(interval), Some(period)) => interval.period(now) != *period,
+            _ => false,
+        };
+        if too_big || new_period {
+            self.rotate(now)?;
+        }
+        let writer = self.writer.as_mut().expect("log file is open");
+        writer.write_all(line.as_bytes())?;
+        writer.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn open(&mut self, now: DateTime<Utc>) -> std::io::Result<()> {
+        if let Some(dir) = self.config.path.parent() {
+            if !dir.as_os_str().is_empty() {
+                std::fs::create_dir_all(dir)?;
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.config.path)?;
+        let metadata = file.metadata()?;
+        self.size = metadata.len();
+        // A file left by a previous run belongs to the period it was written in
+        let started = match metadata.modified() {
+            Ok(modified) if self.size > 0 => DateTime::<Utc>::from(modified),
+            _ => now,
+        };
+        self.period = self.config.interval.map(|i| i.period(started));
+        self.writer = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    fn rotate(&mut self, now: DateTime<Utc>) -> std::io::Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        if self.size > 0 {
+            std::fs::rename(&self.config.path, self.rotated_path(now))?;
+        }
+        self.open(now)?;
+        self.prune();
+        Ok(())
+    }
+
+    fn rotated_path(&self, now: DateTime<Utc>) -> PathBuf {
+        let base = format!(
+            "{}.{}",
+            self.config.path.display(),
+            now.format("%Y%m%d-%H%M%S")
+        );
+        let mut path = PathBuf::from(&base);
+        let mut n = 1;
+        while path.exists() {
+            path = PathBuf::from(format!("{}.{}", base, n));
+            n += 1;
+        }
+        path
+    }
+
+    /// Remove rotated files beyond `keep` or older than `max_age`
+    fn prune(&self) {
+        if self.config.keep.is_none() && self.config.max_age.is_none() {
+            return;
+        }
+        let mut rotated = rotated_files(&self.config.path);
+        rotated.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+        let now = SystemTime::now();
+        for (i, (path, modified)) in rotated.iter().enumerate() {
+            let surplus = self.config.keep.is_some_and(|keep| i >= keep);
+            let expired = self.config.max_age.is_some_and(|max_age| {
+                now.duration_since(*modified).is_ok_and(|age| age > max_age)
+            });
+            if surplus || expired {
+                if let Err(e) = std::fs::remove_file(path) {
+                    tracing::warn!("Failed to remove old access log {:?}: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+/// Rotated copies of `path` with their modification times
+fn rotated_files(path: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", name);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.modified().ok()?)))
+        .collect()
+}
+
+/// Access log middleware
+///
+/// Takes an [`AccessLogConfig`], or an [`AccessLog`] to keep a handle for
+/// [`flush`](AccessLog::flush).
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::{access_log, AccessLogConfig};
+///
+/// app.use_middleware(access_log(
+///     AccessLogConfig::new("logs/access.log").max_size(10 * 1024 * 1024).keep(5),
+/// ));
+/// ```
+pub fn access_log(
+    log: impl Into<AccessLog>,
+) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    let log = log.into();
+
+    move |req: Request, res: Response, next: Next| {
+        let log = log.clone();
+
+        Box::pin(async move {
+            if log.skip_paths.iter().any(|p| req.path().starts_with(p)) {
+                return next(req, res).await;
+            }
+
+            let start = std::time::Instant::now();
+            let mut entry = AccessLogEntry {
+                timestamp: Utc::now(),
+                ip: req.ip().to_string(),
+                user: None,
+                method: req.method().to_string(),
+                uri: req
+                    .uri()
+                    .path_and_query()
+                    .map_or_else(|| req.path().to_string(), |pq| pq.to_string()),
+                protocol: format!("{:?}", req.version()),
+                status: 0,
+                bytes: None,
+                latency_ms: 0,
+                referer: req.header("referer").map(str::to_string),
+                user_agent: req.user_agent().map(str::to_string),
+                request_id: req.header("x-request-id").map(str::to_string),
+                tenant: req.tenant().map(|t| t.id.clone()),
+            };
+            let principal = req.extensions().get::<Principal>().cloned();
+
+            let response = next(req, res).await;
+
+            entry.user = principal.map(|p| p.id);
+            entry.status = response.get_status().as_u16();
+            entry.latency_ms = start.elapsed().as_millis();
+            entry.bytes = response
+                .get_headers()
+                .get("content-length")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .or_else(|| (!response.is_streaming()).then(|| response.get_body().len() as u64));
+            log.write(&entry);
+
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_access_log_rotation() {
+        let dir = std::env::temp_dir().join(format!("rustyx-access-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("access.log");
+        let log = AccessLog::open(
+            AccessLogConfig::new(&path)
+                .format(AccessLogFormat::Common)
+                .max_size(200)
+                .keep(2)
+                .skip(vec!["/health"]),
+        );
+        let app = RustyX::new();
+        app.use_middleware(access_log(log.clone()));
+        app.get("/items", |_req, res| async move { res.send("0123456789") });
+        app.get("/health", |_req, res| async move { res.send("ok") });
+
+        for _ in 0..8 {
+            app.test().get("/items?page=2").send().await;
+        }
+        app.test().get("/health").send().await;
+        log.flush();
+
+        let current = std::fs::read_to_string(&path).unwrap();
+        let line = current.lines().next().unwrap();
+        assert!(line.starts_with("127.0.0.1 - - ["), "{}", line);
+        assert!(
+            line.ends_with("] \"GET /items?page=2 HTTP/1.1\" 200 10"),
+            "{}",
+            line
+        );
+        assert!(!current.contains("/health"));
+        assert!(current.len() <= 200);
+
+        // Two lines fit in each file, and the oldest of the three rotated ones is gone
+        let rotated = rotated_files(&path);
+        assert_eq!(rotated.len(), 2);
+        let total: usize = rotated
+            .iter()
+            .map(|(p, _)| std::fs::read_to_string(p).unwrap().lines().count())
+            .sum();
+        assert_eq!(total + current.lines().count(), 6);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
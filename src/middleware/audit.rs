@@ -0,0 +1,405 @@
+//! Audit Logging Middleware
+//!
+//! Records who did what — actor, route, inputs, outcome and latency — to a
+//! pluggable [`AuditSink`], with sensitive fields redacted.
+
+use crate::middleware::{Next, Principal};
+use crate::request::Request;
+use crate::response::Response;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hyper::Method;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "[REDACTED]";
+
+/// A single audited request
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub request_id: Option<String>,
+    pub actor: Option<String>,
+    pub method: String,
+    /// Request path as sent
+    pub path: String,
+    /// Matched route template, e.g. `/users/:id`
+    pub route: Option<String>,
+    /// Path parameters of the matched route
+    pub params: HashMap<String, String>,
+    /// Query string parameters
+    pub query: HashMap<String, String>,
+    pub body: Option<Value>,
+    pub status: u16,
+    pub latency_ms: u128,
+    pub ip: String,
+    pub user_agent: Option<String>,
+}
+
+/// Destination for audit events
+///
+/// Implement this to write events to a database table, a webhook or a
+/// message queue.
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    /// Record an event
+    async fn record(&self, event: &AuditEvent);
+}
+
+/// Sink that logs events through `tracing`
+pub struct TracingAuditSink;
+
+#[async_trait]
+impl AuditSink for TracingAuditSink {
+    async fn record(&self, event: &AuditEvent) {
+        let json = serde_json::to_string(event).unwrap_or_default();
+        tracing::info!(target: "rustyx::audit", "{}", json);
+    }
+}
+
+/// Sink that appends events to a file as JSON lines
+pub struct FileAuditSink {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl FileAuditSink {
+    /// Create a sink writing to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileAuditSink {
+    async fn record(&self, event: &AuditEvent) {
+        let Ok(mut line) = serde_json::to_string(event) else {
+            return;
+        };
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await;
+
+        match file {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(line.as_bytes()).await {
+                    tracing::error!("Failed to write audit log: {}", e);
+                }
+            }
+            Err(e) => tracing::error!("Failed to open audit log {:?}: {}", self.path, e),
+        }
+    }
+}
+
+/// Sink backed by an async closure
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let sink = FnAuditSink::new(move |event| {
+///     let db = db.clone();
+///     async move {
+///         db.insert("audit_log", &event).await.ok();
+///     }
+/// });
+/// ```
+pub struct FnAuditSink {
+    f: AuditFn,
+}
+
+/// Boxed async closure used by [`FnAuditSink`]
+type AuditFn = Box<dyn Fn(AuditEvent) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+impl FnAuditSink {
+    /// Create a sink from a closure
+    pub fn new<F, Fut>(f: F) -> Self
+    where
+        F: Fn(AuditEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            f: Box::new(move |event| Box::pin(f(event))),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSink for FnAuditSink {
+    async fn record(&self, event: &AuditEvent) {
+        (self.f)(event.clone()).await;
+    }
+}
+
+/// Audit middleware configuration
+#[derive(Clone)]
+pub struct AuditConfig {
+    /// Where events are sent
+    pub sink: Arc<dyn AuditSink>,
+    /// Methods to audit (empty = all)
+    pub methods: Vec<Method>,
+    /// Field names whose values are replaced (case-insensitive)
+    pub redact_fields: Vec<String>,
+    /// Include the parsed JSON/form body
+    pub include_body: bool,
+    /// Paths never audited
+    pub skip_paths: Vec<String>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            sink: Arc::new(TracingAuditSink),
+            methods: vec![Method::POST, Method::PUT, Method::PATCH, Method::DELETE],
+            redact_fields: [
+                "password",
+                "password_confirmation",
+                "token",
+                "access_token",
+                "refresh_token",
+                "secret",
+                "api_key",
+                "authorization",
+                "credit_card",
+                "card_number",
+                "cvv",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            include_body: true,
+            skip_paths: Vec::new(),
+        }
+    }
+}
+
+impl AuditConfig {
+    /// Create a config with the given sink
+    pub fn new(sink: Arc<dyn AuditSink>) -> Self {
+        Self {
+            sink,
+            ..Default::default()
+        }
+    }
+
+    /// Set the audited methods (empty = all)
+    pub fn methods(mut self, methods: Vec<Method>) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Add fields to redact
+    pub fn redact(mut self, fields: Vec<&str>) -> Self {
+        self.redact_fields
+            .extend(fields.iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Include or omit request bodies
+    pub fn include_body(mut self, include: bool) -> Self {
+        self.include_body = include;
+        self
+    }
+
+    /// Skip paths
+    pub fn skip_paths(mut self, paths: Vec<&str>) -> Self {
+        self.skip_paths = paths.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    fn is_redacted(&self, field: &str) -> bool {
+        self.redact_fields
+            .iter()
+            .any(|f| f.eq_ignore_ascii_case(field))
+    }
+
+    /// Redact sensitive fields in a JSON value, recursively
+    pub fn redact_value(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (key, item) in map.iter_mut() {
+                    if self.is_redacted(key) {
+                        *item = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact_value(item);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            _ => {}
+        }
+    }
+
+    /// Redact sensitive entries of a parameter map
+    fn redact_map(&self, map: &mut HashMap<String, String>) {
+        for (key, value) in map.iter_mut() {
+            if self.is_redacted(key) {
+                *value = REDACTED.to_string();
+            }
+        }
+    }
+
+    /// Parse and redact the request body
+    fn body(&self, req: &Request) -> Option<Value> {
+        if !self.include_body || req.body().is_empty() {
+            return None;
+        }
+
+        let content_type = req.content_type().unwrap_or("");
+        let mut value = if content_type.contains("application/json") {
+            serde_json::from_slice(req.body()).ok()?
+        } else if content_type.contains("application/x-www-form-urlencoded") {
+            let map: Map<String, Value> = url::form_urlencoded::parse(req.body())
+                .map(|(k, v)| (k.into_owned(), Value::String(v.into_owned())))
+                .collect();
+            Value::Object(map)
+        } else {
+            return None;
+        };
+
+        self.redact_value(&mut value);
+        Some(value)
+    }
+}
+
+/// Audit logging middleware
+///
+/// Register it after authentication middleware so the [`Principal`] is
+/// available. Events are recorded in a background task and never delay
+/// the response.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::audit::{audit, AuditConfig, FileAuditSink};
+///
+/// app.use_middleware(audit(
+///     AuditConfig::new(Arc::new(FileAuditSink::new("logs/audit.log")))
+///         .redact(vec!["ssn"]),
+/// ));
+/// ```
+pub fn audit(
+    config: AuditConfig,
+) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    let config = Arc::new(config);
+
+    move |req: Request, res: Response, next: Next| {
+        let config = Arc::clone(&config);
+
+        Box::pin(async move {
+            let skipped = (!config.methods.is_empty() && !config.methods.contains(req.method()))
+                || config.skip_paths.iter().any(|p| req.path().starts_with(p));
+            if skipped {
+                return next(req, res).await;
+            }
+
+            let start = std::time::Instant::now();
+            let mut query = req.query().clone();
+            config.redact_map(&mut query);
+
+            let mut event = AuditEvent {
+                timestamp: Utc::now(),
+                request_id: req.header("x-request-id").map(str::to_string),
+                actor: req.extensions().get::<Principal>().map(|p| p.id.clone()),
+                method: req.method().to_string(),
+                path: req.path().to_string(),
+                route: None,
+                params: HashMap::new(),
+                query,
+                body: config.body(&req),
+                status: 0,
+                latency_ms: 0,
+                ip: req.ip().to_string(),
+                user_agent: req.user_agent().map(str::to_string),
+            };
+
+            let response = next(req, res).await;
+
+            // Routing happens inside `next`, so the route is read back from
+            // the response
+            if let Some(route) = response.route() {
+                event.route = Some(route.pattern.to_string());
+                event.params = route.params.clone();
+                config.redact_map(&mut event.params);
+            }
+            event.status = response.get_status().as_u16();
+            event.latency_ms = start.elapsed().as_millis();
+            let sink = Arc::clone(&config.sink);
+            tokio::spawn(async move { sink.record(&event).await });
+
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_value() {
+        let config = AuditConfig::default().redact(vec!["ssn"]);
+        let mut value = json!({
+            "email": "a@b.c",
+            "Password": "hunter2",
+            "profile": { "ssn": "123", "cards": [{ "cvv": "999" }] }
+        });
+
+        config.redact_value(&mut value);
+
+        assert_eq!(value["email"], "a@b.c");
+        assert_eq!(value["Password"], REDACTED);
+        assert_eq!(value["profile"]["ssn"], REDACTED);
+        assert_eq!(value["profile"]["cards"][0]["cvv"], REDACTED);
+    }
+
+    #[tokio::test]
+    async fn test_records_route_params_and_query() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let sink = FnAuditSink::new(move |event| {
+            let tx = tx.clone();
+            async move {
+                tx.send(event).ok();
+            }
+        });
+        let app = crate::RustyX::new();
+        app.use_middleware(audit(AuditConfig::new(Arc::new(sink)).redact(vec!["key"])));
+        app.delete("/users/:id/keys/:key", |_req, res| async move {
+            res.status(204)
+        });
+
+        app.test()
+            .delete("/users/7/keys/abc?token=t&force=1")
+            .send()
+            .await
+            .assert_status(204);
+
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.path, "/users/7/keys/abc");
+        assert_eq!(event.route.as_deref(), Some("/users/:id/keys/:key"));
+        assert_eq!(event.params["id"], "7");
+        assert_eq!(event.params["key"], REDACTED);
+        assert_eq!(event.query["token"], REDACTED);
+        assert_eq!(event.query["force"], "1");
+        assert_eq!(event.status, 204);
+    }
+}
@@ -0,0 +1,318 @@
+//! Authorization Guards
+//!
+//! Role and permission checks against the [`Principal`] placed in request
+//! extensions by an authentication middleware (JWT, session, OAuth, ...).
+
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Authenticated actor for the current request
+///
+/// Authentication middleware inserts this into the request extensions;
+/// guards and audit logging read it back.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// req.extensions_mut().insert(Principal::from_claims(&claims));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Principal {
+    pub id: String,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+    pub claims: Value,
+}
+
+impl Principal {
+    /// Create a principal with an id and no roles
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set roles
+    pub fn roles(mut self, roles: &[&str]) -> Self {
+        self.roles = roles.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Set permissions
+    pub fn permissions(mut self, permissions: &[&str]) -> Self {
+        self.permissions = permissions.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Build a principal from JWT-style claims
+    ///
+    /// Reads the id from `sub` (or `id`), roles from `roles` / `role`, and
+    /// permissions from `permissions` or a space-separated `scope`.
+    pub fn from_claims(claims: &Value) -> Self {
+        let strings = |value: Option<&Value>| -> Vec<String> {
+            match value {
+                Some(Value::Array(items)) => items
+                    .iter()
+                    .filter_map(|v| v.as_str().map(str::to_string))
+                    .collect(),
+                Some(Value::String(s)) => s.split_whitespace().map(str::to_string).collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        let id = claims
+            .get("sub")
+            .or_else(|| claims.get("id"))
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| v.to_string())
+            })
+            .unwrap_or_default();
+        let mut roles = strings(claims.get("roles"));
+        roles.extend(strings(claims.get("role")));
+        let mut permissions = strings(claims.get("permissions"));
+        permissions.extend(strings(claims.get("scope")));
+
+        Self {
+            id,
+            roles,
+            permissions,
+            claims: claims.clone(),
+        }
+    }
+
+    /// Check if the principal has a role
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    /// Check if the principal has a permission
+    ///
+    /// Supports `*` and `resource:*` wildcards on granted permissions.
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| {
+            granted == "*"
+                || granted == permission
+                || granted
+                    .strip_suffix('*')
+                    .map(|prefix| prefix.ends_with(':') && permission.starts_with(prefix))
+                    .unwrap_or(false)
+        })
+    }
+}
+
+/// Reason a request was denied
+#[derive(Debug, Clone, PartialEq)]
+pub enum Denial {
+    /// No principal on the request
+    Unauthenticated,
+    /// Principal present but not allowed
+    Forbidden(String),
+}
+
+/// Custom authorization logic
+///
+/// # Example
+///
+/// ```rust,ignore
+/// struct OwnsResource;
+///
+/// #[async_trait]
+/// impl Policy for OwnsResource {
+///     async fn check(&self, principal: &Principal, req: &Request) -> bool {
+///         req.param("user_id") == Some(&principal.id)
+///     }
+/// }
+///
+/// app.use_middleware(Authorize::new().policy(Arc::new(OwnsResource)).middleware());
+/// ```
+#[async_trait]
+pub trait Policy: Send + Sync {
+    /// Return true to allow the request
+    async fn check(&self, principal: &Principal, req: &Request) -> bool;
+}
+
+/// Handler called when a request is denied
+pub type DenyHandler = Arc<dyn Fn(&Request, Response, Denial) -> Response + Send + Sync>;
+
+/// Authorization guard builder
+#[derive(Clone, Default)]
+pub struct Authorize {
+    /// The principal needs at least one of these roles
+    pub roles: Vec<String>,
+    /// The principal needs all of these permissions
+    pub permissions: Vec<String>,
+    /// Extra policies, all of which must allow the request
+    pub policies: Vec<Arc<dyn Policy>>,
+    /// Custom deny response
+    pub on_deny: Option<DenyHandler>,
+}
+
+impl Authorize {
+    /// Create a guard that only requires authentication
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require any of the given roles
+    pub fn roles(mut self, roles: &[&str]) -> Self {
+        self.roles = roles.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Require all of the given permissions
+    pub fn permissions(mut self, permissions: &[&str]) -> Self {
+        self.permissions = permissions.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Add a custom policy
+    pub fn policy(mut self, policy: Arc<dyn Policy>) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// Customize the deny response
+    pub fn on_deny<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Request, Response, Denial) -> Response + Send + Sync + 'static,
+    {
+        self.on_deny = Some(Arc::new(handler));
+        self
+    }
+
+    /// Check a request
+    pub async fn check(&self, req: &Request) -> Result<(), Denial> {
+        let Some(principal) = req.extensions().get::<Principal>() else {
+            return Err(Denial::Unauthenticated);
+        };
+
+        if !self.roles.is_empty() && !self.roles.iter().any(|r| principal.has_role(r)) {
+            return Err(Denial::Forbidden(format!(
+                "Requires one of roles: {}",
+                self.roles.join(", ")
+            )));
+        }
+
+        if let Some(missing) = self
+            .permissions
+            .iter()
+            .find(|p| !principal.has_permission(p))
+        {
+            return Err(Denial::Forbidden(format!(
+                "Missing permission: {}",
+                missing
+            )));
+        }
+
+        for policy in &self.policies {
+            if !policy.check(principal, req).await {
+                return Err(Denial::Forbidden("Denied by policy".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the middleware
+    pub fn middleware(
+        self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + Clone
+           + 'static {
+        let guard = Arc::new(self);
+
+        move |req: Request, res: Response, next: Next| {
+            let guard = Arc::clone(&guard);
+
+            Box::pin(async move {
+                match guard.check(&req).await {
+                    Ok(()) => next(req, res).await,
+                    Err(denial) => match &guard.on_deny {
+                        Some(handler) => handler(&req, res, denial),
+                        None => match denial {
+                            Denial::Unauthenticated => res.unauthorized(),
+                            Denial::Forbidden(message) => res.status(403).json(serde_json::json!({
+                                "error": "Forbidden",
+                                "message": message
+                            })),
+                        },
+                    },
+                }
+            })
+        }
+    }
+}
+
+/// Require any of the given roles
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::authorize;
+///
+/// let mut admin = Router::new();
+/// admin.use_middleware(authorize(&["admin"]));
+/// ```
+pub fn authorize(
+    roles: &[&str],
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    Authorize::new().roles(roles).middleware()
+}
+
+/// Require all of the given permissions
+pub fn require_permissions(
+    permissions: &[&str],
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    Authorize::new().permissions(permissions).middleware()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_principal_from_claims() {
+        let principal = Principal::from_claims(&json!({
+            "sub": "42",
+            "roles": ["editor"],
+            "scope": "posts:* users:read"
+        }));
+
+        assert_eq!(principal.id, "42");
+        assert!(principal.has_role("editor"));
+        assert!(!principal.has_role("admin"));
+        assert!(principal.has_permission("posts:delete"));
+        assert!(principal.has_permission("users:read"));
+        assert!(!principal.has_permission("users:write"));
+    }
+}
@@ -0,0 +1,475 @@
+//! Bot Detection Middleware
+//!
+//! Scores requests using user-agent heuristics, missing browser headers,
+//! honeypot form fields and per-IP request rate, then tags, throttles or
+//! blocks suspect traffic.
+//!
+//! User agents are trivially spoofed, so allowlisted crawlers only skip the
+//! user-agent and header checks; honeypot fields and request rate still
+//! count for them.
+
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What to do with a request whose score reaches a threshold
+#[derive(Debug, Clone, PartialEq)]
+pub enum BotAction {
+    /// Let the request through untouched
+    Allow,
+    /// Add an `X-Bot-Score` header and a [`BotScore`] request extension
+    Tag,
+    /// Delay the request before handling it
+    Throttle(Duration),
+    /// Reject with 403 Forbidden
+    Block,
+}
+
+/// Score computed for a request (available in request extensions)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BotScore {
+    pub score: u32,
+    pub reasons: Vec<String>,
+}
+
+impl BotScore {
+    fn add(&mut self, points: u32, reason: impl Into<String>) {
+        self.score += points;
+        self.reasons.push(reason.into());
+    }
+}
+
+/// Bot detection configuration
+#[derive(Debug, Clone)]
+pub struct BotDetectionConfig {
+    /// User-agent substrings that mark automated clients (case-insensitive)
+    pub bad_user_agents: Vec<String>,
+    /// User-agent substrings exempt from the user-agent and header checks
+    /// (e.g. search engines)
+    pub allowed_user_agents: Vec<String>,
+    /// Headers every real browser sends
+    pub expected_headers: Vec<String>,
+    /// Form/JSON fields hidden from humans; any value means a bot filled them
+    pub honeypot_fields: Vec<String>,
+    /// Requests per IP allowed in `rate_window` before scoring
+    pub rate_limit: u32,
+    /// Window for the request-rate check
+    pub rate_window: Duration,
+    /// Score at which requests are tagged
+    pub tag_threshold: u32,
+    /// Score at which requests are throttled, with the delay
+    pub throttle: Option<(u32, Duration)>,
+    /// Score at which requests are blocked
+    pub block_threshold: u32,
+    /// Paths never checked
+    pub skip_paths: Vec<String>,
+}
+
+impl Default for BotDetectionConfig {
+    fn default() -> Self {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+
+        Self {
+            bad_user_agents: strings(&[
+                "curl",
+                "wget",
+                "python-requests",
+                "python-urllib",
+                "go-http-client",
+                "scrapy",
+                "headlesschrome",
+                "phantomjs",
+                "sqlmap",
+                "nikto",
+                "nmap",
+                "masscan",
+                "zgrab",
+            ]),
+            allowed_user_agents: strings(&["googlebot", "bingbot", "duckduckbot"]),
+            expected_headers: strings(&["accept", "accept-language", "accept-encoding"]),
+            honeypot_fields: Vec::new(),
+            rate_limit: 120,
+            rate_window: Duration::from_secs(60),
+            tag_threshold: 30,
+            throttle: None,
+            block_threshold: 80,
+            skip_paths: Vec::new(),
+        }
+    }
+}
+
+impl BotDetectionConfig {
+    /// Create a default config
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add user-agent substrings to flag
+    pub fn bad_user_agents(mut self, agents: Vec<&str>) -> Self {
+        self.bad_user_agents
+            .extend(agents.iter().map(|s| s.to_lowercase()));
+        self
+    }
+
+    /// Add user-agent substrings exempt from the user-agent and header checks
+    pub fn allow_user_agents(mut self, agents: Vec<&str>) -> Self {
+        self.allowed_user_agents
+            .extend(agents.iter().map(|s| s.to_lowercase()));
+        self
+    }
+
+    /// Set honeypot field names
+    pub fn honeypot(mut self, fields: Vec<&str>) -> Self {
+        self.honeypot_fields = fields.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Set the request-rate check
+    pub fn rate(mut self, max_requests: u32, window_secs: u64) -> Self {
+        self.rate_limit = max_requests;
+        self.rate_window = Duration::from_secs(window_secs);
+        self
+    }
+
+    /// Set the tag threshold
+    pub fn tag_at(mut self, score: u32) -> Self {
+        self.tag_threshold = score;
+        self
+    }
+
+    /// Throttle requests scoring at least `score`
+    pub fn throttle_at(mut self, score: u32, delay: Duration) -> Self {
+        self.throttle = Some((score, delay));
+        self
+    }
+
+    /// Set the block threshold
+    pub fn block_at(mut self, score: u32) -> Self {
+        self.block_threshold = score;
+        self
+    }
+
+    /// Add paths to skip
+    pub fn skip(mut self, paths: Vec<&str>) -> Self {
+        self.skip_paths = paths.iter().map(|s| s.to_string()).collect();
+        self
+    }
+}
+
+/// Per-IP request counter
+#[derive(Debug, Clone)]
+struct RateEntry {
+    count: u32,
+    window_start: Instant,
+}
+
+/// Per-IP counters with the time they were last pruned
+#[derive(Debug)]
+struct RateTable {
+    entries: HashMap<String, RateEntry>,
+    pruned_at: Instant,
+}
+
+/// Bot detector state
+#[derive(Debug, Clone)]
+pub struct BotDetector {
+    config: BotDetectionConfig,
+    rates: Arc<RwLock<RateTable>>,
+}
+
+impl BotDetector {
+    /// Create a new detector
+    pub fn new(config: BotDetectionConfig) -> Self {
+        Self {
+            config,
+            rates: Arc::new(RwLock::new(RateTable {
+                entries: HashMap::new(),
+                pruned_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Score a request
+    pub fn score(&self, req: &Request) -> BotScore {
+        let mut score = BotScore::default();
+        let user_agent = req.user_agent().unwrap_or("").to_lowercase();
+
+        let allowed_agent = self
+            .config
+            .allowed_user_agents
+            .iter()
+            .any(|ua| user_agent.contains(ua.as_str()));
+
+        if !allowed_agent {
+            if user_agent.trim().is_empty() {
+                score.add(40, "missing user-agent");
+            } else if let Some(ua) = self
+                .config
+                .bad_user_agents
+                .iter()
+                .find(|ua| user_agent.contains(ua.as_str()))
+            {
+                score.add(50, format!("user-agent matches '{}'", ua));
+            }
+
+            for header in &self.config.expected_headers {
+                if req.header(header).is_none() {
+                    score.add(10, format!("missing {} header", header));
+                }
+            }
+        }
+
+        if let Some(field) = self.filled_honeypot(req) {
+            score.add(100, format!("honeypot field '{}' filled", field));
+        }
+
+        if self.rate_exceeded(&req.ip().to_string()) {
+            score.add(30, "request rate exceeded");
+        }
+
+        score
+    }
+
+    /// Decide what to do with a score
+    pub fn action(&self, score: &BotScore) -> BotAction {
+        if score.score >= self.config.block_threshold {
+            return BotAction::Block;
+        }
+        if let Some((threshold, delay)) = self.config.throttle {
+            if score.score >= threshold {
+                return BotAction::Throttle(delay);
+            }
+        }
+        if score.score >= self.config.tag_threshold {
+            BotAction::Tag
+        } else {
+            BotAction::Allow
+        }
+    }
+
+    /// Find a honeypot field with a value in a form or JSON body
+    fn filled_honeypot(&self, req: &Request) -> Option<String> {
+        if self.config.honeypot_fields.is_empty() || req.body().is_empty() {
+            return None;
+        }
+
+        let is_filled = |field: &str| -> bool {
+            if req.is_json() {
+                serde_json::from_slice::<serde_json::Value>(req.body())
+                    .ok()
+                    .and_then(|v| v.get(field).cloned())
+                    .map(|v| !v.is_null() && v != "")
+                    .unwrap_or(false)
+            } else {
+                url::form_urlencoded::parse(req.body())
+                    .any(|(k, v)| k == field && !v.trim().is_empty())
+            }
+        };
+
+        self.config
+            .honeypot_fields
+            .iter()
+            .find(|f| is_filled(f))
+            .cloned()
+    }
+
+    /// Count a request for `key` and check the rate window
+    fn rate_exceeded(&self, key: &str) -> bool {
+        let mut rates = self.rates.write();
+        let now = Instant::now();
+        let window = self.config.rate_window;
+
+        // Drop stale entries once per window, so the map only holds IPs
+        // seen recently and the scan is not paid on every request
+        if now.duration_since(rates.pruned_at) >= window {
+            rates
+                .entries
+                .retain(|_, e| now.duration_since(e.window_start) < window);
+            rates.pruned_at = now;
+        }
+
+        let entry = rates.entries.entry(key.to_string()).or_insert(RateEntry {
+            count: 0,
+            window_start: now,
+        });
+        if now.duration_since(entry.window_start) >= window {
+            entry.count = 0;
+            entry.window_start = now;
+        }
+        entry.count += 1;
+
+        entry.count > self.config.rate_limit
+    }
+
+    /// Get the config
+    pub fn config(&self) -> &BotDetectionConfig {
+        &self.config
+    }
+}
+
+/// Bot detection middleware
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::bot::{bot_detection, BotDetectionConfig};
+///
+/// app.use_middleware(bot_detection(
+///     BotDetectionConfig::new()
+///         .honeypot(vec!["website"])
+///         .throttle_at(50, Duration::from_secs(2))
+///         .block_at(90),
+/// ));
+/// ```
+pub fn bot_detection(
+    config: BotDetectionConfig,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    let detector = BotDetector::new(config);
+
+    move |mut req: Request, res: Response, next: Next| {
+        let detector = detector.clone();
+
+        Box::pin(async move {
+            if detector
+                .config
+                .skip_paths
+                .iter()
+                .any(|p| req.path().starts_with(p))
+            {
+                return next(req, res).await;
+            }
+
+            let score = detector.score(&req);
+            let action = detector.action(&score);
+
+            if action != BotAction::Allow {
+                tracing::debug!(
+                    "Bot score {} for {} {}: {}",
+                    score.score,
+                    req.ip(),
+                    req.path(),
+                    score.reasons.join(", ")
+                );
+            }
+
+            match action {
+                BotAction::Allow => next(req, res).await,
+                BotAction::Block => res.forbidden(),
+                BotAction::Tag | BotAction::Throttle(_) => {
+                    if let BotAction::Throttle(delay) = action {
+                        tokio::time::sleep(delay).await;
+                    }
+                    let value = score.score.to_string();
+                    req.extensions_mut().insert(score);
+                    next(req, res).await.header("x-bot-score", &value)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    fn browser() -> crate::request::RequestBuilder {
+        Request::builder()
+            .header(
+                "user-agent",
+                "Mozilla/5.0 (X11; Linux x86_64) Firefox/128.0",
+            )
+            .header("accept", "text/html")
+            .header("accept-language", "en")
+            .header("accept-encoding", "gzip")
+    }
+
+    #[test]
+    fn test_scoring_rules() {
+        let detector = BotDetector::new(BotDetectionConfig::new().honeypot(vec!["website"]));
+
+        let human = detector.score(&browser().build());
+        assert_eq!(human, BotScore::default());
+        assert_eq!(detector.action(&human), BotAction::Allow);
+
+        let curl = detector.score(
+            &Request::builder()
+                .header("user-agent", "curl/8.5.0")
+                .header("accept", "*/*")
+                .build(),
+        );
+        assert_eq!(curl.score, 70);
+        assert_eq!(curl.reasons[0], "user-agent matches 'curl'");
+        assert_eq!(detector.action(&curl), BotAction::Tag);
+
+        let anonymous = detector.score(&Request::builder().build());
+        assert_eq!(anonymous.score, 70);
+        assert_eq!(anonymous.reasons[0], "missing user-agent");
+
+        let filled = browser()
+            .form(&[("email", "a@example.com"), ("website", "spam.example")])
+            .build();
+        let honeypot = detector.score(&filled);
+        assert_eq!(honeypot.score, 100);
+        assert_eq!(detector.action(&honeypot), BotAction::Block);
+        let empty = browser()
+            .json(&serde_json::json!({ "website": "" }))
+            .build();
+        assert_eq!(detector.score(&empty).score, 0);
+    }
+
+    #[test]
+    fn test_allowlisted_agents_keep_honeypot_and_rate_checks() {
+        let detector = BotDetector::new(
+            BotDetectionConfig::new()
+                .honeypot(vec!["website"])
+                .rate(2, 60),
+        );
+        let crawler = || {
+            Request::builder()
+                .header(
+                    "user-agent",
+                    "Mozilla/5.0 (compatible; Googlebot/2.1) python-requests",
+                )
+                .remote_addr(SocketAddr::from(([203, 0, 113, 9], 4000)))
+        };
+
+        assert_eq!(detector.score(&crawler().build()).score, 0);
+        let spoofed = detector.score(&crawler().form(&[("website", "x")]).build());
+        assert_eq!(spoofed.score, 100);
+        assert_eq!(detector.action(&spoofed), BotAction::Block);
+
+        let flood = detector.score(&crawler().build());
+        assert_eq!(flood.reasons, ["request rate exceeded"]);
+        assert_eq!(detector.action(&flood), BotAction::Tag);
+    }
+    #[test]
+    fn test_rate_table_is_pruned_once_per_window() {
+        let mut config = BotDetectionConfig::new();
+        config.rate_window = Duration::from_millis(50);
+        let detector = BotDetector::new(config);
+
+        for i in 0..100 {
+            detector.rate_exceeded(&format!("10.0.0.{}", i));
+        }
+        assert_eq!(detector.rates.read().entries.len(), 100);
+
+        std::thread::sleep(Duration::from_millis(60));
+        detector.rate_exceeded("10.0.1.1");
+        let rates = detector.rates.read();
+        assert_eq!(rates.entries.len(), 1);
+        assert!(rates.entries.contains_key("10.0.1.1"));
+    }
+}
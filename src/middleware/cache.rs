@@ -0,0 +1,682 @@
+//! Response Caching Middleware
+//!
+//! Stores full responses for idempotent requests so repeated reads can be
+//! answered without running the route handler again.
+//!
+//! Responses to requests carrying `Authorization` or `Cookie` are only
+//! stored when marked `Cache-Control: public`. A response's `Vary` header
+//! adds the listed request headers to its key; `Vary: *` is never stored.
+
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use async_trait::async_trait;
+use hyper::{HeaderMap, Method};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Snapshot of a response stored in the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers
+    pub headers: Vec<(String, String)>,
+    /// Response body
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    /// Capture a response
+    pub fn from_response(res: &Response) -> Self {
+        Self {
+            status: res.get_status().as_u16(),
+            headers: res
+                .get_headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    value
+                        .to_str()
+                        .ok()
+                        .map(|v| (name.to_string(), v.to_string()))
+                })
+                .collect(),
+            body: res.get_body().to_vec(),
+        }
+    }
+
+    /// Lowercased request header names from `Vary`, `None` for `Vary: *`
+    fn vary(&self) -> Option<Vec<String>> {
+        let values = self
+            .headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("vary"))
+            .flat_map(|(_, value)| value.split(','))
+            .map(str::trim);
+
+        let mut names = Vec::new();
+        for name in values {
+            if name == "*" {
+                return None;
+            }
+            if !name.is_empty() {
+                names.push(name.to_lowercase());
+            }
+        }
+        names.sort();
+        names.dedup();
+        Some(names)
+    }
+
+    /// Replay the snapshot onto a response
+    pub fn apply(self, res: Response) -> Response {
+        let mut res = res.status(self.status);
+        for (name, value) in &self.headers {
+            res = res.header(name, value);
+        }
+        res.send_bytes(self.body)
+    }
+}
+
+/// Storage backend for cached responses
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    /// Get a cached response, if present and not expired
+    async fn get(&self, key: &str) -> Option<CachedResponse>;
+
+    /// Store a response for `ttl`
+    async fn set(&self, key: &str, value: CachedResponse, ttl: Duration);
+
+    /// Remove every entry whose key starts with `prefix`
+    async fn remove_prefix(&self, prefix: &str);
+}
+
+/// Cache entry held by the memory store
+#[derive(Debug, Clone)]
+struct MemoryEntry {
+    value: CachedResponse,
+    expires_at: Instant,
+}
+
+/// In-process cache store with a bounded number of entries
+#[derive(Debug, Clone)]
+pub struct MemoryCacheStore {
+    entries: Arc<RwLock<HashMap<String, MemoryEntry>>>,
+    max_entries: usize,
+}
+
+impl MemoryCacheStore {
+    /// Create a new memory store holding at most `max_entries` responses
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            max_entries,
+        }
+    }
+
+    /// Get the number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    /// Check if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+}
+
+#[async_trait]
+impl CacheStore for MemoryCacheStore {
+    async fn get(&self, key: &str) -> Option<CachedResponse> {
+        let entries = self.entries.read();
+        entries
+            .get(key)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.value.clone())
+    }
+
+    async fn set(&self, key: &str, value: CachedResponse, ttl: Duration) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.write();
+        let now = Instant::now();
+
+        if entries.len() >= self.max_entries && !entries.contains_key(key) {
+            // Drop expired entries first, then the one closest to expiry
+            entries.retain(|_, entry| entry.expires_at > now);
+            if entries.len() >= self.max_entries {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                value,
+                expires_at: now + ttl,
+            },
+        );
+    }
+
+    async fn remove_prefix(&self, prefix: &str) {
+        self.entries.write().retain(|k, _| !k.starts_with(prefix));
+    }
+}
+
+/// Redis-backed cache store, shared between server instances
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisCacheStore {
+    conn: redis::aio::ConnectionManager,
+}
+
+#[cfg(feature = "redis")]
+impl RedisCacheStore {
+    /// Connect to Redis (e.g. `redis://127.0.0.1/`)
+    pub async fn connect(url: &str) -> crate::error::Result<Self> {
+        Ok(Self::from_client(
+            &crate::db::redis::RedisClient::connect(url).await?,
+        ))
+    }
+
+    /// Share an existing client's connection
+    pub fn from_client(client: &crate::db::redis::RedisClient) -> Self {
+        Self {
+            conn: client.connection(),
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl CacheStore for RedisCacheStore {
+    async fn get(&self, key: &str) -> Option<CachedResponse> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.conn.clone();
+        match conn.get::<_, Option<Vec<u8>>>(key).await {
+            Ok(raw) => raw.and_then(|raw| serde_json::from_slice(&raw).ok()),
+            Err(e) => {
+                tracing::warn!("Response cache read failed: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn set(&self, key: &str, value: CachedResponse, ttl: Duration) {
+        use redis::AsyncCommands;
+
+        let Ok(raw) = serde_json::to_vec(&value) else {
+            return;
+        };
+        let mut conn = self.conn.clone();
+        if let Err(e) = conn
+            .set_ex::<_, _, ()>(key, raw, ttl.as_secs().max(1))
+            .await
+        {
+            tracing::warn!("Response cache write failed: {}", e);
+        }
+    }
+
+    async fn remove_prefix(&self, prefix: &str) {
+        use redis::AsyncCommands;
+
+        let escaped: String = prefix
+            .chars()
+            .flat_map(|c| match c {
+                '*' | '?' | '[' | ']' | '\\' => vec!['\\', c],
+                _ => vec![c],
+            })
+            .collect();
+
+        let mut conn = self.conn.clone();
+        let keys: Vec<String> = match conn.scan_match::<_, String>(format!("{}*", escaped)).await {
+            Ok(mut iter) => {
+                let mut keys = Vec::new();
+                while let Some(key) = iter.next_item().await {
+                    keys.push(key);
+                }
+                keys
+            }
+            Err(e) => {
+                tracing::warn!("Response cache invalidation failed: {}", e);
+                return;
+            }
+        };
+
+        if !keys.is_empty() {
+            if let Err(e) = conn.del::<_, ()>(keys).await {
+                tracing::warn!("Response cache invalidation failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Response cache configuration
+#[derive(Clone)]
+pub struct CacheConfig {
+    /// How long responses stay cached
+    pub ttl: Duration,
+    /// Maximum number of entries (memory store only)
+    pub max_entries: usize,
+    /// Methods whose responses are cached
+    pub methods: Vec<Method>,
+    /// Request headers that take part in the cache key
+    pub vary_headers: Vec<String>,
+    /// Response status codes that are cached
+    pub statuses: Vec<u16>,
+    /// Skip caching for certain paths
+    pub skip_paths: Vec<String>,
+    /// Prefix applied to every cache key
+    pub key_prefix: String,
+    /// Invalidate a path's entries after a successful POST/PUT/PATCH/DELETE to it
+    pub invalidate_on_write: bool,
+    /// Custom store (defaults to an in-memory store)
+    pub store: Option<Arc<dyn CacheStore>>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(60),
+            max_entries: 1000,
+            methods: vec![Method::GET, Method::HEAD],
+            vary_headers: Vec::new(),
+            statuses: vec![200],
+            skip_paths: Vec::new(),
+            key_prefix: "rustyx:cache:".to_string(),
+            invalidate_on_write: true,
+            store: None,
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Create a new cache config with a TTL in seconds
+    pub fn new(ttl_secs: u64) -> Self {
+        Self {
+            ttl: Duration::from_secs(ttl_secs),
+            ..Default::default()
+        }
+    }
+
+    /// Set maximum number of cached entries
+    pub fn max_entries(mut self, max: usize) -> Self {
+        self.max_entries = max;
+        self
+    }
+
+    /// Set the request headers that vary the cache key
+    pub fn vary(mut self, headers: Vec<&str>) -> Self {
+        self.vary_headers = headers.iter().map(|s| s.to_lowercase()).collect();
+        self
+    }
+
+    /// Set the response status codes that are cached
+    pub fn statuses(mut self, statuses: Vec<u16>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    /// Add paths to skip
+    pub fn skip(mut self, paths: Vec<&str>) -> Self {
+        self.skip_paths = paths.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Set the cache key prefix
+    pub fn key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = prefix.to_string();
+        self
+    }
+
+    /// Enable or disable invalidation on write requests
+    pub fn invalidate_on_write(mut self, enabled: bool) -> Self {
+        self.invalidate_on_write = enabled;
+        self
+    }
+
+    /// Use a custom cache store
+    pub fn store(mut self, store: impl CacheStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+}
+
+/// Response cache state
+///
+/// Keep a clone around to invalidate entries from handlers.
+#[derive(Clone)]
+pub struct ResponseCache {
+    config: CacheConfig,
+    store: Arc<dyn CacheStore>,
+}
+
+impl ResponseCache {
+    /// Create a new response cache
+    pub fn new(config: CacheConfig) -> Self {
+        let store = config
+            .store
+            .clone()
+            .unwrap_or_else(|| Arc::new(MemoryCacheStore::new(config.max_entries)));
+        Self { config, store }
+    }
+
+    /// Get the config
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Build the cache key for a request, which varies by tenant as well
+    pub fn key(&self, req: &Request) -> String {
+        let mut vary: Vec<String> = self
+            .config
+            .vary_headers
+            .iter()
+            .map(|name| format!("{}={}", name, req.header(name).unwrap_or("")))
+            .collect();
+        if let Some(tenant) = req.tenant() {
+            vary.push(format!("tenant={}", tenant.id));
+        }
+
+        format!(
+            "{}{}#{}#{}#{}",
+            self.config.key_prefix,
+            req.path(),
+            req.method(),
+            req.uri().query().unwrap_or(""),
+            vary.join("&")
+        )
+    }
+
+    /// Look up a stored response, following its `Vary` header to the
+    /// variant matching the request
+    async fn lookup(&self, key: &str, headers: &HeaderMap) -> Option<CachedResponse> {
+        let cached = self.store.get(key).await?;
+        match cached.vary() {
+            Some(names) if names.is_empty() => Some(cached),
+            Some(names) => self.store.get(&variant_key(key, &names, headers)).await,
+            None => None,
+        }
+    }
+
+    /// Store a response under its key, or under the variant for the
+    /// request headers it varies on with an index entry at the key
+    async fn save(&self, key: &str, snapshot: CachedResponse, headers: &HeaderMap) {
+        let Some(names) = snapshot.vary() else {
+            return;
+        };
+        if names.is_empty() {
+            self.store.set(key, snapshot, self.config.ttl).await;
+            return;
+        }
+
+        let index = CachedResponse {
+            status: snapshot.status,
+            headers: vec![("vary".to_string(), names.join(", "))],
+            body: Vec::new(),
+        };
+        let variant = variant_key(key, &names, headers);
+        self.store.set(&variant, snapshot, self.config.ttl).await;
+        self.store.set(key, index, self.config.ttl).await;
+    }
+
+    /// Remove every cached response for a path
+    pub async fn invalidate_path(&self, path: &str) {
+        self.store
+            .remove_prefix(&format!("{}{}#", self.config.key_prefix, path))
+            .await;
+    }
+
+    /// Remove every cached response
+    pub async fn clear(&self) {
+        self.store.remove_prefix(&self.config.key_prefix).await;
+    }
+
+    /// Check if a response may be stored
+    ///
+    /// Responses to `credentialed` requests (`Authorization` or `Cookie`)
+    /// are stored only when marked `Cache-Control: public`.
+    fn is_cacheable(&self, res: &Response, credentialed: bool) -> bool {
+        if res.is_streaming() || !self.config.statuses.contains(&res.get_status().as_u16()) {
+            return false;
+        }
+
+        let headers = res.get_headers();
+        if headers.contains_key("set-cookie") {
+            return false;
+        }
+
+        let cache_control = headers
+            .get("cache-control")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase();
+        if cache_control.contains("no-store") || cache_control.contains("private") {
+            return false;
+        }
+        !credentialed || cache_control.contains("public")
+    }
+
+    /// Create the caching middleware
+    pub fn middleware(
+        &self,
+    ) -> impl Fn(
+        Request,
+        Response,
+        Next,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + Clone
+           + 'static {
+        let cache = self.clone();
+
+        move |req: Request, res: Response, next: Next| {
+            let cache = cache.clone();
+
+            Box::pin(async move {
+                if cache
+                    .config
+                    .skip_paths
+                    .iter()
+                    .any(|p| req.path().starts_with(p))
+                {
+                    return next(req, res).await;
+                }
+
+                if !cache.config.methods.contains(req.method()) {
+                    let is_write = matches!(
+                        *req.method(),
+                        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+                    );
+                    let path = req.path().to_string();
+                    let response = next(req, res).await;
+
+                    if is_write
+                        && cache.config.invalidate_on_write
+                        && response.get_status().is_success()
+                    {
+                        cache.invalidate_path(&path).await;
+                    }
+                    return response;
+                }
+
+                let key = cache.key(&req);
+                if let Some(cached) = cache.lookup(&key, req.headers()).await {
+                    return cached.apply(res).header("x-cache", "HIT");
+                }
+
+                let headers = req.headers().clone();
+                let credentialed =
+                    headers.contains_key("authorization") || headers.contains_key("cookie");
+                let response = next(req, res).await;
+                if cache.is_cacheable(&response, credentialed) {
+                    let snapshot = CachedResponse::from_response(&response);
+                    cache.save(&key, snapshot, &headers).await;
+                }
+                response.header("x-cache", "MISS")
+            })
+        }
+    }
+}
+
+/// Key of the variant of `key` for the request headers `names`
+fn variant_key(key: &str, names: &[String], headers: &HeaderMap) -> String {
+    let values: Vec<String> = names
+        .iter()
+        .map(|name| {
+            let value = headers
+                .get(name.as_str())
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            format!("{}={}", name, value)
+        })
+        .collect();
+    format!("{}#vary:{}", key, values.join("&"))
+}
+
+/// Create response caching middleware
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::cache::{cache, CacheConfig};
+///
+/// // Cache GET responses for 30 seconds, varying on Accept-Language
+/// app.use_middleware(cache(CacheConfig::new(30).vary(vec!["accept-language"])));
+/// ```
+pub fn cache(
+    config: CacheConfig,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    ResponseCache::new(config).middleware()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(body: &str) -> CachedResponse {
+        CachedResponse {
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/plain".to_string())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_evicts_when_full() {
+        let store = MemoryCacheStore::new(2);
+        store.set("a", snapshot("a"), Duration::from_secs(10)).await;
+        store.set("b", snapshot("b"), Duration::from_secs(20)).await;
+        store.set("c", snapshot("c"), Duration::from_secs(30)).await;
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get("a").await.is_none());
+        assert_eq!(store.get("c").await.unwrap().body, b"c");
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_remove_prefix() {
+        let store = MemoryCacheStore::new(10);
+        let ttl = Duration::from_secs(10);
+        store.set("p:/users#GET##", snapshot("list"), ttl).await;
+        store
+            .set("p:/users#GET#page=2#", snapshot("page"), ttl)
+            .await;
+        store.set("p:/users/1#GET##", snapshot("one"), ttl).await;
+
+        store.remove_prefix("p:/users#").await;
+
+        assert_eq!(store.len(), 1);
+        assert!(store.get("p:/users/1#GET##").await.is_some());
+    }
+
+    /// App whose handler counts calls, so cache hits are visible
+    fn counting_app(
+        configure: impl Fn(Response) -> Response + Send + Sync + 'static,
+    ) -> crate::RustyX {
+        let app = crate::RustyX::new();
+        app.use_middleware(cache(CacheConfig::new(60)));
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let configure = Arc::new(configure);
+        app.get("/", move |_req, res| {
+            let calls = Arc::clone(&calls);
+            let configure = Arc::clone(&configure);
+            async move {
+                let n = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                configure(res).send(n.to_string())
+            }
+        });
+        app
+    }
+
+    #[tokio::test]
+    async fn test_credentialed_responses_are_not_shared() {
+        let app = counting_app(|res| res);
+        for header in ["authorization", "cookie"] {
+            app.test()
+                .get("/")
+                .header(header, "alice")
+                .send()
+                .await
+                .assert_header("x-cache", "MISS");
+        }
+        let res = app.test().get("/").send().await;
+        res.assert_header("x-cache", "MISS");
+        assert_eq!(res.text(), "2");
+
+        let app = counting_app(|res| res.header("cache-control", "public, max-age=60"));
+        app.test().get("/").header("cookie", "a=1").send().await;
+        let res = app.test().get("/").send().await;
+        res.assert_header("x-cache", "HIT");
+        assert_eq!(res.text(), "0");
+    }
+
+    #[tokio::test]
+    async fn test_vary_headers_are_part_of_the_key() {
+        let app = crate::RustyX::new();
+        app.use_middleware(cache(CacheConfig::new(60)));
+        app.use_middleware(crate::middleware::cors_with_options(
+            crate::middleware::CorsOptions::new().origins(vec!["https://a.com", "https://b.com"]),
+        ));
+        app.get("/", |_req, res| async move { res.send("ok") });
+
+        let get = |origin: &'static str| app.test().get("/").header("origin", origin).send();
+        get("https://a.com").await.assert_header("x-cache", "MISS");
+        let res = get("https://b.com").await;
+        res.assert_header("x-cache", "MISS");
+        res.assert_header("access-control-allow-origin", "https://b.com");
+        let res = get("https://a.com").await;
+        res.assert_header("x-cache", "HIT");
+        res.assert_header("access-control-allow-origin", "https://a.com");
+    }
+
+    #[tokio::test]
+    async fn test_vary_star_is_not_cached() {
+        let app = counting_app(|res| res.header("vary", "*"));
+        app.test().get("/").send().await;
+        let res = app.test().get("/").send().await;
+        res.assert_header("x-cache", "MISS");
+        assert_eq!(res.text(), "1");
+    }
+}
@@ -0,0 +1,204 @@
+//! Conditional Middleware
+//!
+//! Wrappers that run a middleware only for matching requests, so skip logic
+//! doesn't have to live inside every middleware.
+
+use crate::middleware::{MiddlewareFn, Next};
+use crate::request::Request;
+use crate::response::Response;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Run `middleware` only when `predicate` returns true; otherwise call `next` directly
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::{only, timeout};
+///
+/// app.use_middleware(only(|req| req.path().starts_with("/api"), timeout(5000)));
+/// ```
+pub fn only<M, Fut, P>(
+    predicate: P,
+    middleware: M,
+) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static
+where
+    M: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+    P: Fn(&Request) -> bool + Send + Sync + 'static,
+{
+    let predicate = Arc::new(predicate);
+    let middleware = Arc::new(middleware);
+
+    move |req: Request, res: Response, next: Next| {
+        let predicate = Arc::clone(&predicate);
+        let middleware = Arc::clone(&middleware);
+
+        Box::pin(async move {
+            if predicate(&req) {
+                middleware(req, res, next).await
+            } else {
+                next(req, res).await
+            }
+        })
+    }
+}
+
+/// Run `middleware` unless `predicate` returns true
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::{logger, unless};
+///
+/// app.use_middleware(unless(|req| req.path() == "/health", logger()));
+/// ```
+pub fn unless<M, Fut, P>(
+    predicate: P,
+    middleware: M,
+) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static
+where
+    M: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+    P: Fn(&Request) -> bool + Send + Sync + 'static,
+{
+    only(move |req: &Request| !predicate(req), middleware)
+}
+
+/// Check if a request path matches any of the given prefixes
+fn matches_any(req: &Request, prefixes: &[String]) -> bool {
+    prefixes.iter().any(|p| {
+        let path = req.path();
+        path == p
+            || (path.starts_with(p.as_str())
+                && (p.ends_with('/') || path[p.len()..].starts_with('/')))
+    })
+}
+
+/// Method-style combinators for any middleware function
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::{helmet, logger, MiddlewareExt};
+///
+/// app.use_middleware(logger().unless(|req| req.path() == "/health"));
+/// app.use_middleware(helmet().only_paths(&["/admin"]));
+/// ```
+pub trait MiddlewareExt<Fut>: Sized {
+    /// Run this middleware only when `predicate` returns true
+    fn only<P>(self, predicate: P) -> MiddlewareFn
+    where
+        P: Fn(&Request) -> bool + Send + Sync + 'static;
+
+    /// Skip this middleware when `predicate` returns true
+    fn unless<P>(self, predicate: P) -> MiddlewareFn
+    where
+        P: Fn(&Request) -> bool + Send + Sync + 'static;
+
+    /// Run this middleware only for paths under the given prefixes
+    fn only_paths(self, paths: &[&str]) -> MiddlewareFn {
+        let paths: Vec<String> = paths.iter().map(|s| s.to_string()).collect();
+        self.only(move |req| matches_any(req, &paths))
+    }
+
+    /// Skip this middleware for paths under the given prefixes
+    fn unless_paths(self, paths: &[&str]) -> MiddlewareFn {
+        let paths: Vec<String> = paths.iter().map(|s| s.to_string()).collect();
+        self.unless(move |req| matches_any(req, &paths))
+    }
+
+    /// Run this middleware only for the given HTTP methods
+    fn only_methods(self, methods: &[hyper::Method]) -> MiddlewareFn {
+        let methods = methods.to_vec();
+        self.only(move |req| methods.contains(req.method()))
+    }
+}
+
+impl<M, Fut> MiddlewareExt<Fut> for M
+where
+    M: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    fn only<P>(self, predicate: P) -> MiddlewareFn
+    where
+        P: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        Box::new(only(predicate, self))
+    }
+
+    fn unless<P>(self, predicate: P) -> MiddlewareFn
+    where
+        P: Fn(&Request) -> bool + Send + Sync + 'static,
+    {
+        Box::new(unless(predicate, self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::Method;
+
+    /// Middleware that marks the responses it ran for
+    fn tag(
+        req: Request,
+        res: Response,
+        next: Next,
+    ) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+        Box::pin(async move { next(req, res).await.header("x-tagged", "1") })
+    }
+
+    /// Run `middleware` for a request and report whether it ran
+    async fn ran<F>(middleware: &F, method: Method, path: &str) -> bool
+    where
+        F: Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>,
+    {
+        let next: Next = Arc::new(|_req, res| Box::pin(async move { res }));
+        let req = Request::builder().method(method).path(path).build();
+        let res = middleware(req, Response::new(), next).await;
+        res.get_headers().contains_key("x-tagged")
+    }
+
+    #[tokio::test]
+    async fn test_only_and_unless() {
+        let api = |req: &Request| req.path().starts_with("/api");
+        assert!(ran(&only(api, tag), Method::GET, "/api/users").await);
+        assert!(!ran(&only(api, tag), Method::GET, "/health").await);
+        assert!(!ran(&unless(api, tag), Method::GET, "/api/users").await);
+        assert!(ran(&unless(api, tag), Method::GET, "/health").await);
+
+        assert!(ran(&tag.only(api), Method::GET, "/api").await);
+        assert!(!ran(&tag.unless(api), Method::GET, "/api").await);
+    }
+
+    #[tokio::test]
+    async fn test_only_methods() {
+        let writes = tag.only_methods(&[Method::POST, Method::DELETE]);
+        assert!(ran(&writes, Method::POST, "/").await);
+        assert!(ran(&writes, Method::DELETE, "/").await);
+        assert!(!ran(&writes, Method::GET, "/").await);
+    }
+
+    #[tokio::test]
+    async fn test_path_prefixes_match_whole_segments() {
+        let api = tag.only_paths(&["/api"]);
+        assert!(ran(&api, Method::GET, "/api").await);
+        assert!(ran(&api, Method::GET, "/api/x").await);
+        assert!(!ran(&api, Method::GET, "/apix").await);
+        assert!(!ran(&api, Method::GET, "/").await);
+
+        let public = tag.unless_paths(&["/public/"]);
+        assert!(!ran(&public, Method::GET, "/public/a.css").await);
+        assert!(ran(&public, Method::GET, "/publicity").await);
+    }
+}
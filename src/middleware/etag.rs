@@ -0,0 +1,208 @@
+//! Conditional GET Middleware
+//!
+//! Generates ETags from response bodies and answers `If-None-Match` /
+//! `If-Modified-Since` requests with `304 Not Modified`.
+
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use hyper::Method;
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+/// ETag middleware options
+#[derive(Debug, Clone, Default)]
+pub struct EtagOptions {
+    /// Emit weak validators (`W/"..."`)
+    pub weak: bool,
+    /// Skip hashing bodies smaller than this many bytes
+    pub min_size: usize,
+}
+
+impl EtagOptions {
+    /// Create default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emit weak ETags
+    pub fn weak(mut self, weak: bool) -> Self {
+        self.weak = weak;
+        self
+    }
+
+    /// Set the minimum body size to hash
+    pub fn min_size(mut self, bytes: usize) -> Self {
+        self.min_size = bytes;
+        self
+    }
+}
+
+/// Compute an ETag for a response body
+pub fn compute_etag(body: &[u8], weak: bool) -> String {
+    let digest = Sha256::digest(body);
+    let hash: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+
+    if weak {
+        format!("W/\"{}\"", hash)
+    } else {
+        format!("\"{}\"", hash)
+    }
+}
+
+/// Check an `If-None-Match` header against an ETag (weak comparison)
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let strip = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = strip(etag);
+
+    if_none_match
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || strip(candidate) == etag)
+}
+
+/// Check whether a resource modified at `last_modified` is unchanged since `if_modified_since`
+pub fn not_modified_since(if_modified_since: &str, last_modified: &str) -> bool {
+    let parse = |value: &str| httpdate::parse_http_date(value.trim()).ok();
+
+    match (parse(if_modified_since), parse(last_modified)) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
+
+/// Format a timestamp as an HTTP date (for `Last-Modified`)
+pub fn http_date(time: SystemTime) -> String {
+    httpdate::fmt_http_date(time)
+}
+
+/// Conditional GET middleware with default options
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::etag;
+///
+/// app.use_middleware(etag());
+/// ```
+pub fn etag() -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    etag_with_options(EtagOptions::default())
+}
+
+/// Conditional GET middleware
+///
+/// Adds an `ETag` to successful GET/HEAD responses that don't already carry
+/// one. `If-None-Match` takes precedence over `If-Modified-Since`; the latter
+/// is only evaluated when the handler set a `Last-Modified` header.
+pub fn etag_with_options(
+    options: EtagOptions,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    move |req: Request, res: Response, next: Next| {
+        let options = options.clone();
+
+        Box::pin(async move {
+            if req.method() != Method::GET && req.method() != Method::HEAD {
+                return next(req, res).await;
+            }
+
+            let if_none_match = req.header("if-none-match").map(str::to_string);
+            let if_modified_since = req.header("if-modified-since").map(str::to_string);
+
+            let mut response = next(req, res).await;
+            if response.get_status().as_u16() != 200 {
+                return response;
+            }
+
+            let existing = response
+                .get_headers()
+                .get("etag")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let etag = match existing {
+                Some(tag) => Some(tag),
+                // Streamed bodies are never read, but may carry an upstream ETag
+                None if !response.is_streaming()
+                    && response.get_body().len() >= options.min_size =>
+                {
+                    let tag = compute_etag(response.get_body(), options.weak);
+                    response = response.header("etag", &tag);
+                    Some(tag)
+                }
+                None => None,
+            };
+
+            let not_modified = match (&if_none_match, &etag) {
+                (Some(header), Some(tag)) => etag_matches(header, tag),
+                (Some(_), None) => false,
+                (None, _) => match (
+                    &if_modified_since,
+                    response.get_headers().get("last-modified"),
+                ) {
+                    (Some(since), Some(modified)) => modified
+                        .to_str()
+                        .map(|modified| not_modified_since(since, modified))
+                        .unwrap_or(false),
+                    _ => false,
+                },
+            };
+
+            if not_modified {
+                response
+                    .status(304)
+                    .remove_header("content-type")
+                    .remove_header("content-length")
+                    .send_bytes(Vec::new())
+            } else {
+                response
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_etag_matching() {
+        let tag = compute_etag(b"hello", false);
+        assert!(tag.starts_with('"') && tag.ends_with('"'));
+        assert!(etag_matches(&tag, &tag));
+        assert!(etag_matches(&format!("\"other\", W/{}", tag), &tag));
+        assert!(etag_matches("*", &tag));
+        assert!(!etag_matches("\"other\"", &tag));
+    }
+
+    #[test]
+    fn test_not_modified_since() {
+        let modified = "Wed, 21 Oct 2015 07:28:00 GMT";
+        assert!(not_modified_since(
+            "Wed, 21 Oct 2015 07:28:00 GMT",
+            modified
+        ));
+        assert!(not_modified_since(
+            "Thu, 22 Oct 2015 07:28:00 GMT",
+            modified
+        ));
+        assert!(!not_modified_since(
+            "Tue, 20 Oct 2015 07:28:00 GMT",
+            modified
+        ));
+        assert!(!not_modified_since("garbage", modified));
+    }
+}
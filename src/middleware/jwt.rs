@@ -0,0 +1,89 @@
+//! JWT Authentication Middleware
+//!
+//! Verifies the bearer token with a [`Jwt`] and places a [`Principal`]
+//! built from its claims in the request extensions, for guards such as
+//! [`authorize`](super::authorize()).
+
+use crate::error::Error;
+use crate::middleware::{Next, Principal};
+use crate::request::Request;
+use crate::response::Response;
+use crate::utils::jwt::Jwt;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Require a valid bearer token, answering 401 otherwise
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let jwt = Jwt::new(JwtKey::hs256(secret.as_bytes()));
+/// app.use_middleware(jwt_auth(jwt));
+/// app.use_middleware(authorize(&["admin"]));
+/// ```
+pub fn jwt_auth(
+    jwt: Jwt,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    let jwt = Arc::new(jwt);
+
+    move |mut req: Request, res: Response, next: Next| {
+        let jwt = Arc::clone(&jwt);
+
+        Box::pin(async move {
+            let claims = match req.bearer_token() {
+                Some(token) => jwt.verify::<Value>(token),
+                None => Err(Error::unauthorized("missing bearer token")),
+            };
+            match claims {
+                Ok(claims) => {
+                    req.extensions_mut().insert(Principal::from_claims(&claims));
+                    next(req, res).await
+                }
+                Err(error) => res
+                    .unauthorized()
+                    .header("www-authenticate", "Bearer")
+                    .with_error(error),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::jwt::{Claims, JwtKey};
+
+    #[tokio::test]
+    async fn test_jwt_auth() {
+        let jwt = Jwt::new(JwtKey::hs256("secret"));
+        let token = jwt
+            .sign(&Claims::new("42").claim("roles", "admin"))
+            .unwrap();
+        let middleware = jwt_auth(jwt);
+        let next: Next = Arc::new(|req: Request, res: Response| {
+            Box::pin(async move {
+                let principal = req.extensions().get::<Principal>().cloned().unwrap();
+                res.json(serde_json::json!({ "id": principal.id, "roles": principal.roles }))
+            })
+        });
+
+        let req = Request::builder()
+            .header("authorization", &format!("Bearer {}", token))
+            .build();
+        let res = middleware(req, Response::new(), Arc::clone(&next)).await;
+        let body: Value = res.body_json().unwrap();
+        assert_eq!(body, serde_json::json!({ "id": "42", "roles": ["admin"] }));
+
+        let res = middleware(Request::builder().build(), Response::new(), next).await;
+        assert_eq!(res.get_status(), 401);
+        assert_eq!(res.get_headers()["www-authenticate"], "Bearer");
+    }
+}
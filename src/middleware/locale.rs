@@ -0,0 +1,215 @@
+//! Locale Negotiation Middleware
+//!
+//! Resolves the request locale from a query parameter, a cookie or the
+//! `Accept-Language` header and exposes it via [`Request::locale`],
+//! [`Request::t`] and the [`t!`](crate::t) macro.
+
+use crate::i18n::{self, normalize, Catalog, Locale, SharedCatalog};
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use std::sync::Arc;
+
+/// Locale middleware options
+#[derive(Debug, Clone)]
+pub struct LocaleOptions {
+    /// Supported locales (empty = accept anything)
+    pub supported: Vec<String>,
+    /// Locale used when nothing matches
+    pub default_locale: String,
+    /// Query parameter checked first (e.g. `?lang=fr`)
+    pub query_param: Option<String>,
+    /// Cookie checked second
+    pub cookie_name: Option<String>,
+    /// Message catalog exposed to handlers via `req.t()`
+    pub catalog: Option<Arc<Catalog>>,
+}
+
+impl Default for LocaleOptions {
+    fn default() -> Self {
+        Self {
+            supported: Vec::new(),
+            default_locale: crate::i18n::DEFAULT_LOCALE.to_string(),
+            query_param: Some("lang".to_string()),
+            cookie_name: Some("locale".to_string()),
+            catalog: None,
+        }
+    }
+}
+
+impl LocaleOptions {
+    /// Create options with the given supported locales
+    pub fn new(supported: Vec<&str>) -> Self {
+        Self {
+            supported: supported.iter().map(|s| normalize(s)).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the default locale
+    pub fn default_locale(mut self, locale: &str) -> Self {
+        self.default_locale = normalize(locale);
+        self
+    }
+
+    /// Set the query parameter name (`None` disables it)
+    pub fn query_param(mut self, name: Option<&str>) -> Self {
+        self.query_param = name.map(str::to_string);
+        self
+    }
+
+    /// Set the cookie name (`None` disables it)
+    pub fn cookie_name(mut self, name: Option<&str>) -> Self {
+        self.cookie_name = name.map(str::to_string);
+        self
+    }
+
+    /// Attach a message catalog
+    pub fn catalog(mut self, catalog: Catalog) -> Self {
+        self.catalog = Some(Arc::new(catalog));
+        self
+    }
+
+    /// Match a requested tag against the supported locales
+    ///
+    /// Tries an exact match, then the primary language (`fr-CA` -> `fr`),
+    /// then any supported region of the same language (`en` -> `en-US`).
+    fn matching(&self, tag: &str) -> Option<String> {
+        let tag = normalize(tag);
+        if tag.is_empty() || tag == "*" {
+            return None;
+        }
+        if self.supported.is_empty() {
+            return Some(tag);
+        }
+
+        let primary = tag.split('-').next().unwrap_or(&tag).to_string();
+        self.supported
+            .iter()
+            .find(|s| **s == tag)
+            .or_else(|| self.supported.iter().find(|s| **s == primary))
+            .or_else(|| {
+                self.supported
+                    .iter()
+                    .find(|s| s.split('-').next() == Some(primary.as_str()))
+            })
+            .cloned()
+    }
+
+    /// Resolve the locale for a request
+    pub fn resolve(&self, req: &Request) -> String {
+        let from_query = self
+            .query_param
+            .as_ref()
+            .and_then(|name| req.query_param(name))
+            .and_then(|tag| self.matching(tag));
+        let from_cookie = || {
+            self.cookie_name
+                .as_ref()
+                .and_then(|name| req.cookie(name))
+                .and_then(|tag| self.matching(&tag))
+        };
+        let from_header = || {
+            req.header("accept-language")
+                .map(parse_accept_language)
+                .and_then(|tags| tags.iter().find_map(|tag| self.matching(tag)))
+        };
+
+        from_query
+            .or_else(from_cookie)
+            .or_else(from_header)
+            .unwrap_or_else(|| self.default_locale.clone())
+    }
+}
+
+/// Parse an `Accept-Language` header into tags ordered by quality
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut tags: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.split(';');
+            let tag = pieces.next()?.trim();
+            if tag.is_empty() {
+                return None;
+            }
+            let quality = pieces
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (quality > 0.0).then(|| (tag.to_string(), quality))
+        })
+        .collect();
+
+    // Stable sort keeps header order for equal weights
+    tags.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    tags.into_iter().map(|(tag, _)| tag).collect()
+}
+
+/// Locale negotiation middleware
+///
+/// Sets `Content-Language` on the response unless the handler already did.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::i18n::Catalog;
+/// use rustyx::middleware::{locale, LocaleOptions};
+///
+/// let catalog = Catalog::load_dir("locales", "en")?;
+/// app.use_middleware(locale(LocaleOptions::new(vec!["en", "fr", "de"]).catalog(catalog)));
+///
+/// app.get("/", |req, res| async move {
+///     res.send(req.t("home.welcome"))
+/// });
+/// ```
+pub fn locale(
+    options: LocaleOptions,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    let options = Arc::new(options);
+
+    move |mut req: Request, res: Response, next: Next| {
+        let options = Arc::clone(&options);
+
+        Box::pin(async move {
+            let resolved = options.resolve(&req);
+            req.extensions_mut().insert(Locale(resolved.clone()));
+            if let Some(catalog) = &options.catalog {
+                req.extensions_mut()
+                    .insert(SharedCatalog(Arc::clone(catalog)));
+            }
+
+            let catalog = options.catalog.clone();
+            let response = i18n::scope(resolved.clone(), catalog, next(req, res)).await;
+            if response.get_headers().contains_key("content-language") {
+                response
+            } else {
+                response.header("content-language", &resolved)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language_negotiation() {
+        let tags = parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5");
+        assert_eq!(tags, vec!["fr-CH", "fr", "en", "de", "*"]);
+
+        let options = LocaleOptions::new(vec!["en-US", "de"]);
+        let resolved = tags.iter().find_map(|t| options.matching(t));
+        assert_eq!(resolved.as_deref(), Some("en-US"));
+        assert_eq!(options.matching("DE-at").as_deref(), Some("de"));
+        assert_eq!(options.matching("ja"), None);
+    }
+}
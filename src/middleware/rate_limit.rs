@@ -2,6 +2,7 @@
 //!
 //! Provides rate limiting functionality to protect APIs from abuse.
 
+use crate::cache::Cache;
 use crate::middleware::Next;
 use crate::request::Request;
 use crate::response::Response;
@@ -21,6 +22,24 @@ pub struct RateLimiterConfig {
     pub message: String,
     /// Skip rate limiting for certain paths
     pub skip_paths: Vec<String>,
+    /// Which rate limit headers to send
+    pub headers: RateLimitHeaders,
+    /// Count requests in a shared cache instead of in this process
+    pub cache: Option<Cache>,
+}
+
+/// Rate limit header styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitHeaders {
+    /// No rate limit headers
+    None,
+    /// Legacy `X-RateLimit-*` headers
+    Legacy,
+    /// IETF draft `RateLimit-Limit`, `RateLimit-Remaining`, `RateLimit-Reset` and `RateLimit-Policy`
+    Standard,
+    /// Both legacy and standard headers
+    #[default]
+    Both,
 }
 
 impl Default for RateLimiterConfig {
@@ -30,6 +49,8 @@ impl Default for RateLimiterConfig {
             window: Duration::from_secs(60),
             message: "Too many requests. Please try again later.".to_string(),
             skip_paths: vec![],
+            headers: RateLimitHeaders::default(),
+            cache: None,
         }
     }
 }
@@ -55,6 +76,19 @@ impl RateLimiterConfig {
         self.skip_paths = paths.iter().map(|s| s.to_string()).collect();
         self
     }
+
+    /// Set which rate limit headers to send
+    pub fn headers(mut self, headers: RateLimitHeaders) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Keep the counters in `cache`, e.g. [`Cache::redis`] to share limits
+    /// between instances
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
 }
 
 /// Rate limiter entry for tracking requests
@@ -82,6 +116,11 @@ impl RateLimiter {
 
     /// Check if a request should be allowed
     pub fn check(&self, key: &str) -> RateLimitResult {
+        self.check_limit(key, self.config.max_requests)
+    }
+
+    /// Like [`check`](Self::check), allowing `limit` requests per window
+    pub fn check_limit(&self, key: &str, limit: u32) -> RateLimitResult {
         let mut entries = self.entries.write();
         let now = Instant::now();
 
@@ -98,37 +137,127 @@ impl RateLimiter {
 
         entry.count += 1;
 
-        if entry.count > self.config.max_requests {
-            let retry_after = self.config.window.as_secs() as u32
-                - now.duration_since(entry.window_start).as_secs() as u32;
+        let reset = reset_secs(
+            self.config
+                .window
+                .saturating_sub(now.duration_since(entry.window_start)),
+        );
 
+        if entry.count > limit {
             RateLimitResult::Exceeded {
-                retry_after,
-                limit: self.config.max_requests,
+                retry_after: reset,
+                limit,
                 remaining: 0,
             }
         } else {
             RateLimitResult::Allowed {
-                limit: self.config.max_requests,
-                remaining: self.config.max_requests - entry.count,
+                limit,
+                remaining: limit - entry.count,
+                reset,
+            }
+        }
+    }
+
+    /// Count a request against the configured cache, or in this process
+    /// without one
+    ///
+    /// Requests are allowed when the cache fails.
+    pub async fn hit(&self, key: &str) -> RateLimitResult {
+        self.hit_limit(key, self.config.max_requests).await
+    }
+
+    /// Like [`hit`](Self::hit), allowing `limit` requests per window
+    pub async fn hit_limit(&self, key: &str, limit: u32) -> RateLimitResult {
+        let Some(cache) = &self.config.cache else {
+            return self.check_limit(key, limit);
+        };
+        match cache
+            .increment(&format!("ratelimit:{}", key), 1, self.config.window)
+            .await
+        {
+            Ok((count, left)) => {
+                let reset = reset_secs(left.min(self.config.window));
+                if count > limit as i64 {
+                    RateLimitResult::Exceeded {
+                        retry_after: reset,
+                        limit,
+                        remaining: 0,
+                    }
+                } else {
+                    RateLimitResult::Allowed {
+                        limit,
+                        remaining: limit - count.max(0) as u32,
+                        reset,
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Rate limit cache failed: {}", e);
+                RateLimitResult::Allowed {
+                    limit,
+                    remaining: limit,
+                    reset: reset_secs(self.config.window),
+                }
             }
         }
     }
 
+    /// Add the configured rate limit headers to a response
+    pub fn apply_headers(&self, res: Response, limit: u32, remaining: u32, reset: u32) -> Response {
+        let mut res = res;
+        let (limit, remaining, reset) =
+            (limit.to_string(), remaining.to_string(), reset.to_string());
+
+        if matches!(
+            self.config.headers,
+            RateLimitHeaders::Legacy | RateLimitHeaders::Both
+        ) {
+            res = res
+                .header("X-RateLimit-Limit", &limit)
+                .header("X-RateLimit-Remaining", &remaining)
+                .header("X-RateLimit-Reset", &reset);
+        }
+        if matches!(
+            self.config.headers,
+            RateLimitHeaders::Standard | RateLimitHeaders::Both
+        ) {
+            res = res
+                .header("RateLimit-Limit", &limit)
+                .header("RateLimit-Remaining", &remaining)
+                .header("RateLimit-Reset", &reset)
+                .header(
+                    "RateLimit-Policy",
+                    &format!("{};w={}", limit, self.config.window.as_secs()),
+                );
+        }
+
+        res
+    }
+
     /// Get the config
     pub fn config(&self) -> &RateLimiterConfig {
         &self.config
     }
 }
 
+/// Whole seconds until a window resets, rounded up so clients never see
+/// (and retry after) 0 while the window is still open
+fn reset_secs(left: Duration) -> u32 {
+    let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+    secs.clamp(1, u32::MAX as u64) as u32
+}
+
 /// Result of rate limit check
 #[derive(Debug, Clone)]
 pub enum RateLimitResult {
     Allowed {
         limit: u32,
         remaining: u32,
+        /// Seconds until the window resets (at least 1)
+        reset: u32,
     },
     Exceeded {
+        /// Seconds until the window resets (at least 1)
         retry_after: u32,
         limit: u32,
         remaining: u32,
@@ -137,6 +266,9 @@ pub enum RateLimitResult {
 
 /// Create rate limiting middleware
 ///
+/// Sends both `X-RateLimit-*` and IETF draft `RateLimit-*` headers by
+/// default; see [`RateLimiterConfig::headers`].
+///
 /// # Example
 ///
 /// ```rust,ignore
@@ -168,24 +300,32 @@ pub fn rate_limiter(
                 return next(req, res).await;
             }
 
-            // Use IP address as the rate limit key
-            let key = req.ip().to_string();
+            // Use IP address as the rate limit key, counted per tenant with
+            // the tenant's own limit when there is one
+            let (key, limit) = match req.tenant() {
+                Some(tenant) => (
+                    format!("{}:{}", tenant.id, req.ip()),
+                    tenant.rate_limit.unwrap_or(config.max_requests),
+                ),
+                None => (req.ip().to_string(), config.max_requests),
+            };
 
-            match limiter.check(&key) {
-                RateLimitResult::Allowed { limit, remaining } => {
-                    let res = res
-                        .header("X-RateLimit-Limit", &limit.to_string())
-                        .header("X-RateLimit-Remaining", &remaining.to_string());
+            match limiter.hit_limit(&key, limit).await {
+                RateLimitResult::Allowed {
+                    limit,
+                    remaining,
+                    reset,
+                } => {
+                    // Headers are set before the handler runs so handlers can override them
+                    let res = limiter.apply_headers(res, limit, remaining, reset);
                     next(req, res).await
                 }
                 RateLimitResult::Exceeded {
                     retry_after,
                     limit,
-                    remaining: _,
-                } => res
-                    .status(429)
-                    .header("X-RateLimit-Limit", &limit.to_string())
-                    .header("X-RateLimit-Remaining", "0")
+                    remaining,
+                } => limiter
+                    .apply_headers(res.status(429), limit, remaining, retry_after)
                     .header("Retry-After", &retry_after.to_string())
                     .json(serde_json::json!({
                         "error": "Too Many Requests",
@@ -212,3 +352,48 @@ pub fn simple_rate_limit(
        + 'static {
     rate_limiter(RateLimiterConfig::new(max_requests, window_secs))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_headers() {
+        let limiter =
+            RateLimiter::new(RateLimiterConfig::new(2, 60).headers(RateLimitHeaders::Standard));
+
+        assert!(matches!(
+            limiter.check("ip"),
+            RateLimitResult::Allowed { remaining: 1, .. }
+        ));
+        limiter.check("ip");
+        assert!(matches!(
+            limiter.check("ip"),
+            RateLimitResult::Exceeded { limit: 2, .. }
+        ));
+
+        let res = limiter.apply_headers(Response::new(), 2, 0, 30);
+        let headers = res.get_headers();
+        assert_eq!(headers.get("ratelimit-limit").unwrap(), "2");
+        assert_eq!(headers.get("ratelimit-reset").unwrap(), "30");
+        assert_eq!(headers.get("ratelimit-policy").unwrap(), "2;w=60");
+        assert!(headers.get("x-ratelimit-limit").is_none());
+    }
+
+    #[test]
+    fn test_reset_is_never_zero() {
+        assert_eq!(reset_secs(Duration::ZERO), 1);
+        assert_eq!(reset_secs(Duration::from_millis(300)), 1);
+        assert_eq!(reset_secs(Duration::from_millis(1500)), 2);
+        assert_eq!(reset_secs(Duration::from_secs(60)), 60);
+
+        let mut config = RateLimiterConfig::new(1, 1);
+        config.window = Duration::from_millis(200);
+        let limiter = RateLimiter::new(config);
+        limiter.check("ip");
+        assert!(matches!(
+            limiter.check("ip"),
+            RateLimitResult::Exceeded { retry_after: 1, .. }
+        ));
+    }
+}
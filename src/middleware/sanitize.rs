@@ -0,0 +1,285 @@
+//! Input Sanitization Middleware
+//!
+//! Recursively cleans string values in JSON and form-encoded request bodies
+//! before they reach handlers, templates or the database.
+
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use serde_json::Value;
+
+/// Sanitization options
+#[derive(Debug, Clone)]
+pub struct SanitizeOptions {
+    /// Escape `& < > " '` as HTML entities
+    pub html_escape: bool,
+    /// Remove control characters (except newline, carriage return and tab)
+    pub strip_control: bool,
+    /// Trim leading and trailing whitespace
+    pub trim: bool,
+    /// Reject the request with 400 when a value contains a null byte
+    pub reject_null_bytes: bool,
+    /// Fields left untouched, by name (`bio`) or dotted path (`user.bio`)
+    pub allow_fields: Vec<String>,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> Self {
+        Self {
+            html_escape: true,
+            strip_control: true,
+            trim: true,
+            reject_null_bytes: true,
+            allow_fields: Vec::new(),
+        }
+    }
+}
+
+impl SanitizeOptions {
+    /// Create default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable HTML escaping
+    pub fn html_escape(mut self, enabled: bool) -> Self {
+        self.html_escape = enabled;
+        self
+    }
+
+    /// Enable or disable control character stripping
+    pub fn strip_control(mut self, enabled: bool) -> Self {
+        self.strip_control = enabled;
+        self
+    }
+
+    /// Enable or disable whitespace trimming
+    pub fn trim(mut self, enabled: bool) -> Self {
+        self.trim = enabled;
+        self
+    }
+
+    /// Enable or disable null byte rejection
+    pub fn reject_null_bytes(mut self, enabled: bool) -> Self {
+        self.reject_null_bytes = enabled;
+        self
+    }
+
+    /// Leave the given fields untouched
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// // Rich-text fields are sanitized by the editor pipeline instead
+    /// let options = SanitizeOptions::new().allow(vec!["body_html", "profile.bio"]);
+    /// ```
+    pub fn allow(mut self, fields: Vec<&str>) -> Self {
+        self.allow_fields = fields.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Check if a field path is allowlisted
+    fn is_allowed(&self, path: &str) -> bool {
+        let name = path.rsplit('.').next().unwrap_or(path);
+        self.allow_fields.iter().any(|f| f == path || f == name)
+    }
+}
+
+/// Error raised when a value is rejected
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizeError {
+    /// Dotted path of the offending field
+    pub field: String,
+}
+
+impl std::fmt::Display for SanitizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Field contains a null byte: {}", self.field)
+    }
+}
+
+impl std::error::Error for SanitizeError {}
+
+/// Escape HTML special characters
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Sanitize a single string value
+pub fn sanitize_str(
+    value: &str,
+    field: &str,
+    options: &SanitizeOptions,
+) -> Result<String, SanitizeError> {
+    if options.reject_null_bytes && value.contains('\0') {
+        return Err(SanitizeError {
+            field: field.to_string(),
+        });
+    }
+
+    let mut result: String = if options.strip_control {
+        value
+            .chars()
+            .filter(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+            .collect()
+    } else {
+        value.to_string()
+    };
+
+    if options.trim {
+        result = result.trim().to_string();
+    }
+    if options.html_escape {
+        result = escape_html(&result);
+    }
+
+    Ok(result)
+}
+
+/// Recursively sanitize every string in a JSON value
+///
+/// Array elements share their parent's path, so allowlisting `tags`
+/// covers every entry of a `tags` array.
+pub fn sanitize_value(
+    value: &mut Value,
+    path: &str,
+    options: &SanitizeOptions,
+) -> Result<(), SanitizeError> {
+    if !path.is_empty() && options.is_allowed(path) {
+        return Ok(());
+    }
+
+    match value {
+        Value::String(s) => *s = sanitize_str(s, path, options)?,
+        Value::Array(items) => {
+            for item in items {
+                sanitize_value(item, path, options)?;
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                sanitize_value(item, &child, options)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Sanitize a form-urlencoded body
+fn sanitize_form(body: &[u8], options: &SanitizeOptions) -> Result<String, SanitizeError> {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in url::form_urlencoded::parse(body) {
+        let value = if options.is_allowed(&key) {
+            value.into_owned()
+        } else {
+            sanitize_str(&value, &key, options)?
+        };
+        serializer.append_pair(&key, &value);
+    }
+    Ok(serializer.finish())
+}
+
+/// Input sanitization middleware
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::sanitize::{sanitize, SanitizeOptions};
+///
+/// app.use_middleware(sanitize(SanitizeOptions::new().allow(vec!["password"])));
+/// ```
+pub fn sanitize(
+    options: SanitizeOptions,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    move |mut req: Request, res: Response, next: Next| {
+        let options = options.clone();
+
+        Box::pin(async move {
+            if req.body().is_empty() {
+                return next(req, res).await;
+            }
+
+            let content_type = req.content_type().unwrap_or("").to_lowercase();
+            let result = if content_type.contains("application/json") {
+                match serde_json::from_slice::<Value>(req.body()) {
+                    Ok(mut value) => sanitize_value(&mut value, "", &options)
+                        .map(|_| serde_json::to_vec(&value).ok()),
+                    // Leave malformed JSON for the handler to report
+                    Err(_) => Ok(None),
+                }
+            } else if content_type.contains("application/x-www-form-urlencoded") {
+                sanitize_form(req.body(), &options).map(|form| Some(form.into_bytes()))
+            } else {
+                Ok(None)
+            };
+
+            match result {
+                Ok(Some(body)) => {
+                    req.set_body(body);
+                    next(req, res).await
+                }
+                Ok(None) => next(req, res).await,
+                Err(e) => res.status(400).json(serde_json::json!({
+                    "error": "Invalid input",
+                    "message": e.to_string(),
+                    "field": e.field
+                })),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sanitize_value() {
+        let options = SanitizeOptions::new().allow(vec!["profile.bio"]);
+        let mut value = json!({
+            "name": "  <b>Ann</b>\u{7}  ",
+            "tags": [" a&b "],
+            "profile": { "bio": "<i>kept</i>" }
+        });
+
+        sanitize_value(&mut value, "", &options).unwrap();
+
+        assert_eq!(value["name"], "&lt;b&gt;Ann&lt;/b&gt;");
+        assert_eq!(value["tags"][0], "a&amp;b");
+        assert_eq!(value["profile"]["bio"], "<i>kept</i>");
+    }
+
+    #[test]
+    fn test_rejects_null_bytes() {
+        let mut value = json!({ "user": { "name": "a\u{0}b" } });
+        let err = sanitize_value(&mut value, "", &SanitizeOptions::new()).unwrap_err();
+        assert_eq!(err.field, "user.name");
+    }
+}
@@ -0,0 +1,51 @@
+//! Signed URL Middleware
+//!
+//! Rejects requests whose URL was not produced by
+//! [`signed_url::sign`](crate::utils::signed_url::sign) with the same
+//! secret, or has expired.
+
+use crate::middleware::Next;
+use crate::request::Request;
+use crate::response::Response;
+use std::sync::Arc;
+
+/// Require a valid, unexpired signature, answering 403 otherwise
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut links = Router::new();
+/// links.use_middleware(signed(secret));
+/// links.get("/unsubscribe", unsubscribe);
+/// app.use_router("/links", links);
+/// ```
+pub fn signed(
+    secret: &[u8],
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    let secret: Arc<[u8]> = secret.into();
+
+    move |req: Request, res: Response, next: Next| {
+        let secret = Arc::clone(&secret);
+
+        Box::pin(async move {
+            match req.verify_signature(&secret) {
+                Ok(()) => next(req, res).await,
+                Err(error) => res
+                    .status(403)
+                    .json(serde_json::json!({
+                        "error": "Forbidden",
+                        "message": error.to_string(),
+                    }))
+                    .with_error(error),
+            }
+        })
+    }
+}
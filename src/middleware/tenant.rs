@@ -0,0 +1,400 @@
+//! Tenant Resolution Middleware
+//!
+//! Resolves the request's [`Tenant`] from the subdomain, a header or a
+//! claim of the bearer token and exposes it via [`Request::tenant`] and
+//! [`tenancy::current`](crate::tenancy::current).
+//!
+//! Subdomains and headers are chosen by the client. Only tenants from a
+//! [`Tenants`] registry or a [`resolver`](TenancyOptions::resolver) get a
+//! schema or database of their own, and
+//! [`match_claim`](TenancyOptions::match_claim) checks the tenant against the
+//! signed-in user's token.
+
+use crate::error::Error;
+use crate::middleware::{Next, Principal};
+use crate::request::Request;
+use crate::response::Response;
+use crate::tenancy::{self, Tenant, TenantStorage, Tenants};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Async tenant lookup used by [`TenancyOptions::resolver`]
+pub type TenantResolver =
+    Arc<dyn Fn(String) -> Pin<Box<dyn Future<Output = Option<Tenant>> + Send>> + Send + Sync>;
+
+/// Where the tenant id is read from
+#[derive(Debug, Clone, PartialEq)]
+pub enum TenantSource {
+    /// The label before this base domain, e.g. `acme` in `acme.example.com`
+    Subdomain(String),
+    /// A request header, e.g. `X-Tenant`; any client can send any value,
+    /// see [`TenancyOptions::match_claim`]
+    Header(String),
+    /// A claim of the token verified by [`jwt_auth`](super::jwt_auth()),
+    /// which must run first
+    Claim(String),
+}
+
+/// Tenancy middleware options
+#[derive(Clone)]
+pub struct TenancyOptions {
+    /// Sources tried in order
+    pub sources: Vec<TenantSource>,
+    /// Known tenants; without a registry or resolver any well-formed id is
+    /// accepted as a [`Shared`](TenantStorage::Shared) tenant
+    pub tenants: Option<Tenants>,
+    /// Answer 400 when no tenant is found (otherwise the request runs without one)
+    pub required: bool,
+    /// Claim of the signed-in user's token that must name the tenant
+    pub match_claim: Option<String>,
+    resolver: Option<TenantResolver>,
+}
+
+impl std::fmt::Debug for TenancyOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenancyOptions")
+            .field("sources", &self.sources)
+            .field("tenants", &self.tenants)
+            .field("required", &self.required)
+            .field("match_claim", &self.match_claim)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for TenancyOptions {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            tenants: None,
+            required: true,
+            match_claim: None,
+            resolver: None,
+        }
+    }
+}
+
+impl TenancyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the tenant from the subdomain of `base_domain`
+    pub fn subdomain(mut self, base_domain: &str) -> Self {
+        let base = base_domain.trim_start_matches('.').to_lowercase();
+        self.sources.push(TenantSource::Subdomain(base));
+        self
+    }
+
+    /// Read the tenant from a header
+    pub fn header(mut self, name: &str) -> Self {
+        self.sources.push(TenantSource::Header(name.to_lowercase()));
+        self
+    }
+
+    /// Read the tenant from a bearer token claim
+    pub fn claim(mut self, name: &str) -> Self {
+        self.sources.push(TenantSource::Claim(name.to_string()));
+        self
+    }
+
+    /// Only accept these tenants, answering 404 for others
+    pub fn tenants(mut self, tenants: Tenants) -> Self {
+        self.tenants = Some(tenants);
+        self
+    }
+
+    /// Whether requests without a tenant are refused
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    /// Look up tenants missing from the registry, answering 404 when the
+    /// resolver returns `None`
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let options = TenancyOptions::new().subdomain("example.com").resolver(|id| async move {
+    ///     let exists = Account::exists(&id).await.unwrap_or(false);
+    ///     exists.then(|| Tenant::new(&id).storage(TenantStorage::Schema(format!("tenant_{}", id))))
+    /// });
+    /// ```
+    pub fn resolver<F, Fut>(mut self, resolver: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<Tenant>> + Send + 'static,
+    {
+        self.resolver = Some(Arc::new(move |id| Box::pin(resolver(id))));
+        self
+    }
+
+    /// Refuse requests of signed-in users whose token `claim` names another
+    /// tenant, with 403
+    ///
+    /// [`jwt_auth`](super::jwt_auth()) must run first; requests without a
+    /// principal are let through.
+    pub fn match_claim(mut self, claim: &str) -> Self {
+        self.match_claim = Some(claim.to_string());
+        self
+    }
+
+    /// The tenant id in a request, from the first source that has one
+    pub fn tenant_id(&self, req: &Request) -> Option<String> {
+        self.sources.iter().find_map(|source| match source {
+            TenantSource::Subdomain(base) => {
+                let host = req.host()?.split(':').next()?.to_lowercase();
+                let label = host.strip_suffix(base.as_str())?.strip_suffix('.')?;
+                (!label.is_empty() && !label.contains('.') && label != "www")
+                    .then(|| label.to_string())
+            }
+            TenantSource::Header(name) => req
+                .header(name)
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string),
+            TenantSource::Claim(name) => claim(req, name),
+        })
+    }
+
+    /// Resolve the tenant of a request
+    ///
+    /// Ids may only hold letters, digits, `-` and `_`, since they end up in
+    /// connection names and cache keys. Tenants with a schema or database
+    /// of their own must come from the registry or resolver.
+    pub async fn resolve(&self, req: &Request) -> Result<Option<Tenant>, Error> {
+        let Some(id) = self.tenant_id(req) else {
+            return if self.required {
+                Err(Error::bad_request("No tenant given"))
+            } else {
+                Ok(None)
+            };
+        };
+        let valid = id.len() <= 63
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(Error::bad_request("Invalid tenant"));
+        }
+        if let Some(name) = &self.match_claim {
+            if req.extensions().get::<Principal>().is_some()
+                && claim(req, name).as_deref() != Some(id.as_str())
+            {
+                return Err(Error::Forbidden(
+                    "Tenant does not match the signed-in user".to_string(),
+                ));
+            }
+        }
+
+        if let Some(tenant) = self.tenants.as_ref().and_then(|tenants| tenants.get(&id)) {
+            return Ok(Some(tenant));
+        }
+        if let Some(resolver) = &self.resolver {
+            return match resolver(id.clone()).await {
+                Some(tenant) if tenant.id == id => Ok(Some(tenant)),
+                _ => Err(unknown(&id)),
+            };
+        }
+        match &self.tenants {
+            Some(_) => Err(unknown(&id)),
+            None => Ok(Some(Tenant::new(&id))),
+        }
+    }
+}
+
+/// A string or number claim of the request's principal
+fn claim(req: &Request, name: &str) -> Option<String> {
+    match &req.extensions().get::<Principal>()?.claims[name] {
+        serde_json::Value::String(id) => Some(id.clone()),
+        serde_json::Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+fn unknown(id: &str) -> Error {
+    Error::not_found(format!("Unknown tenant `{}`", id))
+}
+
+/// Tenant resolution middleware
+///
+/// Opens the tenant's own pool, if it has one, before the handler runs.
+/// Rate limiters and response caches that run after it count and store
+/// per tenant. Without a registry or resolver every tenant shares the
+/// default connection, so clients can't open pools by inventing ids.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::{tenancy, TenancyOptions};
+///
+/// app.use_middleware(tenancy(
+///     TenancyOptions::new()
+///         .subdomain("example.com")
+///         .resolver(|id| async move {
+///             let schema = format!("tenant_{}", id);
+///             schema_exists(&schema).await.then(|| Tenant::new(&id).storage(TenantStorage::Schema(schema)))
+///         }),
+/// ));
+/// app.use_middleware(rate_limiter(RateLimiterConfig::new(100, 60)));
+/// ```
+pub fn tenancy(
+    options: TenancyOptions,
+) -> impl Fn(
+    Request,
+    Response,
+    Next,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
+    let options = Arc::new(options);
+
+    move |mut req: Request, res: Response, next: Next| {
+        let options = Arc::clone(&options);
+
+        Box::pin(async move {
+            let tenant = match options.resolve(&req).await {
+                Ok(Some(tenant)) => tenant,
+                Ok(None) => return next(req, res).await,
+                Err(error) => return refuse(res, error),
+            };
+            let isolated = matches!(
+                tenant.storage,
+                TenantStorage::Schema(_) | TenantStorage::Database(_)
+            );
+            if isolated && crate::db::connection::get_db().is_some() {
+                if let Err(error) = tenant.connection().await {
+                    tracing::error!("Failed to open database of tenant {}: {}", tenant.id, error);
+                    return refuse(res, error);
+                }
+            }
+            req.extensions_mut().insert(tenant.clone());
+            tenancy::scope(tenant, next(req, res)).await
+        })
+    }
+}
+
+fn refuse(res: Response, error: Error) -> Response {
+    res.status(error.status_code())
+        .json(serde_json::json!({
+            "error": error.to_string(),
+        }))
+        .with_error(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_tenant_resolution() {
+        let tenants = Tenants::new();
+        tenants.register(Tenant::new("acme").data(json!({ "plan": "pro" })));
+        tenants.register(Tenant::new("globex").rate_limit(1));
+        let app = RustyX::new();
+        app.use_middleware(tenancy(
+            TenancyOptions::new()
+                .subdomain("example.com")
+                .header("x-tenant")
+                .tenants(tenants),
+        ));
+        app.use_middleware(rate_limiter(RateLimiterConfig::new(5, 60)));
+        app.get("/", |req, res| async move {
+            let tenant = req.tenant().unwrap();
+            assert_eq!(tenancy::current().unwrap().id, tenant.id);
+            res.json(json!({ "id": tenant.id, "plan": tenant.data["plan"] }))
+        });
+
+        let res = app
+            .test()
+            .get("/")
+            .header("host", "acme.example.com:8080")
+            .send()
+            .await;
+        res.assert_json(json!({ "id": "acme", "plan": "pro" }));
+        let res = app.test().get("/").header("x-tenant", "acme").send().await;
+        res.assert_json_field("id", "acme")
+            .assert_header("x-ratelimit-remaining", "3");
+        let globex = || app.test().get("/").header("x-tenant", "globex").send();
+        globex().await.assert_header("x-ratelimit-limit", "1");
+        globex().await.assert_status(429);
+
+        let res = app
+            .test()
+            .get("/")
+            .header("x-tenant", "initech")
+            .send()
+            .await;
+        res.assert_status(404);
+        let res = app
+            .test()
+            .get("/")
+            .header("x-tenant", "../etc")
+            .send()
+            .await;
+        res.assert_status(400);
+        let res = app
+            .test()
+            .get("/")
+            .header("host", "example.com")
+            .send()
+            .await;
+        res.assert_status(400);
+    }
+
+    #[tokio::test]
+    async fn test_claim_source() {
+        let options = TenancyOptions::new().claim("org").required(false);
+        let mut principal = Principal::new("1");
+        principal.claims = json!({ "org": 42 });
+        let req = Request::builder().extension(principal).build();
+        assert_eq!(options.resolve(&req).await.unwrap().unwrap().id, "42");
+        assert!(options
+            .resolve(&Request::builder().build())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tenants_get_no_pool() {
+        let open = TenancyOptions::new().header("x-tenant");
+        let req = Request::builder().header("x-tenant", "anything").build();
+        let tenant = open.resolve(&req).await.unwrap().unwrap();
+        assert_eq!(tenant.storage, TenantStorage::Shared);
+
+        let resolved = open.clone().resolver(|id| async move {
+            (id == "acme").then(|| Tenant::new(&id).storage(TenantStorage::Schema(id.clone())))
+        });
+        let acme = Request::builder().header("x-tenant", "acme").build();
+        let tenant = resolved.resolve(&acme).await.unwrap().unwrap();
+        assert_eq!(tenant.storage, TenantStorage::Schema("acme".into()));
+        let err = resolved.resolve(&req).await.unwrap_err();
+        assert_eq!(err.status_code(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_match_claim() {
+        let options = TenancyOptions::new().header("x-tenant").match_claim("org");
+        let mut principal = Principal::new("1");
+        principal.claims = json!({ "org": "acme" });
+        let request = |tenant: &str| {
+            Request::builder()
+                .header("x-tenant", tenant)
+                .extension(principal.clone())
+                .build()
+        };
+        assert_eq!(
+            options.resolve(&request("acme")).await.unwrap().unwrap().id,
+            "acme"
+        );
+        let err = options.resolve(&request("globex")).await.unwrap_err();
+        assert_eq!(err.status_code(), 403);
+        let anonymous = Request::builder().header("x-tenant", "globex").build();
+        assert!(options.resolve(&anonymous).await.is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+//! Request Validation Middleware
+//!
+//! Parses the JSON body into a `#[derive(Validate)]` type and runs its
+//! rules before the handler, answering 422 with the field errors when they
+//! fail. Handlers then read the payload with [`Request::validated`].
+
+use crate::middleware::Next;
+use crate::models::validation::Validate;
+use crate::request::{Request, Validated};
+use crate::response::Response;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Validate the JSON body as a `T`, refusing invalid requests
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use rustyx::middleware::validate_json;
+///
+/// #[derive(Deserialize, Validate)]
+/// struct CreatePost {
+///     #[validate(length(min = 1, max = 200))]
+///     title: String,
+/// }
+///
+/// let posts = Router::new();
+/// posts.use_middleware(only(&["POST"], validate_json::<CreatePost>()));
+/// posts.post("/", |req, res| async move {
+///     let post = req.validated::<CreatePost>().unwrap();
+///     res.created(json!({ "title": post.title }))
+/// });
+/// ```
+pub fn validate_json<T>(
+) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static
+where
+    T: DeserializeOwned + Validate + Send + Sync + 'static,
+{
+    move |mut req: Request, res: Response, next: Next| {
+        Box::pin(async move {
+            match req.validated_json::<T>() {
+                Ok(value) => {
+                    req.extensions_mut().insert(Validated(Arc::new(value)));
+                    next(req, res).await
+                }
+                Err(error) => Response::from(error),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Deserialize, Validate)]
+    struct CreatePost {
+        #[validate(length(min = 3, max = 20))]
+        title: String,
+        #[validate(email)]
+        author: String,
+    }
+
+    #[tokio::test]
+    async fn test_validate_json() {
+        let app = RustyX::new();
+        app.use_middleware(validate_json::<CreatePost>());
+        app.post("/posts", |req, res| async move {
+            let post = req.validated::<CreatePost>().unwrap();
+            res.created(json!({ "title": post.title, "author": post.author }))
+        });
+
+        app.test()
+            .post("/posts")
+            .json(&json!({ "title": "Hello", "author": "ann@example.com" }))
+            .send()
+            .await
+            .assert_status(201)
+            .assert_json_field("title", "Hello");
+
+        let res = app
+            .test()
+            .post("/posts")
+            .json(&json!({ "title": "Hi", "author": "ann" }))
+            .send()
+            .await;
+        res.assert_status(422);
+        let body: Value = res.json();
+        let fields: Vec<_> = body["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["field"].as_str().unwrap())
+            .collect();
+        assert_eq!(fields, ["title", "author"]);
+
+        let res = app
+            .test()
+            .post("/posts")
+            .json(&json!({ "title": "Hello" }))
+            .send()
+            .await;
+        res.assert_status(422);
+        let body: Value = res.json();
+        assert_eq!(body["errors"][0]["code"], "required");
+        app.test()
+            .post("/posts")
+            .body("{")
+            .send()
+            .await
+            .assert_status(400);
+    }
+}
@@ -8,6 +8,19 @@ use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use uuid::Uuid;
 
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+pub mod persist;
+pub mod relations;
+pub mod schema;
+pub mod validation;
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+pub use persist::Persist;
+pub use relations::{Relation, Relations};
+pub use rustyx_macros::{Model, Validate};
+pub use schema::Schema;
+pub use validation::Validate;
+
 /// Base Model trait that all models should implement
 #[async_trait]
 pub trait Model: Send + Sync + Serialize + DeserializeOwned + Clone {
@@ -19,6 +32,22 @@ pub trait Model: Send + Sync + Serialize + DeserializeOwned + Clone {
         "id"
     }
 
+    /// `created_at` and `updated_at` columns, maintained on save
+    ///
+    /// `Some` for models implementing [`Timestamps`]: inserts set both,
+    /// updates set `updated_at`.
+    fn timestamp_columns() -> Option<(&'static str, &'static str)> {
+        None
+    }
+
+    /// Column holding the soft-delete timestamp
+    ///
+    /// `Some` for models implementing [`SoftDeletes`]: queries then skip
+    /// deleted rows and deleting sets the column instead of removing the row.
+    fn soft_delete_column() -> Option<&'static str> {
+        None
+    }
+
     /// Get the model's ID
     fn get_id(&self) -> Option<String>;
 
@@ -30,16 +59,22 @@ pub trait Model: Send + Sync + Serialize + DeserializeOwned + Clone {
         Ok(())
     }
 
-    /// Called before saving
-    fn before_save(&mut self) {}
+    /// Called before saving, after [`validate`](Model::validate) passes
+    ///
+    /// Returning an error aborts the save before anything is written.
+    fn before_save(&mut self) -> Result<()> {
+        Ok(())
+    }
 
-    /// Called after saving
+    /// Called after the row is written and the model refreshed from it
     fn after_save(&mut self) {}
 
-    /// Called before deleting
-    fn before_delete(&self) {}
+    /// Called before deleting; returning an error aborts the delete
+    fn before_delete(&self) -> Result<()> {
+        Ok(())
+    }
 
-    /// Called after deleting
+    /// Called after the row is deleted or soft-deleted
     fn after_delete(&self) {}
 }
 
@@ -142,3 +177,58 @@ impl Field {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Serialize, Deserialize, Model)]
+    struct BlogPost {
+        id: Option<i64>,
+        title: String,
+        created_at: DateTime<Utc>,
+        updated_at: Option<DateTime<Utc>>,
+        deleted_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Clone, Serialize, Deserialize, Model)]
+    #[model(table = "people")]
+    struct Person {
+        #[model(primary_key)]
+        uuid: Uuid,
+    }
+
+    #[test]
+    fn test_derive_model() {
+        assert_eq!(BlogPost::collection_name(), "blog_posts");
+        assert_eq!(Person::collection_name(), "people");
+        assert_eq!(Person::primary_key(), "uuid");
+
+        let mut post = BlogPost {
+            id: None,
+            title: "Hello".to_string(),
+            created_at: Utc::now(),
+            updated_at: None,
+            deleted_at: None,
+        };
+        assert_eq!(post.get_id(), None);
+        post.set_id("42".to_string());
+        assert_eq!(post.get_id().as_deref(), Some("42"));
+
+        let now = Utc::now();
+        post.set_updated_at(now);
+        assert_eq!(post.updated_at(), Some(now));
+        assert!(post.created_at().is_some());
+        post.soft_delete();
+        assert!(post.is_deleted());
+        post.restore();
+        assert!(!post.is_deleted());
+
+        let mut person = Person {
+            uuid: Uuid::new_v4(),
+        };
+        let id = Uuid::new_v4().to_string();
+        person.set_id(id.clone());
+        assert_eq!(person.get_id(), Some(id));
+    }
+}
@@ -0,0 +1,351 @@
+//! Model Persistence
+//!
+//! Insert, update and delete single models, running the [`Model`] hooks
+//! and maintaining timestamp and soft-delete columns.
+
+use super::relations::now;
+use super::Model;
+use crate::db::query::{BindValue, QueryBuilder};
+use crate::db::sql::SqlExecutor;
+use crate::db::DbDriver;
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+/// Save and delete models, implemented for every [`Model`]
+///
+/// Rows are written column by column from the model's serialized fields.
+/// Models with [`timestamp_columns`](Model::timestamp_columns) get
+/// `created_at` set on insert and `updated_at` on every write; models with a
+/// [`soft_delete_column`](Model::soft_delete_column) are soft-deleted.
+///
+/// Saves run [`validate`](Model::validate), then
+/// [`before_save`](Model::before_save), write the row and call
+/// [`after_save`](Model::after_save); deletes run
+/// [`before_delete`](Model::before_delete) and
+/// [`after_delete`](Model::after_delete) around the write. An error from
+/// `validate` or a `before_` hook aborts without touching the database.
+///
+/// ```rust,ignore
+/// let mut user = User { id: None, email: "a@example.com".into(), ..Default::default() };
+/// user.save(&sql).await?; // INSERT, sets id, created_at and updated_at
+/// user.email = "b@example.com".into();
+/// user.save(&sql).await?; // UPDATE, bumps updated_at
+/// ```
+#[async_trait]
+pub trait Persist: Model {
+    /// Insert the model, filling in a generated primary key
+    async fn insert(&mut self, sql: &SqlExecutor) -> Result<()> {
+        self.validate()?;
+        self.before_save()?;
+        let mut row = to_row(self)?;
+        if let Some((created_at, updated_at)) = Self::timestamp_columns() {
+            let now = now()?;
+            row.insert(created_at.to_string(), now.clone());
+            row.insert(updated_at.to_string(), now);
+        }
+
+        let pk = Self::primary_key();
+        let generated = row.get(pk).is_none_or(Value::is_null);
+        if generated {
+            row.remove(pk);
+        }
+        let builder = QueryBuilder::table(Self::collection_name()).driver(sql.driver());
+        let (mut query, binds) = builder.build_insert(columns(&row));
+
+        if generated {
+            // MySQL has no RETURNING, but reports the generated id
+            let id = if sql.driver() == DbDriver::MySQL {
+                sql.insert_with(&query, binds).await?.map(Value::from)
+            } else {
+                query.push_str(&format!(" RETURNING {}", pk));
+                let rows: Vec<Map<String, Value>> = sql.query_with(&query, binds).await?;
+                rows.into_iter().next().and_then(|mut r| r.remove(pk))
+            };
+            row.insert(pk.to_string(), id.unwrap_or(Value::Null));
+        } else {
+            sql.execute_with(&query, binds).await?;
+        }
+
+        from_row(self, row)?;
+        self.after_save();
+        Ok(())
+    }
+
+    /// Write all columns of the model to its row
+    async fn update(&mut self, sql: &SqlExecutor) -> Result<()> {
+        let id = self.get_id().ok_or_else(|| {
+            Error::Database(format!(
+                "Cannot update {} without a primary key",
+                Self::collection_name()
+            ))
+        })?;
+        self.validate()?;
+        self.before_save()?;
+        let mut row = to_row(self)?;
+        if let Some((_, updated_at)) = Self::timestamp_columns() {
+            row.insert(updated_at.to_string(), now()?);
+        }
+
+        let pk = Self::primary_key();
+        let key = row.remove(pk).unwrap_or(Value::Null);
+        let (query, binds) = QueryBuilder::table(Self::collection_name())
+            .driver(sql.driver())
+            .where_eq(pk, &key)
+            .build_update(columns(&row));
+        if sql.execute_with(&query, binds).await? == 0 {
+            return Err(Error::NotFound(format!(
+                "{} {}",
+                Self::collection_name(),
+                id
+            )));
+        }
+
+        row.insert(pk.to_string(), key);
+        from_row(self, row)?;
+        self.after_save();
+        Ok(())
+    }
+
+    /// Insert the model, or update the existing row with the same
+    /// `conflict` columns (a primary key or unique constraint)
+    ///
+    /// `created_at` is only set when the row is inserted. On SQLite and
+    /// PostgreSQL the model is refreshed from the stored row.
+    async fn insert_or_update(&mut self, sql: &SqlExecutor, conflict: &[&str]) -> Result<()> {
+        self.validate()?;
+        self.before_save()?;
+        let mut row = to_row(self)?;
+        let mut kept = conflict.to_vec();
+        if let Some((created_at, updated_at)) = Self::timestamp_columns() {
+            let now = now()?;
+            row.insert(created_at.to_string(), now.clone());
+            row.insert(updated_at.to_string(), now);
+            kept.push(created_at);
+        }
+
+        let pk = Self::primary_key();
+        if row.get(pk).is_none_or(Value::is_null) {
+            row.remove(pk);
+        }
+        let update: Vec<&str> = row
+            .keys()
+            .map(String::as_str)
+            .filter(|column| *column != pk && !kept.contains(column))
+            .collect();
+        let (mut query, binds) = QueryBuilder::table(Self::collection_name())
+            .driver(sql.driver())
+            .build_upsert(columns(&row), conflict, &update);
+
+        if sql.driver() == DbDriver::MySQL {
+            sql.execute_with(&query, binds).await?;
+        } else {
+            query.push_str(" RETURNING *");
+            let stored: Vec<Map<String, Value>> = sql.query_with(&query, binds).await?;
+            if let Some(stored) = stored.into_iter().next() {
+                row = stored;
+            }
+        }
+
+        from_row(self, row)?;
+        self.after_save();
+        Ok(())
+    }
+
+    /// Insert the model if it has no primary key yet, otherwise update it
+    async fn save(&mut self, sql: &SqlExecutor) -> Result<()> {
+        if self.get_id().is_some() {
+            self.update(sql).await
+        } else {
+            self.insert(sql).await
+        }
+    }
+
+    /// Delete the model's row, or soft-delete it
+    async fn delete(&mut self, sql: &SqlExecutor) -> Result<()> {
+        self.before_delete()?;
+        let mut row = to_row(self)?;
+        let pk = Self::primary_key();
+        let key = row.get(pk).cloned().unwrap_or(Value::Null);
+        let builder = QueryBuilder::table(Self::collection_name())
+            .driver(sql.driver())
+            .where_eq(pk, &key);
+
+        match Self::soft_delete_column() {
+            Some(column) => {
+                let now = now()?;
+                let (query, binds) = builder.build_update(vec![(column, BindValue::from(&now))]);
+                sql.execute_with(&query, binds).await?;
+                row.insert(column.to_string(), now);
+                from_row(self, row)?;
+            }
+            None => {
+                let (query, binds) = builder.build_delete();
+                sql.execute_with(&query, binds).await?;
+            }
+        }
+        self.after_delete();
+        Ok(())
+    }
+}
+
+impl<M: Model> Persist for M {}
+
+fn to_row(model: &impl Model) -> Result<Map<String, Value>> {
+    match serde_json::to_value(model)? {
+        Value::Object(row) => Ok(row),
+        _ => Err(Error::Internal(
+            "Models must serialize to an object".to_string(),
+        )),
+    }
+}
+
+fn from_row<M: Model>(model: &mut M, row: Map<String, Value>) -> Result<()> {
+    *model = serde_json::from_value(Value::Object(row))?;
+    Ok(())
+}
+
+fn columns(row: &Map<String, Value>) -> Vec<(&str, BindValue)> {
+    row.iter()
+        .map(|(column, value)| (column.as_str(), BindValue::from(value)))
+        .collect()
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::DatabaseConfig;
+    use crate::models::Schema;
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Serialize, Deserialize, crate::models::Model)]
+    struct Article {
+        id: Option<i64>,
+        title: String,
+        created_at: Option<DateTime<Utc>>,
+        updated_at: Option<DateTime<Utc>>,
+        deleted_at: Option<DateTime<Utc>>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, crate::models::Model)]
+    #[model(before_save = "normalize_tag", before_delete = "keep_pinned")]
+    struct Tag {
+        id: Option<i64>,
+        name: String,
+    }
+
+    fn normalize_tag(tag: &mut Tag) -> Result<()> {
+        tag.name = tag.name.trim().to_lowercase();
+        if tag.name.is_empty() {
+            return Err(Error::Validation("tag name is blank".to_string()));
+        }
+        Ok(())
+    }
+
+    fn keep_pinned(tag: &Tag) -> Result<()> {
+        if tag.name == "pinned" {
+            return Err(Error::Forbidden(
+                "pinned tags cannot be deleted".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hooks_can_abort() {
+        let config = DatabaseConfig::sqlite_memory();
+        let sql = SqlExecutor::new(crate::db::sql::connect(&config).await.unwrap());
+        sql.execute("CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT)")
+            .await
+            .unwrap();
+
+        let mut tag = Tag {
+            id: None,
+            name: " Pinned ".to_string(),
+        };
+        tag.save(&sql).await.unwrap();
+        assert_eq!(tag.name, "pinned");
+        assert!(matches!(tag.delete(&sql).await, Err(Error::Forbidden(_))));
+
+        let mut blank = Tag {
+            id: None,
+            name: "  ".to_string(),
+        };
+        assert!(blank.save(&sql).await.is_err());
+        let stored: Vec<Tag> = sql.query("SELECT * FROM tags").await.unwrap();
+        assert_eq!(stored.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_save_maintains_timestamps() {
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(crate::db::sql::connect(&config).await.unwrap());
+        let articles = Schema::create("articles", |t| {
+            t.increments("id");
+            t.string("title");
+            t.timestamps();
+            t.soft_deletes();
+        });
+        sql.execute(&articles.to_sql(DbDriver::SQLite))
+            .await
+            .unwrap();
+
+        let mut article = Article {
+            id: None,
+            title: "Draft".to_string(),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        };
+        article.save(&sql).await.unwrap();
+        assert_eq!(article.id, Some(1));
+        let created = article.created_at.unwrap();
+        assert_eq!(article.updated_at, Some(created));
+
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        article.title = "Published".to_string();
+        article.save(&sql).await.unwrap();
+        assert_eq!(article.created_at, Some(created));
+        assert!(article.updated_at.unwrap() > created);
+
+        let stored: Vec<Article> = sql.query("SELECT * FROM articles").await.unwrap();
+        assert_eq!(stored[0].title, "Published");
+        assert_eq!(stored[0].updated_at, article.updated_at);
+
+        article.delete(&sql).await.unwrap();
+        assert!(article.deleted_at.is_some());
+        let stored: Vec<Article> = sql.query("SELECT * FROM articles").await.unwrap();
+        assert_eq!(stored[0].deleted_at, article.deleted_at);
+
+        let mut draft = Article {
+            id: None,
+            title: "Again".to_string(),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        };
+        draft.insert_or_update(&sql, &["id"]).await.unwrap();
+        assert_eq!(draft.id, Some(2));
+        let mut replacement = Article {
+            id: Some(2),
+            title: "Replaced".to_string(),
+            ..draft.clone()
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        replacement.created_at = None;
+        replacement.insert_or_update(&sql, &["id"]).await.unwrap();
+        assert_eq!(replacement.title, "Replaced");
+        assert_eq!(replacement.created_at, draft.created_at);
+        assert!(replacement.updated_at > draft.updated_at);
+
+        let mut missing = Article {
+            id: Some(7),
+            ..article
+        };
+        assert!(matches!(
+            missing.update(&sql).await,
+            Err(Error::NotFound(_))
+        ));
+    }
+}
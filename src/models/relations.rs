@@ -0,0 +1,628 @@
+//! Model Relations
+//!
+//! `has_many` / `has_one` / `belongs_to` declarations, loading related rows
+//! for one model, and [`ModelQuery`] with batched eager loading and
+//! soft-delete scoping.
+
+use super::Model;
+use crate::db::query::{BindValue, QueryBuilder};
+use serde_json::{json, Value};
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+use {
+    crate::db::sql::SqlExecutor,
+    crate::error::{Error, Result},
+    async_trait::async_trait,
+    serde::{de::DeserializeOwned, Serialize},
+    serde_json::Map,
+    std::collections::{HashMap, HashSet},
+    std::marker::PhantomData,
+};
+
+/// Kind of relation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationKind {
+    HasMany,
+    HasOne,
+    BelongsTo,
+}
+
+/// A relation from a model to the rows of another table
+///
+/// # Example
+///
+/// ```rust,ignore
+/// impl Relations for User {
+///     fn relations() -> Vec<Relation> {
+///         vec![Relation::has_many::<Post>("posts", "user_id")]
+///     }
+/// }
+///
+/// impl Relations for Post {
+///     fn relations() -> Vec<Relation> {
+///         vec![Relation::belongs_to::<User>("author", "user_id")]
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Relation {
+    /// Key the related rows are embedded under
+    pub name: String,
+    pub kind: RelationKind,
+    /// Related table or collection
+    pub table: String,
+    /// Referencing column: on the related table for `HasMany` / `HasOne`,
+    /// on the model itself for `BelongsTo`
+    pub foreign_key: String,
+    /// Referenced column on the other side, the primary key by default
+    pub owner_key: String,
+    /// Soft-delete column of the related model; deleted rows are not loaded
+    pub soft_delete_column: Option<String>,
+}
+
+impl Relation {
+    /// Rows of `R` whose `foreign_key` holds this model's `id`
+    pub fn has_many<R: Model>(name: &str, foreign_key: &str) -> Self {
+        Self::new::<R>(name, RelationKind::HasMany, foreign_key, "id")
+    }
+
+    /// Like [`has_many`](Self::has_many), loading at most one row
+    pub fn has_one<R: Model>(name: &str, foreign_key: &str) -> Self {
+        Self::new::<R>(name, RelationKind::HasOne, foreign_key, "id")
+    }
+
+    /// The `R` whose primary key is held in this model's `foreign_key`
+    pub fn belongs_to<R: Model>(name: &str, foreign_key: &str) -> Self {
+        Self::new::<R>(name, RelationKind::BelongsTo, foreign_key, R::primary_key())
+    }
+
+    fn new<R: Model>(name: &str, kind: RelationKind, foreign_key: &str, owner_key: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            table: R::collection_name().to_string(),
+            foreign_key: foreign_key.to_string(),
+            owner_key: owner_key.to_string(),
+            soft_delete_column: R::soft_delete_column().map(str::to_string),
+        }
+    }
+
+    /// Reference a column other than the primary key
+    pub fn owner_key(mut self, key: &str) -> Self {
+        self.owner_key = key.to_string();
+        self
+    }
+
+    /// The matched columns: (on the model, on the related table)
+    pub fn keys(&self) -> (&str, &str) {
+        match self.kind {
+            RelationKind::BelongsTo => (&self.foreign_key, &self.owner_key),
+            _ => (&self.owner_key, &self.foreign_key),
+        }
+    }
+
+    /// Query for the related rows of the given key values
+    pub fn query(&self, keys: Vec<BindValue>) -> QueryBuilder {
+        let query = QueryBuilder::table(&self.table).where_in(self.keys().1, keys);
+        match &self.soft_delete_column {
+            Some(column) => query.where_is_null(column),
+            None => query,
+        }
+    }
+
+    /// MongoDB aggregation stages embedding the relation under its name
+    ///
+    /// `$lookup` always produces an array, so `HasOne` and `BelongsTo`
+    /// add an `$unwind` that keeps documents without a match.
+    pub fn lookup_stages(&self) -> Vec<Value> {
+        let (local, foreign) = self.keys();
+        let mut stages = vec![json!({
+            "$lookup": {
+                "from": self.table,
+                "localField": local,
+                "foreignField": foreign,
+                "as": self.name,
+            }
+        })];
+        if self.kind != RelationKind::HasMany {
+            stages.push(json!({
+                "$unwind": {
+                    "path": format!("${}", self.name),
+                    "preserveNullAndEmptyArrays": true,
+                }
+            }));
+        }
+        stages
+    }
+
+    /// Load the rows related to one model
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub async fn fetch<R: DeserializeOwned>(
+        &self,
+        sql: &SqlExecutor,
+        model: &impl Serialize,
+    ) -> Result<Vec<R>> {
+        let model = serde_json::to_value(model)?;
+        let key = match model.get(self.keys().0) {
+            Some(key) if !key.is_null() => key,
+            _ => return Ok(Vec::new()),
+        };
+        let (query, binds) = self.query(vec![key.into()]).driver(sql.driver()).build();
+        sql.query_with(&query, binds).await
+    }
+}
+
+/// Relations declared by a model
+pub trait Relations: Model {
+    fn relations() -> Vec<Relation> {
+        Vec::new()
+    }
+
+    /// Look up a declared relation by name
+    fn relation(name: &str) -> Option<Relation> {
+        Self::relations().into_iter().find(|r| r.name == name)
+    }
+
+    /// Query this model's table, optionally eager loading relations
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    fn query() -> ModelQuery<Self> {
+        ModelQuery::new()
+    }
+}
+
+/// Load related models from an instance, using the global connection
+///
+/// Implemented for every [`Model`], so relation accessors are one line:
+///
+/// ```rust,ignore
+/// impl User {
+///     async fn posts(&self) -> Result<Vec<Post>> {
+///         self.has_many("user_id").await
+///     }
+/// }
+/// ```
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+#[async_trait]
+pub trait Related: Model {
+    async fn has_many<R: Model>(&self, foreign_key: &str) -> Result<Vec<R>> {
+        Relation::has_many::<R>("", foreign_key)
+            .owner_key(Self::primary_key())
+            .fetch(&SqlExecutor::global()?, self)
+            .await
+    }
+
+    async fn has_one<R: Model>(&self, foreign_key: &str) -> Result<Option<R>> {
+        let rows = Relation::has_one::<R>("", foreign_key)
+            .owner_key(Self::primary_key())
+            .fetch(&SqlExecutor::global()?, self)
+            .await?;
+        Ok(rows.into_iter().next())
+    }
+
+    async fn belongs_to<R: Model>(&self, foreign_key: &str) -> Result<Option<R>> {
+        let rows = Relation::belongs_to::<R>("", foreign_key)
+            .fetch(&SqlExecutor::global()?, self)
+            .await?;
+        Ok(rows.into_iter().next())
+    }
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+impl<M: Model> Related for M {}
+
+/// Query over a model's table with eager loaded relations
+///
+/// Each relation named in [`with`](Self::with) costs one extra query,
+/// `WHERE key IN (...)` over all the parent rows, whatever their number.
+///
+/// Models with a [`soft_delete_column`](Model::soft_delete_column) only
+/// see rows where it is NULL, unless [`with_trashed`](Self::with_trashed)
+/// or [`only_trashed`](Self::only_trashed) is used.
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// struct UserWithPosts {
+///     #[serde(flatten)]
+///     user: User,
+///     posts: Vec<Post>,
+/// }
+///
+/// let users: Vec<UserWithPosts> = User::query()
+///     .filter(|q| q.where_eq("active", true))
+///     .with("posts")
+///     .get(&sql)
+///     .await?;
+/// ```
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+pub struct ModelQuery<M> {
+    builder: QueryBuilder,
+    with: Vec<String>,
+    trashed: Trashed,
+    model: PhantomData<M>,
+}
+
+/// Which soft-deleted rows a [`ModelQuery`] sees
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trashed {
+    Without,
+    With,
+    Only,
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+impl<M: Relations> ModelQuery<M> {
+    pub fn new() -> Self {
+        Self {
+            builder: QueryBuilder::table(M::collection_name()),
+            with: Vec::new(),
+            trashed: Trashed::Without,
+            model: PhantomData,
+        }
+    }
+
+    /// Refine the underlying [`QueryBuilder`]
+    pub fn filter(mut self, f: impl FnOnce(QueryBuilder) -> QueryBuilder) -> Self {
+        self.builder = f(self.builder);
+        self
+    }
+
+    /// Eager load a declared relation
+    pub fn with(mut self, relation: &str) -> Self {
+        self.with.push(relation.to_string());
+        self
+    }
+
+    /// Include soft-deleted rows
+    pub fn with_trashed(mut self) -> Self {
+        self.trashed = Trashed::With;
+        self
+    }
+
+    /// Only soft-deleted rows
+    pub fn only_trashed(mut self) -> Self {
+        self.trashed = Trashed::Only;
+        self
+    }
+
+    /// The filter with the soft-delete scope applied
+    fn scoped(&self, sql: &SqlExecutor) -> QueryBuilder {
+        let builder = self.builder.clone().driver(sql.driver());
+        match (M::soft_delete_column(), self.trashed) {
+            (Some(column), Trashed::Without) => builder.scope(|q| q.where_is_null(column)),
+            (Some(column), Trashed::Only) => builder.scope(|q| q.where_is_not_null(column)),
+            _ => builder,
+        }
+    }
+
+    /// Delete the matching rows, returning how many were affected
+    ///
+    /// Soft-deleting models get their deleted timestamp set instead; rows
+    /// already deleted keep theirs.
+    pub async fn delete(self, sql: &SqlExecutor) -> Result<u64> {
+        let Some(column) = M::soft_delete_column() else {
+            return self.force_delete(sql).await;
+        };
+        let (query, binds) = self
+            .builder
+            .driver(sql.driver())
+            .scope(|q| q.where_is_null(column))
+            .build_update(vec![(column, BindValue::from(&now()?))]);
+        sql.execute_with(&query, binds).await
+    }
+
+    /// Remove the matching rows, soft-deleting model or not
+    pub async fn force_delete(self, sql: &SqlExecutor) -> Result<u64> {
+        let (query, binds) = self.scoped(sql).build_delete();
+        sql.execute_with(&query, binds).await
+    }
+
+    /// Clear the deleted timestamp of the matching soft-deleted rows
+    pub async fn restore(self, sql: &SqlExecutor) -> Result<u64> {
+        let column = M::soft_delete_column().ok_or_else(|| {
+            Error::Database(format!(
+                "{} does not use soft deletes",
+                M::collection_name()
+            ))
+        })?;
+        let (query, binds) = self
+            .builder
+            .driver(sql.driver())
+            .scope(|q| q.where_is_not_null(column))
+            .build_update(vec![(column, BindValue::Null)]);
+        sql.execute_with(&query, binds).await
+    }
+
+    /// Run a page of the query, see [`QueryBuilder::paginate`]
+    ///
+    /// Relations are not loaded for paginated queries.
+    pub async fn paginate<T: DeserializeOwned>(
+        &self,
+        sql: &SqlExecutor,
+        page: u32,
+        per_page: u32,
+    ) -> Result<crate::utils::PaginatedResponse<T>> {
+        self.scoped(sql).paginate(sql.pool(), page, per_page).await
+    }
+
+    /// Run the query, embedding each relation under its name
+    ///
+    /// `HasMany` relations embed an array, the others an object or `null`.
+    pub async fn get<T: DeserializeOwned>(self, sql: &SqlExecutor) -> Result<Vec<T>> {
+        let relations = self
+            .with
+            .iter()
+            .map(|name| {
+                M::relation(name).ok_or_else(|| {
+                    Error::Database(format!(
+                        "Unknown relation '{}' on {}",
+                        name,
+                        M::collection_name()
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let (query, binds) = self.scoped(sql).build();
+        let mut rows: Vec<Map<String, Value>> = sql.query_with(&query, binds).await?;
+
+        for relation in relations {
+            let (local, foreign) = relation.keys();
+            let mut seen = HashSet::new();
+            let keys: Vec<BindValue> = rows
+                .iter()
+                .filter_map(|row| row.get(local).filter(|key| !key.is_null()))
+                .filter(|key| seen.insert(key_string(key)))
+                .map(BindValue::from)
+                .collect();
+
+            let mut related: HashMap<String, Vec<Value>> = HashMap::new();
+            if !keys.is_empty() {
+                let (query, binds) = relation.query(keys).driver(sql.driver()).build();
+                let children: Vec<Map<String, Value>> = sql.query_with(&query, binds).await?;
+                for child in children {
+                    if let Some(key) = child.get(foreign).map(key_string) {
+                        related.entry(key).or_default().push(Value::Object(child));
+                    }
+                }
+            }
+
+            for row in &mut rows {
+                let children = row
+                    .get(local)
+                    .and_then(|key| related.get(&key_string(key)))
+                    .cloned()
+                    .unwrap_or_default();
+                let value = match relation.kind {
+                    RelationKind::HasMany => Value::Array(children),
+                    _ => children.into_iter().next().unwrap_or(Value::Null),
+                };
+                row.insert(relation.name.clone(), value);
+            }
+        }
+
+        rows.into_iter()
+            .map(|row| Ok(serde_json::from_value(Value::Object(row))?))
+            .collect()
+    }
+}
+
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+impl<M: Relations> Default for ModelQuery<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The current time as stored by serde for `DateTime<Utc>` fields
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+pub(crate) fn now() -> Result<Value> {
+    Ok(serde_json::to_value(chrono::Utc::now())?)
+}
+
+/// Key values compared as text, so `1` and `"1"` match across tables
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+fn key_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::{DatabaseConfig, DbDriver};
+    use crate::models::Schema;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Serialize, Deserialize, crate::models::Model)]
+    struct User {
+        id: i64,
+        name: String,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, crate::models::Model)]
+    struct Post {
+        id: i64,
+        user_id: i64,
+        title: String,
+    }
+
+    impl Relations for User {
+        fn relations() -> Vec<Relation> {
+            vec![Relation::has_many::<Post>("posts", "user_id")]
+        }
+    }
+
+    impl Relations for Post {
+        fn relations() -> Vec<Relation> {
+            vec![Relation::belongs_to::<User>("author", "user_id")]
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, crate::models::Model)]
+    struct Note {
+        id: i64,
+        body: String,
+        deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    }
+
+    impl Relations for Note {}
+
+    #[derive(Deserialize)]
+    struct UserWithPosts {
+        #[serde(flatten)]
+        user: User,
+        posts: Vec<Post>,
+    }
+
+    #[derive(Deserialize)]
+    struct PostWithAuthor {
+        title: String,
+        author: Option<User>,
+    }
+
+    #[tokio::test]
+    async fn test_relations() {
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(crate::db::sql::connect(&config).await.unwrap());
+        for schema in [
+            Schema::create("users", |t| {
+                t.increments("id");
+                t.string("name");
+            }),
+            Schema::create("posts", |t| {
+                t.increments("id");
+                t.integer("user_id");
+                t.string("title");
+            }),
+        ] {
+            sql.execute(&schema.to_sql(DbDriver::SQLite)).await.unwrap();
+        }
+        sql.execute("INSERT INTO users (name) VALUES ('ann'), ('bob'), ('cy')")
+            .await
+            .unwrap();
+        sql.execute(
+            "INSERT INTO posts (user_id, title) VALUES (1, 'a'), (2, 'b'), (1, 'c'), (9, 'd')",
+        )
+        .await
+        .unwrap();
+
+        let users: Vec<UserWithPosts> = User::query()
+            .filter(|q| q.order_by("id", crate::db::query::Order::Asc))
+            .with("posts")
+            .get(&sql)
+            .await
+            .unwrap();
+        let titles: Vec<Vec<&str>> = users
+            .iter()
+            .map(|u| u.posts.iter().map(|p| p.title.as_str()).collect())
+            .collect();
+        assert_eq!(titles, [vec!["a", "c"], vec!["b"], vec![]]);
+        assert_eq!(users[0].user.name, "ann");
+
+        let posts: Vec<PostWithAuthor> = Post::query().with("author").get(&sql).await.unwrap();
+        let authors: Vec<(&str, Option<&str>)> = posts
+            .iter()
+            .map(|p| (p.title.as_str(), p.author.as_ref().map(|a| a.name.as_str())))
+            .collect();
+        assert_eq!(
+            authors,
+            [
+                ("a", Some("ann")),
+                ("b", Some("bob")),
+                ("c", Some("ann")),
+                ("d", None)
+            ]
+        );
+
+        let ann = &users[0].user;
+        let posts: Vec<Post> = User::relation("posts")
+            .unwrap()
+            .fetch(&sql, ann)
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 2);
+        assert!(User::query()
+            .with("comments")
+            .get::<User>(&sql)
+            .await
+            .is_err());
+
+        let stages = Post::relation("author").unwrap().lookup_stages();
+        assert_eq!(stages[0]["$lookup"]["localField"], "user_id");
+        assert_eq!(stages[1]["$unwind"]["path"], "$author");
+    }
+
+    #[tokio::test]
+    async fn test_soft_deletes() {
+        use crate::db::query::Operator;
+
+        let config = DatabaseConfig::new(DbDriver::SQLite, ":memory:").max_connections(1);
+        let sql = SqlExecutor::new(crate::db::sql::connect(&config).await.unwrap());
+        let notes = Schema::create("notes", |t| {
+            t.increments("id");
+            t.string("body");
+            t.soft_deletes();
+        });
+        sql.execute(&notes.to_sql(DbDriver::SQLite)).await.unwrap();
+        sql.execute("INSERT INTO notes (body) VALUES ('a'), ('b'), ('c')")
+            .await
+            .unwrap();
+
+        let deleted = Note::query()
+            .filter(|q| q.where_eq("id", 1))
+            .delete(&sql)
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(
+            sql.query::<Note>("SELECT * FROM notes")
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let live: Vec<Note> = Note::query()
+            .filter(|q| q.where_eq("id", 1).or_where("id", Operator::Eq, 2))
+            .get(&sql)
+            .await
+            .unwrap();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].body, "b");
+        assert_eq!(
+            Note::query()
+                .with_trashed()
+                .get::<Note>(&sql)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+        let trashed: Vec<Note> = Note::query().only_trashed().get(&sql).await.unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert!(trashed[0].deleted_at.is_some());
+
+        assert_eq!(Note::query().restore(&sql).await.unwrap(), 1);
+        assert_eq!(Note::query().get::<Note>(&sql).await.unwrap().len(), 3);
+        assert_eq!(
+            Note::query()
+                .filter(|q| q.where_eq("id", 3))
+                .force_delete(&sql)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            Note::query()
+                .with_trashed()
+                .get::<Note>(&sql)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
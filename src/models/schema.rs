@@ -0,0 +1,347 @@
+//! Schema Builder
+//!
+//! Table definitions built from [`Field`]s, rendered as `CREATE TABLE`
+//! statements for SQLite, MySQL or PostgreSQL.
+
+use super::{Field, FieldType};
+use crate::db::DbDriver;
+
+/// A table definition
+///
+/// # Example
+///
+/// ```rust
+/// use rustyx::db::DbDriver;
+/// use rustyx::models::schema::Schema;
+///
+/// let users = Schema::create("users", |t| {
+///     t.increments("id");
+///     t.string("email").required().unique();
+///     t.integer("karma").default("0");
+///     t.timestamps();
+/// });
+/// let sql = users.to_sql(DbDriver::PostgreSQL);
+/// assert!(sql.starts_with(r#"CREATE TABLE IF NOT EXISTS "users" ("id" BIGSERIAL PRIMARY KEY"#));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Schema {
+    table: String,
+    columns: Vec<Column>,
+    unique: Vec<Vec<String>>,
+    foreign: Vec<ForeignKey>,
+}
+
+/// A column: a [`Field`] plus key information
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub field: Field,
+    /// Maximum length of `String` columns (default 255)
+    pub length: Option<u32>,
+    pub primary: bool,
+    pub auto_increment: bool,
+}
+
+#[derive(Debug, Clone)]
+struct ForeignKey {
+    column: String,
+    table: String,
+    references: String,
+    on_delete: Option<String>,
+}
+
+/// Builder for the column just added to a [`Schema`]
+pub struct ColumnBuilder<'a>(&'a mut Column);
+
+impl ColumnBuilder<'_> {
+    /// Add `NOT NULL`
+    pub fn required(self) -> Self {
+        self.0.field.required = true;
+        self
+    }
+
+    /// Add a `UNIQUE` constraint
+    pub fn unique(self) -> Self {
+        self.0.field.unique = true;
+        self
+    }
+
+    /// Set the default, as a SQL expression (`"0"`, `"'draft'"`, `"CURRENT_TIMESTAMP"`)
+    pub fn default(self, value: &str) -> Self {
+        self.0.field.default = Some(value.to_string());
+        self
+    }
+
+    /// Make this the primary key
+    pub fn primary(self) -> Self {
+        self.0.primary = true;
+        self
+    }
+
+    /// Set the maximum length of a string column
+    pub fn length(self, length: u32) -> Self {
+        self.0.length = Some(length);
+        self
+    }
+}
+
+impl Schema {
+    /// Define a table
+    pub fn create(table: &str, define: impl FnOnce(&mut Schema)) -> Self {
+        let mut schema = Self {
+            table: table.to_string(),
+            columns: Vec::new(),
+            unique: Vec::new(),
+            foreign: Vec::new(),
+        };
+        define(&mut schema);
+        schema
+    }
+
+    /// Table name
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Columns in definition order
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Add a column of any type
+    pub fn column(&mut self, name: &str, field_type: FieldType) -> ColumnBuilder<'_> {
+        self.add(Field::new(name, field_type))
+    }
+
+    /// Add an existing field definition
+    pub fn add(&mut self, field: Field) -> ColumnBuilder<'_> {
+        self.columns.push(Column {
+            field,
+            length: None,
+            primary: false,
+            auto_increment: false,
+        });
+        ColumnBuilder(self.columns.last_mut().expect("column was just added"))
+    }
+
+    /// Auto-incrementing integer primary key
+    pub fn increments(&mut self, name: &str) -> ColumnBuilder<'_> {
+        let column = self.column(name, FieldType::Integer).primary();
+        column.0.auto_increment = true;
+        column
+    }
+
+    /// UUID primary key
+    pub fn uuid_primary(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Uuid).primary()
+    }
+
+    pub fn string(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::String)
+    }
+
+    pub fn text(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Text)
+    }
+
+    pub fn integer(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Integer)
+    }
+
+    pub fn float(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Float)
+    }
+
+    pub fn boolean(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Boolean)
+    }
+
+    pub fn datetime(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::DateTime)
+    }
+
+    pub fn json(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Json)
+    }
+
+    pub fn uuid(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Uuid)
+    }
+
+    pub fn binary(&mut self, name: &str) -> ColumnBuilder<'_> {
+        self.column(name, FieldType::Binary)
+    }
+
+    /// `created_at` and `updated_at` columns, see [`Timestamps`](super::Timestamps)
+    pub fn timestamps(&mut self) {
+        self.datetime("created_at");
+        self.datetime("updated_at");
+    }
+
+    /// Nullable `deleted_at` column, see [`SoftDeletes`](super::SoftDeletes)
+    pub fn soft_deletes(&mut self) {
+        self.datetime("deleted_at");
+    }
+
+    /// Unique constraint over several columns
+    pub fn unique(&mut self, columns: &[&str]) {
+        self.unique
+            .push(columns.iter().map(|c| c.to_string()).collect());
+    }
+
+    /// Foreign key from `column` to `table(references)`
+    pub fn foreign(&mut self, column: &str, table: &str, references: &str) {
+        self.foreign.push(ForeignKey {
+            column: column.to_string(),
+            table: table.to_string(),
+            references: references.to_string(),
+            on_delete: None,
+        });
+    }
+
+    /// Foreign key whose rows are deleted along with the referenced row
+    pub fn foreign_cascade(&mut self, column: &str, table: &str, references: &str) {
+        self.foreign(column, table, references);
+        if let Some(key) = self.foreign.last_mut() {
+            key.on_delete = Some("CASCADE".to_string());
+        }
+    }
+
+    /// Render the `CREATE TABLE IF NOT EXISTS` statement
+    pub fn to_sql(&self, driver: DbDriver) -> String {
+        let quote = |name: &str| quote(&driver, name);
+        let mut definitions: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| column_sql(&driver, column))
+            .collect();
+
+        for columns in &self.unique {
+            let columns: Vec<String> = columns.iter().map(|c| quote(c)).collect();
+            definitions.push(format!("UNIQUE ({})", columns.join(", ")));
+        }
+        for key in &self.foreign {
+            let mut sql = format!(
+                "FOREIGN KEY ({}) REFERENCES {} ({})",
+                quote(&key.column),
+                quote(&key.table),
+                quote(&key.references)
+            );
+            if let Some(action) = &key.on_delete {
+                sql.push_str(&format!(" ON DELETE {}", action));
+            }
+            definitions.push(sql);
+        }
+
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            quote(&self.table),
+            definitions.join(", ")
+        )
+    }
+
+    /// Render a `DROP TABLE IF EXISTS` statement for a table
+    pub fn drop_sql(table: &str, driver: DbDriver) -> String {
+        format!("DROP TABLE IF EXISTS {}", quote(&driver, table))
+    }
+}
+
+/// Quote an identifier for the dialect
+fn quote(driver: &DbDriver, name: &str) -> String {
+    match driver {
+        DbDriver::MySQL => format!("`{}`", name.replace('`', "``")),
+        _ => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+fn column_sql(driver: &DbDriver, column: &Column) -> String {
+    let field = &column.field;
+    let mut sql = quote(driver, &field.name);
+
+    if column.auto_increment {
+        sql.push_str(match driver {
+            DbDriver::MySQL => " BIGINT AUTO_INCREMENT PRIMARY KEY",
+            DbDriver::PostgreSQL => " BIGSERIAL PRIMARY KEY",
+            // INTEGER PRIMARY KEY is SQLite's rowid alias
+            _ => " INTEGER PRIMARY KEY AUTOINCREMENT",
+        });
+        return sql;
+    }
+
+    sql.push(' ');
+    sql.push_str(&column_type(driver, &field.field_type, column.length));
+    if column.primary {
+        sql.push_str(" PRIMARY KEY");
+    } else {
+        if field.required {
+            sql.push_str(" NOT NULL");
+        }
+        if field.unique {
+            sql.push_str(" UNIQUE");
+        }
+    }
+    if let Some(default) = &field.default {
+        sql.push_str(&format!(" DEFAULT {}", default));
+    }
+    sql
+}
+
+fn column_type(driver: &DbDriver, field_type: &FieldType, length: Option<u32>) -> String {
+    let length = length.unwrap_or(255);
+    let name = match (driver, field_type) {
+        (DbDriver::MySQL | DbDriver::PostgreSQL, FieldType::String) => {
+            return format!("VARCHAR({})", length)
+        }
+        (DbDriver::MySQL, FieldType::Integer) => "BIGINT",
+        (DbDriver::MySQL, FieldType::Float) => "DOUBLE",
+        (DbDriver::MySQL, FieldType::Boolean) => "BOOLEAN",
+        (DbDriver::MySQL, FieldType::DateTime) => "DATETIME",
+        (DbDriver::MySQL, FieldType::Json) => "JSON",
+        (DbDriver::MySQL, FieldType::Uuid) => "CHAR(36)",
+        (DbDriver::MySQL, FieldType::Binary) => "BLOB",
+        (DbDriver::PostgreSQL, FieldType::Integer) => "BIGINT",
+        (DbDriver::PostgreSQL, FieldType::Float) => "DOUBLE PRECISION",
+        (DbDriver::PostgreSQL, FieldType::Boolean) => "BOOLEAN",
+        (DbDriver::PostgreSQL, FieldType::DateTime) => "TIMESTAMPTZ",
+        (DbDriver::PostgreSQL, FieldType::Json) => "JSONB",
+        (DbDriver::PostgreSQL, FieldType::Uuid) => "UUID",
+        (DbDriver::PostgreSQL, FieldType::Binary) => "BYTEA",
+        // SQLite type affinities
+        (_, FieldType::Integer | FieldType::Boolean) => "INTEGER",
+        (_, FieldType::Float) => "REAL",
+        (_, FieldType::Binary) => "BLOB",
+        _ => "TEXT",
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialects() {
+        let posts = Schema::create("posts", |t| {
+            t.increments("id");
+            t.integer("user_id").required();
+            t.string("slug").length(120).required();
+            t.boolean("published").default("false");
+            t.json("meta");
+            t.soft_deletes();
+            t.unique(&["user_id", "slug"]);
+            t.foreign_cascade("user_id", "users", "id");
+        });
+
+        assert_eq!(
+            posts.to_sql(DbDriver::SQLite),
+            r#"CREATE TABLE IF NOT EXISTS "posts" ("id" INTEGER PRIMARY KEY AUTOINCREMENT, "user_id" INTEGER NOT NULL, "slug" TEXT NOT NULL, "published" INTEGER DEFAULT false, "meta" TEXT, "deleted_at" TEXT, UNIQUE ("user_id", "slug"), FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON DELETE CASCADE)"#
+        );
+        assert_eq!(
+            posts.to_sql(DbDriver::MySQL),
+            "CREATE TABLE IF NOT EXISTS `posts` (`id` BIGINT AUTO_INCREMENT PRIMARY KEY, `user_id` BIGINT NOT NULL, `slug` VARCHAR(120) NOT NULL, `published` BOOLEAN DEFAULT false, `meta` JSON, `deleted_at` DATETIME, UNIQUE (`user_id`, `slug`), FOREIGN KEY (`user_id`) REFERENCES `users` (`id`) ON DELETE CASCADE)"
+        );
+        assert_eq!(
+            posts.to_sql(DbDriver::PostgreSQL),
+            r#"CREATE TABLE IF NOT EXISTS "posts" ("id" BIGSERIAL PRIMARY KEY, "user_id" BIGINT NOT NULL, "slug" VARCHAR(120) NOT NULL, "published" BOOLEAN DEFAULT false, "meta" JSONB, "deleted_at" TIMESTAMPTZ, UNIQUE ("user_id", "slug"), FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON DELETE CASCADE)"#
+        );
+    }
+}
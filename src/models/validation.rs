@@ -0,0 +1,292 @@
+//! Model Validation Rules
+//!
+//! The checks behind `#[validate(...)]` field attributes on
+//! `#[derive(Model)]` structs. Each returns the [`FieldError`] for a failed
+//! rule, and [`finish`] turns the collected errors into
+//! [`Error::ValidationFields`], a 422 response.
+//!
+//! ```rust,ignore
+//! #[derive(Clone, Serialize, Deserialize, Model)]
+//! struct User {
+//!     id: Option<i64>,
+//!     #[validate(email)]
+//!     email: String,
+//!     #[validate(length(min = 3, max = 32))]
+//!     name: String,
+//!     #[validate(range(min = 13))]
+//!     age: Option<u32>,
+//! }
+//! ```
+//!
+//! Rules on `Option` fields only run when the value is present, except
+//! `required`.
+//!
+//! `#[derive(Validate)]` applies the same rules to structs that are not
+//! models, such as request payloads. `#[validate(nested)]` runs a field's
+//! own rules and reports failures with a path: `address.city`, or
+//! `items[2].sku` inside a `Vec`.
+
+use crate::error::{Error, FieldError, Result};
+use crate::utils::validation;
+use std::collections::HashMap;
+
+/// Values that check themselves, usually via `#[derive(Validate)]`
+pub trait Validate {
+    /// `Err(Error::ValidationFields)` listing every failed rule
+    fn validate(&self) -> Result<()>;
+}
+
+/// Values with a length: characters for strings, elements for collections
+pub trait Length {
+    fn length(&self) -> usize;
+}
+
+impl Length for str {
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl Length for String {
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+}
+
+impl<T> Length for Vec<T> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V, S> Length for HashMap<K, V, S> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Numbers checked by `range`
+pub trait Number {
+    fn to_f64(&self) -> f64;
+}
+
+macro_rules! number {
+    ($($t:ty),*) => {
+        $(impl Number for $t {
+            fn to_f64(&self) -> f64 {
+                *self as f64
+            }
+        })*
+    };
+}
+
+number!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+/// Values checked by `required`: present and, for strings, not blank
+pub trait Required {
+    fn is_present(&self) -> bool;
+}
+
+impl<T> Required for Option<T> {
+    fn is_present(&self) -> bool {
+        self.is_some()
+    }
+}
+
+impl Required for String {
+    fn is_present(&self) -> bool {
+        !self.trim().is_empty()
+    }
+}
+
+impl<T> Required for Vec<T> {
+    fn is_present(&self) -> bool {
+        !self.is_empty()
+    }
+}
+
+pub fn required<T: Required + ?Sized>(field: &str, value: &T) -> Option<FieldError> {
+    (!value.is_present()).then(|| FieldError::new(field, "required", "is required"))
+}
+
+pub fn email(field: &str, value: &str) -> Option<FieldError> {
+    (!validation::is_email(value))
+        .then(|| FieldError::new(field, "email", "must be a valid email address"))
+}
+
+pub fn url(field: &str, value: &str) -> Option<FieldError> {
+    (!validation::is_url(value)).then(|| FieldError::new(field, "url", "must be a valid URL"))
+}
+
+pub fn length<T: Length + ?Sized>(
+    field: &str,
+    value: &T,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Option<FieldError> {
+    let len = value.length();
+    let message = match (min, max) {
+        (Some(min), Some(max)) if len < min || len > max => {
+            format!("length must be between {} and {}", min, max)
+        }
+        (Some(min), _) if len < min => format!("length must be at least {}", min),
+        (_, Some(max)) if len > max => format!("length must be at most {}", max),
+        _ => return None,
+    };
+    Some(FieldError::new(field, "length", message))
+}
+
+pub fn range<T: Number>(
+    field: &str,
+    value: &T,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> Option<FieldError> {
+    let value = value.to_f64();
+    let message = match (min, max) {
+        (Some(min), Some(max)) if value < min || value > max => {
+            format!("must be between {} and {}", min, max)
+        }
+        (Some(min), _) if value < min => format!("must be at least {}", min),
+        (_, Some(max)) if value > max => format!("must be at most {}", max),
+        _ => return None,
+    };
+    Some(FieldError::new(field, "range", message))
+}
+
+/// Run a `custom = "path"` rule: a function returning the failure message
+pub fn custom<T: ?Sized>(
+    field: &str,
+    value: &T,
+    check: fn(&T) -> std::result::Result<(), String>,
+) -> Option<FieldError> {
+    check(value)
+        .err()
+        .map(|message| FieldError::new(field, "custom", message))
+}
+
+/// Run a `nested` rule: the value's own rules, reported under `field`
+pub fn nested<T: Validate + ?Sized>(field: &str, value: &T) -> Vec<FieldError> {
+    match value.validate() {
+        Ok(()) => Vec::new(),
+        Err(Error::ValidationFields(errors)) => {
+            errors.into_iter().map(|e| e.prefixed(field)).collect()
+        }
+        Err(e) => vec![FieldError::new(field, "invalid", e.to_string())],
+    }
+}
+
+/// Run a `nested` rule on each element, reported under `field[index]`
+pub fn nested_each<T: Validate>(field: &str, values: &[T]) -> Vec<FieldError> {
+    values
+        .iter()
+        .enumerate()
+        .flat_map(|(i, value)| nested(&format!("{}[{}]", field, i), value))
+        .collect()
+}
+
+/// `Ok` when no rule failed
+pub fn finish(errors: Vec<FieldError>) -> Result<()> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::ValidationFields(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::models::{Model, Validate};
+    use serde::{Deserialize, Serialize};
+
+    fn not_admin(value: &String) -> std::result::Result<(), String> {
+        if value == "admin" {
+            Err("is reserved".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Serialize, Deserialize, Model)]
+    struct Signup {
+        id: Option<i64>,
+        #[validate(required, email)]
+        email: String,
+        #[validate(length(min = 3, max = 8), custom = "not_admin")]
+        name: String,
+        #[validate(range(min = 13))]
+        age: Option<u32>,
+        #[validate(url)]
+        website: Option<String>,
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let mut signup = Signup {
+            id: None,
+            email: "a@example.com".to_string(),
+            name: "ann".to_string(),
+            age: None,
+            website: None,
+        };
+        assert!(signup.validate().is_ok());
+
+        signup.email = String::new();
+        signup.name = "admin".to_string();
+        signup.age = Some(9);
+        signup.website = Some("not a url".to_string());
+        let err = signup.validate().unwrap_err();
+        assert_eq!(err.status_code(), 422);
+        let Error::ValidationFields(errors) = err else {
+            panic!("expected field errors");
+        };
+        let codes: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|e| (e.field.as_str(), e.code.as_str()))
+            .collect();
+        assert_eq!(
+            codes,
+            [
+                ("email", "required"),
+                ("email", "email"),
+                ("name", "custom"),
+                ("age", "range"),
+                ("website", "url"),
+            ]
+        );
+        assert_eq!(errors[3].message, "must be at least 13");
+    }
+
+    #[derive(Validate)]
+    struct Line {
+        #[validate(length(min = 1))]
+        sku: String,
+    }
+
+    #[derive(Validate)]
+    struct Order {
+        #[validate(nested)]
+        lines: Vec<Line>,
+        #[validate(nested)]
+        gift: Option<Line>,
+    }
+
+    #[test]
+    fn test_nested_field_paths() {
+        let order = Order {
+            lines: vec![
+                Line {
+                    sku: "A-1".to_string(),
+                },
+                Line { sku: String::new() },
+            ],
+            gift: Some(Line { sku: String::new() }),
+        };
+        let Err(Error::ValidationFields(errors)) = order.validate() else {
+            panic!("expected field errors");
+        };
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["lines[1].sku", "gift.sku"]);
+    }
+}
@@ -0,0 +1,770 @@
+//! OpenAPI Documentation
+//!
+//! Builds an OpenAPI 3.1 document from the app's routes. Routes describe
+//! themselves with an [`Operation`], and request and response schemas come
+//! from types implementing [`ToSchema`], usually through
+//! `#[derive(ToSchema)]`, which follows the type's serde attributes.
+//!
+//! ```rust,ignore
+//! /// A registered user
+//! #[derive(Serialize, Deserialize, ToSchema)]
+//! struct User {
+//!     id: u64,
+//!     name: String,
+//!     email: Option<String>,
+//! }
+//!
+//! app.post("/users", create_user).doc(
+//!     Operation::new("Create a user")
+//!         .tag("users")
+//!         .body::<NewUser>()
+//!         .response::<User>(201, "The created user")
+//!         .status(422, "Validation failed"),
+//! );
+//!
+//! // GET /openapi.json, plus Swagger UI at /docs
+//! app.openapi(OpenApi::new("Users API", "1.0.0").swagger_ui("/docs"));
+//! ```
+//!
+//! Routes without an `Operation` are listed with their path parameters and
+//! a generic `200` response, so the document always covers the whole app.
+
+use crate::middleware::sanitize::escape_html;
+use crate::router::Router;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+pub use rustyx_macros::ToSchema;
+
+/// A type with a JSON Schema, as used in OpenAPI 3.1 documents
+///
+/// ```rust,ignore
+/// struct Money(i64);
+///
+/// impl ToSchema for Money {
+///     fn schema() -> Value {
+///         json!({ "type": "integer", "description": "Amount in cents" })
+///     }
+/// }
+/// ```
+pub trait ToSchema {
+    /// JSON Schema describing the serialized value
+    fn schema() -> Value;
+
+    /// Whether the value must be present when used as a field or
+    /// parameter; `false` for `Option`
+    fn required() -> bool {
+        true
+    }
+}
+
+macro_rules! impl_schema {
+    ($($ty:ty),* => $schema:tt) => {
+        $(
+            impl ToSchema for $ty {
+                fn schema() -> Value {
+                    json!($schema)
+                }
+            }
+        )*
+    };
+}
+
+impl_schema!(bool => { "type": "boolean" });
+impl_schema!(i8, i16, i32 => { "type": "integer", "format": "int32" });
+impl_schema!(u8, u16 => { "type": "integer", "format": "int32", "minimum": 0 });
+impl_schema!(i64, i128, isize => { "type": "integer", "format": "int64" });
+impl_schema!(u32, u64, u128, usize => { "type": "integer", "format": "int64", "minimum": 0 });
+impl_schema!(f32 => { "type": "number", "format": "float" });
+impl_schema!(f64 => { "type": "number", "format": "double" });
+impl_schema!(String, str, char => { "type": "string" });
+impl_schema!(uuid::Uuid => { "type": "string", "format": "uuid" });
+impl_schema!(chrono::NaiveDate => { "type": "string", "format": "date" });
+impl_schema!(chrono::NaiveDateTime => { "type": "string", "format": "date-time" });
+impl_schema!(Value => {});
+
+impl<Tz: chrono::TimeZone> ToSchema for chrono::DateTime<Tz> {
+    fn schema() -> Value {
+        json!({ "type": "string", "format": "date-time" })
+    }
+}
+
+impl<T: ToSchema> ToSchema for Option<T> {
+    /// The inner schema, also allowing `null`
+    fn schema() -> Value {
+        let mut schema = T::schema();
+        match schema.get_mut("type") {
+            Some(Value::String(ty)) => {
+                let ty = std::mem::take(ty);
+                schema["type"] = json!([ty, "null"]);
+                schema
+            }
+            _ => json!({ "oneOf": [schema, { "type": "null" }] }),
+        }
+    }
+
+    fn required() -> bool {
+        false
+    }
+}
+
+impl<T: ToSchema + ?Sized> ToSchema for &T {
+    fn schema() -> Value {
+        T::schema()
+    }
+
+    fn required() -> bool {
+        T::required()
+    }
+}
+
+impl<T: ToSchema + ?Sized> ToSchema for Box<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+
+    fn required() -> bool {
+        T::required()
+    }
+}
+
+impl<T: ToSchema> ToSchema for [T] {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: ToSchema> ToSchema for Vec<T> {
+    fn schema() -> Value {
+        <[T]>::schema()
+    }
+}
+
+impl<T: ToSchema, S> ToSchema for HashSet<T, S> {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema(), "uniqueItems": true })
+    }
+}
+
+impl<T: ToSchema> ToSchema for BTreeSet<T> {
+    fn schema() -> Value {
+        HashSet::<T>::schema()
+    }
+}
+
+impl<K, V: ToSchema, S> ToSchema for HashMap<K, V, S> {
+    fn schema() -> Value {
+        json!({ "type": "object", "additionalProperties": V::schema() })
+    }
+}
+
+impl<K, V: ToSchema> ToSchema for BTreeMap<K, V> {
+    fn schema() -> Value {
+        HashMap::<K, V>::schema()
+    }
+}
+
+/// Builder for object schemas, used by `#[derive(ToSchema)]` and handy in
+/// manual [`ToSchema`] impls
+#[derive(Debug, Default)]
+pub struct ObjectSchema {
+    description: Option<String>,
+    properties: Map<String, Value>,
+    required: Vec<String>,
+}
+
+impl ObjectSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Add a property with the schema of `T`, required unless `T` is an
+    /// `Option`
+    pub fn property<T: ToSchema + ?Sized>(self, name: &str) -> Self {
+        self.field(name, T::schema(), T::required())
+    }
+
+    /// Add a property with an explicit schema
+    pub fn field(mut self, name: &str, schema: Value, required: bool) -> Self {
+        self.properties.insert(name.to_string(), schema);
+        if required {
+            self.required.push(name.to_string());
+        }
+        self
+    }
+
+    /// Merge the properties of another object schema, as
+    /// `#[serde(flatten)]` does
+    pub fn flatten(mut self, schema: Value) -> Self {
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            self.properties.extend(properties.clone());
+        }
+        if let Some(Value::Array(required)) = schema.get("required") {
+            self.required
+                .extend(required.iter().filter_map(|r| r.as_str().map(String::from)));
+        }
+        self
+    }
+
+    pub fn build(self) -> Value {
+        let mut schema = json!({ "type": "object", "properties": self.properties });
+        if !self.required.is_empty() {
+            schema["required"] = json!(self.required);
+        }
+        if let Some(description) = self.description {
+            schema["description"] = json!(description);
+        }
+        schema
+    }
+}
+
+/// `schema` with a `description`
+pub fn described(mut schema: Value, description: &str) -> Value {
+    if let Value::Object(map) = &mut schema {
+        map.insert("description".to_string(), json!(description));
+    }
+    schema
+}
+
+/// Where a parameter is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamLocation {
+    Path,
+    Query,
+    Header,
+    Cookie,
+}
+
+impl ParamLocation {
+    fn as_str(self) -> &'static str {
+        match self {
+            ParamLocation::Path => "path",
+            ParamLocation::Query => "query",
+            ParamLocation::Header => "header",
+            ParamLocation::Cookie => "cookie",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Parameter {
+    name: String,
+    location: ParamLocation,
+    description: String,
+    required: bool,
+    schema: Value,
+}
+
+#[derive(Debug, Clone)]
+struct ResponseDoc {
+    status: u16,
+    description: String,
+    schema: Option<Value>,
+}
+
+/// Documentation for one route
+///
+/// Attach it with `.doc(...)` after registering the route on the app, a
+/// [`Router`] or a [`RouteGroup`](crate::routes::RouteGroup).
+#[derive(Debug, Clone, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    description: Option<String>,
+    operation_id: Option<String>,
+    tags: Vec<String>,
+    parameters: Vec<Parameter>,
+    request_body: Option<(String, Value)>,
+    responses: Vec<ResponseDoc>,
+    security: Vec<String>,
+    deprecated: bool,
+    hidden: bool,
+}
+
+impl Operation {
+    pub fn new(summary: &str) -> Self {
+        Self {
+            summary: Some(summary.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Longer description, CommonMark allowed
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn operation_id(mut self, id: &str) -> Self {
+        self.operation_id = Some(id.to_string());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Describe a path parameter; undescribed ones are listed as strings
+    pub fn param<T: ToSchema>(self, name: &str, description: &str) -> Self {
+        self.parameter::<T>(ParamLocation::Path, name, description)
+    }
+
+    /// Describe a query parameter, optional when `T` is an `Option`
+    pub fn query<T: ToSchema>(self, name: &str, description: &str) -> Self {
+        self.parameter::<T>(ParamLocation::Query, name, description)
+    }
+
+    /// Describe a request header, optional when `T` is an `Option`
+    pub fn header<T: ToSchema>(self, name: &str, description: &str) -> Self {
+        self.parameter::<T>(ParamLocation::Header, name, description)
+    }
+
+    pub fn parameter<T: ToSchema>(
+        mut self,
+        location: ParamLocation,
+        name: &str,
+        description: &str,
+    ) -> Self {
+        self.parameters.push(Parameter {
+            name: name.to_string(),
+            location,
+            description: description.to_string(),
+            required: location == ParamLocation::Path || T::required(),
+            schema: T::schema(),
+        });
+        self
+    }
+
+    /// JSON request body
+    pub fn body<T: ToSchema>(self) -> Self {
+        self.body_with("application/json", T::schema())
+    }
+
+    /// Request body with another content type, e.g. `multipart/form-data`
+    pub fn body_with(mut self, content_type: &str, schema: Value) -> Self {
+        self.request_body = Some((content_type.to_string(), schema));
+        self
+    }
+
+    /// Response with a JSON body
+    pub fn response<T: ToSchema>(mut self, status: u16, description: &str) -> Self {
+        self.responses.push(ResponseDoc {
+            status,
+            description: description.to_string(),
+            schema: Some(T::schema()),
+        });
+        self
+    }
+
+    /// Response without a body
+    pub fn status(mut self, status: u16, description: &str) -> Self {
+        self.responses.push(ResponseDoc {
+            status,
+            description: description.to_string(),
+            schema: None,
+        });
+        self
+    }
+
+    /// Require a security scheme declared with [`OpenApi::security_scheme`]
+    /// or [`OpenApi::bearer_auth`]
+    pub fn security(mut self, scheme: &str) -> Self {
+        self.security.push(scheme.to_string());
+        self
+    }
+
+    pub fn deprecated(mut self) -> Self {
+        self.deprecated = true;
+        self
+    }
+
+    /// Leave the route out of the document
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    pub(crate) fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    fn to_json(&self, path_params: &[String]) -> Value {
+        let mut op = Map::new();
+        if let Some(summary) = &self.summary {
+            op.insert("summary".to_string(), json!(summary));
+        }
+        if let Some(description) = &self.description {
+            op.insert("description".to_string(), json!(description));
+        }
+        if let Some(id) = &self.operation_id {
+            op.insert("operationId".to_string(), json!(id));
+        }
+        if !self.tags.is_empty() {
+            op.insert("tags".to_string(), json!(self.tags));
+        }
+
+        let mut parameters: Vec<Value> = path_params
+            .iter()
+            .filter(|name| {
+                !self
+                    .parameters
+                    .iter()
+                    .any(|p| p.location == ParamLocation::Path && &p.name == *name)
+            })
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        parameters.extend(self.parameters.iter().map(|p| {
+            let mut param = json!({
+                "name": p.name,
+                "in": p.location.as_str(),
+                "required": p.required,
+                "schema": p.schema,
+            });
+            if !p.description.is_empty() {
+                param["description"] = json!(p.description);
+            }
+            param
+        }));
+        if !parameters.is_empty() {
+            op.insert("parameters".to_string(), json!(parameters));
+        }
+
+        if let Some((content_type, schema)) = &self.request_body {
+            op.insert(
+                "requestBody".to_string(),
+                json!({ "required": true, "content": { content_type: { "schema": schema } } }),
+            );
+        }
+
+        let mut responses = Map::new();
+        for response in &self.responses {
+            let mut doc = json!({ "description": response.description });
+            if let Some(schema) = &response.schema {
+                doc["content"] = json!({ "application/json": { "schema": schema } });
+            }
+            responses.insert(response.status.to_string(), doc);
+        }
+        if responses.is_empty() {
+            responses.insert("200".to_string(), json!({ "description": "OK" }));
+        }
+        op.insert("responses".to_string(), Value::Object(responses));
+
+        if !self.security.is_empty() {
+            let security: Vec<Value> = self.security.iter().map(|s| json!({ s: [] })).collect();
+            op.insert("security".to_string(), json!(security));
+        }
+        if self.deprecated {
+            op.insert("deprecated".to_string(), json!(true));
+        }
+        Value::Object(op)
+    }
+}
+
+/// Document settings and the pages serving it
+///
+/// Registered with [`RustyX::openapi`](crate::RustyX::openapi).
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+    servers: Vec<String>,
+    security_schemes: Map<String, Value>,
+    pub(crate) path: String,
+    pub(crate) swagger_ui: Option<String>,
+    pub(crate) redoc: Option<String>,
+}
+
+impl OpenApi {
+    pub fn new(title: &str, version: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            version: version.to_string(),
+            description: None,
+            servers: Vec::new(),
+            security_schemes: Map::new(),
+            path: "/openapi.json".to_string(),
+            swagger_ui: None,
+            redoc: None,
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    /// Add a server URL, e.g. `https://api.example.com`
+    pub fn server(mut self, url: &str) -> Self {
+        self.servers.push(url.to_string());
+        self
+    }
+
+    /// Declare a security scheme referenced by [`Operation::security`]
+    pub fn security_scheme(mut self, name: &str, scheme: Value) -> Self {
+        self.security_schemes.insert(name.to_string(), scheme);
+        self
+    }
+
+    /// Declare the `bearerAuth` scheme for JWT bearer tokens
+    pub fn bearer_auth(self) -> Self {
+        self.security_scheme(
+            "bearerAuth",
+            json!({ "type": "http", "scheme": "bearer", "bearerFormat": "JWT" }),
+        )
+    }
+
+    /// Serve the document at `path` instead of `/openapi.json`
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Serve a Swagger UI page at `path`
+    pub fn swagger_ui(mut self, path: &str) -> Self {
+        self.swagger_ui = Some(path.to_string());
+        self
+    }
+
+    /// Serve a Redoc page at `path`
+    pub fn redoc(mut self, path: &str) -> Self {
+        self.redoc = Some(path.to_string());
+        self
+    }
+
+    /// The OpenAPI document for `router`'s routes
+    pub fn document(&self, router: &Router) -> Value {
+        let mut paths: BTreeMap<String, Map<String, Value>> = BTreeMap::new();
+        for (method, path, operation) in router.operations() {
+            if operation.is_some_and(|op| op.hidden) {
+                continue;
+            }
+            let (path, params) = openapi_path(path);
+            let operation = match operation {
+                Some(op) => op.to_json(&params),
+                None => Operation::default().to_json(&params),
+            };
+            paths
+                .entry(path)
+                .or_default()
+                .insert(method.as_str().to_lowercase(), operation);
+        }
+
+        let mut info = json!({ "title": self.title, "version": self.version });
+        if let Some(description) = &self.description {
+            info["description"] = json!(description);
+        }
+        let mut doc = json!({ "openapi": "3.1.0", "info": info, "paths": paths });
+        if !self.servers.is_empty() {
+            let servers: Vec<Value> = self
+                .servers
+                .iter()
+                .map(|url| json!({ "url": url }))
+                .collect();
+            doc["servers"] = json!(servers);
+        }
+        if !self.security_schemes.is_empty() {
+            doc["components"] = json!({ "securitySchemes": self.security_schemes });
+        }
+        doc
+    }
+
+    pub(crate) fn swagger_ui_html(&self) -> String {
+        format!(
+            r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>{title}</title>
+  <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://cdn.jsdelivr.net/npm/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({{ url: "{spec}", dom_id: "#swagger-ui" }});</script>
+</body>
+</html>
+"##,
+            title = escape_html(&self.title),
+            spec = escape_html(&self.path),
+        )
+    }
+
+    pub(crate) fn redoc_html(&self) -> String {
+        format!(
+            r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1">
+  <title>{title}</title>
+</head>
+<body>
+  <redoc spec-url="{spec}"></redoc>
+  <script src="https://cdn.jsdelivr.net/npm/redoc@2/bundles/redoc.standalone.js"></script>
+</body>
+</html>
+"##,
+            title = escape_html(&self.title),
+            spec = escape_html(&self.path),
+        )
+    }
+}
+
+/// OpenAPI form of a route path (`/users/:id` → `/users/{id}`) and its
+/// parameter names
+fn openapi_path(path: &str) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(path.len());
+    let mut params = Vec::new();
+    for (i, segment) in path.split('/').enumerate() {
+        if i > 0 {
+            out.push('/');
+        }
+        let name = segment
+            .strip_prefix(':')
+            .or_else(|| (segment == "*").then_some("wildcard"));
+        match name {
+            Some(name) => {
+                out.push_str(&format!("{{{}}}", name));
+                params.push(name.to_string());
+            }
+            None => out.push_str(segment),
+        }
+    }
+    (out, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    /// A registered user
+    #[derive(Serialize, Deserialize, ToSchema)]
+    #[serde(rename_all = "camelCase")]
+    #[allow(dead_code)]
+    struct User {
+        id: u64,
+        /// Display name
+        full_name: String,
+        email: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+        #[serde(skip)]
+        password_hash: String,
+        role: Role,
+    }
+
+    #[derive(Serialize, Deserialize, ToSchema)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(dead_code)]
+    enum Role {
+        Admin,
+        Member,
+    }
+
+    #[derive(Serialize, ToSchema)]
+    #[allow(dead_code)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { width: f64, height: f64 },
+    }
+
+    #[test]
+    fn test_derived_schema() {
+        assert_eq!(
+            User::schema(),
+            json!({
+                "type": "object",
+                "description": "A registered user",
+                "properties": {
+                    "id": { "type": "integer", "format": "int64", "minimum": 0 },
+                    "fullName": { "type": "string", "description": "Display name" },
+                    "email": { "type": ["string", "null"] },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "role": { "type": "string", "enum": ["admin", "member"] },
+                },
+                "required": ["id", "fullName", "role"],
+            })
+        );
+        assert_eq!(
+            Shape::schema(),
+            json!({ "oneOf": [
+                { "type": "string", "const": "Empty" },
+                {
+                    "type": "object",
+                    "properties": { "Circle": { "type": "number", "format": "double" } },
+                    "required": ["Circle"],
+                },
+                {
+                    "type": "object",
+                    "properties": { "Rect": {
+                        "type": "object",
+                        "properties": {
+                            "width": { "type": "number", "format": "double" },
+                            "height": { "type": "number", "format": "double" },
+                        },
+                        "required": ["width", "height"],
+                    } },
+                    "required": ["Rect"],
+                },
+            ] })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_openapi_route() {
+        let app = RustyX::new();
+        app.get("/users/:id", |_req, res| async move { res.json(json!({})) })
+            .doc(
+                Operation::new("Get a user")
+                    .tag("users")
+                    .query::<Option<bool>>("expand", "Include relations")
+                    .response::<User>(200, "The user")
+                    .status(404, "No such user"),
+            );
+        app.delete("/users/:id", |_req, res| async move { res.status(204) });
+        app.openapi(OpenApi::new("Users", "1.0.0").swagger_ui("/docs"));
+
+        let res = app.test().get("/openapi.json").send().await;
+        res.assert_status(200);
+        let doc: Value = res.json();
+        assert_eq!(doc["openapi"], "3.1.0");
+        assert_eq!(doc["info"]["title"], "Users");
+        assert!(doc["paths"].get("/openapi.json").is_none());
+
+        let get = &doc["paths"]["/users/{id}"]["get"];
+        assert_eq!(get["summary"], "Get a user");
+        assert_eq!(get["parameters"][0]["name"], "id");
+        assert_eq!(get["parameters"][0]["in"], "path");
+        assert_eq!(get["parameters"][1]["required"], false);
+        assert_eq!(
+            get["responses"]["200"]["content"]["application/json"]["schema"],
+            User::schema()
+        );
+        assert_eq!(get["responses"]["404"]["description"], "No such user");
+        let delete = &doc["paths"]["/users/{id}"]["delete"];
+        assert_eq!(delete["responses"]["200"]["description"], "OK");
+
+        app.test()
+            .get("/docs")
+            .send()
+            .await
+            .assert_status(200)
+            .assert_header("content-type", "text/html");
+    }
+}
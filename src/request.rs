@@ -2,15 +2,19 @@
 //!
 //! Provides the Request struct similar to Express's req object.
 
-use crate::error::{Error, Result};
+use crate::error::{Error, FieldError, Result};
+use crate::models::validation::Validate;
 
 use bytes::Bytes;
 use http_body_util::BodyExt;
-use hyper::body::Incoming;
-use hyper::{header::HeaderValue, HeaderMap, Method, Uri, Version};
+use hyper::header::{HeaderName, HeaderValue, CONTENT_LENGTH};
+use hyper::http::Extensions;
+use hyper::{HeaderMap, Method, Uri, Version};
 use serde::de::DeserializeOwned;
 use std::collections::HashMap;
+use std::future::Future;
 use std::net::SocketAddr;
+use tokio_util::sync::CancellationToken;
 
 /// Request struct similar to Express's req object
 #[derive(Debug)]
@@ -23,19 +27,79 @@ pub struct Request {
     params: HashMap<String, String>,
     query: HashMap<String, String>,
     remote_addr: SocketAddr,
+    extensions: Extensions,
+    cancel: CancellationToken,
 }
 
 impl Request {
     /// Create a new Request from a hyper request
-    pub async fn from_hyper(
-        req: hyper::Request<Incoming>,
-        remote_addr: SocketAddr,
-    ) -> Result<Self> {
+    ///
+    /// Bodies wrapped in [`Limited`](http_body_util::Limited) fail with
+    /// [`Error::PayloadTooLarge`] past their limit.
+    pub async fn from_hyper<B>(req: hyper::Request<B>, remote_addr: SocketAddr) -> Result<Self>
+    where
+        B: hyper::body::Body,
+        B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
         let (parts, body) = req.into_parts();
 
+        // Collect body bytes
+        let body_bytes = body
+            .collect()
+            .await
+            .map_err(|e| {
+                let e = e.into();
+                if e.is::<http_body_util::LengthLimitError>() {
+                    Error::PayloadTooLarge(e.to_string())
+                } else {
+                    Error::Internal(e.to_string())
+                }
+            })?
+            .to_bytes();
+
+        let mut request = Self::from_parts(
+            parts.method,
+            parts.uri,
+            parts.headers,
+            body_bytes,
+            remote_addr,
+        );
+        request.version = parts.version;
+        request.extensions = parts.extensions;
+        Ok(request)
+    }
+
+    /// Build a request without a connection, e.g. to unit-test handlers
+    /// and middleware
+    ///
+    /// ```rust
+    /// use rustyx::Request;
+    /// use hyper::Method;
+    ///
+    /// let req = Request::builder()
+    ///     .method(Method::POST)
+    ///     .path("/users/7?notify=true")
+    ///     .param("id", "7")
+    ///     .header("authorization", "Bearer abc")
+    ///     .json(&serde_json::json!({ "name": "Ann" }))
+    ///     .build();
+    /// assert_eq!(req.param("id").unwrap(), "7");
+    /// assert_eq!(req.query_param("notify").unwrap(), "true");
+    /// assert_eq!(req.bearer_token(), Some("abc"));
+    /// ```
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::new()
+    }
+
+    fn from_parts(
+        method: Method,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Bytes,
+        remote_addr: SocketAddr,
+    ) -> Self {
         // Parse query string
-        let query = parts
-            .uri
+        let query = uri
             .query()
             .map(|q| {
                 url::form_urlencoded::parse(q.as_bytes())
@@ -44,23 +108,18 @@ impl Request {
             })
             .unwrap_or_default();
 
-        // Collect body bytes
-        let body_bytes = body
-            .collect()
-            .await
-            .map_err(|e| Error::Internal(e.to_string()))?
-            .to_bytes();
-
-        Ok(Self {
-            method: parts.method,
-            uri: parts.uri,
-            version: parts.version,
-            headers: parts.headers,
-            body: body_bytes,
+        Self {
+            method,
+            uri,
+            version: Version::HTTP_11,
+            headers,
+            body,
             params: HashMap::new(),
             query,
             remote_addr,
-        })
+            extensions: Extensions::new(),
+            cancel: CancellationToken::new(),
+        }
     }
 
     /// Get the HTTP method
@@ -103,6 +162,11 @@ impl Request {
         &self.body
     }
 
+    /// Replace the body bytes (used by body-rewriting middleware)
+    pub fn set_body(&mut self, body: impl Into<Bytes>) {
+        self.body = body.into();
+    }
+
     /// Get the body as a string
     pub fn body_string(&self) -> Result<String> {
         String::from_utf8(self.body.to_vec())
@@ -122,9 +186,44 @@ impl Request {
     ///
     /// let user: CreateUser = req.json()?;
     /// ```
+    ///
+    /// Malformed JSON is a `ParseError` (400). JSON that does not fit `T`,
+    /// such as a missing field, is `Error::ValidationFields` (422) naming
+    /// the field when serde reports it.
     pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
-        serde_json::from_slice(&self.body)
-            .map_err(|e| Error::ParseError(format!("JSON parse error: {}", e)))
+        serde_json::from_slice(&self.body).map_err(json_error)
+    }
+
+    /// Parse the JSON body and run its `#[derive(Validate)]` rules
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// #[derive(Deserialize, Validate)]
+    /// struct CreateUser {
+    ///     #[validate(length(min = 3))]
+    ///     name: String,
+    ///     #[validate(email)]
+    ///     email: String,
+    /// }
+    ///
+    /// let user: CreateUser = req.validated_json()?;
+    /// ```
+    ///
+    /// Fails like [`json`](Self::json), or with `Error::ValidationFields`
+    /// (422) listing every rule the payload breaks.
+    pub fn validated_json<T: DeserializeOwned + Validate>(&self) -> Result<T> {
+        let value: T = self.json()?;
+        value.validate()?;
+        Ok(value)
+    }
+
+    /// The payload checked by the
+    /// [`validate_json`](crate::middleware::validate_json()) middleware
+    pub fn validated<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions
+            .get::<Validated<T>>()
+            .map(|validated| validated.0.as_ref())
     }
 
     /// Get route parameters
@@ -166,6 +265,106 @@ impl Request {
         self.query.get(name)
     }
 
+    /// Get a cookie value from the Cookie header
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.header("cookie")?
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.trim_matches('"').to_string())
+    }
+
+    /// Get request extensions (per-request data set by middleware)
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get mutable request extensions
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// #[derive(Clone)]
+    /// struct CurrentUser(String);
+    ///
+    /// req.extensions_mut().insert(CurrentUser("alice".into()));
+    /// let user = req.extensions().get::<CurrentUser>();
+    /// ```
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get the locale resolved by the locale middleware (defaults to `en`)
+    pub fn locale(&self) -> &str {
+        self.extensions
+            .get::<crate::i18n::Locale>()
+            .map(|l| l.0.as_str())
+            .unwrap_or(crate::i18n::DEFAULT_LOCALE)
+    }
+
+    /// Get the tenant resolved by the tenancy middleware
+    pub fn tenant(&self) -> Option<&crate::tenancy::Tenant> {
+        self.extensions.get::<crate::tenancy::Tenant>()
+    }
+
+    /// Translate a message key into the request locale
+    ///
+    /// Returns the key itself when no catalog is configured or the key is missing.
+    pub fn t(&self, key: &str) -> String {
+        self.t_with(key, &[])
+    }
+
+    /// Translate a message key with `{name}` arguments; a numeric `count`
+    /// selects the plural form
+    ///
+    /// ```rust,ignore
+    /// req.t_with("cart.items", &[("count", json!(3))])
+    /// ```
+    pub fn t_with(&self, key: &str, args: &[(&str, serde_json::Value)]) -> String {
+        self.extensions
+            .get::<crate::i18n::SharedCatalog>()
+            .and_then(|catalog| catalog.0.format(self.locale(), key, args))
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    /// Get a token that is cancelled when the client disconnects
+    ///
+    /// Pass it to spawned work so it stops when nobody is waiting for the
+    /// result. The handler future itself is dropped on disconnect.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Wait until the client disconnects
+    ///
+    /// The handler is dropped on disconnect, so await this from work that
+    /// outlives it, such as a spawned task.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// app.post("/reports", |req, res| async move {
+    ///     let token = req.cancellation_token();
+    ///     let closed = req.on_close();
+    ///     tokio::spawn(async move {
+    ///         tokio::select! {
+    ///             report = build_report(token) => store_report(report).await,
+    ///             _ = closed => tracing::info!("client left, report abandoned"),
+    ///         }
+    ///     });
+    ///     res.status(202)
+    /// });
+    /// ```
+    pub fn on_close(&self) -> impl Future<Output = ()> + Send + 'static {
+        let token = self.cancel.clone();
+        async move { token.cancelled().await }
+    }
+
+    /// Check if the client has disconnected
+    pub fn is_closed(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
     /// Get the remote address of the client
     pub fn remote_addr(&self) -> SocketAddr {
         self.remote_addr
@@ -228,4 +427,220 @@ impl Request {
             .filter(|auth| auth.starts_with("Bearer "))
             .map(|auth| &auth[7..])
     }
+
+    /// Check a link from [`signed_url::sign`](crate::utils::signed_url::sign)
+    pub fn verify_signature(&self, secret: &[u8]) -> Result<()> {
+        let path = self
+            .uri
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/");
+        crate::utils::signed_url::verify(path, secret)
+    }
+
+    /// Check the `X-Webhook-Signature` header of a webhook sent by
+    /// `Webhooks` (feature `webhooks`) or another signer using the same
+    /// scheme, see [`utils::webhook`](crate::utils::webhook)
+    pub fn verify_webhook(&self, secret: &[u8], tolerance: std::time::Duration) -> Result<()> {
+        let header = self
+            .header(crate::utils::webhook::SIGNATURE_HEADER)
+            .ok_or_else(|| Error::Unauthorized("webhook signature is missing".to_string()))?;
+        crate::utils::webhook::verify(secret, header, &self.body, tolerance)
+    }
+}
+
+/// Builder returned by [`Request::builder`]
+///
+/// Setters panic on invalid paths and headers, as the builder is meant
+/// for tests.
+#[derive(Debug)]
+pub struct RequestBuilder {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+    params: HashMap<String, String>,
+    remote_addr: SocketAddr,
+    extensions: Extensions,
+}
+
+impl Default for RequestBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestBuilder {
+    /// A `GET /` request from `127.0.0.1`
+    pub fn new() -> Self {
+        Self {
+            method: Method::GET,
+            uri: Uri::from_static("/"),
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+            params: HashMap::new(),
+            remote_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            extensions: Extensions::new(),
+        }
+    }
+
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Set the path, with an optional query string
+    pub fn path(mut self, path: &str) -> Self {
+        self.uri = path
+            .parse()
+            .unwrap_or_else(|_| panic!("invalid request path `{}`", path));
+        self
+    }
+
+    /// Set a header, replacing any previous value
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .unwrap_or_else(|_| panic!("invalid header name `{}`", name));
+        let value = HeaderValue::from_str(value)
+            .unwrap_or_else(|_| panic!("invalid value for header `{}`", name));
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Set a route parameter, as the router would for `/:name` segments
+    pub fn param(mut self, name: &str, value: &str) -> Self {
+        self.params.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set a raw body
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Set a JSON body and content type
+    pub fn json<T: serde::Serialize + ?Sized>(self, value: &T) -> Self {
+        let body = serde_json::to_vec(value).expect("request body serializes to JSON");
+        self.header("content-type", "application/json").body(body)
+    }
+
+    /// Set a URL-encoded form body and content type
+    pub fn form(self, fields: &[(&str, &str)]) -> Self {
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(fields)
+            .finish();
+        self.header("content-type", "application/x-www-form-urlencoded")
+            .body(body)
+    }
+
+    /// Set the client address
+    pub fn remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.remote_addr = addr;
+        self
+    }
+
+    /// Add a typed extension, e.g. a `Principal` set by auth middleware
+    pub fn extension<T: Clone + Send + Sync + 'static>(mut self, value: T) -> Self {
+        self.extensions.insert(value);
+        self
+    }
+
+    pub fn build(self) -> Request {
+        let mut headers = self.headers;
+        if !self.body.is_empty() && !headers.contains_key(CONTENT_LENGTH) {
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(self.body.len()));
+        }
+        let mut request =
+            Request::from_parts(self.method, self.uri, headers, self.body, self.remote_addr);
+        request.params = self.params;
+        request.extensions = self.extensions;
+        request
+    }
+}
+
+/// A payload stored by the validation middleware
+pub(crate) struct Validated<T>(pub(crate) std::sync::Arc<T>);
+
+impl<T> Clone for Validated<T> {
+    fn clone(&self) -> Self {
+        Self(std::sync::Arc::clone(&self.0))
+    }
+}
+
+fn json_error(e: serde_json::Error) -> Error {
+    if !e.is_data() {
+        return Error::ParseError(format!("JSON parse error: {}", e));
+    }
+    let message = e.to_string();
+    let message = message.split(" at line ").next().unwrap_or_default();
+    let field = message.split('`').nth(1).unwrap_or_default();
+    let code = if message.starts_with("missing field") {
+        "required"
+    } else if message.starts_with("unknown field") {
+        "unknown"
+    } else {
+        "invalid"
+    };
+    let field = if code == "invalid" { "" } else { field };
+    Error::ValidationFields(vec![FieldError::new(field, code, message)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RustyX;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_dropped_dispatch_cancels_request() {
+        let app = RustyX::new();
+        let abandoned = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&abandoned);
+        app.get("/slow", move |req, res| {
+            let flag = Arc::clone(&flag);
+            async move {
+                let closed = req.on_close();
+                tokio::spawn(async move {
+                    closed.await;
+                    flag.store(true, Ordering::SeqCst);
+                });
+                std::future::pending::<()>().await;
+                res
+            }
+        });
+
+        let req = Request::builder().path("/slow").build();
+        let token = req.cancellation_token();
+        let closed = req.on_close();
+        let dispatch = app.handle(req);
+        assert!(tokio::time::timeout(Duration::from_millis(20), dispatch)
+            .await
+            .is_err());
+
+        assert!(token.is_cancelled());
+        tokio::time::timeout(Duration::from_secs(1), closed)
+            .await
+            .expect("on_close resolves");
+        for _ in 0..100 {
+            if abandoned.load(Ordering::SeqCst) {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert!(abandoned.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_completed_dispatch_keeps_token() {
+        let app = RustyX::new();
+        app.get("/", |_req, res| async move { res.send("ok") });
+        let req = Request::builder().path("/").build();
+        let token = req.cancellation_token();
+        let res = app.handle(req).await;
+        assert_eq!(res.get_status(), 200);
+        assert!(!token.is_cancelled());
+    }
 }
@@ -2,16 +2,51 @@
 //!
 //! Provides the Response struct similar to Express's res object.
 
+use crate::error::{Error, ErrorFormat};
+use crate::router::MatchedRoute;
+use crate::views::Views;
 use bytes::Bytes;
-use http_body_util::Full;
+use futures::{Stream, TryStreamExt};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full, StreamBody};
+use hyper::body::Frame;
 use hyper::{header, HeaderMap, StatusCode};
 use serde::Serialize;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// A body sent to the client as it is produced
+pub type BodyStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// The body type of responses handed to hyper
+pub type ResponseBody = UnsyncBoxBody<Bytes, std::io::Error>;
+
+/// Headers [`Response::pipe`] copies from the upstream response
+pub const PIPED_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "content-disposition",
+    "content-encoding",
+    "content-language",
+    "content-range",
+    "accept-ranges",
+    "etag",
+    "last-modified",
+    "cache-control",
+    "expires",
+];
 
 /// Response struct similar to Express's res object
 pub struct Response {
     status: StatusCode,
     headers: HeaderMap,
     body: Bytes,
+    /// Replaces `body` when set
+    stream: Option<BodyStream>,
+    error: Option<Arc<Error>>,
+    /// The app's views and whether to reload templates, for `render`
+    views: Option<(Arc<Views>, bool)>,
+    route: Option<MatchedRoute>,
 }
 
 impl Response {
@@ -21,6 +56,10 @@ impl Response {
             status: StatusCode::OK,
             headers: HeaderMap::new(),
             body: Bytes::new(),
+            stream: None,
+            error: None,
+            views: None,
+            route: None,
         }
     }
 
@@ -57,6 +96,12 @@ impl Response {
         self
     }
 
+    /// Remove a response header
+    pub fn remove_header(mut self, name: &str) -> Self {
+        self.headers.remove(name);
+        self
+    }
+
     /// Set the Content-Type header
     pub fn content_type(self, content_type: &str) -> Self {
         self.header("content-type", content_type)
@@ -74,6 +119,7 @@ impl Response {
     pub fn send(mut self, body: impl Into<String>) -> Self {
         let body_string = body.into();
         self.body = Bytes::from(body_string);
+        self.stream = None;
         if !self.headers.contains_key(header::CONTENT_TYPE) {
             self = self.content_type("text/plain; charset=utf-8");
         }
@@ -93,6 +139,7 @@ impl Response {
     /// ```
     pub fn send_bytes(mut self, body: Vec<u8>) -> Self {
         self.body = Bytes::from(body);
+        self.stream = None;
         self
     }
 
@@ -110,11 +157,13 @@ impl Response {
         match serde_json::to_vec(&data) {
             Ok(json_bytes) => {
                 self.body = Bytes::from(json_bytes);
+                self.stream = None;
                 self = self.content_type("application/json; charset=utf-8");
             }
             Err(e) => {
                 self.status = StatusCode::INTERNAL_SERVER_ERROR;
                 self.body = Bytes::from(format!(r#"{{"error":"Serialization error: {}"}}"#, e));
+                self.stream = None;
                 self = self.content_type("application/json; charset=utf-8");
             }
         }
@@ -132,9 +181,37 @@ impl Response {
     /// ```
     pub fn html(mut self, html: impl Into<String>) -> Self {
         self.body = Bytes::from(html.into());
+        self.stream = None;
         self.content_type("text/html; charset=utf-8")
     }
 
+    /// Render a template with the app's [`Views`] as an HTML response
+    ///
+    /// `context` must serialize to a JSON object. Rendering failures become
+    /// error responses.
+    ///
+    /// ```rust,ignore
+    /// res.render("users/show.html", json!({ "user": user }))
+    /// ```
+    pub fn render<T: Serialize>(self, template: &str, context: T) -> Self {
+        let rendered = match (&self.views, serde_json::to_value(context)) {
+            (Some((views, reload)), Ok(context)) => views.render(template, &context, *reload),
+            (None, _) => Err(Error::Internal(
+                "no view engine configured, see RustyX::views".to_string(),
+            )),
+            (_, Err(e)) => Err(e.into()),
+        };
+        match rendered {
+            Ok(html) => self.html(html),
+            Err(error) => self.status(error.status_code()).with_error(error),
+        }
+    }
+
+    pub(crate) fn with_views(mut self, views: Option<(Arc<Views>, bool)>) -> Self {
+        self.views = views;
+        self
+    }
+
     /// Send a redirect response
     ///
     /// # Example
@@ -192,6 +269,7 @@ impl Response {
     pub fn no_content(mut self) -> Self {
         self.status = StatusCode::NO_CONTENT;
         self.body = Bytes::new();
+        self.stream = None;
         self
     }
 
@@ -223,7 +301,7 @@ impl Response {
     /// # Example
     ///
     /// ```rust
-    /// use rustyx::Response;
+    /// use rustyx::response::{CookieOptions, Response};
     ///
     /// let res = Response::new()
     ///     .cookie("session", "abc123", CookieOptions::default());
@@ -265,15 +343,99 @@ impl Response {
         )
     }
 
+    /// Stream the body to the client as `stream` yields chunks
+    ///
+    /// Nothing is buffered, so middleware that rewrites bodies (ETags,
+    /// response caching, live reload) leaves streamed responses alone.
+    ///
+    /// ```rust,ignore
+    /// let file = tokio::fs::File::open("video.mp4").await?;
+    /// Ok(res
+    ///     .content_type("video/mp4")
+    ///     .stream(tokio_util::io::ReaderStream::new(file)))
+    /// ```
+    pub fn stream<S, E>(mut self, stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        self.body = Bytes::new();
+        self.stream = Some(Box::pin(stream.map_err(std::io::Error::other)));
+        self
+    }
+
+    /// Forward an upstream response: its status, the [`PIPED_HEADERS`]
+    /// not already set on this response, and its body as a stream
+    ///
+    /// Accepts a `reqwest::Response` (with the `reqwest` feature), e.g.
+    /// from [`S3Storage::get_object`](crate::upload::s3::S3Storage::get_object),
+    /// or an [`Upstream`] built from any byte stream.
+    ///
+    /// ```rust,ignore
+    /// app.get("/media/:key", move |req, res| {
+    ///     let s3 = s3.clone();
+    ///     async move {
+    ///         let object = s3
+    ///             .get_object(req.param("key").unwrap(), req.header("range"))
+    ///             .await
+    ///             .map_err(|e| Error::Internal(e.to_string()))?
+    ///             .ok_or_else(|| Error::not_found("No such file"))?;
+    ///         Ok::<_, Error>(res.header("cache-control", "private").pipe(object))
+    ///     }
+    /// });
+    /// ```
+    pub fn pipe(self, source: impl PipeSource) -> Self {
+        self.pipe_with_headers(source, PIPED_HEADERS)
+    }
+
+    /// Like [`pipe`](Self::pipe), copying only the `headers` listed
+    pub fn pipe_with_headers(mut self, source: impl PipeSource, headers: &[&str]) -> Self {
+        let upstream = source.into_upstream();
+        self.status = upstream.status;
+        for name in headers {
+            if self.headers.contains_key(*name) {
+                continue;
+            }
+            for value in upstream.headers.get_all(*name) {
+                if let Ok(name) = header::HeaderName::from_bytes(name.as_bytes()) {
+                    self.headers.append(name, value.clone());
+                }
+            }
+        }
+        self.body = Bytes::new();
+        self.stream = Some(upstream.body);
+        self
+    }
+
+    /// Whether the body is streamed rather than held in memory
+    pub fn is_streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Read a streamed body into memory, e.g. to inspect it in tests
+    pub(crate) async fn buffer(mut self) -> std::io::Result<Self> {
+        if let Some(stream) = self.stream.take() {
+            let chunks: Vec<Bytes> = stream.try_collect().await?;
+            self.body = Bytes::from(chunks.concat());
+        }
+        Ok(self)
+    }
+
     /// Convert to hyper Response
-    pub fn into_hyper(self) -> hyper::Response<Full<Bytes>> {
+    pub fn into_hyper(self) -> hyper::Response<ResponseBody> {
         let mut response = hyper::Response::builder().status(self.status);
 
         for (name, value) in self.headers.iter() {
             response = response.header(name, value);
         }
 
-        response.body(Full::new(self.body)).unwrap()
+        let body = match self.stream {
+            Some(stream) => BodyExt::boxed_unsync(StreamBody::new(stream.map_ok(Frame::data))),
+            None => Full::new(self.body)
+                .map_err(|never| match never {})
+                .boxed_unsync(),
+        };
+        response.body(body).unwrap()
     }
 
     /// Get the current status code
@@ -285,6 +447,101 @@ impl Response {
     pub fn get_headers(&self) -> &HeaderMap {
         &self.headers
     }
+
+    /// Get the current body
+    pub fn get_body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// The body as UTF-8 text, replacing invalid sequences
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Deserialize the JSON body, e.g. to check a handler's output in tests
+    pub fn body_json<T: serde::de::DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    /// The error this response was rendered from, if any
+    ///
+    /// Set when a handler returns `Err`, so middleware running after
+    /// `next` can log or re-render failures.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_deref()
+    }
+
+    pub(crate) fn with_error(mut self, error: Error) -> Self {
+        self.error = Some(Arc::new(error));
+        self
+    }
+
+    /// The route that produced this response, `None` when nothing matched
+    ///
+    /// Lets middleware running after `next` see the route template and
+    /// path parameters.
+    pub fn route(&self) -> Option<&MatchedRoute> {
+        self.route.as_ref()
+    }
+
+    pub(crate) fn with_route(mut self, route: MatchedRoute) -> Self {
+        self.route = Some(route);
+        self
+    }
+
+    /// Re-render an error response in `format`, keeping its other headers
+    ///
+    /// HTML errors use the views' error template when one is set.
+    pub(crate) fn render_error(self, format: &ErrorFormat, development: bool) -> Self {
+        let Some(error) = self.error.clone() else {
+            return self;
+        };
+        let page = match (format, &self.views) {
+            (ErrorFormat::Html, Some((views, reload))) => {
+                views.error_page(&error, development, *reload)
+            }
+            _ => None,
+        };
+        let mut rendered = page.unwrap_or_else(|| format.render(&error, development));
+        for (name, value) in &self.headers {
+            if name != header::CONTENT_TYPE && !rendered.headers.contains_key(name) {
+                rendered.headers.insert(name.clone(), value.clone());
+            }
+        }
+        rendered.error = Some(error);
+        rendered
+    }
+}
+
+/// Values route handlers can return
+///
+/// Handlers may return a [`Response`] or a `Result<Response>`, so `?`
+/// works inside them; errors are converted with `From<Error> for Response`.
+///
+/// ```rust,ignore
+/// async fn show_user(req: Request, res: Response) -> Result<Response> {
+///     let id: i64 = req.param("id").unwrap_or(&String::new()).parse()
+///         .map_err(|_| Error::bad_request("invalid id"))?;
+///     let user: User = SqlExecutor::global()?.fetch_one("SELECT * FROM users WHERE id = ?", params![id]).await?;
+///     Ok(res.json(user))
+/// }
+///
+/// app.get("/users/:id", show_user);
+/// ```
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl IntoResponse for Result<Response, Error> {
+    fn into_response(self) -> Response {
+        self.unwrap_or_else(Response::from)
+    }
 }
 
 impl Default for Response {
@@ -293,6 +550,70 @@ impl Default for Response {
     }
 }
 
+/// An upstream response for [`Response::pipe`]
+pub struct Upstream {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: BodyStream,
+}
+
+impl Upstream {
+    /// A `200 OK` upstream streaming `body`
+    pub fn new<S, E>(body: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+    {
+        Self {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Box::pin(body.map_err(std::io::Error::other)),
+        }
+    }
+
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+}
+
+/// Things [`Response::pipe`] can forward
+pub trait PipeSource {
+    fn into_upstream(self) -> Upstream;
+}
+
+impl PipeSource for Upstream {
+    fn into_upstream(self) -> Upstream {
+        self
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl PipeSource for reqwest::Response {
+    fn into_upstream(self) -> Upstream {
+        // reqwest 0.11 uses http 0.2, so carry the status and headers over
+        let status =
+            StatusCode::from_u16(self.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers() {
+            if let (Ok(name), Ok(value)) = (
+                header::HeaderName::from_bytes(name.as_str().as_bytes()),
+                header::HeaderValue::from_bytes(value.as_bytes()),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        Upstream::new(self.bytes_stream())
+            .status(status)
+            .headers(headers)
+    }
+}
+
 /// Cookie options for setting cookies
 #[derive(Debug, Clone, Default)]
 pub struct CookieOptions {
@@ -353,3 +674,73 @@ impl CookieOptions {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_pipe_streams_upstream() {
+        let app = RustyX::new();
+        app.get("/media", |_req, res| async move {
+            let mut headers = HeaderMap::new();
+            headers.insert("content-type", "video/mp4".parse().unwrap());
+            headers.insert("content-range", "bytes 0-5/12".parse().unwrap());
+            headers.insert("cache-control", "public".parse().unwrap());
+            headers.insert("x-amz-request-id", "abc".parse().unwrap());
+            let chunks = futures::stream::iter(
+                ["abc", "def"].map(|c| Ok::<_, std::io::Error>(Bytes::from_static(c.as_bytes()))),
+            );
+            let upstream = Upstream::new(chunks)
+                .status(StatusCode::PARTIAL_CONTENT)
+                .headers(headers);
+            res.header("cache-control", "private").pipe(upstream)
+        });
+
+        let res = app.test().get("/media").send().await;
+        res.assert_status(206)
+            .assert_header("content-type", "video/mp4")
+            .assert_header("content-range", "bytes 0-5/12")
+            .assert_header("cache-control", "private");
+        assert_eq!(res.text(), "abcdef");
+        assert!(res.header("x-amz-request-id").is_none());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/media", listener.local_addr().unwrap());
+        tokio::spawn(app.serve(listener, None));
+        let res = reqwest::get(&url).await.unwrap();
+        assert_eq!(res.status().as_u16(), 206);
+        assert_eq!(res.headers()["transfer-encoding"], "chunked");
+        assert_eq!(res.text().await.unwrap(), "abcdef");
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn test_pipe_reqwest_response() {
+        let origin = RustyX::new();
+        origin.get("/file", |_req, res| async move {
+            res.header("etag", "\"v1\"")
+                .header("x-origin", "1")
+                .send("file contents")
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/file", listener.local_addr().unwrap());
+        tokio::spawn(origin.serve(listener, None));
+
+        let app = RustyX::new();
+        app.get("/proxy", move |_req, res| {
+            let url = url.clone();
+            async move {
+                let upstream = reqwest::get(&url)
+                    .await
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+                Ok::<_, Error>(res.pipe(upstream))
+            }
+        });
+        let res = app.test().get("/proxy").send().await;
+        res.assert_status(200).assert_header("etag", "\"v1\"");
+        assert!(res.header("x-origin").is_none());
+        assert_eq!(res.text(), "file contents");
+    }
+}
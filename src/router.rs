@@ -2,9 +2,13 @@
 //!
 //! Provides routing functionality similar to Express Router.
 
-use crate::app::HandlerFn;
+use crate::app::{handler_fn, HandlerFn};
+use crate::controllers::{Controller, ResourceController};
+use crate::error::ErrorFormat;
+use crate::middleware::{from_middleware, Middleware, MiddlewareStack, Next};
+use crate::openapi::Operation;
 use crate::request::Request;
-use crate::response::Response;
+use crate::response::{IntoResponse, Response};
 
 use hyper::Method;
 use matchit::Router as MatchitRouter;
@@ -15,42 +19,129 @@ use std::sync::Arc;
 /// Route handler with its matched parameters
 pub struct RouteHandler {
     handler: HandlerFn,
+    pattern: Arc<str>,
+}
+
+/// The route a request matched
+#[derive(Debug, Clone)]
+pub struct MatchedRoute {
+    /// Route template as registered, e.g. `/users/:id`
+    pub pattern: Arc<str>,
+    /// Path parameters extracted from the request path
+    pub params: HashMap<String, String>,
+}
+
+/// Registered route, kept so routers can be re-mounted under other prefixes
+#[derive(Clone)]
+struct RouteRecord {
+    method: Method,
+    path: String,
+    handler: HandlerFn,
+    operation: Option<Operation>,
 }
 
 /// Express-like Router for grouping routes
 pub struct Router {
     routes: HashMap<Method, MatchitRouter<RouteHandler>>,
+    records: Vec<RouteRecord>,
     prefix: String,
+    middleware: MiddlewareStack,
+    groups: Vec<String>,
+    /// Error formats by path prefix, including this router's prefix
+    error_formats: Vec<(String, ErrorFormat)>,
+    /// Request body limits by path prefix, including this router's prefix
+    body_limits: Vec<(String, usize)>,
 }
 
 impl Router {
     /// Create a new Router instance
     pub fn new() -> Self {
-        Self {
-            routes: HashMap::new(),
-            prefix: String::new(),
-        }
+        Self::with_prefix("")
     }
 
     /// Create a new Router with a path prefix
     pub fn with_prefix(prefix: &str) -> Self {
         Self {
             routes: HashMap::new(),
+            records: Vec::new(),
             prefix: prefix.to_string(),
+            middleware: MiddlewareStack::new(),
+            groups: Vec::new(),
+            error_formats: Vec::new(),
+            body_limits: Vec::new(),
         }
     }
 
     /// Add a route to the router
     pub fn add_route(&mut self, method: Method, path: &str, handler: HandlerFn) {
         let full_path = format!("{}{}", self.prefix, path);
-        let router = self.routes.entry(method).or_insert_with(MatchitRouter::new);
+        self.insert(method, full_path, handler, None);
+    }
+
+    /// Add a route documented by `operation`
+    pub(crate) fn add_documented_route(
+        &mut self,
+        method: Method,
+        path: &str,
+        handler: HandlerFn,
+        operation: Option<Operation>,
+    ) {
+        let full_path = format!("{}{}", self.prefix, path);
+        self.insert(method, full_path, handler, operation);
+    }
+
+    /// Insert a route at its final path
+    fn insert(
+        &mut self,
+        method: Method,
+        full_path: String,
+        handler: HandlerFn,
+        operation: Option<Operation>,
+    ) {
+        let router = self.routes.entry(method.clone()).or_default();
 
         // Convert Express-style params (:id) to matchit style ({id})
         let converted_path = convert_express_params(&full_path);
 
-        if let Err(e) = router.insert(&converted_path, RouteHandler { handler }) {
+        if let Err(e) = router.insert(
+            &converted_path,
+            RouteHandler {
+                handler: Arc::clone(&handler),
+                pattern: Arc::from(full_path.as_str()),
+            },
+        ) {
             tracing::warn!("Failed to insert route {}: {:?}", converted_path, e);
+            return;
         }
+
+        self.records.push(RouteRecord {
+            method,
+            path: full_path,
+            handler,
+            operation,
+        });
+    }
+
+    /// Document the most recently added route for
+    /// [`OpenApi`](crate::openapi::OpenApi)
+    ///
+    /// ```rust,ignore
+    /// router
+    ///     .get("/users/:id", show)
+    ///     .doc(Operation::new("Get a user").response::<User>(200, "The user"));
+    /// ```
+    pub fn doc(&mut self, operation: Operation) -> &mut Self {
+        if let Some(record) = self.records.last_mut() {
+            record.operation = Some(operation);
+        }
+        self
+    }
+
+    /// Registered routes with their full paths and documentation
+    pub(crate) fn operations(&self) -> impl Iterator<Item = (&Method, &str, Option<&Operation>)> {
+        self.records
+            .iter()
+            .map(|r| (&r.method, r.path.as_str(), r.operation.as_ref()))
     }
 
     /// Find a route handler for the given method and path
@@ -59,93 +150,208 @@ impl Router {
         method: &Method,
         path: &str,
     ) -> Option<(&HandlerFn, HashMap<String, String>)> {
-        if let Some(router) = self.routes.get(method) {
-            if let Ok(matched) = router.at(path) {
-                let params: HashMap<String, String> = matched
-                    .params
-                    .iter()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect();
-                return Some((&matched.value.handler, params));
-            }
-        }
-        None
+        self.match_route(method, path)
+            .map(|(handler, route)| (handler, route.params))
+    }
+
+    /// Find a route handler and the route it matched
+    pub(crate) fn match_route(
+        &self,
+        method: &Method,
+        path: &str,
+    ) -> Option<(&HandlerFn, MatchedRoute)> {
+        let matched = self.routes.get(method)?.at(path).ok()?;
+        let params = matched
+            .params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let route = MatchedRoute {
+            pattern: Arc::clone(&matched.value.pattern),
+            params,
+        };
+        Some((&matched.value.handler, route))
     }
 
     /// Mount another router at a path prefix
-    pub fn mount(&mut self, _prefix: &str, mut other: Router) {
-        for (method, other_router) in other.routes.drain() {
-            self.routes.entry(method).or_insert(other_router);
+    ///
+    /// The mounted router's middleware wraps each of its routes.
+    pub fn mount(&mut self, prefix: &str, other: Router) {
+        let Router {
+            records,
+            middleware,
+            error_formats,
+            body_limits,
+            ..
+        } = other;
+
+        for record in records {
+            let path = join_paths(prefix, &record.path);
+            self.add_documented_route(
+                record.method,
+                &path,
+                middleware.compose(record.handler),
+                record.operation,
+            );
+        }
+        for (path, format) in error_formats {
+            let path = format!("{}{}", self.prefix, join_paths(prefix, &path));
+            self.error_formats.push((path, format));
         }
+        for (path, limit) in body_limits {
+            let path = format!("{}{}", self.prefix, join_paths(prefix, &path));
+            self.body_limits.push((path, limit));
+        }
+    }
+
+    /// Render errors from this router's routes in `format`
+    ///
+    /// Applies to every path under the router's prefix once mounted, including
+    /// unmatched ones, and takes precedence over
+    /// [`RustyX::error_format`](crate::RustyX::error_format).
+    ///
+    /// ```rust,ignore
+    /// let mut api = Router::new();
+    /// api.error_format(ErrorFormat::ProblemJson);
+    /// app.use_router("/api", api);
+    /// app.error_format(ErrorFormat::Html);
+    /// ```
+    pub fn error_format(&mut self, format: ErrorFormat) -> &mut Self {
+        self.error_formats.push((self.prefix.clone(), format));
+        self
+    }
+
+    /// The error format of the most specific prefix containing `path`
+    pub(crate) fn error_format_for(&self, path: &str) -> Option<ErrorFormat> {
+        scoped(&self.error_formats, path).copied()
+    }
+
+    /// Refuse request bodies over `bytes` on this router's paths with `413`
+    ///
+    /// The limit is enforced while the body is read, before any middleware
+    /// or handler runs, and takes precedence over the app's `body_limit`
+    /// setting, e.g. to allow large uploads on one router only.
+    ///
+    /// ```rust,ignore
+    /// let mut uploads = Router::new();
+    /// uploads.body_limit(100 * 1024 * 1024);
+    /// app.use_router("/uploads", uploads);
+    /// app.set("body_limit", 1024 * 1024);
+    /// ```
+    pub fn body_limit(&mut self, bytes: usize) -> &mut Self {
+        self.body_limits.push((self.prefix.clone(), bytes));
+        self
+    }
+
+    /// The body limit of the most specific prefix containing `path`
+    pub(crate) fn body_limit_for(&self, path: &str) -> Option<usize> {
+        scoped(&self.body_limits, path).copied()
+    }
+
+    /// Add middleware that runs only for this router's routes
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut admin = Router::new();
+    /// admin.use_middleware(helmet());
+    /// admin.get("/stats", stats_handler);
+    /// app.use_router("/admin", admin);
+    /// ```
+    pub fn use_middleware<F, Fut>(&mut self, middleware: F) -> &mut Self
+    where
+        F: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.middleware.push(Box::new(move |req, res, next| {
+            Box::pin(middleware(req, res, next))
+        }));
+        self
+    }
+
+    /// Add struct-based middleware that runs only for this router's routes
+    pub fn use_middleware_obj(&mut self, middleware: Arc<dyn Middleware>) -> &mut Self {
+        self.use_middleware(from_middleware(middleware))
+    }
+
+    /// Apply a named middleware group (see [`RustyX::middleware_group`](crate::RustyX::middleware_group))
+    /// to this router's routes when it is mounted
+    pub fn use_group(&mut self, name: &str) -> &mut Self {
+        self.groups.push(name.to_string());
+        self
+    }
+
+    /// Take the names of the middleware groups applied to this router
+    pub(crate) fn take_groups(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.groups)
+    }
+
+    /// Run `stack` before this router's own middleware
+    pub(crate) fn prepend_middleware(&mut self, mut stack: MiddlewareStack) {
+        stack.extend(&self.middleware);
+        self.middleware = stack;
     }
 
     /// Register a GET route
-    pub fn get<F, Fut>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn get<F, Fut, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
-        self.add_route(
-            Method::GET,
-            path,
-            Arc::new(move |req, res| Box::pin(handler(req, res))),
-        );
+        self.add_route(Method::GET, path, handler_fn(handler));
         self
     }
 
     /// Register a POST route
-    pub fn post<F, Fut>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn post<F, Fut, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
-        self.add_route(
-            Method::POST,
-            path,
-            Arc::new(move |req, res| Box::pin(handler(req, res))),
-        );
+        self.add_route(Method::POST, path, handler_fn(handler));
         self
     }
 
     /// Register a PUT route
-    pub fn put<F, Fut>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn put<F, Fut, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
-        self.add_route(
-            Method::PUT,
-            path,
-            Arc::new(move |req, res| Box::pin(handler(req, res))),
-        );
+        self.add_route(Method::PUT, path, handler_fn(handler));
         self
     }
 
     /// Register a DELETE route
-    pub fn delete<F, Fut>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn delete<F, Fut, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
-        self.add_route(
-            Method::DELETE,
-            path,
-            Arc::new(move |req, res| Box::pin(handler(req, res))),
-        );
+        self.add_route(Method::DELETE, path, handler_fn(handler));
         self
     }
 
     /// Register a PATCH route
-    pub fn patch<F, Fut>(&mut self, path: &str, handler: F) -> &mut Self
+    pub fn patch<F, Fut, R>(&mut self, path: &str, handler: F) -> &mut Self
     where
         F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
-        Fut: Future<Output = Response> + Send + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
     {
-        self.add_route(
-            Method::PATCH,
-            path,
-            Arc::new(move |req, res| Box::pin(handler(req, res))),
-        );
+        self.add_route(Method::PATCH, path, handler_fn(handler));
+        self
+    }
+
+    /// Register the CRUD routes of a controller
+    ///
+    /// See [`RustyX::resource`](crate::app::RustyX::resource).
+    pub fn resource<C: Controller + 'static>(&mut self, path: &str, controller: C) -> &mut Self {
+        ResourceController::new(path, controller).register(self);
         self
     }
 
@@ -158,8 +364,22 @@ impl Router {
         configure(&mut group_router);
 
         // Merge group routes into main router
-        for (method, group_routes) in group_router.routes {
-            self.routes.entry(method).or_insert(group_routes);
+        let Router {
+            records,
+            middleware,
+            error_formats,
+            body_limits,
+            ..
+        } = group_router;
+        self.error_formats.extend(error_formats);
+        self.body_limits.extend(body_limits);
+        for record in records {
+            self.insert(
+                record.method,
+                record.path,
+                middleware.compose(record.handler),
+                record.operation,
+            );
         }
 
         self
@@ -172,8 +392,39 @@ impl Default for Router {
     }
 }
 
+/// The value of the most specific prefix containing `path`
+fn scoped<'a, T>(entries: &'a [(String, T)], path: &str) -> Option<&'a T> {
+    entries
+        .iter()
+        .filter(|(prefix, _)| {
+            let prefix = prefix.trim_end_matches('/');
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .max_by_key(|(prefix, _)| prefix.trim_end_matches('/').len())
+        .map(|(_, value)| value)
+}
+
+/// Join a mount prefix and a route path
+fn join_paths(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        path.to_string()
+    } else if path.is_empty() || path == "/" {
+        prefix.to_string()
+    } else {
+        format!("{}{}", prefix, path)
+    }
+}
+
 /// Convert Express-style route parameters to matchit format
+///
+/// A trailing `/*` becomes a catch-all parameter named `wildcard`.
 fn convert_express_params(path: &str) -> String {
+    if let Some(base) = path.strip_suffix("/*") {
+        return format!("{}/{{*wildcard}}", convert_express_params(base));
+    }
+
     let mut result = String::with_capacity(path.len());
     let mut chars = path.chars().peekable();
 
@@ -208,5 +459,101 @@ mod tests {
             "/users/{id}/posts/{postId}"
         );
         assert_eq!(convert_express_params("/static"), "/static");
+        assert_eq!(
+            convert_express_params("/files/:id/*"),
+            "/files/{id}/{*wildcard}"
+        );
+    }
+
+    #[test]
+    fn test_mount_keeps_routes_for_existing_methods() {
+        let handler: HandlerFn = Arc::new(|_req, res| Box::pin(async move { res }));
+
+        let mut users = Router::new();
+        users.add_route(Method::GET, "/", Arc::clone(&handler));
+        users.add_route(Method::GET, "/:id", Arc::clone(&handler));
+
+        let mut app = Router::new();
+        app.add_route(Method::GET, "/", Arc::clone(&handler));
+        app.mount("/api/users", users);
+
+        assert!(app.find_route(&Method::GET, "/").is_some());
+        assert!(app.find_route(&Method::GET, "/api/users").is_some());
+        let (_, params) = app.find_route(&Method::GET, "/api/users/7").unwrap();
+        assert_eq!(params.get("id").map(String::as_str), Some("7"));
+    }
+
+    #[test]
+    fn test_route_syntax() {
+        let handler: HandlerFn = Arc::new(|_req, res| Box::pin(async move { res }));
+        let mut app = Router::new();
+        app.add_route(Method::GET, "/users/:id", Arc::clone(&handler));
+        app.add_route(Method::GET, "/orgs/{org}", Arc::clone(&handler));
+        app.add_route(Method::GET, "/assets/*", Arc::clone(&handler));
+        app.add_route(Method::GET, "/{{literal}}", Arc::clone(&handler));
+
+        let param = |path, name| {
+            app.find_route(&Method::GET, path)
+                .and_then(|(_, params)| params.get(name).cloned())
+        };
+        assert_eq!(param("/users/7", "id").as_deref(), Some("7"));
+        assert_eq!(param("/orgs/acme", "org").as_deref(), Some("acme"));
+        assert_eq!(
+            param("/assets/css/a.css", "wildcard").as_deref(),
+            Some("css/a.css")
+        );
+        assert!(app.find_route(&Method::GET, "/{literal}").is_some());
+        assert!(app.find_route(&Method::GET, "/literal").is_none());
+    }
+
+    #[test]
+    fn test_scoped_error_formats() {
+        let mut api = Router::new();
+        api.error_format(ErrorFormat::ProblemJson);
+        let mut app = Router::new();
+        app.error_format(ErrorFormat::Html);
+        app.mount("/api", api);
+
+        let format = |path| app.error_format_for(path);
+        assert!(matches!(format("/api"), Some(ErrorFormat::ProblemJson)));
+        assert!(matches!(
+            format("/api/users/7"),
+            Some(ErrorFormat::ProblemJson)
+        ));
+        assert!(matches!(format("/apis"), Some(ErrorFormat::Html)));
+        assert!(matches!(format("/"), Some(ErrorFormat::Html)));
+    }
+
+    #[test]
+    fn test_scoped_body_limits() {
+        let mut uploads = Router::new();
+        uploads.body_limit(100);
+        let mut api = Router::new();
+        api.group("/avatars", |group| {
+            group.body_limit(10);
+        });
+        api.mount("/uploads", uploads);
+        let mut app = Router::new();
+        app.mount("/api", api);
+
+        assert_eq!(app.body_limit_for("/api/uploads/7"), Some(100));
+        assert_eq!(app.body_limit_for("/api/avatars"), Some(10));
+        assert_eq!(app.body_limit_for("/api/users"), None);
+    }
+
+    #[test]
+    fn test_result_handlers() {
+        async fn find(_req: Request, res: Response) -> crate::Result<Response> {
+            Err(crate::Error::not_found("user 7"))?;
+            Ok(res)
+        }
+
+        let mut router = Router::new();
+        router.get("/users/:id", find);
+        assert!(router.find_route(&Method::GET, "/users/7").is_some());
+
+        let res = Err::<Response, _>(crate::Error::not_found("user 7")).into_response();
+        assert_eq!(res.get_status(), 404);
+        assert!(matches!(res.error(), Some(crate::Error::NotFound(_))));
     }
 }
@@ -2,18 +2,63 @@
 //!
 //! Provides utilities for defining and organizing routes.
 
+use crate::app::{handler_fn, HandlerFn};
+use crate::controllers::{action_handler, Controller, ResourceAction};
+use crate::middleware::{MiddlewareStack, Next};
+use crate::openapi::Operation;
+use crate::request::Request;
+use crate::response::{IntoResponse, Response};
 use crate::router::Router;
+use chrono::{DateTime, Utc};
+use hyper::Method;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
 
 /// Trait for route definitions
+///
+/// Keep each route module in its own file and attach it with
+/// [`RustyX::register`](crate::app::RustyX::register).
+///
+/// ```rust,ignore
+/// pub struct UserRoutes;
+///
+/// impl RouteDefinition for UserRoutes {
+///     fn register(router: &mut Router) {
+///         RouteGroup::new("/users")
+///             .get("", list_users)
+///             .get("/:id", show_user)
+///             .post("", create_user)
+///             .register(router);
+///     }
+/// }
+///
+/// app.register::<UserRoutes>();
+/// ```
 pub trait RouteDefinition {
     /// Register routes on the router
     fn register(router: &mut Router);
 }
 
 /// Route group builder
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let users = RouteGroup::new("/users")
+///     .use_middleware(logger())
+///     .get("", list_users)
+///     .name("users.index")
+///     .post("", create_user)
+///     .name("users.create")
+///     .middleware(require_auth());
+///
+/// app.mount_group(users);
+/// ```
 pub struct RouteGroup {
     prefix: String,
     routes: Vec<RouteEntry>,
+    middleware: MiddlewareStack,
 }
 
 #[derive(Clone)]
@@ -21,6 +66,8 @@ pub struct RouteEntry {
     pub method: String,
     pub path: String,
     pub name: Option<String>,
+    pub handler: HandlerFn,
+    pub operation: Option<Operation>,
 }
 
 impl RouteGroup {
@@ -29,45 +76,127 @@ impl RouteGroup {
         Self {
             prefix: prefix.to_string(),
             routes: Vec::new(),
+            middleware: MiddlewareStack::new(),
         }
     }
 
     /// Add a GET route
-    pub fn get(mut self, path: &str) -> Self {
-        self.routes.push(RouteEntry {
-            method: "GET".to_string(),
-            path: format!("{}{}", self.prefix, path),
-            name: None,
-        });
-        self
+    pub fn get<F, Fut, R>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::GET, path, handler)
     }
 
     /// Add a POST route
-    pub fn post(mut self, path: &str) -> Self {
-        self.routes.push(RouteEntry {
-            method: "POST".to_string(),
-            path: format!("{}{}", self.prefix, path),
-            name: None,
-        });
-        self
+    pub fn post<F, Fut, R>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::POST, path, handler)
     }
 
     /// Add a PUT route
-    pub fn put(mut self, path: &str) -> Self {
-        self.routes.push(RouteEntry {
-            method: "PUT".to_string(),
-            path: format!("{}{}", self.prefix, path),
-            name: None,
-        });
-        self
+    pub fn put<F, Fut, R>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::PUT, path, handler)
     }
 
     /// Add a DELETE route
-    pub fn delete(mut self, path: &str) -> Self {
+    pub fn delete<F, Fut, R>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::DELETE, path, handler)
+    }
+
+    /// Add a PATCH route
+    pub fn patch<F, Fut, R>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::PATCH, path, handler)
+    }
+
+    /// Name the most recently added route
+    pub fn name(mut self, name: &str) -> Self {
+        if let Some(entry) = self.routes.last_mut() {
+            entry.name = Some(name.to_string());
+        }
+        self
+    }
+
+    /// Document the most recently added route
+    pub fn doc(mut self, operation: Operation) -> Self {
+        if let Some(entry) = self.routes.last_mut() {
+            entry.operation = Some(operation);
+        }
+        self
+    }
+
+    /// Wrap the most recently added route in middleware
+    pub fn middleware<F, Fut>(mut self, middleware: F) -> Self
+    where
+        F: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        if let Some(entry) = self.routes.last_mut() {
+            let mut stack = MiddlewareStack::new();
+            stack.push(Box::new(move |req, res, next| {
+                Box::pin(middleware(req, res, next))
+            }));
+            entry.handler = stack.compose(Arc::clone(&entry.handler));
+        }
+        self
+    }
+
+    /// Add middleware that runs for every route in the group
+    pub fn use_middleware<F, Fut>(mut self, middleware: F) -> Self
+    where
+        F: Fn(Request, Response, Next) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        self.middleware.push(Box::new(move |req, res, next| {
+            Box::pin(middleware(req, res, next))
+        }));
+        self
+    }
+
+    /// Find a route by name
+    pub fn named(&self, name: &str) -> Option<&RouteEntry> {
+        self.routes
+            .iter()
+            .find(|entry| entry.name.as_deref() == Some(name))
+    }
+
+    fn route<F, Fut, R>(self, method: Method, path: &str, handler: F) -> Self
+    where
+        F: Fn(Request, Response) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+        R: IntoResponse,
+    {
+        self.add_handler(method, path, handler_fn(handler))
+    }
+
+    fn add_handler(mut self, method: Method, path: &str, handler: HandlerFn) -> Self {
         self.routes.push(RouteEntry {
-            method: "DELETE".to_string(),
+            method: method.to_string(),
             path: format!("{}{}", self.prefix, path),
             name: None,
+            handler,
+            operation: None,
         });
         self
     }
@@ -81,12 +210,60 @@ impl RouteGroup {
     pub fn prefix(&self) -> &str {
         &self.prefix
     }
+
+    /// Add the group's routes to a router
+    ///
+    /// Group middleware runs before each route's own middleware.
+    pub fn register(self, router: &mut Router) {
+        for entry in self.routes {
+            let handler = self.middleware.compose(entry.handler);
+            match entry.method.parse::<Method>() {
+                Ok(method) => {
+                    router.add_documented_route(method, &entry.path, handler, entry.operation)
+                }
+                Err(_) => tracing::warn!("Invalid route method {}", entry.method),
+            }
+        }
+    }
 }
 
 /// API versioning helper
+///
+/// Deprecated versions mounted with
+/// [`RustyX::use_version`](crate::app::RustyX::use_version) answer with
+/// `Deprecation`, `Sunset` and `Link: <..>; rel="successor-version"`
+/// headers and log a warning on every hit.
+///
+/// ```rust,ignore
+/// let v2 = ApiVersion::new(2);
+/// let v1 = ApiVersion::new(1)
+///     .deprecated_since(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+///     .sunset(Utc.with_ymd_and_hms(2025, 12, 31, 0, 0, 0).unwrap())
+///     .successor(&v2)
+///     .gone_after_sunset(true);
+///
+/// app.use_version(&v1, v1_routes());
+/// app.use_version(&v2, v2_routes());
+/// ```
+#[derive(Debug, Clone)]
 pub struct ApiVersion {
     pub version: String,
     pub prefix: String,
+    pub deprecation: Option<Deprecation>,
+}
+
+/// Deprecation policy of an [`ApiVersion`]
+#[derive(Debug, Clone, Default)]
+pub struct Deprecation {
+    /// When the version was deprecated, sent as `Deprecation: @<unix time>`
+    /// (`Deprecation: true` when unset)
+    pub since: Option<DateTime<Utc>>,
+    /// When the version stops working, sent as the `Sunset` header
+    pub sunset: Option<DateTime<Utc>>,
+    /// Prefix of the version replacing this one
+    pub successor: Option<String>,
+    /// Answer `410 Gone` once the sunset date has passed
+    pub gone_after_sunset: bool,
 }
 
 impl ApiVersion {
@@ -94,20 +271,180 @@ impl ApiVersion {
         Self {
             version: format!("v{}", version),
             prefix: format!("/api/v{}", version),
+            deprecation: None,
         }
     }
 
     pub fn prefix(&self) -> &str {
         &self.prefix
     }
+
+    /// Mark the version deprecated
+    pub fn deprecated(mut self) -> Self {
+        self.deprecation.get_or_insert_with(Deprecation::default);
+        self
+    }
+
+    /// Mark the version deprecated as of `date`
+    pub fn deprecated_since(mut self, date: DateTime<Utc>) -> Self {
+        self.deprecation
+            .get_or_insert_with(Deprecation::default)
+            .since = Some(date);
+        self
+    }
+
+    /// Announce when the version will be removed; implies deprecation
+    pub fn sunset(mut self, date: DateTime<Utc>) -> Self {
+        self.deprecation
+            .get_or_insert_with(Deprecation::default)
+            .sunset = Some(date);
+        self
+    }
+
+    /// Point clients at the version replacing this one; implies deprecation
+    pub fn successor(mut self, successor: &ApiVersion) -> Self {
+        self.deprecation
+            .get_or_insert_with(Deprecation::default)
+            .successor = Some(successor.prefix.clone());
+        self
+    }
+
+    /// Reject requests with `410 Gone` after the sunset date
+    pub fn gone_after_sunset(mut self, gone: bool) -> Self {
+        self.deprecation
+            .get_or_insert_with(Deprecation::default)
+            .gone_after_sunset = gone;
+        self
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecation.is_some()
+    }
+
+    /// Middleware applying the deprecation policy, a no-op for current versions
+    pub fn middleware(
+        &self,
+    ) -> impl Fn(Request, Response, Next) -> Pin<Box<dyn Future<Output = Response> + Send>>
+           + Send
+           + Sync
+           + Clone {
+        let version = self.version.clone();
+        let policy = self.deprecation.clone().map(Arc::new);
+        move |req: Request, res: Response, next: Next| {
+            let version = version.clone();
+            let policy = policy.clone();
+            Box::pin(async move {
+                let Some(policy) = policy else {
+                    return next(req, res).await;
+                };
+                tracing::warn!(
+                    "Deprecated API version {} called: {} {}",
+                    version,
+                    req.method(),
+                    req.path()
+                );
+
+                let past_sunset = policy.sunset.is_some_and(|sunset| sunset <= Utc::now());
+                let response = if past_sunset && policy.gone_after_sunset {
+                    res.status(410).json(serde_json::json!({
+                        "error": format!("API {} has been retired", version)
+                    }))
+                } else {
+                    next(req, res).await
+                };
+                policy.apply_headers(response)
+            })
+        }
+    }
+}
+
+impl Deprecation {
+    fn apply_headers(&self, response: Response) -> Response {
+        let deprecation = match self.since {
+            Some(since) => format!("@{}", since.timestamp()),
+            None => "true".to_string(),
+        };
+        let mut response = response.header("deprecation", &deprecation);
+        if let Some(sunset) = self.sunset {
+            response = response.header("sunset", &httpdate::fmt_http_date(sunset.into()));
+        }
+        if let Some(successor) = &self.successor {
+            response = response.header(
+                "link",
+                &format!("<{}>; rel=\"successor-version\"", successor),
+            );
+        }
+        response
+    }
 }
 
-/// Resource route helper - creates standard CRUD routes
-pub fn resource_routes(name: &str) -> RouteGroup {
+/// Resource route helper - creates standard CRUD routes for a controller
+pub fn resource_routes<C: Controller + 'static>(name: &str, controller: C) -> RouteGroup {
+    let controller = Arc::new(controller);
+    let handler = |action| action_handler(&controller, action);
     RouteGroup::new(&format!("/{}", name))
-        .get("") // index
-        .get("/:id") // show
-        .post("") // create
-        .put("/:id") // update
-        .delete("/:id") // delete
+        .add_handler(Method::GET, "", handler(ResourceAction::Index))
+        .add_handler(Method::GET, "/:id", handler(ResourceAction::Show))
+        .add_handler(Method::POST, "", handler(ResourceAction::Create))
+        .add_handler(Method::PUT, "/:id", handler(ResourceAction::Update))
+        .add_handler(Method::DELETE, "/:id", handler(ResourceAction::Destroy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ApiRoutes;
+
+    impl RouteDefinition for ApiRoutes {
+        fn register(router: &mut Router) {
+            RouteGroup::new("/api")
+                .get("/status", |_req, res| async move { res.send("ok") })
+                .post("/echo", |req, res| async move {
+                    res.send(req.body_string().unwrap_or_default())
+                })
+                .register(router);
+        }
+    }
+
+    #[test]
+    fn test_route_definition_registers_group() {
+        let mut router = Router::new();
+        ApiRoutes::register(&mut router);
+
+        assert!(router.find_route(&Method::GET, "/api/status").is_some());
+        assert!(router.find_route(&Method::POST, "/api/echo").is_some());
+        assert!(router.find_route(&Method::GET, "/api/echo").is_none());
+    }
+
+    #[test]
+    fn test_deprecation_headers() {
+        let v2 = ApiVersion::new(2);
+        let v1 = ApiVersion::new(1)
+            .deprecated_since(DateTime::from_timestamp(1_700_000_000, 0).unwrap())
+            .sunset(DateTime::from_timestamp(1_800_000_000, 0).unwrap())
+            .successor(&v2);
+        assert!(v1.is_deprecated());
+        assert!(!v2.is_deprecated());
+
+        let res = v1.deprecation.unwrap().apply_headers(Response::new());
+        let headers = res.get_headers();
+        assert_eq!(headers["deprecation"], "@1700000000");
+        assert_eq!(headers["sunset"], "Fri, 15 Jan 2027 08:00:00 GMT");
+        assert_eq!(headers["link"], "</api/v2>; rel=\"successor-version\"");
+    }
+
+    #[test]
+    fn test_group_names_and_patch() {
+        let group = RouteGroup::new("/items")
+            .use_middleware(|req, res, next| async move { next(req, res).await })
+            .patch("/:id", |_req, res| async move { res.send("patched") })
+            .name("items.update")
+            .middleware(|req, res, next| async move { next(req, res).await });
+        assert_eq!(group.named("items.update").unwrap().path, "/items/:id");
+
+        let mut router = Router::new();
+        group.register(&mut router);
+        assert!(router.find_route(&Method::PATCH, "/items/3").is_some());
+    }
 }
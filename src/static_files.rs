@@ -2,34 +2,217 @@
 //!
 //! Middleware for serving static files.
 
+use crate::middleware::etag::{compute_etag, etag_matches, http_date, not_modified_since};
 use crate::request::Request;
 use crate::response::Response;
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
 use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 /// Static file server configuration
 #[derive(Debug, Clone)]
 pub struct StaticConfig {
     /// Root directory for static files
     pub root: PathBuf,
+    /// URL prefix the handler is mounted at, stripped before resolving files
+    pub prefix: String,
     /// Index file name
     pub index: String,
     /// Enable directory listing
     pub directory_listing: bool,
-    /// Cache control max-age in seconds
+    /// Cache control max-age in seconds (used when no cache rule matches)
     pub max_age: u32,
-    /// Enable gzip compression
+    /// Cache-Control rules, first match wins
+    pub cache_rules: Vec<CacheRule>,
+    /// Enable gzip: serve `file.gz` when present, otherwise compress text on the fly
     pub gzip: bool,
+    /// Serve pre-compressed `file.br` to clients that accept Brotli
+    pub brotli: bool,
+    /// How ETags are generated
+    pub etag: StaticEtag,
+    /// Send `Last-Modified` and honour `If-Modified-Since`
+    pub last_modified: bool,
+    /// How to treat dotfiles (`.env`, `.git/...`)
+    pub dotfiles: DotFiles,
+    /// Follow symlinks inside the root (links escaping the root are always refused)
+    pub follow_symlinks: bool,
+    /// Files served for error statuses (e.g. 404 → `404.html`), relative to `root`
+    pub error_pages: HashMap<u16, PathBuf>,
+}
+
+/// `Cache-Control` policy for a static file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// `max-age=<seconds>`
+    MaxAge(u32),
+    /// `public, max-age=31536000, immutable`, for fingerprinted assets
+    Immutable,
+    /// `no-cache` (always revalidate)
+    NoCache,
+    /// `no-store`
+    NoStore,
+    /// Any other header value
+    Custom(String),
+}
+
+impl CachePolicy {
+    /// Header value for the policy
+    pub fn header_value(&self) -> String {
+        match self {
+            CachePolicy::MaxAge(seconds) => format!("max-age={}", seconds),
+            CachePolicy::Immutable => "public, max-age=31536000, immutable".to_string(),
+            CachePolicy::NoCache => "no-cache".to_string(),
+            CachePolicy::NoStore => "no-store".to_string(),
+            CachePolicy::Custom(value) => value.clone(),
+        }
+    }
+}
+
+/// Which files a cache rule applies to
+#[derive(Debug, Clone)]
+pub enum CacheMatch {
+    /// Filenames with a content hash (`app.3f9a2c.js`, `index-BdX3k9aZ.js`)
+    Fingerprinted,
+    /// File extensions (case-insensitive, without the dot)
+    Extensions(Vec<String>),
+    /// Glob on the path relative to the root (`images/**`, `*.min.js`)
+    Glob(String),
+}
+
+impl CacheMatch {
+    /// Check a path relative to the root
+    pub fn matches(&self, path: &Path) -> bool {
+        match self {
+            CacheMatch::Fingerprinted => is_fingerprinted(path),
+            CacheMatch::Extensions(extensions) => path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext))),
+            CacheMatch::Glob(pattern) => {
+                let path = path.to_string_lossy().replace('\\', "/");
+                glob_match(pattern.trim_start_matches('/').as_bytes(), path.as_bytes())
+            }
+        }
+    }
+}
+
+/// A `Cache-Control` rule
+#[derive(Debug, Clone)]
+pub struct CacheRule {
+    /// Files the rule applies to
+    pub matcher: CacheMatch,
+    /// Policy for matching files
+    pub policy: CachePolicy,
+}
+
+impl CacheRule {
+    /// Create a rule
+    pub fn new(matcher: CacheMatch, policy: CachePolicy) -> Self {
+        Self { matcher, policy }
+    }
+}
+
+/// Match a glob against a `/`-separated path
+///
+/// `*` and `?` stay within a segment; `**` spans any number of segments.
+/// Patterns without a `/` match the filename at any depth.
+pub fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    if !pattern.contains(&b'/') {
+        let name = path.rsplit(|&b| b == b'/').next().unwrap_or(path);
+        return glob_segment(pattern, name);
+    }
+    glob_segment(pattern, path)
+}
+
+fn glob_segment(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // `**/` matches zero or more whole segments
+            glob_segment(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &b)| b == b'/' && glob_segment(rest, &path[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|i| glob_segment(rest, &path[i..])),
+        [b'*', rest @ ..] => {
+            let segment_end = path.iter().position(|&b| b == b'/').unwrap_or(path.len());
+            (0..=segment_end).any(|i| glob_segment(rest, &path[i..]))
+        }
+        [b'?', rest @ ..] => {
+            matches!(path.first(), Some(&b) if b != b'/') && glob_segment(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && glob_segment(rest, &path[1..]),
+    }
+}
+
+/// Whether a filename contains a content hash segment
+///
+/// Looks for a `.`/`-` separated segment of the stem that is either at
+/// least 6 hex characters with a digit, or 8 mixed-case alphanumerics with
+/// a digit (as produced by webpack, Vite and esbuild).
+pub fn is_fingerprinted(path: &Path) -> bool {
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return false;
+    };
+
+    stem.split(['.', '-']).skip(1).any(|segment| {
+        let has_digit = segment.bytes().any(|b| b.is_ascii_digit());
+        let hex = segment.len() >= 6 && segment.bytes().all(|b| b.is_ascii_hexdigit());
+        let mixed = segment.len() == 8
+            && segment
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_')
+            && segment.bytes().any(|b| b.is_ascii_uppercase())
+            && segment.bytes().any(|b| b.is_ascii_lowercase());
+        has_digit && (hex || mixed)
+    })
+}
+
+/// Dotfile policy for static files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DotFiles {
+    /// Serve dotfiles like any other file
+    Allow,
+    /// Pretend dotfiles don't exist (404)
+    #[default]
+    Ignore,
+    /// Refuse dotfiles (403)
+    Deny,
+}
+
+/// ETag strategy for static files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StaticEtag {
+    /// No ETags
+    Disabled,
+    /// Derived from file size and modification time (no extra read)
+    #[default]
+    Metadata,
+    /// SHA-256 of the file contents
+    ContentHash,
 }
 
 impl Default for StaticConfig {
     fn default() -> Self {
         Self {
             root: PathBuf::from("public"),
+            prefix: "/static".to_string(),
             index: "index.html".to_string(),
             directory_listing: false,
             max_age: 3600,
+            cache_rules: Vec::new(),
             gzip: true,
+            brotli: true,
+            etag: StaticEtag::default(),
+            last_modified: true,
+            error_pages: HashMap::new(),
+            dotfiles: DotFiles::default(),
+            follow_symlinks: true,
         }
     }
 }
@@ -43,6 +226,23 @@ impl StaticConfig {
         }
     }
 
+    /// Set the URL prefix the handler is mounted at (`""` for the site root)
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Path of a request relative to the prefix (`None` if outside it)
+    pub fn strip_prefix<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let prefix = self.prefix.trim_end_matches('/');
+        let rest = path.strip_prefix(prefix)?;
+        if rest.is_empty() || rest.starts_with('/') {
+            Some(rest.trim_start_matches('/'))
+        } else {
+            None
+        }
+    }
+
     /// Set index file
     pub fn index(mut self, index: &str) -> Self {
         self.index = index.to_string();
@@ -60,6 +260,111 @@ impl StaticConfig {
         self.max_age = seconds;
         self
     }
+
+    /// Enable or disable gzip (pre-compressed and on the fly)
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Enable or disable pre-compressed Brotli files
+    pub fn brotli(mut self, enabled: bool) -> Self {
+        self.brotli = enabled;
+        self
+    }
+
+    /// Set the dotfile policy
+    pub fn dotfiles(mut self, policy: DotFiles) -> Self {
+        self.dotfiles = policy;
+        self
+    }
+
+    /// Follow symlinks that stay inside the root
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Serve a file for an error status instead of the JSON error body
+    ///
+    /// ```rust,ignore
+    /// let config = StaticConfig::new("./public")
+    ///     .error_page(404, "404.html")
+    ///     .error_page(403, "403.html");
+    /// ```
+    pub fn error_page(mut self, status: u16, path: impl Into<PathBuf>) -> Self {
+        self.error_pages.insert(status, path.into());
+        self
+    }
+
+    /// Add a `Cache-Control` rule (checked in order, before `max_age`)
+    pub fn cache_rule(mut self, matcher: CacheMatch, policy: CachePolicy) -> Self {
+        self.cache_rules.push(CacheRule::new(matcher, policy));
+        self
+    }
+
+    /// Set a policy for file extensions
+    ///
+    /// ```rust,ignore
+    /// let config = StaticConfig::new("./public")
+    ///     .cache_extensions(&["css", "js"], CachePolicy::MaxAge(86400))
+    ///     .cache_extensions(&["png", "jpg", "webp"], CachePolicy::MaxAge(604800))
+    ///     .cache_extensions(&["html"], CachePolicy::NoCache);
+    /// ```
+    pub fn cache_extensions(self, extensions: &[&str], policy: CachePolicy) -> Self {
+        let extensions = extensions
+            .iter()
+            .map(|e| e.trim_start_matches('.').to_string())
+            .collect();
+        self.cache_rule(CacheMatch::Extensions(extensions), policy)
+    }
+
+    /// Set a policy for paths matching a glob (relative to the root)
+    ///
+    /// ```rust,ignore
+    /// let config = StaticConfig::new("./public")
+    ///     .cache_path("private/**", CachePolicy::NoStore)
+    ///     .cache_path("*.min.js", CachePolicy::MaxAge(86400));
+    /// ```
+    pub fn cache_path(self, pattern: &str, policy: CachePolicy) -> Self {
+        self.cache_rule(CacheMatch::Glob(pattern.to_string()), policy)
+    }
+
+    /// Cache fingerprinted assets forever and make HTML revalidate
+    ///
+    /// ```rust,ignore
+    /// // app.3f9a2c.js -> public, max-age=31536000, immutable
+    /// // index.html    -> no-cache
+    /// let config = StaticConfig::new("./dist").immutable_assets();
+    /// ```
+    pub fn immutable_assets(self) -> Self {
+        self.cache_rule(CacheMatch::Fingerprinted, CachePolicy::Immutable)
+            .cache_rule(
+                CacheMatch::Extensions(vec!["html".to_string(), "htm".to_string()]),
+                CachePolicy::NoCache,
+            )
+    }
+
+    /// `Cache-Control` value for a path relative to the root
+    pub fn cache_control(&self, path: &Path) -> String {
+        self.cache_rules
+            .iter()
+            .find(|rule| rule.matcher.matches(path))
+            .map(|rule| rule.policy.header_value())
+            .unwrap_or_else(|| CachePolicy::MaxAge(self.max_age).header_value())
+    }
+
+    /// Set the ETag strategy
+    pub fn etag(mut self, etag: StaticEtag) -> Self {
+        self.etag = etag;
+        self
+    }
+
+    /// Enable or disable `Last-Modified`
+    pub fn last_modified(mut self, enabled: bool) -> Self {
+        self.last_modified = enabled;
+        self
+    }
 }
 
 /// Serve a static file
@@ -72,12 +377,154 @@ pub async fn serve_file(path: &Path) -> Option<(Vec<u8>, String)> {
     }
 }
 
+/// Result of evaluating a `Range` header against a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// Serve the whole file (no range, or one we don't handle)
+    Full,
+    /// Serve bytes `start..=end`
+    Partial { start: u64, end: u64 },
+    /// The range lies outside the file (416)
+    Unsatisfiable,
+}
+
+/// Parse a `Range` header for a file of `len` bytes
+///
+/// Supports a single `bytes=start-end`, `bytes=start-` or `bytes=-suffix`
+/// range; multiple ranges and other units fall back to the full file.
+pub fn parse_range(header: &str, len: u64) -> ByteRange {
+    let Some(spec) = header.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(start) => (start, len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+            _ => return ByteRange::Full,
+        },
+    };
+
+    if len == 0 || start >= len {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial { start, end }
+    }
+}
+
+/// Read `len` bytes of a file starting at `start`
+async fn read_range(path: &Path, start: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path).await?;
+    file.seek(SeekFrom::Start(start)).await?;
+
+    let mut buf = Vec::with_capacity(len as usize);
+    file.take(len).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Check an `If-Range` header against the file's ETag or modification time
+///
+/// Entity tags use strong comparison, so weak tags never match.
+fn if_range_matches(if_range: &str, etag: Option<&str>, modified: Option<SystemTime>) -> bool {
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        return !if_range.starts_with("W/") && etag == Some(if_range);
+    }
+
+    match (httpdate::parse_http_date(if_range), modified) {
+        (Ok(date), Some(modified)) => http_date(modified) == http_date(date),
+        _ => false,
+    }
+}
+
+/// ETag from file size and modification time
+fn metadata_etag(len: u64, modified: Option<SystemTime>) -> String {
+    let mtime = modified
+        .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", mtime, len)
+}
+
+/// Check whether an `Accept-Encoding` header allows an encoding
+pub fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let rejected = parts.any(|param| {
+            param
+                .trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                == Some(0.0)
+        });
+        (name.eq_ignore_ascii_case(encoding) || name == "*") && !rejected
+    })
+}
+
+/// Whether a MIME type is worth compressing on the fly
+fn is_compressible(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || mime.starts_with("application/javascript")
+        || mime.starts_with("application/json")
+        || mime.starts_with("application/xml")
+        || mime.starts_with("application/wasm")
+        || mime.starts_with("image/svg+xml")
+}
+
+/// Gzip a buffer
+fn gzip(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Find a pre-compressed sibling (`file.br` / `file.gz`) the client accepts
+async fn precompressed(
+    config: &StaticConfig,
+    accept_encoding: &str,
+    path: &Path,
+) -> Option<(PathBuf, std::fs::Metadata, &'static str)> {
+    let candidates = [("br", "br", config.brotli), ("gzip", "gz", config.gzip)];
+
+    for (encoding, suffix, enabled) in candidates {
+        if !enabled || !accepts_encoding(accept_encoding, encoding) {
+            continue;
+        }
+        let mut candidate = path.as_os_str().to_owned();
+        candidate.push(".");
+        candidate.push(suffix);
+        let candidate = PathBuf::from(candidate);
+
+        if let Ok(metadata) = fs::metadata(&candidate).await {
+            if metadata.is_file() {
+                return Some((candidate, metadata, encoding));
+            }
+        }
+    }
+    None
+}
+
 /// Get MIME type from file extension
 pub fn get_mime_type(path: &Path) -> String {
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
 
     match extension.to_lowercase().as_str() {
         // Text
@@ -89,7 +536,7 @@ pub fn get_mime_type(path: &Path) -> String {
         "txt" => "text/plain; charset=utf-8",
         "md" => "text/markdown; charset=utf-8",
         "csv" => "text/csv; charset=utf-8",
-        
+
         // Images
         "png" => "image/png",
         "jpg" | "jpeg" => "image/jpeg",
@@ -98,27 +545,27 @@ pub fn get_mime_type(path: &Path) -> String {
         "ico" => "image/x-icon",
         "webp" => "image/webp",
         "avif" => "image/avif",
-        
+
         // Fonts
         "woff" => "font/woff",
         "woff2" => "font/woff2",
         "ttf" => "font/ttf",
         "otf" => "font/otf",
         "eot" => "application/vnd.ms-fontobject",
-        
+
         // Documents
         "pdf" => "application/pdf",
         "doc" => "application/msword",
         "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
         "xls" => "application/vnd.ms-excel",
         "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-        
+
         // Archives
         "zip" => "application/zip",
         "tar" => "application/x-tar",
         "gz" => "application/gzip",
         "rar" => "application/vnd.rar",
-        
+
         // Media
         "mp3" => "audio/mpeg",
         "wav" => "audio/wav",
@@ -126,10 +573,10 @@ pub fn get_mime_type(path: &Path) -> String {
         "mp4" => "video/mp4",
         "webm" => "video/webm",
         "avi" => "video/x-msvideo",
-        
+
         // WebAssembly
         "wasm" => "application/wasm",
-        
+
         // Default
         _ => "application/octet-stream",
     }
@@ -143,64 +590,400 @@ pub fn get_mime_type(path: &Path) -> String {
 /// ```rust,ignore
 /// use rustyx::static_files::{static_handler, StaticConfig};
 ///
-/// let config = StaticConfig::new("./public");
-/// app.get("/static/*", static_handler(config));
+/// let config = StaticConfig::new("./public").prefix("/assets");
+/// app.get("/assets/*", static_handler(config));
 /// ```
 pub fn static_handler(
     config: StaticConfig,
-) -> impl Fn(Request, Response) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>> + Send + Sync + Clone + 'static {
+) -> impl Fn(
+    Request,
+    Response,
+) -> std::pin::Pin<Box<dyn std::future::Future<Output = Response> + Send>>
+       + Send
+       + Sync
+       + Clone
+       + 'static {
     move |req: Request, res: Response| {
         let config = config.clone();
-        
+
         Box::pin(async move {
-            let request_path = req.path();
-            
-            // Remove leading /static/ or similar prefix
-            let file_path = request_path
-                .trim_start_matches('/')
-                .trim_start_matches("static/");
-            
-            let full_path = config.root.join(file_path);
-            
-            // Security check - prevent path traversal
-            if !full_path.starts_with(&config.root) {
-                return res.status(403).json(serde_json::json!({
-                    "error": "Forbidden",
-                    "message": "Access denied"
-                }));
+            let Some(file_path) = config.strip_prefix(req.path()) else {
+                return error_response(&config, res, 404).await;
+            };
+
+            let (full_path, relative) = match resolve(&config, file_path).await {
+                Ok(resolved) => resolved,
+                Err(status) => return error_response(&config, res, status).await,
+            };
+
+            match fs::metadata(&full_path).await {
+                Ok(metadata) if metadata.is_file() => {
+                    send_file(&config, &req, res, &full_path, &relative, &metadata).await
+                }
+                _ => error_response(&config, res, 404).await,
             }
+        })
+    }
+}
 
-            // Check if path is a directory
-            if full_path.is_dir() {
-                let index_path = full_path.join(&config.index);
-                if index_path.exists() {
-                    if let Some((content, mime)) = serve_file(&index_path).await {
-                        return res
-                            .status(200)
-                            .header("Content-Type", &mime)
-                            .header("Cache-Control", &format!("max-age={}", config.max_age))
-                            .send_bytes(content);
-                    }
+/// Map a request path to a file under the root, or the error status to send
+///
+/// Rejects `..` segments, applies the dotfile policy and canonicalizes the
+/// result so symlinks can't lead outside the root.
+///
+/// Returns the file's canonical path and its path relative to the root.
+async fn resolve(config: &StaticConfig, file_path: &str) -> Result<(PathBuf, PathBuf), u16> {
+    let relative = Path::new(file_path);
+    for component in relative.components() {
+        match component {
+            Component::Normal(name) if name.to_string_lossy().starts_with('.') => {
+                match config.dotfiles {
+                    DotFiles::Allow => {}
+                    DotFiles::Ignore => return Err(404),
+                    DotFiles::Deny => return Err(403),
                 }
-                
-                return res.status(404).json(serde_json::json!({
-                    "error": "Not Found",
-                    "message": "File not found"
-                }));
             }
+            Component::Normal(_) | Component::CurDir => {}
+            _ => return Err(403),
+        }
+    }
+
+    let root = fs::canonicalize(&config.root).await.map_err(|_| 404_u16)?;
+    let mut relative = relative.to_path_buf();
+    let mut expected = root.join(&relative);
+    let mut path = fs::canonicalize(config.root.join(&relative))
+        .await
+        .map_err(|_| 404_u16)?;
+
+    // Directories are served through their index file
+    if path.is_dir() {
+        relative = relative.join(&config.index);
+        expected = expected.join(&config.index);
+        path = fs::canonicalize(path.join(&config.index))
+            .await
+            .map_err(|_| 404_u16)?;
+    }
+
+    if !path.starts_with(&root) {
+        return Err(403);
+    }
+    // Without symlinks the canonical path is just root + request path
+    if !config.follow_symlinks && path != expected {
+        return Err(403);
+    }
+    Ok((path, relative))
+}
+
+/// Error response: the configured error page, or a JSON body
+async fn error_response(config: &StaticConfig, res: Response, status: u16) -> Response {
+    if let Some(page) = config.error_pages.get(&status) {
+        let path = config.root.join(page);
+        if let Ok(content) = fs::read(&path).await {
+            return res
+                .status(status)
+                .header("Content-Type", &get_mime_type(&path))
+                .header("Cache-Control", "no-cache")
+                .send_bytes(content);
+        }
+    }
+
+    let (error, message) = match status {
+        403 => ("Forbidden", "Access denied"),
+        _ => ("Not Found", "File not found"),
+    };
+    res.status(status).json(serde_json::json!({
+        "error": error,
+        "message": message
+    }))
+}
+
+/// Send a file, honouring conditional, `Range` / `If-Range` and
+/// `Accept-Encoding` headers
+async fn send_file(
+    config: &StaticConfig,
+    req: &Request,
+    res: Response,
+    path: &Path,
+    relative: &Path,
+    metadata: &std::fs::Metadata,
+) -> Response {
+    let mime = get_mime_type(relative);
+    let mut res = res
+        .header("Content-Type", &mime)
+        .header("Cache-Control", &config.cache_control(relative))
+        .header("Accept-Ranges", "bytes");
+
+    // Pick the representation: pre-compressed sibling, on-the-fly gzip, or as is
+    let accept_encoding = req.header("accept-encoding").unwrap_or("");
+    let variant = precompressed(config, accept_encoding, path).await;
+    let compress = variant.is_none()
+        && config.gzip
+        && accepts_encoding(accept_encoding, "gzip")
+        && is_compressible(&mime)
+        && req.header("range").is_none();
+    if config.gzip || config.brotli {
+        res = res.header("Vary", "Accept-Encoding");
+    }
+    let (path, metadata) = match &variant {
+        Some((path, metadata, encoding)) => {
+            res = res.header("Content-Encoding", encoding);
+            (path.as_path(), metadata)
+        }
+        None => (path, metadata),
+    };
+
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = match config.etag {
+        StaticEtag::Disabled => None,
+        StaticEtag::Metadata => Some(metadata_etag(len, modified)),
+        StaticEtag::ContentHash => match fs::read(path).await {
+            Ok(content) => Some(compute_etag(&content, false)),
+            Err(_) => return res.internal_error("Failed to read file"),
+        },
+    };
+    // The gzipped body is a different representation, so it needs its own tag
+    let etag = etag.map(|tag| {
+        if compress {
+            format!("{}-gzip\"", tag.trim_end_matches('"'))
+        } else {
+            tag
+        }
+    });
+    let last_modified = modified.filter(|_| config.last_modified).map(http_date);
+
+    if let Some(etag) = &etag {
+        res = res.header("ETag", etag);
+    }
+    if let Some(last_modified) = &last_modified {
+        res = res.header("Last-Modified", last_modified);
+    }
+
+    // If-None-Match takes precedence over If-Modified-Since
+    let not_modified = match (req.header("if-none-match"), &etag) {
+        (Some(header), Some(etag)) => etag_matches(header, etag),
+        (Some(_), None) => false,
+        (None, _) => match (req.header("if-modified-since"), &last_modified) {
+            (Some(since), Some(modified)) => not_modified_since(since, modified),
+            _ => false,
+        },
+    };
+    if not_modified {
+        return res
+            .status(304)
+            .remove_header("Content-Type")
+            .send_bytes(Vec::new());
+    }
 
-            // Serve the file
-            if let Some((content, mime)) = serve_file(&full_path).await {
-                res.status(200)
-                    .header("Content-Type", &mime)
-                    .header("Cache-Control", &format!("max-age={}", config.max_age))
-                    .send_bytes(content)
-            } else {
-                res.status(404).json(serde_json::json!({
-                    "error": "Not Found",
-                    "message": "File not found"
-                }))
+    let range = match req.header("range") {
+        Some(range)
+            if req
+                .header("if-range")
+                .is_none_or(|if_range| if_range_matches(if_range, etag.as_deref(), modified)) =>
+        {
+            parse_range(range, len)
+        }
+        _ => ByteRange::Full,
+    };
+
+    match range {
+        ByteRange::Full => match fs::read(path).await {
+            Ok(content) if compress => match gzip(&content) {
+                Ok(compressed) => res
+                    .status(200)
+                    .header("Content-Encoding", "gzip")
+                    .send_bytes(compressed),
+                Err(_) => res.status(200).send_bytes(content),
+            },
+            Ok(content) => res.status(200).send_bytes(content),
+            Err(_) => res.internal_error("Failed to read file"),
+        },
+        ByteRange::Partial { start, end } => match read_range(path, start, end - start + 1).await {
+            Ok(content) => res
+                .status(206)
+                .header("Content-Range", &format!("bytes {}-{}/{}", start, end, len))
+                .send_bytes(content),
+            Err(_) => res.internal_error("Failed to read file"),
+        },
+        ByteRange::Unsatisfiable => res
+            .status(416)
+            .header("Content-Range", &format!("bytes */{}", len))
+            .send_bytes(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse_range("bytes=0-99", 1000),
+            ByteRange::Partial { start: 0, end: 99 }
+        );
+        assert_eq!(
+            parse_range("bytes=900-", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
             }
-        })
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
+            }
+        );
+        assert_eq!(
+            parse_range("bytes=500-5000", 1000),
+            ByteRange::Partial {
+                start: 500,
+                end: 999
+            }
+        );
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=9-1", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        let config = StaticConfig::new("public").prefix("/assets/");
+        assert_eq!(config.strip_prefix("/assets/app.js"), Some("app.js"));
+        assert_eq!(config.strip_prefix("/assets"), Some(""));
+        assert_eq!(config.strip_prefix("/assetsfoo/app.js"), None);
+        assert_eq!(config.strip_prefix("/other/app.js"), None);
+
+        let root = StaticConfig::new("public").prefix("");
+        assert_eq!(root.strip_prefix("/css/site.css"), Some("css/site.css"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_policies() {
+        let root = std::env::temp_dir().join(format!("rustyx-static-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("css")).unwrap();
+        std::fs::write(root.join("css/site.css"), "body{}").unwrap();
+        std::fs::write(root.join(".env"), "SECRET=1").unwrap();
+
+        let config = StaticConfig::new(root.to_str().unwrap());
+        assert!(resolve(&config, "css/site.css").await.is_ok());
+        assert_eq!(resolve(&config, ".env").await, Err(404));
+        assert_eq!(resolve(&config, "../etc/passwd").await, Err(403));
+        let deny = config.clone().dotfiles(DotFiles::Deny);
+        assert_eq!(resolve(&deny, ".env").await, Err(403));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc", root.join("escape")).unwrap();
+            std::os::unix::fs::symlink(root.join("css"), root.join("styles")).unwrap();
+            assert_eq!(resolve(&config, "escape/hostname").await, Err(403));
+            assert!(resolve(&config, "styles/site.css").await.is_ok());
+            let strict = config.clone().follow_symlinks(false);
+            assert_eq!(resolve(&strict, "styles/site.css").await, Err(403));
+        }
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_error_page() {
+        let root = std::env::temp_dir().join(format!("rustyx-static-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("404.html"), "<h1>Lost</h1>").unwrap();
+
+        let config = StaticConfig::new(root.to_str().unwrap()).prefix("/assets");
+        let missing = || Request::builder().path("/assets/missing.css").build();
+
+        let handler = static_handler(config.clone().error_page(404, "404.html"));
+        let res = handler(missing(), Response::new()).await;
+        assert_eq!(res.get_status(), 404);
+        assert_eq!(
+            res.get_headers()["content-type"],
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(&res.get_body()[..], b"<h1>Lost</h1>");
+
+        let res = static_handler(config)(missing(), Response::new()).await;
+        assert_eq!(res.get_status(), 404);
+        let body: serde_json::Value = serde_json::from_slice(res.get_body()).unwrap();
+        assert_eq!(body["error"], "Not Found");
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_cache_rules() {
+        assert!(is_fingerprinted(Path::new("app.3f9a2c.js")));
+        assert!(is_fingerprinted(Path::new("assets/index-BdX3k9aZ.js")));
+        assert!(!is_fingerprinted(Path::new("app.js")));
+        assert!(!is_fingerprinted(Path::new("jquery-min.js")));
+        assert!(!is_fingerprinted(Path::new("facade.decade.css")));
+
+        let config = StaticConfig::new("dist").immutable_assets().max_age(60);
+        assert_eq!(
+            config.cache_control(Path::new("app.3f9a2c.js")),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(config.cache_control(Path::new("index.html")), "no-cache");
+        assert_eq!(config.cache_control(Path::new("logo.png")), "max-age=60");
+
+        let config = StaticConfig::new("public")
+            .cache_path("private/**", CachePolicy::NoStore)
+            .cache_extensions(&["css", ".JS"], CachePolicy::MaxAge(86400));
+        assert_eq!(
+            config.cache_control(Path::new("private/a/b.css")),
+            "no-store"
+        );
+        assert_eq!(
+            config.cache_control(Path::new("css/site.CSS")),
+            "max-age=86400"
+        );
+        assert_eq!(config.cache_control(Path::new("app.js")), "max-age=86400");
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"images/**", b"images/a/b/c.png"));
+        assert!(glob_match(b"**/*.png", b"logo.png"));
+        assert!(glob_match(b"**/*.png", b"a/b/logo.png"));
+        assert!(glob_match(b"*.min.js", b"vendor/jquery.min.js"));
+        assert!(glob_match(b"img/?.gif", b"img/a.gif"));
+        assert!(!glob_match(b"img/*.gif", b"img/sub/a.gif"));
+        assert!(!glob_match(b"images/**", b"css/site.css"));
+    }
+
+    #[test]
+    fn test_accepts_encoding() {
+        assert!(accepts_encoding("gzip, deflate, br", "br"));
+        assert!(accepts_encoding("GZIP;q=0.5", "gzip"));
+        assert!(accepts_encoding("*", "gzip"));
+        assert!(!accepts_encoding("gzip;q=0, br", "gzip"));
+        assert!(!accepts_encoding("deflate", "gzip"));
+    }
+
+    #[test]
+    fn test_if_range() {
+        let modified = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").ok();
+        let etag = metadata_etag(10, modified);
+
+        assert!(if_range_matches(&etag, Some(&etag), modified));
+        assert!(!if_range_matches(
+            &format!("W/{}", etag),
+            Some(&etag),
+            modified
+        ));
+        assert!(if_range_matches(
+            "Wed, 21 Oct 2015 07:28:00 GMT",
+            None,
+            modified
+        ));
+        assert!(!if_range_matches(
+            "Thu, 22 Oct 2015 07:28:00 GMT",
+            None,
+            modified
+        ));
     }
 }
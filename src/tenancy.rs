@@ -0,0 +1,291 @@
+//! Multi-Tenancy
+//!
+//! A [`Tenant`] is resolved for each request by the
+//! [`tenancy`](crate::middleware::tenancy()) middleware and exposed via
+//! [`Request::tenant`](crate::Request::tenant). Its [`TenantStorage`]
+//! decides how its data is kept apart from other tenants':
+//!
+//! - [`Shared`](TenantStorage::Shared) - the default connection; filter rows yourself
+//! - [`Schema`](TenantStorage::Schema) - a PostgreSQL schema (`search_path`), or a
+//!   database on the same server for MySQL and MongoDB
+//! - [`Database`](TenantStorage::Database) - a database of its own (a file for SQLite)
+//! - [`Prefix`](TenantStorage::Prefix) - table and collection names prefixed on the
+//!   default connection
+//!
+//! Schema and database tenants get a pool of their own, opened from the
+//! default connection's settings on first use and registered as
+//! `tenant:{id}`. While a request runs, [`SqlExecutor::global`] and models
+//! use that pool.
+//!
+//! ```rust,ignore
+//! let tenants = Tenants::new();
+//! tenants.register(Tenant::new("acme").storage(TenantStorage::Schema("acme".into())));
+//! tenants.register(Tenant::new("globex").rate_limit(1000));
+//!
+//! app.use_middleware(tenancy(
+//!     TenancyOptions::new()
+//!         .subdomain("example.com")
+//!         .header("x-tenant")
+//!         .tenants(tenants),
+//! ));
+//!
+//! app.get("/projects", |req, res| async move {
+//!     let tenant = req.tenant().unwrap();
+//!     let projects = Project::all().await?; // runs against the tenant's schema
+//!     Ok::<_, Error>(res.json(json!({ "tenant": tenant.id, "projects": projects })))
+//! });
+//! ```
+//!
+//! [`SqlExecutor::global`]: crate::db::sql::SqlExecutor::global
+
+use crate::cache::Cache;
+use crate::db::connection::{add_connection, db, DatabaseConnection, DEFAULT_CONNECTION};
+use crate::db::DbDriver;
+use crate::error::{Error, Result};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Serializes opening tenant pools, so concurrent first requests open one
+static OPENING: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// Where a tenant's data lives
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum TenantStorage {
+    /// The default connection, shared with every tenant
+    #[default]
+    Shared,
+    /// A schema on the default server
+    Schema(String),
+    /// A database of its own on the default server
+    Database(String),
+    /// Table and collection names starting with this prefix
+    Prefix(String),
+}
+
+/// The tenant a request belongs to
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    pub id: String,
+    pub storage: TenantStorage,
+    /// Requests per rate limit window, replacing the limiter's default
+    pub rate_limit: Option<u32>,
+    /// Anything else the app keeps per tenant, e.g. plan or display name
+    pub data: Value,
+}
+
+impl Tenant {
+    /// A tenant sharing the default connection
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            storage: TenantStorage::Shared,
+            rate_limit: None,
+            data: Value::Null,
+        }
+    }
+
+    pub fn storage(mut self, storage: TenantStorage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn rate_limit(mut self, max_requests: u32) -> Self {
+        self.rate_limit = Some(max_requests);
+        self
+    }
+
+    pub fn data(mut self, data: Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Name of the connection holding this tenant's data
+    pub fn connection_name(&self) -> String {
+        match self.storage {
+            TenantStorage::Schema(_) | TenantStorage::Database(_) => {
+                format!("tenant:{}", self.id)
+            }
+            TenantStorage::Shared | TenantStorage::Prefix(_) => DEFAULT_CONNECTION.to_string(),
+        }
+    }
+
+    /// This tenant's connection, opening its pool on first use
+    pub async fn connection(&self) -> Result<DatabaseConnection> {
+        let name = self.connection_name();
+        if let Ok(conn) = db(&name) {
+            return Ok(conn);
+        }
+        let _opening = OPENING.lock().await;
+        if let Ok(conn) = db(&name) {
+            return Ok(conn);
+        }
+        let mut config = db(DEFAULT_CONNECTION)?.config().clone();
+        match (&self.storage, &config.driver) {
+            (TenantStorage::Schema(schema), DbDriver::PostgreSQL) => {
+                config = config.option("options", &format!("-c search_path={}", schema));
+            }
+            (TenantStorage::Schema(_), DbDriver::SQLite | DbDriver::Redis) => {
+                return Err(Error::Database(format!(
+                    "Schema per tenant is not supported for {:?}",
+                    config.driver
+                )));
+            }
+            (TenantStorage::Schema(name) | TenantStorage::Database(name), _) => {
+                config.database = name.clone();
+            }
+            (TenantStorage::Shared | TenantStorage::Prefix(_), _) => unreachable!(),
+        }
+        add_connection(&name, config).await?;
+        db(&name)
+    }
+
+    /// Query executor for this tenant's data
+    #[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+    pub async fn sql(&self) -> Result<crate::db::sql::SqlExecutor> {
+        self.connection().await?.sql()
+    }
+
+    /// A table or collection name with this tenant's prefix, if it has one
+    pub fn table(&self, name: &str) -> String {
+        match &self.storage {
+            TenantStorage::Prefix(prefix) => format!("{}{}", prefix, name),
+            _ => name.to_string(),
+        }
+    }
+
+    /// A MongoDB collection of this tenant's
+    #[cfg(feature = "mongodb")]
+    pub async fn collection<T>(&self, name: &str) -> Result<mongodb::Collection<T>> {
+        let database = self
+            .connection()
+            .await?
+            .mongo_database()
+            .ok_or_else(|| Error::Database("No MongoDB connection".to_string()))?;
+        Ok(database.collection(&self.table(name)))
+    }
+
+    /// A view of `cache` holding only this tenant's keys
+    pub fn cache(&self, cache: &Cache) -> Cache {
+        cache.prefix(&format!("tenant:{}:", self.id))
+    }
+}
+
+/// Known tenants, looked up by id
+///
+/// Cloning is cheap and clones share the registry.
+#[derive(Debug, Clone, Default)]
+pub struct Tenants {
+    tenants: Arc<RwLock<HashMap<String, Tenant>>>,
+}
+
+impl Tenants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a tenant
+    pub fn register(&self, tenant: Tenant) {
+        self.tenants.write().insert(tenant.id.clone(), tenant);
+    }
+
+    /// Remove a tenant; its pool, if open, stays registered until
+    /// [`remove_connection`](crate::db::connection::remove_connection)
+    pub fn remove(&self, id: &str) -> Option<Tenant> {
+        self.tenants.write().remove(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Tenant> {
+        self.tenants.read().get(id).cloned()
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.tenants.read().keys().cloned().collect()
+    }
+}
+
+tokio::task_local! {
+    static CURRENT: Tenant;
+}
+
+/// Run `future` on behalf of `tenant`
+///
+/// The [`tenancy`](crate::middleware::tenancy()) middleware does this for
+/// each request.
+pub async fn scope<F: std::future::Future>(tenant: Tenant, future: F) -> F::Output {
+    CURRENT.scope(tenant, future).await
+}
+
+/// The tenant of the request being handled, if any
+pub fn current() -> Option<Tenant> {
+    CURRENT.try_with(Tenant::clone).ok()
+}
+
+/// The open pool of the current tenant, when it has one of its own
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+pub(crate) fn current_connection() -> Option<DatabaseConnection> {
+    CURRENT
+        .try_with(|tenant| match tenant.storage {
+            TenantStorage::Schema(_) | TenantStorage::Database(_) => {
+                db(&tenant.connection_name()).ok()
+            }
+            TenantStorage::Shared | TenantStorage::Prefix(_) => None,
+        })
+        .ok()
+        .flatten()
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::connection::{init_db, remove_connection};
+    use crate::db::sql::SqlExecutor;
+    use crate::db::DatabaseConfig;
+
+    #[tokio::test]
+    async fn test_tenant_database_is_used_in_scope() {
+        let dir = std::env::temp_dir().join(format!("rustyx-tenancy-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        init_db(DatabaseConfig::new(DbDriver::SQLite, &path("main.db")).option("mode", "rwc"))
+            .await
+            .unwrap();
+
+        let tenant = Tenant::new("acme").storage(TenantStorage::Database(path("acme.db")));
+        let conn = tenant.connection().await.unwrap();
+        assert_eq!(tenant.connection_name(), "tenant:acme");
+        assert_eq!(conn.config().database, path("acme.db"));
+        tenant
+            .sql()
+            .await
+            .unwrap()
+            .execute("CREATE TABLE notes (id INTEGER)")
+            .await
+            .unwrap();
+
+        let tables = "SELECT name FROM sqlite_master WHERE name = 'notes'";
+        let scoped = scope(tenant.clone(), async {
+            assert_eq!(current().unwrap().id, "acme");
+            SqlExecutor::global()?.query::<Value>(tables).await
+        })
+        .await
+        .unwrap();
+        assert_eq!(scoped.len(), 1);
+        let shared = SqlExecutor::global()
+            .unwrap()
+            .query::<Value>(tables)
+            .await
+            .unwrap();
+        assert!(shared.is_empty());
+        assert!(current().is_none());
+
+        let prefixed = Tenant::new("globex").storage(TenantStorage::Prefix("globex_".into()));
+        assert_eq!(prefixed.table("notes"), "globex_notes");
+        assert_eq!(prefixed.connection_name(), DEFAULT_CONNECTION);
+
+        remove_connection("tenant:acme");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
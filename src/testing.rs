@@ -0,0 +1,255 @@
+//! Test Client
+//!
+//! Sends requests through an app's middleware and router in-process, so
+//! routes can be tested without starting a server.
+//!
+//! ```rust,ignore
+//! #[tokio::test]
+//! async fn creates_users() {
+//!     let app = build_app();
+//!     let res = app
+//!         .test()
+//!         .post("/users")
+//!         .json(&json!({ "name": "Ann" }))
+//!         .send()
+//!         .await;
+//!     res.assert_status(201)
+//!         .assert_header("content-type", "application/json")
+//!         .assert_json_field("name", "Ann");
+//! }
+//! ```
+
+use crate::app::RustyX;
+use crate::request::{Request, RequestBuilder};
+use crate::response::Response;
+use bytes::Bytes;
+use hyper::{HeaderMap, Method, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::net::SocketAddr;
+
+/// Client returned by [`RustyX::test`]
+pub struct TestClient<'a> {
+    app: &'a RustyX,
+}
+
+impl<'a> TestClient<'a> {
+    pub(crate) fn new(app: &'a RustyX) -> Self {
+        Self { app }
+    }
+
+    /// Start a request with any method
+    pub fn request(&self, method: Method, path: &str) -> TestRequest<'a> {
+        TestRequest {
+            app: self.app,
+            builder: Request::builder().method(method).path(path),
+        }
+    }
+
+    pub fn get(&self, path: &str) -> TestRequest<'a> {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> TestRequest<'a> {
+        self.request(Method::POST, path)
+    }
+
+    pub fn put(&self, path: &str) -> TestRequest<'a> {
+        self.request(Method::PUT, path)
+    }
+
+    pub fn patch(&self, path: &str) -> TestRequest<'a> {
+        self.request(Method::PATCH, path)
+    }
+
+    pub fn delete(&self, path: &str) -> TestRequest<'a> {
+        self.request(Method::DELETE, path)
+    }
+}
+
+/// A request being built by [`TestClient`]
+pub struct TestRequest<'a> {
+    app: &'a RustyX,
+    builder: RequestBuilder,
+}
+
+impl TestRequest<'_> {
+    /// Set a header, panicking on invalid names or values
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.builder = self.builder.header(name, value);
+        self
+    }
+
+    /// Send `Authorization: Bearer <token>`
+    pub fn bearer(self, token: &str) -> Self {
+        self.header("authorization", &format!("Bearer {}", token))
+    }
+
+    /// Set a raw body
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.builder = self.builder.body(body);
+        self
+    }
+
+    /// Send a JSON body
+    pub fn json<T: Serialize + ?Sized>(mut self, value: &T) -> Self {
+        self.builder = self.builder.json(value);
+        self
+    }
+
+    /// Send a URL-encoded form body
+    pub fn form(mut self, fields: &[(&str, &str)]) -> Self {
+        self.builder = self.builder.form(fields);
+        self
+    }
+
+    /// Set the client address seen by the app
+    pub fn remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.builder = self.builder.remote_addr(addr);
+        self
+    }
+
+    /// Run the request through the app, reading streamed bodies into memory
+    pub async fn send(self) -> TestResponse {
+        TestResponse {
+            response: self
+                .app
+                .handle(self.builder.build())
+                .await
+                .buffer()
+                .await
+                .expect("streamed response body failed"),
+        }
+    }
+}
+
+/// Response from [`TestRequest::send`], with panicking assertions
+pub struct TestResponse {
+    response: Response,
+}
+
+impl TestResponse {
+    pub fn status(&self) -> StatusCode {
+        self.response.get_status()
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.response.get_headers()
+    }
+
+    /// A header value as a string
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers().get(name).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn body(&self) -> &Bytes {
+        self.response.get_body()
+    }
+
+    /// The body as UTF-8 text
+    pub fn text(&self) -> String {
+        self.response.body_text()
+    }
+
+    /// Deserialize the JSON body, panicking when it doesn't parse as `T`
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        self.response.body_json().unwrap_or_else(|e| {
+            panic!(
+                "response body is not the expected JSON ({}): {}",
+                e,
+                self.text()
+            )
+        })
+    }
+
+    /// The underlying response
+    pub fn into_response(self) -> Response {
+        self.response
+    }
+
+    pub fn assert_status(&self, status: u16) -> &Self {
+        assert_eq!(
+            self.status().as_u16(),
+            status,
+            "unexpected status, body: {}",
+            self.text()
+        );
+        self
+    }
+
+    /// Assert a header is present and starts with `value`, so
+    /// `application/json` matches `application/json; charset=utf-8`
+    pub fn assert_header(&self, name: &str, value: &str) -> &Self {
+        match self.header(name) {
+            Some(actual) => assert!(
+                actual.starts_with(value),
+                "header `{}` is `{}`, expected `{}`",
+                name,
+                actual,
+                value
+            ),
+            None => panic!("missing header `{}`", name),
+        }
+        self
+    }
+
+    /// Assert the JSON body equals `expected`
+    pub fn assert_json(&self, expected: Value) -> &Self {
+        assert_eq!(self.json::<Value>(), expected);
+        self
+    }
+
+    /// Assert a top-level field of the JSON body equals `expected`
+    pub fn assert_json_field(&self, field: &str, expected: impl Into<Value>) -> &Self {
+        assert_eq!(
+            self.json::<Value>().get(field),
+            Some(&expected.into()),
+            "JSON field `{}` differs, body: {}",
+            field,
+            self.text()
+        );
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[tokio::test]
+    async fn test_client_runs_full_pipeline() {
+        let app = RustyX::new();
+        app.use_middleware(
+            |req, res, next| async move { next(req, res).await.header("x-seen", "1") },
+        );
+        app.post("/users", |req, res| async move {
+            let body: Value = req.json()?;
+            Ok::<_, Error>(res.status(201).json(json!({ "name": body["name"] })))
+        });
+        app.get("/users/:id", |req, res| async move {
+            let id = req.param("id").cloned().unwrap_or_default();
+            let page = req.query_param("page").cloned().unwrap_or_default();
+            res.json(json!({ "id": id, "page": page }))
+        });
+
+        app.test()
+            .post("/users")
+            .json(&json!({ "name": "Ann" }))
+            .send()
+            .await
+            .assert_status(201)
+            .assert_header("x-seen", "1")
+            .assert_header("content-type", "application/json")
+            .assert_json_field("name", "Ann");
+
+        app.test()
+            .get("/users/7?page=2")
+            .send()
+            .await
+            .assert_json(json!({ "id": "7", "page": "2" }));
+
+        let res = app.test().post("/users").body("{").send().await;
+        res.assert_status(400);
+        app.test().get("/missing").send().await.assert_status(404);
+    }
+}
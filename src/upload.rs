@@ -7,7 +7,13 @@
 //! - Single and multiple file uploads
 //! - File type validation
 //! - File size limits
-//! - Disk and memory storage
+//! - Disk, memory and S3-compatible storage (feature `s3`)
+//! - Pluggable storage backends
+//! - Magic-byte content verification
+//! - Scan hooks (ClamAV or custom) before files are stored
+//! - Per-user daily/total byte quotas
+//! - Content-hash deduplication
+//! - Image resizing, thumbnails and WebP conversion (feature `image`)
 //! - Custom file naming
 //!
 //! # Example
@@ -36,9 +42,34 @@
 //! });
 //! ```
 
+pub mod fields;
+#[cfg(feature = "image")]
+pub mod processing;
+pub mod quota;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod scan;
+pub mod storage;
+
+pub use fields::{FieldSpec, FieldsUpload, UploadedFields};
+#[cfg(feature = "image")]
+pub use processing::{ImageOptions, Thumbnail};
+
+use quota::QuotaReservation;
+pub use quota::{MemoryQuotaStore, QuotaLimits, QuotaPeriod, QuotaStore, QuotaUsage, UploadQuota};
+#[cfg(feature = "s3")]
+pub use s3::{ObjectMetadata, PresignedUpload, S3Config};
+pub use scan::{ClamAvScanner, FileScanner, FnScanner, ScanFile, ScanHook, ScanVerdict};
+pub use storage::{DiskStorage, MemoryStorage, StorageBackend, StorageContext, StoredFile};
+
+use crate::middleware::Principal;
+use crate::request::Request;
+use bytes::Bytes;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
-use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Uploaded file information
@@ -48,7 +79,7 @@ pub struct UploadedFile {
     pub original_name: String,
     /// Saved filename on disk (may be different from original)
     pub filename: String,
-    /// Full path to the saved file
+    /// Full path to the saved file (object key for S3 storage)
     pub path: PathBuf,
     /// MIME type of the file
     pub mimetype: String,
@@ -58,10 +89,43 @@ pub struct UploadedFile {
     pub field_name: String,
     /// File extension
     pub extension: String,
+    /// File contents, kept for memory storage (`None` for disk storage)
+    pub data: Option<Bytes>,
+    /// Object URL for remote storage (e.g. S3)
+    pub url: Option<String>,
+    /// Generated image variants (thumbnails), with the `image` feature
+    pub variants: Vec<ImageVariant>,
+    /// SHA-256 of the uploaded contents, with content-hash naming
+    pub hash: Option<String>,
+    /// The contents were already stored and the existing file was reused
+    ///
+    /// Deleting a deduplicated file removes it for every upload sharing it.
+    pub deduplicated: bool,
 }
 
-/// Storage type for uploaded files
+/// A stored image variant such as a thumbnail
 #[derive(Debug, Clone)]
+pub struct ImageVariant {
+    /// Variant name (e.g. `small`)
+    pub name: String,
+    /// Saved filename
+    pub filename: String,
+    /// Path or object key
+    pub path: PathBuf,
+    /// Object URL for remote storage
+    pub url: Option<String>,
+    /// MIME type
+    pub mimetype: String,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Size in bytes
+    pub size: usize,
+}
+
+/// Storage type for uploaded files
+#[derive(Clone)]
 pub enum StorageType {
     /// Store files on disk
     Disk {
@@ -70,6 +134,26 @@ pub enum StorageType {
     },
     /// Keep files in memory
     Memory,
+    /// Upload files to an S3-compatible bucket
+    #[cfg(feature = "s3")]
+    S3(S3Config),
+    /// Write through a custom backend
+    Custom(Arc<dyn StorageBackend>),
+}
+
+impl std::fmt::Debug for StorageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageType::Disk { destination } => f
+                .debug_struct("Disk")
+                .field("destination", destination)
+                .finish(),
+            StorageType::Memory => write!(f, "Memory"),
+            #[cfg(feature = "s3")]
+            StorageType::S3(config) => f.debug_tuple("S3").field(config).finish(),
+            StorageType::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
 }
 
 impl Default for StorageType {
@@ -81,24 +165,32 @@ impl Default for StorageType {
 }
 
 /// File naming strategy
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum FileNaming {
     /// Keep original filename
     Original,
     /// Use UUID for filename
     Uuid,
     /// Use UUID with original extension
+    #[default]
     UuidWithExtension,
     /// Use timestamp with original extension
     TimestampWithExtension,
     /// Custom prefix with UUID
     CustomPrefix(String),
+    /// SHA-256 of the contents with original extension
+    ContentHash,
 }
 
-impl Default for FileNaming {
-    fn default() -> Self {
-        FileNaming::UuidWithExtension
-    }
+/// Which uploads share a deduplicated file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupScope {
+    /// Only files from the same owner; files without a known owner are
+    /// always stored
+    #[default]
+    Owner,
+    /// Every upload, whoever sent it
+    Global,
 }
 
 /// Upload configuration
@@ -120,6 +212,21 @@ pub struct UploadConfig {
     pub create_dir: bool,
     /// Preserve file extension
     pub preserve_extension: bool,
+    /// Check file contents (magic bytes) against the declared type and allowlist
+    pub verify_content: bool,
+    /// Limits enforced while parsing multipart bodies
+    pub limits: MultipartLimits,
+    /// Scanner run before files are stored (e.g. antivirus)
+    pub scan_hook: Option<ScanHook>,
+    /// Per-user byte quota for request-level uploads
+    pub quota: Option<UploadQuota>,
+    /// Reuse an already stored file with the same content hash
+    pub deduplicate: bool,
+    /// Which uploads share a deduplicated file
+    pub dedup_scope: DedupScope,
+    /// Image pipeline run on image uploads
+    #[cfg(feature = "image")]
+    pub image: Option<ImageOptions>,
 }
 
 impl Default for UploadConfig {
@@ -133,6 +240,14 @@ impl Default for UploadConfig {
             naming: FileNaming::default(),
             create_dir: true,
             preserve_extension: true,
+            verify_content: false,
+            limits: MultipartLimits::default(),
+            scan_hook: None,
+            quota: None,
+            deduplicate: false,
+            dedup_scope: DedupScope::Owner,
+            #[cfg(feature = "image")]
+            image: None,
         }
     }
 }
@@ -157,6 +272,19 @@ impl UploadConfig {
         self
     }
 
+    /// Write files through a custom storage backend
+    pub fn backend(mut self, backend: Arc<dyn StorageBackend>) -> Self {
+        self.storage = StorageType::Custom(backend);
+        self
+    }
+
+    /// Upload files to an S3-compatible bucket
+    #[cfg(feature = "s3")]
+    pub fn s3(mut self, config: S3Config) -> Self {
+        self.storage = StorageType::S3(config);
+        self
+    }
+
     /// Set maximum file size in bytes
     pub fn max_file_size(mut self, size: usize) -> Self {
         self.max_file_size = size;
@@ -249,6 +377,76 @@ impl UploadConfig {
         self
     }
 
+    /// Sniff file contents and reject files whose real type doesn't match
+    ///
+    /// The type detected from the file's magic bytes must match the
+    /// declared MIME type and extension, and is what `allowed_types` is
+    /// checked against. Formats without a signature (plain text, CSV, ...)
+    /// are accepted when their declared type has no signature either.
+    pub fn verify_content(mut self, verify: bool) -> Self {
+        self.verify_content = verify;
+        self
+    }
+
+    /// Set the limits enforced while parsing multipart bodies
+    pub fn limits(mut self, limits: MultipartLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Scan files before they are stored
+    ///
+    /// Files the scanner rejects fail with [`UploadError::Rejected`].
+    ///
+    /// ```rust,ignore
+    /// let config = UploadConfig::new().scan_hook(ClamAvScanner::tcp("127.0.0.1:3310"));
+    /// ```
+    pub fn scan_hook(mut self, scanner: impl FileScanner + 'static) -> Self {
+        self.scan_hook = Some(ScanHook(Arc::new(scanner)));
+        self
+    }
+
+    /// Store files under their content hash and reuse identical uploads
+    ///
+    /// Switches naming to [`FileNaming::ContentHash`]. When the same owner
+    /// already stored a file with the same hash, it is returned with
+    /// [`UploadedFile::deduplicated`] set instead of being written again.
+    /// The owner is the quota key or [`Principal`] of request-level uploads,
+    /// or the one set with [`Uploader::owned_by`]. Works with disk and S3
+    /// storage (as long as the key template only uses `{filename}`/`{ext}`)
+    /// and custom backends implementing [`StorageBackend::find`].
+    pub fn deduplicate(mut self) -> Self {
+        self.naming = FileNaming::ContentHash;
+        self.deduplicate = true;
+        self
+    }
+
+    /// Deduplicate across all owners
+    ///
+    /// Saves more space than [`deduplicate`](Self::deduplicate), but
+    /// [`UploadedFile::deduplicated`] then tells a user whether anyone else
+    /// uploaded the same file.
+    pub fn deduplicate_globally(mut self) -> Self {
+        self.dedup_scope = DedupScope::Global;
+        self.deduplicate()
+    }
+
+    /// Enforce a per-user byte quota
+    ///
+    /// Applies to the request-level methods (`single`, `array`, `any`,
+    /// `fields`), which know who is uploading.
+    pub fn quota(mut self, quota: UploadQuota) -> Self {
+        self.quota = Some(quota);
+        self
+    }
+
+    /// Process image uploads (resize, orient, thumbnails, WebP)
+    #[cfg(feature = "image")]
+    pub fn image(mut self, options: ImageOptions) -> Self {
+        self.image = Some(options);
+        self
+    }
+
     /// Use original filenames
     pub fn keep_original_name(mut self) -> Self {
         self.naming = FileNaming::Original;
@@ -281,6 +479,71 @@ pub enum UploadError {
     IoError(String),
     /// Parse error
     ParseError(String),
+    /// Remote storage error
+    StorageError(String),
+    /// File contents don't match the declared type
+    ContentMismatch { declared: String, detected: String },
+    /// File sent in a field that isn't accepted
+    UnexpectedField { field: String },
+    /// Upload quota exceeded
+    QuotaExceeded {
+        period: QuotaPeriod,
+        limit: u64,
+        used: u64,
+    },
+    /// File rejected by the scan hook
+    Rejected { reason: String },
+    /// Multipart body exceeds a parsing limit
+    LimitExceeded { limit: &'static str, max: usize },
+    /// Error for a specific form field
+    InField {
+        field: String,
+        error: Box<UploadError>,
+    },
+}
+
+impl UploadError {
+    /// Attach the offending field name to an error
+    pub fn in_field(field: &str, error: UploadError) -> Self {
+        match error {
+            UploadError::InField { .. } | UploadError::UnexpectedField { .. } => error,
+            error => UploadError::InField {
+                field: field.to_string(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// HTTP status code to respond with
+    ///
+    /// Size and total-quota errors map to 413, the daily quota to 429,
+    /// type errors to 415 and storage failures to 500.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            UploadError::FileTooLarge { .. } | UploadError::LimitExceeded { .. } => 413,
+            UploadError::QuotaExceeded {
+                period: QuotaPeriod::Daily,
+                ..
+            } => 429,
+            UploadError::QuotaExceeded { .. } => 413,
+            UploadError::TypeNotAllowed { .. }
+            | UploadError::ExtensionNotAllowed { .. }
+            | UploadError::ContentMismatch { .. } => 415,
+            UploadError::IoError(_) | UploadError::StorageError(_) => 500,
+            UploadError::InField { error, .. } => error.status_code(),
+            _ => 400,
+        }
+    }
+
+    /// Get the form field the error is about, if known
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            UploadError::FieldNotFound { field }
+            | UploadError::UnexpectedField { field }
+            | UploadError::InField { field, .. } => Some(field),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for UploadError {
@@ -304,22 +567,109 @@ impl std::fmt::Display for UploadError {
             }
             UploadError::IoError(msg) => write!(f, "IO error: {}", msg),
             UploadError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            UploadError::StorageError(msg) => write!(f, "Storage error: {}", msg),
+            UploadError::UnexpectedField { field } => write!(f, "Unexpected file field: {}", field),
+            UploadError::QuotaExceeded {
+                period,
+                limit,
+                used,
+            } => write!(
+                f,
+                "Upload quota exceeded: {} limit is {} bytes ({} used)",
+                period, limit, used
+            ),
+            UploadError::Rejected { reason } => write!(f, "File rejected: {}", reason),
+            UploadError::LimitExceeded { limit, max } => {
+                write!(f, "Multipart limit exceeded: {} (max: {})", limit, max)
+            }
+            UploadError::InField { field, error } => write!(f, "{}: {}", field, error),
+            UploadError::ContentMismatch { declared, detected } => write!(
+                f,
+                "File content does not match its type: declared {}, detected {}",
+                declared, detected
+            ),
         }
     }
 }
 
 impl std::error::Error for UploadError {}
 
+/// File ready to be stored, after optional image processing
+struct Prepared {
+    filename: String,
+    extension: String,
+    mimetype: String,
+    data: Vec<u8>,
+    thumbnails: Vec<PreparedVariant>,
+}
+
+impl Prepared {
+    fn unchanged(filename: String, extension: &str, mimetype: String, data: Vec<u8>) -> Self {
+        Self {
+            filename,
+            extension: extension.to_string(),
+            mimetype,
+            data,
+            thumbnails: Vec::new(),
+        }
+    }
+}
+
+/// Encoded image variant waiting to be stored
+struct PreparedVariant {
+    name: String,
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
 /// File uploader (similar to Multer)
 #[derive(Debug, Clone)]
 pub struct Uploader {
     config: UploadConfig,
+    /// Who deduplicated files are scoped to
+    owner: Option<String>,
 }
 
 impl Uploader {
     /// Create a new uploader with configuration
     pub fn new(config: UploadConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            owner: None,
+        }
+    }
+
+    /// Scope deduplication to `owner` (a user or tenant ID)
+    ///
+    /// Request-level methods pick the owner themselves; use this with
+    /// [`upload_single`](Self::upload_single) and friends.
+    pub fn owned_by(&self, owner: impl Into<String>) -> Self {
+        Self {
+            config: self.config.clone(),
+            owner: Some(owner.into()),
+        }
+    }
+
+    /// This uploader scoped to the owner of a request
+    pub(crate) fn for_request(&self, req: &Request) -> Self {
+        let owner = match &self.config.quota {
+            Some(quota) => quota.key(req),
+            None => req
+                .extensions()
+                .get::<Principal>()
+                .map(|principal| principal.id.clone()),
+        };
+        match owner {
+            Some(owner) => self.owned_by(owner),
+            None => self.clone(),
+        }
+    }
+
+    /// Whether stored files are looked up before writing
+    fn dedups(&self) -> bool {
+        self.config.deduplicate
+            && (self.config.dedup_scope == DedupScope::Global || self.owner.is_some())
     }
 
     /// Create a simple uploader for a destination directory
@@ -337,15 +687,64 @@ impl Uploader {
         &self.config
     }
 
+    /// Parse the multipart body of a request using the configured limits
+    pub fn parse(&self, req: &Request) -> Result<Vec<MultipartField>, UploadError> {
+        let content_type = req.content_type().unwrap_or("");
+        if !content_type.contains("multipart/form-data") {
+            return Err(UploadError::ParseError(
+                "Content-Type must be multipart/form-data".to_string(),
+            ));
+        }
+        let boundary = parse_boundary(content_type)
+            .ok_or_else(|| UploadError::ParseError("Missing multipart boundary".to_string()))?;
+
+        let declared = req
+            .header("content-length")
+            .and_then(|length| length.parse::<usize>().ok());
+        if declared.is_some_and(|length| length > self.config.limits.max_body_size) {
+            return Err(UploadError::LimitExceeded {
+                limit: "body size",
+                max: self.config.limits.max_body_size,
+            });
+        }
+
+        parse_multipart_with_limits(req.body(), &boundary, &self.config.limits)
+    }
+
+    /// Run the scan hook, if any
+    async fn scan(
+        &self,
+        field_name: &str,
+        original_name: &str,
+        mimetype: &str,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, UploadError> {
+        let Some(ScanHook(scanner)) = &self.config.scan_hook else {
+            return Ok(data);
+        };
+
+        let file = ScanFile {
+            original_name: original_name.to_string(),
+            field_name: field_name.to_string(),
+            mimetype: mimetype.to_string(),
+            data: Bytes::from(data),
+        };
+        match scanner.scan(&file).await? {
+            ScanVerdict::Clean => Ok(Vec::from(file.data)),
+            ScanVerdict::Rejected(reason) => Err(UploadError::Rejected { reason }),
+        }
+    }
+
     /// Generate filename based on naming strategy
-    fn generate_filename(&self, original: &str) -> String {
-        let extension = Path::new(original)
+    fn generate_filename(&self, original: &str, hash: &str) -> String {
+        let original = sanitize_filename(original);
+        let extension = Path::new(&original)
             .extension()
             .and_then(|e| e.to_str())
             .unwrap_or("");
 
         match &self.config.naming {
-            FileNaming::Original => original.to_string(),
+            FileNaming::Original => original.clone(),
             FileNaming::Uuid => Uuid::new_v4().to_string(),
             FileNaming::UuidWithExtension => {
                 if extension.is_empty() {
@@ -369,6 +768,13 @@ impl Uploader {
                     format!("{}_{}.{}", prefix, Uuid::new_v4(), extension)
                 }
             }
+            FileNaming::ContentHash => {
+                if extension.is_empty() {
+                    hash.to_string()
+                } else {
+                    format!("{}.{}", hash, extension.to_lowercase())
+                }
+            }
         }
     }
 
@@ -407,32 +813,279 @@ impl Uploader {
         Ok(())
     }
 
-    /// Save file to disk
-    async fn save_to_disk(
+    /// Check file contents against the declared type, extension and allowlist
+    fn verify_content(
         &self,
-        destination: &Path,
-        filename: &str,
+        declared: &str,
+        extension: &str,
         data: &[u8],
-    ) -> Result<PathBuf, UploadError> {
-        // Create directory if needed
-        if self.config.create_dir && !destination.exists() {
-            fs::create_dir_all(destination)
+    ) -> Result<(), UploadError> {
+        let declared = normalize_mime(declared);
+        let mismatch = |detected: &str| UploadError::ContentMismatch {
+            declared: declared.to_string(),
+            detected: detected.to_string(),
+        };
+
+        let Some(kind) = infer::get(data) else {
+            // No signature: fine for text formats, suspicious for binary ones
+            return if infer::is_mime_supported(declared) {
+                Err(mismatch("unknown"))
+            } else {
+                Ok(())
+            };
+        };
+        let detected = normalize_mime(kind.mime_type());
+
+        if detected != declared {
+            return Err(mismatch(detected));
+        }
+
+        let by_extension = normalize_mime(get_mime_type(extension));
+        if by_extension != "application/octet-stream" && by_extension != detected {
+            return Err(mismatch(detected));
+        }
+
+        if !self.config.allowed_types.is_empty()
+            && !self
+                .config
+                .allowed_types
+                .iter()
+                .any(|t| normalize_mime(t) == detected)
+        {
+            return Err(UploadError::TypeNotAllowed {
+                mimetype: detected.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Run the image pipeline on image uploads
+    #[cfg(feature = "image")]
+    async fn prepare(
+        &self,
+        filename: String,
+        mimetype: String,
+        extension: &str,
+        data: Vec<u8>,
+    ) -> Result<Prepared, UploadError> {
+        let options = match &self.config.image {
+            Some(options) if processing::is_processable(&mimetype) => options.clone(),
+            _ => return Ok(Prepared::unchanged(filename, extension, mimetype, data)),
+        };
+
+        let ext = extension.to_string();
+        let processed =
+            tokio::task::spawn_blocking(move || processing::process(&data, &ext, &options))
                 .await
-                .map_err(|e| UploadError::IoError(e.to_string()))?;
+                .map_err(|e| UploadError::IoError(e.to_string()))??;
+
+        Ok(Prepared {
+            filename: if processed.extension != extension {
+                with_extension(&filename, &processed.extension)
+            } else {
+                filename
+            },
+            extension: processed.extension,
+            mimetype: processed.mimetype,
+            data: processed.main.data,
+            thumbnails: processed
+                .thumbnails
+                .into_iter()
+                .map(|(name, image)| PreparedVariant {
+                    name,
+                    data: image.data,
+                    width: image.width,
+                    height: image.height,
+                })
+                .collect(),
+        })
+    }
+
+    /// Without the `image` feature files are stored as uploaded
+    #[cfg(not(feature = "image"))]
+    async fn prepare(
+        &self,
+        filename: String,
+        mimetype: String,
+        extension: &str,
+        data: Vec<u8>,
+    ) -> Result<Prepared, UploadError> {
+        Ok(Prepared::unchanged(filename, extension, mimetype, data))
+    }
+
+    /// Write a file through the configured storage backend
+    async fn store(
+        &self,
+        ctx: &StorageContext<'_>,
+        data: Bytes,
+    ) -> Result<StoredFile, UploadError> {
+        match &self.config.storage {
+            StorageType::Disk { destination } => {
+                DiskStorage::new(destination)
+                    .create_dir(self.config.create_dir)
+                    .store(ctx, data)
+                    .await
+            }
+            StorageType::Memory => MemoryStorage.store(ctx, data).await,
+            #[cfg(feature = "s3")]
+            StorageType::S3(config) => config.store(ctx, data).await,
+            StorageType::Custom(backend) => backend.store(ctx, data).await,
         }
+    }
 
-        let file_path = destination.join(filename);
+    /// Find an already stored file (for deduplication)
+    async fn find(&self, ctx: &StorageContext<'_>) -> Result<Option<StoredFile>, UploadError> {
+        match &self.config.storage {
+            StorageType::Disk { destination } => DiskStorage::new(destination).find(ctx).await,
+            StorageType::Memory => MemoryStorage.find(ctx).await,
+            #[cfg(feature = "s3")]
+            StorageType::S3(config) => config.find(ctx).await,
+            StorageType::Custom(backend) => backend.find(ctx).await,
+        }
+    }
 
-        // Write file
-        let mut file = fs::File::create(&file_path)
-            .await
-            .map_err(|e| UploadError::IoError(e.to_string()))?;
+    /// Store a file, or reuse the stored copy when deduplicating
+    ///
+    /// Lookup and write hold a lock on the name, so concurrent uploads of
+    /// the same file in this process store it once.
+    async fn store_or_find(
+        &self,
+        ctx: &StorageContext<'_>,
+        data: Bytes,
+    ) -> Result<(StoredFile, bool), UploadError> {
+        if !self.dedups() {
+            return Ok((self.store(ctx, data).await?, false));
+        }
 
-        file.write_all(data)
-            .await
-            .map_err(|e| UploadError::IoError(e.to_string()))?;
+        let _guard = dedup_lock(ctx.filename).lock().await;
+        if let Some(stored) = self.find(ctx).await? {
+            return Ok((stored, true));
+        }
+        Ok((self.store(ctx, data).await?, false))
+    }
+
+    /// Delete a previously uploaded file from its storage backend
+    pub async fn delete(&self, file: &UploadedFile) -> Result<(), UploadError> {
+        match &self.config.storage {
+            StorageType::Disk { destination } => DiskStorage::new(destination).delete(file).await,
+            StorageType::Memory => MemoryStorage.delete(file).await,
+            #[cfg(feature = "s3")]
+            StorageType::S3(config) => config.delete(file).await,
+            StorageType::Custom(backend) => backend.delete(file).await,
+        }
+    }
+
+    /// Create a pre-signed URL for uploading a file directly to S3
+    ///
+    /// The filename's extension is checked against the allowlists up front;
+    /// size, type and (with `verify_content`) contents are checked by
+    /// [`Uploader::confirm_upload`] once the client reports the upload done.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let presigned = uploader.presign_put("report.pdf", Duration::from_secs(600))?;
+    /// // client PUTs the file to presigned.url, then:
+    /// let file = uploader.confirm_upload(&presigned).await?;
+    /// ```
+    #[cfg(feature = "s3")]
+    pub fn presign_put(
+        &self,
+        filename: &str,
+        ttl: std::time::Duration,
+    ) -> Result<PresignedUpload, UploadError> {
+        let StorageType::S3(s3) = &self.config.storage else {
+            return Err(UploadError::StorageError(
+                "pre-signed uploads require S3 storage".to_string(),
+            ));
+        };
+
+        let original_name = sanitize_filename(filename);
+        let extension = Path::new(&original_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_string();
+        let mimetype = get_mime_type(&extension).to_string();
+        self.validate(&mimetype, &extension, 0)?;
+
+        // Contents aren't known yet, so content-hash naming gets a random id
+        let filename = self.generate_filename(&original_name, &Uuid::new_v4().simple().to_string());
+        let key = s3.render_key(&filename, &original_name, "", &extension);
+
+        Ok(PresignedUpload {
+            url: s3.presign_put(&key, ttl),
+            key,
+            filename,
+            original_name,
+            mimetype,
+            expires_at: chrono::Utc::now()
+                + chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::days(7)),
+        })
+    }
+
+    /// Verify a file uploaded through [`Uploader::presign_put`]
+    ///
+    /// Checks that the object exists and passes the size, type and content
+    /// rules; objects that fail are deleted from the bucket.
+    #[cfg(feature = "s3")]
+    pub async fn confirm_upload(
+        &self,
+        upload: &PresignedUpload,
+    ) -> Result<UploadedFile, UploadError> {
+        let StorageType::S3(s3) = &self.config.storage else {
+            return Err(UploadError::StorageError(
+                "pre-signed uploads require S3 storage".to_string(),
+            ));
+        };
+        let metadata = s3
+            .head_object(&upload.key)
+            .await?
+            .ok_or(UploadError::NoFile)?;
+
+        let extension = Path::new(&upload.filename)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_string();
+        let mimetype = metadata
+            .content_type
+            .map(|ct| normalize_mime(&ct).to_string())
+            .filter(|ct| ct != "application/octet-stream" && ct != "binary/octet-stream")
+            .unwrap_or_else(|| upload.mimetype.clone());
+
+        let mut check = self.validate(&mimetype, &extension, metadata.size);
+        if check.is_ok() && self.config.verify_content {
+            // Magic bytes sit in the first few KB
+            let head = s3.get_object_prefix(&upload.key, 8192).await?;
+            check = self.verify_content(&mimetype, &extension, &head);
+        }
+        if let Err(e) = check {
+            if let Err(delete_error) = s3.delete_object(&upload.key).await {
+                tracing::warn!(
+                    "Failed to delete rejected upload {}: {}",
+                    upload.key,
+                    delete_error
+                );
+            }
+            return Err(e);
+        }
 
-        Ok(file_path)
+        Ok(UploadedFile {
+            original_name: upload.original_name.clone(),
+            filename: upload.filename.clone(),
+            path: PathBuf::from(&upload.key),
+            mimetype,
+            size: metadata.size,
+            field_name: String::new(),
+            extension,
+            data: None,
+            url: Some(s3.object_url(&upload.key)),
+            variants: Vec::new(),
+            hash: None,
+            deduplicated: false,
+        })
     }
 
     /// Upload a single file from multipart form data
@@ -448,6 +1101,21 @@ impl Uploader {
         data: Vec<u8>,
         original_name: &str,
         mimetype: &str,
+    ) -> Result<UploadedFile, UploadError> {
+        let result = self
+            .store_single(field_name, data, original_name, mimetype)
+            .await;
+        let size = result.as_ref().map_or(0, |file| file.size);
+        crate::metrics::Metrics::global().record_upload(result.is_ok(), size);
+        result
+    }
+
+    async fn store_single(
+        &self,
+        field_name: &str,
+        data: Vec<u8>,
+        original_name: &str,
+        mimetype: &str,
     ) -> Result<UploadedFile, UploadError> {
         let extension = Path::new(original_name)
             .extension()
@@ -457,26 +1125,80 @@ impl Uploader {
 
         // Validate
         self.validate(mimetype, &extension, data.len())?;
-
-        // Generate filename
-        let filename = self.generate_filename(original_name);
+        if self.config.verify_content {
+            self.verify_content(mimetype, &extension, &data)?;
+        }
+        let data = self.scan(field_name, original_name, mimetype, data).await?;
+
+        // Generate filename and run the image pipeline; owner-scoped
+        // deduplication names files by owner and hash
+        let hash =
+            matches!(self.config.naming, FileNaming::ContentHash).then(|| content_hash(&data));
+        let name_hash = match (&hash, &self.owner, self.config.dedup_scope) {
+            (Some(hash), Some(owner), DedupScope::Owner) => {
+                content_hash(format!("{}\n{}", owner, hash).as_bytes())
+            }
+            (hash, _, _) => hash.clone().unwrap_or_default(),
+        };
+        let filename = self.generate_filename(original_name, &name_hash);
+        let Prepared {
+            filename,
+            extension,
+            mimetype,
+            data,
+            thumbnails,
+        } = self
+            .prepare(filename, mimetype.to_string(), &extension, data)
+            .await?;
 
         // Save file
-        let path = match &self.config.storage {
-            StorageType::Disk { destination } => {
-                self.save_to_disk(destination, &filename, &data).await?
-            }
-            StorageType::Memory => PathBuf::new(), // No path for memory storage
+        let size = data.len();
+        let ctx = StorageContext {
+            filename: &filename,
+            original_name,
+            field_name,
+            extension: &extension,
+            mimetype: &mimetype,
         };
+        let (stored, deduplicated) = self.store_or_find(&ctx, Bytes::from(data)).await?;
+        let filename = stored.filename.clone().unwrap_or_else(|| filename.clone());
+
+        let mut variants = Vec::new();
+        for thumbnail in thumbnails {
+            let variant_name = variant_filename(&filename, &thumbnail.name);
+            let ctx = StorageContext {
+                filename: &variant_name,
+                ..ctx.clone()
+            };
+            let size = thumbnail.data.len();
+            let (stored, _) = self
+                .store_or_find(&ctx, Bytes::from(thumbnail.data))
+                .await?;
+            variants.push(ImageVariant {
+                name: thumbnail.name,
+                filename: stored.filename.unwrap_or(variant_name),
+                path: stored.path,
+                url: stored.url,
+                mimetype: mimetype.clone(),
+                width: thumbnail.width,
+                height: thumbnail.height,
+                size,
+            });
+        }
 
         Ok(UploadedFile {
             original_name: original_name.to_string(),
             filename,
-            path,
-            mimetype: mimetype.to_string(),
-            size: data.len(),
+            path: stored.path,
+            mimetype,
+            size,
             field_name: field_name.to_string(),
             extension,
+            data: stored.data,
+            url: stored.url,
+            variants,
+            hash,
+            deduplicated,
         })
     }
 
@@ -510,6 +1232,116 @@ impl Uploader {
 
         Ok(uploaded)
     }
+
+    /// Parse a request and upload the single file in `field`
+    ///
+    /// Files sent in any other field are rejected, as with Multer's
+    /// `single()`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let file = uploader.single(&req, "avatar").await?;
+    /// ```
+    pub async fn single(&self, req: &Request, field: &str) -> Result<UploadedFile, UploadError> {
+        let mut uploaded = self.fields([(field, 1)]).upload(req).await?;
+        uploaded
+            .files
+            .remove(field)
+            .and_then(|files| files.into_iter().next())
+            .ok_or_else(|| UploadError::FieldNotFound {
+                field: field.to_string(),
+            })
+    }
+
+    /// Parse a request and upload up to `max_count` files from `field`
+    ///
+    /// Returns an empty list when the field is absent.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let photos = uploader.array(&req, "photos", 8).await?;
+    /// ```
+    pub async fn array(
+        &self,
+        req: &Request,
+        field: &str,
+        max_count: usize,
+    ) -> Result<Vec<UploadedFile>, UploadError> {
+        let mut uploaded = self.fields([(field, max_count)]).upload(req).await?;
+        Ok(uploaded.files.remove(field).unwrap_or_default())
+    }
+
+    /// Parse a request and upload every file, whatever its field
+    ///
+    /// The total is still capped by `max_files`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let files = uploader.any(&req).await?;
+    /// ```
+    pub async fn any(&self, req: &Request) -> Result<Vec<UploadedFile>, UploadError> {
+        let files = self
+            .parse(req)?
+            .into_iter()
+            .filter_map(|part| {
+                let filename = part.filename?;
+                let mimetype = part
+                    .content_type
+                    .unwrap_or_else(|| "application/octet-stream".to_string());
+                Some((part.name, part.data, filename, mimetype))
+            })
+            .collect::<Vec<_>>();
+
+        let bytes = files.iter().map(|(_, data, _, _)| data.len() as u64).sum();
+        let reservation = self.reserve_quota(req, bytes).await?;
+        let result = self.for_request(req).upload_multiple(files).await;
+        if let (Err(_), Some(reservation)) = (&result, reservation) {
+            reservation.release().await;
+        }
+        result
+    }
+
+    /// Reserve quota for a request's files, if a quota is configured
+    pub(crate) async fn reserve_quota(
+        &self,
+        req: &Request,
+        bytes: u64,
+    ) -> Result<Option<QuotaReservation>, UploadError> {
+        match &self.config.quota {
+            Some(quota) => quota.reserve(req, bytes).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Accept files from several fields, each with its own limits
+    ///
+    /// Files in fields not listed are rejected with
+    /// [`UploadError::UnexpectedField`]; other errors are wrapped in
+    /// [`UploadError::InField`] so the response can name the field.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let upload = uploader.fields(&[("avatar", 1), ("gallery", 8)]);
+    /// // or with per-field limits
+    /// let upload = uploader.fields([
+    ///     FieldSpec::new("avatar", 1).max_file_size(1024 * 1024).allowed_types(vec!["image/png"]),
+    ///     FieldSpec::new("gallery", 8),
+    /// ]);
+    ///
+    /// let uploaded = upload.upload(&req).await?;
+    /// let avatar = uploaded.first("avatar");
+    /// ```
+    pub fn fields<S, I>(&self, specs: I) -> FieldsUpload
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<FieldSpec>,
+    {
+        FieldsUpload::new(self.clone(), specs.into_iter().map(Into::into).collect())
+    }
 }
 
 impl Default for Uploader {
@@ -544,73 +1376,248 @@ pub struct MultipartField {
     pub data: Vec<u8>,
 }
 
-/// Parse multipart form data
+/// Limits enforced while parsing multipart bodies
 ///
-/// Note: This is a simplified parser. For production use,
-/// consider using a dedicated multipart parsing library.
-pub fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<MultipartField>, UploadError> {
-    let mut fields = Vec::new();
-    let boundary_bytes = format!("--{}", boundary);
-    let end_boundary = format!("--{}--", boundary);
+/// They bound how much work and memory a crafted body can cost before any
+/// file validation runs. File sizes are checked later against
+/// [`UploadConfig::max_file_size`].
+///
+/// Request bodies are read before handlers run, so `max_body_size` can only
+/// refuse a body once it is in memory (or up front when `Content-Length`
+/// declares it too large). To stop oversized uploads while they are read,
+/// give their router the same [`body_limit`](crate::Router::body_limit).
+#[derive(Debug, Clone)]
+pub struct MultipartLimits {
+    /// Maximum size of the whole body in bytes (default: 50MB)
+    pub max_body_size: usize,
+    /// Maximum number of parts, files included (default: 100)
+    pub max_parts: usize,
+    /// Maximum number of non-file fields (default: 50)
+    pub max_fields: usize,
+    /// Maximum size of a part's header block in bytes (default: 8KB)
+    pub max_header_size: usize,
+    /// Maximum length of a non-file field value in bytes (default: 1MB)
+    pub max_field_size: usize,
+}
 
-    // Convert to string for easier parsing
-    // Note: This may fail for binary data in some edge cases
-    let body_str = String::from_utf8_lossy(body);
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            max_body_size: 50 * 1024 * 1024,
+            max_parts: 100,
+            max_fields: 50,
+            max_header_size: 8 * 1024,
+            max_field_size: 1024 * 1024,
+        }
+    }
+}
 
-    for part in body_str.split(&boundary_bytes) {
-        let part = part.trim();
-        if part.is_empty() || part == "--" || part.starts_with(&end_boundary) {
-            continue;
+impl MultipartLimits {
+    /// Create default limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// No limits (used by [`parse_multipart`])
+    pub fn unlimited() -> Self {
+        Self {
+            max_body_size: usize::MAX,
+            max_parts: usize::MAX,
+            max_fields: usize::MAX,
+            max_header_size: usize::MAX,
+            max_field_size: usize::MAX,
         }
+    }
+
+    /// Set the maximum body size in bytes
+    pub fn max_body_size(mut self, bytes: usize) -> Self {
+        self.max_body_size = bytes;
+        self
+    }
+
+    /// Set the maximum number of parts
+    pub fn max_parts(mut self, count: usize) -> Self {
+        self.max_parts = count;
+        self
+    }
+
+    /// Set the maximum number of non-file fields
+    pub fn max_fields(mut self, count: usize) -> Self {
+        self.max_fields = count;
+        self
+    }
+
+    /// Set the maximum header block size per part
+    pub fn max_header_size(mut self, bytes: usize) -> Self {
+        self.max_header_size = bytes;
+        self
+    }
+
+    /// Set the maximum non-file field value length
+    pub fn max_field_size(mut self, bytes: usize) -> Self {
+        self.max_field_size = bytes;
+        self
+    }
+}
+
+/// Parse multipart form data
+///
+/// Note: This is a simplified parser without limits. Request handlers
+/// should prefer [`Uploader::parse`], which applies [`MultipartLimits`].
+pub fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<MultipartField>, UploadError> {
+    parse_multipart_with_limits(body, boundary, &MultipartLimits::unlimited())
+}
 
-        // Split headers and content
-        if let Some(header_end) = part.find("\r\n\r\n") {
-            let headers = &part[..header_end];
-            let content = &part[header_end + 4..];
+/// Parse multipart form data, enforcing limits as parts are read
+pub fn parse_multipart_with_limits(
+    body: &[u8],
+    boundary: &str,
+    limits: &MultipartLimits,
+) -> Result<Vec<MultipartField>, UploadError> {
+    if body.len() > limits.max_body_size {
+        return Err(UploadError::LimitExceeded {
+            limit: "body size",
+            max: limits.max_body_size,
+        });
+    }
 
-            // Remove trailing CRLF
-            let content = content.trim_end_matches("\r\n");
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let separator = [b"\r\n".as_slice(), &delimiter].concat();
+    let mut fields = Vec::new();
+    let mut parts = 0;
+    let mut text_fields = 0;
+
+    let Some(first) = find_bytes(body, &delimiter, 0) else {
+        return Ok(fields);
+    };
+    let mut pos = first + delimiter.len();
+
+    // Each part: CRLF headers CRLF CRLF content CRLF --boundary
+    while !body[pos..].starts_with(b"--") {
+        let start = pos
+            + if body[pos..].starts_with(b"\r\n") {
+                2
+            } else {
+                0
+            };
+        let end = find_bytes(body, &separator, start).ok_or_else(|| {
+            UploadError::ParseError("Missing closing multipart boundary".to_string())
+        })?;
+        let part = &body[start..end];
+        pos = end + separator.len();
+
+        parts += 1;
+        if parts > limits.max_parts {
+            return Err(UploadError::LimitExceeded {
+                limit: "parts",
+                max: limits.max_parts,
+            });
+        }
 
-            // Parse Content-Disposition header
-            let mut name = String::new();
-            let mut filename = None;
-            let mut content_type = None;
+        let header_end = find_bytes(part, b"\r\n\r\n", 0);
+        if header_end.unwrap_or(part.len()) > limits.max_header_size {
+            return Err(UploadError::LimitExceeded {
+                limit: "part header size",
+                max: limits.max_header_size,
+            });
+        }
+        let Some(header_end) = header_end else {
+            continue;
+        };
 
-            for line in headers.lines() {
-                if line.to_lowercase().starts_with("content-disposition:") {
-                    // Parse name
-                    if let Some(name_start) = line.find("name=\"") {
-                        let rest = &line[name_start + 6..];
-                        if let Some(name_end) = rest.find('"') {
-                            name = rest[..name_end].to_string();
-                        }
+        let headers = String::from_utf8_lossy(&part[..header_end]);
+        let content = &part[header_end + 4..];
+
+        // Parse Content-Disposition header
+        let mut name = String::new();
+        let mut filename = None;
+        let mut content_type = None;
+
+        for line in headers.lines() {
+            if line.to_lowercase().starts_with("content-disposition:") {
+                // Parse name
+                if let Some(name_start) = line.find(" name=\"").or_else(|| line.find(";name=\"")) {
+                    let rest = &line[name_start + 7..];
+                    if let Some(name_end) = rest.find('"') {
+                        name = rest[..name_end].to_string();
                     }
-                    // Parse filename
-                    if let Some(fname_start) = line.find("filename=\"") {
-                        let rest = &line[fname_start + 10..];
-                        if let Some(fname_end) = rest.find('"') {
-                            filename = Some(rest[..fname_end].to_string());
-                        }
+                }
+                // Parse filename
+                if let Some(fname_start) = line.find("filename=\"") {
+                    let rest = &line[fname_start + 10..];
+                    if let Some(fname_end) = rest.find('"') {
+                        filename = Some(rest[..fname_end].to_string());
                     }
-                } else if line.to_lowercase().starts_with("content-type:") {
-                    content_type = Some(line[13..].trim().to_string());
                 }
+            } else if line.to_lowercase().starts_with("content-type:") {
+                content_type = Some(line[13..].trim().to_string());
             }
+        }
 
-            if !name.is_empty() {
-                fields.push(MultipartField {
-                    name,
-                    filename,
-                    content_type,
-                    data: content.as_bytes().to_vec(),
+        if name.is_empty() {
+            continue;
+        }
+
+        if filename.is_none() {
+            text_fields += 1;
+            if text_fields > limits.max_fields {
+                return Err(UploadError::LimitExceeded {
+                    limit: "fields",
+                    max: limits.max_fields,
                 });
             }
+            if content.len() > limits.max_field_size {
+                return Err(UploadError::in_field(
+                    &name,
+                    UploadError::LimitExceeded {
+                        limit: "field size",
+                        max: limits.max_field_size,
+                    },
+                ));
+            }
         }
+
+        fields.push(MultipartField {
+            name,
+            filename,
+            content_type,
+            data: content.to_vec(),
+        });
     }
 
     Ok(fields)
 }
 
+/// Locks serializing deduplicated stores, striped by file name
+static DEDUP_LOCKS: Lazy<Vec<tokio::sync::Mutex<()>>> =
+    Lazy::new(|| (0..64).map(|_| tokio::sync::Mutex::new(())).collect());
+
+/// The lock guarding stores under `name`
+fn dedup_lock(name: &str) -> &'static tokio::sync::Mutex<()> {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    name.hash(&mut hasher);
+    &DEDUP_LOCKS[hasher.finish() as usize % DEDUP_LOCKS.len()]
+}
+
+/// Hex-encoded SHA-256 of file contents
+fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Find the first occurrence of `needle` in `haystack` at or after `from`
+fn find_bytes(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from > haystack.len() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| i + from)
+}
+
 /// Get MIME type from file extension
 pub fn get_mime_type(extension: &str) -> &'static str {
     match extension.to_lowercase().as_str() {
@@ -662,6 +1669,95 @@ pub fn get_mime_type(extension: &str) -> &'static str {
     }
 }
 
+/// Make a client-supplied filename safe to write to disk
+///
+/// Drops any directory components, control and reserved characters and
+/// leading dots, avoids Windows device names and caps the length at 255
+/// bytes. Returns `file` when nothing usable is left.
+///
+/// # Example
+///
+/// ```rust
+/// use rustyx::upload::sanitize_filename;
+///
+/// assert_eq!(sanitize_filename("../../etc/cron.d/x"), "x");
+/// assert_eq!(sanitize_filename("..\\boot.ini"), "boot.ini");
+/// assert_eq!(sanitize_filename(".htaccess"), "htaccess");
+/// ```
+pub fn sanitize_filename(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+    let mut cleaned = cleaned
+        .trim_start_matches(['.', ' '])
+        .trim_end_matches(['.', ' '])
+        .to_string();
+
+    let stem = cleaned.split('.').next().unwrap_or("").to_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit());
+    if reserved {
+        cleaned.insert(0, '_');
+    }
+
+    // Cap the length, keeping the extension and a valid UTF-8 boundary
+    if cleaned.len() > 255 {
+        let ext = Path::new(&cleaned)
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|e| e.len() < 16)
+            .map(|e| format!(".{}", e))
+            .unwrap_or_default();
+        let mut end = 255 - ext.len();
+        while !cleaned.is_char_boundary(end) {
+            end -= 1;
+        }
+        cleaned = format!("{}{}", &cleaned[..end], ext);
+    }
+
+    if cleaned.is_empty() {
+        "file".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Replace (or add) the extension of a filename
+#[cfg(feature = "image")]
+fn with_extension(filename: &str, extension: &str) -> String {
+    match filename.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => format!("{}.{}", stem, extension),
+        _ => format!("{}.{}", filename, extension),
+    }
+}
+
+/// Sibling filename for a variant: `photo.png` -> `photo_small.png`
+fn variant_filename(filename: &str, variant: &str) -> String {
+    match filename.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}_{}.{}", stem, variant, ext),
+        _ => format!("{}_{}", filename, variant),
+    }
+}
+
+/// Normalize MIME type aliases and strip parameters
+fn normalize_mime(mimetype: &str) -> &str {
+    match mimetype.split(';').next().unwrap_or("").trim() {
+        "image/jpg" | "image/pjpeg" => "image/jpeg",
+        "application/x-zip-compressed" => "application/zip",
+        "application/x-gzip" => "application/gzip",
+        other => other,
+    }
+}
+
 /// Check if MIME type is an image
 pub fn is_image(mimetype: &str) -> bool {
     mimetype.starts_with("image/")
@@ -723,6 +1819,351 @@ mod tests {
         assert!(uploader.validate("image/jpeg", "jpg", 500).is_err());
     }
 
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("photo.png"), "photo.png");
+        assert_eq!(sanitize_filename("/var/www/../../x.sh"), "x.sh");
+        assert_eq!(sanitize_filename("a<b>:c?.txt"), "a_b__c_.txt");
+        assert_eq!(sanitize_filename("evil\0name\r\n.txt"), "evilname.txt");
+        assert_eq!(sanitize_filename("CON.txt"), "_CON.txt");
+        assert_eq!(sanitize_filename("com1"), "_com1");
+        assert_eq!(sanitize_filename(".."), "file");
+        assert_eq!(sanitize_filename(""), "file");
+
+        let long = format!("{}.pdf", "é".repeat(200));
+        let sanitized = sanitize_filename(&long);
+        assert!(sanitized.len() <= 255);
+        assert!(sanitized.ends_with(".pdf"));
+    }
+
+    #[test]
+    fn test_verify_content() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let uploader = Uploader::new(
+            UploadConfig::new()
+                .allowed_types(vec!["image/png"])
+                .verify_content(true),
+        );
+
+        assert!(uploader.verify_content("image/png", "png", png).is_ok());
+        // Renamed executable posing as an image
+        assert!(matches!(
+            uploader.verify_content("image/png", "png", b"MZ\x90\0\x03\0\0\0"),
+            Err(UploadError::ContentMismatch { .. })
+        ));
+        // PNG bytes declared as JPEG
+        assert!(uploader.verify_content("image/jpeg", "jpg", png).is_err());
+        // Plain text has no signature and is accepted
+        assert!(Uploader::memory()
+            .verify_content("text/plain", "txt", b"hello")
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_memory_upload_keeps_data() {
+        let uploader = Uploader::memory();
+        let file = uploader
+            .upload_single("file", b"hello".to_vec(), "hello.txt", "text/plain")
+            .await
+            .unwrap();
+
+        assert_eq!(file.size, 5);
+        assert_eq!(file.data.as_deref(), Some(&b"hello"[..]));
+        assert_eq!(file.path, PathBuf::new());
+    }
+
+    #[tokio::test]
+    async fn test_content_hash_deduplication() {
+        let dir = std::env::temp_dir().join(format!("rustyx-dedup-{}", Uuid::new_v4()));
+        let uploader = Uploader::new(
+            UploadConfig::new()
+                .destination(dir.to_str().unwrap())
+                .deduplicate(),
+        );
+        let alice = uploader.owned_by("alice");
+
+        let first = alice
+            .upload_single("file", b"same".to_vec(), "a.TXT", "text/plain")
+            .await
+            .unwrap();
+        let second = alice
+            .upload_single("file", b"same".to_vec(), "b.txt", "text/plain")
+            .await
+            .unwrap();
+
+        assert!(!first.deduplicated);
+        assert!(second.deduplicated);
+        assert_eq!(first.path, second.path);
+        assert_eq!(second.original_name, "b.txt");
+        assert_eq!(first.hash, second.hash);
+
+        // Other owners, and uploads without one, never see alice's copy
+        let bob = uploader
+            .owned_by("bob")
+            .upload_single("file", b"same".to_vec(), "c.txt", "text/plain")
+            .await
+            .unwrap();
+        let anonymous = uploader
+            .upload_single("file", b"same".to_vec(), "d.txt", "text/plain")
+            .await
+            .unwrap();
+        assert!(!bob.deduplicated);
+        assert!(!anonymous.deduplicated);
+        assert_ne!(bob.path, first.path);
+        assert_ne!(anonymous.path, first.path);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_global_deduplication_is_race_free() {
+        let dir = std::env::temp_dir().join(format!("rustyx-dedup-{}", Uuid::new_v4()));
+        let uploader = Uploader::new(
+            UploadConfig::new()
+                .destination(dir.to_str().unwrap())
+                .deduplicate_globally(),
+        );
+
+        let uploads = (0..8).map(|i| {
+            let uploader = uploader.clone();
+            tokio::spawn(async move {
+                uploader
+                    .upload_single("file", vec![7; 4096], &format!("{}.bin", i), "text/plain")
+                    .await
+                    .unwrap()
+            })
+        });
+        let files = futures::future::join_all(uploads).await;
+        let files: Vec<UploadedFile> = files.into_iter().map(Result::unwrap).collect();
+
+        assert_eq!(files.iter().filter(|f| !f.deduplicated).count(), 1);
+        assert!(files.iter().all(|f| f.path == files[0].path));
+        assert!(files[0]
+            .filename
+            .starts_with(files[0].hash.as_deref().unwrap()));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_multipart_limits() {
+        let body = b"--XyZ\r\n\
+Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+Holiday\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"photo\"; filename=\"a.bin\"\r\n\
+Content-Type: application/octet-stream\r\n\r\n\
+\xff\x00\xfe\r\n\r\n\
+--XyZ--\r\n";
+
+        let fields = parse_multipart(body, "XyZ").unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].data, b"Holiday");
+        assert_eq!(fields[1].filename.as_deref(), Some("a.bin"));
+        assert_eq!(fields[1].data, b"\xff\x00\xfe\r\n");
+
+        let limited = |limits: MultipartLimits| parse_multipart_with_limits(body, "XyZ", &limits);
+        assert!(limited(MultipartLimits::new().max_body_size(32)).is_err());
+        assert!(limited(MultipartLimits::new().max_parts(1)).is_err());
+        assert!(limited(MultipartLimits::new().max_fields(0)).is_err());
+        assert!(limited(MultipartLimits::new().max_header_size(16)).is_err());
+        let err = limited(MultipartLimits::new().max_field_size(3)).unwrap_err();
+        assert_eq!(err.field(), Some("title"));
+    }
+
+    /// Backend keeping objects in a shared map, addressed by `mem://` URLs
+    #[derive(Default)]
+    struct MapStorage {
+        objects: parking_lot::Mutex<std::collections::HashMap<String, Bytes>>,
+    }
+
+    #[async_trait::async_trait]
+    impl StorageBackend for MapStorage {
+        async fn store(
+            &self,
+            ctx: &StorageContext<'_>,
+            data: Bytes,
+        ) -> Result<StoredFile, UploadError> {
+            self.objects.lock().insert(ctx.filename.to_string(), data);
+            Ok(StoredFile {
+                path: ctx.filename.into(),
+                url: Some(format!("mem://{}", ctx.filename)),
+                ..Default::default()
+            })
+        }
+
+        async fn delete(&self, file: &UploadedFile) -> Result<(), UploadError> {
+            self.objects.lock().remove(&file.filename);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_storage_backend() {
+        let backend = Arc::new(MapStorage::default());
+        let uploader = Uploader::new(UploadConfig::new().backend(backend.clone()));
+
+        let file = uploader
+            .upload_single("doc", b"contents".to_vec(), "notes.txt", "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(
+            file.url.as_deref(),
+            Some(format!("mem://{}", file.filename).as_str())
+        );
+        assert_eq!(file.path, PathBuf::from(&file.filename));
+        assert_eq!(
+            backend.objects.lock().get(&file.filename).map(|b| &b[..]),
+            Some(&b"contents"[..])
+        );
+
+        uploader.delete(&file).await.unwrap();
+        assert!(backend.objects.lock().is_empty());
+    }
+
+    /// Build a multipart request from `(field, filename, data)` parts
+    fn build_multipart_body(parts: &[(&str, Option<&str>, &[u8])]) -> Request {
+        let mut body = Vec::new();
+        for (name, filename, data) in parts {
+            body.extend_from_slice(b"--XyZ\r\n");
+            match filename {
+                Some(filename) => body.extend_from_slice(
+                    format!(
+                        "Content-Disposition: form-data; name=\"{name}\"; filename=\"{filename}\"\r\n\
+                         Content-Type: text/plain\r\n\r\n"
+                    )
+                    .as_bytes(),
+                ),
+                None => body.extend_from_slice(
+                    format!("Content-Disposition: form-data; name=\"{name}\"\r\n\r\n").as_bytes(),
+                ),
+            }
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--XyZ--\r\n");
+
+        Request::builder()
+            .method(hyper::Method::POST)
+            .header("content-type", "multipart/form-data; boundary=XyZ")
+            .body(body)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_single_upload() {
+        let uploader = Uploader::memory();
+
+        let req = build_multipart_body(&[("avatar", Some("a.txt"), b"hello")]);
+        let file = uploader.single(&req, "avatar").await.unwrap();
+        assert_eq!(file.data.as_deref(), Some(&b"hello"[..]));
+
+        let req = build_multipart_body(&[("title", None, b"Holiday")]);
+        let err = uploader.single(&req, "avatar").await.unwrap_err();
+        assert!(matches!(err, UploadError::FieldNotFound { ref field } if field == "avatar"));
+
+        let req = build_multipart_body(&[("other", Some("a.txt"), b"hello")]);
+        let err = uploader.single(&req, "avatar").await.unwrap_err();
+        assert!(matches!(err, UploadError::UnexpectedField { ref field } if field == "other"));
+    }
+
+    #[tokio::test]
+    async fn test_array_upload() {
+        let uploader = Uploader::memory();
+
+        let req = build_multipart_body(&[
+            ("photos", Some("a.txt"), b"a"),
+            ("photos", Some("b.txt"), b"b"),
+        ]);
+        assert_eq!(uploader.array(&req, "photos", 2).await.unwrap().len(), 2);
+        let err = uploader.array(&req, "photos", 1).await.unwrap_err();
+        assert!(matches!(
+            err,
+            UploadError::InField { ref field, ref error }
+                if field == "photos"
+                    && matches!(**error, UploadError::TooManyFiles { max: 1, actual: 2 })
+        ));
+
+        let req = build_multipart_body(&[("title", None, b"Holiday")]);
+        assert!(uploader.array(&req, "photos", 2).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_any_upload_limits_and_quota() {
+        let store = MemoryQuotaStore::new();
+        let quota = UploadQuota::new(|_| Some("user".to_string()))
+            .total_bytes(1000)
+            .store(store.clone());
+        let uploader = Uploader::new(UploadConfig::new().memory().max_files(1).quota(quota));
+
+        let req = build_multipart_body(&[("a", Some("a.txt"), b"hello")]);
+        assert_eq!(uploader.any(&req).await.unwrap().len(), 1);
+        assert_eq!(store.usage("user").await.unwrap().total_bytes, 5);
+
+        let req = build_multipart_body(&[
+            ("a", Some("a.txt"), b"hello"),
+            ("b", Some("b.txt"), b"world"),
+        ]);
+        let err = uploader.any(&req).await.unwrap_err();
+        assert!(matches!(
+            err,
+            UploadError::TooManyFiles { max: 1, actual: 2 }
+        ));
+        assert_eq!(store.usage("user").await.unwrap().total_bytes, 5);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_refused_while_read() {
+        use http_body_util::{Full, StreamBody};
+        use hyper::body::Frame;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let app = crate::RustyX::new();
+        let mut uploads = crate::Router::new();
+        uploads.body_limit(8 * 1024);
+        uploads.post("/", |_req, res| async move { res.send("stored") });
+        app.use_router("/uploads", uploads);
+        let addr = std::net::SocketAddr::from(([127, 0, 0, 1], 9000));
+
+        // An endless chunked body without a Content-Length
+        let read = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = std::sync::Arc::clone(&read);
+        let chunks = futures::stream::repeat_with(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, std::io::Error>(Frame::data(Bytes::from(vec![b'x'; 1024])))
+        });
+        let req = hyper::Request::post("/uploads")
+            .header("content-type", "multipart/form-data; boundary=XyZ")
+            .body(StreamBody::new(chunks))
+            .unwrap();
+        let res = app.handle_request(req, addr).await;
+        assert_eq!(res.status(), 413);
+        assert!(read.load(Ordering::SeqCst) <= 9);
+
+        let req = hyper::Request::post("/uploads")
+            .body(Full::new(Bytes::from_static(b"small")))
+            .unwrap();
+        assert_eq!(app.handle_request(req, addr).await.status(), 200);
+
+        let req = Request::builder()
+            .header("content-type", "multipart/form-data; boundary=XyZ")
+            .header("content-length", "1000000000")
+            .build();
+        let uploader = Uploader::new(
+            UploadConfig::new()
+                .memory()
+                .limits(MultipartLimits::new().max_body_size(1024)),
+        );
+        assert!(matches!(
+            uploader.parse(&req),
+            Err(UploadError::LimitExceeded {
+                limit: "body size",
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn test_get_mime_type() {
         assert_eq!(get_mime_type("png"), "image/png");
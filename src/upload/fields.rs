@@ -0,0 +1,276 @@
+//! Per-field Uploads
+//!
+//! Multer-style `fields()`: accept files from several form fields, each
+//! with its own count, size and type limits.
+
+use super::{MultipartField, UploadError, UploadedFile, Uploader};
+use crate::request::Request;
+use std::collections::HashMap;
+
+/// Limits for one form field
+#[derive(Debug, Clone)]
+pub struct FieldSpec {
+    /// Field name
+    pub name: String,
+    /// Maximum number of files in this field
+    pub max_count: usize,
+    /// Maximum file size (defaults to the uploader's limit)
+    pub max_file_size: Option<usize>,
+    /// Allowed MIME types (defaults to the uploader's list)
+    pub allowed_types: Option<Vec<String>>,
+    /// Allowed extensions (defaults to the uploader's list)
+    pub allowed_extensions: Option<Vec<String>>,
+}
+
+impl FieldSpec {
+    /// Create a spec allowing up to `max_count` files
+    pub fn new(name: &str, max_count: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            max_count,
+            max_file_size: None,
+            allowed_types: None,
+            allowed_extensions: None,
+        }
+    }
+
+    /// Set the maximum file size for this field
+    pub fn max_file_size(mut self, size: usize) -> Self {
+        self.max_file_size = Some(size);
+        self
+    }
+
+    /// Set allowed MIME types for this field
+    pub fn allowed_types(mut self, types: Vec<&str>) -> Self {
+        self.allowed_types = Some(types.iter().map(|s| s.to_string()).collect());
+        self
+    }
+
+    /// Set allowed extensions for this field
+    pub fn allowed_extensions(mut self, extensions: Vec<&str>) -> Self {
+        self.allowed_extensions = Some(extensions.iter().map(|s| s.to_lowercase()).collect());
+        self
+    }
+}
+
+impl From<(&str, usize)> for FieldSpec {
+    fn from((name, max_count): (&str, usize)) -> Self {
+        Self::new(name, max_count)
+    }
+}
+
+impl From<&(&str, usize)> for FieldSpec {
+    fn from(spec: &(&str, usize)) -> Self {
+        Self::new(spec.0, spec.1)
+    }
+}
+
+impl From<&FieldSpec> for FieldSpec {
+    fn from(spec: &FieldSpec) -> Self {
+        spec.clone()
+    }
+}
+
+/// Files and text fields from a multipart request
+#[derive(Debug, Clone, Default)]
+pub struct UploadedFields {
+    /// Uploaded files by field name
+    pub files: HashMap<String, Vec<UploadedFile>>,
+    /// Non-file form fields
+    pub fields: HashMap<String, String>,
+}
+
+impl UploadedFields {
+    /// Get the files uploaded in a field
+    pub fn get(&self, field: &str) -> &[UploadedFile] {
+        self.files.get(field).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Get the first file uploaded in a field
+    pub fn first(&self, field: &str) -> Option<&UploadedFile> {
+        self.get(field).first()
+    }
+
+    /// Get a text field
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+/// Upload configured by [`Uploader::fields`]
+#[derive(Debug, Clone)]
+pub struct FieldsUpload {
+    uploader: Uploader,
+    specs: Vec<FieldSpec>,
+}
+
+impl FieldsUpload {
+    pub(crate) fn new(uploader: Uploader, specs: Vec<FieldSpec>) -> Self {
+        Self { uploader, specs }
+    }
+
+    /// Parse, validate and store the files in a request
+    pub async fn upload(&self, req: &Request) -> Result<UploadedFields, UploadError> {
+        let parts = self.uploader.parse(req)?;
+        let bytes = parts
+            .iter()
+            .filter(|part| part.filename.is_some())
+            .map(|part| part.data.len() as u64)
+            .sum();
+
+        let reservation = self.uploader.reserve_quota(req, bytes).await?;
+        let scoped = Self::new(self.uploader.for_request(req), self.specs.clone());
+        let result = scoped.upload_parts(parts).await;
+        if let (Err(_), Some(reservation)) = (&result, reservation) {
+            reservation.release().await;
+        }
+        result
+    }
+
+    /// Validate and store already-parsed multipart fields
+    ///
+    /// Counts and unexpected fields are checked before anything is stored;
+    /// if storing a later file fails, files stored so far are deleted.
+    pub async fn upload_parts(
+        &self,
+        parts: Vec<MultipartField>,
+    ) -> Result<UploadedFields, UploadError> {
+        let mut result = UploadedFields::default();
+        let mut files: Vec<(&FieldSpec, MultipartField, String)> = Vec::new();
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+
+        for part in parts {
+            let Some(filename) = part.filename.clone() else {
+                result.fields.insert(
+                    part.name.clone(),
+                    String::from_utf8_lossy(&part.data).into_owned(),
+                );
+                continue;
+            };
+            let Some(spec) = self.specs.iter().find(|s| s.name == part.name) else {
+                return Err(UploadError::UnexpectedField { field: part.name });
+            };
+
+            let count = counts.entry(spec.name.as_str()).or_default();
+            *count += 1;
+            if *count > spec.max_count {
+                return Err(UploadError::in_field(
+                    &spec.name,
+                    UploadError::TooManyFiles {
+                        max: spec.max_count,
+                        actual: *count,
+                    },
+                ));
+            }
+
+            files.push((spec, part, filename));
+        }
+
+        let total: usize = counts.values().sum();
+        if total > self.uploader.config.max_files {
+            return Err(UploadError::TooManyFiles {
+                max: self.uploader.config.max_files,
+                actual: total,
+            });
+        }
+
+        for (spec, part, filename) in files {
+            let uploader = self.field_uploader(spec);
+            let mimetype = part
+                .content_type
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+
+            match uploader
+                .upload_single(&part.name, part.data, &filename, &mimetype)
+                .await
+            {
+                Ok(file) => result.files.entry(part.name).or_default().push(file),
+                Err(e) => {
+                    let stored = result.files.values().flatten();
+                    for stored in stored.filter(|file| !file.deduplicated) {
+                        let _ = self.uploader.delete(stored).await;
+                    }
+                    return Err(UploadError::in_field(&spec.name, e));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Uploader with the field's overrides applied
+    fn field_uploader(&self, spec: &FieldSpec) -> Uploader {
+        let mut config = self.uploader.config.clone();
+        if let Some(size) = spec.max_file_size {
+            config.max_file_size = size;
+        }
+        if let Some(types) = &spec.allowed_types {
+            config.allowed_types = types.clone();
+        }
+        if let Some(extensions) = &spec.allowed_extensions {
+            config.allowed_extensions = extensions.clone();
+        }
+        Uploader {
+            config,
+            owner: self.uploader.owner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upload::UploadConfig;
+
+    fn part(name: &str, filename: Option<&str>, data: &[u8]) -> MultipartField {
+        MultipartField {
+            name: name.to_string(),
+            filename: filename.map(str::to_string),
+            content_type: Some("image/png".to_string()),
+            data: data.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fields_limits() {
+        let uploader = Uploader::memory();
+        let upload = uploader.fields([
+            FieldSpec::new("avatar", 1).max_file_size(4),
+            FieldSpec::new("gallery", 2),
+        ]);
+
+        let ok = upload
+            .upload_parts(vec![
+                part("avatar", Some("a.png"), b"abc"),
+                part("gallery", Some("b.png"), b"abcdef"),
+                part("title", None, b"Holiday"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(ok.get("gallery").len(), 1);
+        assert_eq!(ok.first("avatar").unwrap().size, 3);
+        assert_eq!(ok.field("title"), Some("Holiday"));
+
+        let too_big = upload
+            .upload_parts(vec![part("avatar", Some("a.png"), b"abcdef")])
+            .await
+            .unwrap_err();
+        assert_eq!(too_big.field(), Some("avatar"));
+
+        let unexpected = upload
+            .upload_parts(vec![part("other", Some("x.png"), b"x")])
+            .await
+            .unwrap_err();
+        assert_eq!(unexpected.field(), Some("other"));
+
+        let too_many = Uploader::new(UploadConfig::new().memory())
+            .fields([("avatar", 1)])
+            .upload_parts(vec![
+                part("avatar", Some("a.png"), b"a"),
+                part("avatar", Some("b.png"), b"b"),
+            ])
+            .await
+            .unwrap_err();
+        assert!(too_many.to_string().starts_with("avatar: Too many files"));
+    }
+}
@@ -0,0 +1,223 @@
+//! Image Processing
+//!
+//! Post-upload pipeline for images: downscale to maximum dimensions,
+//! apply EXIF orientation, generate thumbnails and convert to WebP.
+//! Requires the `image` feature.
+
+use super::UploadError;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::io::Cursor;
+
+/// Thumbnail size, stored as a sibling file named `<stem>_<name>.<ext>`
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Image pipeline options
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// Downscale images wider than this
+    pub max_width: Option<u32>,
+    /// Downscale images taller than this
+    pub max_height: Option<u32>,
+    /// Rotate/flip according to EXIF orientation
+    pub auto_orient: bool,
+    /// Thumbnails to generate (fit within the box, aspect ratio kept)
+    pub thumbnails: Vec<Thumbnail>,
+    /// Re-encode the image and its thumbnails as (lossless) WebP
+    pub convert_to_webp: bool,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            max_height: None,
+            auto_orient: true,
+            thumbnails: Vec::new(),
+            convert_to_webp: false,
+        }
+    }
+}
+
+impl ImageOptions {
+    /// Create default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum dimensions
+    pub fn max_dimensions(mut self, width: u32, height: u32) -> Self {
+        self.max_width = Some(width);
+        self.max_height = Some(height);
+        self
+    }
+
+    /// Enable or disable EXIF auto-orientation
+    pub fn auto_orient(mut self, enabled: bool) -> Self {
+        self.auto_orient = enabled;
+        self
+    }
+
+    /// Add a thumbnail size
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let options = ImageOptions::new()
+    ///     .max_dimensions(2048, 2048)
+    ///     .thumbnail("small", 150, 150)
+    ///     .thumbnail("medium", 600, 600)
+    ///     .webp(true);
+    ///
+    /// let uploader = Uploader::new(UploadConfig::new().images_only().image(options));
+    /// ```
+    pub fn thumbnail(mut self, name: &str, width: u32, height: u32) -> Self {
+        self.thumbnails.push(Thumbnail {
+            name: name.to_string(),
+            width,
+            height,
+        });
+        self
+    }
+
+    /// Convert images to WebP
+    pub fn webp(mut self, enabled: bool) -> Self {
+        self.convert_to_webp = enabled;
+        self
+    }
+}
+
+/// An encoded image
+pub(crate) struct EncodedImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Result of running the pipeline
+pub(crate) struct ProcessedImage {
+    pub main: EncodedImage,
+    pub extension: String,
+    pub mimetype: String,
+    pub thumbnails: Vec<(String, EncodedImage)>,
+}
+
+/// Check if a MIME type can go through the pipeline
+///
+/// GIFs are skipped so animations survive; SVGs aren't raster images.
+pub(crate) fn is_processable(mimetype: &str) -> bool {
+    matches!(
+        mimetype,
+        "image/png" | "image/jpeg" | "image/jpg" | "image/webp" | "image/bmp" | "image/tiff"
+    )
+}
+
+/// Run the pipeline (CPU-bound; call from a blocking task)
+pub(crate) fn process(
+    data: &[u8],
+    extension: &str,
+    options: &ImageOptions,
+) -> Result<ProcessedImage, UploadError> {
+    let error = |e: image::ImageError| UploadError::ParseError(format!("Invalid image: {}", e));
+
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| UploadError::IoError(e.to_string()))?;
+    let source_format = reader
+        .format()
+        .ok_or_else(|| UploadError::ParseError("Unknown image format".to_string()))?;
+    let mut decoder = reader.into_decoder().map_err(error)?;
+    let orientation = decoder.orientation().map_err(error)?;
+    let mut img = DynamicImage::from_decoder(decoder).map_err(error)?;
+
+    if options.auto_orient {
+        img.apply_orientation(orientation);
+    }
+
+    let max_width = options.max_width.unwrap_or(u32::MAX);
+    let max_height = options.max_height.unwrap_or(u32::MAX);
+    if img.width() > max_width || img.height() > max_height {
+        img = img.resize(max_width, max_height, image::imageops::FilterType::Lanczos3);
+    }
+
+    let (format, extension, mimetype) = if options.convert_to_webp {
+        (ImageFormat::WebP, "webp".to_string(), "image/webp")
+    } else {
+        (
+            source_format,
+            extension.to_string(),
+            source_format.to_mime_type(),
+        )
+    };
+
+    let thumbnails = options
+        .thumbnails
+        .iter()
+        .map(|t| {
+            Ok((
+                t.name.clone(),
+                encode(&img.thumbnail(t.width, t.height), format)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, UploadError>>()?;
+
+    Ok(ProcessedImage {
+        main: encode(&img, format)?,
+        extension,
+        mimetype: mimetype.to_string(),
+        thumbnails,
+    })
+}
+
+/// Encode an image, converting pixel formats the encoder can't take
+fn encode(img: &DynamicImage, format: ImageFormat) -> Result<EncodedImage, UploadError> {
+    let converted = match format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(img.to_rgb8()),
+        ImageFormat::WebP => DynamicImage::ImageRgba8(img.to_rgba8()),
+        _ => img.clone(),
+    };
+
+    let mut data = Vec::new();
+    converted
+        .write_to(&mut Cursor::new(&mut data), format)
+        .map_err(|e| UploadError::IoError(format!("Failed to encode image: {}", e)))?;
+
+    Ok(EncodedImage {
+        data,
+        width: img.width(),
+        height: img.height(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_and_thumbnails() {
+        let mut source = Vec::new();
+        DynamicImage::new_rgb8(400, 200)
+            .write_to(&mut Cursor::new(&mut source), ImageFormat::Png)
+            .unwrap();
+
+        let options = ImageOptions::new()
+            .max_dimensions(200, 200)
+            .thumbnail("small", 50, 50)
+            .webp(true);
+        let processed = process(&source, "png", &options).unwrap();
+
+        assert_eq!((processed.main.width, processed.main.height), (200, 100));
+        assert_eq!(processed.extension, "webp");
+        assert_eq!(processed.mimetype, "image/webp");
+        assert_eq!(processed.thumbnails.len(), 1);
+        assert_eq!(processed.thumbnails[0].1.width, 50);
+        assert_eq!(
+            infer::get(&processed.main.data).unwrap().mime_type(),
+            "image/webp"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Upload Quotas
+//!
+//! Per-user byte limits for uploads. A key extractor picks who a request
+//! belongs to (user ID, API key, ...) and a [`QuotaStore`] tracks how many
+//! bytes each key has uploaded today and in total.
+
+use super::UploadError;
+use crate::middleware::Principal;
+use crate::request::Request;
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Which quota was exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaPeriod {
+    /// Bytes uploaded today (UTC)
+    Daily,
+    /// Bytes uploaded overall
+    Total,
+}
+
+impl std::fmt::Display for QuotaPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuotaPeriod::Daily => write!(f, "daily"),
+            QuotaPeriod::Total => write!(f, "total"),
+        }
+    }
+}
+
+/// Byte limits per key
+#[derive(Debug, Clone, Copy, Default)]
+pub struct QuotaLimits {
+    /// Maximum bytes per UTC day
+    pub daily_bytes: Option<u64>,
+    /// Maximum bytes overall
+    pub total_bytes: Option<u64>,
+}
+
+/// Bytes uploaded by a key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuotaUsage {
+    /// Bytes uploaded today (UTC)
+    pub daily_bytes: u64,
+    /// Bytes uploaded overall
+    pub total_bytes: u64,
+}
+
+/// Storage for quota usage
+///
+/// `try_consume` must check and record atomically so concurrent uploads
+/// can't both squeeze under the limit.
+#[async_trait]
+pub trait QuotaStore: Send + Sync {
+    /// Record `bytes` for `key`, or fail with [`UploadError::QuotaExceeded`]
+    async fn try_consume(
+        &self,
+        key: &str,
+        bytes: u64,
+        limits: &QuotaLimits,
+    ) -> Result<(), UploadError>;
+
+    /// Give back bytes from an upload that failed
+    async fn release(&self, key: &str, bytes: u64) -> Result<(), UploadError>;
+
+    /// Current usage for a key
+    async fn usage(&self, key: &str) -> Result<QuotaUsage, UploadError>;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct UsageEntry {
+    day: NaiveDate,
+    daily_bytes: u64,
+    total_bytes: u64,
+}
+
+impl UsageEntry {
+    /// Usage with the daily counter reset if the day changed
+    fn current(self, today: NaiveDate) -> Self {
+        if self.day == today {
+            self
+        } else {
+            Self {
+                day: today,
+                daily_bytes: 0,
+                ..self
+            }
+        }
+    }
+}
+
+/// In-memory quota store (per process)
+#[derive(Debug, Clone, Default)]
+pub struct MemoryQuotaStore {
+    entries: Arc<RwLock<HashMap<String, UsageEntry>>>,
+}
+
+impl MemoryQuotaStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl QuotaStore for MemoryQuotaStore {
+    async fn try_consume(
+        &self,
+        key: &str,
+        bytes: u64,
+        limits: &QuotaLimits,
+    ) -> Result<(), UploadError> {
+        let today = Utc::now().date_naive();
+        let mut entries = self.entries.write();
+        let entry = entries
+            .get(key)
+            .copied()
+            .unwrap_or(UsageEntry {
+                day: today,
+                daily_bytes: 0,
+                total_bytes: 0,
+            })
+            .current(today);
+
+        let checks = [
+            (QuotaPeriod::Daily, limits.daily_bytes, entry.daily_bytes),
+            (QuotaPeriod::Total, limits.total_bytes, entry.total_bytes),
+        ];
+        for (period, limit, used) in checks {
+            if let Some(limit) = limit {
+                if used.saturating_add(bytes) > limit {
+                    return Err(UploadError::QuotaExceeded {
+                        period,
+                        limit,
+                        used,
+                    });
+                }
+            }
+        }
+
+        entries.insert(
+            key.to_string(),
+            UsageEntry {
+                daily_bytes: entry.daily_bytes + bytes,
+                total_bytes: entry.total_bytes + bytes,
+                ..entry
+            },
+        );
+        Ok(())
+    }
+
+    async fn release(&self, key: &str, bytes: u64) -> Result<(), UploadError> {
+        let today = Utc::now().date_naive();
+        if let Some(entry) = self.entries.write().get_mut(key) {
+            *entry = entry.current(today);
+            entry.daily_bytes = entry.daily_bytes.saturating_sub(bytes);
+            entry.total_bytes = entry.total_bytes.saturating_sub(bytes);
+        }
+        Ok(())
+    }
+
+    async fn usage(&self, key: &str) -> Result<QuotaUsage, UploadError> {
+        let today = Utc::now().date_naive();
+        Ok(self
+            .entries
+            .read()
+            .get(key)
+            .map(|entry| {
+                let entry = entry.current(today);
+                QuotaUsage {
+                    daily_bytes: entry.daily_bytes,
+                    total_bytes: entry.total_bytes,
+                }
+            })
+            .unwrap_or_default())
+    }
+}
+
+/// Extracts the quota key from a request
+pub type QuotaKeyFn = Arc<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// Per-key upload quota
+///
+/// Requests for which the extractor returns `None` aren't counted.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let quota = UploadQuota::new(|req| req.header("x-api-key").map(str::to_string))
+///     .daily_bytes(100 * 1024 * 1024)
+///     .total_bytes(1024 * 1024 * 1024);
+///
+/// let uploader = Uploader::new(UploadConfig::new().quota(quota));
+/// ```
+#[derive(Clone)]
+pub struct UploadQuota {
+    /// Byte limits
+    pub limits: QuotaLimits,
+    /// Usage store
+    pub store: Arc<dyn QuotaStore>,
+    key: QuotaKeyFn,
+}
+
+impl UploadQuota {
+    /// Create a quota keyed by `key`, with an in-memory store and no limits
+    pub fn new<F>(key: F) -> Self
+    where
+        F: Fn(&Request) -> Option<String> + Send + Sync + 'static,
+    {
+        Self {
+            limits: QuotaLimits::default(),
+            store: Arc::new(MemoryQuotaStore::new()),
+            key: Arc::new(key),
+        }
+    }
+
+    /// Key by the authenticated [`Principal`] id
+    pub fn by_principal() -> Self {
+        Self::new(|req| {
+            req.extensions()
+                .get::<Principal>()
+                .map(|principal| principal.id.clone())
+        })
+    }
+
+    /// Set the daily byte limit
+    pub fn daily_bytes(mut self, bytes: u64) -> Self {
+        self.limits.daily_bytes = Some(bytes);
+        self
+    }
+
+    /// Set the total byte limit
+    pub fn total_bytes(mut self, bytes: u64) -> Self {
+        self.limits.total_bytes = Some(bytes);
+        self
+    }
+
+    /// Use a different usage store
+    pub fn store(mut self, store: impl QuotaStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Get the quota key for a request
+    pub fn key(&self, req: &Request) -> Option<String> {
+        (self.key)(req)
+    }
+
+    /// Reserve bytes for a request's upload
+    pub(crate) async fn reserve(
+        &self,
+        req: &Request,
+        bytes: u64,
+    ) -> Result<Option<QuotaReservation>, UploadError> {
+        let Some(key) = self.key(req) else {
+            return Ok(None);
+        };
+        self.store.try_consume(&key, bytes, &self.limits).await?;
+
+        Ok(Some(QuotaReservation {
+            store: self.store.clone(),
+            key,
+            bytes,
+        }))
+    }
+}
+
+impl std::fmt::Debug for UploadQuota {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadQuota")
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Bytes reserved for an upload in progress
+pub(crate) struct QuotaReservation {
+    store: Arc<dyn QuotaStore>,
+    key: String,
+    bytes: u64,
+}
+
+impl QuotaReservation {
+    /// Give the bytes back after a failed upload
+    pub(crate) async fn release(self) {
+        if let Err(e) = self.store.release(&self.key, self.bytes).await {
+            tracing::warn!("Failed to release upload quota for {}: {}", self.key, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_quota_store() {
+        let store = MemoryQuotaStore::new();
+        let limits = QuotaLimits {
+            daily_bytes: Some(100),
+            total_bytes: Some(150),
+        };
+
+        store.try_consume("alice", 60, &limits).await.unwrap();
+        let err = store.try_consume("alice", 50, &limits).await.unwrap_err();
+        assert!(matches!(
+            err,
+            UploadError::QuotaExceeded {
+                period: QuotaPeriod::Daily,
+                used: 60,
+                ..
+            }
+        ));
+        assert_eq!(err.status_code(), 429);
+
+        store.try_consume("bob", 100, &limits).await.unwrap();
+        store.release("alice", 20).await.unwrap();
+        assert_eq!(store.usage("alice").await.unwrap().total_bytes, 40);
+    }
+}
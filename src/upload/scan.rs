@@ -0,0 +1,218 @@
+//! Upload Scanning
+//!
+//! Hooks that inspect file contents before they are persisted, e.g. an
+//! antivirus check. A [`ScanVerdict::Rejected`] verdict fails the upload
+//! with [`UploadError::Rejected`].
+
+use super::UploadError;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// File handed to a scanner
+#[derive(Debug, Clone)]
+pub struct ScanFile {
+    /// Original filename from the client
+    pub original_name: String,
+    /// Form field name
+    pub field_name: String,
+    /// Declared MIME type
+    pub mimetype: String,
+    /// File contents
+    pub data: Bytes,
+}
+
+/// Result of scanning a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    /// File may be stored
+    Clean,
+    /// File must not be stored, with the reason (e.g. signature name)
+    Rejected(String),
+}
+
+/// Scanner run on every upload before it is stored
+///
+/// Return `Err` when the scan itself failed (scanner unreachable, ...);
+/// the upload fails either way, but the error says why.
+#[async_trait]
+pub trait FileScanner: Send + Sync {
+    /// Scan a file
+    async fn scan(&self, file: &ScanFile) -> Result<ScanVerdict, UploadError>;
+}
+
+/// Scanner configured on an [`UploadConfig`](super::UploadConfig)
+#[derive(Clone)]
+pub struct ScanHook(pub Arc<dyn FileScanner>);
+
+impl std::fmt::Debug for ScanHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ScanHook(..)")
+    }
+}
+
+/// Scanner backed by an async callback
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let config = UploadConfig::new().scan_hook(FnScanner::new(|file: ScanFile| async move {
+///     if file.data.windows(4).any(|w| w == b"EVIL") {
+///         ScanVerdict::Rejected("evil bytes".into())
+///     } else {
+///         ScanVerdict::Clean
+///     }
+/// }));
+/// ```
+pub struct FnScanner<F> {
+    callback: F,
+}
+
+impl<F> FnScanner<F> {
+    /// Wrap a callback
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+#[async_trait]
+impl<F, Fut> FileScanner for FnScanner<F>
+where
+    F: Fn(ScanFile) -> Fut + Send + Sync,
+    Fut: Future<Output = ScanVerdict> + Send,
+{
+    async fn scan(&self, file: &ScanFile) -> Result<ScanVerdict, UploadError> {
+        Ok((self.callback)(file.clone()).await)
+    }
+}
+
+/// Where clamd listens
+#[derive(Debug, Clone)]
+pub enum ClamAvAddress {
+    /// TCP address, e.g. `127.0.0.1:3310`
+    Tcp(String),
+    /// Unix socket path, e.g. `/var/run/clamav/clamd.ctl`
+    #[cfg(unix)]
+    Unix(std::path::PathBuf),
+}
+
+/// ClamAV scanner using clamd's `INSTREAM` command
+#[derive(Debug, Clone)]
+pub struct ClamAvScanner {
+    /// clamd address
+    pub address: ClamAvAddress,
+    /// Size of the chunks streamed to clamd (default: 64KB)
+    pub chunk_size: usize,
+    /// Timeout for the whole scan (default: 30s)
+    pub timeout: Duration,
+}
+
+impl ClamAvScanner {
+    /// Connect to clamd over TCP
+    pub fn tcp(address: &str) -> Self {
+        Self::with_address(ClamAvAddress::Tcp(address.to_string()))
+    }
+
+    /// Connect to clamd over a Unix socket
+    #[cfg(unix)]
+    pub fn unix(path: impl Into<std::path::PathBuf>) -> Self {
+        Self::with_address(ClamAvAddress::Unix(path.into()))
+    }
+
+    fn with_address(address: ClamAvAddress) -> Self {
+        Self {
+            address,
+            chunk_size: 64 * 1024,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the chunk size
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Set the scan timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn scan_stream<S>(&self, mut stream: S, data: &[u8]) -> std::io::Result<String>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in data.chunks(self.chunk_size) {
+            stream
+                .write_all(&(chunk.len() as u32).to_be_bytes())
+                .await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+        stream.flush().await?;
+
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        let reply = String::from_utf8_lossy(&reply);
+        Ok(reply.trim_end_matches(['\0', '\n']).to_string())
+    }
+}
+
+#[async_trait]
+impl FileScanner for ClamAvScanner {
+    async fn scan(&self, file: &ScanFile) -> Result<ScanVerdict, UploadError> {
+        let scan = async {
+            match &self.address {
+                ClamAvAddress::Tcp(address) => {
+                    let stream = tokio::net::TcpStream::connect(address).await?;
+                    self.scan_stream(stream, &file.data).await
+                }
+                #[cfg(unix)]
+                ClamAvAddress::Unix(path) => {
+                    let stream = tokio::net::UnixStream::connect(path).await?;
+                    self.scan_stream(stream, &file.data).await
+                }
+            }
+        };
+
+        let reply = tokio::time::timeout(self.timeout, scan)
+            .await
+            .map_err(|_| UploadError::IoError("clamd scan timed out".to_string()))?
+            .map_err(|e| UploadError::IoError(format!("clamd: {}", e)))?;
+
+        parse_clamd_reply(&reply)
+    }
+}
+
+/// Interpret a clamd reply (`stream: OK`, `stream: <name> FOUND`, `... ERROR`)
+fn parse_clamd_reply(reply: &str) -> Result<ScanVerdict, UploadError> {
+    let result = reply.strip_prefix("stream:").unwrap_or(reply).trim();
+
+    if result == "OK" {
+        Ok(ScanVerdict::Clean)
+    } else if let Some(signature) = result.strip_suffix("FOUND") {
+        Ok(ScanVerdict::Rejected(signature.trim().to_string()))
+    } else {
+        Err(UploadError::IoError(format!("clamd: {}", result)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clamd_reply() {
+        assert_eq!(parse_clamd_reply("stream: OK").unwrap(), ScanVerdict::Clean);
+        assert_eq!(
+            parse_clamd_reply("stream: Eicar-Test-Signature FOUND").unwrap(),
+            ScanVerdict::Rejected("Eicar-Test-Signature".to_string())
+        );
+        assert!(parse_clamd_reply("INSTREAM size limit exceeded. ERROR").is_err());
+    }
+}
@@ -0,0 +1,242 @@
+//! Storage Backends
+//!
+//! Where uploaded bytes end up. Disk and memory storage are built in; S3
+//! is available with the `s3` feature, and anything else (Google Cloud
+//! Storage, Azure Blob, encrypted stores) can implement [`StorageBackend`].
+
+use super::{UploadError, UploadedFile};
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// Metadata about the file being stored
+#[derive(Debug, Clone)]
+pub struct StorageContext<'a> {
+    /// Generated filename
+    pub filename: &'a str,
+    /// Original filename from the client
+    pub original_name: &'a str,
+    /// Form field name
+    pub field_name: &'a str,
+    /// File extension
+    pub extension: &'a str,
+    /// MIME type
+    pub mimetype: &'a str,
+}
+
+/// Where a backend put a file
+#[derive(Debug, Clone, Default)]
+pub struct StoredFile {
+    /// Local path or object key
+    pub path: PathBuf,
+    /// Bytes, for backends that keep files in memory
+    pub data: Option<Bytes>,
+    /// Public or object URL, for remote backends
+    pub url: Option<String>,
+    /// Final filename, when the backend had to rename the file
+    pub filename: Option<String>,
+}
+
+/// Storage backend for uploaded files
+///
+/// # Example
+///
+/// ```rust,ignore
+/// struct GcsStorage { bucket: String }
+///
+/// #[async_trait]
+/// impl StorageBackend for GcsStorage {
+///     async fn store(&self, ctx: &StorageContext<'_>, data: Bytes) -> Result<StoredFile, UploadError> {
+///         let url = gcs_put(&self.bucket, ctx.filename, data).await?;
+///         Ok(StoredFile { path: ctx.filename.into(), url: Some(url), ..Default::default() })
+///     }
+/// }
+///
+/// let uploader = Uploader::new(UploadConfig::new().backend(Arc::new(GcsStorage { .. })));
+/// ```
+#[async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// Persist a file
+    async fn store(&self, ctx: &StorageContext<'_>, data: Bytes)
+        -> Result<StoredFile, UploadError>;
+
+    /// Remove a previously stored file
+    async fn delete(&self, _file: &UploadedFile) -> Result<(), UploadError> {
+        Ok(())
+    }
+
+    /// Look up a file already stored under `ctx.filename`
+    ///
+    /// Used by content-hash deduplication; backends that can't tell
+    /// return `None` and the file is stored again.
+    async fn find(&self, _ctx: &StorageContext<'_>) -> Result<Option<StoredFile>, UploadError> {
+        Ok(None)
+    }
+}
+
+/// Store files in a directory on disk
+#[derive(Debug, Clone)]
+pub struct DiskStorage {
+    destination: PathBuf,
+    create_dir: bool,
+}
+
+impl DiskStorage {
+    /// Create disk storage for a directory
+    pub fn new(destination: impl Into<PathBuf>) -> Self {
+        Self {
+            destination: destination.into(),
+            create_dir: true,
+        }
+    }
+
+    /// Create the destination directory if it doesn't exist
+    pub fn create_dir(mut self, create: bool) -> Self {
+        self.create_dir = create;
+        self
+    }
+}
+
+#[async_trait]
+impl StorageBackend for DiskStorage {
+    async fn store(
+        &self,
+        ctx: &StorageContext<'_>,
+        data: Bytes,
+    ) -> Result<StoredFile, UploadError> {
+        // Create directory if needed
+        if self.create_dir && !self.destination.exists() {
+            fs::create_dir_all(&self.destination)
+                .await
+                .map_err(|e| UploadError::IoError(e.to_string()))?;
+        }
+
+        // Never overwrite: add a numeric suffix until the name is free
+        let (stem, ext) = match ctx.filename.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+            _ => (ctx.filename, String::new()),
+        };
+        let mut attempt = 0;
+        let (file_path, mut file) = loop {
+            let name = if attempt == 0 {
+                ctx.filename.to_string()
+            } else {
+                format!("{}-{}{}", stem, attempt, ext)
+            };
+            let file_path = self.destination.join(&name);
+
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&file_path)
+                .await
+            {
+                Ok(file) => break (file_path, file),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 1000 => {
+                    attempt += 1;
+                }
+                Err(e) => return Err(UploadError::IoError(e.to_string())),
+            }
+        };
+
+        // Write file
+        file.write_all(&data)
+            .await
+            .map_err(|e| UploadError::IoError(e.to_string()))?;
+
+        let filename = file_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .filter(|n| n != ctx.filename);
+
+        Ok(StoredFile {
+            path: file_path,
+            filename,
+            ..Default::default()
+        })
+    }
+
+    async fn delete(&self, file: &UploadedFile) -> Result<(), UploadError> {
+        fs::remove_file(&file.path)
+            .await
+            .map_err(|e| UploadError::IoError(e.to_string()))
+    }
+
+    async fn find(&self, ctx: &StorageContext<'_>) -> Result<Option<StoredFile>, UploadError> {
+        let path = self.destination.join(ctx.filename);
+        match fs::try_exists(&path).await {
+            Ok(true) => Ok(Some(StoredFile {
+                path,
+                ..Default::default()
+            })),
+            Ok(false) => Ok(None),
+            Err(e) => Err(UploadError::IoError(e.to_string())),
+        }
+    }
+}
+
+/// Keep files in memory and hand the bytes back on [`UploadedFile::data`]
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStorage;
+
+#[async_trait]
+impl StorageBackend for MemoryStorage {
+    async fn store(
+        &self,
+        _ctx: &StorageContext<'_>,
+        data: Bytes,
+    ) -> Result<StoredFile, UploadError> {
+        Ok(StoredFile {
+            data: Some(data),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(feature = "s3")]
+#[async_trait]
+impl StorageBackend for super::S3Config {
+    async fn store(
+        &self,
+        ctx: &StorageContext<'_>,
+        data: Bytes,
+    ) -> Result<StoredFile, UploadError> {
+        let key = self.render_key(
+            ctx.filename,
+            ctx.original_name,
+            ctx.field_name,
+            ctx.extension,
+        );
+        let url = self.put_object(&key, data, ctx.mimetype).await?;
+
+        Ok(StoredFile {
+            path: PathBuf::from(key),
+            url: Some(url),
+            ..Default::default()
+        })
+    }
+
+    async fn delete(&self, file: &UploadedFile) -> Result<(), UploadError> {
+        self.delete_object(&file.path.to_string_lossy()).await
+    }
+
+    async fn find(&self, ctx: &StorageContext<'_>) -> Result<Option<StoredFile>, UploadError> {
+        let key = self.render_key(
+            ctx.filename,
+            ctx.original_name,
+            ctx.field_name,
+            ctx.extension,
+        );
+        if !self.object_exists(&key).await? {
+            return Ok(None);
+        }
+
+        Ok(Some(StoredFile {
+            url: Some(self.object_url(&key)),
+            path: PathBuf::from(key),
+            ..Default::default()
+        }))
+    }
+}
@@ -0,0 +1,475 @@
+//! Typed Configuration
+//!
+//! Loads a serde struct from defaults, `.env` files and the process
+//! environment, later sources overriding earlier ones.
+//!
+//! ```rust,ignore
+//! #[derive(Deserialize)]
+//! struct AppConfig {
+//!     port: u16,
+//!     database_url: String,
+//!     #[serde(default)]
+//!     debug: bool,
+//!     allowed_origins: Vec<String>, // RUSTYX_ALLOWED_ORIGINS=a.com,b.com
+//!     mail: MailConfig,             // RUSTYX_MAIL__HOST, RUSTYX_MAIL__PORT
+//! }
+//!
+//! let config: AppConfig = ConfigLoader::new()
+//!     .prefix("RUSTYX")
+//!     .default("port", 3000)
+//!     .load()?;
+//! ```
+//!
+//! Variable names map to fields by dropping the prefix and lowercasing;
+//! `__` separates nested structs. Lists are comma-separated.
+
+use crate::error::{Error, Result};
+use serde::de::value::{SeqDeserializer, StringDeserializer};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Load `T` from `.env` and the environment, without a prefix
+pub fn load<T: DeserializeOwned>() -> Result<T> {
+    ConfigLoader::new().load()
+}
+
+/// Configuration sources and naming
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    prefix: Option<String>,
+    files: Vec<PathBuf>,
+    defaults: Vec<(String, String)>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigLoader {
+    /// Read `.env` (if present) and the environment
+    pub fn new() -> Self {
+        Self {
+            prefix: None,
+            files: vec![PathBuf::from(".env")],
+            defaults: Vec::new(),
+        }
+    }
+
+    /// Only read variables starting with `{prefix}_`, e.g. `RUSTYX_PORT`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = Some(prefix.trim_end_matches('_').to_uppercase());
+        self
+    }
+
+    /// Also read a `.env`-style file, overriding earlier files; missing
+    /// files are skipped
+    pub fn env_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.files.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Value used when no file or variable sets `key` (a field name, with
+    /// `.` for nested fields)
+    pub fn default(mut self, key: &str, value: impl ToString) -> Self {
+        self.defaults
+            .push((key.replace('.', "__").to_lowercase(), value.to_string()));
+        self
+    }
+
+    /// Merge the sources and deserialize `T`
+    ///
+    /// Errors name the variable to set, e.g. ``missing configuration value
+    /// `RUSTYX_DATABASE_URL` ``.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<T> {
+        let mut entries = read_env_files(&self.files)?;
+        entries.extend(std::env::vars());
+        self.load_from(entries)
+    }
+
+    fn load_from<T: DeserializeOwned>(
+        &self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<T> {
+        let mut root = BTreeMap::new();
+        for (key, value) in &self.defaults {
+            insert(&mut root, key, value.clone());
+        }
+        for (name, value) in vars {
+            let key = match &self.prefix {
+                Some(prefix) => match name
+                    .strip_prefix(prefix.as_str())
+                    .and_then(|rest| rest.strip_prefix('_'))
+                {
+                    Some(key) => key.to_lowercase(),
+                    None => continue,
+                },
+                None => name.to_lowercase(),
+            };
+            insert(&mut root, &key, value);
+        }
+
+        T::deserialize(NodeDeserializer(Node::Table(root))).map_err(|e| {
+            let var = |path: &[String]| {
+                let name = path.join("__").to_uppercase();
+                match &self.prefix {
+                    Some(prefix) => format!("{}_{}", prefix, name),
+                    None => name,
+                }
+            };
+            let message = match e.kind {
+                ErrorKind::Missing(field) => {
+                    let mut path = e.path;
+                    path.push(field);
+                    format!("missing configuration value `{}`", var(&path))
+                }
+                ErrorKind::Invalid(message) => {
+                    format!(
+                        "invalid configuration value `{}`: {}",
+                        var(&e.path),
+                        message
+                    )
+                }
+            };
+            Error::Custom(message)
+        })
+    }
+}
+
+/// The variables in `.env`-style files, later files last; missing files
+/// are skipped
+pub(crate) fn read_env_files(files: &[PathBuf]) -> Result<Vec<(String, String)>> {
+    let mut entries = Vec::new();
+    for path in files {
+        let iter = match dotenvy::from_path_iter(path) {
+            Ok(iter) => iter,
+            Err(e) if e.not_found() => continue,
+            Err(e) => return Err(file_error(path, e)),
+        };
+        for entry in iter {
+            entries.push(entry.map_err(|e| file_error(path, e))?);
+        }
+    }
+    Ok(entries)
+}
+
+fn file_error(path: &Path, e: dotenvy::Error) -> Error {
+    Error::Custom(format!("failed to read {}: {}", path.display(), e))
+}
+
+/// Set `key` (`__`-separated) in the tree, replacing a value or table
+/// already there
+fn insert(table: &mut BTreeMap<String, Node>, key: &str, value: String) {
+    match key.split_once("__") {
+        Some((head, rest)) => {
+            let node = table
+                .entry(head.to_string())
+                .or_insert_with(|| Node::Table(BTreeMap::new()));
+            if let Node::Value(_) = node {
+                *node = Node::Table(BTreeMap::new());
+            }
+            if let Node::Table(inner) = node {
+                insert(inner, rest, value);
+            }
+        }
+        None => {
+            table.insert(key.to_string(), Node::Value(value));
+        }
+    }
+}
+
+enum Node {
+    Value(String),
+    Table(BTreeMap<String, Node>),
+}
+
+#[derive(Debug)]
+struct ConfigError {
+    /// Keys from the root down to the failing value
+    path: Vec<String>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug)]
+enum ErrorKind {
+    Missing(String),
+    Invalid(String),
+}
+
+impl ConfigError {
+    fn invalid(message: String) -> Self {
+        Self {
+            path: Vec::new(),
+            kind: ErrorKind::Invalid(message),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Missing(field) => write!(f, "missing field `{}`", field),
+            ErrorKind::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl de::Error for ConfigError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::invalid(msg.to_string())
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self {
+            path: Vec::new(),
+            kind: ErrorKind::Missing(field.to_string()),
+        }
+    }
+}
+
+/// Deserializes strings into the type the visitor asks for
+struct NodeDeserializer(Node);
+
+impl NodeDeserializer {
+    fn value(self, expected: &str) -> std::result::Result<String, ConfigError> {
+        match self.0 {
+            Node::Value(value) => Ok(value),
+            Node::Table(_) => Err(ConfigError::invalid(format!(
+                "expected {}, found nested values",
+                expected
+            ))),
+        }
+    }
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident: $t:ty),* $(,)?) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> std::result::Result<V::Value, Self::Error> {
+            let value = self.value(stringify!($t))?;
+            match value.trim().parse::<$t>() {
+                Ok(parsed) => visitor.$visit(parsed),
+                Err(_) => Err(ConfigError::invalid(format!(
+                    "expected {}, got `{}`",
+                    stringify!($t),
+                    value
+                ))),
+            }
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for NodeDeserializer {
+    type Error = ConfigError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match self.0 {
+            Node::Value(value) => visitor.visit_string(value),
+            Node::Table(table) => visitor.visit_map(TableAccess {
+                iter: table.into_iter(),
+                current: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let value = self.value("bool")?;
+        match value.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => visitor.visit_bool(true),
+            "false" | "0" | "no" | "off" | "" => visitor.visit_bool(false),
+            _ => Err(ConfigError::invalid(format!(
+                "expected bool, got `{}`",
+                value
+            ))),
+        }
+    }
+
+    parse_value! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+    }
+
+    /// Empty values are `None`
+    fn deserialize_option<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        match &self.0 {
+            Node::Value(value) if value.is_empty() => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let value = self.value("a list")?;
+        let items = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| NodeDeserializer(Node::Value(item.to_string())));
+        visitor.visit_seq(SeqDeserializer::new(items))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let value = self.value("a variant name")?;
+        visitor.visit_enum(StringDeserializer::<ConfigError>::new(value))
+    }
+
+    serde::forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, ConfigError> for NodeDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct TableAccess {
+    iter: std::collections::btree_map::IntoIter<String, Node>,
+    current: Option<(String, Node)>,
+}
+
+impl<'de> MapAccess<'de> for TableAccess {
+    type Error = ConfigError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> std::result::Result<Option<K::Value>, Self::Error> {
+        let Some((key, node)) = self.iter.next() else {
+            return Ok(None);
+        };
+        let parsed = seed.deserialize(StringDeserializer::<ConfigError>::new(key.clone()))?;
+        self.current = Some((key, node));
+        Ok(Some(parsed))
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> std::result::Result<S::Value, Self::Error> {
+        let (key, node) = self
+            .current
+            .take()
+            .ok_or_else(|| ConfigError::invalid("value requested before key".to_string()))?;
+        seed.deserialize(NodeDeserializer(node)).map_err(|mut e| {
+            e.path.insert(0, key);
+            e
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Mail {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct AppConfig {
+        port: u16,
+        #[serde(default)]
+        debug: bool,
+        origins: Vec<String>,
+        token: Option<String>,
+        mail: Mail,
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_load_config() {
+        let loader = ConfigLoader::new().prefix("APP").default("port", 3000);
+        let config: AppConfig = loader
+            .load_from(vars(&[
+                ("APP_DEBUG", "yes"),
+                ("APP_ORIGINS", "a.com, b.com"),
+                ("APP_MAIL__HOST", "smtp.local"),
+                ("APP_MAIL__PORT", "25"),
+                ("PATH", "/usr/bin"),
+            ]))
+            .unwrap();
+        assert_eq!(
+            config,
+            AppConfig {
+                port: 3000,
+                debug: true,
+                origins: vec!["a.com".to_string(), "b.com".to_string()],
+                token: None,
+                mail: Mail {
+                    host: "smtp.local".to_string(),
+                    port: 25,
+                },
+            }
+        );
+
+        let err = loader
+            .load_from::<AppConfig>(vars(&[("APP_ORIGINS", ""), ("APP_MAIL__PORT", "25")]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing configuration value `APP_MAIL__HOST`"
+        );
+        let err = loader
+            .load_from::<AppConfig>(vars(&[
+                ("APP_PORT", "http"),
+                ("APP_ORIGINS", ""),
+                ("APP_MAIL__HOST", "smtp.local"),
+                ("APP_MAIL__PORT", "25"),
+            ]))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration value `APP_PORT`: expected u16, got `http`"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Symmetric Encryption
+//!
+//! AES-256-GCM with keys derived from an application secret via
+//! HKDF-SHA256. Ciphertexts are URL-safe base64 of a version byte, the
+//! nonce, and the sealed data, so they fit in cookies and query strings.
+//!
+//! ```rust,ignore
+//! let token = crypto::encrypt(secret, b"user=42")?;
+//! assert_eq!(crypto::decrypt(secret, &token)?, b"user=42");
+//!
+//! // Separate keys per use, and decryption with a retired secret
+//! let cookies = Cipher::with_purpose(new_secret, "cookies").fallback(old_secret);
+//! ```
+
+use crate::error::{Error, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Format version written as the first byte of every ciphertext
+const VERSION: u8 = 1;
+const SALT: &[u8] = b"rustyx.crypto";
+
+/// Encrypt with a key derived from `secret`
+pub fn encrypt(secret: &[u8], plaintext: &[u8]) -> Result<String> {
+    Cipher::new(secret).encrypt(plaintext)
+}
+
+/// Decrypt a value from [`encrypt`] with the same secret
+pub fn decrypt(secret: &[u8], ciphertext: &str) -> Result<Vec<u8>> {
+    Cipher::new(secret).decrypt(ciphertext)
+}
+
+/// AES-256-GCM cipher
+///
+/// Encrypts with the key derived from the first secret and decrypts with
+/// it or any [`fallback`](Cipher::fallback) secret.
+pub struct Cipher {
+    purpose: String,
+    keys: Vec<LessSafeKey>,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher")
+            .field("purpose", &self.purpose)
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+impl Cipher {
+    /// Cipher keyed by `secret`, which should be at least 32 random bytes
+    pub fn new(secret: &[u8]) -> Self {
+        Self::with_purpose(secret, "")
+    }
+
+    /// Cipher keyed by `secret` and `purpose`, so values encrypted for one
+    /// purpose (e.g. `"cookies"`) can't be decrypted as another
+    pub fn with_purpose(secret: &[u8], purpose: &str) -> Self {
+        let mut cipher = Self {
+            purpose: purpose.to_string(),
+            keys: Vec::new(),
+        };
+        cipher.keys.push(cipher.derive(secret));
+        cipher
+    }
+
+    /// Also decrypt values encrypted with a previous secret
+    pub fn fallback(mut self, secret: &[u8]) -> Self {
+        let key = self.derive(secret);
+        self.keys.push(key);
+        self
+    }
+
+    fn derive(&self, secret: &[u8]) -> LessSafeKey {
+        let info = format!("rustyx aes-256-gcm v{} {}", VERSION, self.purpose);
+        let info = [info.as_bytes()];
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, SALT).extract(secret);
+        let okm = prk
+            .expand(&info, &aead::AES_256_GCM)
+            .expect("AES-256 key length is a valid HKDF output length");
+        LessSafeKey::new(UnboundKey::from(okm))
+    }
+
+    /// Encrypt with a random nonce
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| Error::Internal("failed to generate nonce".to_string()))?;
+
+        let mut sealed = plaintext.to_vec();
+        self.keys[0]
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from([VERSION]),
+                &mut sealed,
+            )
+            .map_err(|_| Error::Internal("encryption failed".to_string()))?;
+
+        let mut out = Vec::with_capacity(1 + NONCE_LEN + sealed.len());
+        out.push(VERSION);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(URL_SAFE_NO_PAD.encode(out))
+    }
+
+    /// Decrypt and authenticate, failing with [`Error::BadRequest`] for
+    /// tampered, truncated or foreign values
+    pub fn decrypt(&self, ciphertext: &str) -> Result<Vec<u8>> {
+        let invalid = || Error::bad_request("invalid encrypted value");
+        let data = URL_SAFE_NO_PAD.decode(ciphertext).map_err(|_| invalid())?;
+        let (&version, rest) = data.split_first().ok_or_else(invalid)?;
+        if version != VERSION {
+            return Err(Error::bad_request(format!(
+                "unsupported encrypted value version {}",
+                version
+            )));
+        }
+        if rest.len() < NONCE_LEN + aead::AES_256_GCM.tag_len() {
+            return Err(invalid());
+        }
+        let (nonce, sealed) = rest.split_at(NONCE_LEN);
+
+        self.keys
+            .iter()
+            .find_map(|key| {
+                let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+                let mut buf = sealed.to_vec();
+                let len = key
+                    .open_in_place(nonce, Aad::from([version]), &mut buf)
+                    .ok()?
+                    .len();
+                buf.truncate(len);
+                Some(buf)
+            })
+            .ok_or_else(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let secret = b"0123456789abcdef0123456789abcdef";
+        let token = encrypt(secret, b"user=42").unwrap();
+        assert_eq!(decrypt(secret, &token).unwrap(), b"user=42");
+        assert_ne!(encrypt(secret, b"user=42").unwrap(), token);
+
+        assert!(decrypt(b"another secret", &token).is_err());
+        let mut tampered = URL_SAFE_NO_PAD.decode(&token).unwrap();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(secret, &URL_SAFE_NO_PAD.encode(tampered)).is_err());
+
+        let cookies = Cipher::with_purpose(secret, "cookies");
+        assert!(cookies.decrypt(&token).is_err());
+        let rotated = Cipher::new(b"new secret").fallback(secret);
+        assert_eq!(rotated.decrypt(&token).unwrap(), b"user=42");
+    }
+}
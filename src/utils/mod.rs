@@ -2,6 +2,14 @@
 //!
 //! Provides helper functions and utilities.
 
+pub mod config;
+pub mod crypto;
+pub mod jwt;
+pub mod signed_url;
+pub mod text;
+pub mod validation;
+pub mod webhook;
+
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -49,6 +57,16 @@ impl<T> PaginatedResponse<T> {
     }
 }
 
+/// Cursor-paginated response
+///
+/// `next_cursor` is the cursor column's value in the last row, to pass as
+/// `after` for the next page; `None` on the last page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorPaginatedResponse<T> {
+    pub data: Vec<T>,
+    pub next_cursor: Option<serde_json::Value>,
+}
+
 /// API response wrapper
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
@@ -86,26 +104,6 @@ impl<T: Serialize> ApiResponse<T> {
     }
 }
 
-/// Validation helpers
-pub mod validation {
-    pub fn is_email(value: &str) -> bool {
-        value.contains('@') && value.contains('.')
-    }
-
-    pub fn min_length(value: &str, min: usize) -> bool {
-        value.len() >= min
-    }
-    pub fn max_length(value: &str, max: usize) -> bool {
-        value.len() <= max
-    }
-    pub fn is_numeric(value: &str) -> bool {
-        value.chars().all(|c| c.is_numeric())
-    }
-    pub fn is_alphanumeric(value: &str) -> bool {
-        value.chars().all(|c| c.is_alphanumeric())
-    }
-}
-
 /// Hash password (placeholder - use bcrypt/argon2 in production)
 pub fn hash_password(password: &str) -> String {
     format!("hashed_{}", password) // Replace with actual hashing
@@ -0,0 +1,140 @@
+//! Signed URLs
+//!
+//! Expiring links verified without storage: an `expires` timestamp and an
+//! HMAC-SHA256 `signature` over the path and query are appended to the URL.
+//! The host is not signed, so links keep working behind proxies.
+//!
+//! ```rust,ignore
+//! let link = signed_url::sign("/downloads/report.pdf", Duration::from_secs(3600), secret)?;
+//! // "/downloads/report.pdf?expires=1700003600&signature=..."
+//!
+//! let mut downloads = Router::new();
+//! downloads.use_middleware(middleware::signed(secret));
+//! downloads.get("/:file", download);
+//! app.use_router("/downloads", downloads);
+//! ```
+
+use crate::error::{Error, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+use url::Url;
+
+const EXPIRES: &str = "expires";
+const SIGNATURE: &str = "signature";
+
+/// Base for parsing relative URLs, stripped again from the output
+const RELATIVE_BASE: &str = "http://relative.invalid";
+
+/// Sign `url` (absolute, or a path with optional query) to stay valid
+/// for `ttl`
+pub fn sign(url: &str, ttl: Duration, secret: &[u8]) -> Result<String> {
+    let expires = chrono::Utc::now().timestamp() + ttl.as_secs() as i64;
+    sign_until(url, expires, secret)
+}
+
+/// Sign `url` to stay valid until the Unix timestamp `expires`
+pub fn sign_until(url: &str, expires: i64, secret: &[u8]) -> Result<String> {
+    let (mut parsed, relative) = parse(url)?;
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(k, _)| k != EXPIRES && k != SIGNATURE)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    parsed
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(EXPIRES, &expires.to_string());
+
+    let signature = URL_SAFE_NO_PAD.encode(mac(&parsed, secret).finalize().into_bytes());
+    parsed.query_pairs_mut().append_pair(SIGNATURE, &signature);
+
+    Ok(if relative {
+        parsed[url::Position::BeforePath..].to_string()
+    } else {
+        parsed.to_string()
+    })
+}
+
+/// Check a URL from [`sign`], failing with [`Error::Forbidden`] when the
+/// signature is missing or wrong, or the link has expired
+pub fn verify(url: &str, secret: &[u8]) -> Result<()> {
+    let invalid = || Error::Forbidden("invalid signature".to_string());
+    let (mut parsed, _) = parse(url).map_err(|_| invalid())?;
+
+    let mut signature = None;
+    let mut expires = None;
+    let mut pairs = Vec::new();
+    for (k, v) in parsed.query_pairs() {
+        match k.as_ref() {
+            SIGNATURE => signature = Some(v.into_owned()),
+            EXPIRES => {
+                expires = v.parse::<i64>().ok();
+                pairs.push((k.into_owned(), v.into_owned()));
+            }
+            _ => pairs.push((k.into_owned(), v.into_owned())),
+        }
+    }
+    let signature = signature
+        .and_then(|s| URL_SAFE_NO_PAD.decode(s).ok())
+        .ok_or_else(invalid)?;
+    let expires = expires.ok_or_else(invalid)?;
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+
+    mac(&parsed, secret)
+        .verify_slice(&signature)
+        .map_err(|_| invalid())?;
+    if chrono::Utc::now().timestamp() > expires {
+        return Err(Error::Forbidden("link expired".to_string()));
+    }
+    Ok(())
+}
+
+fn parse(url: &str) -> Result<(Url, bool)> {
+    match Url::parse(url) {
+        Ok(parsed) => Ok((parsed, false)),
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            let base = Url::parse(RELATIVE_BASE).expect("valid base URL");
+            let parsed = base
+                .join(url)
+                .map_err(|e| Error::bad_request(format!("invalid URL: {}", e)))?;
+            Ok((parsed, true))
+        }
+        Err(e) => Err(Error::bad_request(format!("invalid URL: {}", e))),
+    }
+}
+
+/// HMAC over the path and query
+fn mac(url: &Url, secret: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(url.path().as_bytes());
+    mac.update(b"?");
+    mac.update(url.query().unwrap_or("").as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret = b"secret";
+        let link = sign("/files/a b.pdf?user=7", Duration::from_secs(60), secret).unwrap();
+        assert!(link.starts_with("/files/a%20b.pdf?user=7&expires="));
+        assert!(verify(&link, secret).is_ok());
+        assert!(verify(&format!("https://cdn.example.com{}", link), secret).is_ok());
+
+        assert!(verify(&link.replace("user=7", "user=8"), secret).is_err());
+        assert!(verify(&link, b"other").is_err());
+        assert!(verify("/files/a%20b.pdf?user=7", secret).is_err());
+
+        let absolute = sign_until("https://example.com/confirm?t=1", 1, secret).unwrap();
+        assert!(absolute.starts_with("https://example.com/confirm?t=1&expires=1&signature="));
+        let err = verify(&absolute, secret).unwrap_err();
+        assert_eq!(err.to_string(), "Forbidden: link expired");
+    }
+}
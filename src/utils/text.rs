@@ -0,0 +1,252 @@
+//! Text Helpers
+//!
+//! Slugs, case conversion, truncation and ASCII transliteration.
+//!
+//! ```rust,ignore
+//! assert_eq!(slugify("Crème Brûlée: 10 Recipes!"), "creme-brulee-10-recipes");
+//! assert_eq!(to_snake_case("HTTPServerError"), "http_server_error");
+//! assert_eq!(truncate_words("one two three four", 2), "one two...");
+//! ```
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// URL-friendly version of `text`: transliterated, lowercase ASCII words
+/// joined by `-`
+pub fn slugify(text: &str) -> String {
+    slug_with(text, '-')
+}
+
+/// [`slugify`] with a custom separator, e.g. `_`
+pub fn slug_with(text: &str, separator: char) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let mut pending = false;
+    for c in transliterate(text).chars() {
+        if c.is_ascii_alphanumeric() {
+            if pending && !slug.is_empty() {
+                slug.push(separator);
+            }
+            pending = false;
+            slug.push(c.to_ascii_lowercase());
+        } else {
+            pending = true;
+        }
+    }
+    slug
+}
+
+/// Replace accented and non-Latin letters with ASCII approximations
+///
+/// Accents are stripped (`é` -> `e`), ligatures and special letters are
+/// spelled out (`ß` -> `ss`, `æ` -> `ae`), and Greek and Cyrillic are
+/// romanized. Characters without an approximation are kept.
+pub fn transliterate(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else if let Some(ascii) = special(c) {
+            if c.is_uppercase() {
+                out.push_str(&capitalize(ascii));
+            } else {
+                out.push_str(ascii);
+            }
+        } else {
+            let start = out.len();
+            out.extend(c.nfkd().filter(|c| !is_combining_mark(*c)));
+            if out[start..].is_empty() {
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+/// Lowercase romanization of letters without an ASCII decomposition
+fn special(c: char) -> Option<&'static str> {
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    let ascii = match lower {
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ø' => "o",
+        'đ' | 'ð' => "d",
+        'ł' => "l",
+        'þ' => "th",
+        'ı' => "i",
+        // Greek
+        'α' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' | 'έ' => "e",
+        'ζ' => "z",
+        'η' | 'ή' => "i",
+        'θ' => "th",
+        'ι' | 'ί' | 'ϊ' | 'ΐ' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ό' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' | 'ύ' | 'ϋ' | 'ΰ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' | 'ώ' => "o",
+        'ά' => "a",
+        // Cyrillic
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' => "e",
+        'ё' => "yo",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ы' => "y",
+        'э' => "e",
+        'ю' => "yu",
+        'я' => "ya",
+        'є' => "ye",
+        'і' => "i",
+        'ї' => "yi",
+        'ґ' => "g",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// First `max_words` words of `text`, followed by `...` when cut
+pub fn truncate_words(text: &str, max_words: usize) -> String {
+    let mut words = text.split_whitespace();
+    let kept: Vec<&str> = words.by_ref().take(max_words).collect();
+    let mut out = kept.join(" ");
+    if words.next().is_some() {
+        out.push_str("...");
+    }
+    out
+}
+
+/// Split identifiers and phrases into words: on non-alphanumeric
+/// characters, lower-to-upper changes (`userId`), and the end of
+/// acronyms (`HTTPServer`)
+fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for chunk in text.split(|c: char| !c.is_alphanumeric()) {
+        let chars: Vec<char> = chunk.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if i > 0 && c.is_uppercase() {
+                let prev = chars[i - 1];
+                let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower) {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// `user_id` from `userId`, `UserID` or `user id`
+pub fn to_snake_case(text: &str) -> String {
+    words(text)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// `user-id` from `userId`, `UserID` or `user id`
+pub fn to_kebab_case(text: &str) -> String {
+    words(text)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// `userId` from `user_id`, `UserID` or `user id`
+pub fn to_camel_case(text: &str) -> String {
+    let mut out = String::new();
+    for (i, word) in words(text).iter().enumerate() {
+        if i == 0 {
+            out.push_str(&word.to_lowercase());
+        } else {
+            out.push_str(&capitalize(word));
+        }
+    }
+    out
+}
+
+/// `UserId` from `user_id`, `userID` or `user id`
+pub fn to_pascal_case(text: &str) -> String {
+    words(text).iter().map(|w| capitalize(w)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_helpers() {
+        assert_eq!(
+            slugify("  Crème Brûlée: 10 Recipes! "),
+            "creme-brulee-10-recipes"
+        );
+        assert_eq!(slugify("Straße & Smørrebrød"), "strasse-smorrebrod");
+        assert_eq!(slugify("Привет, мир"), "privet-mir");
+        assert_eq!(slug_with("Hello World", '_'), "hello_world");
+        assert_eq!(transliterate("Ærø ﬁle Щука 東京"), "Aero file Shchuka 東京");
+
+        assert_eq!(to_snake_case("HTTPServerError"), "http_server_error");
+        assert_eq!(to_snake_case("userId2Fa"), "user_id2_fa");
+        assert_eq!(to_kebab_case("User Profile_page"), "user-profile-page");
+        assert_eq!(to_camel_case("user_profile-id"), "userProfileId");
+        assert_eq!(to_pascal_case("user profile ID"), "UserProfileId");
+
+        assert_eq!(truncate_words("one  two three four", 2), "one two...");
+        assert_eq!(truncate_words("one two", 2), "one two");
+    }
+}
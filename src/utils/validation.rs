@@ -0,0 +1,211 @@
+//! Validation helpers
+//!
+//! Format checks returning `bool`, shared by the model validation rules.
+
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use regex_automata::meta::Regex;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// RFC 5322 `addr-spec`: a dot-atom or quoted local part, and a host name
+/// or bracketed IP literal
+///
+/// Comments and folding whitespace are not accepted.
+pub fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.rsplit_once('@') else {
+        return false;
+    };
+    if local.is_empty() || local.len() > 64 || value.len() > 254 {
+        return false;
+    }
+
+    let local_ok = if let Some(quoted) = local
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        let mut escaped = false;
+        quoted.chars().all(|c| {
+            let ok = escaped || (c != '"' && (c == ' ' || c.is_ascii_graphic()));
+            escaped = !escaped && c == '\\';
+            ok
+        }) && !escaped
+    } else {
+        local.split('.').all(|atom| {
+            !atom.is_empty()
+                && atom
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c))
+        })
+    };
+
+    let domain_ok = match domain
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        Some(literal) => match literal.strip_prefix("IPv6:") {
+            Some(v6) => v6.parse::<Ipv6Addr>().is_ok(),
+            None => literal.parse::<Ipv4Addr>().is_ok(),
+        },
+        None => is_hostname(domain),
+    };
+
+    local_ok && domain_ok
+}
+
+/// Dot-separated labels of letters, digits and inner hyphens
+pub fn is_hostname(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 253
+        && value.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Absolute URL with a host, e.g. `https://example.com/path`
+pub fn is_url(value: &str) -> bool {
+    url::Url::parse(value)
+        .map(|url| url.has_host())
+        .unwrap_or(false)
+}
+
+/// Hyphenated UUID, e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`
+pub fn is_uuid(value: &str) -> bool {
+    value.len() == 36 && uuid::Uuid::parse_str(value).is_ok()
+}
+
+pub fn is_ip(value: &str) -> bool {
+    value.parse::<IpAddr>().is_ok()
+}
+
+pub fn is_ipv4(value: &str) -> bool {
+    value.parse::<Ipv4Addr>().is_ok()
+}
+
+pub fn is_ipv6(value: &str) -> bool {
+    value.parse::<Ipv6Addr>().is_ok()
+}
+
+/// E.164 phone number: `+`, a country code not starting with 0, and at
+/// most 15 digits in total
+pub fn is_phone(value: &str) -> bool {
+    let Some(digits) = value.strip_prefix('+') else {
+        return false;
+    };
+    (2..=15).contains(&digits.len())
+        && !digits.starts_with('0')
+        && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// ISO 8601 calendar date, `YYYY-MM-DD`
+pub fn is_date(value: &str) -> bool {
+    value.len() == 10 && is_date_format(value, "%Y-%m-%d")
+}
+
+/// Date in a `chrono` format, e.g. `"%d/%m/%Y"`
+pub fn is_date_format(value: &str, format: &str) -> bool {
+    chrono::NaiveDate::parse_from_str(value, format).is_ok()
+}
+
+/// Card number of 12 to 19 digits passing the Luhn check; spaces and
+/// hyphens between digit groups are ignored
+pub fn is_credit_card(value: &str) -> bool {
+    let digits: String = value.chars().filter(|c| *c != ' ' && *c != '-').collect();
+    (12..=19).contains(&digits.len()) && luhn(&digits)
+}
+
+/// Luhn checksum over a string of ASCII digits
+pub fn luhn(digits: &str) -> bool {
+    let mut sum = 0;
+    for (i, c) in digits.chars().rev().enumerate() {
+        let Some(mut digit) = c.to_digit(10) else {
+            return false;
+        };
+        if i % 2 == 1 {
+            digit *= 2;
+            if digit > 9 {
+                digit -= 9;
+            }
+        }
+        sum += digit;
+    }
+    !digits.is_empty() && sum % 10 == 0
+}
+
+static PATTERNS: Lazy<RwLock<HashMap<String, Regex>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Whether `pattern` matches anywhere in `value`; anchor it with `^...$`
+/// to match the whole value
+///
+/// Compiled patterns are cached. Panics if `pattern` is not a valid regex.
+pub fn matches(value: &str, pattern: &str) -> bool {
+    if let Some(regex) = PATTERNS.read().get(pattern) {
+        return regex.is_match(value);
+    }
+    let regex = Regex::new(pattern)
+        .unwrap_or_else(|e| panic!("invalid validation pattern `{}`: {}", pattern, e));
+    let matched = regex.is_match(value);
+    PATTERNS.write().insert(pattern.to_string(), regex);
+    matched
+}
+
+pub fn min_length(value: &str, min: usize) -> bool {
+    value.len() >= min
+}
+pub fn max_length(value: &str, max: usize) -> bool {
+    value.len() <= max
+}
+pub fn is_numeric(value: &str) -> bool {
+    value.chars().all(|c| c.is_numeric())
+}
+pub fn is_alphanumeric(value: &str) -> bool {
+    value.chars().all(|c| c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        for email in [
+            "a@example.com",
+            "first.last+tag@sub.example.co.uk",
+            "\"john doe\"@example.com",
+            "user@[192.168.0.1]",
+            "user@localhost",
+        ] {
+            assert!(is_email(email), "{}", email);
+        }
+        for email in [
+            "a.example.com",
+            "a@",
+            "@example.com",
+            "a..b@example.com",
+            ".a@example.com",
+            "a@-example.com",
+            "a@example..com",
+            "a b@example.com",
+            "a@b@example.com",
+        ] {
+            assert!(!is_email(email), "{}", email);
+        }
+
+        assert!(is_url("https://example.com/a?b=c"));
+        assert!(!is_url("example.com"));
+        assert!(is_uuid("67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!is_uuid("67e5504410b1426f9247bb680e5fe0c8"));
+        assert!(is_ip("::1") && is_ipv4("10.0.0.1") && !is_ipv4("::1"));
+        assert!(is_phone("+14155552671"));
+        assert!(!is_phone("4155552671") && !is_phone("+04155552671"));
+        assert!(is_date("2024-02-29") && !is_date("2023-02-29") && !is_date("2024-2-9"));
+        assert!(is_credit_card("4111 1111 1111 1111"));
+        assert!(!is_credit_card("4111 1111 1111 1112"));
+        assert!(matches("AB-123", r"^[A-Z]{2}-\d{3}$"));
+        assert!(!matches("AB-12", r"^[A-Z]{2}-\d{3}$"));
+    }
+}
@@ -0,0 +1,112 @@
+//! Webhook Signatures
+//!
+//! Signs webhook bodies sent by [`webhooks`](crate::webhooks) and checks
+//! them on the receiving side. The `X-Webhook-Signature` header carries a
+//! timestamp and an HMAC-SHA256 over `"{timestamp}.{body}"`:
+//!
+//! ```text
+//! X-Webhook-Signature: t=1700000000,v1=5257a869e7ecebeda32affa62cdca3fa51cad7e77a0e56ff536d0ce8e108d8bd
+//! ```
+//!
+//! Several `v1` values may be present while a secret is being rotated.
+//!
+//! ```rust,ignore
+//! app.post("/hooks/orders", |req, res| async move {
+//!     req.verify_webhook(b"whsec_...", Duration::from_secs(300))?;
+//!     let event: Value = req.json()?;
+//!     Ok::<_, Error>(res.status(204))
+//! });
+//! ```
+
+use crate::error::{Error, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+
+/// Header holding the signature
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Signature header value for `body` sent at the Unix time `timestamp`
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let digest = mac(secret, timestamp, body).finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("t={},v1={}", timestamp, hex)
+}
+
+/// Check a signature header against `body`, failing with
+/// [`Error::Unauthorized`] when no signature matches or the timestamp is
+/// more than `tolerance` away from now
+pub fn verify(secret: &[u8], header: &str, body: &[u8], tolerance: Duration) -> Result<()> {
+    let invalid = |reason: &str| Error::Unauthorized(format!("webhook signature {}", reason));
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.extend(decode_hex(value)),
+            _ => {}
+        }
+    }
+    let timestamp = timestamp.ok_or_else(|| invalid("has no timestamp"))?;
+    if signatures.is_empty() {
+        return Err(invalid("is missing"));
+    }
+    let mac = mac(secret, timestamp, body);
+    if !signatures
+        .iter()
+        .any(|signature| mac.clone().verify_slice(signature).is_ok())
+    {
+        return Err(invalid("does not match"));
+    }
+    let age = (chrono::Utc::now().timestamp() - timestamp).unsigned_abs();
+    if age > tolerance.as_secs() {
+        return Err(invalid("has expired"));
+    }
+    Ok(())
+}
+
+fn mac(secret: &[u8], timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let now = chrono::Utc::now().timestamp();
+        let body = br#"{"event":"order.paid"}"#;
+        let tolerance = Duration::from_secs(300);
+        let header = sign(b"secret", now, body);
+        assert!(header.starts_with(&format!("t={},v1=", now)));
+        assert!(verify(b"secret", &header, body, tolerance).is_ok());
+
+        let rotated = format!("{},v1={}", header, "00".repeat(32));
+        assert!(verify(b"secret", &rotated, body, tolerance).is_ok());
+        assert!(verify(b"other", &header, body, tolerance).is_err());
+        assert!(verify(b"secret", &header, b"{}", tolerance).is_err());
+        assert!(verify(b"secret", "v1=abcd", body, tolerance).is_err());
+
+        let stale = sign(b"secret", now - 600, body);
+        let err = verify(b"secret", &stale, body, tolerance).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unauthorized: webhook signature has expired"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Views Module
+//!
+//! Server-rendered HTML through a pluggable [`ViewEngine`]. Adapters for
+//! [Tera](https://keats.github.io/tera/) and
+//! [Handlebars](https://docs.rs/handlebars) are available with the `tera`
+//! and `handlebars` features; other engines implement the trait.
+//!
+//! ```rust,ignore
+//! app.views(
+//!     Views::new(TeraEngine::new("templates/**/*.html")?)
+//!         .layout("layouts/main.html")
+//!         .error_template("error.html"),
+//! );
+//!
+//! app.get("/users/:id", |req, res| async move {
+//!     let user = find_user(req.param("id")).await?;
+//!     Ok::<_, Error>(res.render("users/show.html", json!({ "user": user })))
+//! });
+//! ```
+//!
+//! Engines parse templates once and cache them. With `env` set to
+//! `development` (the default) templates are re-read before every render,
+//! so edits show up without a restart; [`Views::reload`] overrides this.
+
+use crate::error::{public_message, public_trace, Error, Result};
+use crate::response::Response;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// A template engine
+pub trait ViewEngine: Send + Sync {
+    /// Render the template `name` with `context`, a JSON object
+    fn render(&self, name: &str, context: &Value) -> Result<String>;
+
+    /// Re-read templates from their source, called before each render when
+    /// reloading is enabled
+    fn reload(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A [`ViewEngine`] with app-wide rendering options
+///
+/// Registered with [`RustyX::views`](crate::RustyX::views).
+#[derive(Clone)]
+pub struct Views {
+    engine: Arc<dyn ViewEngine>,
+    layout: Option<String>,
+    error_template: Option<String>,
+    reload: Option<bool>,
+}
+
+impl std::fmt::Debug for Views {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Views")
+            .field("layout", &self.layout)
+            .field("error_template", &self.error_template)
+            .field("reload", &self.reload)
+            .finish()
+    }
+}
+
+impl Views {
+    pub fn new(engine: impl ViewEngine + 'static) -> Self {
+        Self {
+            engine: Arc::new(engine),
+            layout: None,
+            error_template: None,
+            reload: None,
+        }
+    }
+
+    /// Wrap every rendered view in the template `name`, which receives the
+    /// view's context plus its HTML as `content`
+    pub fn layout(mut self, name: &str) -> Self {
+        self.layout = Some(name.to_string());
+        self
+    }
+
+    /// Render [`ErrorFormat::Html`](crate::ErrorFormat::Html) error pages
+    /// with the template `name`
+    ///
+    /// The context has `status`, `title`, `message`, validation `errors`
+    /// and, in development, the error `trace`.
+    pub fn error_template(mut self, name: &str) -> Self {
+        self.error_template = Some(name.to_string());
+        self
+    }
+
+    /// Re-read templates before every render, regardless of `env`
+    pub fn reload(mut self, reload: bool) -> Self {
+        self.reload = Some(reload);
+        self
+    }
+
+    /// Re-read templates now, e.g. when a watcher sees them change
+    #[cfg(feature = "dev")]
+    pub(crate) fn reload_engine(&self) -> Result<()> {
+        self.engine.reload()
+    }
+
+    /// Render `name` without the layout, e.g. for email bodies
+    #[cfg(feature = "mail")]
+    pub(crate) fn render_content(&self, name: &str, context: &Value) -> Result<String> {
+        self.engine.render(name, context)
+    }
+
+    pub(crate) fn reloads(&self, development: bool) -> bool {
+        self.reload.unwrap_or(development)
+    }
+
+    /// Render `name` and its layout
+    pub fn render(&self, name: &str, context: &Value, reload: bool) -> Result<String> {
+        if reload {
+            self.engine.reload()?;
+        }
+        let html = self.engine.render(name, context)?;
+        match &self.layout {
+            Some(layout) => {
+                let mut context = context.clone();
+                if let Value::Object(map) = &mut context {
+                    map.insert("content".to_string(), Value::String(html));
+                }
+                self.engine.render(layout, &context)
+            }
+            None => Ok(html),
+        }
+    }
+
+    /// An error page from the error template, or `None` without one or
+    /// when rendering it fails
+    pub(crate) fn error_page(
+        &self,
+        error: &Error,
+        development: bool,
+        reload: bool,
+    ) -> Option<Response> {
+        let template = self.error_template.as_ref()?;
+        let res = Response::new().status(error.status_code());
+        let mut context = json!({
+            "status": error.status_code(),
+            "title": res.get_status().canonical_reason().unwrap_or("Error"),
+            "message": public_message(error, development),
+        });
+        if let Error::ValidationFields(fields) = error.root() {
+            context["errors"] = json!(fields);
+        }
+        if let Some(trace) = public_trace(error, development) {
+            context["trace"] = json!(trace);
+        }
+        match self.render(template, &context, reload) {
+            Ok(html) => Some(res.html(html)),
+            Err(e) => {
+                tracing::error!("Failed to render error template {}: {}", template, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "tera", feature = "handlebars"))]
+fn template_error(e: impl std::fmt::Display) -> Error {
+    Error::Internal(format!("template error: {}", e))
+}
+
+/// [Tera](https://keats.github.io/tera/) templates loaded from a glob
+///
+/// Templates are named by their path relative to the glob's directory,
+/// e.g. `users/show.html`, and use Tera's own `extends` / `include` for
+/// layouts and partials.
+#[cfg(feature = "tera")]
+pub struct TeraEngine {
+    tera: std::sync::RwLock<tera::Tera>,
+}
+
+#[cfg(feature = "tera")]
+impl TeraEngine {
+    /// Load templates matching `glob`, e.g. `templates/**/*.html`
+    pub fn new(glob: &str) -> Result<Self> {
+        Ok(Self::from_tera(
+            tera::Tera::new(glob).map_err(template_error)?,
+        ))
+    }
+
+    /// Use a configured `Tera`, e.g. with custom filters
+    pub fn from_tera(tera: tera::Tera) -> Self {
+        Self {
+            tera: std::sync::RwLock::new(tera),
+        }
+    }
+}
+
+#[cfg(feature = "tera")]
+impl ViewEngine for TeraEngine {
+    fn render(&self, name: &str, context: &Value) -> Result<String> {
+        let context = tera::Context::from_value(context.clone()).map_err(template_error)?;
+        self.tera
+            .read()
+            .unwrap()
+            .render(name, &context)
+            .map_err(template_error)
+    }
+
+    fn reload(&self) -> Result<()> {
+        self.tera
+            .write()
+            .unwrap()
+            .full_reload()
+            .map_err(template_error)
+    }
+}
+
+/// [Handlebars](https://docs.rs/handlebars) templates loaded from a
+/// directory
+///
+/// Every `.hbs` file is registered under its path without the extension,
+/// e.g. `users/show`, and can be used as a partial (`{{> partials/nav}}`)
+/// or a layout (`{{#> layouts/main}}...{{/layouts/main}}`).
+#[cfg(feature = "handlebars")]
+pub struct HandlebarsEngine {
+    dir: Option<std::path::PathBuf>,
+    handlebars: std::sync::RwLock<handlebars::Handlebars<'static>>,
+}
+
+#[cfg(feature = "handlebars")]
+impl HandlebarsEngine {
+    /// Load the `.hbs` templates under `dir`
+    pub fn new(dir: impl Into<std::path::PathBuf>) -> Result<Self> {
+        let engine = Self {
+            dir: Some(dir.into()),
+            handlebars: std::sync::RwLock::new(handlebars::Handlebars::new()),
+        };
+        engine.reload()?;
+        Ok(engine)
+    }
+
+    /// Use a configured `Handlebars` registry, e.g. with helpers or
+    /// templates registered from strings
+    pub fn from_handlebars(handlebars: handlebars::Handlebars<'static>) -> Self {
+        Self {
+            dir: None,
+            handlebars: std::sync::RwLock::new(handlebars),
+        }
+    }
+}
+
+#[cfg(feature = "handlebars")]
+impl ViewEngine for HandlebarsEngine {
+    fn render(&self, name: &str, context: &Value) -> Result<String> {
+        self.handlebars
+            .read()
+            .unwrap()
+            .render(name, context)
+            .map_err(template_error)
+    }
+
+    fn reload(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let mut handlebars = self.handlebars.write().unwrap();
+        handlebars.clear_templates();
+        handlebars
+            .register_templates_directory(dir, handlebars::DirectorySourceOptions::default())
+            .map_err(template_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    /// Replaces `{{key}}` with top-level context values
+    struct Simple;
+
+    impl ViewEngine for Simple {
+        fn render(&self, name: &str, context: &Value) -> crate::Result<String> {
+            let template = match name {
+                "layout" => "<main>{{content}}</main>",
+                "hello" => "Hello {{name}}",
+                "error" => "{{status}}: {{message}}",
+                _ => return Err(Error::not_found(format!("template {}", name))),
+            };
+            let mut html = template.to_string();
+            if let Value::Object(map) = context {
+                for (key, value) in map {
+                    let value = value.as_str().map_or(value.to_string(), String::from);
+                    html = html.replace(&format!("{{{{{}}}}}", key), &value);
+                }
+            }
+            Ok(html)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_render_views_and_error_pages() {
+        let app = RustyX::new();
+        app.views(Views::new(Simple).layout("layout").error_template("error"));
+        app.error_format(ErrorFormat::Html);
+        app.get("/hello", |_req, res| async move {
+            res.render("hello", json!({ "name": "Ann" }))
+        });
+        app.get("/broken", |_req, res| async move {
+            res.render("missing", json!({}))
+        });
+
+        app.test()
+            .get("/hello")
+            .send()
+            .await
+            .assert_status(200)
+            .assert_header("content-type", "text/html");
+        let res = app.test().get("/hello").send().await;
+        assert_eq!(res.text(), "<main>Hello Ann</main>");
+
+        let res = app.test().get("/nowhere").send().await;
+        res.assert_status(404);
+        assert_eq!(res.text(), "<main>404: Not found: /nowhere</main>");
+
+        let res = app.test().get("/broken").send().await;
+        res.assert_status(404);
+        assert!(res.text().contains("template missing"));
+    }
+
+    #[cfg(feature = "tera")]
+    #[test]
+    fn test_tera_engine() {
+        let mut tera = tera::Tera::default();
+        tera.add_raw_template("hello.html", "Hello {{ name }}")
+            .unwrap();
+        let engine = TeraEngine::from_tera(tera);
+        let html = engine
+            .render("hello.html", &json!({ "name": "<b>" }))
+            .unwrap();
+        assert_eq!(html, "Hello &lt;b&gt;");
+    }
+
+    #[cfg(feature = "handlebars")]
+    #[test]
+    fn test_handlebars_engine() {
+        let mut handlebars = handlebars::Handlebars::new();
+        handlebars
+            .register_template_string("hello", "Hello {{name}}")
+            .unwrap();
+        let engine = HandlebarsEngine::from_handlebars(handlebars);
+        let html = engine.render("hello", &json!({ "name": "<b>" })).unwrap();
+        assert_eq!(html, "Hello &lt;b&gt;");
+    }
+}
@@ -0,0 +1,365 @@
+//! Outgoing Webhooks
+//!
+//! Registers endpoints for events and POSTs each event to them as signed
+//! JSON, retrying failures with exponential backoff and keeping a log of
+//! deliveries. Receivers check the signature with
+//! [`Request::verify_webhook`](crate::Request::verify_webhook) or
+//! [`utils::webhook::verify`](crate::utils::webhook::verify).
+//!
+//! ```rust,ignore
+//! let webhooks = Webhooks::new().retries(5, Duration::from_secs(2));
+//! webhooks.register("https://partner.example/hooks", b"whsec_...", &["order.paid"]);
+//!
+//! app.post("/orders/:id/pay", move |req, res| {
+//!     let webhooks = webhooks.clone();
+//!     async move {
+//!         let order = pay(req.param("id").unwrap()).await?;
+//!         webhooks.dispatch("order.paid", &order)?;
+//!         Ok::<_, Error>(res.json(order))
+//!     }
+//! });
+//! ```
+//!
+//! Each request carries `X-Webhook-Id` (the same for every attempt),
+//! `X-Webhook-Event`, `X-Webhook-Signature` and a body of the form
+//!
+//! ```json
+//! { "id": "…", "event": "order.paid", "created_at": "2024-01-01T00:00:00Z", "data": { … } }
+//! ```
+
+use crate::error::Result;
+use crate::utils::webhook::{sign, SIGNATURE_HEADER};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+type DeliveryHook = Arc<dyn Fn(&Delivery) + Send + Sync>;
+
+/// A URL receiving events
+#[derive(Clone)]
+pub struct WebhookEndpoint {
+    pub id: String,
+    pub url: String,
+    secret: Vec<u8>,
+    /// Event names, or `*` for every event
+    pub events: Vec<String>,
+}
+
+impl std::fmt::Debug for WebhookEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookEndpoint")
+            .field("id", &self.id)
+            .field("url", &self.url)
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+impl WebhookEndpoint {
+    fn accepts(&self, event: &str) -> bool {
+        self.events.iter().any(|e| e == "*" || e == event)
+    }
+}
+
+/// One try at delivering an event
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryAttempt {
+    pub at: DateTime<Utc>,
+    /// Response status, `None` when no response arrived
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// The outcome of delivering an event to an endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct Delivery {
+    /// Sent as `X-Webhook-Id`
+    pub id: String,
+    pub endpoint_id: String,
+    pub url: String,
+    pub event: String,
+    pub delivered: bool,
+    pub attempts: Vec<DeliveryAttempt>,
+}
+
+/// Registered endpoints and the delivery log
+///
+/// Cloning is cheap and clones share endpoints and log.
+#[derive(Clone)]
+pub struct Webhooks {
+    endpoints: Arc<RwLock<Vec<WebhookEndpoint>>>,
+    log: Arc<RwLock<VecDeque<Delivery>>>,
+    client: reqwest::Client,
+    retries: u32,
+    backoff: Duration,
+    log_capacity: usize,
+    on_delivery: Option<DeliveryHook>,
+}
+
+impl std::fmt::Debug for Webhooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Webhooks")
+            .field("endpoints", &self.endpoints.read())
+            .field("retries", &self.retries)
+            .field("backoff", &self.backoff)
+            .finish()
+    }
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Webhooks {
+    /// Retry 5 times from 1 second, with a 10 second request timeout,
+    /// keeping the last 1000 deliveries
+    pub fn new() -> Self {
+        Self {
+            endpoints: Arc::new(RwLock::new(Vec::new())),
+            log: Arc::new(RwLock::new(VecDeque::new())),
+            client: client(Duration::from_secs(10)),
+            retries: 5,
+            backoff: Duration::from_secs(1),
+            log_capacity: 1000,
+            on_delivery: None,
+        }
+    }
+
+    /// Retry failed deliveries `retries` times, waiting `backoff` and
+    /// doubling it each time
+    pub fn retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up on a request after `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client = client(timeout);
+        self
+    }
+
+    /// Keep the last `capacity` deliveries in [`deliveries`](Self::deliveries)
+    pub fn log_capacity(mut self, capacity: usize) -> Self {
+        self.log_capacity = capacity;
+        self
+    }
+
+    /// Call `hook` with every finished delivery, e.g. to store the log
+    pub fn on_delivery(mut self, hook: impl Fn(&Delivery) + Send + Sync + 'static) -> Self {
+        self.on_delivery = Some(Arc::new(hook));
+        self
+    }
+
+    /// Send `events` (or `*` for all) to `url`, signed with `secret`,
+    /// returning the endpoint id
+    pub fn register(&self, url: &str, secret: &[u8], events: &[&str]) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.endpoints.write().push(WebhookEndpoint {
+            id: id.clone(),
+            url: url.to_string(),
+            secret: secret.to_vec(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+        });
+        id
+    }
+
+    /// Remove an endpoint, returning whether it existed
+    pub fn unregister(&self, endpoint_id: &str) -> bool {
+        let mut endpoints = self.endpoints.write();
+        let before = endpoints.len();
+        endpoints.retain(|e| e.id != endpoint_id);
+        endpoints.len() != before
+    }
+
+    pub fn endpoints(&self) -> Vec<WebhookEndpoint> {
+        self.endpoints.read().clone()
+    }
+
+    /// Recent deliveries, oldest first
+    pub fn deliveries(&self) -> Vec<Delivery> {
+        self.log.read().iter().cloned().collect()
+    }
+
+    /// Send `event` to every endpoint registered for it, in the background
+    ///
+    /// Serialization errors are returned right away. The handles resolve
+    /// with each delivery once it succeeds or runs out of retries.
+    pub fn dispatch<T: Serialize + ?Sized>(
+        &self,
+        event: &str,
+        data: &T,
+    ) -> Result<Vec<JoinHandle<Delivery>>> {
+        let data = serde_json::to_value(data)?;
+        let endpoints: Vec<_> = self
+            .endpoints
+            .read()
+            .iter()
+            .filter(|e| e.accepts(event))
+            .cloned()
+            .collect();
+        let mut handles = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            let id = uuid::Uuid::new_v4().to_string();
+            let body = serde_json::to_vec(&serde_json::json!({
+                "id": id,
+                "event": event,
+                "created_at": Utc::now(),
+                "data": data,
+            }))?;
+            let webhooks = self.clone();
+            let event = event.to_string();
+            handles.push(tokio::spawn(async move {
+                let delivery = webhooks.deliver(endpoint, id, event, body).await;
+                webhooks.record(&delivery);
+                delivery
+            }));
+        }
+        Ok(handles)
+    }
+
+    async fn deliver(
+        &self,
+        endpoint: WebhookEndpoint,
+        id: String,
+        event: String,
+        body: Vec<u8>,
+    ) -> Delivery {
+        let mut delivery = Delivery {
+            id,
+            endpoint_id: endpoint.id.clone(),
+            url: endpoint.url.clone(),
+            event,
+            delivered: false,
+            attempts: Vec::new(),
+        };
+        let mut backoff = self.backoff;
+        loop {
+            let at = Utc::now();
+            let started = Instant::now();
+            let signature = sign(&endpoint.secret, at.timestamp(), &body);
+            let result = self
+                .client
+                .post(&endpoint.url)
+                .header("content-type", "application/json")
+                .header("x-webhook-id", &delivery.id)
+                .header("x-webhook-event", &delivery.event)
+                .header(SIGNATURE_HEADER, signature)
+                .body(body.clone())
+                .send()
+                .await;
+            let (status, error) = match result {
+                Ok(res) if res.status().is_success() => (Some(res.status().as_u16()), None),
+                Ok(res) => (
+                    Some(res.status().as_u16()),
+                    Some(format!("endpoint answered {}", res.status())),
+                ),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            delivery.delivered = error.is_none();
+            delivery.attempts.push(DeliveryAttempt {
+                at,
+                status,
+                error,
+                duration_ms: started.elapsed().as_millis() as u64,
+            });
+            if delivery.delivered || delivery.attempts.len() > self.retries as usize {
+                if !delivery.delivered {
+                    warn!(
+                        "Giving up on webhook {} to {} after {} attempts",
+                        delivery.event,
+                        delivery.url,
+                        delivery.attempts.len()
+                    );
+                }
+                return delivery;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    fn record(&self, delivery: &Delivery) {
+        if let Some(hook) = &self.on_delivery {
+            hook(delivery);
+        }
+        let mut log = self.log.write();
+        log.push_back(delivery.clone());
+        while log.len() > self.log_capacity {
+            log.pop_front();
+        }
+    }
+}
+
+fn client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("RustyX-Webhooks/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("valid HTTP client configuration")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_signed_delivery_with_retry() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let receiver = RustyX::new();
+        let counter = Arc::clone(&calls);
+        receiver.post("/hooks", move |req, res| {
+            let first = counter.fetch_add(1, Ordering::SeqCst) == 0;
+            async move {
+                if first {
+                    return Ok(res.status(500));
+                }
+                req.verify_webhook(b"secret", Duration::from_secs(60))?;
+                let body: Value = req.json()?;
+                assert_eq!(body["data"]["order"], 7);
+                assert_eq!(req.header("x-webhook-event"), Some("order.paid"));
+                Ok::<_, Error>(res.status(204))
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        tokio::spawn(receiver.serve(listener, None));
+
+        let logged = Arc::new(AtomicU32::new(0));
+        let hook_count = Arc::clone(&logged);
+        let webhooks = Webhooks::new()
+            .retries(1, Duration::from_millis(10))
+            .on_delivery(move |_| {
+                hook_count.fetch_add(1, Ordering::SeqCst);
+            });
+        webhooks.register(&url, b"secret", &["order.paid"]);
+        let refunds = webhooks.register(&url, b"secret", &["order.refunded"]);
+
+        let handles = webhooks
+            .dispatch("order.paid", &json!({ "order": 7 }))
+            .unwrap();
+        assert_eq!(handles.len(), 1);
+        let delivery = handles.into_iter().next().unwrap().await.unwrap();
+        assert!(delivery.delivered);
+        let statuses: Vec<_> = delivery.attempts.iter().map(|a| a.status).collect();
+        assert_eq!(statuses, [Some(500), Some(204)]);
+        assert_eq!(webhooks.deliveries().len(), 1);
+        assert_eq!(logged.load(Ordering::SeqCst), 1);
+
+        assert!(webhooks.unregister(&refunds));
+        assert!(webhooks
+            .dispatch("order.refunded", &json!({}))
+            .unwrap()
+            .is_empty());
+    }
+}
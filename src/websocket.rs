@@ -1,45 +1,131 @@
 //! WebSocket Support Module
 //!
 //! Provides WebSocket functionality for real-time communication.
+//!
+//! Register an endpoint with [`RustyX::ws`](crate::RustyX::ws); the
+//! handshake and framing follow RFC 6455.
+
+mod adapter;
+mod connection;
+mod deflate;
+mod events;
+mod metrics;
+mod protocol;
+mod queue;
+mod stream;
 
+#[cfg(feature = "redis")]
+pub use adapter::RedisWsAdapter;
+pub use adapter::{WsAdapter, WsEnvelope};
+pub use connection::WsConn;
+pub use deflate::DeflateConfig;
+pub use events::WsEvents;
+pub use metrics::{WsConnInfo, WsHistogram, WsMetrics};
+pub use protocol::accept_key;
+pub use queue::{Backpressure, QueueStats, WsReceiver, WsSender};
+pub use stream::{StreamConfig, WsStream};
+
+use crate::request::Request;
+use crate::response::Response;
+use hyper_util::rt::TokioIo;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use tracing::warn;
 
 /// WebSocket connection ID
 pub type ConnectionId = String;
 
 /// WebSocket message types
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WsMessage {
     Text(String),
     Binary(Vec<u8>),
     Ping(Vec<u8>),
     Pong(Vec<u8>),
-    Close,
+    /// Close the connection, optionally with a status code and reason
+    Close(Option<CloseFrame>),
+}
+
+/// Status code and reason of a close frame
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloseFrame {
+    pub code: u16,
+    pub reason: String,
+}
+
+impl CloseFrame {
+    /// Normal closure
+    pub const NORMAL: u16 = 1000;
+    /// Endpoint going away (server shutdown, page navigation)
+    pub const GOING_AWAY: u16 = 1001;
+    /// Protocol error
+    pub const PROTOCOL_ERROR: u16 = 1002;
+    /// Message violated a policy
+    pub const POLICY_VIOLATION: u16 = 1008;
+    /// Message too big to process
+    pub const TOO_BIG: u16 = 1009;
+    /// Unexpected server error
+    pub const INTERNAL_ERROR: u16 = 1011;
+    /// Server is restarting
+    pub const RESTART: u16 = 1012;
+    /// Temporary overload, try again later
+    pub const TRY_AGAIN_LATER: u16 = 1013;
+
+    /// Create a close frame
+    pub fn new(code: u16, reason: impl Into<String>) -> Self {
+        Self {
+            code,
+            reason: reason.into(),
+        }
+    }
 }
 
 /// WebSocket connection handler trait
+///
+/// Each callback receives the [`WsConn`] it concerns, which can reply to
+/// the client directly (`conn.send_text(..)`) or close the connection.
 pub trait WsHandler: Send + Sync {
     /// Called when a new connection is established
-    fn on_open(&self, conn_id: &ConnectionId);
+    fn on_open(&self, conn: &WsConn);
 
     /// Called when a message is received
-    fn on_message(&self, conn_id: &ConnectionId, message: WsMessage);
+    fn on_message(&self, conn: &WsConn, message: WsMessage);
 
-    /// Called when a connection is closed
-    fn on_close(&self, conn_id: &ConnectionId);
+    /// Called when a connection is closed, with the client's close frame if
+    /// it sent one (`None` when the connection dropped or timed out)
+    fn on_close(&self, conn: &WsConn, frame: Option<CloseFrame>);
 
     /// Called when an error occurs
-    fn on_error(&self, conn_id: &ConnectionId, error: String);
+    fn on_error(&self, conn: &WsConn, error: String);
+
+    /// Called when the client starts a binary stream (requires
+    /// [`WsConfig::streams`]); dropping the stream cancels it
+    fn on_stream(&self, conn: &WsConn, stream: WsStream) {
+        let _ = (conn, stream);
+    }
+}
+
+/// A room member and the metadata it joined with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsPresence {
+    /// Member connection
+    pub id: ConnectionId,
+    /// Application data shown to other members (name, avatar, status)
+    pub meta: serde_json::Value,
+    /// When the member joined
+    pub joined_at: chrono::DateTime<chrono::Utc>,
 }
 
 /// WebSocket room for group messaging
 #[derive(Debug, Clone)]
 pub struct WsRoom {
     name: String,
-    members: Arc<RwLock<Vec<ConnectionId>>>,
+    members: Arc<RwLock<Vec<WsPresence>>>,
+    announce: Arc<std::sync::atomic::AtomicBool>,
 }
 
 impl WsRoom {
@@ -48,28 +134,73 @@ impl WsRoom {
         Self {
             name: name.to_string(),
             members: Arc::new(RwLock::new(Vec::new())),
+            announce: Arc::default(),
         }
     }
 
     /// Join the room
-    pub fn join(&self, conn_id: ConnectionId) {
+    pub fn join(&self, conn_id: ConnectionId) -> bool {
+        self.join_with(conn_id, serde_json::Value::Null)
+    }
+
+    /// Join the room with presence metadata; returns `false` if already a member
+    pub fn join_with(&self, conn_id: ConnectionId, meta: serde_json::Value) -> bool {
         let mut members = self.members.write();
-        if !members.contains(&conn_id) {
-            members.push(conn_id);
+        if members.iter().any(|member| member.id == conn_id) {
+            return false;
         }
+        members.push(WsPresence {
+            id: conn_id,
+            meta,
+            joined_at: chrono::Utc::now(),
+        });
+        true
     }
 
-    /// Leave the room
-    pub fn leave(&self, conn_id: &ConnectionId) {
+    /// Leave the room; returns `false` if not a member
+    pub fn leave(&self, conn_id: &ConnectionId) -> bool {
         let mut members = self.members.write();
-        members.retain(|id| id != conn_id);
+        let before = members.len();
+        members.retain(|member| member.id != *conn_id);
+        members.len() != before
+    }
+
+    /// Replace a member's presence metadata
+    pub fn set_meta(&self, conn_id: &ConnectionId, meta: serde_json::Value) -> bool {
+        let mut members = self.members.write();
+        match members.iter_mut().find(|member| member.id == *conn_id) {
+            Some(member) => {
+                member.meta = meta;
+                true
+            }
+            None => false,
+        }
     }
 
     /// Get all members
     pub fn members(&self) -> Vec<ConnectionId> {
+        self.members
+            .read()
+            .iter()
+            .map(|member| member.id.clone())
+            .collect()
+    }
+
+    /// Snapshot of the members and their metadata, in join order
+    pub fn presence(&self) -> Vec<WsPresence> {
         self.members.read().clone()
     }
 
+    /// Notify members when someone joins or leaves through [`WsServer`]
+    ///
+    /// Members get `presence:join` / `presence:leave` events (see
+    /// [`WsEvents`]) whose data is the [`WsPresence`] entry plus the room name.
+    pub fn announce_presence(&self, enabled: bool) -> &Self {
+        self.announce
+            .store(enabled, std::sync::atomic::Ordering::Relaxed);
+        self
+    }
+
     /// Get room name
     pub fn name(&self) -> &str {
         &self.name
@@ -79,13 +210,23 @@ impl WsRoom {
     pub fn count(&self) -> usize {
         self.members.read().len()
     }
+
+    /// Whether join/leave notifications are enabled
+    fn announces(&self) -> bool {
+        self.announce.load(std::sync::atomic::Ordering::Relaxed)
+    }
 }
 
 /// WebSocket server for managing connections
 #[derive(Clone)]
 pub struct WsServer {
-    connections: Arc<RwLock<HashMap<ConnectionId, mpsc::Sender<WsMessage>>>>,
+    connections: Arc<RwLock<HashMap<ConnectionId, WsSender>>>,
+    /// Handles of the connections this server accepted, for introspection
+    handles: Arc<RwLock<HashMap<ConnectionId, WsConn>>>,
     rooms: Arc<RwLock<HashMap<String, WsRoom>>>,
+    adapter: Arc<RwLock<Option<Arc<dyn WsAdapter>>>>,
+    node_id: Arc<str>,
+    metrics: Arc<metrics::Counters>,
 }
 
 impl WsServer {
@@ -93,61 +234,178 @@ impl WsServer {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            handles: Arc::new(RwLock::new(HashMap::new())),
             rooms: Arc::new(RwLock::new(HashMap::new())),
+            adapter: Arc::new(RwLock::new(None)),
+            node_id: uuid::Uuid::new_v4().to_string().into(),
+            metrics: Arc::default(),
         }
     }
 
     /// Register a new connection
-    pub fn register(&self, conn_id: ConnectionId, sender: mpsc::Sender<WsMessage>) {
+    pub fn register(&self, conn_id: ConnectionId, sender: WsSender) {
         let mut connections = self.connections.write();
         connections.insert(conn_id, sender);
+        self.metrics.opened();
+    }
+
+    /// Register a connection accepted by this server, keeping its handle
+    pub(crate) fn register_conn(&self, conn: &WsConn) {
+        self.handles.write().insert(conn.id().clone(), conn.clone());
+        self.register(conn.id().clone(), conn.sender());
     }
 
     /// Unregister a connection
     pub fn unregister(&self, conn_id: &ConnectionId) {
         let mut connections = self.connections.write();
-        connections.remove(conn_id);
+        let removed = connections.remove(conn_id).is_some();
+
+        drop(connections);
+
+        let conn = self.handles.write().remove(conn_id);
+        if removed {
+            self.metrics.closed(conn.as_ref().map(WsConn::connected_at));
+        }
 
         // Remove from all rooms
-        let rooms = self.rooms.read();
-        for room in rooms.values() {
-            room.leave(conn_id);
+        let rooms: Vec<WsRoom> = self.rooms.read().values().cloned().collect();
+        for room in rooms {
+            self.leave(&room, conn_id);
         }
     }
 
     /// Send message to a specific connection
     pub async fn send_to(&self, conn_id: &ConnectionId, message: WsMessage) -> bool {
-        let connections = self.connections.read();
-        if let Some(sender) = connections.get(conn_id) {
-            sender.send(message).await.is_ok()
+        let sender = self.connections.read().get(conn_id).cloned();
+        if let Some(sender) = sender {
+            self.push(&sender, message)
         } else {
+            self.metrics.send_failed();
             false
         }
     }
 
     /// Broadcast message to all connections
     pub async fn broadcast(&self, message: WsMessage) {
-        let connections = self.connections.read();
-        for sender in connections.values() {
-            let _ = sender.send(message.clone()).await;
+        self.fan_out(None, None, message).await;
+    }
+
+    /// Broadcast message to all connections except one (usually the sender)
+    pub async fn broadcast_except(&self, conn_id: &ConnectionId, message: WsMessage) {
+        self.fan_out(None, Some(conn_id), message).await;
+    }
+
+    /// Send message to several connections, returning how many were reached
+    pub async fn send_to_many(&self, conn_ids: &[ConnectionId], message: WsMessage) -> usize {
+        let mut delivered = 0;
+        for sender in self.senders(conn_ids.iter()) {
+            if self.push(&sender, message.clone()) {
+                delivered += 1;
+            }
         }
+        delivered
     }
 
     /// Broadcast to a specific room
     pub async fn broadcast_to_room(&self, room_name: &str, message: WsMessage) {
-        let rooms = self.rooms.read();
-        if let Some(room) = rooms.get(room_name) {
-            let members = room.members();
-            let connections = self.connections.read();
-
-            for member_id in members {
-                if let Some(sender) = connections.get(&member_id) {
-                    let _ = sender.send(message.clone()).await;
-                }
+        self.fan_out(Some(room_name), None, message).await;
+    }
+
+    /// Broadcast to a room, skipping one member (usually the sender)
+    pub async fn broadcast_to_room_except(
+        &self,
+        room_name: &str,
+        conn_id: &ConnectionId,
+        message: WsMessage,
+    ) {
+        self.fan_out(Some(room_name), Some(conn_id), message).await;
+    }
+
+    /// Relay broadcasts through an adapter so they reach other instances
+    ///
+    /// Subscribes the adapter to this server; broadcasts made afterwards are
+    /// delivered locally and published to the other instances.
+    pub async fn set_adapter(&self, adapter: impl WsAdapter + 'static) -> crate::error::Result<()> {
+        adapter.subscribe(self.clone()).await?;
+        *self.adapter.write() = Some(Arc::new(adapter));
+        Ok(())
+    }
+
+    /// ID distinguishing this instance's broadcasts from other replicas'
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Deliver a broadcast relayed from another instance
+    ///
+    /// Called by adapters; envelopes published by this instance are ignored.
+    pub async fn deliver(&self, envelope: &WsEnvelope) {
+        if envelope.origin == *self.node_id {
+            return;
+        }
+        self.deliver_local(
+            envelope.room.as_deref(),
+            envelope.except.as_ref(),
+            &envelope.message,
+        )
+        .await;
+    }
+
+    /// Deliver a broadcast locally, then publish it through the adapter
+    async fn fan_out(&self, room: Option<&str>, except: Option<&ConnectionId>, message: WsMessage) {
+        self.deliver_local(room, except, &message).await;
+
+        let adapter = self.adapter.read().clone();
+        if let Some(adapter) = adapter {
+            let envelope = WsEnvelope {
+                origin: self.node_id.to_string(),
+                room: room.map(str::to_string),
+                except: except.cloned(),
+                message,
+            };
+            if let Err(e) = adapter.publish(&envelope).await {
+                warn!("WebSocket broadcast relay failed: {}", e);
             }
         }
     }
 
+    /// Send to local connections, optionally limited to a room
+    async fn deliver_local(
+        &self,
+        room: Option<&str>,
+        except: Option<&ConnectionId>,
+        message: &WsMessage,
+    ) {
+        let targets = match room {
+            Some(room) => match self.rooms.read().get(room) {
+                Some(room) => room.members(),
+                None => return,
+            },
+            None => self.connections(),
+        };
+        let targets = targets.iter().filter(|id| Some(*id) != except);
+        for sender in self.senders(targets) {
+            self.push(&sender, message.clone());
+        }
+    }
+
+    /// Queue a message, counting it if it's dropped
+    fn push(&self, sender: &WsSender, message: WsMessage) -> bool {
+        let queued = sender.send(message);
+        if !queued {
+            self.metrics.send_failed();
+        }
+        queued
+    }
+
+    /// Look up the senders for a set of connections, skipping unknown IDs
+    fn senders<'a>(&self, conn_ids: impl Iterator<Item = &'a ConnectionId>) -> Vec<WsSender> {
+        let connections = self.connections.read();
+        conn_ids
+            .filter_map(|id| connections.get(id).cloned())
+            .collect()
+    }
+
     /// Create or get a room
     pub fn room(&self, name: &str) -> WsRoom {
         let mut rooms = self.rooms.write();
@@ -159,18 +417,96 @@ impl WsServer {
 
     /// Join a room
     pub fn join_room(&self, room_name: &str, conn_id: ConnectionId) {
+        self.join_room_with(room_name, conn_id, serde_json::Value::Null);
+    }
+
+    /// Join a room with presence metadata, notifying members if the room announces presence
+    pub fn join_room_with(&self, room_name: &str, conn_id: ConnectionId, meta: serde_json::Value) {
         let room = self.room(room_name);
-        room.join(conn_id);
+        if room.join_with(conn_id.clone(), meta) && room.announces() {
+            let presence = room.presence().into_iter().find(|m| m.id == conn_id);
+            if let Some(presence) = presence {
+                self.announce(&room, "presence:join", &presence);
+            }
+        }
     }
 
     /// Leave a room
     pub fn leave_room(&self, room_name: &str, conn_id: &ConnectionId) {
-        let rooms = self.rooms.read();
-        if let Some(room) = rooms.get(room_name) {
-            room.leave(conn_id);
+        let room = self.rooms.read().get(room_name).cloned();
+        if let Some(room) = room {
+            self.leave(&room, conn_id);
+        }
+    }
+
+    /// Remove a member, notifying the others if the room announces presence
+    fn leave(&self, room: &WsRoom, conn_id: &ConnectionId) {
+        let presence = room.presence().into_iter().find(|m| m.id == *conn_id);
+        if let Some(presence) = presence {
+            if room.leave(conn_id) && room.announces() {
+                self.announce(room, "presence:leave", &presence);
+            }
+        }
+    }
+
+    /// Send a presence event to the room's other (local) members
+    fn announce(&self, room: &WsRoom, event: &str, presence: &WsPresence) {
+        let mut data = serde_json::to_value(presence).unwrap_or_default();
+        data["room"] = serde_json::Value::String(room.name().to_string());
+        let Ok(text) = events::event_text(event, &data, None) else {
+            return;
+        };
+
+        let members = room.members();
+        let others = members.iter().filter(|id| **id != presence.id);
+        for sender in self.senders(others) {
+            self.push(&sender, WsMessage::Text(text.clone()));
+        }
+    }
+
+    /// Close every connection with a close frame
+    pub fn close_all(&self, code: u16, reason: &str) {
+        let frame = CloseFrame::new(code, reason);
+        for sender in self.connections.read().values() {
+            sender.send(WsMessage::Close(Some(frame.clone())));
         }
     }
 
+    /// Close every connection with `1001 Going Away` and wait for them to finish
+    ///
+    /// Call this when shutting the server down so clients get a proper close
+    /// handshake instead of a dropped TCP connection. Returns `false` if
+    /// connections were still open after `timeout`.
+    pub async fn shutdown(&self, timeout: std::time::Duration) -> bool {
+        self.close_all(CloseFrame::GOING_AWAY, "Server shutting down");
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.connection_count() > 0 {
+            if tokio::time::Instant::now() >= deadline {
+                return false;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        true
+    }
+
+    /// Disconnect a client with `1008 Policy Violation`
+    ///
+    /// Returns `false` if the connection isn't known to this instance.
+    pub fn kick(&self, conn_id: &ConnectionId, reason: &str) -> bool {
+        let sender = self.connections.read().get(conn_id).cloned();
+        sender.is_some_and(|sender| {
+            sender.send(WsMessage::Close(Some(CloseFrame::new(
+                CloseFrame::POLICY_VIOLATION,
+                reason,
+            ))))
+        })
+    }
+
+    /// Send queue statistics for a connection
+    pub fn queue_stats(&self, conn_id: &ConnectionId) -> Option<QueueStats> {
+        self.connections.read().get(conn_id).map(WsSender::stats)
+    }
+
     /// Get connection count
     pub fn connection_count(&self) -> usize {
         self.connections.read().len()
@@ -188,15 +524,117 @@ impl Default for WsServer {
     }
 }
 
+/// Boxed guard function, see [`WsConfig::guard`]
+type GuardFn = dyn Fn(Request) -> Pin<Box<dyn Future<Output = crate::error::Result<Request>> + Send>>
+    + Send
+    + Sync;
+
+/// Handshake guard run before a connection is accepted
+#[derive(Clone)]
+pub struct WsGuard(Arc<GuardFn>);
+
+impl std::fmt::Debug for WsGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WsGuard")
+    }
+}
+
 /// WebSocket configuration
 #[derive(Debug, Clone)]
 pub struct WsConfig {
     /// Maximum message size in bytes
     pub max_message_size: usize,
-    /// Ping interval in seconds
+    /// Ping interval in seconds (0 disables the heartbeat)
     pub ping_interval: u64,
-    /// Connection timeout in seconds
+    /// Drop connections that send nothing (not even a pong) for this many seconds
     pub timeout: u64,
+    /// Outbound messages queued per connection
+    pub queue_capacity: usize,
+    /// What to do when a connection's queue is full
+    pub backpressure: Backpressure,
+    /// permessage-deflate compression, if enabled
+    pub deflate: Option<DeflateConfig>,
+    /// Chunked binary streams, if enabled
+    pub streams: Option<StreamConfig>,
+    /// Guard checked before the upgrade completes
+    pub guard: Option<WsGuard>,
+}
+
+impl WsConfig {
+    /// Create the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum message size in bytes
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Set the heartbeat ping interval in seconds
+    pub fn ping_interval(mut self, seconds: u64) -> Self {
+        self.ping_interval = seconds;
+        self
+    }
+
+    /// Set the idle timeout in seconds
+    pub fn timeout(mut self, seconds: u64) -> Self {
+        self.timeout = seconds;
+        self
+    }
+
+    /// Set how many outbound messages each connection may queue
+    pub fn queue_capacity(mut self, messages: usize) -> Self {
+        self.queue_capacity = messages;
+        self
+    }
+
+    /// Set the policy for slow clients whose queue is full
+    pub fn backpressure(mut self, policy: Backpressure) -> Self {
+        self.backpressure = policy;
+        self
+    }
+
+    /// Offer permessage-deflate compression to clients that support it
+    pub fn deflate(mut self, deflate: DeflateConfig) -> Self {
+        self.deflate = Some(deflate);
+        self
+    }
+
+    /// Enable chunked binary streams ([`WsConn::send_stream`],
+    /// [`WsHandler::on_stream`])
+    pub fn streams(mut self, streams: StreamConfig) -> Self {
+        self.streams = Some(streams);
+        self
+    }
+
+    /// Check the upgrade request before accepting the connection
+    ///
+    /// The guard gets the request (headers, cookies, query) and either hands
+    /// it back, usually with the resolved identity in its extensions, or
+    /// rejects it: `Error::Unauthorized` answers 401, `Error::Forbidden` 403.
+    /// Request extensions become the connection's state, see [`WsConn::get`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let config = WsConfig::new().guard(|mut req| async move {
+    ///     let token = req.query_param("token").cloned();
+    ///     let user = verify(token.as_deref()).ok_or_else(|| Error::unauthorized("Invalid token"))?;
+    ///     req.extensions_mut().insert(user);
+    ///     Ok(req)
+    /// });
+    /// app.ws_with_config("/live", LiveHandler, config);
+    /// ```
+    pub fn guard<F, Fut>(mut self, guard: F) -> Self
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = crate::error::Result<Request>> + Send + 'static,
+    {
+        self.guard = Some(WsGuard(Arc::new(move |req| Box::pin(guard(req)))));
+        self
+    }
 }
 
 impl Default for WsConfig {
@@ -205,6 +643,478 @@ impl Default for WsConfig {
             max_message_size: 64 * 1024, // 64KB
             ping_interval: 30,
             timeout: 60,
+            queue_capacity: 64,
+            backpressure: Backpressure::default(),
+            deflate: None,
+            streams: None,
+            guard: None,
+        }
+    }
+}
+
+/// Check whether a comma-separated header contains a token (case-insensitive)
+fn has_token(value: Option<&str>, token: &str) -> bool {
+    value
+        .map(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+        .unwrap_or(false)
+}
+
+/// Answer a WebSocket upgrade request
+///
+/// Validates the handshake headers, runs the configured guard and returns
+/// `101 Switching Protocols`; the connection itself runs on a spawned task
+/// once hyper hands over the socket. Invalid requests get `400`, or `426`
+/// for unsupported versions.
+pub(crate) async fn accept(
+    mut req: Request,
+    res: Response,
+    handler: Arc<dyn WsHandler>,
+    server: WsServer,
+    config: WsConfig,
+) -> Response {
+    if !has_token(req.header("upgrade"), "websocket")
+        || !has_token(req.header("connection"), "upgrade")
+    {
+        return res
+            .status(426)
+            .header("upgrade", "websocket")
+            .json(serde_json::json!({ "error": "Expected WebSocket upgrade" }));
+    }
+    if req.header("sec-websocket-version").map(str::trim) != Some("13") {
+        return res
+            .status(426)
+            .header("sec-websocket-version", "13")
+            .json(serde_json::json!({ "error": "Unsupported WebSocket version" }));
+    }
+    let Some(key) = req.header("sec-websocket-key").map(accept_key) else {
+        return res.bad_request("Missing Sec-WebSocket-Key header");
+    };
+    let Some(on_upgrade) = req.extensions_mut().remove::<hyper::upgrade::OnUpgrade>() else {
+        return res.bad_request("Connection cannot be upgraded");
+    };
+
+    if let Some(WsGuard(guard)) = &config.guard {
+        req = match guard(req).await {
+            Ok(req) => req,
+            Err(e) => {
+                return res
+                    .status(e.status_code())
+                    .json(serde_json::json!({ "error": e.to_string() }))
+            }
+        };
+    }
+
+    let negotiated = match (&config.deflate, req.header("sec-websocket-extensions")) {
+        (Some(deflate), Some(offers)) => deflate::negotiate(deflate, offers),
+        _ => None,
+    };
+    let (deflate, extensions) = negotiated.unzip();
+    let upgrade = connection::Upgrade {
+        id: uuid::Uuid::new_v4().to_string(),
+        peer_addr: req.remote_addr(),
+        state: std::mem::take(req.extensions_mut()),
+        deflate,
+    };
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => {
+                let stream = TokioIo::new(upgraded);
+                connection::run(stream, upgrade, handler, server, config).await
+            }
+            Err(e) => warn!("WebSocket upgrade failed: {}", e),
+        }
+    });
+
+    let res = res
+        .status(101)
+        .header("upgrade", "websocket")
+        .header("connection", "Upgrade")
+        .header("sec-websocket-accept", &key);
+    match extensions {
+        Some(extensions) => res.header("sec-websocket-extensions", &extensions),
+        None => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[derive(Default)]
+    struct Recorder(parking_lot::Mutex<Vec<String>>);
+
+    impl WsHandler for Recorder {
+        fn on_open(&self, conn: &WsConn) {
+            assert_eq!(conn.get::<&str>(), Some("alice"));
+            conn.insert(0_usize);
+            self.0.lock().push("open".into());
+        }
+
+        fn on_message(&self, conn: &WsConn, message: WsMessage) {
+            if let WsMessage::Text(text) = message {
+                conn.with_mut(|count: &mut usize| *count += 1);
+                conn.send_text(text.to_uppercase());
+                self.0
+                    .lock()
+                    .push(format!("{}#{}", text, conn.get::<usize>().unwrap()));
+            }
+        }
+
+        fn on_close(&self, _conn: &WsConn, frame: Option<CloseFrame>) {
+            let code = frame.map(|f| f.code).unwrap_or_default();
+            self.0.lock().push(format!("close {}", code));
+        }
+
+        fn on_error(&self, _conn: &WsConn, error: String) {
+            self.0.lock().push(error);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_targeted_sends() {
+        let server = WsServer::new();
+        let mut receivers = Vec::new();
+        for id in ["a", "b", "c"] {
+            let (tx, rx) = WsSender::channel(4, Backpressure::default());
+            server.register(id.to_string(), tx);
+            receivers.push(rx);
+        }
+
+        server
+            .broadcast_except(&"a".to_string(), WsMessage::Text("hi".into()))
+            .await;
+        let ids = ["a".to_string(), "c".to_string(), "gone".to_string()];
+        assert_eq!(server.send_to_many(&ids, WsMessage::Close(None)).await, 2);
+
+        let received: Vec<usize> = receivers
+            .iter()
+            .map(|rx| std::iter::from_fn(|| rx.try_recv()).count())
+            .collect();
+        assert_eq!(received, [1, 1, 2]);
+    }
+
+    #[derive(Default)]
+    struct Outbox(parking_lot::Mutex<Vec<WsEnvelope>>);
+
+    #[async_trait::async_trait]
+    impl WsAdapter for Arc<Outbox> {
+        async fn publish(&self, envelope: &WsEnvelope) -> crate::error::Result<()> {
+            self.0.lock().push(envelope.clone());
+            Ok(())
+        }
+
+        async fn subscribe(&self, _server: WsServer) -> crate::error::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_room_presence() {
+        let server = WsServer::new();
+        let (tx, rx) = WsSender::channel(4, Backpressure::default());
+        server.register("a".to_string(), tx);
+        server.room("chat").announce_presence(true);
+
+        server.join_room_with(
+            "chat",
+            "a".to_string(),
+            serde_json::json!({ "name": "Ann" }),
+        );
+        server.join_room_with(
+            "chat",
+            "b".to_string(),
+            serde_json::json!({ "name": "Bob" }),
+        );
+        let presence = server.room("chat").presence();
+        assert_eq!(presence.len(), 2);
+        assert_eq!(presence[1].meta["name"], "Bob");
+
+        server.unregister(&"b".to_string());
+        assert_eq!(server.room("chat").count(), 1);
+
+        let events: Vec<serde_json::Value> = std::iter::from_fn(|| rx.try_recv())
+            .filter_map(|message| match message {
+                WsMessage::Text(text) => serde_json::from_str(&text).ok(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["event"], "presence:join");
+        assert_eq!(events[1]["event"], "presence:leave");
+        assert_eq!(events[1]["data"]["room"], "chat");
+    }
+
+    #[tokio::test]
+    async fn test_adapter_relay() {
+        let (a, b) = (WsServer::new(), WsServer::new());
+        let outbox = Arc::new(Outbox::default());
+        a.set_adapter(outbox.clone()).await.unwrap();
+
+        let (tx, rx) = WsSender::channel(4, Backpressure::default());
+        b.register("remote".to_string(), tx);
+        b.join_room("chat", "remote".to_string());
+
+        a.broadcast_to_room("chat", WsMessage::Text("hi".into()))
+            .await;
+        let envelope = outbox.0.lock().pop().unwrap();
+        assert_eq!(envelope.room.as_deref(), Some("chat"));
+
+        // Relayed to the other instance, but not back to the origin
+        b.deliver(&envelope).await;
+        assert!(matches!(rx.try_recv(), Some(WsMessage::Text(text)) if text == "hi"));
+        let (tx, rx) = WsSender::channel(4, Backpressure::default());
+        a.register("local".to_string(), tx);
+        a.deliver(&envelope).await;
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_drops_silent_client() {
+        let (mut client, socket) = tokio::io::duplex(1024);
+        let server = WsServer::new();
+        let config = WsConfig::new().ping_interval(1).timeout(1);
+        let mut state = hyper::http::Extensions::new();
+        state.insert("alice");
+
+        let task = tokio::spawn(connection::run(
+            socket,
+            connection::Upgrade {
+                id: "c1".to_string(),
+                peer_addr: "127.0.0.1:9000".parse().unwrap(),
+                state,
+                deflate: None,
+            },
+            Arc::new(Recorder::default()),
+            server.clone(),
+            config,
+        ));
+
+        let mut ping = [0u8; 2];
+        client.read_exact(&mut ping).await.unwrap();
+        assert_eq!(ping, [0x89, 0x00]);
+
+        // Never answered: the connection is reaped after the next tick
+        tokio::time::timeout(std::time::Duration::from_secs(10), task)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(server.connection_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_close_handshake() {
+        let (mut client, socket) = tokio::io::duplex(1024);
+        let server = WsServer::new();
+        let handler = Arc::new(Recorder::default());
+        let mut state = hyper::http::Extensions::new();
+        state.insert("alice");
+        tokio::spawn(connection::run(
+            socket,
+            connection::Upgrade {
+                id: "c1".to_string(),
+                peer_addr: "127.0.0.1:9000".parse().unwrap(),
+                state,
+                deflate: None,
+            },
+            handler.clone(),
+            server.clone(),
+            WsConfig::default(),
+        ));
+        while server.connection_count() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let shutdown = tokio::spawn({
+            let server = server.clone();
+            async move { server.shutdown(std::time::Duration::from_secs(5)).await }
+        });
+
+        // 1001 Going Away, then the client answers with its own close frame
+        let mut head = [0u8; 4];
+        client.read_exact(&mut head).await.unwrap();
+        assert_eq!(head, [0x88, 22, 0x03, 0xE9]);
+        let mut reason = [0u8; 20];
+        client.read_exact(&mut reason).await.unwrap();
+        client
+            .write_all(&[0x88, 0x82, 0, 0, 0, 0, 0x03, 0xE9])
+            .await
+            .unwrap();
+
+        assert!(shutdown.await.unwrap());
+        assert_eq!(handler.0.lock().last().unwrap(), "close 1001");
+    }
+
+    #[tokio::test]
+    async fn test_connection_lifecycle() {
+        let (mut client, socket) = tokio::io::duplex(1024);
+        let handler = Arc::new(Recorder::default());
+        let server = WsServer::new();
+        let mut state = hyper::http::Extensions::new();
+        state.insert("alice");
+
+        let task = tokio::spawn(connection::run(
+            socket,
+            connection::Upgrade {
+                id: "c1".to_string(),
+                peer_addr: "127.0.0.1:9000".parse().unwrap(),
+                state,
+                deflate: None,
+            },
+            handler.clone(),
+            server.clone(),
+            WsConfig::default(),
+        ));
+
+        // Masked "Hel" + continuation "lo", then a masked close with code 1000
+        client
+            .write_all(&[0x01, 0x83, 0, 0, 0, 0, b'H', b'e', b'l'])
+            .await
+            .unwrap();
+        client
+            .write_all(&[0x80, 0x82, 0, 0, 0, 0, b'l', b'o'])
+            .await
+            .unwrap();
+
+        let mut echo = [0u8; 7];
+        client.read_exact(&mut echo).await.unwrap();
+        assert_eq!(echo, [0x81, 0x05, b'H', b'E', b'L', b'L', b'O']);
+
+        client
+            .write_all(&[0x88, 0x82, 0, 0, 0, 0, 0x03, 0xE8])
+            .await
+            .unwrap();
+
+        let mut reply = [0u8; 4];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(reply, [0x88, 0x02, 0x03, 0xE8]);
+
+        task.await.unwrap();
+        assert_eq!(*handler.0.lock(), ["open", "Hello#1", "close 1000"]);
+        assert_eq!(server.connection_count(), 0);
+
+        let metrics = server.metrics();
+        assert_eq!(metrics.connections_closed, 1);
+        assert_eq!((metrics.messages_in, metrics.bytes_in), (1, 5));
+        assert_eq!((metrics.messages_out, metrics.bytes_out), (1, 5));
+        assert_eq!(metrics.connection_duration.count, 1);
+    }
+
+    /// Identity resolved at the handshake
+    #[derive(Clone)]
+    struct User(&'static str);
+
+    /// Greets the sender by name and closes with 4000 on "bye"
+    struct Greeter;
+
+    impl WsHandler for Greeter {
+        fn on_open(&self, _conn: &WsConn) {}
+
+        fn on_message(&self, conn: &WsConn, message: WsMessage) {
+            match message {
+                WsMessage::Text(text) if text == "bye" => {
+                    conn.close(4000, "bye");
+                }
+                WsMessage::Text(text) => {
+                    let User(name) = conn.get::<User>().unwrap();
+                    conn.send_text(format!("{}: {}", name, text));
+                }
+                _ => {}
+            }
+        }
+
+        fn on_close(&self, _conn: &WsConn, _frame: Option<CloseFrame>) {}
+        fn on_error(&self, _conn: &WsConn, _error: String) {}
+    }
+
+    #[tokio::test]
+    async fn test_conn_send_state_and_close() {
+        let (mut client, socket) = tokio::io::duplex(1024);
+        let server = WsServer::new();
+        let mut state = hyper::http::Extensions::new();
+        state.insert(User("ann"));
+
+        let task = tokio::spawn(connection::run(
+            socket,
+            connection::Upgrade {
+                id: "c1".to_string(),
+                peer_addr: "127.0.0.1:9000".parse().unwrap(),
+                state,
+                deflate: None,
+            },
+            Arc::new(Greeter),
+            server.clone(),
+            WsConfig::default(),
+        ));
+
+        // The upgrade state is visible to the message handler, and the reply
+        // arrives as a text frame
+        client
+            .write_all(&[0x81, 0x82, 0, 0, 0, 0, b'h', b'i'])
+            .await
+            .unwrap();
+        let mut reply = [0u8; 9];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(
+            reply,
+            [0x81, 0x07, b'a', b'n', b'n', b':', b' ', b'h', b'i']
+        );
+
+        // Closing sends the code and reason, then waits for the client's reply
+        client
+            .write_all(&[0x81, 0x83, 0, 0, 0, 0, b'b', b'y', b'e'])
+            .await
+            .unwrap();
+        let mut close = [0u8; 7];
+        client.read_exact(&mut close).await.unwrap();
+        assert_eq!(close, [0x88, 0x05, 0x0F, 0xA0, b'b', b'y', b'e']);
+        client
+            .write_all(&[0x88, 0x82, 0, 0, 0, 0, 0x0F, 0xA0])
+            .await
+            .unwrap();
+
+        task.await.unwrap();
+        assert_eq!(server.connection_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_guard_rejects_handshake() {
+        let handshake = |token: &str| {
+            let mut upgrade = hyper::Request::new(());
+            Request::builder()
+                .path("/live")
+                .header("upgrade", "websocket")
+                .header("connection", "Upgrade")
+                .header("sec-websocket-version", "13")
+                .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+                .header("authorization", token)
+                .extension(hyper::upgrade::on(&mut upgrade))
+                .build()
+        };
+        let config = WsConfig::new().guard(|req| async move {
+            match req.header("authorization") {
+                Some("admin") => Ok(req),
+                Some("guest") => Err(crate::error::Error::Forbidden("Admins only".into())),
+                _ => Err(crate::error::Error::unauthorized("Invalid token")),
+            }
+        });
+
+        let server = WsServer::new();
+        for (token, status) in [("nobody", 401), ("guest", 403), ("admin", 101)] {
+            let res = accept(
+                handshake(token),
+                Response::new(),
+                Arc::new(Greeter),
+                server.clone(),
+                config.clone(),
+            )
+            .await;
+            assert_eq!(res.get_status(), status);
+            assert_eq!(
+                res.get_headers().contains_key("sec-websocket-accept"),
+                status == 101
+            );
         }
+        assert_eq!(server.connection_count(), 0);
     }
 }
@@ -0,0 +1,128 @@
+//! WebSocket Adapters
+//!
+//! Adapters relay broadcasts between server instances, so that
+//! `broadcast_to_room` also reaches room members connected to other
+//! replicas. Each instance still only delivers to its own connections.
+
+use super::{ConnectionId, WsMessage, WsServer};
+use crate::error::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// A broadcast as relayed between instances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsEnvelope {
+    /// Node ID of the instance that sent the broadcast
+    pub origin: String,
+    /// Target room, or every connection when `None`
+    pub room: Option<String>,
+    /// Connection to skip (usually the sender)
+    pub except: Option<ConnectionId>,
+    /// The message itself
+    pub message: WsMessage,
+}
+
+/// Cross-instance broadcast transport
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let adapter = RedisWsAdapter::connect("redis://127.0.0.1/").await?;
+/// app.ws_server().set_adapter(adapter).await?;
+/// ```
+#[async_trait]
+pub trait WsAdapter: Send + Sync {
+    /// Send a broadcast to the other instances
+    async fn publish(&self, envelope: &WsEnvelope) -> Result<()>;
+
+    /// Start relaying broadcasts from other instances into `server`
+    ///
+    /// Implementations should spawn their listener and return once the
+    /// subscription is established, handing each envelope to
+    /// [`WsServer::deliver`].
+    async fn subscribe(&self, server: WsServer) -> Result<()>;
+}
+
+/// Redis pub/sub adapter
+#[cfg(feature = "redis")]
+#[derive(Clone)]
+pub struct RedisWsAdapter {
+    client: redis::Client,
+    conn: redis::aio::ConnectionManager,
+    channel: String,
+}
+
+#[cfg(feature = "redis")]
+impl RedisWsAdapter {
+    /// Connect to Redis (e.g. `redis://127.0.0.1/`)
+    pub async fn connect(url: &str) -> Result<Self> {
+        Ok(Self::from_client(
+            &crate::db::redis::RedisClient::connect(url).await?,
+        ))
+    }
+
+    /// Share an existing client's connection
+    pub fn from_client(client: &crate::db::redis::RedisClient) -> Self {
+        Self {
+            client: client.client().clone(),
+            conn: client.connection(),
+            channel: "rustyx:ws".to_string(),
+        }
+    }
+
+    /// Set the pub/sub channel (default `rustyx:ws`)
+    ///
+    /// Instances only see each other's broadcasts on the same channel.
+    pub fn channel(mut self, channel: &str) -> Self {
+        self.channel = channel.to_string();
+        self
+    }
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl WsAdapter for RedisWsAdapter {
+    async fn publish(&self, envelope: &WsEnvelope) -> Result<()> {
+        use redis::AsyncCommands;
+
+        let payload = serde_json::to_vec(envelope)?;
+        let mut conn = self.conn.clone();
+        conn.publish::<_, _, ()>(&self.channel, payload)
+            .await
+            .map_err(|e| crate::error::Error::Database(e.to_string()))
+    }
+
+    async fn subscribe(&self, server: WsServer) -> Result<()> {
+        use futures::StreamExt;
+
+        let mut pubsub = self
+            .client
+            .get_async_pubsub()
+            .await
+            .map_err(|e| crate::error::Error::Database(e.to_string()))?;
+        pubsub
+            .subscribe(&self.channel)
+            .await
+            .map_err(|e| crate::error::Error::Database(e.to_string()))?;
+
+        tokio::spawn(async move {
+            let mut messages = pubsub.into_on_message();
+            while let Some(msg) = messages.next().await {
+                let payload: Vec<u8> = match msg.get_payload() {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        tracing::warn!("Invalid WebSocket relay payload: {}", e);
+                        continue;
+                    }
+                };
+                match serde_json::from_slice::<WsEnvelope>(&payload) {
+                    Ok(envelope) => server.deliver(&envelope).await,
+                    Err(e) => tracing::warn!("Invalid WebSocket relay payload: {}", e),
+                }
+            }
+            tracing::warn!("WebSocket relay subscription ended");
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+//! WebSocket Connection
+//!
+//! Drives an upgraded connection: reads client frames, reassembles
+//! fragmented messages, answers pings and closes, and forwards messages
+//! queued through [`WsServer`](super::WsServer) to the socket.
+
+use super::deflate::{DeflateParams, Deflater, Inflater};
+use super::events::PendingAcks;
+use super::metrics::{ConnStats, Counters};
+use super::protocol::{
+    parse_close, Frame, ProtocolError, OP_BINARY, OP_CLOSE, OP_CONTINUATION, OP_PING, OP_PONG,
+    OP_TEXT,
+};
+use super::queue::{QueueStats, WsSender};
+use super::stream::Streams;
+use super::{CloseFrame, ConnectionId, WsConfig, WsHandler, WsMessage, WsServer};
+use chrono::{DateTime, Utc};
+use hyper::http::Extensions;
+use parking_lot::{Mutex, RwLock};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// How long to wait for the client's close reply after the server closes
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Handle to a single WebSocket connection
+///
+/// Passed to every [`WsHandler`] callback. Sends are non-blocking: they
+/// queue the message for the connection's writer and return `false` when
+/// the message was dropped (see [`Backpressure`](super::Backpressure)) or
+/// the connection has closed.
+///
+/// Typed state can be attached with [`insert`](WsConn::insert). It starts
+/// out as the upgrade request's extensions, so anything middleware put on
+/// the request (an authenticated user, say) is available to the handler.
+#[derive(Debug, Clone)]
+pub struct WsConn {
+    id: ConnectionId,
+    peer_addr: SocketAddr,
+    state: Arc<RwLock<Extensions>>,
+    connected_at: DateTime<Utc>,
+    last_seen: Arc<Mutex<Instant>>,
+    messages: WsSender,
+    control: mpsc::Sender<Frame>,
+    pub(super) acks: Arc<PendingAcks>,
+    pub(super) streams: Arc<Streams>,
+    pub(super) stats: Arc<ConnStats>,
+    metrics: Arc<Counters>,
+}
+
+impl WsConn {
+    /// Connection ID, as used by [`WsServer`] and rooms
+    pub fn id(&self) -> &ConnectionId {
+        &self.id
+    }
+
+    /// Address of the connected client
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// When the connection was accepted
+    pub fn connected_at(&self) -> DateTime<Utc> {
+        self.connected_at
+    }
+
+    /// When the last frame (message, ping or pong) arrived from the client
+    pub fn last_seen(&self) -> Instant {
+        *self.last_seen.lock()
+    }
+
+    /// Attach a value to the connection, replacing any previous one of that type
+    pub fn insert<T: Clone + Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        self.state.write().insert(value)
+    }
+
+    /// Get a copy of the attached value of type `T`
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// fn on_message(&self, conn: &WsConn, message: WsMessage) {
+    ///     if let Some(user) = conn.get::<Principal>() {
+    ///         // ...
+    ///     }
+    /// }
+    /// ```
+    pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.state.read().get::<T>().cloned()
+    }
+
+    /// Modify the attached value of type `T` in place
+    pub fn with_mut<T, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.state.write().get_mut::<T>().map(f)
+    }
+
+    /// Detach and return the value of type `T`
+    pub fn remove<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.state.write().remove::<T>()
+    }
+
+    /// Queue a message for this connection
+    pub fn send(&self, message: WsMessage) -> bool {
+        let queued = self.messages.send(message);
+        if !queued {
+            self.metrics.send_failed();
+        }
+        queued
+    }
+
+    /// Send a text message
+    pub fn send_text(&self, text: impl Into<String>) -> bool {
+        self.send(WsMessage::Text(text.into()))
+    }
+
+    /// Send a binary message
+    pub fn send_binary(&self, data: impl Into<Vec<u8>>) -> bool {
+        self.send(WsMessage::Binary(data.into()))
+    }
+
+    /// Start the close handshake with a status code and reason
+    ///
+    /// The close frame goes out ahead of queued messages; the connection
+    /// ends once the client replies (or after a short grace period).
+    pub fn close(&self, code: u16, reason: &str) -> bool {
+        self.control.try_send(Frame::close(code, reason)).is_ok()
+    }
+
+    /// Send queue statistics
+    pub fn queue_stats(&self) -> QueueStats {
+        self.messages.stats()
+    }
+
+    /// Sending half of the connection's queue
+    pub(super) fn sender(&self) -> WsSender {
+        self.messages.clone()
+    }
+
+    /// Whether the connection is still open
+    pub fn is_open(&self) -> bool {
+        !self.control.is_closed()
+    }
+}
+
+/// An accepted upgrade, ready to run
+pub(crate) struct Upgrade {
+    pub id: ConnectionId,
+    pub peer_addr: SocketAddr,
+    /// Initial connection state (the upgrade request's extensions)
+    pub state: Extensions,
+    /// Negotiated permessage-deflate parameters
+    pub deflate: Option<DeflateParams>,
+}
+
+/// Run a connection until either side closes it
+pub(crate) async fn run<S>(
+    stream: S,
+    upgrade: Upgrade,
+    handler: Arc<dyn WsHandler>,
+    server: WsServer,
+    config: WsConfig,
+) where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let (tx, rx) = WsSender::channel(config.queue_capacity, config.backpressure);
+    let (control_tx, mut control_rx) = mpsc::channel::<Frame>(8);
+    let done = CancellationToken::new();
+
+    let conn = WsConn {
+        id: upgrade.id.clone(),
+        peer_addr: upgrade.peer_addr,
+        state: Arc::new(RwLock::new(upgrade.state)),
+        connected_at: Utc::now(),
+        last_seen: Arc::new(Mutex::new(Instant::now())),
+        messages: tx,
+        control: control_tx,
+        acks: Arc::default(),
+        streams: Arc::new(Streams::new(config.streams.clone())),
+        stats: Arc::default(),
+        metrics: Arc::clone(&server.metrics),
+    };
+    server.register_conn(&conn);
+    let mut deflater = upgrade.deflate.clone().map(Deflater::new);
+    let inflater = upgrade.deflate.as_ref().map(Inflater::new);
+    handler.on_open(&conn);
+
+    // Control frames (pong, close) go out ahead of queued messages. The
+    // writer stops once it has sent a close frame or the reader is done.
+    let writer_done = done.clone();
+    let (metrics, stats) = (Arc::clone(&conn.metrics), Arc::clone(&conn.stats));
+    let mut writer_task = tokio::spawn(async move {
+        loop {
+            let frame = tokio::select! {
+                biased;
+                Some(frame) = control_rx.recv() => frame,
+                message = rx.recv() => match message {
+                    Some(message) => compress(message_frame(message), &mut deflater),
+                    // Closed by the backpressure policy
+                    None => Frame::close(CloseFrame::POLICY_VIOLATION, "Send queue overflow"),
+                },
+                _ = writer_done.cancelled() => break,
+            };
+
+            let closing = frame.opcode == OP_CLOSE;
+            if frame.write(&mut writer).await.is_err() || closing {
+                break;
+            }
+            if matches!(frame.opcode, OP_TEXT | OP_BINARY) {
+                metrics.message_out(&stats, frame.payload.len());
+            }
+        }
+        let _ = writer.shutdown().await;
+    });
+
+    let result = tokio::select! {
+        result = read_loop(&mut reader, &conn, handler.as_ref(), &config, inflater) => result,
+        // No frames from the client within the timeout: drop it as dead
+        _ = heartbeat(&conn, &config) => {
+            tracing::debug!("WebSocket connection {} timed out", conn.id);
+            Ok(None)
+        }
+        // The writer finished first: the server sent a close frame (or the
+        // socket broke), so give the client a moment to reply, then drop it
+        _ = async {
+            let _ = (&mut writer_task).await;
+            tokio::time::sleep(CLOSE_TIMEOUT).await;
+        } => Ok(None),
+    };
+
+    done.cancel();
+    // The handle may already have been awaited in the select above
+    if !writer_task.is_finished()
+        && tokio::time::timeout(CLOSE_TIMEOUT, &mut writer_task)
+            .await
+            .is_err()
+    {
+        writer_task.abort();
+    }
+
+    server.unregister(&conn.id);
+    conn.streams.close();
+    let frame = match result {
+        Ok(frame) => frame,
+        Err(error) => {
+            handler.on_error(&conn, error.to_string());
+            None
+        }
+    };
+    handler.on_close(&conn, frame);
+}
+
+/// Read frames and hand complete messages to the handler
+async fn read_loop<R>(
+    reader: &mut R,
+    conn: &WsConn,
+    handler: &dyn WsHandler,
+    config: &WsConfig,
+    mut inflater: Option<Inflater>,
+) -> Result<Option<CloseFrame>, ProtocolError>
+where
+    R: AsyncRead + Unpin,
+{
+    let control = &conn.control;
+    let max_size = config.max_message_size;
+    // Opcode, compression flag and payload of a fragmented message in progress
+    let mut partial: Option<(u8, bool, Vec<u8>)> = None;
+
+    loop {
+        let frame = match Frame::read(reader, max_size).await {
+            Ok(frame) => frame,
+            // Client went away without a close handshake
+            Err(ProtocolError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            Err(e) => return fail(control, e).await,
+        };
+        *conn.last_seen.lock() = Instant::now();
+
+        // RSV1 marks a compressed message, set on its first frame only
+        let first_data_frame = matches!(frame.opcode, OP_TEXT | OP_BINARY);
+        if frame.rsv1 && !(first_data_frame && inflater.is_some()) {
+            return fail(control, ProtocolError::Protocol("unexpected extension bit")).await;
+        }
+
+        let (opcode, compressed, payload) = match frame.opcode {
+            OP_TEXT | OP_BINARY => {
+                if partial.is_some() {
+                    return fail(
+                        control,
+                        ProtocolError::Protocol("expected continuation frame"),
+                    )
+                    .await;
+                }
+                (frame.opcode, frame.rsv1, frame.payload)
+            }
+            OP_CONTINUATION => {
+                let Some((opcode, compressed, mut payload)) = partial.take() else {
+                    return fail(
+                        control,
+                        ProtocolError::Protocol("unexpected continuation frame"),
+                    )
+                    .await;
+                };
+                if payload.len() + frame.payload.len() > max_size {
+                    return fail(control, ProtocolError::TooBig).await;
+                }
+                payload.extend_from_slice(&frame.payload);
+                (opcode, compressed, payload)
+            }
+            OP_PING => {
+                let _ = control
+                    .send(Frame::new(OP_PONG, frame.payload.clone()))
+                    .await;
+                handler.on_message(conn, WsMessage::Ping(frame.payload));
+                continue;
+            }
+            OP_PONG => {
+                handler.on_message(conn, WsMessage::Pong(frame.payload));
+                continue;
+            }
+            OP_CLOSE => {
+                // Echo the code back to complete the close handshake
+                let close = match parse_close(&frame.payload) {
+                    Ok(close) => close.map(|(code, reason)| CloseFrame::new(code, reason)),
+                    Err(e) => return fail(control, e).await,
+                };
+                let code = close.as_ref().map_or(CloseFrame::NORMAL, |f| f.code);
+                let _ = control.send(Frame::close(code, "")).await;
+                return Ok(close);
+            }
+            _ => return fail(control, ProtocolError::Protocol("unknown opcode")).await,
+        };
+
+        if !frame.fin {
+            partial = Some((opcode, compressed, payload));
+            continue;
+        }
+        let payload = match (compressed, inflater.as_mut()) {
+            (true, Some(inflater)) => match inflater.decompress(&payload, max_size) {
+                Ok(payload) => payload,
+                Err(e) => return fail(control, e).await,
+            },
+            _ => payload,
+        };
+        conn.metrics.message_in(&conn.stats, payload.len());
+        match decode(opcode, payload) {
+            Ok(message) => {
+                if let Some(message) = conn.streams.dispatch(conn, handler, message) {
+                    handler.on_message(conn, message);
+                }
+            }
+            Err(e) => return fail(control, e).await,
+        }
+    }
+}
+
+/// Ping the client every `ping_interval` seconds; returns once it has been
+/// silent for longer than `timeout` seconds
+async fn heartbeat(conn: &WsConn, config: &WsConfig) {
+    if config.ping_interval == 0 {
+        return std::future::pending().await;
+    }
+
+    let timeout = Duration::from_secs(config.timeout);
+    let mut ticker = tokio::time::interval(Duration::from_secs(config.ping_interval));
+    ticker.tick().await;
+    let mut last_ping: Option<Instant> = None;
+
+    loop {
+        ticker.tick().await;
+        // Only give up on a client that hasn't answered the previous ping
+        let last_seen = conn.last_seen();
+        if last_ping.is_some_and(|ping| last_seen < ping) && last_seen.elapsed() > timeout {
+            return;
+        }
+        let _ = conn.control.try_send(Frame::new(OP_PING, Vec::new()));
+        last_ping = Some(Instant::now());
+    }
+}
+
+/// Close the connection with the error's close code and return the error
+async fn fail<T>(control: &mpsc::Sender<Frame>, error: ProtocolError) -> Result<T, ProtocolError> {
+    if let Some(code) = error.close_code() {
+        let _ = control.send(Frame::close(code, &error.to_string())).await;
+    }
+    Err(error)
+}
+
+/// Turn a complete data payload into a message
+fn decode(opcode: u8, payload: Vec<u8>) -> Result<WsMessage, ProtocolError> {
+    if opcode == OP_TEXT {
+        String::from_utf8(payload)
+            .map(WsMessage::Text)
+            .map_err(|_| ProtocolError::InvalidUtf8)
+    } else {
+        Ok(WsMessage::Binary(payload))
+    }
+}
+
+/// Compress a data frame if permessage-deflate was negotiated
+fn compress(frame: Frame, deflater: &mut Option<Deflater>) -> Frame {
+    let Some(deflater) = deflater else {
+        return frame;
+    };
+    if !matches!(frame.opcode, OP_TEXT | OP_BINARY) {
+        return frame;
+    }
+    match deflater.compress(&frame.payload) {
+        Some(payload) => Frame {
+            rsv1: true,
+            payload,
+            ..frame
+        },
+        None => frame,
+    }
+}
+
+/// Frame for an outgoing message
+fn message_frame(message: WsMessage) -> Frame {
+    match message {
+        WsMessage::Text(text) => Frame::new(OP_TEXT, text.into_bytes()),
+        WsMessage::Binary(data) => Frame::new(OP_BINARY, data),
+        WsMessage::Ping(data) => Frame::new(OP_PING, data),
+        WsMessage::Pong(data) => Frame::new(OP_PONG, data),
+        WsMessage::Close(Some(frame)) => Frame::close(frame.code, &frame.reason),
+        WsMessage::Close(None) => Frame::close(CloseFrame::NORMAL, ""),
+    }
+}
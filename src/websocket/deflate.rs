@@ -0,0 +1,310 @@
+//! permessage-deflate
+//!
+//! Negotiation and codec for the RFC 7692 compression extension. Messages
+//! are compressed as raw deflate streams flushed with a sync marker, which
+//! is stripped before sending and restored before inflating.
+
+use super::protocol::ProtocolError;
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress};
+
+/// Trailer of a sync flush, left off the wire
+const SYNC_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// permessage-deflate settings
+#[derive(Debug, Clone)]
+pub struct DeflateConfig {
+    /// Only compress messages at least this many bytes long
+    pub threshold: usize,
+    /// Compression level (0-9)
+    pub level: u32,
+    /// Largest LZ77 window (9-15 bits) the server compresses with
+    pub server_max_window_bits: u8,
+    /// Window (9-15 bits) clients are asked to limit themselves to
+    pub client_max_window_bits: u8,
+    /// Reset the compressor after every message (less memory, worse ratio)
+    pub server_no_context_takeover: bool,
+    /// Ask clients to reset their compressor after every message
+    pub client_no_context_takeover: bool,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 1024,
+            level: 6,
+            server_max_window_bits: 15,
+            client_max_window_bits: 15,
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+        }
+    }
+}
+
+impl DeflateConfig {
+    /// Create the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum message size to compress
+    pub fn threshold(mut self, bytes: usize) -> Self {
+        self.threshold = bytes;
+        self
+    }
+
+    /// Set the compression level (0-9)
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Set the server and client window sizes (9-15 bits)
+    pub fn window_bits(mut self, server: u8, client: u8) -> Self {
+        self.server_max_window_bits = server.clamp(9, 15);
+        self.client_max_window_bits = client.clamp(9, 15);
+        self
+    }
+
+    /// Disable context takeover on both sides
+    pub fn no_context_takeover(mut self) -> Self {
+        self.server_no_context_takeover = true;
+        self.client_no_context_takeover = true;
+        self
+    }
+}
+
+/// Parameters agreed with a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DeflateParams {
+    pub threshold: usize,
+    pub level: u32,
+    pub server_window_bits: u8,
+    pub server_no_context_takeover: bool,
+    pub client_no_context_takeover: bool,
+}
+
+/// Pick the first acceptable permessage-deflate offer from a
+/// `Sec-WebSocket-Extensions` header, returning the agreed parameters and
+/// the response header value
+pub(crate) fn negotiate(config: &DeflateConfig, header: &str) -> Option<(DeflateParams, String)> {
+    header
+        .split(',')
+        .find_map(|offer| accept_offer(config, offer))
+}
+
+fn accept_offer(config: &DeflateConfig, offer: &str) -> Option<(DeflateParams, String)> {
+    let mut parts = offer.split(';').map(str::trim);
+    if !parts.next()?.eq_ignore_ascii_case("permessage-deflate") {
+        return None;
+    }
+
+    let mut params = DeflateParams {
+        threshold: config.threshold,
+        level: config.level,
+        server_window_bits: config.server_max_window_bits.clamp(9, 15),
+        server_no_context_takeover: config.server_no_context_takeover,
+        client_no_context_takeover: config.client_no_context_takeover,
+    };
+    let mut client_window_supported = false;
+
+    for param in parts {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        let bits = |value: Option<&str>| value.and_then(|v| v.parse::<u8>().ok());
+
+        match (name, value) {
+            ("server_no_context_takeover", None) => params.server_no_context_takeover = true,
+            ("client_no_context_takeover", None) => params.client_no_context_takeover = true,
+            ("server_max_window_bits", value) => match bits(value) {
+                // A raw deflate stream can't use an 8-bit window
+                Some(bits @ 9..=15) => {
+                    params.server_window_bits = params.server_window_bits.min(bits)
+                }
+                _ => return None,
+            },
+            ("client_max_window_bits", None) => client_window_supported = true,
+            ("client_max_window_bits", value) => match bits(value) {
+                Some(8..=15) => client_window_supported = true,
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+
+    let mut response = String::from("permessage-deflate");
+    if params.server_no_context_takeover {
+        response.push_str("; server_no_context_takeover");
+    }
+    if params.client_no_context_takeover {
+        response.push_str("; client_no_context_takeover");
+    }
+    if params.server_window_bits < 15 {
+        response.push_str(&format!(
+            "; server_max_window_bits={}",
+            params.server_window_bits
+        ));
+    }
+    if client_window_supported && config.client_max_window_bits < 15 {
+        response.push_str(&format!(
+            "; client_max_window_bits={}",
+            config.client_max_window_bits.clamp(9, 15)
+        ));
+    }
+
+    Some((params, response))
+}
+
+/// Outgoing message compressor
+pub(crate) struct Deflater {
+    compress: Compress,
+    params: DeflateParams,
+}
+
+impl Deflater {
+    pub(crate) fn new(params: DeflateParams) -> Self {
+        Self {
+            compress: Compress::new_with_window_bits(
+                Compression::new(params.level),
+                false,
+                params.server_window_bits,
+            ),
+            params,
+        }
+    }
+
+    /// Compress a message payload, or `None` if it's under the threshold
+    pub(crate) fn compress(&mut self, data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() < self.params.threshold {
+            return None;
+        }
+
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let mut offset = 0;
+        loop {
+            if out.capacity() - out.len() < 64 {
+                out.reserve(out.capacity().max(64));
+            }
+            let before = self.compress.total_in();
+            self.compress
+                .compress_vec(&data[offset..], &mut out, FlushCompress::Sync)
+                .ok()?;
+            offset += (self.compress.total_in() - before) as usize;
+            // Spare room left means the flush has been fully written
+            if offset >= data.len() && out.len() < out.capacity() {
+                break;
+            }
+        }
+
+        if out.ends_with(&SYNC_TRAILER) {
+            out.truncate(out.len() - SYNC_TRAILER.len());
+        }
+        if self.params.server_no_context_takeover {
+            self.compress.reset();
+        }
+        Some(out)
+    }
+}
+
+/// Incoming message decompressor
+pub(crate) struct Inflater {
+    decompress: Decompress,
+    no_context_takeover: bool,
+}
+
+impl Inflater {
+    pub(crate) fn new(params: &DeflateParams) -> Self {
+        Self {
+            decompress: Decompress::new_with_window_bits(false, 15),
+            no_context_takeover: params.client_no_context_takeover,
+        }
+    }
+
+    /// Inflate a compressed message, failing once it exceeds `max_size`
+    pub(crate) fn decompress(
+        &mut self,
+        payload: &[u8],
+        max_size: usize,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let mut input = Vec::with_capacity(payload.len() + SYNC_TRAILER.len());
+        input.extend_from_slice(payload);
+        input.extend_from_slice(&SYNC_TRAILER);
+
+        let mut out = Vec::with_capacity((payload.len() * 2).clamp(64, max_size.max(64)));
+        let mut offset = 0;
+        loop {
+            if out.capacity() - out.len() < 64 {
+                out.reserve(out.capacity().max(64));
+            }
+            let (before_in, before_out) = (self.decompress.total_in(), self.decompress.total_out());
+            self.decompress
+                .decompress_vec(&input[offset..], &mut out, FlushDecompress::Sync)
+                .map_err(|_| ProtocolError::Protocol("invalid compressed data"))?;
+            offset += (self.decompress.total_in() - before_in) as usize;
+
+            if out.len() > max_size {
+                return Err(ProtocolError::TooBig);
+            }
+            if offset >= input.len() && out.len() < out.capacity() {
+                break;
+            }
+            if self.decompress.total_in() == before_in && self.decompress.total_out() == before_out
+            {
+                return Err(ProtocolError::Protocol("invalid compressed data"));
+            }
+        }
+
+        if self.no_context_takeover {
+            self.decompress.reset(false);
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let config = DeflateConfig::new();
+        let (params, response) = negotiate(
+            &config,
+            "x-webkit-deflate-frame, permessage-deflate; server_max_window_bits=10; client_max_window_bits",
+        )
+        .unwrap();
+        assert_eq!(params.server_window_bits, 10);
+        assert_eq!(response, "permessage-deflate; server_max_window_bits=10");
+
+        assert!(negotiate(&config, "permessage-deflate; server_max_window_bits=8").is_none());
+        assert!(negotiate(&config, "permessage-deflate; unknown").is_none());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let params = negotiate(&DeflateConfig::new().threshold(0), "permessage-deflate")
+            .unwrap()
+            .0;
+        let mut inflater = Inflater::new(&params);
+
+        // "Hello" from RFC 7692, section 7.2.3.1
+        let hello = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        assert_eq!(inflater.decompress(&hello, 1024).unwrap(), b"Hello");
+
+        let mut deflater = Deflater::new(params);
+        let text = "the quick brown fox ".repeat(50);
+        for _ in 0..2 {
+            let compressed = deflater.compress(text.as_bytes()).unwrap();
+            assert!(compressed.len() < text.len() / 4);
+            assert_eq!(
+                inflater.decompress(&compressed, 4096).unwrap(),
+                text.as_bytes()
+            );
+        }
+        assert!(matches!(
+            inflater.decompress(&deflater.compress(text.as_bytes()).unwrap(), 100),
+            Err(ProtocolError::TooBig)
+        ));
+    }
+}
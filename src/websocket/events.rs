@@ -0,0 +1,322 @@
+//! WebSocket Events
+//!
+//! An optional socket.io-style layer over text messages. Packets are JSON:
+//! `{"event": "chat:new", "data": {...}, "id": 1}` for events (the `id` asks
+//! for an acknowledgement) and `{"ack": 1, "data": ...}` for replies.
+
+use super::{CloseFrame, WsConn, WsHandler, WsMessage, WsServer};
+use crate::error::{Error, Result};
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Wire format of the event layer
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Packet {
+    Event {
+        event: String,
+        #[serde(default)]
+        data: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u64>,
+    },
+    Ack {
+        ack: u64,
+        #[serde(default)]
+        data: Value,
+    },
+}
+
+/// Serialize an event packet
+pub(super) fn event_text<T: Serialize>(event: &str, data: &T, id: Option<u64>) -> Result<String> {
+    Ok(serde_json::to_string(&Packet::Event {
+        event: event.to_string(),
+        data: serde_json::to_value(data)?,
+        id,
+    })?)
+}
+
+/// Acknowledgements a connection is waiting for
+#[derive(Debug, Default)]
+pub(crate) struct PendingAcks {
+    next_id: AtomicU64,
+    waiting: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+}
+
+impl WsConn {
+    /// Send an event with a JSON payload
+    pub fn emit<T: Serialize>(&self, event: &str, data: &T) -> bool {
+        match event_text(event, data, None) {
+            Ok(text) => self.send_text(text),
+            Err(_) => false,
+        }
+    }
+
+    /// Send an event and wait for the client's acknowledgement
+    ///
+    /// Acknowledgements are only routed when the endpoint's handler is a
+    /// [`WsEvents`] router.
+    pub async fn emit_with_ack<T, R>(&self, event: &str, data: &T, timeout: Duration) -> Result<R>
+    where
+        T: Serialize,
+        R: DeserializeOwned,
+    {
+        let acks = &self.acks;
+        let id = acks.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, rx) = oneshot::channel();
+        acks.waiting.lock().insert(id, tx);
+
+        let text = event_text(event, data, Some(id))?;
+        if !self.send_text(text) {
+            acks.waiting.lock().remove(&id);
+            return Err(Error::Internal("WebSocket connection closed".to_string()));
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(value)) => Ok(serde_json::from_value(value)?),
+            Ok(Err(_)) => Err(Error::Internal("WebSocket connection closed".to_string())),
+            Err(_) => {
+                acks.waiting.lock().remove(&id);
+                Err(Error::Internal(format!(
+                    "No acknowledgement for '{}' within {:?}",
+                    event, timeout
+                )))
+            }
+        }
+    }
+}
+
+impl WsServer {
+    /// Send an event to all connections
+    pub async fn emit<T: Serialize>(&self, event: &str, data: &T) -> Result<()> {
+        let text = event_text(event, data, None)?;
+        self.broadcast(WsMessage::Text(text)).await;
+        Ok(())
+    }
+
+    /// Send an event to a room
+    pub async fn emit_to_room<T: Serialize>(
+        &self,
+        room: &str,
+        event: &str,
+        data: &T,
+    ) -> Result<()> {
+        let text = event_text(event, data, None)?;
+        self.broadcast_to_room(room, WsMessage::Text(text)).await;
+        Ok(())
+    }
+}
+
+/// Boxed event handler: raw payload in, acknowledgement payload out
+type EventHandler = Arc<
+    dyn Fn(
+            WsConn,
+            Value,
+        ) -> Pin<Box<dyn Future<Output = std::result::Result<Value, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// Connection lifecycle callback
+type ConnCallback = Arc<dyn Fn(&WsConn) + Send + Sync>;
+
+/// Event router, usable as the handler of a WebSocket endpoint
+///
+/// Payloads are deserialized into the handler's argument type and the
+/// handler's return value is sent back when the client asked for an
+/// acknowledgement. A payload that doesn't deserialize is answered with an
+/// `error` event. Handlers run on their own tasks, so events from one
+/// client may be processed concurrently.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// struct NewMessage { text: String }
+///
+/// let server = app.ws_server();
+/// let events = WsEvents::new()
+///     .on_connect(|conn| { conn.emit("welcome", &json!({ "id": conn.id() })); })
+///     .on("chat:new", move |conn: WsConn, msg: NewMessage| {
+///         let server = server.clone();
+///         async move {
+///             let _ = server.emit("chat:new", &json!({ "from": conn.id(), "text": msg.text })).await;
+///             json!({ "ok": true })
+///         }
+///     });
+///
+/// app.ws("/chat", events);
+/// ```
+#[derive(Clone, Default)]
+pub struct WsEvents {
+    handlers: HashMap<String, EventHandler>,
+    on_connect: Option<ConnCallback>,
+    on_disconnect: Option<ConnCallback>,
+}
+
+impl WsEvents {
+    /// Create an empty event router
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle an event
+    pub fn on<T, R, F, Fut>(mut self, event: &str, handler: F) -> Self
+    where
+        T: DeserializeOwned + Send + 'static,
+        R: Serialize + 'static,
+        F: Fn(WsConn, T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = R> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.handlers.insert(
+            event.to_string(),
+            Arc::new(move |conn, data| {
+                let handler = Arc::clone(&handler);
+                Box::pin(async move {
+                    let data: T = serde_json::from_value(data).map_err(|e| e.to_string())?;
+                    let reply = handler(conn, data).await;
+                    serde_json::to_value(reply).map_err(|e| e.to_string())
+                })
+            }),
+        );
+        self
+    }
+
+    /// Called when a client connects
+    pub fn on_connect(mut self, callback: impl Fn(&WsConn) + Send + Sync + 'static) -> Self {
+        self.on_connect = Some(Arc::new(callback));
+        self
+    }
+
+    /// Called when a client disconnects
+    pub fn on_disconnect(mut self, callback: impl Fn(&WsConn) + Send + Sync + 'static) -> Self {
+        self.on_disconnect = Some(Arc::new(callback));
+        self
+    }
+}
+
+impl WsHandler for WsEvents {
+    fn on_open(&self, conn: &WsConn) {
+        if let Some(callback) = &self.on_connect {
+            callback(conn);
+        }
+    }
+
+    fn on_message(&self, conn: &WsConn, message: WsMessage) {
+        let WsMessage::Text(text) = message else {
+            return;
+        };
+
+        match serde_json::from_str::<Packet>(&text) {
+            Ok(Packet::Event { event, data, id }) => {
+                let Some(handler) = self.handlers.get(&event).cloned() else {
+                    tracing::debug!("Unhandled WebSocket event '{}'", event);
+                    return;
+                };
+                let conn = conn.clone();
+                tokio::spawn(async move {
+                    match handler(conn.clone(), data).await {
+                        Ok(reply) => {
+                            if let Some(ack) = id {
+                                if let Ok(text) =
+                                    serde_json::to_string(&Packet::Ack { ack, data: reply })
+                                {
+                                    conn.send_text(text);
+                                }
+                            }
+                        }
+                        Err(message) => {
+                            conn.emit(
+                                "error",
+                                &serde_json::json!({ "event": event, "message": message }),
+                            );
+                        }
+                    }
+                });
+            }
+            Ok(Packet::Ack { ack, data }) => {
+                if let Some(waiter) = conn.acks.waiting.lock().remove(&ack) {
+                    let _ = waiter.send(data);
+                }
+            }
+            Err(_) => tracing::debug!("Ignoring non-event WebSocket message"),
+        }
+    }
+
+    fn on_close(&self, conn: &WsConn, _frame: Option<CloseFrame>) {
+        // Dropping the waiters fails any pending `emit_with_ack`
+        conn.acks.waiting.lock().clear();
+        if let Some(callback) = &self.on_disconnect {
+            callback(conn);
+        }
+    }
+
+    fn on_error(&self, _conn: &WsConn, error: String) {
+        tracing::debug!("WebSocket error: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Masked (zero key) client text frame
+    fn client_frame(text: &str) -> Vec<u8> {
+        let mut frame = vec![0x81, 0x80 | text.len() as u8, 0, 0, 0, 0];
+        frame.extend_from_slice(text.as_bytes());
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_event_ack() {
+        let events = WsEvents::new().on(
+            "add",
+            |_conn: WsConn, (a, b): (i64, i64)| async move { a + b },
+        );
+        let (mut client, socket) = tokio::io::duplex(1024);
+        tokio::spawn(super::super::connection::run(
+            socket,
+            super::super::connection::Upgrade {
+                id: "c1".to_string(),
+                peer_addr: "127.0.0.1:9000".parse().unwrap(),
+                state: Default::default(),
+                deflate: None,
+            },
+            Arc::new(events),
+            WsServer::new(),
+            Default::default(),
+        ));
+
+        client
+            .write_all(&client_frame(r#"{"event":"add","data":[2,3],"id":7}"#))
+            .await
+            .unwrap();
+        let expected = br#"{"ack":7,"data":5}"#;
+        let mut reply = vec![0u8; 2 + expected.len()];
+        client.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply[2..], expected);
+
+        client
+            .write_all(&client_frame(r#"{"event":"add","data":"x"}"#))
+            .await
+            .unwrap();
+        let mut head = [0u8; 2];
+        client.read_exact(&mut head).await.unwrap();
+        let mut error = vec![0u8; head[1] as usize];
+        client.read_exact(&mut error).await.unwrap();
+        let error: Value = serde_json::from_slice(&error).unwrap();
+        assert_eq!(error["event"], "error");
+        assert_eq!(error["data"]["event"], "add");
+    }
+}
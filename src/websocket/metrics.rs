@@ -0,0 +1,452 @@
+//! WebSocket Metrics
+//!
+//! Counters kept by [`WsServer`], per-connection details for the admin API
+//! and Prometheus text exposition.
+
+use super::queue::QueueStats;
+use super::{ConnectionId, WsServer};
+use crate::request::Request;
+use crate::response::Response;
+use crate::router::Router;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Message size buckets, in bytes
+const SIZE_BUCKETS: &[f64] = &[
+    64.0, 256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0,
+];
+
+/// Connection duration buckets, in seconds
+const DURATION_BUCKETS: &[f64] = &[1.0, 5.0, 15.0, 60.0, 300.0, 900.0, 3600.0, 14400.0];
+
+/// Server-wide counters
+#[derive(Debug)]
+pub(crate) struct Counters {
+    opened: AtomicU64,
+    closed: AtomicU64,
+    messages_in: AtomicU64,
+    messages_out: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    send_failures: AtomicU64,
+    message_size: Histogram,
+    duration: Histogram,
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self {
+            opened: AtomicU64::new(0),
+            closed: AtomicU64::new(0),
+            messages_in: AtomicU64::new(0),
+            messages_out: AtomicU64::new(0),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            send_failures: AtomicU64::new(0),
+            message_size: Histogram::new(SIZE_BUCKETS),
+            duration: Histogram::new(DURATION_BUCKETS),
+        }
+    }
+}
+
+impl Counters {
+    pub(crate) fn opened(&self) {
+        self.opened.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn closed(&self, connected_at: Option<DateTime<Utc>>) {
+        self.closed.fetch_add(1, Ordering::Relaxed);
+        if let Some(connected_at) = connected_at {
+            let seconds = (Utc::now() - connected_at).num_milliseconds() as f64 / 1000.0;
+            self.duration.observe(seconds);
+        }
+    }
+
+    /// A message arrived from a client
+    pub(crate) fn message_in(&self, conn: &ConnStats, bytes: usize) {
+        self.messages_in.fetch_add(1, Ordering::Relaxed);
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.message_size.observe(bytes as f64);
+        conn.messages_in.fetch_add(1, Ordering::Relaxed);
+        conn.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// A message was written to a client
+    pub(crate) fn message_out(&self, conn: &ConnStats, bytes: usize) {
+        self.messages_out.fetch_add(1, Ordering::Relaxed);
+        self.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+        conn.messages_out.fetch_add(1, Ordering::Relaxed);
+        conn.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// A message was dropped or its connection was gone
+    pub(crate) fn send_failed(&self) {
+        self.send_failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Per-connection counters
+#[derive(Debug, Default)]
+pub(crate) struct ConnStats {
+    messages_in: AtomicU64,
+    messages_out: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Histogram {
+    bounds: &'static [f64],
+    state: Mutex<(Vec<u64>, f64, u64)>,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            state: Mutex::new((vec![0; bounds.len()], 0.0, 0)),
+        }
+    }
+
+    fn observe(&self, value: f64) {
+        let mut state = self.state.lock();
+        if let Some(i) = self.bounds.iter().position(|bound| value <= *bound) {
+            state.0[i] += 1;
+        }
+        state.1 += value;
+        state.2 += 1;
+    }
+
+    fn snapshot(&self) -> WsHistogram {
+        let state = self.state.lock();
+        let mut total = 0;
+        let buckets = self
+            .bounds
+            .iter()
+            .zip(&state.0)
+            .map(|(bound, count)| {
+                total += count;
+                (*bound, total)
+            })
+            .collect();
+        WsHistogram {
+            buckets,
+            sum: state.1,
+            count: state.2,
+        }
+    }
+}
+
+/// Histogram snapshot with cumulative buckets
+#[derive(Debug, Clone, Serialize)]
+pub struct WsHistogram {
+    /// Upper bound and number of observations at or below it
+    pub buckets: Vec<(f64, u64)>,
+    /// Sum of all observations
+    pub sum: f64,
+    /// Number of observations
+    pub count: u64,
+}
+
+/// Snapshot of a server's WebSocket metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct WsMetrics {
+    /// Open connections
+    pub active_connections: usize,
+    /// Connections opened since start
+    pub connections_opened: u64,
+    /// Connections closed since start
+    pub connections_closed: u64,
+    /// Rooms with at least one member
+    pub rooms: usize,
+    /// Messages received from clients
+    pub messages_in: u64,
+    /// Messages written to clients
+    pub messages_out: u64,
+    /// Payload bytes received
+    pub bytes_in: u64,
+    /// Payload bytes written (after compression)
+    pub bytes_out: u64,
+    /// Sends dropped by backpressure or aimed at closed connections
+    pub send_failures: u64,
+    /// Size of received messages in bytes
+    pub message_size: WsHistogram,
+    /// How long closed connections stayed open, in seconds
+    pub connection_duration: WsHistogram,
+}
+
+impl WsMetrics {
+    /// Render in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        };
+
+        metric(
+            "rustyx_ws_connections_active",
+            "gauge",
+            "Open WebSocket connections",
+            &[("", self.active_connections as f64)],
+        );
+        metric(
+            "rustyx_ws_connections_opened_total",
+            "counter",
+            "WebSocket connections opened",
+            &[("", self.connections_opened as f64)],
+        );
+        metric(
+            "rustyx_ws_connections_closed_total",
+            "counter",
+            "WebSocket connections closed",
+            &[("", self.connections_closed as f64)],
+        );
+        metric(
+            "rustyx_ws_rooms",
+            "gauge",
+            "WebSocket rooms with members",
+            &[("", self.rooms as f64)],
+        );
+        metric(
+            "rustyx_ws_messages_total",
+            "counter",
+            "WebSocket messages by direction",
+            &[
+                ("{direction=\"in\"}", self.messages_in as f64),
+                ("{direction=\"out\"}", self.messages_out as f64),
+            ],
+        );
+        metric(
+            "rustyx_ws_bytes_total",
+            "counter",
+            "WebSocket payload bytes by direction",
+            &[
+                ("{direction=\"in\"}", self.bytes_in as f64),
+                ("{direction=\"out\"}", self.bytes_out as f64),
+            ],
+        );
+        metric(
+            "rustyx_ws_send_failures_total",
+            "counter",
+            "WebSocket sends that were dropped",
+            &[("", self.send_failures as f64)],
+        );
+
+        histogram(
+            &mut out,
+            "rustyx_ws_message_size_bytes",
+            "Size of received WebSocket messages",
+            &self.message_size,
+        );
+        histogram(
+            &mut out,
+            "rustyx_ws_connection_duration_seconds",
+            "Lifetime of closed WebSocket connections",
+            &self.connection_duration,
+        );
+        out
+    }
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, histogram: &WsHistogram) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (bound, count) in &histogram.buckets {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+    }
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, histogram.count);
+    let _ = writeln!(out, "{}_sum {}", name, histogram.sum);
+    let _ = writeln!(out, "{}_count {}", name, histogram.count);
+}
+
+/// A connection as shown by the admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct WsConnInfo {
+    /// Connection ID
+    pub id: ConnectionId,
+    /// Client address
+    pub peer_addr: SocketAddr,
+    /// When the connection was accepted
+    pub connected_at: DateTime<Utc>,
+    /// Seconds since the client last sent a frame
+    pub idle_secs: u64,
+    /// Rooms the connection is in
+    pub rooms: Vec<String>,
+    /// Messages received from the client
+    pub messages_in: u64,
+    /// Messages written to the client
+    pub messages_out: u64,
+    /// Payload bytes received
+    pub bytes_in: u64,
+    /// Payload bytes written
+    pub bytes_out: u64,
+    /// Send queue state
+    pub queue: QueueStats,
+}
+
+impl WsServer {
+    /// Current metrics snapshot
+    pub fn metrics(&self) -> WsMetrics {
+        let counters = &self.metrics;
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        WsMetrics {
+            active_connections: self.connection_count(),
+            connections_opened: load(&counters.opened),
+            connections_closed: load(&counters.closed),
+            rooms: self
+                .rooms
+                .read()
+                .values()
+                .filter(|room| room.count() > 0)
+                .count(),
+            messages_in: load(&counters.messages_in),
+            messages_out: load(&counters.messages_out),
+            bytes_in: load(&counters.bytes_in),
+            bytes_out: load(&counters.bytes_out),
+            send_failures: load(&counters.send_failures),
+            message_size: counters.message_size.snapshot(),
+            connection_duration: counters.duration.snapshot(),
+        }
+    }
+
+    /// Details of a connection accepted by this server
+    ///
+    /// Connections added with [`register`](WsServer::register) only have
+    /// queue statistics and are not listed.
+    pub fn connection_info(&self, conn_id: &ConnectionId) -> Option<WsConnInfo> {
+        let conn = self.handles.read().get(conn_id).cloned()?;
+        let rooms = self
+            .rooms
+            .read()
+            .values()
+            .filter(|room| room.members().contains(conn_id))
+            .map(|room| room.name().to_string())
+            .collect();
+        let stats = &conn.stats;
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        Some(WsConnInfo {
+            id: conn_id.clone(),
+            peer_addr: conn.peer_addr(),
+            connected_at: conn.connected_at(),
+            idle_secs: conn.last_seen().elapsed().as_secs(),
+            rooms,
+            messages_in: load(&stats.messages_in),
+            messages_out: load(&stats.messages_out),
+            bytes_in: load(&stats.bytes_in),
+            bytes_out: load(&stats.bytes_out),
+            queue: conn.queue_stats(),
+        })
+    }
+
+    /// Details of every connection accepted by this server
+    pub fn list_connections(&self) -> Vec<WsConnInfo> {
+        let ids: Vec<ConnectionId> = self.handles.read().keys().cloned().collect();
+        let mut list: Vec<WsConnInfo> = ids
+            .iter()
+            .filter_map(|id| self.connection_info(id))
+            .collect();
+        list.sort_by_key(|info| info.connected_at);
+        list
+    }
+
+    /// Admin routes for inspecting and disconnecting clients
+    ///
+    /// - `GET /connections` lists connections, `GET /connections/:id` shows one
+    /// - `DELETE /connections/:id` kicks a connection (`?reason=...`)
+    /// - `GET /metrics` renders Prometheus text, `GET /stats` the same as JSON
+    ///
+    /// The routes expose client addresses, so mount them behind a guard.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut admin = app.ws_server().admin_router();
+    /// admin.use_middleware(authorize(&["admin"]));
+    /// app.use_router("/admin/ws", admin);
+    /// ```
+    pub fn admin_router(&self) -> Router {
+        let mut router = Router::new();
+
+        let server = self.clone();
+        router.get("/connections", move |_req: Request, res: Response| {
+            let list = server.list_connections();
+            async move { res.json(list) }
+        });
+
+        let server = self.clone();
+        router.get("/connections/:id", move |req: Request, res: Response| {
+            let info = req.param("id").and_then(|id| server.connection_info(id));
+            async move {
+                match info {
+                    Some(info) => res.json(info),
+                    None => res.not_found(),
+                }
+            }
+        });
+
+        let server = self.clone();
+        router.delete("/connections/:id", move |req: Request, res: Response| {
+            let reason = req
+                .query_param("reason")
+                .cloned()
+                .unwrap_or_else(|| "Disconnected by an administrator".to_string());
+            let kicked = req.param("id").is_some_and(|id| server.kick(id, &reason));
+            async move {
+                if kicked {
+                    res.json(serde_json::json!({ "kicked": true }))
+                } else {
+                    res.not_found()
+                }
+            }
+        });
+
+        let server = self.clone();
+        router.get("/metrics", move |_req: Request, res: Response| {
+            let text = server.metrics().to_prometheus();
+            async move {
+                res.content_type("text/plain; version=0.0.4; charset=utf-8")
+                    .send(text)
+            }
+        });
+
+        let server = self.clone();
+        router.get("/stats", move |_req: Request, res: Response| {
+            let metrics = server.metrics();
+            async move { res.json(metrics) }
+        });
+
+        router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_text() {
+        let server = WsServer::new();
+        let conn = ConnStats::default();
+        server.metrics.opened();
+        server.metrics.message_in(&conn, 100);
+        server.metrics.message_in(&conn, 5000);
+        server.metrics.message_out(&conn, 10);
+
+        let text = server.metrics().to_prometheus();
+        assert!(text.contains("rustyx_ws_connections_opened_total 1"));
+        assert!(text.contains("# TYPE rustyx_ws_messages_total counter"));
+        assert!(text.contains("rustyx_ws_messages_total{direction=\"in\"} 2"));
+        assert!(text.contains("rustyx_ws_message_size_bytes_bucket{le=\"256\"} 1"));
+        assert!(text.contains("rustyx_ws_message_size_bytes_bucket{le=\"16384\"} 2"));
+        assert!(text.contains("rustyx_ws_message_size_bytes_bucket{le=\"+Inf\"} 2"));
+        assert!(text.contains("rustyx_ws_message_size_bytes_sum 5100"));
+    }
+}
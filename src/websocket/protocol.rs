@@ -0,0 +1,231 @@
+//! WebSocket Protocol
+//!
+//! RFC 6455 handshake and framing: just enough of the wire format for the
+//! server side (clients mask, servers don't).
+
+use base64::Engine;
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// GUID appended to the client key when computing `Sec-WebSocket-Accept`
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Frame opcodes
+pub(crate) const OP_CONTINUATION: u8 = 0x0;
+pub(crate) const OP_TEXT: u8 = 0x1;
+pub(crate) const OP_BINARY: u8 = 0x2;
+pub(crate) const OP_CLOSE: u8 = 0x8;
+pub(crate) const OP_PING: u8 = 0x9;
+pub(crate) const OP_PONG: u8 = 0xA;
+
+/// Compute the `Sec-WebSocket-Accept` value for a client key
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(ACCEPT_GUID.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+/// Protocol violation, mapped to the close code sent to the peer
+#[derive(Debug)]
+pub(crate) enum ProtocolError {
+    /// Malformed frame (1002)
+    Protocol(&'static str),
+    /// Text message that isn't UTF-8 (1007)
+    InvalidUtf8,
+    /// Message over the size limit (1009)
+    TooBig,
+    /// Transport error; the connection is gone
+    Io(std::io::Error),
+}
+
+impl ProtocolError {
+    /// Close code to send, if the connection is still usable
+    pub(crate) fn close_code(&self) -> Option<u16> {
+        match self {
+            ProtocolError::Protocol(_) => Some(1002),
+            ProtocolError::InvalidUtf8 => Some(1007),
+            ProtocolError::TooBig => Some(1009),
+            ProtocolError::Io(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::Protocol(msg) => write!(f, "protocol error: {}", msg),
+            ProtocolError::InvalidUtf8 => write!(f, "invalid UTF-8 in text message"),
+            ProtocolError::TooBig => write!(f, "message too big"),
+            ProtocolError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ProtocolError {
+    fn from(e: std::io::Error) -> Self {
+        ProtocolError::Io(e)
+    }
+}
+
+/// A single frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub fin: bool,
+    pub rsv1: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Create a final frame
+    pub(crate) fn new(opcode: u8, payload: Vec<u8>) -> Self {
+        Self {
+            fin: true,
+            rsv1: false,
+            opcode,
+            payload,
+        }
+    }
+
+    /// Close frame with a status code and reason
+    pub(crate) fn close(code: u16, reason: &str) -> Self {
+        let mut payload = code.to_be_bytes().to_vec();
+        // Control frame payloads are limited to 125 bytes
+        let mut end = reason.len().min(123);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        payload.extend_from_slice(&reason.as_bytes()[..end]);
+        Self::new(OP_CLOSE, payload)
+    }
+
+    /// Serialize an unmasked (server) frame
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let len = self.payload.len();
+        let mut out = Vec::with_capacity(len + 10);
+        out.push((self.fin as u8) << 7 | (self.rsv1 as u8) << 6 | self.opcode);
+
+        if len < 126 {
+            out.push(len as u8);
+        } else if len <= u16::MAX as usize {
+            out.push(126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        } else {
+            out.push(127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    /// Write the frame to a stream
+    pub(crate) async fn write<W: AsyncWrite + Unpin>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&self.encode()).await?;
+        writer.flush().await
+    }
+
+    /// Read one masked (client) frame
+    pub(crate) async fn read<R: AsyncRead + Unpin>(
+        reader: &mut R,
+        max_size: usize,
+    ) -> Result<Self, ProtocolError> {
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head).await?;
+
+        let fin = head[0] & 0x80 != 0;
+        let rsv1 = head[0] & 0x40 != 0;
+        if head[0] & 0x30 != 0 {
+            return Err(ProtocolError::Protocol("reserved bits set"));
+        }
+        let opcode = head[0] & 0x0F;
+        if head[1] & 0x80 == 0 {
+            return Err(ProtocolError::Protocol("client frames must be masked"));
+        }
+
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut buf = [0u8; 2];
+                reader.read_exact(&mut buf).await?;
+                u16::from_be_bytes(buf) as u64
+            }
+            127 => {
+                let mut buf = [0u8; 8];
+                reader.read_exact(&mut buf).await?;
+                u64::from_be_bytes(buf)
+            }
+            len => len as u64,
+        };
+
+        if opcode >= OP_CLOSE && (!fin || len > 125) {
+            return Err(ProtocolError::Protocol("invalid control frame"));
+        }
+        if len > max_size as u64 {
+            return Err(ProtocolError::TooBig);
+        }
+
+        let mut mask = [0u8; 4];
+        reader.read_exact(&mut mask).await?;
+        let mut payload = vec![0u8; len as usize];
+        reader.read_exact(&mut payload).await?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        Ok(Self {
+            fin,
+            rsv1,
+            opcode,
+            payload,
+        })
+    }
+}
+
+/// Parse a close frame payload into code and reason
+pub(crate) fn parse_close(payload: &[u8]) -> Result<Option<(u16, String)>, ProtocolError> {
+    match payload {
+        [] => Ok(None),
+        [_] => Err(ProtocolError::Protocol("invalid close payload")),
+        [hi, lo, reason @ ..] => {
+            let code = u16::from_be_bytes([*hi, *lo]);
+            // Codes a peer may send (RFC 6455, section 7.4)
+            if !matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999) {
+                return Err(ProtocolError::Protocol("invalid close code"));
+            }
+            let reason = std::str::from_utf8(reason).map_err(|_| ProtocolError::InvalidUtf8)?;
+            Ok(Some((code, reason.to_string())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455, section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frame_roundtrip() {
+        // Masked "Hello" from RFC 6455, section 5.7
+        let masked = [
+            0x81u8, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let frame = Frame::read(&mut &masked[..], 1024).await.unwrap();
+        assert_eq!(frame, Frame::new(OP_TEXT, b"Hello".to_vec()));
+        assert_eq!(frame.encode(), [0x81, 0x05, b'H', b'e', b'l', b'l', b'o']);
+
+        let unmasked = [0x81u8, 0x05, b'H', b'e', b'l', b'l', b'o'];
+        assert!(Frame::read(&mut &unmasked[..], 1024).await.is_err());
+        assert!(matches!(
+            Frame::read(&mut &masked[..], 4).await,
+            Err(ProtocolError::TooBig)
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+//! WebSocket Send Queues
+//!
+//! Each connection has a bounded outbound queue. Sends never wait: when a
+//! slow client lets its queue fill up, the configured [`Backpressure`]
+//! policy decides what gives, so one client can't stall a broadcast.
+
+use super::WsMessage;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// What to do when a connection's send queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backpressure {
+    /// Drop the oldest queued message to make room
+    DropOldest,
+    /// Drop the message being sent
+    #[default]
+    DropMessage,
+    /// Close the connection (code 1008)
+    Disconnect,
+}
+
+/// Snapshot of a connection's send queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct QueueStats {
+    /// Messages waiting to be written
+    pub queued: usize,
+    /// Queue capacity
+    pub capacity: usize,
+    /// Messages handed to the socket so far
+    pub sent: u64,
+    /// Messages dropped because the queue was full
+    pub dropped: u64,
+}
+
+#[derive(Debug)]
+struct Shared {
+    queue: Mutex<VecDeque<WsMessage>>,
+    notify: Notify,
+    capacity: usize,
+    policy: Backpressure,
+    closed: AtomicBool,
+    sent: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// Sending half of a connection's queue, as registered with `WsServer`
+#[derive(Debug, Clone)]
+pub struct WsSender {
+    shared: Arc<Shared>,
+}
+
+/// Receiving half of a connection's queue
+#[derive(Debug)]
+pub struct WsReceiver {
+    shared: Arc<Shared>,
+}
+
+impl WsSender {
+    /// Create a queue holding up to `capacity` messages
+    pub fn channel(capacity: usize, policy: Backpressure) -> (WsSender, WsReceiver) {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(VecDeque::with_capacity(capacity.min(1024))),
+            notify: Notify::new(),
+            capacity: capacity.max(1),
+            policy,
+            closed: AtomicBool::new(false),
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        });
+        (
+            WsSender {
+                shared: Arc::clone(&shared),
+            },
+            WsReceiver { shared },
+        )
+    }
+
+    /// Queue a message; returns `false` if it was dropped or the queue is closed
+    pub fn send(&self, message: WsMessage) -> bool {
+        let shared = &self.shared;
+        if shared.closed.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let mut queue = shared.queue.lock();
+        // Close frames are never dropped, so a full queue can't block a close
+        let accepted = if queue.len() < shared.capacity || matches!(message, WsMessage::Close(_)) {
+            queue.push_back(message);
+            true
+        } else {
+            shared.dropped.fetch_add(1, Ordering::Relaxed);
+            match shared.policy {
+                Backpressure::DropOldest => {
+                    queue.pop_front();
+                    queue.push_back(message);
+                    true
+                }
+                Backpressure::DropMessage => false,
+                Backpressure::Disconnect => {
+                    shared.closed.store(true, Ordering::Release);
+                    false
+                }
+            }
+        };
+        drop(queue);
+
+        shared.notify.notify_one();
+        accepted
+    }
+
+    /// Whether the queue has been closed (the connection is going away)
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Acquire)
+    }
+
+    /// Current queue statistics
+    pub fn stats(&self) -> QueueStats {
+        let shared = &self.shared;
+        QueueStats {
+            queued: shared.queue.lock().len(),
+            capacity: shared.capacity,
+            sent: shared.sent.load(Ordering::Relaxed),
+            dropped: shared.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl WsReceiver {
+    /// Wait for the next message; `None` once the queue is closed, even if
+    /// messages are still queued
+    pub async fn recv(&self) -> Option<WsMessage> {
+        loop {
+            let notified = self.shared.notify.notified();
+            if self.shared.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            if let Some(message) = self.try_recv() {
+                return Some(message);
+            }
+            notified.await;
+        }
+    }
+
+    /// Take the next message without waiting
+    pub fn try_recv(&self) -> Option<WsMessage> {
+        let message = self.shared.queue.lock().pop_front();
+        if message.is_some() {
+            self.shared.sent.fetch_add(1, Ordering::Relaxed);
+        }
+        message
+    }
+
+    /// Close the queue; further sends fail
+    pub fn close(&self) {
+        self.shared.closed.store(true, Ordering::Release);
+    }
+}
+
+impl Drop for WsReceiver {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(n: usize) -> WsMessage {
+        WsMessage::Text(n.to_string())
+    }
+
+    #[test]
+    fn test_backpressure_policies() {
+        let (tx, rx) = WsSender::channel(2, Backpressure::DropOldest);
+        assert!(tx.send(text(1)) && tx.send(text(2)) && tx.send(text(3)));
+        assert!(matches!(rx.try_recv(), Some(WsMessage::Text(t)) if t == "2"));
+        assert_eq!(tx.stats().dropped, 1);
+        assert_eq!(tx.stats().sent, 1);
+
+        let (tx, rx) = WsSender::channel(1, Backpressure::DropMessage);
+        assert!(tx.send(text(1)) && !tx.send(text(2)));
+        assert!(matches!(rx.try_recv(), Some(WsMessage::Text(t)) if t == "1"));
+        assert!(tx.send(text(3)));
+
+        let (tx, _rx) = WsSender::channel(1, Backpressure::Disconnect);
+        assert!(tx.send(text(1)) && !tx.send(text(2)));
+        assert!(tx.is_closed());
+    }
+}
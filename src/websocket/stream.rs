@@ -0,0 +1,588 @@
+//! WebSocket Streams
+//!
+//! Chunked binary transfers over an existing connection, so files can move
+//! in either direction without buffering the whole payload. The sender
+//! announces a stream with a text packet, sends binary chunks prefixed with
+//! the 4-byte big-endian stream ID, and finishes with an `end` packet:
+//!
+//! ```text
+//! {"stream": "start", "id": 1, "name": "photo.jpg", "size": 52311, "meta": {...}}
+//! <binary: 00 00 00 01 | chunk bytes> ...
+//! {"stream": "end", "id": 1}            or {"stream": "abort", "id": 1, "reason": "..."}
+//! ```
+//!
+//! The receiver acknowledges chunks as it consumes them with
+//! `{"stream": "ack", "id": 1, "seq": 3}` (chunks received so far) and may
+//! give up with `{"stream": "cancel", "id": 1}`. A sender never has more
+//! than `window` unacknowledged chunks in flight.
+
+use super::{WsConn, WsHandler, WsMessage};
+use crate::error::{Error, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch};
+
+/// Chunked transfer settings
+#[derive(Debug, Clone)]
+pub struct StreamConfig {
+    /// Bytes per chunk sent by the server
+    pub chunk_size: usize,
+    /// Unacknowledged chunks allowed in flight, in either direction
+    pub window: usize,
+    /// Seconds to wait for the client to acknowledge a chunk
+    pub ack_timeout: u64,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 16 * 1024, // 16KB
+            window: 8,
+            ack_timeout: 30,
+        }
+    }
+}
+
+impl StreamConfig {
+    /// Create the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the size of outgoing chunks in bytes
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Set how many chunks may be unacknowledged at once
+    pub fn window(mut self, chunks: usize) -> Self {
+        self.window = chunks.max(1);
+        self
+    }
+
+    /// Set the acknowledgement timeout in seconds
+    pub fn ack_timeout(mut self, seconds: u64) -> Self {
+        self.ack_timeout = seconds;
+        self
+    }
+}
+
+/// Control packets of the stream protocol
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "stream", rename_all = "lowercase")]
+enum Packet {
+    Start {
+        id: u32,
+        #[serde(default)]
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<u64>,
+        #[serde(default, skip_serializing_if = "Value::is_null")]
+        meta: Value,
+    },
+    End {
+        id: u32,
+    },
+    Abort {
+        id: u32,
+        #[serde(default)]
+        reason: String,
+    },
+    Ack {
+        id: u32,
+        seq: u64,
+    },
+    Cancel {
+        id: u32,
+    },
+}
+
+impl Packet {
+    fn text(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// What an incoming stream's reader gets
+#[derive(Debug)]
+enum Item {
+    Chunk(Vec<u8>),
+    End,
+    Abort(String),
+}
+
+/// Streams in progress on a connection
+#[derive(Debug)]
+pub(crate) struct Streams {
+    config: Option<StreamConfig>,
+    next_id: AtomicU32,
+    /// Client uploads, by the client's stream ID
+    incoming: Mutex<HashMap<u32, mpsc::Sender<Item>>>,
+    /// Server downloads and their acknowledged chunk count, by our stream ID
+    outgoing: Mutex<HashMap<u32, watch::Sender<u64>>>,
+}
+
+impl Streams {
+    pub(crate) fn new(config: Option<StreamConfig>) -> Self {
+        Self {
+            config,
+            next_id: AtomicU32::new(0),
+            incoming: Mutex::default(),
+            outgoing: Mutex::default(),
+        }
+    }
+
+    /// Handle stream packets and chunks, handing anything else back
+    pub(crate) fn dispatch(
+        &self,
+        conn: &WsConn,
+        handler: &dyn WsHandler,
+        message: WsMessage,
+    ) -> Option<WsMessage> {
+        let Some(config) = &self.config else {
+            return Some(message);
+        };
+
+        match message {
+            WsMessage::Binary(data) if data.len() >= 4 => {
+                let id = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+                let Some(chunks) = self.incoming.lock().get(&id).cloned() else {
+                    return Some(WsMessage::Binary(data));
+                };
+                match chunks.try_send(Item::Chunk(data[4..].to_vec())) {
+                    Ok(()) => {}
+                    // The client ignored the window: give up on the upload
+                    Err(TrySendError::Full(_)) => {
+                        self.incoming.lock().remove(&id);
+                        conn.send_text(Packet::Cancel { id }.text());
+                    }
+                    // The reader is gone and has already cancelled
+                    Err(TrySendError::Closed(_)) => {}
+                }
+                None
+            }
+            WsMessage::Text(text) => {
+                let packet = match serde_json::from_str::<Packet>(&text) {
+                    Ok(packet) => packet,
+                    Err(_) => return Some(WsMessage::Text(text)),
+                };
+                self.handle(conn, handler, config, packet);
+                None
+            }
+            message => Some(message),
+        }
+    }
+
+    fn handle(
+        &self,
+        conn: &WsConn,
+        handler: &dyn WsHandler,
+        config: &StreamConfig,
+        packet: Packet,
+    ) {
+        match packet {
+            Packet::Start {
+                id,
+                name,
+                size,
+                meta,
+            } => {
+                let (tx, rx) = mpsc::channel(config.window);
+                if self.incoming.lock().insert(id, tx).is_some() {
+                    tracing::debug!("WebSocket stream {} restarted", id);
+                }
+                handler.on_stream(
+                    conn,
+                    WsStream {
+                        id,
+                        name,
+                        size,
+                        meta,
+                        chunks: rx,
+                        conn: conn.clone(),
+                        received: 0,
+                        bytes: 0,
+                        done: false,
+                    },
+                );
+            }
+            Packet::End { id } => self.finish(id, Item::End),
+            Packet::Abort { id, reason } => self.finish(id, Item::Abort(reason)),
+            Packet::Ack { id, seq } => {
+                if let Some(acked) = self.outgoing.lock().get(&id) {
+                    acked.send_modify(|acked| *acked = (*acked).max(seq));
+                }
+            }
+            // Dropping the sender wakes the download with an error
+            Packet::Cancel { id } => {
+                self.outgoing.lock().remove(&id);
+            }
+        }
+    }
+
+    fn finish(&self, id: u32, item: Item) {
+        let Some(chunks) = self.incoming.lock().remove(&id) else {
+            return;
+        };
+        // A full window shouldn't lose the end marker
+        if let Err(TrySendError::Full(item)) = chunks.try_send(item) {
+            tokio::spawn(async move {
+                let _ = chunks.send(item).await;
+            });
+        }
+    }
+
+    /// Interrupt every stream; called when the connection ends
+    pub(crate) fn close(&self) {
+        self.incoming.lock().clear();
+        self.outgoing.lock().clear();
+    }
+}
+
+/// A binary stream started by the client, see [`WsHandler::on_stream`]
+///
+/// Each chunk is acknowledged as it is read, so a slow reader slows the
+/// client down. Dropping the stream before the end cancels the upload.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// fn on_stream(&self, conn: &WsConn, stream: WsStream) {
+///     let conn = conn.clone();
+///     tokio::spawn(async move {
+///         let path = format!("uploads/{}", stream.name());
+///         match stream.save(&path).await {
+///             Ok(bytes) => conn.emit("upload:done", &json!({ "bytes": bytes })),
+///             Err(e) => conn.emit("upload:failed", &json!({ "error": e.to_string() })),
+///         };
+///     });
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WsStream {
+    id: u32,
+    name: String,
+    size: Option<u64>,
+    meta: Value,
+    chunks: mpsc::Receiver<Item>,
+    conn: WsConn,
+    received: u64,
+    bytes: u64,
+    done: bool,
+}
+
+impl WsStream {
+    /// Stream ID chosen by the client
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// File name announced by the client
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Total size announced by the client, if any
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Application data sent with the start packet
+    pub fn meta(&self) -> &Value {
+        &self.meta
+    }
+
+    /// Bytes read so far
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Wait for the next chunk; `None` once the client has finished
+    ///
+    /// Fails if the client aborts the stream or the connection closes
+    /// before the end.
+    pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let item = self.chunks.recv().await;
+        if !matches!(item, Some(Item::Chunk(_))) {
+            self.done = true;
+        }
+        match item {
+            Some(Item::Chunk(data)) => {
+                self.received += 1;
+                self.bytes += data.len() as u64;
+                self.conn.send_text(
+                    Packet::Ack {
+                        id: self.id,
+                        seq: self.received,
+                    }
+                    .text(),
+                );
+                Ok(Some(data))
+            }
+            Some(Item::End) => Ok(None),
+            Some(Item::Abort(reason)) => Err(Error::BadRequest(format!(
+                "Stream '{}' aborted: {}",
+                self.name, reason
+            ))),
+            None => Err(Error::Internal(format!(
+                "Stream '{}' interrupted",
+                self.name
+            ))),
+        }
+    }
+
+    /// Copy the rest of the stream into a writer, returning the total bytes read
+    pub async fn copy_to<W: AsyncWrite + Unpin>(mut self, writer: &mut W) -> Result<u64> {
+        while let Some(chunk) = self.next_chunk().await? {
+            writer.write_all(&chunk).await?;
+        }
+        writer.flush().await?;
+        Ok(self.bytes)
+    }
+
+    /// Write the stream to a file, returning its size
+    pub async fn save(self, path: impl AsRef<Path>) -> Result<u64> {
+        let mut file = tokio::fs::File::create(path).await?;
+        self.copy_to(&mut file).await
+    }
+}
+
+impl Drop for WsStream {
+    fn drop(&mut self) {
+        if !self.done && self.conn.streams.incoming.lock().remove(&self.id).is_some() {
+            self.conn.send_text(Packet::Cancel { id: self.id }.text());
+        }
+    }
+}
+
+impl WsConn {
+    /// Stream a reader to the client in chunks, returning the bytes sent
+    ///
+    /// Requires [`WsConfig::streams`](super::WsConfig::streams). At most
+    /// `window` chunks are sent ahead of the client's acknowledgements, so
+    /// the reader is only consumed as fast as the client keeps up.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let file = tokio::fs::File::open("report.pdf").await?;
+    /// conn.send_stream("report.pdf", Some(size), file).await?;
+    /// ```
+    pub async fn send_stream<R>(&self, name: &str, size: Option<u64>, mut reader: R) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let Some(config) = self.streams.config.clone() else {
+            return Err(Error::Internal(
+                "WebSocket streams are not enabled".to_string(),
+            ));
+        };
+
+        let id = self.streams.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, acked) = watch::channel(0);
+        self.streams.outgoing.lock().insert(id, tx);
+
+        let start = Packet::Start {
+            id,
+            name: name.to_string(),
+            size,
+            meta: Value::Null,
+        };
+        let result = if self.send_text(start.text()) {
+            self.send_chunks(id, &mut reader, &config, acked).await
+        } else {
+            Err(Error::Internal("WebSocket connection closed".to_string()))
+        };
+        self.streams.outgoing.lock().remove(&id);
+
+        match &result {
+            Ok(_) => self.send_text(Packet::End { id }.text()),
+            Err(e) => self.send_text(
+                Packet::Abort {
+                    id,
+                    reason: e.to_string(),
+                }
+                .text(),
+            ),
+        };
+        result
+    }
+
+    /// Stream a file to the client under its file name
+    pub async fn send_file(&self, path: impl AsRef<Path>) -> Result<u64> {
+        let path = path.as_ref();
+        let file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.send_stream(&name, Some(size), file).await
+    }
+
+    async fn send_chunks<R>(
+        &self,
+        id: u32,
+        reader: &mut R,
+        config: &StreamConfig,
+        mut acked: watch::Receiver<u64>,
+    ) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let timeout = Duration::from_secs(config.ack_timeout);
+        let window = config.window as u64;
+        let mut seq = 0u64;
+        let mut bytes = 0u64;
+
+        loop {
+            match tokio::time::timeout(timeout, acked.wait_for(|acked| seq - acked < window)).await
+            {
+                Ok(Ok(_)) => {}
+                Ok(Err(_)) => {
+                    return Err(Error::Internal(
+                        "Stream cancelled by the client".to_string(),
+                    ))
+                }
+                Err(_) => {
+                    return Err(Error::Internal(format!(
+                        "No acknowledgement within {:?}",
+                        timeout
+                    )))
+                }
+            }
+
+            let mut chunk = Vec::with_capacity(config.chunk_size + 4);
+            chunk.extend_from_slice(&id.to_be_bytes());
+            let read = (&mut *reader)
+                .take(config.chunk_size as u64)
+                .read_to_end(&mut chunk)
+                .await?;
+            if read == 0 {
+                return Ok(bytes);
+            }
+            if !self.send_binary(chunk) {
+                return Err(Error::Internal(
+                    "Chunk dropped or connection closed".to_string(),
+                ));
+            }
+            seq += 1;
+            bytes += read as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{CloseFrame, WsConfig, WsServer};
+    use super::*;
+    use std::sync::Arc;
+
+    /// Masked (zero key) client frame
+    fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8, 0, 0, 0, 0];
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Read one unmasked server frame
+    async fn server_frame(client: &mut tokio::io::DuplexStream) -> (u8, Vec<u8>) {
+        let mut head = [0u8; 2];
+        client.read_exact(&mut head).await.unwrap();
+        let mut payload = vec![0u8; head[1] as usize];
+        client.read_exact(&mut payload).await.unwrap();
+        (head[0] & 0x0F, payload)
+    }
+
+    struct Upload(Mutex<Option<tokio::sync::oneshot::Sender<Vec<u8>>>>);
+
+    impl WsHandler for Upload {
+        fn on_open(&self, conn: &WsConn) {
+            let conn = conn.clone();
+            tokio::spawn(async move {
+                conn.send_stream("hello.txt", Some(11), &b"hello world"[..])
+                    .await
+                    .unwrap();
+            });
+        }
+        fn on_message(&self, _conn: &WsConn, _message: WsMessage) {}
+        fn on_close(&self, _conn: &WsConn, _frame: Option<CloseFrame>) {}
+        fn on_error(&self, _conn: &WsConn, _error: String) {}
+
+        fn on_stream(&self, _conn: &WsConn, stream: WsStream) {
+            let done = self.0.lock().take().unwrap();
+            tokio::spawn(async move {
+                let mut data = Vec::new();
+                stream.copy_to(&mut data).await.unwrap();
+                let _ = done.send(data);
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_both_ways() {
+        let (done, uploaded) = tokio::sync::oneshot::channel();
+        let (mut client, socket) = tokio::io::duplex(4096);
+        tokio::spawn(super::super::connection::run(
+            socket,
+            super::super::connection::Upgrade {
+                id: "c1".to_string(),
+                peer_addr: "127.0.0.1:9000".parse().unwrap(),
+                state: Default::default(),
+                deflate: None,
+            },
+            Arc::new(Upload(Mutex::new(Some(done)))),
+            WsServer::new(),
+            WsConfig::default().streams(StreamConfig::new().chunk_size(4).window(2)),
+        ));
+
+        // Download: the window stops the server after two chunks until acked
+        let (_, start) = server_frame(&mut client).await;
+        assert_eq!(
+            start,
+            br#"{"stream":"start","id":1,"name":"hello.txt","size":11}"#
+        );
+        let mut received = Vec::new();
+        for seq in 1..=3u64 {
+            let (opcode, chunk) = server_frame(&mut client).await;
+            assert_eq!(opcode, 0x2);
+            assert_eq!(chunk[..4], [0, 0, 0, 1]);
+            received.extend_from_slice(&chunk[4..]);
+            if seq >= 2 {
+                let ack = format!(r#"{{"stream":"ack","id":1,"seq":{}}}"#, seq - 1);
+                client
+                    .write_all(&client_frame(0x1, ack.as_bytes()))
+                    .await
+                    .unwrap();
+            }
+        }
+        assert_eq!(received, b"hello world");
+        let (_, end) = server_frame(&mut client).await;
+        assert_eq!(end, br#"{"stream":"end","id":1}"#);
+
+        // Upload: chunks are acknowledged as the handler reads them
+        for frame in [
+            client_frame(0x1, br#"{"stream":"start","id":7,"name":"up.bin"}"#),
+            client_frame(0x2, &[0, 0, 0, 7, b'a', b'b']),
+            client_frame(0x2, &[0, 0, 0, 7, b'c']),
+            client_frame(0x1, br#"{"stream":"end","id":7}"#),
+        ] {
+            client.write_all(&frame).await.unwrap();
+        }
+        assert_eq!(uploaded.await.unwrap(), b"abc");
+        let (_, ack) = server_frame(&mut client).await;
+        assert_eq!(ack, br#"{"stream":"ack","id":7,"seq":1}"#);
+    }
+}